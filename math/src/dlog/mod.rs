@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{gcd, isqrt, mod_exp, mul_mod, Num};
+use std::collections::HashMap;
+
+/**
+ * Find the smallest non-negative `x` such that `base^x == target (mod modulus)` using the
+ * baby-step giant-step algorithm.
+ *
+ * Rather than inverting `base^m`, the baby steps store `target * base^j` and the giant steps walk
+ * `base^(i * m)`, so that a match gives `x = i * m - j`. This requires `base` to be invertible
+ * modulo `modulus`, so if it isn't, the common factors are first divided out of the congruence:
+ * each one peels off a factor of `base`, leaving `coeff * base^y == target' (mod modulus')` with
+ * `base` invertible, and `x = y + k` after `k` divisions.
+ *
+ * Both the time and memory usage are `O(sqrt(modulus))`, so this is only practical for toy-sized
+ * moduli. Returns `None` if no such `x` exists.
+ */
+pub fn discrete_log(mut base: Num, mut target: Num, mut modulus: Num) -> Option<Num> {
+    assert!(modulus > 0);
+
+    base %= modulus;
+    target %= modulus;
+
+    if target == 1 % modulus {
+        // x^0 == 1 (mod n)
+        return Some(0);
+    }
+
+    // divide `g = gcd(base, modulus)` out of `coeff * base^y == target` until `base` is
+    // invertible; `target` must be divisible by it, or there's no solution
+    let mut coeff = 1 % modulus;
+    let mut divisions = 0;
+    loop {
+        let g = gcd(base, modulus);
+        if g == 1 {
+            break;
+        } else if target == coeff {
+            return Some(divisions);
+        } else if !target.is_multiple_of(g) {
+            return None;
+        }
+
+        let factor = base / g;
+        target /= g;
+        modulus /= g;
+        base %= modulus;
+        coeff = mul_mod(coeff, factor, modulus);
+        divisions += 1;
+    }
+
+    if target == coeff {
+        return Some(divisions);
+    }
+
+    // the order of `base` is at most `modulus - 1`, so `m * m >= modulus - 1` covers every exponent
+    let step = isqrt(modulus - 1) + 1;
+
+    // baby steps; later (larger) values of `j` overwrite earlier ones, giving the smallest `x`
+    let mut baby_steps = HashMap::with_capacity(step as usize);
+    let mut value = target;
+    for j in 0..step {
+        baby_steps.insert(value, j);
+        value = mul_mod(value, base, modulus);
+    }
+
+    // giant steps
+    let giant = mod_exp(base, step, modulus);
+    let mut value = mul_mod(coeff, giant, modulus);
+    for i in 1..=step {
+        if let Some(&j) = baby_steps.get(&value) {
+            return Some(i * step - j + divisions);
+        }
+
+        value = mul_mod(value, giant, modulus);
+    }
+
+    None
+}
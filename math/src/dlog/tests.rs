@@ -0,0 +1,76 @@
+use super::*;
+use std::iter;
+
+#[cfg(feature = "rand")]
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn check(base: Num, target: Num, modulus: Num, expected: Option<Num>) {
+    let result = discrete_log(base, target, modulus);
+    assert_eq!(
+        result, expected,
+        "log_{}({}) mod {} gave {:?} instead of {:?}",
+        base, target, modulus, result, expected
+    );
+}
+
+#[test]
+fn test_discrete_log() {
+    // manual tests
+    check(2, 1, 11, Some(0));
+    check(2, 8, 11, Some(3));
+    check(3, 13, 17, Some(4));
+    check(5, 1, 1, Some(0));
+    check(2, 9, 11, Some(6));
+
+    // 2 generates {1, 2, 4} modulo 7, so 3 has no logarithm
+    check(2, 3, 7, None);
+
+    // 2 isn't invertible modulo 8, but its powers still have logarithms
+    check(2, 4, 8, Some(2));
+    check(2, 0, 8, Some(3));
+    check(2, 3, 8, None);
+    check(6, 0, 36, Some(2));
+    check(6, 12, 36, None);
+    check(10, 1000, 9999, Some(3));
+
+    // the smallest exponent is returned even when the order is less than `modulus - 1`
+    check(2, 4, 7, Some(2));
+    check(4, 1, 15, Some(0));
+}
+
+#[test]
+fn test_discrete_log_exhaustive() {
+    // compare against a brute-force search of the exponents, whose powers repeat within `modulus`
+    for modulus in 1..=60 {
+        for base in 0..modulus {
+            let powers: Vec<Num> =
+                iter::successors(Some(1 % modulus), |&value| Some(value * base % modulus))
+                    .take(modulus as usize)
+                    .collect();
+
+            for target in 0..modulus {
+                let expected = powers.iter().position(|&value| value == target);
+                check(base, target, modulus, expected.map(|x| x as Num));
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn random_test_discrete_log() {
+    const ITERATIONS: usize = 20;
+    const PRIME: Num = 4294967311; // smallest prime greater than u32::MAX
+    const ROOT: Num = 3;
+    let mut rng = StdRng::from_entropy();
+
+    for _ in 0..ITERATIONS {
+        let exponent = rng.gen_range(1..PRIME - 1);
+        let target = mod_exp(ROOT, exponent, PRIME);
+        let log = discrete_log(ROOT, target, PRIME).unwrap();
+
+        // `ROOT` may not be a primitive root, in which case a smaller exponent can be found
+        assert!(log <= exponent);
+        assert_eq!(mod_exp(ROOT, log, PRIME), target);
+    }
+}
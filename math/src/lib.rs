@@ -1,10 +1,23 @@
+pub mod dlog;
+pub mod modint;
+pub mod montgomery;
 #[cfg(feature = "rand")]
 pub mod primes;
 
 #[cfg(test)]
 mod tests;
 
+#[cfg(not(feature = "wide"))]
 pub type Num = u64;
+#[cfg(feature = "wide")]
+pub type Num = u128;
+
+/**
+ * An integer type wide enough to hold the product of any two `Num`s. There's no native integer
+ * type twice as wide as `u128`, so this isn't available with the `wide` feature; use `mul_mod`
+ * there instead.
+ */
+#[cfg(not(feature = "wide"))]
 pub type BigNum = u128;
 
 const BITS_PER_BYTE: usize = 8;
@@ -14,7 +27,6 @@ const BITS_PER_NUM: usize = std::mem::size_of::<Num>() * BITS_PER_BYTE;
  * Shortcuts for modular exponentiation. The mathematical justification(s) are
  * (for the most part) briefly listed within each branch.
  */
-
 fn get_mod_exp_optimization(base: Num, exponent: Num, modulus: Num) -> Option<Num> {
     if exponent == 0 {
         // x^0 == 1
@@ -52,6 +64,173 @@ fn get_mod_exp_optimization(base: Num, exponent: Num, modulus: Num) -> Option<Nu
     }
 }
 
+/**
+ * Calculate `(a + b) % modulus` without overflowing, given that `a` and `b` are already reduced.
+ */
+fn add_mod(a: Num, b: Num, modulus: Num) -> Num {
+    debug_assert!(a < modulus && b < modulus);
+
+    // the sum is less than `2 * modulus`, so it needs at most one subtraction
+    let (sum, overflowed) = a.overflowing_add(b);
+    if overflowed || sum >= modulus {
+        sum.wrapping_sub(modulus)
+    } else {
+        sum
+    }
+}
+
+/**
+ * Calculate `(a * b) % modulus` without overflowing, using a `BigNum` intermediate product.
+ *
+ * Prefer this over casting to `BigNum` by hand, since it also works with the `wide` feature.
+ */
+#[cfg(not(feature = "wide"))]
+pub fn mul_mod(a: Num, b: Num, modulus: Num) -> Num {
+    ((a as BigNum * b as BigNum) % modulus as BigNum) as Num
+}
+
+/**
+ * Calculate `(a * b) % modulus` without overflowing, by doubling and adding (i.e., Russian peasant
+ * multiplication), since there's no integer type wide enough to hold the intermediate product.
+ */
+#[cfg(feature = "wide")]
+pub fn mul_mod(a: Num, mut b: Num, modulus: Num) -> Num {
+    let mut addend = a % modulus;
+    let mut result = 0;
+
+    while b != 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, addend, modulus);
+        }
+
+        addend = add_mod(addend, addend, modulus);
+        b >>= 1;
+    }
+
+    result
+}
+
+/**
+ * Calculate the greatest common divisor of `a` and `b` using the Euclidean algorithm.
+ */
+pub fn gcd(mut a: Num, mut b: Num) -> Num {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+
+    a
+}
+
+/**
+ * Calculate the greatest common divisor of `a` and `modulus` along with a Bezout coefficient `x`,
+ * using the extended Euclidean algorithm. The coefficient satisfies
+ * `a * x == gcd(a, modulus) (mod modulus)`, and is returned reduced modulo `modulus` so that no
+ * signed arithmetic is needed.
+ *
+ * Returns the pair `(gcd, x)`. Panics if `modulus == 0`.
+ */
+pub fn ext_gcd(a: Num, modulus: Num) -> (Num, Num) {
+    assert!(modulus > 0);
+
+    let sub = |a: Num, b: Num| if a >= b { a - b } else { modulus - (b - a) };
+
+    // invariant: `a * coeff == remainder (mod modulus)` for both pairs
+    let (mut remainder, mut next_remainder) = (modulus, a % modulus);
+    let (mut coeff, mut next_coeff) = (0, 1 % modulus);
+
+    while next_remainder != 0 {
+        let quotient = remainder / next_remainder;
+        let new_coeff = sub(coeff, mul_mod(quotient, next_coeff, modulus));
+        coeff = next_coeff;
+        next_coeff = new_coeff;
+
+        let new_remainder = remainder - quotient * next_remainder;
+        remainder = next_remainder;
+        next_remainder = new_remainder;
+    }
+
+    (remainder, coeff)
+}
+
+/**
+ * Calculate the integer square root of `n`, i.e., the largest value `r` such that `r * r <= n`.
+ */
+pub fn isqrt(n: Num) -> Num {
+    if n < 2 {
+        return n;
+    }
+
+    // Newton's method converges to the root from above, so start at a power of two that's at
+    // least the root and stop once the estimate stops decreasing
+    let bits = BITS_PER_NUM as u32 - n.leading_zeros();
+    let mut root: Num = 1 << bits.div_ceil(2);
+
+    loop {
+        let next = (root + n / root) / 2;
+        if next >= root {
+            return root;
+        }
+
+        root = next;
+    }
+}
+
+/**
+ * Calculate a square root of `n` modulo the odd prime `prime`, i.e., a value `r` such that
+ * `r * r == n (mod prime)`, using the Tonelli-Shanks algorithm. The other root is `prime - r`.
+ *
+ * Returns `None` if `n` isn't a quadratic residue modulo `prime`. The result is meaningless if
+ * `prime` isn't an odd prime.
+ */
+pub fn sqrt_mod(n: Num, prime: Num) -> Option<Num> {
+    assert!(prime > 2);
+
+    let n = n % prime;
+    if n == 0 {
+        return Some(0);
+    }
+
+    // Euler's criterion: `n` is a residue iff `n^((p - 1) / 2) == 1`
+    let half = (prime - 1) / 2;
+    if mod_exp(n, half, prime) != 1 {
+        return None;
+    }
+
+    // write `prime - 1` as `q * 2^s` with `q` odd, and find any non-residue `z`
+    let s = (prime - 1).trailing_zeros();
+    let q = (prime - 1) >> s;
+    let z = (2..prime).find(|&z| mod_exp(z, half, prime) == prime - 1)?;
+
+    // invariant: `root^2 == n * t`, where `t` has an order dividing `2^(m - 1)`
+    let mut m = s;
+    let mut c = mod_exp(z, q, prime);
+    let mut t = mod_exp(n, q, prime);
+    let mut root = mod_exp(n, q.div_ceil(2), prime);
+
+    while t != 1 {
+        // find the least `i` such that `t^(2^i) == 1`
+        let mut i = 0;
+        let mut power = t;
+        while power != 1 {
+            power = mul_mod(power, power, prime);
+            i += 1;
+            if i == m {
+                return None;
+            }
+        }
+
+        let b = mod_exp(c, 1 << (m - i - 1), prime);
+        m = i;
+        c = mul_mod(b, b, prime);
+        t = mul_mod(t, c, prime);
+        root = mul_mod(root, b, prime);
+    }
+
+    Some(root)
+}
+
 /**
  * Calculate `base` raised to `exponent` modulo `modulus`.
  */
@@ -65,20 +244,57 @@ pub fn mod_exp(mut base: Num, exponent: Num, modulus: Num) -> Num {
         return val;
     }
 
-    let mut result: BigNum = 1;
+    let mut result: Num = 1;
     let mut mask: Num = (1 as Num) << (BITS_PER_NUM - 1);
 
     // iterate through each bit in the exponent, performing the square/multiply ops
     // as specified in the algorithm covered in class
     for _ in 0..BITS_PER_NUM {
-        result = result.pow(2) % modulus as BigNum;
+        result = mul_mod(result, result, modulus);
 
         if exponent & mask != 0 {
-            result = (result * base as BigNum) % modulus as BigNum;
+            result = mul_mod(result, base, modulus);
         }
 
         mask >>= 1;
     }
 
-    result as Num
+    result
+}
+
+/**
+ * Calculate `base` raised to `exponent` modulo `modulus` as in `mod_exp`, but with a Montgomery
+ * ladder, which performs the same sequence of multiplications whatever the exponent's bits are.
+ * This is slower than `mod_exp`, so it's meant for secret exponents, whose bits would otherwise
+ * leak through the time taken.
+ *
+ * With the `wide` feature, `mul_mod` itself takes time that depends on its operands, so only the
+ * sequence of operations is independent of the exponent.
+ */
+pub fn mod_exp_ct(base: Num, exponent: Num, modulus: Num) -> Num {
+    assert!(modulus > 0);
+
+    let mut low = 1 % modulus;
+    let mut high = base % modulus;
+
+    // invariant: `high == low * base`, with `low` raised to the bits of `exponent` seen so far
+    for i in (0..BITS_PER_NUM).rev() {
+        let bit = (exponent >> i) & 1;
+        swap_if(bit, &mut low, &mut high);
+        high = mul_mod(low, high, modulus);
+        low = mul_mod(low, low, modulus);
+        swap_if(bit, &mut low, &mut high);
+    }
+
+    low
+}
+
+/**
+ * Swap `a` and `b` if `bit` is 1, without branching on it.
+ */
+fn swap_if(bit: Num, a: &mut Num, b: &mut Num) {
+    let mask = bit.wrapping_neg();
+    let diff = mask & (*a ^ *b);
+    *a ^= diff;
+    *b ^= diff;
 }
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{add_mod, ext_gcd, mod_exp, mul_mod, Num};
+use std::{
+    fmt::{self, Display},
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/**
+ * An integer modulo `modulus`. The value is always kept reduced, and arithmetic between two
+ * `ModInt`s reduces its result automatically.
+ *
+ * Arithmetic between values with different moduli is a logic error, and panics.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ModInt {
+    value: Num,
+    modulus: Num,
+}
+
+impl ModInt {
+    /**
+     * Create a new integer with the value `value % modulus`.
+     *
+     * Panics if `modulus == 0`.
+     */
+    pub fn new(value: Num, modulus: Num) -> Self {
+        assert!(modulus > 0);
+
+        Self {
+            value: value % modulus,
+            modulus,
+        }
+    }
+
+    /**
+     * Get the reduced value of the integer, which is always less than the modulus.
+     */
+    pub fn value(self) -> Num {
+        self.value
+    }
+
+    /**
+     * Get the modulus that the integer is reduced by.
+     */
+    pub fn modulus(self) -> Num {
+        self.modulus
+    }
+
+    /**
+     * Raise the integer to the power `exponent`. Unlike `mod_exp`, `0^0` is defined as 1.
+     */
+    pub fn pow(self, exponent: Num) -> Self {
+        if exponent == 0 {
+            Self::new(1, self.modulus)
+        } else {
+            Self::new(mod_exp(self.value, exponent, self.modulus), self.modulus)
+        }
+    }
+
+    /**
+     * Find the multiplicative inverse of the integer, i.e., the value `x` such that
+     * `self * x == 1`.
+     *
+     * Returns `None` if the value and the modulus aren't coprime, since no inverse exists.
+     */
+    pub fn inverse(self) -> Option<Self> {
+        match ext_gcd(self.value, self.modulus) {
+            (1, coeff) => Some(Self::new(coeff, self.modulus)),
+            _ => None,
+        }
+    }
+
+    /**
+     * Check that `other` has the same modulus, returning the shared modulus.
+     */
+    fn shared_modulus(self, other: Self) -> Num {
+        assert_eq!(
+            self.modulus, other.modulus,
+            "Arithmetic between integers with different moduli"
+        );
+        self.modulus
+    }
+}
+
+impl Add for ModInt {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let modulus = self.shared_modulus(other);
+
+        Self {
+            value: add_mod(self.value, other.value, modulus),
+            modulus,
+        }
+    }
+}
+
+impl Sub for ModInt {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for ModInt {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let modulus = self.shared_modulus(other);
+        Self {
+            value: mul_mod(self.value, other.value, modulus),
+            modulus,
+        }
+    }
+}
+
+impl Neg for ModInt {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let value = match self.value {
+            0 => 0,
+            v => self.modulus - v,
+        };
+
+        Self {
+            value,
+            modulus: self.modulus,
+        }
+    }
+}
+
+impl Display for ModInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (mod {})", self.value, self.modulus)
+    }
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+const PRIME: Num = 18446744073709551557; // largest prime less than 2^64
+
+fn m(value: Num) -> ModInt {
+    ModInt::new(value, PRIME)
+}
+
+#[test]
+fn test_new() {
+    assert_eq!(ModInt::new(10, 7).value(), 3);
+    assert_eq!(ModInt::new(7, 7).value(), 0);
+    assert_eq!(ModInt::new(Num::MAX, 1).value(), 0);
+    assert_eq!(m(u64::MAX as Num).value(), u64::MAX as Num - PRIME);
+}
+
+#[test]
+fn test_arithmetic() {
+    // addition wraps without overflowing `Num`
+    assert_eq!(m(PRIME - 1) + m(PRIME - 1), m(PRIME - 2));
+    assert_eq!(ModInt::new(5, 7) + ModInt::new(4, 7), ModInt::new(2, 7));
+    assert_eq!(
+        ModInt::new(Num::MAX - 1, Num::MAX) + ModInt::new(Num::MAX - 1, Num::MAX),
+        ModInt::new(Num::MAX - 2, Num::MAX)
+    );
+
+    assert_eq!(ModInt::new(2, 7) - ModInt::new(5, 7), ModInt::new(4, 7));
+    assert_eq!(m(0) - m(1), m(PRIME - 1));
+    assert_eq!(-m(0), m(0));
+
+    assert_eq!(ModInt::new(5, 7) * ModInt::new(4, 7), ModInt::new(6, 7));
+    assert_eq!(m(PRIME - 1) * m(PRIME - 1), m(1));
+}
+
+#[test]
+fn test_pow() {
+    assert_eq!(ModInt::new(16, 13).pow(4), ModInt::new(3, 13));
+    assert_eq!(m(0).pow(0), m(1));
+    assert_eq!(m(0).pow(5), m(0));
+    assert_eq!(m(12345).pow(PRIME - 1), m(1));
+}
+
+#[test]
+fn test_inverse() {
+    assert_eq!(ModInt::new(3, 11).inverse(), Some(ModInt::new(4, 11)));
+    assert_eq!(ModInt::new(4, 8).inverse(), None);
+    assert_eq!(m(0).inverse(), None);
+
+    for &value in &[1, 2, 3, 12345, 1 << 40, PRIME - 1] {
+        let x = m(value);
+        assert_eq!(x * x.inverse().unwrap(), m(1));
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_mismatched_moduli() {
+    let _ = ModInt::new(1, 7) + ModInt::new(1, 11);
+}
@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{mul_mod, Num, BITS_PER_NUM};
+
+// the number of exponent bits handled by each row of a `FixedBase` table
+const WINDOW_BITS: usize = 4;
+const WINDOW_SIZE: usize = 1 << WINDOW_BITS;
+const WINDOWS: usize = BITS_PER_NUM / WINDOW_BITS;
+
+/**
+ * Precomputed constants for Montgomery multiplication modulo an odd modulus `n`.
+ *
+ * Values are kept in Montgomery form `a * R mod n`, where `R = 2^BITS_PER_NUM`, which lets each
+ * product be reduced with multiplications and shifts instead of a division. Converting into and
+ * out of the form costs a multiplication each, so this pays off for long chains of operations
+ * under one modulus, such as exponentiation.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Montgomery {
+    modulus: Num,
+    // `-n^-1 mod R`
+    neg_inverse: Num,
+    // `R^2 mod n`
+    r_squared: Num,
+    // `R mod n`, i.e., 1 in Montgomery form
+    one: Num,
+}
+
+impl Montgomery {
+    /**
+     * Precompute the constants for `modulus`.
+     *
+     * Returns `None` if `modulus` is even or less than 3.
+     */
+    pub fn new(modulus: Num) -> Option<Self> {
+        if modulus.is_multiple_of(2) || modulus < 3 {
+            return None;
+        }
+
+        // Newton's iteration doubles the number of correct low bits of the inverse each step
+        let mut inverse: Num = 1;
+        for _ in 0..BITS_PER_NUM.trailing_zeros() {
+            inverse = inverse.wrapping_mul((2 as Num).wrapping_sub(modulus.wrapping_mul(inverse)));
+        }
+
+        let one = (Num::MAX % modulus + 1) % modulus;
+        Some(Self {
+            modulus,
+            neg_inverse: inverse.wrapping_neg(),
+            r_squared: mul_mod(one, one, modulus),
+            one,
+        })
+    }
+
+    /**
+     * Get the modulus.
+     */
+    pub fn modulus(&self) -> Num {
+        self.modulus
+    }
+
+    /**
+     * Convert `value` to Montgomery form.
+     */
+    pub fn to_form(&self, value: Num) -> Num {
+        self.mul(value % self.modulus, self.r_squared)
+    }
+
+    /**
+     * Convert `value` out of Montgomery form.
+     */
+    pub fn from_form(&self, value: Num) -> Num {
+        self.reduce(0, value)
+    }
+
+    /**
+     * Multiply two values in Montgomery form.
+     */
+    pub fn mul(&self, a: Num, b: Num) -> Num {
+        let (high, low) = mul_wide(a, b);
+        self.reduce(high, low)
+    }
+
+    /**
+     * Raise `base`, in Montgomery form, to `exponent`, returning the result in Montgomery form.
+     */
+    pub fn pow(&self, base: Num, exponent: Num) -> Num {
+        let mut result = self.one;
+        let mut power = base;
+        let mut exponent = exponent;
+
+        while exponent != 0 {
+            if exponent & 1 == 1 {
+                result = self.mul(result, power);
+            }
+
+            power = self.mul(power, power);
+            exponent >>= 1;
+        }
+
+        result
+    }
+
+    /**
+     * Raise `base`, in Montgomery form, to `exponent` as in `pow`, but with a Montgomery ladder,
+     * which performs the same sequence of multiplications whatever the exponent's bits are (see
+     * `mod_exp_ct`).
+     */
+    pub fn pow_ct(&self, base: Num, exponent: Num) -> Num {
+        let mut low = self.one;
+        let mut high = base;
+
+        for i in (0..BITS_PER_NUM).rev() {
+            let bit = (exponent >> i) & 1;
+            crate::swap_if(bit, &mut low, &mut high);
+            high = self.mul(low, high);
+            low = self.mul(low, low);
+            crate::swap_if(bit, &mut low, &mut high);
+        }
+
+        low
+    }
+
+    /**
+     * Montgomery reduction: compute `t * R^-1 mod n` for the double-width value
+     * `t = high * R + low`, given that `t < n * R`.
+     */
+    fn reduce(&self, high: Num, low: Num) -> Num {
+        // adding `m * n` makes the low half zero, so the high half is `t * R^-1` up to one `n`
+        let m = low.wrapping_mul(self.neg_inverse);
+        let (m_high, m_low) = mul_wide(m, self.modulus);
+        let carry = (low.overflowing_add(m_low).1) as Num;
+
+        let (sum, overflowed) = high.overflowing_add(m_high);
+        let (sum, carried) = sum.overflowing_add(carry);
+        if overflowed || carried || sum >= self.modulus {
+            sum.wrapping_sub(self.modulus)
+        } else {
+            sum
+        }
+    }
+}
+
+/**
+ * A table of powers of a fixed base, for exponentiating it faster than `Montgomery::pow`.
+ *
+ * Row `i` holds `base^(j * 16^i)` for each `j < 16`, so raising the base to any exponent takes one
+ * multiplication per 4-bit digit of the exponent, with no squaring.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedBase {
+    montgomery: Montgomery,
+    table: Vec<[Num; WINDOW_SIZE]>,
+}
+
+impl FixedBase {
+    /**
+     * Build the table for `base` modulo the modulus of `montgomery`.
+     */
+    pub fn new(base: Num, montgomery: Montgomery) -> Self {
+        let mut table = Vec::with_capacity(WINDOWS);
+        let mut row_base = montgomery.to_form(base);
+
+        for _ in 0..WINDOWS {
+            let mut row = [montgomery.one; WINDOW_SIZE];
+            for j in 1..WINDOW_SIZE {
+                row[j] = montgomery.mul(row[j - 1], row_base);
+            }
+
+            row_base = montgomery.mul(row[WINDOW_SIZE - 1], row_base);
+            table.push(row);
+        }
+
+        Self { montgomery, table }
+    }
+
+    /**
+     * Calculate `base^exponent` modulo the modulus, as an ordinary value rather than in Montgomery
+     * form.
+     */
+    pub fn pow(&self, exponent: Num) -> Num {
+        let digit_mask = (WINDOW_SIZE - 1) as Num;
+        let result = self
+            .table
+            .iter()
+            .enumerate()
+            .fold(self.montgomery.one, |acc, (i, row)| {
+                let digit = (exponent >> (i * WINDOW_BITS)) & digit_mask;
+                self.montgomery.mul(acc, row[digit as usize])
+            });
+
+        self.montgomery.from_form(result)
+    }
+}
+
+/**
+ * Calculate the full product `a * b` as its high and low halves.
+ */
+#[cfg(not(feature = "wide"))]
+fn mul_wide(a: Num, b: Num) -> (Num, Num) {
+    let product = a as crate::BigNum * b as crate::BigNum;
+    ((product >> BITS_PER_NUM) as Num, product as Num)
+}
+
+/**
+ * Calculate the full product `a * b` as its high and low halves, by schoolbook multiplication of
+ * their halves, since there's no integer type twice as wide as `Num`.
+ */
+#[cfg(feature = "wide")]
+fn mul_wide(a: Num, b: Num) -> (Num, Num) {
+    const HALF_BITS: usize = BITS_PER_NUM / 2;
+    const HALF_MASK: Num = (1 << HALF_BITS) - 1;
+
+    let (a_high, a_low) = (a >> HALF_BITS, a & HALF_MASK);
+    let (b_high, b_low) = (b >> HALF_BITS, b & HALF_MASK);
+
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+
+    // the middle terms straddle the halves; this sum can't overflow
+    let middle = (low_low >> HALF_BITS) + (high_low & HALF_MASK) + (low_high & HALF_MASK);
+    let low = (middle << HALF_BITS) | (low_low & HALF_MASK);
+    let high =
+        high_high + (high_low >> HALF_BITS) + (low_high >> HALF_BITS) + (middle >> HALF_BITS);
+    (high, low)
+}
@@ -0,0 +1,81 @@
+use super::*;
+use crate::mod_exp;
+
+const MODULI: [Num; 6] = [
+    3,
+    55,
+    4294967387,
+    18446744073709551557, // largest prime less than 2^64
+    (u64::MAX - 2) as Num,
+    Num::MAX,
+];
+
+const VALUES: [Num; 9] = [
+    0,
+    1,
+    2,
+    54,
+    65537,
+    u32::MAX as Num,
+    18446744073709551556,
+    Num::MAX - 1,
+    Num::MAX,
+];
+
+#[test]
+fn test_new() {
+    assert!(Montgomery::new(0).is_none());
+    assert!(Montgomery::new(1).is_none());
+    assert!(Montgomery::new(4294967386).is_none());
+
+    for &modulus in &MODULI {
+        let montgomery = Montgomery::new(modulus).unwrap();
+        assert_eq!(montgomery.modulus(), modulus);
+        assert_eq!(montgomery.neg_inverse.wrapping_mul(modulus), Num::MAX);
+    }
+}
+
+#[test]
+fn test_mul_wide() {
+    assert_eq!(mul_wide(0, Num::MAX), (0, 0));
+    assert_eq!(mul_wide(Num::MAX, 2), (1, Num::MAX - 1));
+    assert_eq!(mul_wide(Num::MAX, Num::MAX), (Num::MAX - 1, 1));
+}
+
+#[test]
+fn test_arithmetic() {
+    for &modulus in &MODULI {
+        let montgomery = Montgomery::new(modulus).unwrap();
+
+        for &a in &VALUES {
+            let a_form = montgomery.to_form(a);
+            assert_eq!(montgomery.from_form(a_form), a % modulus);
+
+            for &b in &VALUES {
+                let b_form = montgomery.to_form(b);
+                let product = montgomery.from_form(montgomery.mul(a_form, b_form));
+                assert_eq!(product, mul_mod(a, b, modulus));
+
+                if a != 0 || b != 0 {
+                    let expected = mod_exp(a, b, modulus);
+                    assert_eq!(montgomery.from_form(montgomery.pow(a_form, b)), expected);
+                    assert_eq!(montgomery.from_form(montgomery.pow_ct(a_form, b)), expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fixed_base() {
+    for &modulus in &MODULI {
+        let montgomery = Montgomery::new(modulus).unwrap();
+
+        for &base in &VALUES[1..] {
+            let table = FixedBase::new(base, montgomery);
+            for &exponent in &VALUES {
+                assert_eq!(table.pow(exponent), mod_exp(base, exponent, modulus));
+            }
+        }
+    }
+}
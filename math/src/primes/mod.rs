@@ -1,9 +1,13 @@
 #[cfg(test)]
 mod tests;
 
-use crate::{mod_exp, Num};
-use rand::prelude::Rng;
-use std::iter;
+use crate::{isqrt, mod_exp, Num};
+use rand::{prelude::Rng, rngs::StdRng, SeedableRng};
+use std::{
+    cmp, iter,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub enum PrimeError {
@@ -15,7 +19,7 @@ pub enum PrimeError {
  * Returns true if `val` is a witness for the compositeness of `n`, otherwise false.
  */
 pub fn is_witness(n: Num, val: Num) -> bool {
-    if n < 3 || n % 2 == 0 {
+    if n < 3 || n.is_multiple_of(2) {
         // `n` isn't prime
         return false;
     }
@@ -24,7 +28,7 @@ pub fn is_witness(n: Num, val: Num) -> bool {
     let mut q = n - 1;
 
     // break n-1 up into 2^k * q
-    while q % 2 == 0 {
+    while q.is_multiple_of(2) {
         q >>= 1;
         k += 1;
     }
@@ -45,24 +49,73 @@ pub fn is_witness(n: Num, val: Num) -> bool {
 }
 
 /**
- * Run `check_witness` on `n` with `witnesses` random witnesses.
+ * Run `is_witness` on `n` with `witness_count` random witnesses generated by `rng`.
  *
- * Returns true if a witness for the compositeness of `n` was found, otherwise false.
+ * Returns true if a witness for the compositeness of `n` was found, otherwise false. Note that
+ * even numbers and numbers less than 3 are never considered to have witnesses; use `is_prime` for
+ * a complete primality test.
  */
-fn check_random_witnesses<T: Rng>(n: Num, witness_count: usize, rng: &mut T) -> bool {
-    if n < 3 || n % 2 == 0 {
+pub fn check_random_witnesses<T: Rng>(n: Num, witness_count: usize, rng: &mut T) -> bool {
+    if n < 3 || n.is_multiple_of(2) {
         // `n` isn't prime
         return false;
     }
 
-    iter::repeat_with(|| rng.gen_range(2..n - 1))
-        .take(witness_count)
-        .any(|val| is_witness(n, val))
+    let candidates = iter::repeat_with(|| rng.gen_range(2..n - 1)).take(witness_count);
+    contains_witness(n, candidates)
 }
 
-fn is_prime<T: Rng>(n: Num, rng: &mut T) -> bool {
-    const WITNESS_COUNT: usize = 25;
-    !check_random_witnesses(n, WITNESS_COUNT, rng)
+/**
+ * Check each value in `candidates` with `is_witness`, stopping at the first witness found.
+ */
+#[cfg(not(feature = "parallel"))]
+fn contains_witness<I: Iterator<Item = Num>>(n: Num, mut candidates: I) -> bool {
+    candidates.any(|val| is_witness(n, val))
+}
+
+/**
+ * Check each value in `candidates` with `is_witness` across the `rayon` thread pool, stopping at
+ * the first witness found.
+ *
+ * The candidates are all drawn up front so that the caller's RNG is only ever used by the calling
+ * thread, which avoids requiring a thread-safe RNG and keeps seeded results reproducible.
+ */
+#[cfg(feature = "parallel")]
+fn contains_witness<I: Iterator<Item = Num>>(n: Num, candidates: I) -> bool {
+    use rayon::prelude::*;
+
+    let candidates: Vec<Num> = candidates.collect();
+    candidates.into_par_iter().any(|val| is_witness(n, val))
+}
+
+/**
+ * The number of Miller-Rabin witnesses used for primality testing when no other count is given.
+ * This gives an error probability of at most `4^-25`, or roughly `10^-15`, per composite tested.
+ */
+pub const DEFAULT_WITNESS_COUNT: usize = 25;
+
+/**
+ * Calculate the number of Miller-Rabin witnesses needed for a composite to be reported as prime
+ * with probability at most `error_bound`, based on the `4^-k` error bound for `k` witnesses.
+ *
+ * Panics if `error_bound` isn't in the range `(0, 1]`.
+ */
+pub fn witnesses_for_error_bound(error_bound: f64) -> usize {
+    assert!(error_bound > 0.0 && error_bound <= 1.0);
+    (-error_bound.log(4.0)).ceil() as usize
+}
+
+/**
+ * Check if `n` is probably prime using the Miller-Rabin test with `witness_count` random witnesses
+ * generated by `rng`. A composite `n` is reported as prime with probability at most
+ * `4^-witness_count`, while a prime `n` is always reported as prime.
+ */
+pub fn is_prime<T: Rng>(n: Num, witness_count: usize, rng: &mut T) -> bool {
+    match n {
+        0 | 1 | 4 => false,
+        2 | 3 => true,
+        _ => n % 2 == 1 && !check_random_witnesses(n, witness_count, rng),
+    }
 }
 
 /**
@@ -72,7 +125,7 @@ fn is_prime<T: Rng>(n: Num, rng: &mut T) -> bool {
  *
  * The math is based on proofs by Dusart and Nagura, who proved the following:
  *  - For `x >= 25`, a prime exists in the range `[x, 6x / 5]` (See: [Nagura 1952][1])
- *  - For `x >= 3275`, a prime exists in the range `[x, x + x / (2 ln^2 x)]` (See: [Dusart 1998][1])
+ *  - For `x >= 3275`, a prime exists in the range `[x, x + x / (2 ln^2 x)]` (See: [Dusart 1998][2])
  *  - For `x >= 89693`, a prime exists in the range `[x, x + x / (ln^3 x)]` (See: [Dusart 2016][3])
  *
  * For use in this function, the upper bounds can be factored into the form `(1 + epsilon) * x`,
@@ -85,9 +138,13 @@ fn is_prime<T: Rng>(n: Num, rng: &mut T) -> bool {
  * [2]: https://www.unilim.fr/laco/theses/1998/T1998_01.pdf
  * [3]: http://link.springer.com/article/10.1007/s11139-016-9839-4
  */
-fn range_contains_known_prime(min: Num, max: Num) -> bool {
-    assert!(min >= 3);
-    assert!(max >= min);
+pub fn range_contains_known_prime(min: Num, max: Num) -> bool {
+    if min > max {
+        return false;
+    } else if min < 3 {
+        // the range contains 2 if it contains anything at or above it
+        return max >= 2;
+    }
 
     const NAGURA_1952_MIN: Num = 25;
     const DUSART_1998_MIN: Num = 3275;
@@ -115,49 +172,343 @@ fn range_contains_known_prime(min: Num, max: Num) -> bool {
 }
 
 /**
- * Pick a random prime in the inclusive range `[min, max]` using `rng`, and a random primitive root
- * of the prime.
+ * Calculate the logarithmic integral `li(x)`, the integral of `1 / ln t` from 0 to `x`, using
+ * Ramanujan's series. See: [Logarithmic integral function][1]
  *
- * On success, returns a pair `(prime, primitive_root)`.
+ * Panics if `x <= 1`, since the integral diverges at `x = 1`.
+ *
+ * [1]: https://mathworld.wolfram.com/LogarithmicIntegral.html
  */
-pub fn pick_random_with_root<T: Rng>(
+fn log_integral(x: f64) -> f64 {
+    assert!(x > 1.0);
+
+    const EULER_MASCHERONI: f64 = 0.577_215_664_901_532_9;
+    const MAX_TERMS: i32 = 1000;
+
+    let ln_x = x.ln();
+    let mut sum = 0.0;
+    let mut coefficient = 1.0; // (-1)^(n - 1) * ln^n x / (n! * 2^(n - 1))
+    let mut inner_sum = 0.0; // sum of 1 / (2k + 1) for k from 0 to (n - 1) / 2
+
+    for n in 1..=MAX_TERMS {
+        coefficient *= ln_x / n as f64;
+        if n > 1 {
+            coefficient *= -0.5;
+        }
+
+        if n % 2 == 1 {
+            inner_sum += 1.0 / n as f64;
+        }
+
+        let term = coefficient * inner_sum;
+        sum += term;
+
+        if term.abs() < sum.abs() * f64::EPSILON {
+            break;
+        }
+    }
+
+    EULER_MASCHERONI + ln_x.ln() + x.sqrt() * sum
+}
+
+/**
+ * Estimate the number of primes in the inclusive range `[min, max]` using the offset logarithmic
+ * integral, i.e., `li(max) - li(min)`. By the prime number theorem, the relative error of this
+ * estimate shrinks as the range grows; for ranges above `2^32` spanning a few million integers,
+ * it's typically within a fraction of a percent.
+ *
+ * This can be used along with `range_contains_known_prime` to predict how long
+ * `pick_random_with_root` will take, since about `(max - min) / count` candidates are needed for
+ * each prime found. Note that only a fraction of these primes are safe primes.
+ */
+pub fn estimated_prime_count(min: Num, max: Num) -> f64 {
+    if max < 2 || min > max {
+        return 0.0;
+    }
+
+    let min = cmp::max(min, 2);
+    log_integral(max as f64) - log_integral(min as f64)
+}
+
+/**
+ * Pick a random prime in the inclusive range `[min, max]` using `rng`. Unlike
+ * `pick_random_with_root`, the prime doesn't have to be a safe prime. Each candidate is tested for
+ * primality with `witness_count` Miller-Rabin witnesses.
+ */
+pub fn pick_random<T: Rng>(
     min: Num,
     max: Num,
+    witness_count: usize,
     rng: &mut T,
-) -> Result<(Num, Num), PrimeError> {
-    if min > max || max < 3 {
+) -> Result<Num, PrimeError> {
+    if min > max || max < 2 {
         return Err(PrimeError::InvalidRange);
     }
 
-    let mut primality_rng = rand::thread_rng();
-    let gen_candidate = || (rng.gen_range(min..=max) - 1) / 2;
-    let get_prime = |n| {
-        // FilterMap closure to return only primes with a primitive root of 2
+    let min = cmp::max(min, 2);
+    let gen_prime = || {
+        let n = rng.gen_range(min..=max);
+        if is_prime(n, witness_count, rng) {
+            Some(n)
+        } else {
+            None
+        }
+    };
+
+    let candidates = iter::repeat_with(gen_prime);
+    let result = if range_contains_known_prime(min, max) {
+        candidates.flatten().next()
+    } else {
+        // unknown whether or not the range contains a prime, so use a probabilistic approach
+        let prime_attempts = 5_usize.saturating_mul((max - min).saturating_add(1) as usize);
+        candidates.take(prime_attempts).flatten().next()
+    };
+
+    result.ok_or(PrimeError::PrimeNotFound)
+}
+
+/**
+ * The primitive root of every safe prime picked by `pick_random_with_root`.
+ */
+const SAFE_PRIME_ROOT: Num = 2;
+
+/**
+ * Generate candidates for `pick_random_with_root` in the inclusive range `[min, max]`, which must
+ * be valid. Each candidate is either a safe prime with a primitive root of 2, or `None`.
+ */
+fn safe_prime_candidates<T: Rng>(
+    min: Num,
+    max: Num,
+    witness_count: usize,
+    rng: &mut T,
+) -> impl Iterator<Item = Option<Num>> + '_ {
+    iter::repeat_with(move || {
+        // only return safe primes with a primitive root of 2
+        let n = (rng.gen_range(min..=max) - 1) / 2;
         let prime = 2 * n + 1;
         if n % 2 == 1
             && n % 12 == 5
-            && is_prime(n, &mut primality_rng)
-            && is_prime(prime, &mut primality_rng)
+            && is_prime(n, witness_count, rng)
+            && is_prime(prime, witness_count, rng)
         {
             Some(prime)
         } else {
             None
         }
-    };
+    })
+}
 
-    let candidates = iter::repeat_with(gen_candidate);
-    let result = if range_contains_known_prime(min, max) {
-        candidates.filter_map(get_prime).next()
+/**
+ * Pick a random prime in the inclusive range `[min, max]` using `rng`, and a random primitive root
+ * of the prime. Each candidate is tested for primality with `witness_count` Miller-Rabin witnesses.
+ *
+ * On success, returns a pair `(prime, primitive_root)`.
+ */
+pub fn pick_random_with_root<T: Rng>(
+    min: Num,
+    max: Num,
+    witness_count: usize,
+    rng: &mut T,
+) -> Result<(Num, Num), PrimeError> {
+    if min > max || max < 3 {
+        return Err(PrimeError::InvalidRange);
+    }
+
+    // safe primes are at least 5, so nothing is lost by skipping 0 through 2
+    let min = cmp::max(min, 3);
+    let known_prime = range_contains_known_prime(min, max);
+    let candidates = safe_prime_candidates(min, max, witness_count, rng);
+    let result = if known_prime {
+        candidates.flatten().next()
     } else {
         // unknown whether or not the range contains a prime, so use a probabilistic approach
-        let prime_attempts = 5_usize.saturating_mul((min - max + 1) as usize);
-        candidates.take(prime_attempts).filter_map(get_prime).next()
+        let prime_attempts = 5_usize.saturating_mul((max - min).saturating_add(1) as usize);
+        candidates.take(prime_attempts).flatten().next()
     };
 
-    if let Some(prime) = result {
-        const ROOT: Num = 2;
-        Ok((prime, ROOT))
-    } else {
-        Err(PrimeError::PrimeNotFound)
+    result
+        .map(|prime| (prime, SAFE_PRIME_ROOT))
+        .ok_or(PrimeError::PrimeNotFound)
+}
+
+/**
+ * Pick a random prime and primitive root as in `pick_random_with_root`, but give up after testing
+ * `candidate_count` random candidates, even if the range is known to contain a safe prime.
+ *
+ * Returns `PrimeError::PrimeNotFound` if none of the candidates were safe primes.
+ */
+pub fn pick_random_with_root_bounded<T: Rng>(
+    min: Num,
+    max: Num,
+    witness_count: usize,
+    candidate_count: usize,
+    rng: &mut T,
+) -> Result<(Num, Num), PrimeError> {
+    if min > max || max < 3 {
+        return Err(PrimeError::InvalidRange);
     }
+
+    let min = cmp::max(min, 3);
+    safe_prime_candidates(min, max, witness_count, rng)
+        .take(candidate_count)
+        .flatten()
+        .next()
+        .map(|prime| (prime, SAFE_PRIME_ROOT))
+        .ok_or(PrimeError::PrimeNotFound)
+}
+
+/**
+ * Pick a random prime and primitive root as in `pick_random_with_root_bounded`, testing candidates
+ * across `thread_count` threads.
+ *
+ * Each thread tests candidates from its own `StdRng`, seeded from `rng`, and the candidates are
+ * numbered so that thread `i` tests candidates `i`, `i + thread_count`, and so on. The safe prime
+ * with the lowest number is returned, so for a given `rng` and thread count, the result doesn't
+ * depend on how the threads are scheduled. It does depend on the thread count, though, and with
+ * a single thread, this is the same as `pick_random_with_root_bounded`.
+ */
+pub fn pick_random_with_root_threaded<T: Rng>(
+    min: Num,
+    max: Num,
+    witness_count: usize,
+    candidate_count: usize,
+    thread_count: usize,
+    rng: &mut T,
+) -> Result<(Num, Num), PrimeError> {
+    if thread_count <= 1 {
+        return pick_random_with_root_bounded(min, max, witness_count, candidate_count, rng);
+    }
+
+    if min > max || max < 3 {
+        return Err(PrimeError::InvalidRange);
+    }
+
+    let min = cmp::max(min, 3);
+    let seeds: Vec<<StdRng as SeedableRng>::Seed> =
+        iter::repeat_with(|| rng.gen()).take(thread_count).collect();
+
+    // the lowest number of any safe prime found so far; threads stop once they pass it
+    let best = AtomicUsize::new(usize::MAX);
+    let search = |index: usize, seed| {
+        let mut rng = StdRng::from_seed(seed);
+        let numbers = (index..).step_by(thread_count);
+        let candidates = safe_prime_candidates(min, max, witness_count, &mut rng);
+
+        for (number, candidate) in numbers.zip(candidates) {
+            if number >= candidate_count || number > best.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            if let Some(prime) = candidate {
+                best.fetch_min(number, Ordering::Relaxed);
+                return Some((number, prime));
+            }
+        }
+
+        None
+    };
+
+    let found: Vec<(usize, Num)> = thread::scope(|scope| {
+        let search = &search;
+        let handles: Vec<_> = seeds
+            .into_iter()
+            .enumerate()
+            .map(|(index, seed)| scope.spawn(move || search(index, seed)))
+            .collect();
+
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    found
+        .into_iter()
+        .min_by_key(|&(number, _)| number)
+        .map(|(_, prime)| (prime, SAFE_PRIME_ROOT))
+        .ok_or(PrimeError::PrimeNotFound)
+}
+
+/**
+ * Find all primes less than or equal to `max` using a plain sieve of Eratosthenes. This is used to
+ * generate the sieving primes for `primes_in_range`.
+ */
+fn sieve_up_to(max: Num) -> Vec<Num> {
+    if max < 2 {
+        return Vec::new();
+    }
+
+    let len = max as usize + 1;
+    let mut is_composite = vec![false; len];
+
+    for i in (2..len).take_while(|i| i * i < len) {
+        if !is_composite[i] {
+            (i * i..len).step_by(i).for_each(|j| is_composite[j] = true);
+        }
+    }
+
+    (2..len)
+        .filter(|&i| !is_composite[i])
+        .map(|i| i as Num)
+        .collect()
+}
+
+/**
+ * Sieve the inclusive segment `[start, end]` with the given sieving primes, which must contain
+ * every prime less than or equal to `sqrt(end)`.
+ *
+ * Returns the primes within the segment in increasing order.
+ */
+fn sieve_segment(start: Num, end: Num, sieving_primes: &[Num]) -> Vec<Num> {
+    let mut is_composite = vec![false; (end - start) as usize + 1];
+
+    for &p in sieving_primes.iter().take_while(|&&p| p <= end / p) {
+        // start at the first multiple of `p` within the segment, skipping `p` itself
+        let first_multiple = match start.div_ceil(p).checked_mul(p) {
+            Some(multiple) => cmp::max(p * p, multiple),
+            None => continue,
+        };
+
+        // step through the remaining multiples by index to avoid overflow near `Num::MAX`
+        let first_index = (first_multiple - start) as usize;
+        (first_index..is_composite.len())
+            .step_by(p as usize)
+            .for_each(|i| is_composite[i] = true);
+    }
+
+    is_composite
+        .iter()
+        .zip(start..=end)
+        .filter(|&(&composite, n)| !composite && n >= 2)
+        .map(|(_, n)| n)
+        .collect()
+}
+
+/**
+ * Iterate over every prime in the inclusive range `[min, max]` in increasing order, using a
+ * segmented sieve of Eratosthenes.
+ *
+ * Unlike `pick_random_with_root`, this is exhaustive and deterministic, but it requires
+ * `O(sqrt(max))` memory for the sieving primes and time proportional to the width of the range.
+ * It's intended for small ranges, such as those used in tests and parameter searches.
+ */
+pub fn primes_in_range(min: Num, max: Num) -> impl Iterator<Item = Num> {
+    const SEGMENT_LEN: Num = 1 << 16;
+
+    let sieving_primes = if min <= max {
+        sieve_up_to(isqrt(max))
+    } else {
+        Vec::new()
+    };
+
+    let segment_starts = iter::successors(Some(min).filter(|_| min <= max), move |&start| {
+        start
+            .checked_add(SEGMENT_LEN)
+            .filter(|&next_start| next_start <= max)
+    });
+
+    segment_starts.flat_map(move |start| {
+        let end = cmp::min(start.saturating_add(SEGMENT_LEN - 1), max);
+        sieve_segment(start, end, &sieving_primes)
+    })
 }
@@ -3,6 +3,7 @@ use super::*;
 use rand::{rngs::StdRng, SeedableRng};
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_check_witness() {
     assert_eq!(is_witness(221, 174), false);
     assert_eq!(is_witness(221, 137), true);
@@ -15,6 +16,80 @@ fn test_check_witness() {
     assert_eq!(is_witness(95721889, 21906436), true);
 }
 
+#[test]
+fn test_check_random_witnesses() {
+    const WITNESS_COUNT: usize = 50;
+    let mut rng = StdRng::seed_from_u64(0);
+
+    // primes never have witnesses
+    for &n in &[5, 101, 65537, 4294967311, 18446744073709551557] {
+        assert!(!check_random_witnesses(n, WITNESS_COUNT, &mut rng));
+    }
+
+    // composites, including Carmichael numbers and strong pseudoprimes to base 2
+    for &n in &[
+        9,
+        221,
+        561,
+        2047,
+        3215031751,
+        4294967297,
+        18446744073709551555,
+    ] {
+        assert!(check_random_witnesses(n, WITNESS_COUNT, &mut rng));
+    }
+
+    // even numbers and numbers less than 3 are rejected outright
+    assert!(!check_random_witnesses(1, WITNESS_COUNT, &mut rng));
+    assert!(!check_random_witnesses(1024, WITNESS_COUNT, &mut rng));
+
+    // results are reproducible for a given seed, even when witnesses are checked in parallel
+    let mut first_rng = StdRng::seed_from_u64(1);
+    let mut second_rng = StdRng::seed_from_u64(1);
+    for n in (5..10_000).step_by(2) {
+        assert_eq!(
+            check_random_witnesses(n, 3, &mut first_rng),
+            check_random_witnesses(n, 3, &mut second_rng)
+        );
+    }
+}
+
+#[test]
+fn test_is_prime() {
+    let mut rng = StdRng::from_entropy();
+    let primes: Vec<Num> = primes_in_range(0, 10_000).collect();
+
+    for n in 0..=10_000 {
+        let expected = primes.binary_search(&n).is_ok();
+        assert_eq!(
+            is_prime(n, DEFAULT_WITNESS_COUNT, &mut rng),
+            expected,
+            "n = {}",
+            n
+        );
+    }
+
+    // Carmichael numbers
+    for &n in &[561, 1105, 1729, 2465, 2821, 6601, 8911] {
+        assert!(!is_prime(n, DEFAULT_WITNESS_COUNT, &mut rng));
+    }
+
+    // with no witnesses, odd composites can't be detected
+    assert!(is_prime(221, 0, &mut rng));
+}
+
+#[test]
+fn test_witnesses_for_error_bound() {
+    assert_eq!(witnesses_for_error_bound(1.0), 0);
+    assert_eq!(witnesses_for_error_bound(0.25), 1);
+    assert_eq!(witnesses_for_error_bound(0.2), 2);
+    assert_eq!(
+        witnesses_for_error_bound(4.0_f64.powi(-25)),
+        DEFAULT_WITNESS_COUNT
+    );
+    assert_eq!(witnesses_for_error_bound(2.0_f64.powi(-128)), 64);
+}
+
 #[test]
 fn test_pick_random() {
     const ITERATIONS: usize = 100;
@@ -23,9 +98,243 @@ fn test_pick_random() {
     let mut rng = StdRng::from_entropy();
 
     for _ in 0..ITERATIONS {
-        let (prime, _) = pick_random_with_root(MIN, MAX, &mut rng).unwrap();
+        let (prime, _) = pick_random_with_root(MIN, MAX, DEFAULT_WITNESS_COUNT, &mut rng).unwrap();
         assert!(prime >= MIN);
         assert!(prime <= MAX);
-        assert!(is_prime(prime, &mut rng));
+        assert!(is_prime(prime, DEFAULT_WITNESS_COUNT, &mut rng));
+    }
+}
+
+#[test]
+fn test_pick_random_prime() {
+    const ITERATIONS: usize = 100;
+    const MIN: Num = 1 << 31;
+    const MAX: Num = (1 << 32) - 1;
+    let mut rng = StdRng::from_entropy();
+
+    for _ in 0..ITERATIONS {
+        let prime = pick_random(MIN, MAX, DEFAULT_WITNESS_COUNT, &mut rng).unwrap();
+        assert!((MIN..=MAX).contains(&prime));
+        assert!(is_prime_naive(prime));
+    }
+
+    assert!(matches!(
+        pick_random(24, 28, DEFAULT_WITNESS_COUNT, &mut rng),
+        Err(PrimeError::PrimeNotFound)
+    ));
+    assert!(matches!(
+        pick_random(10, 1, DEFAULT_WITNESS_COUNT, &mut rng),
+        Err(PrimeError::InvalidRange)
+    ));
+    assert!(matches!(
+        pick_random(0, 2, DEFAULT_WITNESS_COUNT, &mut rng),
+        Ok(2)
+    ));
+}
+
+/**
+ * Check primality by trial division, for comparison against the sieve.
+ */
+fn is_prime_naive(n: Num) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|i| i * i <= n)
+            .all(|i| !n.is_multiple_of(i))
+}
+
+#[test]
+fn test_primes_in_range() {
+    let small: Vec<Num> = primes_in_range(0, 30).collect();
+    assert_eq!(small, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+
+    assert_eq!(primes_in_range(2, 2).collect::<Vec<_>>(), [2]);
+    assert_eq!(primes_in_range(24, 28).count(), 0);
+    assert_eq!(primes_in_range(10, 9).count(), 0);
+
+    // spans several segments
+    assert_eq!(primes_in_range(0, 1_000_000).count(), 78498);
+
+    // crosses the 32-bit boundary, where key generation picks its primes
+    let (min, max) = (u32::MAX as Num - 5_000, u32::MAX as Num + 5_000);
+    let expected: Vec<Num> = (min..=max).filter(|&n| is_prime_naive(n)).collect();
+    assert_eq!(primes_in_range(min, max).collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_seeded_determinism() {
+    const SEED: u64 = 0x5eed;
+    const MIN: Num = (u32::MAX as Num) + 1;
+    const MAX: Num = u64::MAX as Num;
+
+    let pick = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        pick_random_with_root(MIN, MAX, DEFAULT_WITNESS_COUNT, &mut rng).unwrap()
+    };
+
+    // the same seed always gives the same prime, since no other source of randomness is used
+    assert_eq!(pick(SEED), pick(SEED));
+    assert_ne!(pick(SEED), pick(SEED + 1));
+
+    let witnesses = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (5..2_000)
+            .step_by(2)
+            .map(|n| check_random_witnesses(n, 1, &mut rng))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(witnesses(SEED), witnesses(SEED));
+}
+
+#[test]
+fn test_pick_random_small_range() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    // 11 is the only safe prime in the range with a primitive root of 2
+    for _ in 0..10 {
+        assert_eq!(
+            pick_random_with_root(10, 12, DEFAULT_WITNESS_COUNT, &mut rng).unwrap(),
+            (11, 2)
+        );
+    }
+
+    assert!(matches!(
+        pick_random_with_root(12, 10, DEFAULT_WITNESS_COUNT, &mut rng),
+        Err(PrimeError::InvalidRange)
+    ));
+}
+
+#[test]
+fn test_pick_random_with_root_threaded() {
+    const THREADS: usize = 4;
+    let (min, max) = (1 << 31, (1 << 32) - 1);
+    let pick = |seed: u64, candidate_count: usize, thread_count: usize| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        pick_random_with_root_threaded(
+            min,
+            max,
+            DEFAULT_WITNESS_COUNT,
+            candidate_count,
+            thread_count,
+            &mut rng,
+        )
+    };
+
+    for seed in 0..10 {
+        let (prime, root) = pick(seed, 1 << 16, THREADS).unwrap();
+        assert_eq!(root, 2);
+        assert!((min..=max).contains(&prime));
+        assert!(is_prime_naive(prime) && is_prime_naive((prime - 1) / 2));
+
+        // the result only depends on the seed, not on which thread finishes first
+        for _ in 0..5 {
+            assert_eq!(pick(seed, 1 << 16, THREADS).unwrap(), (prime, root));
+        }
+    }
+
+    // a single thread is the same as the bounded search
+    let mut rng = StdRng::seed_from_u64(0);
+    assert_eq!(
+        pick(0, 1 << 16, 1).unwrap(),
+        pick_random_with_root_bounded(min, max, DEFAULT_WITNESS_COUNT, 1 << 16, &mut rng).unwrap()
+    );
+
+    assert!(matches!(
+        pick(0, 0, THREADS),
+        Err(PrimeError::PrimeNotFound)
+    ));
+    let mut rng = StdRng::seed_from_u64(0);
+    assert!(matches!(
+        pick_random_with_root_threaded(10, 1, DEFAULT_WITNESS_COUNT, 1, THREADS, &mut rng),
+        Err(PrimeError::InvalidRange)
+    ));
+}
+
+#[test]
+fn test_pick_random_with_root_bounded() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    // the bounded search gives up even though the range is known to contain a safe prime
+    assert!(matches!(
+        pick_random_with_root_bounded(10, 12, DEFAULT_WITNESS_COUNT, 0, &mut rng),
+        Err(PrimeError::PrimeNotFound)
+    ));
+    assert!(matches!(
+        pick_random_with_root_bounded(12, 10, DEFAULT_WITNESS_COUNT, 100, &mut rng),
+        Err(PrimeError::InvalidRange)
+    ));
+    assert_eq!(
+        pick_random_with_root_bounded(10, 12, DEFAULT_WITNESS_COUNT, 100, &mut rng).unwrap(),
+        (11, 2)
+    );
+
+    let (min, max) = (1 << 31, (1 << 32) - 1);
+    let (prime, _) =
+        pick_random_with_root_bounded(min, max, DEFAULT_WITNESS_COUNT, 1 << 16, &mut rng).unwrap();
+    assert!((min..=max).contains(&prime));
+    assert!(is_prime_naive(prime) && is_prime_naive((prime - 1) / 2));
+}
+
+#[test]
+fn test_range_contains_known_prime() {
+    assert!(range_contains_known_prime(0, 2));
+    assert!(range_contains_known_prime(3, 6));
+    assert!(range_contains_known_prime(25, 30));
+    assert!(range_contains_known_prime(100_000, 100_100));
+    assert!(range_contains_known_prime(u32::MAX as Num, Num::MAX));
+
+    // no known prime, although a prime may still exist
+    assert!(!range_contains_known_prime(0, 1));
+    assert!(!range_contains_known_prime(10, 9));
+    assert!(!range_contains_known_prime(24, 28));
+    assert!(!range_contains_known_prime(3, 5));
+    assert!(!range_contains_known_prime(100_000, 100_010));
+
+    // whenever a prime is known to exist, the sieve must find one
+    for min in 3..200 {
+        for max in min..400 {
+            if range_contains_known_prime(min, max) {
+                assert!(primes_in_range(min, max).next().is_some());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_log_integral() {
+    let check = |x: f64, expected: f64| {
+        let result = log_integral(x);
+        assert!(
+            (result - expected).abs() < expected * 1e-9,
+            "li({}) = {}, expected {}",
+            x,
+            result,
+            expected
+        );
+    };
+
+    check(2.0, 1.045_163_780_117_492_7);
+    check(10.0, 6.165_599_504_787_297);
+    check(1000.0, 177.609_657_990_152);
+    check(1e6, 78_627.549_159_462_18);
+    check(1e18, 24_739_954_309_690_415.0);
+}
+
+#[test]
+fn test_estimated_prime_count() {
+    assert_eq!(estimated_prime_count(0, 1), 0.0);
+    assert_eq!(estimated_prime_count(10, 9), 0.0);
+
+    for &(min, max) in &[(0, 1_000_000), (1_000_000_000, 1_010_000_000)] {
+        let actual = primes_in_range(min, max).count() as f64;
+        let estimate = estimated_prime_count(min, max);
+        assert!(
+            (estimate - actual).abs() < actual * 0.01,
+            "estimated {} primes in [{}, {}], but found {}",
+            estimate,
+            min,
+            max,
+            actual
+        );
     }
 }
@@ -51,6 +51,7 @@ fn check(base: Num, exp: Num, modulus: Num, expected: Num) {
  * Each argument in `args` is optional. Any missing argument is taken as a wildcard
  * for the set of values in `SAFE_VALS`.
  */
+#[allow(clippy::redundant_pattern_matching)]
 fn check_wildcard(mut args: [Option<Num>; 3], expected: Num) {
     for i in 0..args.len() {
         if let None = args[i] {
@@ -88,3 +89,110 @@ fn test_mod_exp() {
     check(Num::MAX, Num::MAX, u32::MAX as Num, 0);
     check(Num::MAX, Num::MAX, Num::MAX, 0);
 }
+
+#[test]
+fn test_mod_exp_ct() {
+    let values = [
+        0,
+        1,
+        2,
+        3,
+        16,
+        23,
+        391,
+        65537,
+        u32::MAX as Num,
+        Num::MAX - 1,
+        Num::MAX,
+    ];
+
+    for &base in &values {
+        for &exp in &values {
+            for &modulus in &values[1..] {
+                if base != 0 || exp != 0 {
+                    assert_eq!(mod_exp_ct(base, exp, modulus), mod_exp(base, exp, modulus));
+                }
+            }
+        }
+    }
+
+    // 0^0 is taken to be 1, unlike `mod_exp`, which rejects it
+    assert_eq!(mod_exp_ct(0, 0, 7), 1);
+}
+
+#[test]
+fn test_isqrt() {
+    assert_eq!(isqrt(0), 0);
+    assert_eq!(isqrt(1), 1);
+    assert_eq!(isqrt(15), 3);
+    assert_eq!(isqrt(16), 4);
+    assert_eq!(isqrt(u32::MAX as Num), 65535);
+    assert_eq!(isqrt(u64::MAX as Num), u32::MAX as Num);
+    assert_eq!(isqrt(Num::MAX), (1 << (BITS_PER_NUM / 2)) - 1);
+    assert_eq!(isqrt((1 << 52) + 1), 1 << 26);
+}
+
+#[test]
+fn test_sqrt_mod() {
+    // primes whose `p - 1` has varying powers of two, which take different paths through the loop
+    let primes: [Num; 6] = [7, 13, 17, 41, 18446744073709551557, 18446744069414584321];
+    for &prime in &primes {
+        for n in (0..50).chain(prime.saturating_sub(50)..prime) {
+            let is_residue = n % prime == 0 || mod_exp(n, (prime - 1) / 2, prime) == 1;
+            match sqrt_mod(n, prime) {
+                Some(root) => {
+                    assert!(is_residue);
+                    assert_eq!(mul_mod(root, root, prime), n % prime);
+                }
+                None => assert!(!is_residue),
+            }
+        }
+    }
+
+    assert_eq!(sqrt_mod(0, 7), Some(0));
+    assert_eq!(sqrt_mod(3, 7), None);
+    assert_eq!(sqrt_mod(16 + 17, 17).map(|r| r.min(17 - r)), Some(4));
+}
+
+#[test]
+fn test_gcd() {
+    assert_eq!(gcd(0, 0), 0);
+    assert_eq!(gcd(0, 7), 7);
+    assert_eq!(gcd(7, 0), 7);
+    assert_eq!(gcd(12, 18), 6);
+    assert_eq!(gcd(17, 5), 1);
+    assert_eq!(gcd(Num::MAX, Num::MAX - 1), 1);
+}
+
+#[test]
+fn test_ext_gcd() {
+    let check_ext_gcd = |a: Num, modulus: Num, expected_gcd: Num| {
+        let (g, x) = ext_gcd(a, modulus);
+        assert_eq!(g, expected_gcd);
+        assert!(x < modulus);
+        assert_eq!(mul_mod(a, x, modulus), g % modulus);
+    };
+
+    check_ext_gcd(3, 11, 1);
+    check_ext_gcd(10, 17, 1);
+    check_ext_gcd(12, 18, 6);
+    check_ext_gcd(0, 7, 7);
+    check_ext_gcd(7, 1, 1);
+    check_ext_gcd(Num::MAX - 1, Num::MAX, 1);
+    check_ext_gcd(18446744073709551556, 18446744073709551557, 1);
+
+    assert_eq!(ext_gcd(3, 11), (1, 4));
+}
+
+#[test]
+fn test_mul_mod() {
+    assert_eq!(mul_mod(0, 5, 7), 0);
+    assert_eq!(mul_mod(3, 5, 7), 1);
+    assert_eq!(mul_mod(Num::MAX, Num::MAX, 1), 0);
+    assert_eq!(mul_mod(Num::MAX - 1, Num::MAX - 1, Num::MAX), 1);
+    assert_eq!(mul_mod(Num::MAX, 2, Num::MAX - 1), 2);
+    assert_eq!(
+        mul_mod(u64::MAX as Num, u64::MAX as Num, 18446744073709551557),
+        3364
+    );
+}
@@ -0,0 +1,89 @@
+/*!
+ * Compare the two ways `decrypt_block` could cancel out `c1^x`: exponentiating by the
+ * precomputed `p - x - 1`, which gives `c1^-x` directly, or computing `c1^x` and inverting it with
+ * `ext_gcd`. Run with `cargo bench -p pubcrypt-core` (add `--features wide` for 128-bit keys).
+ */
+
+use math::{ext_gcd, mod_exp, mul_mod, Num};
+use pubcrypt_core::KeyPair;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{hint::black_box, time::Instant};
+
+const KEYS: usize = 8;
+const BLOCKS_PER_KEY: usize = 4096;
+const ROUNDS: usize = 5;
+
+/**
+ * A ciphertext pair for the group element `message`, along with the group and private exponent
+ * that decrypt it.
+ */
+struct Case {
+    prime: Num,
+    exponent: Num,
+    c1: Num,
+    c2: Num,
+    message: Num,
+}
+
+fn by_exponent(case: &Case) -> Num {
+    let c1_term = mod_exp(case.c1, case.prime - case.exponent - 1, case.prime);
+    mul_mod(c1_term, case.c2, case.prime)
+}
+
+fn by_inverse(case: &Case) -> Num {
+    let (_, c1_term) = ext_gcd(mod_exp(case.c1, case.exponent, case.prime), case.prime);
+    mul_mod(c1_term, case.c2, case.prime)
+}
+
+/**
+ * Time `decrypt` over every case, returning the best per-block time of several rounds in
+ * nanoseconds.
+ */
+fn time(cases: &[Case], decrypt: fn(&Case) -> Num) -> f64 {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            for case in cases {
+                black_box(decrypt(black_box(case)));
+            }
+            start.elapsed().as_nanos() as f64 / cases.len() as f64
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut cases = Vec::with_capacity(KEYS * BLOCKS_PER_KEY);
+
+    for _ in 0..KEYS {
+        let keys = KeyPair::generate(&mut rng).unwrap();
+        let (prime, root) = (keys.public.key().prime(), keys.public.key().root());
+        let exponent = rng.gen_range(1..prime - 1);
+        let value = mod_exp(root, exponent, prime);
+
+        for _ in 0..BLOCKS_PER_KEY {
+            let r = rng.gen_range(1..prime - 1);
+            let message = rng.gen_range(1..prime);
+            let c1 = mod_exp(root, r, prime);
+            let c2 = mul_mod(mod_exp(value, r, prime), message, prime);
+            cases.push(Case {
+                prime,
+                exponent,
+                c1,
+                c2,
+                message,
+            });
+        }
+    }
+
+    for case in &cases {
+        assert_eq!(by_exponent(case), case.message);
+        assert_eq!(by_inverse(case), case.message);
+    }
+
+    let exponent = time(&cases, by_exponent);
+    let inverse = time(&cases, by_inverse);
+    println!("c1^(p - x - 1):      {:8.1} ns/block", exponent);
+    println!("inverse of c1^x:     {:8.1} ns/block", inverse);
+    println!("inverse / exponent:  {:8.2}", inverse / exponent);
+}
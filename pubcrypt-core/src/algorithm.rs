@@ -0,0 +1,68 @@
+use std::fmt;
+
+/**
+ * A public-key algorithm, recorded in key files and ciphertext headers so that keys and
+ * ciphertexts for different algorithms can be told apart. See `cipher::PublicKeyCipher`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Algorithm {
+    /**
+     * ElGamal over the multiplicative group of integers modulo a safe prime, as implemented by
+     * the rest of this crate.
+     */
+    ElGamal,
+    /**
+     * RSA, as implemented by the `rsa` module.
+     */
+    Rsa,
+    /**
+     * ElGamal over an elliptic curve, as implemented by the `ec` module.
+     */
+    EcElGamal,
+}
+
+impl Algorithm {
+    /**
+     * Get the identifier used for the algorithm in key files and ciphertext headers.
+     */
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Self::ElGamal => 0,
+            Self::Rsa => 1,
+            Self::EcElGamal => 2,
+        }
+    }
+
+    /**
+     * Get the algorithm with the given identifier, if any.
+     */
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::ElGamal),
+            1 => Some(Self::Rsa),
+            2 => Some(Self::EcElGamal),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ElGamal => write!(f, "ElGamal"),
+            Self::Rsa => write!(f, "RSA"),
+            Self::EcElGamal => write!(f, "EC-ElGamal"),
+        }
+    }
+}
+
+impl Default for Algorithm {
+    /**
+     * Get `Algorithm::ElGamal`, which keys and ciphertexts that don't record an algorithm use.
+     */
+    fn default() -> Self {
+        Self::ElGamal
+    }
+}
@@ -0,0 +1,586 @@
+/*!
+ * An archive holding a directory tree, which is encrypted in place of bare contents when the
+ * header's `archive` flag is set, so that decryption can recreate the tree. It's much like a
+ * minimal tar file, but with a digest of each file's contents.
+ *
+ * An archive consists of the magic bytes `PUBCRARC` and the version byte `VERSION`, followed by
+ * one entry for each directory and file, and finally a zero byte. Each entry is its kind byte (1
+ * for a directory, 2 for a file), the big-endian two-byte length of its path, the path in UTF-8
+ * with `/` between its components, and the big-endian eight-byte modification time (in seconds
+ * since the Unix epoch). Files continue with the big-endian eight-byte size, the contents, and the
+ * SHA-256 digest of the contents.
+ *
+ * Paths start with the name of the archived directory, and every directory comes before the
+ * entries inside it, so `unpack` only ever writes inside directories it has just created.
+ *
+ * The paths are part of the plaintext, so like the contents, they're only visible once the
+ * archive is decrypted; the header doesn't list them. Entries can be chosen for extraction by
+ * path or by an `obfuscated_name`, which hides the path from whoever does the choosing.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    container,
+    hash::{Sha256, DIGEST_BYTES},
+    hex,
+    mac::Hmac,
+    CryptError, Fingerprint,
+};
+use std::{
+    cmp,
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+/**
+ * The bytes that every archive starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRARC";
+
+/**
+ * The version of the archive format written by `Archiver`.
+ */
+pub const VERSION: u8 = 1;
+
+/**
+ * The maximum number of bytes in an entry's path.
+ */
+pub const MAX_PATH_BYTES: usize = u16::MAX as usize;
+
+/**
+ * The number of bytes of the HMAC in an obfuscated name, which has twice as many hex digits.
+ */
+pub const OBFUSCATED_NAME_BYTES: usize = 16;
+
+const END_KIND: u8 = 0;
+const DIRECTORY_KIND: u8 = 1;
+const FILE_KIND: u8 = 2;
+const LEN_BYTES: usize = 2;
+const SIZE_BYTES: usize = 8;
+const TIME_BYTES: usize = 8;
+const COPY_BUF_BYTES: usize = 8192;
+
+/**
+ * The kind of an entry in an archive.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    Directory,
+    File,
+}
+
+/**
+ * A directory or file in an archive.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /**
+     * The entry's path, starting with the name of the archived directory, with `/` between its
+     * components.
+     */
+    pub path: String,
+    pub kind: EntryKind,
+    /**
+     * The number of bytes in the file, or 0 for a directory.
+     */
+    pub size: u64,
+    /**
+     * The entry's modification time in seconds since the Unix epoch.
+     */
+    pub modified: u64,
+}
+
+impl Entry {
+    /**
+     * Get the bytes of the entry before the contents.
+     */
+    fn to_bytes(&self) -> Vec<u8> {
+        let kind = match self.kind {
+            EntryKind::Directory => DIRECTORY_KIND,
+            EntryKind::File => FILE_KIND,
+        };
+
+        let mut result = vec![kind];
+        result.extend_from_slice(&(self.path.len() as u16).to_be_bytes());
+        result.extend_from_slice(self.path.as_bytes());
+        result.extend_from_slice(&self.modified.to_be_bytes());
+        if self.kind == EntryKind::File {
+            result.extend_from_slice(&self.size.to_be_bytes());
+        }
+        result
+    }
+}
+
+/**
+ * A reader that archives the directory tree under a root directory.
+ */
+pub struct Archiver {
+    // the directory containing the root, which the entries' paths are relative to
+    base: PathBuf,
+    entries: Vec<Entry>,
+    next_entry: usize,
+    // the bytes to give out before the next contents, and how many of them have been
+    pending: Vec<u8>,
+    pending_pos: usize,
+    current: Option<(File, u64, Sha256)>,
+    finished: bool,
+}
+
+impl Archiver {
+    /**
+     * Create an archiver for the tree under the directory `root`, which is walked immediately.
+     * Files are only opened as they're read.
+     *
+     * Entries whose name or path below `root` matches any of the patterns in `exclude` (see
+     * `matches_pattern`) are left out, along with everything inside excluded directories.
+     * Symbolic links and special files are skipped, and the entries in each directory are
+     * archived in order of name.
+     *
+     * Returns an error of kind `io::ErrorKind::InvalidInput` if `root` isn't a directory with a
+     * name, or `io::ErrorKind::InvalidData` if a name isn't valid UTF-8 or a path is longer than
+     * `MAX_PATH_BYTES`.
+     */
+    pub fn new<S: AsRef<str>>(root: &Path, exclude: &[S]) -> io::Result<Self> {
+        let root = fs::canonicalize(root)?;
+        let name = match root.file_name() {
+            Some(name) if root.is_dir() => utf8_name(name)?,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the path isn't a named directory",
+                ))
+            }
+        };
+
+        let mut entries = Vec::new();
+        let exclude: Vec<&str> = exclude.iter().map(AsRef::as_ref).collect();
+        let entry = entry_for(&root, name.clone(), EntryKind::Directory, 0)?;
+        entries.push(entry);
+        walk(&root, &name, "", &exclude, &mut entries)?;
+
+        let mut pending = MAGIC.to_vec();
+        pending.push(VERSION);
+
+        Ok(Self {
+            base: root.parent().map_or_else(PathBuf::new, Path::to_path_buf),
+            entries,
+            next_entry: 0,
+            pending,
+            pending_pos: 0,
+            current: None,
+            finished: false,
+        })
+    }
+
+    /**
+     * Get the entries that are archived, in the order they're written.
+     */
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /**
+     * Replace the pending bytes with `bytes`.
+     */
+    fn set_pending(&mut self, bytes: Vec<u8>) {
+        self.pending = bytes;
+        self.pending_pos = 0;
+    }
+}
+
+impl Read for Archiver {
+    /**
+     * Read the next bytes of the archive. Returns an error of kind
+     * `io::ErrorKind::UnexpectedEof` if a file is shorter than when the tree was walked, and
+     * `io::ErrorKind::InvalidData` if it's longer, which happens when a file changes while it's
+     * being archived.
+     */
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.pending_pos < self.pending.len() {
+                let count = cmp::min(buf.len(), self.pending.len() - self.pending_pos);
+                let pending = &self.pending[self.pending_pos..self.pending_pos + count];
+                buf[..count].copy_from_slice(pending);
+                self.pending_pos += count;
+                return Ok(count);
+            }
+
+            if let Some((file, remaining, hasher)) = &mut self.current {
+                if *remaining > 0 {
+                    let len = cmp::min(buf.len() as u64, *remaining) as usize;
+                    let count = file.read(&mut buf[..len])?;
+                    if count == 0 && len > 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "a file is shorter than its recorded size",
+                        ));
+                    }
+
+                    hasher.update(&buf[..count]);
+                    *remaining -= count as u64;
+                    return Ok(count);
+                }
+
+                if file.read(&mut [0_u8; 1])? > 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "a file is longer than its recorded size",
+                    ));
+                }
+
+                // every byte of the contents has been read, so the digest comes next
+                let (_, _, hasher) = self.current.take().unwrap();
+                self.set_pending(hasher.finalize().to_vec());
+            } else if let Some(entry) = self.entries.get(self.next_entry) {
+                self.next_entry += 1;
+                let bytes = entry.to_bytes();
+                if entry.kind == EntryKind::File {
+                    let file = File::open(self.base.join(&entry.path))?;
+                    self.current = Some((file, entry.size, Sha256::new()));
+                }
+                self.set_pending(bytes);
+            } else if !self.finished {
+                self.finished = true;
+                self.set_pending(vec![END_KIND]);
+            } else {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+/**
+ * Add the entries inside the directory `dir` to `entries`, recursively. `path` is the archive
+ * path of `dir`, and `relative` is its path below the root, which is empty for the root itself.
+ */
+fn walk(
+    dir: &Path,
+    path: &str,
+    relative: &str,
+    exclude: &[&str],
+    entries: &mut Vec<Entry>,
+) -> io::Result<()> {
+    let mut children = fs::read_dir(dir)?
+        .map(|child| child.map(|child| child.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    children.sort();
+
+    for child in children {
+        let name = utf8_name(child.file_name().unwrap_or_default())?;
+        let child_path = format!("{}/{}", path, name);
+        let child_relative = if relative.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", relative, name)
+        };
+
+        let is_excluded = exclude.iter().any(|pattern| {
+            matches_pattern(pattern, &name) || matches_pattern(pattern, &child_relative)
+        });
+        if is_excluded {
+            continue;
+        }
+
+        let metadata = fs::symlink_metadata(&child)?;
+        if metadata.is_dir() {
+            entries.push(entry_for(
+                &child,
+                child_path.clone(),
+                EntryKind::Directory,
+                0,
+            )?);
+            walk(&child, &child_path, &child_relative, exclude, entries)?;
+        } else if metadata.is_file() {
+            let size = metadata.len();
+            entries.push(entry_for(&child, child_path, EntryKind::File, size)?);
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * List the files under the directory `root` by their paths below it, with `/` between their
+ * components, for handling each file on its own. The tree is walked as by `Archiver::new`, so the
+ * same `exclude` patterns apply, and symbolic links and special files are skipped.
+ */
+pub fn list_files<S: AsRef<str>>(root: &Path, exclude: &[S]) -> io::Result<Vec<String>> {
+    let exclude: Vec<&str> = exclude.iter().map(AsRef::as_ref).collect();
+    let mut entries = Vec::new();
+    // the root's archive path is empty, so every entry's path starts with `/`
+    walk(root, "", "", &exclude, &mut entries)?;
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.kind == EntryKind::File)
+        .map(|entry| entry.path[1..].to_string())
+        .collect())
+}
+
+/**
+ * Create the entry for the directory or file at `fs_path`, with the archive path `path`.
+ */
+fn entry_for(fs_path: &Path, path: String, kind: EntryKind, size: u64) -> io::Result<Entry> {
+    if path.len() > MAX_PATH_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "a path is too long to archive",
+        ));
+    }
+
+    // entries modified before the epoch are recorded as modified at it
+    let modified = fs::symlink_metadata(fs_path)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    Ok(Entry {
+        path,
+        kind,
+        size,
+        modified,
+    })
+}
+
+/**
+ * Convert a file name to a `String`, failing if it isn't valid UTF-8.
+ */
+fn utf8_name(name: &std::ffi::OsStr) -> io::Result<String> {
+    name.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "a file name isn't valid UTF-8"))
+}
+
+/**
+ * Check whether `text` matches the shell-style `pattern`, where `*` matches any run of
+ * characters and `?` matches any one character, but neither matches `/`. Every other character
+ * matches only itself.
+ */
+pub fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // the position of the last `*` and of the text it was tried against, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == text[t] || (c == '?' && text[t] != '/') => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // let the last `*` match one more character, unless it would be a `/`
+                Some((star_p, star_t)) if text[star_t] != '/' => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                _ => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/**
+ * Get the obfuscated name of the entry at `path` in an archive encrypted for the public key with
+ * the fingerprint `recipient`: the hexadecimal HMAC-SHA-256 of the path, keyed by the
+ * fingerprint and truncated to `OBFUSCATED_NAME_BYTES`. The same path and key always give the same
+ * name, so a list of names can be handed out to choose entries for `unpack_selected` without
+ * revealing the paths in it.
+ *
+ * The names only hide paths that can't be guessed, since anyone with the public key can compute
+ * the name of any path they think of.
+ */
+pub fn obfuscated_name(recipient: &Fingerprint, path: &str) -> String {
+    let mut mac = Hmac::new(recipient.as_bytes());
+    mac.update(b"pubcrypt archive name");
+    mac.update(path.as_bytes());
+    hex::encode(&mac.finalize()[..OBFUSCATED_NAME_BYTES])
+}
+
+/**
+ * Recreate the tree in the archive read from `reader` inside the directory `dir`, returning the
+ * entries that were created. Files get their recorded modification times, and nothing that
+ * already exists is ever replaced.
+ *
+ * Returns `CryptError::InvalidArchive` if the archive doesn't start with `MAGIC`, is from another
+ * version, is truncated, has anything after its end, or has an entry whose path isn't inside a
+ * directory created earlier in the archive, and `CryptError::DigestMismatch` if a file doesn't
+ * match its digest. Entries before the offending one have already been created.
+ */
+pub fn unpack<R: Read>(reader: &mut R, dir: &Path) -> Result<Vec<Entry>, CryptError> {
+    unpack_selected(reader, dir, |_| true)
+}
+
+/**
+ * Recreate the entries in the archive read from `reader` that `select` accepts inside the
+ * directory `dir`, as in `unpack`, along with everything inside the selected directories. The
+ * directories containing a selected entry are created too, but nothing else inside them.
+ *
+ * Every entry is read and checked, whether it's selected or not, and errors are as in `unpack`.
+ */
+pub fn unpack_selected<R, F>(
+    reader: &mut R,
+    dir: &Path,
+    select: F,
+) -> Result<Vec<Entry>, CryptError>
+where
+    R: Read,
+    F: Fn(&Entry) -> bool,
+{
+    let mut magic = [0_u8; MAGIC.len() + 1];
+    read_archive_bytes(reader, &mut magic)?;
+    if magic[..MAGIC.len()] != MAGIC || magic[MAGIC.len()] != VERSION {
+        return Err(CryptError::InvalidArchive);
+    }
+
+    // every directory in the archive so far, and those that were selected or created
+    let mut directories = HashSet::new();
+    let mut selected_dirs = HashSet::new();
+    let mut created_dirs = HashSet::new();
+    let mut entries = Vec::new();
+    let mut is_empty = true;
+    loop {
+        let mut kind = [0_u8; 1];
+        read_archive_bytes(reader, &mut kind)?;
+        let kind = match kind[0] {
+            END_KIND => break,
+            DIRECTORY_KIND => EntryKind::Directory,
+            FILE_KIND => EntryKind::File,
+            _ => return Err(CryptError::InvalidArchive),
+        };
+
+        let mut len = [0_u8; LEN_BYTES];
+        read_archive_bytes(reader, &mut len)?;
+        let mut path = vec![0_u8; u16::from_be_bytes(len) as usize];
+        read_archive_bytes(reader, &mut path)?;
+        let path = String::from_utf8(path).map_err(|_| CryptError::InvalidArchive)?;
+
+        // only the root can be outside a directory, and it has to come first
+        let parent = path.rsplit_once('/').map(|(parent, _)| parent.to_string());
+        let is_valid = path.split('/').all(container::is_plain_name)
+            && match &parent {
+                Some(parent) => directories.contains(parent),
+                None => is_empty && kind == EntryKind::Directory,
+            };
+        if !is_valid {
+            return Err(CryptError::InvalidArchive);
+        }
+        is_empty = false;
+
+        let mut modified = [0_u8; TIME_BYTES];
+        read_archive_bytes(reader, &mut modified)?;
+        let mut entry = Entry {
+            path,
+            kind,
+            size: 0,
+            modified: u64::from_be_bytes(modified),
+        };
+        if kind == EntryKind::File {
+            let mut size = [0_u8; SIZE_BYTES];
+            read_archive_bytes(reader, &mut size)?;
+            entry.size = u64::from_be_bytes(size);
+        }
+
+        if kind == EntryKind::Directory {
+            directories.insert(entry.path.clone());
+        }
+
+        let is_selected =
+            parent.is_some_and(|parent| selected_dirs.contains(&parent)) || select(&entry);
+        if !is_selected {
+            if kind == EntryKind::File {
+                copy_contents(reader, &mut io::sink(), entry.size)?;
+            }
+            continue;
+        }
+
+        // the directories containing the entry are only created once something in them is
+        let mut ancestor_end = 0;
+        while let Some(len) = entry.path[ancestor_end..].find('/') {
+            ancestor_end += len;
+            let ancestor = &entry.path[..ancestor_end];
+            if created_dirs.insert(ancestor.to_string()) {
+                fs::create_dir(dir.join(ancestor))?;
+            }
+            ancestor_end += 1;
+        }
+
+        let fs_path = dir.join(&entry.path);
+        match kind {
+            EntryKind::Directory => {
+                fs::create_dir(&fs_path)?;
+                created_dirs.insert(entry.path.clone());
+                selected_dirs.insert(entry.path.clone());
+            }
+            EntryKind::File => {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&fs_path)?;
+                let mut writer = BufWriter::new(file);
+                copy_contents(reader, &mut writer, entry.size)?;
+                let file = writer.into_inner().map_err(|e| e.into_error())?;
+                file.set_modified(UNIX_EPOCH + Duration::from_secs(entry.modified))?;
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    if is_empty || reader.read(&mut [0_u8; 1])? > 0 {
+        return Err(CryptError::InvalidArchive);
+    }
+
+    Ok(entries)
+}
+
+/**
+ * Copy `size` bytes of a file's contents from `reader` to `writer`, and check them against the
+ * digest that follows.
+ */
+fn copy_contents<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    size: u64,
+) -> Result<(), CryptError> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0_u8; COPY_BUF_BYTES];
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = cmp::min(buf.len() as u64, remaining) as usize;
+        read_archive_bytes(reader, &mut buf[..len])?;
+        hasher.update(&buf[..len]);
+        writer.write_all(&buf[..len])?;
+        remaining -= len as u64;
+    }
+
+    let mut digest = [0_u8; DIGEST_BYTES];
+    read_archive_bytes(reader, &mut digest)?;
+    if hasher.finalize() != digest {
+        return Err(CryptError::DigestMismatch);
+    }
+
+    Ok(())
+}
+
+/**
+ * Fill `buf` from `reader`, returning `CryptError::InvalidArchive` if the archive ends first.
+ */
+fn read_archive_bytes<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), CryptError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => CryptError::InvalidArchive,
+        _ => CryptError::from(e),
+    })
+}
@@ -0,0 +1,266 @@
+use super::*;
+
+/**
+ * Create an empty scratch directory for the test named `name`.
+ */
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pubcrypt-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/**
+ * Create a small tree under `dir/tree`, returning its root.
+ */
+fn make_tree(dir: &Path) -> PathBuf {
+    let root = dir.join("tree");
+    fs::create_dir_all(root.join("sub/deeper")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("a.txt"), b"alpha").unwrap();
+    fs::write(root.join("notes.log"), b"log").unwrap();
+    fs::write(root.join("sub/b.bin"), vec![7_u8; COPY_BUF_BYTES * 2 + 3]).unwrap();
+    fs::write(root.join("sub/deeper/empty"), b"").unwrap();
+    fs::write(root.join("target/c.o"), b"object").unwrap();
+    root
+}
+
+fn archive(root: &Path, exclude: &[&str]) -> Vec<u8> {
+    let mut archived = Vec::new();
+    Archiver::new(root, exclude)
+        .unwrap()
+        .read_to_end(&mut archived)
+        .unwrap();
+    archived
+}
+
+#[test]
+fn test_round_trip() {
+    let dir = scratch_dir("archive-round-trip");
+    let root = make_tree(&dir);
+    let archived = archive(&root, &[] as &[&str]);
+    assert!(archived.starts_with(&MAGIC));
+
+    let out = dir.join("out");
+    fs::create_dir(&out).unwrap();
+    let entries = unpack(&mut &archived[..], &out).unwrap();
+    let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "tree",
+            "tree/a.txt",
+            "tree/notes.log",
+            "tree/sub",
+            "tree/sub/b.bin",
+            "tree/sub/deeper",
+            "tree/sub/deeper/empty",
+            "tree/target",
+            "tree/target/c.o",
+        ]
+    );
+
+    for entry in entries.iter().filter(|entry| entry.kind == EntryKind::File) {
+        let original = fs::read(dir.join(&entry.path)).unwrap();
+        let restored = out.join(&entry.path);
+        assert_eq!(fs::read(&restored).unwrap(), original);
+        let modified = fs::metadata(&restored).unwrap().modified().unwrap();
+        assert_eq!(modified, UNIX_EPOCH + Duration::from_secs(entry.modified));
+    }
+
+    // nothing that exists is replaced
+    assert!(matches!(
+        unpack(&mut &archived[..], &out),
+        Err(CryptError::Io(_))
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_exclude() {
+    let dir = scratch_dir("archive-exclude");
+    let root = make_tree(&dir);
+    let archiver = Archiver::new(&root, &["*.log", "target", "sub/deeper"]).unwrap();
+    let paths: Vec<&str> = archiver
+        .entries()
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect();
+    assert_eq!(paths, ["tree", "tree/a.txt", "tree/sub", "tree/sub/b.bin"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_list_files() {
+    let dir = scratch_dir("archive-list-files");
+    let root = make_tree(&dir);
+    let files = list_files(&root, &[] as &[&str]).unwrap();
+    assert_eq!(
+        files,
+        [
+            "a.txt",
+            "notes.log",
+            "sub/b.bin",
+            "sub/deeper/empty",
+            "target/c.o"
+        ]
+    );
+
+    let files = list_files(&root, &["*.log", "target"]).unwrap();
+    assert_eq!(files, ["a.txt", "sub/b.bin", "sub/deeper/empty"]);
+    assert!(list_files(&root.join("a.txt"), &[] as &[&str]).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_matches_pattern() {
+    assert!(matches_pattern("*.log", "notes.log"));
+    assert!(matches_pattern("*", "anything"));
+    assert!(matches_pattern("a?c", "abc"));
+    assert!(matches_pattern("a*b*c", "aXbYbZc"));
+    assert!(matches_pattern("sub/*", "sub/file"));
+    assert!(matches_pattern("", ""));
+    assert!(!matches_pattern("*.log", "notes.txt"));
+    assert!(!matches_pattern("*", "sub/file"));
+    assert!(!matches_pattern("a?c", "a/c"));
+    assert!(!matches_pattern("abc", "abcd"));
+}
+
+#[test]
+fn test_unsafe_paths() {
+    let dir = scratch_dir("archive-unsafe");
+    let entry = |path: &str, kind| Entry {
+        path: path.to_string(),
+        kind,
+        size: 0,
+        modified: 0,
+    };
+    let cases = [
+        vec![entry("../escape", EntryKind::Directory)],
+        vec![entry("/abs", EntryKind::Directory)],
+        vec![entry("top", EntryKind::File)],
+        vec![
+            entry("a", EntryKind::Directory),
+            entry("b/c", EntryKind::Directory),
+        ],
+        vec![
+            entry("a", EntryKind::Directory),
+            entry("a/../c", EntryKind::Directory),
+        ],
+        vec![
+            entry("a", EntryKind::Directory),
+            entry("b", EntryKind::Directory),
+        ],
+    ];
+
+    for (i, entries) in cases.iter().enumerate() {
+        let out = dir.join(i.to_string());
+        fs::create_dir(&out).unwrap();
+
+        let mut archived = MAGIC.to_vec();
+        archived.push(VERSION);
+        for entry in entries {
+            archived.extend_from_slice(&entry.to_bytes());
+        }
+        archived.push(END_KIND);
+
+        assert!(
+            matches!(
+                unpack(&mut &archived[..], &out),
+                Err(CryptError::InvalidArchive)
+            ),
+            "{:?}",
+            entries
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_corrupt_archives() {
+    let dir = scratch_dir("archive-corrupt");
+    let root = make_tree(&dir);
+    let archived = archive(&root, &["sub", "target"]);
+
+    let check = |archived: &[u8], is_expected: fn(&CryptError) -> bool| {
+        let out = dir.join("out");
+        let _ = fs::remove_dir_all(&out);
+        fs::create_dir(&out).unwrap();
+        let error = unpack(&mut &archived[..], &out).unwrap_err();
+        assert!(is_expected(&error), "{:?}", error);
+    };
+
+    let truncated = &archived[..archived.len() - 1];
+    check(truncated, |e| matches!(e, CryptError::InvalidArchive));
+
+    let mut followed = archived.clone();
+    followed.push(0);
+    check(&followed, |e| matches!(e, CryptError::InvalidArchive));
+
+    // the first file's contents are "alpha"
+    let mut corrupt = archived.clone();
+    let start = archived.windows(5).position(|w| w == b"alpha").unwrap();
+    corrupt[start] ^= 1;
+    check(&corrupt, |e| matches!(e, CryptError::DigestMismatch));
+
+    let mut wrong_version = archived.clone();
+    wrong_version[MAGIC.len()] += 1;
+    check(&wrong_version, |e| matches!(e, CryptError::InvalidArchive));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_unpack_selected() {
+    let dir = scratch_dir("archive-selected");
+    let root = make_tree(&dir);
+    let archived = archive(&root, &[] as &[&str]);
+
+    let out = dir.join("out");
+    fs::create_dir(&out).unwrap();
+    let selected = ["tree/sub/deeper", "tree/a.txt"];
+    let entries = unpack_selected(&mut &archived[..], &out, |entry| {
+        selected.contains(&entry.path.as_str())
+    })
+    .unwrap();
+    let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        ["tree/a.txt", "tree/sub/deeper", "tree/sub/deeper/empty"]
+    );
+
+    // the containing directories are created, but nothing else in them
+    assert!(out.join("tree/sub/deeper/empty").is_file());
+    assert!(!out.join("tree/sub/b.bin").exists());
+    assert!(!out.join("tree/notes.log").exists());
+    assert!(!out.join("tree/target").exists());
+
+    // skipped files are still checked
+    let mut corrupt = archived.clone();
+    let start = archived.windows(5).position(|w| w == b"alpha").unwrap();
+    corrupt[start] ^= 1;
+    let out = dir.join("out2");
+    fs::create_dir(&out).unwrap();
+    assert!(matches!(
+        unpack_selected(&mut &corrupt[..], &out, |_| false),
+        Err(CryptError::DigestMismatch)
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_obfuscated_name() {
+    let recipient = Fingerprint::from([1; crate::FINGERPRINT_BYTES]);
+    let other = Fingerprint::from([2; crate::FINGERPRINT_BYTES]);
+    let name = obfuscated_name(&recipient, "tree/a.txt");
+    assert_eq!(name.len(), OBFUSCATED_NAME_BYTES * 2);
+    assert!(name.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(name, obfuscated_name(&recipient, "tree/a.txt"));
+    assert_ne!(name, obfuscated_name(&recipient, "tree/b.txt"));
+    assert_ne!(name, obfuscated_name(&other, "tree/a.txt"));
+}
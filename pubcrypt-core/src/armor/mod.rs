@@ -0,0 +1,315 @@
+/*!
+ * ASCII armor, which wraps binary keys and ciphertexts in base64 text that can be pasted into
+ * email or chat, in the style of OpenPGP.
+ *
+ * Armored text starts with a line like `-----BEGIN PUBCRYPT MESSAGE-----` naming its `ArmorKind`,
+ * followed by the base64 encoding of the data in lines of `LINE_CHARS` characters, a line with
+ * `=` and the base64 encoding of the data's CRC-24 checksum, and a matching `-----END ...-----`
+ * line. The checksum catches text that was mangled in transit before the data is used.
+ *
+ * `pem` writes the same text without the checksum line, as in the PEM format of RFC 7468, which
+ * other tools are more likely to understand. `dearmor` reads both, and checks the checksum
+ * whenever there is one.
+ *
+ * `pem_labeled` and `depem_labeled` do the same for PEM text with other tools' labels, such as
+ * OpenSSL's `DH PARAMETERS`, which aren't prefixed with `PUBCRYPT`. Header lines like the
+ * `Version: ...` lines of OpenPGP armor are skipped.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::ArmorError;
+
+/**
+ * The maximum number of base64 characters on each line of armored text.
+ */
+pub const LINE_CHARS: usize = 64;
+
+const BEGIN_PREFIX: &str = "-----BEGIN ";
+const END_PREFIX: &str = "-----END ";
+const LABEL_PREFIX: &str = "PUBCRYPT ";
+const DELIMITER_SUFFIX: &str = "-----";
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const CRC24_INIT: u32 = 0xb7_04ce;
+const CRC24_POLY: u32 = 0x186_4cfb;
+const CRC24_BYTES: usize = 3;
+
+/**
+ * The kind of data in armored text, which is named in its delimiter lines.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArmorKind {
+    /**
+     * A public key file.
+     */
+    PublicKey,
+    /**
+     * A private key file, which may be protected.
+     */
+    PrivateKey,
+    /**
+     * A key pair file, holding both a public and a private key file.
+     */
+    KeyPair,
+    /**
+     * A ciphertext.
+     */
+    Message,
+    /**
+     * A detached signature file.
+     */
+    Signature,
+}
+
+impl ArmorKind {
+    const ALL: [Self; 5] = [
+        Self::PublicKey,
+        Self::PrivateKey,
+        Self::KeyPair,
+        Self::Message,
+        Self::Signature,
+    ];
+
+    /**
+     * Get the label used in the delimiter lines for this kind, e.g., `PUBLIC KEY`.
+     */
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PublicKey => "PUBLIC KEY",
+            Self::PrivateKey => "PRIVATE KEY",
+            Self::KeyPair => "KEY PAIR",
+            Self::Message => "MESSAGE",
+            Self::Signature => "SIGNATURE",
+        }
+    }
+
+    /**
+     * Check whether this kind is a key of either kind, or a key pair.
+     */
+    pub fn is_key(self) -> bool {
+        matches!(self, Self::PublicKey | Self::PrivateKey | Self::KeyPair)
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.label() == label)
+    }
+}
+
+/**
+ * Wrap `bytes` in armor of the given kind. The result ends with a newline.
+ */
+pub fn armor(kind: ArmorKind, bytes: &[u8]) -> String {
+    wrap(&full_label(kind), bytes, true)
+}
+
+/**
+ * Wrap `bytes` in PEM text of the given kind, i.e., armor without the checksum line. The result
+ * ends with a newline.
+ */
+pub fn pem(kind: ArmorKind, bytes: &[u8]) -> String {
+    wrap(&full_label(kind), bytes, false)
+}
+
+/**
+ * Wrap `bytes` in PEM text with exactly the given label, e.g., `DH PARAMETERS`, for other tools
+ * to read. The result ends with a newline.
+ */
+pub fn pem_labeled(label: &str, bytes: &[u8]) -> String {
+    wrap(label, bytes, false)
+}
+
+/**
+ * Get the label in the delimiter lines of armor for `kind`, e.g., `PUBCRYPT PUBLIC KEY`.
+ */
+fn full_label(kind: ArmorKind) -> String {
+    format!("{}{}", LABEL_PREFIX, kind.label())
+}
+
+/**
+ * Wrap `bytes` as in `armor` with the full label `label`, leaving out the checksum line unless
+ * `checksum` is set.
+ */
+fn wrap(label: &str, bytes: &[u8], checksum: bool) -> String {
+    let encoded = encode_base64(bytes);
+    let mut result = format!("{}{}{}\n", BEGIN_PREFIX, label, DELIMITER_SUFFIX);
+    for line in encoded.as_bytes().chunks(LINE_CHARS) {
+        // the encoding is ASCII, so any chunk of it is valid UTF-8
+        result.push_str(std::str::from_utf8(line).unwrap());
+        result.push('\n');
+    }
+
+    if checksum {
+        let crc = crc24(bytes).to_be_bytes();
+        result.push('=');
+        result.push_str(&encode_base64(&crc[1..]));
+        result.push('\n');
+    }
+
+    result.push_str(&format!("{}{}{}\n", END_PREFIX, label, DELIMITER_SUFFIX));
+    result
+}
+
+/**
+ * Unwrap armored or PEM text, returning its kind and the data inside it. Whitespace around the
+ * text and around each line is ignored, as are carriage returns.
+ *
+ * Returns `ArmorError::InvalidDelimiters` if the text doesn't start and end with matching
+ * delimiter lines, `UnknownLabel` if the kind isn't known, `InvalidBase64` if the data or
+ * checksum isn't valid base64, and `ChecksumMismatch` if there's a checksum that doesn't match
+ * the data.
+ */
+pub fn dearmor(text: &[u8]) -> Result<(ArmorKind, Vec<u8>), ArmorError> {
+    let (label, bytes) = unwrap(text)?;
+    let kind = label
+        .strip_prefix(LABEL_PREFIX)
+        .and_then(ArmorKind::from_label)
+        .ok_or(ArmorError::UnknownLabel)?;
+    Ok((kind, bytes))
+}
+
+/**
+ * Unwrap PEM text written with `pem_labeled`, or by another tool, whose label must be exactly
+ * `label`. Errors are as in `dearmor`, with `ArmorError::UnknownLabel` for any other label.
+ */
+pub fn depem_labeled(label: &str, text: &[u8]) -> Result<Vec<u8>, ArmorError> {
+    match unwrap(text)? {
+        (actual, bytes) if actual == label => Ok(bytes),
+        _ => Err(ArmorError::UnknownLabel),
+    }
+}
+
+/**
+ * Unwrap armored or PEM text as in `dearmor`, returning its full label and data.
+ */
+fn unwrap(text: &[u8]) -> Result<(&str, Vec<u8>), ArmorError> {
+    let text = std::str::from_utf8(text).map_err(|_| ArmorError::InvalidBase64)?;
+    let mut lines = text.trim().lines().map(str::trim);
+
+    let label = lines
+        .next()
+        .and_then(|line| line.strip_prefix(BEGIN_PREFIX))
+        .and_then(|line| line.strip_suffix(DELIMITER_SUFFIX))
+        .ok_or(ArmorError::InvalidDelimiters)?;
+    let end_label = lines
+        .next_back()
+        .and_then(|line| line.strip_prefix(END_PREFIX))
+        .and_then(|line| line.strip_suffix(DELIMITER_SUFFIX));
+    if end_label != Some(label) {
+        return Err(ArmorError::InvalidDelimiters);
+    }
+
+    // OpenPGP armor may have headers like `Version: ...` before the data, and `:` isn't base64
+    let mut lines: Vec<&str> = lines.filter(|line| !line.contains(':')).collect();
+
+    // base64 lines never start with padding, so only a checksum line can start with `=`
+    let checksum = match lines.last().and_then(|line| line.strip_prefix('=')) {
+        Some(checksum) => {
+            let checksum = decode_base64(checksum)
+                .filter(|checksum| checksum.len() == CRC24_BYTES)
+                .ok_or(ArmorError::InvalidBase64)?;
+            lines.pop();
+            Some(checksum)
+        }
+        None => None,
+    };
+
+    let bytes = decode_base64(&lines.concat()).ok_or(ArmorError::InvalidBase64)?;
+    match checksum {
+        Some(checksum) if crc24(&bytes).to_be_bytes()[1..] != checksum[..] => {
+            Err(ArmorError::ChecksumMismatch)
+        }
+        _ => Ok((label, bytes)),
+    }
+}
+
+/**
+ * Check whether `bytes` looks like armored text, i.e., starts with a `BEGIN` delimiter line after
+ * any whitespace. The rest of the text is checked by `dearmor`.
+ */
+pub fn is_armored(bytes: &[u8]) -> bool {
+    let start = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let prefix = format!("{}{}", BEGIN_PREFIX, LABEL_PREFIX);
+    bytes[start..].starts_with(prefix.as_bytes())
+}
+
+/**
+ * Encode `bytes` in padded base64.
+ */
+pub(crate) fn encode_base64(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut buf = [0_u8; 3];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        let group = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]);
+
+        // n bytes fill n + 1 characters, and the rest of the group is padding
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3f;
+                result.push(ALPHABET[index as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+
+    result
+}
+
+/**
+ * Decode padded base64, returning `None` if it's invalid.
+ */
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(text.len() / 4 * 3);
+    for (i, chunk) in text.chunks(4).enumerate() {
+        let is_last = (i + 1) * 4 == text.len();
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return None;
+        }
+
+        let mut group = 0_u32;
+        for &c in &chunk[..4 - padding] {
+            let value = ALPHABET.iter().position(|&a| a == c)?;
+            group = group << 6 | value as u32;
+        }
+        group <<= 6 * padding;
+
+        let bytes = group.to_be_bytes();
+        let len = 3 - padding;
+        // the bits past the last byte must be zero, so each encoding is unique
+        if bytes[1 + len..].iter().any(|&b| b != 0) {
+            return None;
+        }
+        result.extend_from_slice(&bytes[1..1 + len]);
+    }
+
+    Some(result)
+}
+
+/**
+ * Compute the CRC-24 checksum of `bytes`, as in OpenPGP.
+ */
+fn crc24(bytes: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for &byte in bytes {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+
+    crc & 0xff_ffff
+}
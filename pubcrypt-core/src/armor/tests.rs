@@ -0,0 +1,127 @@
+use super::*;
+
+#[test]
+fn test_base64() {
+    let vectors: [(&[u8], &str); 7] = [
+        (b"", ""),
+        (b"f", "Zg=="),
+        (b"fo", "Zm8="),
+        (b"foo", "Zm9v"),
+        (b"foob", "Zm9vYg=="),
+        (b"fooba", "Zm9vYmE="),
+        (b"foobar", "Zm9vYmFy"),
+    ];
+    for (bytes, text) in vectors {
+        assert_eq!(encode_base64(bytes), text);
+        assert_eq!(decode_base64(text).as_deref(), Some(bytes));
+    }
+
+    let all_bytes: Vec<u8> = (0..=255).collect();
+    assert_eq!(decode_base64(&encode_base64(&all_bytes)), Some(all_bytes));
+
+    for invalid in ["Zg=", "Zg===", "Z===", "Zg==Zm8=", "Zh==", "Zm9*", "Zm 9"] {
+        assert_eq!(decode_base64(invalid), None, "{:?}", invalid);
+    }
+}
+
+#[test]
+fn test_crc24() {
+    assert_eq!(crc24(b""), CRC24_INIT);
+    assert_eq!(crc24(b"123456789"), 0x21_cf02);
+}
+
+#[test]
+fn test_armor_round_trip() {
+    for len in [0, 1, 2, 3, 47, 48, 49, 200] {
+        let bytes: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+        for kind in ArmorKind::ALL {
+            let text = armor(kind, &bytes);
+            assert!(is_armored(text.as_bytes()));
+            assert!(text
+                .lines()
+                .all(|line| line.len() <= LINE_CHARS || line.starts_with("-----")));
+            assert_eq!(dearmor(text.as_bytes()), Ok((kind, bytes.clone())));
+
+            // surrounding whitespace and CRLF line endings are tolerated
+            let pasted = format!("\n  {}\n", text.replace('\n', "\r\n"));
+            assert!(is_armored(pasted.as_bytes()));
+            assert_eq!(dearmor(pasted.as_bytes()), Ok((kind, bytes.clone())));
+        }
+    }
+
+    // PEM text is armor without the checksum line
+    let bytes = b"a key long enough to need more than one line of base64 text in the armor";
+    let text = pem(ArmorKind::PrivateKey, bytes);
+    let with_checksum = armor(ArmorKind::PrivateKey, bytes);
+    let lines: Vec<&str> = with_checksum.lines().collect();
+    assert_eq!(
+        text.lines().collect::<Vec<_>>(),
+        [&lines[..3], &lines[4..]].concat()
+    );
+    assert!(is_armored(text.as_bytes()));
+    assert_eq!(
+        dearmor(text.as_bytes()),
+        Ok((ArmorKind::PrivateKey, bytes.to_vec()))
+    );
+
+    let text = armor(ArmorKind::PublicKey, b"key");
+    assert!(text.starts_with("-----BEGIN PUBCRYPT PUBLIC KEY-----\n"));
+    assert!(text.ends_with("-----END PUBCRYPT PUBLIC KEY-----\n"));
+    assert!(!is_armored(b"PUBCRKMD"));
+}
+
+#[test]
+fn test_invalid_armor() {
+    let text = armor(
+        ArmorKind::Message,
+        b"a message long enough to span two lines of armor",
+    );
+
+    let mismatched = text.replace("END PUBCRYPT MESSAGE", "END PUBCRYPT PUBLIC KEY");
+    assert_eq!(
+        dearmor(mismatched.as_bytes()),
+        Err(ArmorError::InvalidDelimiters)
+    );
+    let truncated = &text[..text.len() / 2];
+    assert_eq!(
+        dearmor(truncated.as_bytes()),
+        Err(ArmorError::InvalidDelimiters)
+    );
+
+    let unknown = text.replace("MESSAGE", "SECRET");
+    assert_eq!(dearmor(unknown.as_bytes()), Err(ArmorError::UnknownLabel));
+
+    let invalid = text.replacen("YSBt", "YS*t", 1);
+    assert_eq!(dearmor(invalid.as_bytes()), Err(ArmorError::InvalidBase64));
+
+    // a changed character is caught by the checksum
+    let damaged = text.replacen("YSBt", "YSBu", 1);
+    assert_eq!(
+        dearmor(damaged.as_bytes()),
+        Err(ArmorError::ChecksumMismatch)
+    );
+}
+
+#[test]
+fn test_pem_labeled() {
+    let text = pem_labeled("DH PARAMETERS", b"parameters");
+    assert!(text.starts_with("-----BEGIN DH PARAMETERS-----\n"));
+    assert!(text.ends_with("-----END DH PARAMETERS-----\n"));
+    assert!(!is_armored(text.as_bytes()));
+    assert_eq!(
+        depem_labeled("DH PARAMETERS", text.as_bytes()),
+        Ok(b"parameters".to_vec())
+    );
+
+    assert_eq!(
+        depem_labeled("X9.42 DH PARAMETERS", text.as_bytes()),
+        Err(ArmorError::UnknownLabel)
+    );
+    assert_eq!(dearmor(text.as_bytes()), Err(ArmorError::UnknownLabel));
+
+    let ours = pem(ArmorKind::PublicKey, b"key");
+    assert_eq!(
+        depem_labeled("PUBCRYPT PUBLIC KEY", ours.as_bytes()),
+        Ok(b"key".to_vec())
+    );
+}
@@ -0,0 +1,219 @@
+/*!
+ * Thorough checks of keys and ciphertexts from untrusted sources.
+ *
+ * Keys are already checked when they're read (see `Key::validate`), but only as far as using them
+ * needs. The checks here go further, testing primality with more Miller-Rabin witnesses and
+ * rejecting trivial values, and report each check on its own so that it's clear what's wrong with
+ * a corrupt or maliciously crafted key. Ciphertexts are only checked for their structure, so
+ * nothing is decrypted and no private key is needed.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    header::Header,
+    mac::MAC_BYTES,
+    stream::{self, ChunkReader, CTR_SEGMENT_BYTES},
+    Algorithm, Block, CryptError, Key, KeyPair, Mode, Num, Padding, PrivateKey, PublicKey,
+    BLOCK_BYTES, NUM_BYTES,
+};
+use math::{mod_exp, primes};
+use rand::Rng;
+use std::{convert::TryInto, io::Read};
+
+/**
+ * The number of Miller-Rabin witnesses used to test a key's prime, which is more than the
+ * `primes::DEFAULT_WITNESS_COUNT` used when keys are read.
+ */
+pub const WITNESS_COUNT: usize = 4 * primes::DEFAULT_WITNESS_COUNT;
+
+/**
+ * The outcome of a single check.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Check {
+    /**
+     * What was checked, e.g., `prime is a safe prime`.
+     */
+    pub name: &'static str,
+    /**
+     * Whether the check passed.
+     */
+    pub passed: bool,
+}
+
+impl Check {
+    pub(crate) fn new(name: &'static str, passed: bool) -> Self {
+        Self { name, passed }
+    }
+}
+
+/**
+ * Check the parameters of the public or private key `key`: its prime must be a safe prime large
+ * enough to hold a block, as tested with `WITNESS_COUNT` witnesses chosen with `rng`, its root
+ * must be a primitive root of the prime, and its value must be in the range `[1, prime)` and not
+ * one of the trivial values 1 or `prime - 1`.
+ */
+pub fn check_key<T: Rng>(key: &Key, rng: &mut T) -> Vec<Check> {
+    let (prime, root, value) = (key.prime, key.root, key.value);
+    let q = prime.saturating_sub(1) / 2;
+    let is_prime = primes::is_prime(prime, WITNESS_COUNT, rng);
+    let is_safe = is_prime && primes::is_prime(q, WITNESS_COUNT, rng);
+
+    // the order of the group is `2q`, so an element is a generator iff it doesn't have order 1,
+    // 2, or `q`, which only holds if the prime is safe
+    let is_generator = is_safe && root > 1 && root < prime - 1 && mod_exp(root, q, prime) != 1;
+
+    vec![
+        Check::new(
+            "prime is large enough to hold a block",
+            prime > Block::MAX as Num,
+        ),
+        Check::new("prime is prime", is_prime),
+        Check::new("prime is a safe prime", is_safe),
+        Check::new("generator is a primitive root", is_generator),
+        Check::new(
+            "value is in the range [1, prime)",
+            value >= 1 && value < prime,
+        ),
+        Check::new(
+            "value isn't 1 or prime - 1",
+            value != 1 && value != prime.saturating_sub(1),
+        ),
+    ]
+}
+
+/**
+ * Check that `public` is the public key of `private`, as in `KeyPair::matches`.
+ */
+pub fn check_pair(private: &PrivateKey, public: &PublicKey) -> Check {
+    let pair = KeyPair {
+        public: public.clone(),
+        private: private.clone(),
+    };
+    Check::new("public key matches private key", pair.matches())
+}
+
+/**
+ * Check the structure of a ciphertext with `header`, as written by `EncryptWriter` or
+ * `cipher::encrypt`, by reading everything after the header from `body`: the header's options
+ * must be usable together, a chunked ciphertext's framing must be intact, an authenticated
+ * ciphertext must have room for its MAC, and the rest must be made up of whole blocks. The
+ * components of an ElGamal ciphertext must also be nonzero, and less than the prime of `key` if
+ * it's given, which also has to match the ciphertext's recipient.
+ *
+ * Nothing is decrypted, so a MAC is only checked for being present. Returns an error if `body`
+ * can't be read.
+ */
+pub fn check_ciphertext<R: Read>(
+    header: &Header,
+    body: R,
+    key: Option<&PublicKey>,
+) -> Result<Vec<Check>, CryptError> {
+    let mut checks = vec![Check::new(
+        "header options are consistent",
+        is_consistent(header),
+    )];
+    if let (Some(recipient), Some(key)) = (header.recipient, key) {
+        checks.push(Check::new(
+            "recipient matches the key",
+            recipient == key.fingerprint(),
+        ));
+    }
+
+    let mut payload = Vec::new();
+    let framed = ChunkReader::new(body, header.chunked).read_to_end(&mut payload);
+    let is_intact = match framed.map_err(CryptError::from) {
+        Ok(_) => true,
+        Err(CryptError::Truncated) | Err(CryptError::InvalidChunk) => false,
+        Err(e) => return Err(e),
+    };
+    if header.chunked {
+        checks.push(Check::new("chunk framing is intact", is_intact));
+    }
+    if !is_intact {
+        return Ok(checks);
+    }
+
+    // the ElGamal components, i.e., everything that should be in the range `[1, prime)`
+    let mut values = Vec::new();
+    let mut body = &payload[..];
+    if header.authenticated {
+        let has_mac = body.len() >= NUM_BYTES + MAC_BYTES;
+        checks.push(Check::new("integrity trailer is present", has_mac));
+        if !has_mac {
+            return Ok(checks);
+        }
+        values.push(read_num(&body[..NUM_BYTES]));
+        body = &body[NUM_BYTES..body.len() - MAC_BYTES];
+    }
+
+    let width = stream::ciphertext_block_width(header.algorithm);
+    let blocks: Vec<&[u8]> = match header.mode {
+        Mode::Hybrid => {
+            if let Some((c1, c2)) = header.wrapped_key {
+                values.extend_from_slice(&[c1, c2]);
+            }
+            Vec::new()
+        }
+        // each segment starts with its encrypted nonce
+        Mode::Ctr => body
+            .chunks(width + CTR_SEGMENT_BYTES)
+            .map(|segment| &segment[..width.min(segment.len())])
+            .collect(),
+        Mode::Ecb | Mode::Cbc => body.chunks(width).collect(),
+    };
+    let is_whole = match header.mode {
+        Mode::Hybrid => true,
+        Mode::Ctr => blocks.iter().all(|block| block.len() == width),
+        // even an empty plaintext is padded to a block
+        Mode::Ecb | Mode::Cbc => !blocks.is_empty() && body.len() % width == 0,
+    };
+    checks.push(Check::new("body is made of whole blocks", is_whole));
+
+    if header.algorithm == Algorithm::ElGamal {
+        for block in blocks.iter().filter(|block| block.len() == width) {
+            values.push(read_num(&block[..NUM_BYTES]));
+            values.push(read_num(&block[NUM_BYTES..]));
+        }
+
+        let check = match key {
+            Some(key) => {
+                let prime = key.key().prime;
+                let in_range = values.iter().all(|&value| value >= 1 && value < prime);
+                Check::new("values are in the range [1, prime)", in_range)
+            }
+            None => Check::new("values are nonzero", !values.contains(&0)),
+        };
+        checks.push(check);
+    }
+
+    Ok(checks)
+}
+
+/**
+ * Check that the options in `header` can be used together, as `crypt` requires when encrypting:
+ * blocks must be nonempty and fit in a `Block` along with their sequence numbers, checksums, and
+ * OAEP transform, none of which can be used in CTR or hybrid mode.
+ */
+fn is_consistent(header: &Header) -> bool {
+    let fits = header.block_bytes > 0 && header.block_width() <= BLOCK_BYTES;
+    let is_stream_usable = match header.mode {
+        Mode::Ctr | Mode::Hybrid => {
+            header.sequence_bytes == 0
+                && header.checksum_bytes == 0
+                && header.padding == Padding::Random
+                && !header.oaep
+        }
+        Mode::Ecb | Mode::Cbc => true,
+    };
+    fits && is_stream_usable
+}
+
+/**
+ * Read a big-endian `Num` from `bytes`, which must be `NUM_BYTES` long.
+ */
+fn read_num(bytes: &[u8]) -> Num {
+    Num::from_be_bytes(bytes.try_into().unwrap())
+}
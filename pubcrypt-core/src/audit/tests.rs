@@ -0,0 +1,155 @@
+use super::*;
+use crate::{
+    header::Header,
+    stream::{EncryptWriter, CHUNK_BYTES},
+    Encoding, KeyPair,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+use std::io::Write;
+
+/**
+ * Get the names of the checks in `checks` that failed.
+ */
+fn failures(checks: &[Check]) -> Vec<&'static str> {
+    checks
+        .iter()
+        .filter(|check| !check.passed)
+        .map(|check| check.name)
+        .collect()
+}
+
+/**
+ * Encrypt `len` bytes for `keys` in `mode`, returning the header and the rest of the ciphertext.
+ */
+fn encrypt(
+    keys: &KeyPair,
+    mode: Mode,
+    authenticated: bool,
+    chunked: bool,
+    len: usize,
+    rng: &mut StdRng,
+) -> (Header, Vec<u8>) {
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.mode = mode;
+    header.authenticated = authenticated;
+    header.chunked = chunked;
+    header.recipient = Some(keys.public.fingerprint());
+    let mut encryptor =
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+    encryptor.write_all(&vec![1_u8; len]).unwrap();
+    let ciphertext = encryptor.finish().unwrap();
+    let header = Header::read(&mut &ciphertext[..]).unwrap();
+    let body = ciphertext[header.len()..].to_vec();
+    (header, body)
+}
+
+#[test]
+fn test_check_key() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    assert!(failures(&check_key(keys.public.key(), &mut rng)).is_empty());
+    assert!(failures(&check_key(keys.private.key(), &mut rng)).is_empty());
+    assert!(check_pair(&keys.private, &keys.public).passed);
+
+    let other = KeyPair::generate(&mut rng).unwrap();
+    assert!(!check_pair(&keys.private, &other.public).passed);
+
+    let (prime, root) = (keys.public.key().prime, keys.public.key().root);
+    let trivial = Key {
+        prime,
+        root,
+        value: prime - 1,
+    };
+    assert_eq!(
+        failures(&check_key(&trivial, &mut rng)),
+        vec!["value isn't 1 or prime - 1"]
+    );
+
+    // a square is a quadratic residue, so it only generates half of the group
+    let square = Key {
+        prime,
+        root: mod_exp(root, 2, prime),
+        value: 2,
+    };
+    assert_eq!(
+        failures(&check_key(&square, &mut rng)),
+        vec!["generator is a primitive root"]
+    );
+
+    let unsafe_prime = (Block::MAX as Num + 2..)
+        .find(|&n| {
+            primes::is_prime(n, WITNESS_COUNT, &mut rng)
+                && !primes::is_prime(n / 2, WITNESS_COUNT, &mut rng)
+        })
+        .unwrap();
+    let unsafe_key = Key {
+        prime: unsafe_prime,
+        root: 3,
+        value: 2,
+    };
+    assert_eq!(
+        failures(&check_key(&unsafe_key, &mut rng)),
+        vec!["prime is a safe prime", "generator is a primitive root"]
+    );
+
+    let composite = Key {
+        prime: Block::MAX as Num * 3,
+        root: 3,
+        value: 2,
+    };
+    assert!(failures(&check_key(&composite, &mut rng)).contains(&"prime is prime"));
+}
+
+#[test]
+fn test_check_ciphertext() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other = KeyPair::generate(&mut rng).unwrap();
+
+    for &mode in &[Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Hybrid] {
+        for &(authenticated, chunked) in &[(false, false), (true, false), (true, true)] {
+            for &len in &[0, 1, 1000, CHUNK_BYTES + 1] {
+                let (header, body) = encrypt(&keys, mode, authenticated, chunked, len, &mut rng);
+                let checks = check_ciphertext(&header, &body[..], Some(&keys.public)).unwrap();
+                assert!(failures(&checks).is_empty(), "{:?} {}", header, len);
+                let checks = check_ciphertext(&header, &body[..], None).unwrap();
+                assert!(failures(&checks).is_empty(), "{:?} {}", header, len);
+
+                let checks = check_ciphertext(&header, &body[..], Some(&other.public)).unwrap();
+                assert!(failures(&checks).contains(&"recipient matches the key"));
+            }
+        }
+    }
+
+    let (header, body) = encrypt(&keys, Mode::Ecb, false, false, 100, &mut rng);
+    let checks = check_ciphertext(&header, &body[..body.len() - 1], None).unwrap();
+    assert_eq!(failures(&checks), vec!["body is made of whole blocks"]);
+
+    let mut zeroed = body.clone();
+    zeroed[..NUM_BYTES].fill(0);
+    let checks = check_ciphertext(&header, &zeroed[..], None).unwrap();
+    assert_eq!(failures(&checks), vec!["values are nonzero"]);
+
+    let mut too_large = body;
+    too_large[..NUM_BYTES].fill(0xff);
+    let checks = check_ciphertext(&header, &too_large[..], Some(&keys.public)).unwrap();
+    assert_eq!(
+        failures(&checks),
+        vec!["values are in the range [1, prime)"]
+    );
+
+    let (header, body) = encrypt(&keys, Mode::Ctr, true, true, 100, &mut rng);
+    let checks = check_ciphertext(&header, &body[..body.len() - 1], None).unwrap();
+    assert_eq!(failures(&checks), vec!["chunk framing is intact"]);
+
+    let (header, body) = encrypt(&keys, Mode::Ctr, true, false, 0, &mut rng);
+    let checks = check_ciphertext(&header, &body[..NUM_BYTES], None).unwrap();
+    assert_eq!(failures(&checks), vec!["integrity trailer is present"]);
+
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.mode = Mode::Ctr;
+    header.oaep = true;
+    let checks = check_ciphertext(&header, &[][..], None).unwrap();
+    assert_eq!(failures(&checks), vec!["header options are consistent"]);
+}
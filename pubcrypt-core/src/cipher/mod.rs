@@ -0,0 +1,390 @@
+/*!
+ * A common interface to public-key algorithms, so that alternatives to ElGamal can share key
+ * files and ciphertext framing.
+ *
+ * Each algorithm implements `PublicKeyCipher`, which generates and serializes keys and encrypts a
+ * `Block` to a fixed number of bytes. `encrypt` and `decrypt` frame any algorithm's blocks in ECB
+ * mode with random padding, behind a `header::Header` that records the algorithm, and
+ * `write_key_file` and `read_key_file` prefix keys with their algorithm. The other modes and
+ * options of the `stream` module are only available for `ElGamal`.
+ *
+ * ElGamal keys are written without an algorithm prefix, exactly as `Key::serialize` writes them,
+ * so key files stay readable by versions from before algorithms were introduced. Every other
+ * algorithm's key file starts with the magic bytes `PUBCRYPK` and the algorithm's identifier.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    decrypt_block, der, encrypt_block,
+    header::{self, Header},
+    peek::PeekReader,
+    Algorithm, Block, CryptError, Encoding, Key, KeyError, KeyGenError, KeyPair, Mode, Num,
+    PrivateKey, PublicKey, BLOCK_BYTES, NUM_BYTES,
+};
+use rand::Rng;
+use std::{
+    convert::TryInto,
+    io::{self, BufRead, Read, Write},
+};
+
+/**
+ * The bytes that the key files of algorithms other than ElGamal start with.
+ */
+pub const KEY_MAGIC: [u8; 8] = *b"PUBCRYPK";
+
+/**
+ * A public-key algorithm that encrypts one `Block` at a time.
+ */
+pub trait PublicKeyCipher {
+    /**
+     * The algorithm recorded in key files and ciphertext headers.
+     */
+    const ALGORITHM: Algorithm;
+
+    /**
+     * The number of bytes each block encrypts to.
+     */
+    const CIPHERTEXT_BYTES: usize;
+
+    type PublicKey: Clone;
+    type PrivateKey: Clone;
+
+    /**
+     * Generate a matching pair of keys, drawing randomness from `rng`.
+     */
+    fn generate<T: Rng>(rng: &mut T) -> Result<(Self::PublicKey, Self::PrivateKey), KeyGenError>;
+
+    /**
+     * Get the number of plaintext bytes that fit in a block encrypted with `key`, which is at most
+     * `BLOCK_BYTES`.
+     */
+    fn max_block_bytes(key: &Self::PublicKey) -> usize;
+
+    /**
+     * Encrypt `block` with `key`, returning `CIPHERTEXT_BYTES` bytes.
+     */
+    fn encrypt_block<T: Rng>(
+        block: Block,
+        key: &Self::PublicKey,
+        rng: &mut T,
+    ) -> Result<Vec<u8>, CryptError>;
+
+    /**
+     * Decrypt a block written by `encrypt_block`.
+     */
+    fn decrypt_block(ciphertext: &[u8], key: &Self::PrivateKey) -> Result<Block, CryptError>;
+
+    /**
+     * Convert a public key to bytes, without an algorithm prefix.
+     */
+    fn serialize_public_key(key: &Self::PublicKey) -> Vec<u8>;
+
+    /**
+     * Convert a private key to bytes, without an algorithm prefix.
+     */
+    fn serialize_private_key(key: &Self::PrivateKey) -> Vec<u8>;
+
+    /**
+     * Read a public key written by `serialize_public_key`, and check that it's valid. Primality
+     * is tested probabilistically using `rng`.
+     */
+    fn deserialize_public_key<T: Rng>(
+        bytes: &[u8],
+        rng: &mut T,
+    ) -> Result<Self::PublicKey, KeyError>;
+
+    /**
+     * Read a private key written by `serialize_private_key`, and check that it's valid as in
+     * `deserialize_public_key`.
+     */
+    fn deserialize_private_key<T: Rng>(
+        bytes: &[u8],
+        rng: &mut T,
+    ) -> Result<Self::PrivateKey, KeyError>;
+}
+
+/**
+ * The ElGamal scheme implemented by the rest of this crate, with blocks encoded as quadratic
+ * residues (see `Encoding::QuadraticResidue`).
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ElGamal;
+
+impl PublicKeyCipher for ElGamal {
+    const ALGORITHM: Algorithm = Algorithm::ElGamal;
+    const CIPHERTEXT_BYTES: usize = NUM_BYTES * 2;
+
+    type PublicKey = PublicKey;
+    type PrivateKey = PrivateKey;
+
+    fn generate<T: Rng>(rng: &mut T) -> Result<(PublicKey, PrivateKey), KeyGenError> {
+        let keys = KeyPair::generate(rng)?;
+        Ok((keys.public, keys.private))
+    }
+
+    fn max_block_bytes(_key: &PublicKey) -> usize {
+        BLOCK_BYTES
+    }
+
+    fn encrypt_block<T: Rng>(
+        block: Block,
+        key: &PublicKey,
+        rng: &mut T,
+    ) -> Result<Vec<u8>, CryptError> {
+        let (c1, c2) = encrypt_block(block, key, Encoding::QuadraticResidue, rng)?;
+        let mut result = c1.to_be_bytes().to_vec();
+        result.extend_from_slice(&c2.to_be_bytes());
+        Ok(result)
+    }
+
+    fn decrypt_block(ciphertext: &[u8], key: &PrivateKey) -> Result<Block, CryptError> {
+        if ciphertext.len() != Self::CIPHERTEXT_BYTES {
+            return Err(CryptError::CorruptBlock);
+        }
+
+        let mut c1_buf = [0_u8; NUM_BYTES];
+        let mut c2_buf = [0_u8; NUM_BYTES];
+        c1_buf.copy_from_slice(&ciphertext[..NUM_BYTES]);
+        c2_buf.copy_from_slice(&ciphertext[NUM_BYTES..]);
+        let (c1, c2) = (Num::from_be_bytes(c1_buf), Num::from_be_bytes(c2_buf));
+        decrypt_block(c1, c2, key, Encoding::QuadraticResidue)
+    }
+
+    fn serialize_public_key(key: &PublicKey) -> Vec<u8> {
+        key.serialize().to_vec()
+    }
+
+    fn serialize_private_key(key: &PrivateKey) -> Vec<u8> {
+        key.serialize().to_vec()
+    }
+
+    fn deserialize_public_key<T: Rng>(bytes: &[u8], rng: &mut T) -> Result<PublicKey, KeyError> {
+        deserialize_key(bytes, rng).map(PublicKey::from)
+    }
+
+    fn deserialize_private_key<T: Rng>(bytes: &[u8], rng: &mut T) -> Result<PrivateKey, KeyError> {
+        deserialize_key(bytes, rng).map(PrivateKey::from)
+    }
+}
+
+/**
+ * Read and validate an ElGamal key of any length, serialized with `Key::serialize` or
+ * `Key::to_der`.
+ */
+fn deserialize_key<T: Rng>(bytes: &[u8], rng: &mut T) -> Result<Key, KeyError> {
+    let key = deserialize_key_unchecked(bytes)?;
+    key.validate(rng)?;
+    Ok(key)
+}
+
+/**
+ * Read an ElGamal key as `ElGamal::deserialize_public_key` does, but without checking it, so that
+ * a malformed key can be inspected with the `audit` module. A DER-encoded key is never
+ * `Key::KEY_BYTES` long, so the two encodings can't be confused.
+ */
+pub fn deserialize_key_unchecked(bytes: &[u8]) -> Result<Key, KeyError> {
+    if bytes.len() != Key::KEY_BYTES && der::is_sequence(bytes) {
+        return Key::from_der(bytes);
+    }
+
+    let buf = bytes.try_into().map_err(|_| KeyError::InvalidLength)?;
+    Ok(Key::deserialize(buf))
+}
+
+/**
+ * Prefix the serialized key `key_bytes` with `algorithm`, giving the contents of a key file.
+ * ElGamal keys are returned as-is.
+ */
+pub fn write_key_file(algorithm: Algorithm, key_bytes: &[u8]) -> Vec<u8> {
+    if algorithm == Algorithm::ElGamal {
+        return key_bytes.to_vec();
+    }
+
+    let mut result = KEY_MAGIC.to_vec();
+    result.push(algorithm.id());
+    result.extend_from_slice(key_bytes);
+    result
+}
+
+/**
+ * Split the contents of a key file written by `write_key_file` into its algorithm and serialized
+ * key. Files that are exactly `Key::KEY_BYTES` long, or contain a single DER sequence (see
+ * `Key::to_der`), are ElGamal keys.
+ *
+ * Returns `KeyError::UnknownAlgorithm` if the file doesn't start with `KEY_MAGIC` and a known
+ * algorithm, and `KeyError::InvalidLength` if it's too short to be a key file.
+ */
+pub fn read_key_file(bytes: &[u8]) -> Result<(Algorithm, &[u8]), KeyError> {
+    if bytes.len() == Key::KEY_BYTES || der::is_sequence(bytes) {
+        return Ok((Algorithm::ElGamal, bytes));
+    }
+
+    match bytes.strip_prefix(&KEY_MAGIC[..]) {
+        Some([id, key_bytes @ ..]) => Algorithm::from_id(*id)
+            .filter(|&algorithm| algorithm != Algorithm::ElGamal)
+            .map(|algorithm| (algorithm, key_bytes))
+            .ok_or(KeyError::UnknownAlgorithm),
+        Some([]) => Err(KeyError::InvalidLength),
+        None if bytes.len() < KEY_MAGIC.len() => Err(KeyError::InvalidLength),
+        None => Err(KeyError::UnknownAlgorithm),
+    }
+}
+
+/**
+ * Encrypt the plaintext read from `reader` with `key`, writing the ciphertext to `writer`. The
+ * ciphertext is framed as in `ecb::encrypt_ecb`, with blocks of `C::max_block_bytes` bytes and
+ * random padding, and its header records `C::ALGORITHM`.
+ *
+ * Returns `CryptError::UnsupportedBlockSize` if the key's block size is 0 or greater than
+ * `BLOCK_BYTES`.
+ */
+pub fn encrypt<C, R, W, T>(
+    reader: &mut R,
+    writer: &mut W,
+    key: &C::PublicKey,
+    rng: &mut T,
+) -> Result<(), CryptError>
+where
+    C: PublicKeyCipher,
+    R: Read,
+    W: Write,
+    T: Rng,
+{
+    let block_bytes = C::max_block_bytes(key);
+    if block_bytes == 0 || block_bytes > BLOCK_BYTES {
+        return Err(CryptError::UnsupportedBlockSize(block_bytes));
+    }
+
+    // the encoding is only used by ElGamal, which always uses quadratic residues here
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.algorithm = C::ALGORITHM;
+    header.block_bytes = block_bytes;
+    header.write(writer)?;
+
+    loop {
+        let mut block_buf = [0_u8; BLOCK_BYTES];
+        let data = &mut block_buf[BLOCK_BYTES - block_bytes..];
+        let len = read_full(reader, data)?;
+
+        // the final block is always padded, even if it's made up solely of padding
+        let is_final = len < block_bytes;
+        if is_final {
+            header.padding.pad(data, (block_bytes - len) as u8, rng);
+        }
+
+        let block = Block::from_be_bytes(block_buf);
+        writer.write_all(&C::encrypt_block(block, key, rng)?)?;
+
+        if is_final {
+            return Ok(writer.flush()?);
+        }
+    }
+}
+
+/**
+ * Decrypt a ciphertext written by `encrypt` with `key`, reading it from `reader` and writing the
+ * plaintext to `writer`.
+ *
+ * Returns `CryptError::WrongAlgorithm` if the ciphertext wasn't encrypted with `C::ALGORITHM`,
+ * and `CryptError::InvalidHeader` if it uses a mode or option that `encrypt` doesn't; ElGamal
+ * ciphertexts like that can be decrypted with `ecb::decrypt_ecb`. Errors in individual blocks are
+ * reported with their offsets as in `ecb::decrypt_ecb`, and the plaintext preceding the offending
+ * block may already have been written.
+ */
+pub fn decrypt<C, R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    key: &C::PrivateKey,
+) -> Result<(), CryptError>
+where
+    C: PublicKeyCipher,
+    R: BufRead,
+    W: Write,
+{
+    let mut reader = PeekReader::new(reader);
+    reader.peek(header::MAGIC.len())?;
+    let header = Header::read(&mut reader)?;
+    if header.algorithm != C::ALGORITHM {
+        return Err(CryptError::WrongAlgorithm(header.algorithm));
+    }
+
+    let is_plain_ecb = header.encoding == Encoding::QuadraticResidue
+        && header.mode == Mode::Ecb
+        && header.sequence_bytes == 0
+        && header.checksum_bytes == 0
+        && !header.contains_file
+        && !header.archive
+        && !header.chunked
+        && !header.oaep
+        && !header.authenticated;
+    if !is_plain_ecb {
+        return Err(CryptError::InvalidHeader);
+    }
+
+    let block_bytes = header.block_bytes;
+    if block_bytes > BLOCK_BYTES {
+        return Err(CryptError::UnsupportedBlockSize(block_bytes));
+    }
+
+    let mut offset = header.len() as u64;
+    let mut pending: Option<(u64, [u8; BLOCK_BYTES])> = None;
+    let mut ciphertext = vec![0_u8; C::CIPHERTEXT_BYTES];
+
+    loop {
+        let at_offset = |error| CryptError::Block {
+            offset,
+            error: Box::new(error),
+        };
+
+        let len = read_full(&mut reader, &mut ciphertext)?;
+        if len == 0 {
+            break;
+        } else if len < ciphertext.len() {
+            return Err(at_offset(CryptError::CorruptBlock));
+        }
+
+        let block = C::decrypt_block(&ciphertext, key).map_err(at_offset)?;
+        let block_buf = block.to_be_bytes();
+        if block_buf[..BLOCK_BYTES - block_bytes]
+            .iter()
+            .any(|&b| b != 0)
+        {
+            return Err(at_offset(CryptError::WrongKey));
+        }
+
+        if let Some((_, previous)) = pending.replace((offset, block_buf)) {
+            writer.write_all(&previous[BLOCK_BYTES - block_bytes..])?;
+        }
+
+        offset += len as u64;
+    }
+
+    let (final_offset, final_block) = pending.ok_or(CryptError::Truncated)?;
+    let data = &final_block[BLOCK_BYTES - block_bytes..];
+    let pad_bytes = header.padding.unpad(data).ok_or(CryptError::Block {
+        offset: final_offset,
+        error: Box::new(CryptError::InvalidPadding(header.padding)),
+    })?;
+
+    writer.write_all(&data[..data.len() - pad_bytes])?;
+    Ok(writer.flush()?)
+}
+
+/**
+ * Read from `reader` until `buf` is full or the input ends, returning the number of bytes read.
+ */
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(len)
+}
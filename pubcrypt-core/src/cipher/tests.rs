@@ -0,0 +1,150 @@
+use super::*;
+use crate::{ec::EcElGamal, ecb, rsa::Rsa, PublicKey};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+fn encrypt_to_vec<C: PublicKeyCipher>(plaintext: &[u8], key: &C::PublicKey) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut ciphertext = Vec::new();
+    encrypt::<C, _, _, _>(&mut &plaintext[..], &mut ciphertext, key, &mut rng).unwrap();
+    ciphertext
+}
+
+fn decrypt_to_vec<C: PublicKeyCipher>(
+    ciphertext: &[u8],
+    key: &C::PrivateKey,
+) -> Result<Vec<u8>, CryptError> {
+    let mut plaintext = Vec::new();
+    decrypt::<C, _, _>(&mut &ciphertext[..], &mut plaintext, key)?;
+    Ok(plaintext)
+}
+
+#[test]
+fn test_elgamal_cipher() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = ElGamal::generate(&mut rng).unwrap();
+
+    for len in 0..3 * BLOCK_BYTES {
+        let plaintext: Vec<u8> = (0..len as u8).collect();
+        let ciphertext = encrypt_to_vec::<ElGamal>(&plaintext, &public);
+        assert_eq!(
+            decrypt_to_vec::<ElGamal>(&ciphertext, &private).unwrap(),
+            plaintext
+        );
+
+        // the framing is the same as the `ecb` module's, so either can decrypt the other's output
+        let mut decrypted = Vec::new();
+        ecb::decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &private).unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        let mut ecb_ciphertext = Vec::new();
+        ecb::encrypt_ecb(&mut &plaintext[..], &mut ecb_ciphertext, &public, &mut rng).unwrap();
+        assert_eq!(
+            decrypt_to_vec::<ElGamal>(&ecb_ciphertext, &private).unwrap(),
+            plaintext
+        );
+    }
+}
+
+#[test]
+fn test_cipher_errors() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = ElGamal::generate(&mut rng).unwrap();
+    let ciphertext = encrypt_to_vec::<ElGamal>(b"some plaintext", &public);
+    let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+
+    let truncated = &ciphertext[..ciphertext.len() - 1];
+    let error = decrypt_to_vec::<ElGamal>(truncated, &private).unwrap_err();
+    assert!(matches!(error.root_cause(), CryptError::CorruptBlock));
+
+    let error = decrypt_to_vec::<ElGamal>(&ciphertext[..header_len], &private).unwrap_err();
+    assert!(matches!(error, CryptError::Truncated));
+
+    // options that the generic framing doesn't support are rejected
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.mode = Mode::Cbc;
+    let mut cbc_ciphertext = Vec::new();
+    header.write(&mut cbc_ciphertext).unwrap();
+    cbc_ciphertext.extend_from_slice(&ciphertext[header_len..]);
+    assert!(matches!(
+        decrypt_to_vec::<ElGamal>(&cbc_ciphertext, &private),
+        Err(CryptError::InvalidHeader)
+    ));
+}
+
+#[test]
+fn test_key_files() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = ElGamal::generate(&mut rng).unwrap();
+
+    // ElGamal key files have no prefix, so they're the same as before algorithms were recorded
+    let public_bytes = ElGamal::serialize_public_key(&public);
+    let file = write_key_file(Algorithm::ElGamal, &public_bytes);
+    assert_eq!(file, public.serialize());
+
+    let (algorithm, key_bytes) = read_key_file(&file).unwrap();
+    assert_eq!(algorithm, Algorithm::ElGamal);
+    assert_eq!(
+        ElGamal::deserialize_public_key(key_bytes, &mut rng).unwrap(),
+        public
+    );
+
+    let private_bytes = ElGamal::serialize_private_key(&private);
+    assert_eq!(
+        ElGamal::deserialize_private_key(&private_bytes, &mut rng).unwrap(),
+        private
+    );
+    assert_eq!(
+        ElGamal::deserialize_public_key(&private_bytes[1..], &mut rng),
+        Err(KeyError::InvalidLength)
+    );
+
+    let mut unknown = KEY_MAGIC.to_vec();
+    unknown.extend_from_slice(&[0xff, 1, 2, 3]);
+    assert_eq!(read_key_file(&unknown), Err(KeyError::UnknownAlgorithm));
+    assert_eq!(read_key_file(&KEY_MAGIC), Err(KeyError::InvalidLength));
+    assert_eq!(read_key_file(b"key"), Err(KeyError::InvalidLength));
+    assert_eq!(
+        read_key_file(&[0_u8; Key::KEY_BYTES + 1]),
+        Err(KeyError::UnknownAlgorithm)
+    );
+}
+
+#[test]
+fn test_generic_keys() {
+    fn round_trip<C: PublicKeyCipher>(public: &C::PublicKey, private: &C::PrivateKey) -> bool
+    where
+        C::PublicKey: PartialEq,
+    {
+        let mut rng = StdRng::seed_from_u64(0);
+        let file = write_key_file(C::ALGORITHM, &C::serialize_public_key(public));
+        let (algorithm, bytes) = read_key_file(&file).unwrap();
+        let reloaded = C::deserialize_public_key(bytes, &mut rng).unwrap();
+
+        let ciphertext = encrypt_to_vec::<C>(b"generic", &reloaded);
+        algorithm == C::ALGORITHM
+            && reloaded == *public
+            && decrypt_to_vec::<C>(&ciphertext, private).unwrap() == b"generic"
+    }
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private): (PublicKey, _) = ElGamal::generate(&mut rng).unwrap();
+    assert!(round_trip::<ElGamal>(&public, &private));
+
+    let (public, private) = Rsa::generate(&mut rng).unwrap();
+    assert!(round_trip::<Rsa>(&public, &private));
+
+    let (ec_public, ec_private) = EcElGamal::generate(&mut rng).unwrap();
+    assert!(round_trip::<EcElGamal>(&ec_public, &ec_private));
+
+    // private and public RSA keys are different lengths, so they can't be mixed up
+    let file = write_key_file(Rsa::ALGORITHM, &Rsa::serialize_private_key(&private));
+    assert_eq!(file[..KEY_MAGIC.len()], KEY_MAGIC);
+    let (algorithm, bytes) = read_key_file(&file).unwrap();
+    assert_eq!(algorithm, Algorithm::Rsa);
+    assert_eq!(Rsa::deserialize_private_key(bytes, &mut rng), Ok(private));
+    assert_eq!(
+        Rsa::deserialize_public_key(bytes, &mut rng),
+        Err(KeyError::InvalidLength)
+    );
+}
@@ -0,0 +1,151 @@
+/*!
+ * Compact public keys, which spell out a key as a short string of letters and digits that can be
+ * read aloud, typed on a phone, or scanned from a QR code when sharing keys in person.
+ *
+ * A compact key is `PREFIX` followed by the base32 encoding (RFC 4648, without padding) of the
+ * serialized key and the first `CHECKSUM_BYTES` bytes of its SHA-256 digest, in groups of
+ * `GROUP_CHARS` characters separated by `-`:
+ *
+ * ```text
+ * PUBCRYPT:C3PMA-ALW6Y-GQD5J-...
+ * ```
+ *
+ * Every character is in the QR code alphanumeric set, so the string fits in a small QR code (see
+ * the `qr` module). When reading a compact key, case, whitespace, and the `-` separators are
+ * ignored, so it can be typed back in however it was written down.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{hash, Key, KeyError, PublicKey};
+
+/**
+ * The text that starts every compact key.
+ */
+pub const PREFIX: &str = "PUBCRYPT:";
+
+/**
+ * The number of bytes of the key's digest that follow it as a checksum.
+ */
+pub const CHECKSUM_BYTES: usize = 4;
+
+/**
+ * The number of characters in each group of a compact key.
+ */
+pub const GROUP_CHARS: usize = 5;
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BITS_PER_CHAR: usize = 5;
+const SEPARATOR: char = '-';
+
+/**
+ * Write `key` as a compact key.
+ */
+pub fn to_compact(key: &PublicKey) -> String {
+    let bytes = key.serialize();
+    let mut data = bytes.to_vec();
+    data.extend_from_slice(&hash::sha256(&bytes)[..CHECKSUM_BYTES]);
+
+    let encoded = encode_base32(&data);
+    let groups: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(GROUP_CHARS)
+        // base32 is ASCII, so every chunk is valid UTF-8
+        .map(|group| std::str::from_utf8(group).unwrap())
+        .collect();
+    format!("{}{}", PREFIX, groups.join(&SEPARATOR.to_string()))
+}
+
+/**
+ * Read a compact key written by `to_compact`. Surrounding whitespace, case, and whitespace or `-`
+ * between characters are ignored. Like `Key::deserialize`, this doesn't check the key.
+ *
+ * Returns `KeyError::InvalidCompact` if the text doesn't start with `PREFIX`, isn't valid base32,
+ * is the wrong length, or its checksum doesn't match.
+ */
+pub fn from_compact(text: &str) -> Result<PublicKey, KeyError> {
+    let text = text.trim();
+    let encoded = match text.get(..PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(PREFIX) => &text[PREFIX.len()..],
+        _ => return Err(KeyError::InvalidCompact),
+    };
+
+    let encoded: String = encoded
+        .chars()
+        .filter(|&c| c != SEPARATOR && !c.is_whitespace())
+        .collect();
+    let data = decode_base32(&encoded).ok_or(KeyError::InvalidCompact)?;
+    if data.len() != Key::KEY_BYTES + CHECKSUM_BYTES {
+        return Err(KeyError::InvalidCompact);
+    }
+
+    let (key_bytes, checksum) = data.split_at(Key::KEY_BYTES);
+    if hash::sha256(key_bytes)[..CHECKSUM_BYTES] != *checksum {
+        return Err(KeyError::InvalidCompact);
+    }
+
+    let mut bytes = [0_u8; Key::KEY_BYTES];
+    bytes.copy_from_slice(key_bytes);
+    Ok(PublicKey::deserialize(&bytes))
+}
+
+/**
+ * Check whether `bytes` looks like a compact key, i.e., starts with `PREFIX` in any case after
+ * any whitespace. The rest of the key is checked by `from_compact`.
+ */
+pub fn is_compact(bytes: &[u8]) -> bool {
+    bytes
+        .trim_ascii_start()
+        .get(..PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(PREFIX.as_bytes()))
+}
+
+/**
+ * Encode `bytes` in unpadded base32.
+ */
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    let mut buffer = 0_u16;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = buffer << 8 | byte as u16;
+        bits += 8;
+        while bits >= BITS_PER_CHAR {
+            bits -= BITS_PER_CHAR;
+            result.push(ALPHABET[(buffer >> bits) as usize & 0x1f] as char);
+        }
+    }
+
+    if bits > 0 {
+        result.push(ALPHABET[(buffer << (BITS_PER_CHAR - bits)) as usize & 0x1f] as char);
+    }
+    result
+}
+
+/**
+ * Decode unpadded base32 in any case, returning `None` if it's invalid, including when the
+ * leftover bits at the end aren't zero.
+ */
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut buffer = 0_u16;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = ALPHABET
+            .iter()
+            .position(|&letter| letter == c.to_ascii_uppercase())?;
+        buffer = buffer << BITS_PER_CHAR | value as u16;
+        bits += BITS_PER_CHAR;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+        }
+    }
+
+    // a whole character of leftover bits, or any that are set, can't come from `encode_base32`
+    if bits >= BITS_PER_CHAR || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(result)
+}
@@ -0,0 +1,92 @@
+use super::*;
+use crate::KeyPair;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_base32() {
+    // test vectors from RFC 4648, without padding
+    let vectors = [
+        ("", ""),
+        ("f", "MY"),
+        ("fo", "MZXQ"),
+        ("foo", "MZXW6"),
+        ("foob", "MZXW6YQ"),
+        ("fooba", "MZXW6YTB"),
+        ("foobar", "MZXW6YTBOI"),
+    ];
+    for (plain, encoded) in &vectors {
+        assert_eq!(encode_base32(plain.as_bytes()), *encoded);
+        assert_eq!(decode_base32(encoded).unwrap(), plain.as_bytes());
+        assert_eq!(
+            decode_base32(&encoded.to_lowercase()).unwrap(),
+            plain.as_bytes()
+        );
+    }
+
+    // leftover bits must be zero, and can't make up a whole character
+    assert_eq!(decode_base32("MZ"), None);
+    assert_eq!(decode_base32("MZX"), None);
+    assert_eq!(decode_base32("MY1"), None);
+}
+
+#[test]
+fn test_compact_keys() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let compact = to_compact(&keys.public);
+    assert!(compact.starts_with(PREFIX));
+    assert!(is_compact(compact.as_bytes()));
+    assert!(compact[PREFIX.len()..]
+        .split(SEPARATOR)
+        .all(|group| !group.is_empty() && group.len() <= GROUP_CHARS));
+    assert!(compact
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == ':' || c == '-'));
+    assert_eq!(from_compact(&compact), Ok(keys.public.clone()));
+
+    // typed back in without separators, in lowercase, or with spaces
+    let typed = compact.to_lowercase().replace('-', "");
+    assert!(is_compact(typed.as_bytes()));
+    assert_eq!(from_compact(&typed), Ok(keys.public.clone()));
+    let spaced = format!("\n {}\n", compact.replace('-', " "));
+    assert_eq!(from_compact(&spaced), Ok(keys.public));
+}
+
+#[test]
+fn test_invalid_compact_keys() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let compact = to_compact(&keys.public);
+    let encoded = &compact[PREFIX.len()..];
+
+    // a changed character breaks the checksum
+    let mut changed = compact.clone().into_bytes();
+    let last = changed.len() - 1;
+    changed[PREFIX.len()] = if changed[PREFIX.len()] == b'A' {
+        b'B'
+    } else {
+        b'A'
+    };
+    let changed = String::from_utf8(changed).unwrap();
+
+    let invalid = [
+        String::new(),
+        encoded.to_string(),
+        format!("PUBKEY:{}", encoded),
+        format!("{}!", compact),
+        format!("{}A", compact),
+        compact[..last].to_string(),
+        changed,
+    ];
+    for text in &invalid {
+        assert_eq!(
+            from_compact(text),
+            Err(KeyError::InvalidCompact),
+            "{}",
+            text
+        );
+    }
+    assert!(!is_compact(encoded.as_bytes()));
+}
@@ -0,0 +1,253 @@
+/*!
+ * A container holding a file's contents along with its name, size, and modification time, which
+ * is encrypted in place of the bare contents when the header's `contains_file` flag is set, so
+ * that decryption can restore the file as it was.
+ *
+ * A container consists of the magic bytes `PUBCRFIL`, the version byte `VERSION`, the big-endian
+ * two-byte length of the file's name, the name in UTF-8, the big-endian eight-byte size and
+ * modification time (in seconds since the Unix epoch), the contents, and finally the SHA-256
+ * digest of the contents. The digest comes last so that a container can be written and read in
+ * a single pass.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    hash::{Sha256, DIGEST_BYTES},
+    CryptError,
+};
+use std::{
+    cmp,
+    io::{self, Read, Write},
+};
+
+/**
+ * The bytes that every container starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRFIL";
+
+/**
+ * The version of the container format written by `Packer`.
+ */
+pub const VERSION: u8 = 1;
+
+/**
+ * The maximum number of bytes in a file's name.
+ */
+pub const MAX_NAME_BYTES: usize = u16::MAX as usize;
+
+const LEN_BYTES: usize = 2;
+const SIZE_BYTES: usize = 8;
+const TIME_BYTES: usize = 8;
+const COPY_BUF_BYTES: usize = 8192;
+
+/**
+ * The metadata of a file in a container.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    /**
+     * The file's name, without any directories.
+     */
+    pub name: String,
+    /**
+     * The number of bytes in the file.
+     */
+    pub size: u64,
+    /**
+     * The file's modification time in seconds since the Unix epoch.
+     */
+    pub modified: u64,
+}
+
+impl FileInfo {
+    /**
+     * Read the metadata at the start of a container, leaving `reader` at the start of the
+     * contents, which can then be read with `unpack`.
+     *
+     * Returns `CryptError::InvalidContainer` if the container doesn't start with `MAGIC`, is from
+     * another version, or is truncated, or if the name isn't a plain file name (see `Packer::new`).
+     */
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, CryptError> {
+        let mut fixed = [0_u8; MAGIC.len() + 1 + LEN_BYTES];
+        read_container_bytes(reader, &mut fixed)?;
+        if fixed[..MAGIC.len()] != MAGIC || fixed[MAGIC.len()] != VERSION {
+            return Err(CryptError::InvalidContainer);
+        }
+
+        let name_len = u16::from_be_bytes([fixed[MAGIC.len() + 1], fixed[MAGIC.len() + 2]]);
+        let mut name = vec![0_u8; name_len as usize];
+        read_container_bytes(reader, &mut name)?;
+        let name = String::from_utf8(name).map_err(|_| CryptError::InvalidContainer)?;
+        if !is_plain_name(&name) {
+            return Err(CryptError::InvalidContainer);
+        }
+
+        let mut size = [0_u8; SIZE_BYTES];
+        let mut modified = [0_u8; TIME_BYTES];
+        read_container_bytes(reader, &mut size)?;
+        read_container_bytes(reader, &mut modified)?;
+
+        Ok(Self {
+            name,
+            size: u64::from_be_bytes(size),
+            modified: u64::from_be_bytes(modified),
+        })
+    }
+
+    /**
+     * Get the bytes of the container before the contents.
+     */
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut result = MAGIC.to_vec();
+        result.push(VERSION);
+        result.extend_from_slice(&(self.name.len() as u16).to_be_bytes());
+        result.extend_from_slice(self.name.as_bytes());
+        result.extend_from_slice(&self.size.to_be_bytes());
+        result.extend_from_slice(&self.modified.to_be_bytes());
+        result
+    }
+}
+
+/**
+ * A reader that packs the contents read from an inner reader into a container.
+ */
+pub struct Packer<R: Read> {
+    inner: R,
+    // the bytes to give out before or after the contents, and how many of them have been
+    pending: Vec<u8>,
+    pending_pos: usize,
+    remaining: u64,
+    hasher: Option<Sha256>,
+}
+
+impl<R: Read> Packer<R> {
+    /**
+     * Create a packer for the file described by `info`, whose contents are read from `inner`.
+     *
+     * Returns `CryptError::InvalidContainer` if the name is empty, longer than `MAX_NAME_BYTES`,
+     * `.` or `..`, or contains a path separator or NUL, so that restoring the file can't write
+     * anywhere but the chosen directory.
+     */
+    pub fn new(info: &FileInfo, inner: R) -> Result<Self, CryptError> {
+        if !is_plain_name(&info.name) {
+            return Err(CryptError::InvalidContainer);
+        }
+
+        Ok(Self {
+            inner,
+            pending: info.to_bytes(),
+            pending_pos: 0,
+            remaining: info.size,
+            hasher: Some(Sha256::new()),
+        })
+    }
+}
+
+impl<R: Read> Read for Packer<R> {
+    /**
+     * Read the next bytes of the container. Returns an error of kind
+     * `io::ErrorKind::UnexpectedEof` if the inner reader ends before the size in the file's
+     * metadata, and `io::ErrorKind::InvalidData` if it goes on past it, which happens when the
+     * file changes while it's being packed.
+     */
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending_pos < self.pending.len() {
+            let count = cmp::min(buf.len(), self.pending.len() - self.pending_pos);
+            buf[..count].copy_from_slice(&self.pending[self.pending_pos..self.pending_pos + count]);
+            self.pending_pos += count;
+            return Ok(count);
+        }
+
+        let hasher = match &mut self.hasher {
+            Some(hasher) => hasher,
+            None => return Ok(0),
+        };
+
+        if self.remaining > 0 {
+            let len = cmp::min(buf.len() as u64, self.remaining) as usize;
+            let count = self.inner.read(&mut buf[..len])?;
+            if count == 0 && len > 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the file is shorter than its recorded size",
+                ));
+            }
+
+            hasher.update(&buf[..count]);
+            self.remaining -= count as u64;
+            return Ok(count);
+        }
+
+        if self.inner.read(&mut [0_u8; 1])? > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the file is longer than its recorded size",
+            ));
+        }
+
+        // every byte of the contents has been read, so the digest comes next
+        self.pending = self.hasher.take().unwrap().finalize().to_vec();
+        self.pending_pos = 0;
+        self.read(buf)
+    }
+}
+
+/**
+ * Copy the contents of a container from `reader` to `writer`, where `info` was just read from
+ * the same reader with `FileInfo::read`, and check them against the digest that follows.
+ *
+ * Returns `CryptError::InvalidContainer` if the container ends early or has anything after the
+ * digest, and `CryptError::DigestMismatch` if the contents don't match the digest. Note that the
+ * contents have already been written when the digest is checked.
+ */
+pub fn unpack<R: Read, W: Write + ?Sized>(
+    reader: &mut R,
+    writer: &mut W,
+    info: &FileInfo,
+) -> Result<(), CryptError> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0_u8; COPY_BUF_BYTES];
+    let mut remaining = info.size;
+    while remaining > 0 {
+        let len = cmp::min(buf.len() as u64, remaining) as usize;
+        read_container_bytes(reader, &mut buf[..len])?;
+        hasher.update(&buf[..len]);
+        writer.write_all(&buf[..len])?;
+        remaining -= len as u64;
+    }
+
+    let mut digest = [0_u8; DIGEST_BYTES];
+    read_container_bytes(reader, &mut digest)?;
+    if hasher.finalize() != digest {
+        return Err(CryptError::DigestMismatch);
+    }
+
+    if reader.read(&mut [0_u8; 1])? > 0 {
+        return Err(CryptError::InvalidContainer);
+    }
+
+    Ok(())
+}
+
+/**
+ * Check whether `name` names a file without referring to any directory.
+ */
+pub(crate) fn is_plain_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_BYTES
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', '\0'])
+}
+
+/**
+ * Fill `buf` from `reader`, returning `CryptError::InvalidContainer` if the container ends first.
+ */
+fn read_container_bytes<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), CryptError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => CryptError::InvalidContainer,
+        _ => CryptError::Io(e),
+    })
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+fn info(name: &str, size: u64) -> FileInfo {
+    FileInfo {
+        name: name.to_string(),
+        size,
+        modified: 1_600_000_000,
+    }
+}
+
+fn pack(info: &FileInfo, contents: &[u8]) -> io::Result<Vec<u8>> {
+    let mut packed = Vec::new();
+    Packer::new(info, contents)
+        .unwrap()
+        .read_to_end(&mut packed)?;
+    Ok(packed)
+}
+
+#[test]
+fn test_round_trip() {
+    for len in [0, 1, 100, COPY_BUF_BYTES * 2 + 1] {
+        let contents: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let info = info("notes.txt", len as u64);
+        let packed = pack(&info, &contents).unwrap();
+        assert!(packed.starts_with(&MAGIC));
+        assert!(packed.ends_with(&crate::hash::sha256(&contents)));
+
+        let mut reader = &packed[..];
+        assert_eq!(FileInfo::read(&mut reader).unwrap(), info);
+        let mut unpacked = Vec::new();
+        unpack(&mut reader, &mut unpacked, &info).unwrap();
+        assert_eq!(unpacked, contents);
+    }
+}
+
+#[test]
+fn test_invalid_names() {
+    let long = "a".repeat(MAX_NAME_BYTES + 1);
+    for name in ["", ".", "..", "dir/file", "dir\\file", "nul\0", &long] {
+        assert!(matches!(
+            Packer::new(&info(name, 0), &[][..]),
+            Err(CryptError::InvalidContainer)
+        ));
+    }
+
+    // a container with a path for a name is rejected when it's read, too
+    let mut packed = pack(&info("..a", 0), &[]).unwrap();
+    let start = MAGIC.len() + 1 + LEN_BYTES;
+    packed[start..start + 3].copy_from_slice(b"../");
+    assert!(matches!(
+        FileInfo::read(&mut &packed[..]),
+        Err(CryptError::InvalidContainer)
+    ));
+}
+
+#[test]
+fn test_wrong_size() {
+    // the file changed between reading its metadata and its contents
+    let shorter = pack(&info("a", 5), b"abc").unwrap_err();
+    assert_eq!(shorter.kind(), io::ErrorKind::UnexpectedEof);
+    let longer = pack(&info("a", 2), b"abc").unwrap_err();
+    assert_eq!(longer.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_corrupt_container() {
+    let info = info("a", 3);
+    let packed = pack(&info, b"abc").unwrap();
+    let unpack_all = |packed: &[u8]| {
+        let mut reader = packed;
+        let info = FileInfo::read(&mut reader)?;
+        unpack(&mut reader, &mut Vec::new(), &info)
+    };
+
+    let mut changed = packed.clone();
+    let contents_start = packed.len() - DIGEST_BYTES - 3;
+    changed[contents_start] ^= 1;
+    assert!(matches!(
+        unpack_all(&changed),
+        Err(CryptError::DigestMismatch)
+    ));
+
+    let mut extended = packed.clone();
+    extended.push(0);
+    assert!(matches!(
+        unpack_all(&extended),
+        Err(CryptError::InvalidContainer)
+    ));
+
+    for len in [0, MAGIC.len(), contents_start, packed.len() - 1] {
+        assert!(matches!(
+            unpack_all(&packed[..len]),
+            Err(CryptError::InvalidContainer)
+        ));
+    }
+
+    let mut wrong_version = packed;
+    wrong_version[MAGIC.len()] = VERSION + 1;
+    assert!(matches!(
+        unpack_all(&wrong_version),
+        Err(CryptError::InvalidContainer)
+    ));
+}
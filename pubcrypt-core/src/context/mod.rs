@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{check_ciphertext, Blinding, Block, CryptError, Encoding, Num, PrivateKey, PublicKey};
+use math::montgomery::{FixedBase, Montgomery};
+use rand::Rng;
+
+/**
+ * A public key prepared for encrypting many blocks.
+ *
+ * Encryption raises the key's root and value to the same random exponent for every block. The
+ * context precomputes the Montgomery constants for the key's prime and a table of powers of each
+ * base once, which makes each encryption about twice as fast as `encrypt_block`, at the cost of
+ * a few kilobytes of memory.
+ */
+#[derive(Debug, Clone)]
+pub struct EncryptContext {
+    prime: Num,
+    montgomery: Montgomery,
+    root_powers: FixedBase,
+    value_powers: FixedBase,
+}
+
+impl EncryptContext {
+    /**
+     * Prepare `key` for encryption.
+     *
+     * Returns `CryptError::MalformedKey` if the key's prime is too small to encrypt a block, or
+     * isn't odd.
+     */
+    pub fn new(key: &PublicKey) -> Result<Self, CryptError> {
+        let key = key.key();
+        if key.prime <= Block::MAX as Num {
+            return Err(CryptError::MalformedKey);
+        }
+
+        let montgomery = Montgomery::new(key.prime).ok_or(CryptError::MalformedKey)?;
+        Ok(Self {
+            prime: key.prime,
+            montgomery,
+            root_powers: FixedBase::new(key.root, montgomery),
+            value_powers: FixedBase::new(key.value, montgomery),
+        })
+    }
+
+    /**
+     * Encrypt `block` as in `encrypt_block`, with a random exponent generated by `rng`.
+     *
+     * Returns `CryptError::MalformedKey` if the key's prime is too small for `encoding`.
+     */
+    pub fn encrypt_block<T: Rng>(
+        &self,
+        block: Block,
+        encoding: Encoding,
+        rng: &mut T,
+    ) -> Result<(Num, Num), CryptError> {
+        self.encrypt_block_det(block, encoding, rng.gen_range(1..self.prime))
+    }
+
+    /**
+     * Encrypt `block` as in `encrypt_block`, using `r` for exponentiation.
+     */
+    pub(crate) fn encrypt_block_det(
+        &self,
+        block: Block,
+        encoding: Encoding,
+        r: Num,
+    ) -> Result<(Num, Num), CryptError> {
+        let message = encoding.encode(block, self.prime)?;
+        let c1 = self.root_powers.pow(r);
+        let shared = self.montgomery.to_form(self.value_powers.pow(r));
+        let c2 = self
+            .montgomery
+            .mul(self.montgomery.to_form(message), shared);
+        Ok((c1, self.montgomery.from_form(c2)))
+    }
+}
+
+/**
+ * A private key prepared for decrypting many blocks.
+ *
+ * The context precomputes the Montgomery constants for the key's prime and the decryption
+ * exponent once, which makes each decryption faster than `decrypt_block`.
+ */
+#[derive(Debug, Clone)]
+pub struct DecryptContext {
+    key: PrivateKey,
+    montgomery: Montgomery,
+    exponent: Num,
+}
+
+impl DecryptContext {
+    /**
+     * Prepare `key` for decryption.
+     *
+     * Returns `CryptError::MalformedKey` if the key's value isn't less than its prime, or if the
+     * prime isn't odd.
+     */
+    pub fn new(key: &PrivateKey) -> Result<Self, CryptError> {
+        let inner = key.key();
+        if inner.value >= inner.prime {
+            return Err(CryptError::MalformedKey);
+        }
+
+        Ok(Self {
+            key: key.clone(),
+            montgomery: Montgomery::new(inner.prime).ok_or(CryptError::MalformedKey)?,
+            exponent: inner.prime - inner.value - 1,
+        })
+    }
+
+    /**
+     * Decrypt the ciphertext pair `(c1, c2)` as in `decrypt_block`, returning the same errors.
+     */
+    pub fn decrypt_block(&self, c1: Num, c2: Num, encoding: Encoding) -> Result<Block, CryptError> {
+        check_ciphertext(c1, c2, self.key.key(), encoding)?;
+
+        let c1_term = self
+            .montgomery
+            .pow(self.montgomery.to_form(c1), self.exponent);
+        self.finish(c1_term, c2, encoding)
+    }
+
+    /**
+     * Decrypt the ciphertext pair `(c1, c2)` as in `decrypt_block_blinded`, returning the same
+     * errors.
+     */
+    pub fn decrypt_block_blinded(
+        &self,
+        c1: Num,
+        c2: Num,
+        encoding: Encoding,
+        blinding: &mut Blinding,
+    ) -> Result<Block, CryptError> {
+        check_ciphertext(c1, c2, self.key.key(), encoding)?;
+        let (factor, correction) = blinding.next(self.montgomery.modulus())?;
+
+        let m = &self.montgomery;
+        let blinded = m.mul(m.to_form(c1), m.to_form(factor));
+        let c1_term = m.mul(m.pow_ct(blinded, self.exponent), m.to_form(correction));
+        self.finish(c1_term, c2, encoding)
+    }
+
+    /**
+     * Multiply `c2` by `c1_term`, which is `c1^-x` in Montgomery form, and decode the result.
+     */
+    fn finish(&self, c1_term: Num, c2: Num, encoding: Encoding) -> Result<Block, CryptError> {
+        let m = &self.montgomery;
+        let result = m.from_form(m.mul(c1_term, m.to_form(c2)));
+        encoding.decode(result, m.modulus())
+    }
+}
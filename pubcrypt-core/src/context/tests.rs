@@ -0,0 +1,81 @@
+use super::*;
+use crate::{decrypt_block, encrypt_block, Key, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_context_round_trip() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..5 {
+        let keys = KeyPair::generate(&mut rng).unwrap();
+        let encryptor = EncryptContext::new(&keys.public).unwrap();
+        let decryptor = DecryptContext::new(&keys.private).unwrap();
+        let mut blinding = Blinding::new(&keys.private, &mut rng);
+
+        for i in 0..1000 {
+            let encoding = if i % 2 == 0 {
+                Encoding::QuadraticResidue
+            } else {
+                Encoding::Raw
+            };
+            let block = rng.gen();
+
+            // contexts are interchangeable with the free functions in both directions
+            let (c1, c2) = encryptor.encrypt_block(block, encoding, &mut rng).unwrap();
+            assert_eq!(
+                decrypt_block(c1, c2, &keys.private, encoding).unwrap(),
+                block
+            );
+            assert_eq!(decryptor.decrypt_block(c1, c2, encoding).unwrap(), block);
+
+            let (c1, c2) = encrypt_block(block, &keys.public, encoding, &mut rng).unwrap();
+            assert_eq!(decryptor.decrypt_block(c1, c2, encoding).unwrap(), block);
+            let blinded = decryptor.decrypt_block_blinded(c1, c2, encoding, &mut blinding);
+            assert_eq!(blinded.unwrap(), block);
+        }
+    }
+}
+
+#[test]
+fn test_context_errors() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    let small_key = Key {
+        prime: 11,
+        root: 2,
+        value: 3,
+    };
+    assert!(matches!(
+        EncryptContext::new(&PublicKey::from(small_key)),
+        Err(CryptError::MalformedKey)
+    ));
+
+    let mut bad_exponent = keys.private.key().clone();
+    bad_exponent.value = bad_exponent.prime;
+    assert!(matches!(
+        DecryptContext::new(&PrivateKey::from(bad_exponent)),
+        Err(CryptError::MalformedKey)
+    ));
+
+    // errors are the same as with the free functions
+    let qr = Encoding::QuadraticResidue;
+    let decryptor = DecryptContext::new(&other_keys.private).unwrap();
+    let (c1, c2) = encrypt_block(0x1234_5678, &keys.public, qr, &mut rng).unwrap();
+    assert!(matches!(
+        decryptor.decrypt_block(c1, c2, qr),
+        Err(CryptError::WrongKey)
+    ));
+    assert!(matches!(
+        decryptor.decrypt_block(0, c2, qr),
+        Err(CryptError::OutOfRange)
+    ));
+
+    let mut other_blinding = Blinding::new(&keys.private, &mut rng);
+    assert!(matches!(
+        decryptor.decrypt_block_blinded(c1, c2, qr, &mut other_blinding),
+        Err(CryptError::IncompatibleKeys)
+    ));
+}
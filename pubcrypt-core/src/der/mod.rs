@@ -0,0 +1,178 @@
+/*!
+ * A minimal subset of DER, the Distinguished Encoding Rules of ASN.1, used to encode keys as
+ * self-describing structures instead of values at fixed offsets (see `Key::to_der`).
+ *
+ * Only SEQUENCEs and non-negative INTEGERs that fit in a `Num` are supported. Each value is a tag
+ * byte, a length, and the contents. Lengths under 128 take one byte; longer ones take a byte of
+ * `0x80` plus the number of length bytes, followed by the big-endian length. As DER requires,
+ * lengths and integers use as few bytes as possible, so every value has exactly one encoding, and
+ * `Reader` rejects any other.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{KeyError, Num, NUM_BYTES};
+
+/**
+ * The tag of a SEQUENCE, whose contents are other values.
+ */
+pub const SEQUENCE_TAG: u8 = 0x30;
+
+/**
+ * The tag of an INTEGER, whose contents are a big-endian two's complement number.
+ */
+pub const INTEGER_TAG: u8 = 0x02;
+
+const LONG_LENGTH: u8 = 0x80;
+
+/**
+ * Append the INTEGER `value` to `out`.
+ */
+pub fn write_integer(out: &mut Vec<u8>, value: Num) {
+    let bytes = value.to_be_bytes();
+    let start = bytes
+        .iter()
+        .position(|&byte| byte != 0)
+        .unwrap_or(NUM_BYTES - 1);
+
+    // a leading 1 bit would make the number negative, so it needs a zero byte before it
+    let mut contents = Vec::with_capacity(NUM_BYTES + 1);
+    if bytes[start] & 0x80 != 0 {
+        contents.push(0);
+    }
+    contents.extend_from_slice(&bytes[start..]);
+    write_value(out, INTEGER_TAG, &contents);
+}
+
+/**
+ * Append a SEQUENCE containing the already-encoded values `contents` to `out`.
+ */
+pub fn write_sequence(out: &mut Vec<u8>, contents: &[u8]) {
+    write_value(out, SEQUENCE_TAG, contents);
+}
+
+/**
+ * Append a value with the given tag and contents to `out`.
+ */
+fn write_value(out: &mut Vec<u8>, tag: u8, contents: &[u8]) {
+    out.push(tag);
+    let len = contents.len();
+    if len < LONG_LENGTH as usize {
+        out.push(len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let start = len_bytes.iter().position(|&byte| byte != 0).unwrap();
+        out.push(LONG_LENGTH | (len_bytes.len() - start) as u8);
+        out.extend_from_slice(&len_bytes[start..]);
+    }
+    out.extend_from_slice(contents);
+}
+
+/**
+ * Check whether `bytes` is a single SEQUENCE with nothing after it. Its contents aren't checked.
+ */
+pub fn is_sequence(bytes: &[u8]) -> bool {
+    let mut reader = Reader::new(bytes);
+    reader.read_sequence().is_ok() && reader.finish().is_ok()
+}
+
+/**
+ * Reads DER values from a byte slice in order. Every method returns `KeyError::InvalidDer` if the
+ * next value isn't what was asked for, or isn't valid DER.
+ */
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /**
+     * Create a reader for the values in `bytes`.
+     */
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /**
+     * Read a SEQUENCE, returning a reader for the values inside it.
+     */
+    pub fn read_sequence(&mut self) -> Result<Reader<'a>, KeyError> {
+        self.read_value(SEQUENCE_TAG).map(Reader::new)
+    }
+
+    /**
+     * Read a non-negative INTEGER, returning `KeyError::NumberTooLarge` if it doesn't fit in a
+     * `Num`.
+     */
+    pub fn read_integer(&mut self) -> Result<Num, KeyError> {
+        let contents = match self.read_value(INTEGER_TAG)? {
+            // a zero byte is only allowed to keep the next byte's top bit from meaning negative
+            [0, next, ..] if next & 0x80 == 0 => return Err(KeyError::InvalidDer),
+            [0, rest @ ..] if !rest.is_empty() => rest,
+            [first, ..] if first & 0x80 != 0 => return Err(KeyError::InvalidDer),
+            [] => return Err(KeyError::InvalidDer),
+            contents => contents,
+        };
+        if contents.len() > NUM_BYTES {
+            return Err(KeyError::NumberTooLarge);
+        }
+
+        let mut buf = [0_u8; NUM_BYTES];
+        buf[NUM_BYTES - contents.len()..].copy_from_slice(contents);
+        Ok(Num::from_be_bytes(buf))
+    }
+
+    /**
+     * Check that every value has been read.
+     */
+    pub fn finish(self) -> Result<(), KeyError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(KeyError::InvalidDer)
+        }
+    }
+
+    /**
+     * Read a value with the given tag, returning its contents.
+     */
+    fn read_value(&mut self, tag: u8) -> Result<&'a [u8], KeyError> {
+        let (&actual_tag, rest) = self.bytes.split_first().ok_or(KeyError::InvalidDer)?;
+        let (&first, mut rest) = rest.split_first().ok_or(KeyError::InvalidDer)?;
+        if actual_tag != tag {
+            return Err(KeyError::InvalidDer);
+        }
+
+        let len = if first < LONG_LENGTH {
+            first as usize
+        } else {
+            let len_bytes = (first & !LONG_LENGTH) as usize;
+            if len_bytes == 0 || len_bytes > std::mem::size_of::<usize>() || rest.len() < len_bytes
+            {
+                return Err(KeyError::InvalidDer);
+            }
+
+            let (len_buf, after) = rest.split_at(len_bytes);
+            rest = after;
+            let len = len_buf
+                .iter()
+                .fold(0_usize, |len, &byte| len << 8 | byte as usize);
+
+            // the long form is only allowed when the short one can't hold the length, and
+            // without leading zero bytes
+            if len < LONG_LENGTH as usize || len_buf[0] == 0 {
+                return Err(KeyError::InvalidDer);
+            }
+            len
+        };
+
+        if rest.len() < len {
+            return Err(KeyError::InvalidDer);
+        }
+
+        let (contents, rest) = rest.split_at(len);
+        self.bytes = rest;
+        Ok(contents)
+    }
+}
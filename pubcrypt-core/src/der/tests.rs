@@ -0,0 +1,115 @@
+use super::*;
+use crate::{
+    cipher::{self, ElGamal, PublicKeyCipher},
+    Algorithm, Key, KeyPair,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_integers() {
+    let vectors: [(Num, &[u8]); 5] = [
+        (0, &[0x02, 0x01, 0x00]),
+        (0x7f, &[0x02, 0x01, 0x7f]),
+        (0x80, &[0x02, 0x02, 0x00, 0x80]),
+        (0x0100, &[0x02, 0x02, 0x01, 0x00]),
+        (0x1234_5678, &[0x02, 0x04, 0x12, 0x34, 0x56, 0x78]),
+    ];
+    for (value, encoded) in vectors {
+        let mut out = Vec::new();
+        write_integer(&mut out, value);
+        assert_eq!(out, encoded);
+
+        let mut reader = Reader::new(encoded);
+        assert_eq!(reader.read_integer(), Ok(value));
+        assert_eq!(reader.finish(), Ok(()));
+    }
+
+    let mut out = Vec::new();
+    write_integer(&mut out, Num::MAX);
+    assert_eq!(out.len(), NUM_BYTES + 3);
+    assert_eq!(Reader::new(&out).read_integer(), Ok(Num::MAX));
+
+    // non-minimal, negative, empty, and mistagged integers are rejected
+    let invalid: [&[u8]; 5] = [
+        &[0x02, 0x02, 0x00, 0x7f],
+        &[0x02, 0x01, 0x80],
+        &[0x02, 0x00],
+        &[0x30, 0x01, 0x00],
+        &[0x02, 0x02, 0x01],
+    ];
+    for encoded in invalid {
+        assert_eq!(
+            Reader::new(encoded).read_integer(),
+            Err(KeyError::InvalidDer),
+            "{:?}",
+            encoded
+        );
+    }
+
+    // valid integers that don't fit in a `Num` get an error of their own
+    let mut too_large = vec![0x02, NUM_BYTES as u8 + 1, 0x01];
+    too_large.extend_from_slice(&[0; NUM_BYTES]);
+    assert_eq!(
+        Reader::new(&too_large).read_integer(),
+        Err(KeyError::NumberTooLarge)
+    );
+}
+
+#[test]
+fn test_sequences() {
+    let contents = [0x02, 0x01, 0x05].repeat(50);
+    let mut out = Vec::new();
+    write_sequence(&mut out, &contents);
+    assert_eq!(out[..3], [0x30, 0x81, 150]);
+    assert!(is_sequence(&out));
+
+    let mut reader = Reader::new(&out);
+    let mut inner = reader.read_sequence().unwrap();
+    assert_eq!(reader.finish(), Ok(()));
+    for _ in 0..50 {
+        assert_eq!(inner.read_integer(), Ok(5));
+    }
+    assert_eq!(inner.finish(), Ok(()));
+
+    // the long form of a short length isn't DER
+    assert!(is_sequence(&[0x30, 0x01, 0x00]));
+    assert!(!is_sequence(&[0x30, 0x81, 0x01, 0x00]));
+    assert!(!is_sequence(&[0x30, 0x82, 0x00, 0x81]));
+    assert!(!is_sequence(&[0x30, 0x02, 0x00]));
+    assert!(!is_sequence(&[0x30, 0x01, 0x00, 0x00]));
+    assert!(!is_sequence(&[]));
+}
+
+#[test]
+fn test_key_der() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    for key in [keys.public.key(), keys.private.key()] {
+        let encoded = key.to_der();
+        assert_ne!(encoded.len(), Key::KEY_BYTES);
+        assert_eq!(Key::from_der(&encoded).as_ref(), Ok(key));
+
+        // DER keys are read wherever serialized ones are
+        assert_eq!(
+            cipher::read_key_file(&encoded),
+            Ok((Algorithm::ElGamal, &encoded[..]))
+        );
+        let public = ElGamal::deserialize_public_key(&encoded, &mut rng).unwrap();
+        assert_eq!(public.key(), key);
+    }
+
+    let encoded = keys.public.key().to_der();
+    let mut newer = encoded.clone();
+    newer[4] = 2;
+    assert_eq!(Key::from_der(&newer), Err(KeyError::UnsupportedVersion(2)));
+
+    let mut extra = encoded.clone();
+    extra.push(0);
+    assert_eq!(Key::from_der(&extra), Err(KeyError::InvalidDer));
+    assert_eq!(
+        Key::from_der(&encoded[..encoded.len() - 1]),
+        Err(KeyError::InvalidDer)
+    );
+}
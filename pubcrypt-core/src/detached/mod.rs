@@ -0,0 +1,150 @@
+/*!
+ * Detached signature files, which sign a file without wrapping or changing it.
+ *
+ * A detached signature file consists of the magic bytes `PUBCRSIG`, the version byte `VERSION`,
+ * the number of bytes in a `Num` when the file was signed, the `Fingerprint` of the signer's
+ * public key, and a `signature::Signature` on the SHA-256 digest of the signed file, so the digest
+ * can be checked against the output of other tools like `sha256sum`.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    hash::{Sha256, DIGEST_BYTES},
+    signature::{self, Signature, SIGNATURE_BYTES},
+    Fingerprint, KeyError, PrivateKey, PublicKey, FINGERPRINT_BYTES, NUM_BYTES,
+};
+use rand::Rng;
+use std::io::{self, Read};
+
+/**
+ * The bytes that every detached signature file starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRSIG";
+
+/**
+ * The version of the detached signature format written by `DetachedSignature::serialize`.
+ */
+pub const VERSION: u8 = 1;
+
+/**
+ * The number of bytes in a serialized `DetachedSignature`.
+ */
+pub const DETACHED_SIGNATURE_BYTES: usize = FINGERPRINT_START + FINGERPRINT_BYTES + SIGNATURE_BYTES;
+
+const VERSION_START: usize = MAGIC.len();
+const WIDTH_START: usize = VERSION_START + 1;
+const FINGERPRINT_START: usize = WIDTH_START + 1;
+const SIGNATURE_START: usize = FINGERPRINT_START + FINGERPRINT_BYTES;
+
+/**
+ * A signature on a file, stored apart from the file, along with the fingerprint of the key that
+ * made it.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DetachedSignature {
+    pub signer: Fingerprint,
+    pub signature: Signature,
+}
+
+impl DetachedSignature {
+    /**
+     * Sign the contents of `reader` with `key`, using `rng` for the signature's nonce (see
+     * `signature::sign`).
+     */
+    pub fn sign<R: Read, T: Rng>(
+        reader: &mut R,
+        key: &PrivateKey,
+        rng: &mut T,
+    ) -> io::Result<Self> {
+        let digest = digest(reader)?;
+        Ok(Self {
+            signer: key.derive_public().fingerprint(),
+            signature: signature::sign(&digest, key, rng),
+        })
+    }
+
+    /**
+     * Check whether the signature is a valid signature on the contents of `reader` by the owner
+     * of `key`. Returns `false` without reading anything if the signature was made by another
+     * key, according to its fingerprint.
+     */
+    pub fn verify<R: Read>(&self, reader: &mut R, key: &PublicKey) -> io::Result<bool> {
+        if self.signer != key.fingerprint() {
+            return Ok(false);
+        }
+
+        Ok(signature::verify(&digest(reader)?, &self.signature, key))
+    }
+
+    /**
+     * Convert the signature to the bytes of a detached signature file.
+     */
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = MAGIC.to_vec();
+        result.push(VERSION);
+        result.push(NUM_BYTES as u8);
+        result.extend_from_slice(self.signer.as_bytes());
+        result.extend_from_slice(&self.signature.serialize());
+        result
+    }
+
+    /**
+     * Read a detached signature file written by `serialize`. The signature is only checked by
+     * `verify`.
+     *
+     * Returns `KeyError::UnsupportedVersion` if the file is from a newer version, `WrongWidth` if
+     * it was signed with a different size of `Num` (see the `wide` feature), and
+     * `InvalidSignatureFile` if it doesn't start with `MAGIC` or has the wrong length.
+     */
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, KeyError> {
+        if !is_detached_signature(bytes) || bytes.len() <= WIDTH_START {
+            return Err(KeyError::InvalidSignatureFile);
+        }
+
+        let version = bytes[VERSION_START];
+        if version != VERSION {
+            return Err(KeyError::UnsupportedVersion(version));
+        }
+
+        if bytes[WIDTH_START] as usize != NUM_BYTES {
+            return Err(KeyError::WrongWidth);
+        }
+
+        if bytes.len() != DETACHED_SIGNATURE_BYTES {
+            return Err(KeyError::InvalidSignatureFile);
+        }
+
+        let mut signer = [0_u8; FINGERPRINT_BYTES];
+        let mut signature = [0_u8; SIGNATURE_BYTES];
+        signer.copy_from_slice(&bytes[FINGERPRINT_START..SIGNATURE_START]);
+        signature.copy_from_slice(&bytes[SIGNATURE_START..]);
+
+        Ok(Self {
+            signer: Fingerprint::from(signer),
+            signature: Signature::deserialize(&signature),
+        })
+    }
+}
+
+/**
+ * Check whether `bytes` starts like a detached signature file.
+ */
+pub fn is_detached_signature(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/**
+ * Compute the SHA-256 digest of everything in `reader`.
+ */
+fn digest<R: Read>(reader: &mut R) -> io::Result<[u8; DIGEST_BYTES]> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0_u8; 8192];
+    loop {
+        match reader.read(&mut buf)? {
+            0 => return Ok(hasher.finalize()),
+            count => hasher.update(&buf[..count]),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use super::*;
+use crate::{hash::sha256, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_sign_and_verify() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+    let file = vec![0x42_u8; 20_000];
+
+    let signed = DetachedSignature::sign(&mut &file[..], &keys.private, &mut rng).unwrap();
+    assert_eq!(signed.signer, keys.public.fingerprint());
+    assert!(signed.verify(&mut &file[..], &keys.public).unwrap());
+
+    // the signature is on the plain digest of the file
+    assert!(signature::verify(
+        &sha256(&file),
+        &signed.signature,
+        &keys.public
+    ));
+
+    let mut changed = file.clone();
+    changed[12_345] ^= 1;
+    assert!(!signed.verify(&mut &changed[..], &keys.public).unwrap());
+    assert!(!signed.verify(&mut &file[..], &other_keys.public).unwrap());
+}
+
+#[test]
+fn test_serialize() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let signed = DetachedSignature::sign(&mut &b"file"[..], &keys.private, &mut rng).unwrap();
+
+    let bytes = signed.serialize();
+    assert_eq!(bytes.len(), DETACHED_SIGNATURE_BYTES);
+    assert!(is_detached_signature(&bytes));
+    assert_eq!(DetachedSignature::deserialize(&bytes), Ok(signed));
+
+    assert_eq!(
+        DetachedSignature::deserialize(&bytes[..bytes.len() - 1]),
+        Err(KeyError::InvalidSignatureFile)
+    );
+    assert_eq!(
+        DetachedSignature::deserialize(&bytes[1..]),
+        Err(KeyError::InvalidSignatureFile)
+    );
+
+    let mut newer = bytes.clone();
+    newer[VERSION_START] = VERSION + 1;
+    assert_eq!(
+        DetachedSignature::deserialize(&newer),
+        Err(KeyError::UnsupportedVersion(VERSION + 1))
+    );
+
+    let mut wider = bytes;
+    wider[WIDTH_START] = NUM_BYTES as u8 * 2;
+    assert_eq!(
+        DetachedSignature::deserialize(&wider),
+        Err(KeyError::WrongWidth)
+    );
+}
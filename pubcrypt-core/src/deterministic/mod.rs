@@ -0,0 +1,140 @@
+/*!
+ * Deterministic encryption, for reproducible test fixtures and deduplication.
+ *
+ * **WARNING**: ElGamal's security depends on every block being encrypted with a fresh random
+ * exponent. Deterministic encryption derives the exponents from a caller-provided nonce instead,
+ * so encrypting the same plaintext with the same key and nonce always gives the same ciphertext,
+ * and anyone can tell when that happens. Reusing a nonce for two different plaintexts is worse:
+ * blocks at the same position share an exponent, so the ratio of their `c2` values is the ratio
+ * of the plaintext blocks. Only use this when those leaks are acceptable, and never reuse a nonce
+ * for different data.
+ *
+ * The exponent for block `i` is derived from the SHA-256 hash of the nonce and `i`, and all other
+ * randomness (padding, the OAEP transform, MAC key shares, CTR and hybrid nonces) comes from a
+ * `DeterministicRng` seeded with the nonce. See `stream::EncryptWriter::deterministic`.
+ *
+ * Key generation can be made deterministic the same way with `KeyPair::from_seed`, which is
+ * secure as long as the seed is secret and random.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    hash::{Sha256, DIGEST_BYTES},
+    signature::hash_to_exponent,
+    Num,
+};
+use rand::{Error, RngCore};
+
+/**
+ * A random number generator whose output is determined entirely by its nonce: SHA-256 in counter
+ * mode, keyed by the hash of the nonce.
+ *
+ * Unlike the generators in `rand`, its output is guaranteed not to change between versions of
+ * this crate, so fixtures made with it stay reproducible.
+ */
+#[derive(Debug, Clone)]
+pub struct DeterministicRng {
+    key: [u8; DIGEST_BYTES],
+    counter: u64,
+    block: [u8; DIGEST_BYTES],
+    block_pos: usize,
+}
+
+impl DeterministicRng {
+    /**
+     * Create a generator for `nonce`.
+     */
+    pub fn new(nonce: &[u8]) -> Self {
+        Self::with_label(b"pubcrypt deterministic rng", nonce)
+    }
+
+    /**
+     * Create a generator for `nonce` whose output is independent of generators with other labels.
+     */
+    pub(crate) fn with_label(label: &[u8], nonce: &[u8]) -> Self {
+        Self {
+            key: derive_key(label, nonce),
+            counter: 0,
+            block: [0_u8; DIGEST_BYTES],
+            block_pos: DIGEST_BYTES,
+        }
+    }
+}
+
+impl RngCore for DeterministicRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0_u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_be_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0_u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_be_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for b in dest {
+            if self.block_pos == DIGEST_BYTES {
+                let mut hasher = Sha256::new();
+                hasher.update(&self.key);
+                hasher.update(&self.counter.to_be_bytes());
+                self.block = hasher.finalize();
+                self.block_pos = 0;
+                self.counter += 1;
+            }
+
+            *b = self.block[self.block_pos];
+            self.block_pos += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/**
+ * Derives the encryption exponent for each block from a nonce.
+ */
+#[derive(Debug, Clone)]
+pub struct ExponentSource {
+    key: [u8; DIGEST_BYTES],
+}
+
+impl ExponentSource {
+    /**
+     * Create a source of exponents for `nonce`.
+     */
+    pub fn new(nonce: &[u8]) -> Self {
+        Self {
+            key: derive_key(b"pubcrypt deterministic exponent", nonce),
+        }
+    }
+
+    /**
+     * Get the exponent for block `index` of a ciphertext under a key with the given prime, which
+     * is in the range `[1, prime)`.
+     */
+    pub fn exponent(&self, index: u64, prime: Num) -> Num {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.key);
+        hasher.update(&index.to_be_bytes());
+        hash_to_exponent(&hasher.finalize(), prime - 1).value() + 1
+    }
+}
+
+/**
+ * Hash `nonce` with `label`, so that each use of the nonce is independent of the others.
+ */
+fn derive_key(label: &[u8], nonce: &[u8]) -> [u8; DIGEST_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.update(label);
+    hasher.update(&(nonce.len() as u64).to_be_bytes());
+    hasher.update(nonce);
+    hasher.finalize()
+}
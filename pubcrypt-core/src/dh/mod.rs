@@ -0,0 +1,133 @@
+/*!
+ * Diffie-Hellman key agreement, using the same keys as encryption.
+ *
+ * A public key's value is `root^x mod p`, so two key pairs in the same group (see
+ * `KeyPair::generate_in_group`) can each combine their private exponent with the other's public
+ * key to arrive at the same value `root^(x * y) mod p`, without sending anything but their public
+ * keys. That value is then passed through HKDF-SHA-256 (RFC 5869) to produce uniformly random key
+ * material.
+ *
+ * Agreement only tells each side that it shares a secret with the owner of the public key it used,
+ * so if a public key was swapped in transit, each side shares a secret with whoever swapped it.
+ * Comparing a `SharedSecret::confirmation` over a channel that can't be tampered with, such as a
+ * phone call, rules that out.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    mac::{Hmac, MAC_BYTES},
+    mnemonic::{self, BITS_PER_WORD},
+    CryptError, PrivateKey, PublicKey,
+};
+use math::mod_exp;
+use std::fmt;
+
+/**
+ * The number of bytes in a `SharedSecret`.
+ */
+pub const SHARED_SECRET_BYTES: usize = MAC_BYTES;
+
+/**
+ * The number of words in a `SharedSecret::confirmation`.
+ */
+pub const CONFIRMATION_WORDS: usize = 4;
+
+const EXTRACT_SALT: &[u8] = b"pubcrypt dh shared secret";
+const CONFIRMATION_INFO: &[u8] = b"pubcrypt dh confirmation";
+
+/**
+ * Key material shared between the owners of two key pairs.
+ */
+#[derive(Clone, PartialEq, Eq)]
+pub struct SharedSecret([u8; SHARED_SECRET_BYTES]);
+
+impl SharedSecret {
+    /**
+     * Get the raw key material.
+     */
+    pub fn as_bytes(&self) -> &[u8; SHARED_SECRET_BYTES] {
+        &self.0
+    }
+
+    /**
+     * Fill `output` with key material derived from the secret for the purpose described by
+     * `info`, using HKDF's expand step. Different `info` values give independent keys, so one
+     * secret can key several algorithms.
+     *
+     * Panics if `output` is longer than `255 * SHARED_SECRET_BYTES` bytes.
+     */
+    pub fn expand(&self, info: &[u8], output: &mut [u8]) {
+        assert!(output.len() <= 255 * SHARED_SECRET_BYTES);
+
+        // each block is keyed by the secret and chained to the previous block
+        let mut block = [0_u8; MAC_BYTES];
+        for (i, chunk) in output.chunks_mut(MAC_BYTES).enumerate() {
+            let mut mac = Hmac::new(&self.0);
+            if i > 0 {
+                mac.update(&block);
+            }
+            mac.update(info);
+            mac.update(&[i as u8 + 1]);
+
+            block = mac.finalize();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+
+    /**
+     * Get a short authentication string of `CONFIRMATION_WORDS` words from the BIP 39 word list
+     * (see `mnemonic`), which both sides can read to each other to check that they derived the
+     * same secret. It's expanded from the secret with its own `info`, so it reveals nothing about
+     * keys expanded for other purposes.
+     */
+    pub fn confirmation(&self) -> Vec<&'static str> {
+        let mut bytes = [0_u8; 8];
+        self.expand(CONFIRMATION_INFO, &mut bytes);
+        let bits = u64::from_be_bytes(bytes);
+
+        let mask = (1 << BITS_PER_WORD) - 1;
+        (0..CONFIRMATION_WORDS)
+            .map(|i| {
+                let index = (bits >> (i * BITS_PER_WORD)) & mask;
+                mnemonic::words().nth(index as usize).unwrap()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the secret itself is never printed
+        f.write_str("SharedSecret(..)")
+    }
+}
+
+/**
+ * Derive the secret shared between the owner of `private` and the owner of `public`.
+ *
+ * Returns `CryptError::IncompatibleKeys` if the keys aren't in the same group, and
+ * `CryptError::MalformedKey` if the public key's value is 0, 1, or `p - 1`, which would make the
+ * secret predictable.
+ */
+pub fn derive_shared_secret(
+    private: &PrivateKey,
+    public: &PublicKey,
+) -> Result<SharedSecret, CryptError> {
+    let (private, public) = (private.key(), public.key());
+    if private.prime != public.prime || private.root != public.root {
+        return Err(CryptError::IncompatibleKeys);
+    }
+
+    let prime = public.prime;
+    if public.value <= 1 || public.value >= prime - 1 {
+        return Err(CryptError::MalformedKey);
+    }
+
+    let shared = mod_exp(public.value, private.value, prime);
+    let mut mac = Hmac::new(EXTRACT_SALT);
+    mac.update(&prime.to_be_bytes());
+    mac.update(&shared.to_be_bytes());
+    Ok(SharedSecret(mac.finalize()))
+}
@@ -0,0 +1,106 @@
+use super::*;
+use crate::{Key, KeyPair, NUM_BYTES};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_shared_secret_agreement() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let alice = KeyPair::generate(&mut rng).unwrap();
+    let bob = KeyPair::generate_in_group(&alice.public.key().group(), &mut rng);
+    let carol = KeyPair::generate_in_group(&alice.public.key().group(), &mut rng);
+
+    let alice_secret = derive_shared_secret(&alice.private, &bob.public).unwrap();
+    let bob_secret = derive_shared_secret(&bob.private, &alice.public).unwrap();
+    assert_eq!(alice_secret, bob_secret);
+
+    // other pairs of keys share different secrets
+    let carol_secret = derive_shared_secret(&carol.private, &alice.public).unwrap();
+    assert_ne!(carol_secret, alice_secret);
+
+    // keys in different groups can't agree on anything
+    let outsider = KeyPair::generate(&mut rng).unwrap();
+    assert!(matches!(
+        derive_shared_secret(&alice.private, &outsider.public),
+        Err(CryptError::IncompatibleKeys)
+    ));
+}
+
+#[test]
+fn test_confirmation() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let alice = KeyPair::generate(&mut rng).unwrap();
+    let bob = KeyPair::generate_in_group(&alice.public.key().group(), &mut rng);
+    let carol = KeyPair::generate_in_group(&alice.public.key().group(), &mut rng);
+
+    let alice_secret = derive_shared_secret(&alice.private, &bob.public).unwrap();
+    let bob_secret = derive_shared_secret(&bob.private, &alice.public).unwrap();
+    let confirmation = alice_secret.confirmation();
+    assert_eq!(confirmation.len(), CONFIRMATION_WORDS);
+    assert_eq!(bob_secret.confirmation(), confirmation);
+    assert!(confirmation
+        .iter()
+        .all(|word| mnemonic::words().any(|w| w == *word)));
+
+    // a swapped public key gives a different confirmation
+    let carol_secret = derive_shared_secret(&alice.private, &carol.public).unwrap();
+    assert_ne!(carol_secret.confirmation(), confirmation);
+}
+
+#[test]
+fn test_degenerate_public_keys() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let key = keys.public.key();
+
+    for &value in &[0, 1, key.prime() - 1] {
+        let mut bytes = key.serialize();
+        bytes[NUM_BYTES * 2..].copy_from_slice(&value.to_be_bytes());
+        let public = PublicKey::from(Key::deserialize(&bytes));
+
+        assert!(matches!(
+            derive_shared_secret(&keys.private, &public),
+            Err(CryptError::MalformedKey)
+        ));
+    }
+}
+
+#[test]
+fn test_expand() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let alice = KeyPair::generate(&mut rng).unwrap();
+    let bob = KeyPair::generate_in_group(&alice.public.key().group(), &mut rng);
+    let secret = derive_shared_secret(&alice.private, &bob.public).unwrap();
+
+    // longer outputs extend shorter ones, and different purposes give unrelated keys
+    let mut short = [0_u8; 16];
+    let mut long = [0_u8; 100];
+    let mut other = [0_u8; 16];
+    secret.expand(b"encryption", &mut short);
+    secret.expand(b"encryption", &mut long);
+    secret.expand(b"authentication", &mut other);
+
+    assert_eq!(short, long[..16]);
+    assert_ne!(short, other);
+    assert_ne!(long[..32], long[32..64]);
+    assert_eq!(format!("{:?}", secret), "SharedSecret(..)");
+}
+
+#[test]
+fn test_expand_vector() {
+    // test case 1 from RFC 5869, starting from its pseudorandom key
+    let prk = [
+        0x07, 0x77, 0x09, 0x36, 0x2c, 0x2e, 0x32, 0xdf, 0x0d, 0xdc, 0x3f, 0x0d, 0xc4, 0x7b, 0xba,
+        0x63, 0x90, 0xb6, 0xc7, 0x3b, 0xb5, 0x0f, 0x9c, 0x31, 0x22, 0xec, 0x84, 0x4a, 0xd7, 0xc2,
+        0xb3, 0xe5,
+    ];
+    let info: Vec<u8> = (0xf0..=0xf9).collect();
+    let mut okm = [0_u8; 42];
+    SharedSecret(prk).expand(&info, &mut okm);
+
+    let hex: String = okm.iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(
+        hex,
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+    );
+}
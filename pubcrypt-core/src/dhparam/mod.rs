@@ -0,0 +1,58 @@
+/*!
+ * Diffie-Hellman parameters in the format of PKCS#3, which is what OpenSSL's `dhparam` command
+ * reads and writes, so that groups can be shared with OpenSSL.
+ *
+ * The parameters are a DER SEQUENCE of the group's prime and base (a `Group`'s prime and root, or
+ * a `Key`'s, via `Key::group`), optionally followed by the length of private values, which is
+ * ignored. In PEM text, they're labeled `PEM_LABEL`; see `armor::pem_labeled`.
+ *
+ * OpenSSL's groups are much larger than a `Num` and its bases usually only generate the quadratic
+ * residues, so its groups are rejected by `from_der` and `Group::validate`, respectively, but
+ * OpenSSL can read and check the groups that pubcrypt exports.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{der, Group, KeyError};
+
+/**
+ * The label of DH parameters in PEM text.
+ */
+pub const PEM_LABEL: &str = "DH PARAMETERS";
+
+/**
+ * Encode `group` as PKCS#3 DH parameters, without a private value length.
+ */
+pub fn to_der(group: &Group) -> Vec<u8> {
+    let mut contents = Vec::new();
+    der::write_integer(&mut contents, group.prime());
+    der::write_integer(&mut contents, group.root());
+
+    let mut result = Vec::new();
+    der::write_sequence(&mut result, &contents);
+    result
+}
+
+/**
+ * Read a group from PKCS#3 DH parameters. Like `Group::new`, this doesn't check the group.
+ *
+ * Returns `KeyError::NumberTooLarge` if the prime or base doesn't fit in a `Num`, and
+ * `KeyError::InvalidDer` if the bytes aren't DH parameters.
+ */
+pub fn from_der(bytes: &[u8]) -> Result<Group, KeyError> {
+    let mut reader = der::Reader::new(bytes);
+    let mut fields = reader.read_sequence()?;
+    reader.finish()?;
+
+    let prime = fields.read_integer()?;
+    let root = fields.read_integer()?;
+    // the private value length only limits the size of exponents, which pubcrypt chooses itself
+    let mut rest = fields.clone();
+    if rest.read_integer().is_ok() {
+        fields = rest;
+    }
+    fields.finish()?;
+
+    Ok(Group::new(prime, root))
+}
@@ -0,0 +1,65 @@
+use super::*;
+use crate::{armor, KeyPair, Num, NUM_BYTES};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_dhparam_round_trip() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let group = keys.public.key().group();
+
+    let encoded = to_der(&group);
+    assert_eq!(from_der(&encoded), Ok(group));
+
+    let text = armor::pem_labeled(PEM_LABEL, &encoded);
+    assert!(text.starts_with("-----BEGIN DH PARAMETERS-----\n"));
+    let decoded = armor::depem_labeled(PEM_LABEL, text.as_bytes()).unwrap();
+    assert_eq!(from_der(&decoded), Ok(group));
+
+    let largest = Group::new(Num::MAX, 2);
+    assert_eq!(from_der(&to_der(&largest)), Ok(largest));
+}
+
+#[test]
+fn test_dhparam_layout() {
+    // SEQUENCE { INTEGER 23, INTEGER 5 }, as written by OpenSSL
+    let encoded = [0x30, 0x06, 0x02, 0x01, 0x17, 0x02, 0x01, 0x05];
+    assert_eq!(to_der(&Group::new(23, 5)), encoded);
+    assert_eq!(from_der(&encoded), Ok(Group::new(23, 5)));
+
+    // a private value length is allowed, but ignored
+    let with_length = [
+        0x30, 0x09, 0x02, 0x01, 0x17, 0x02, 0x01, 0x05, 0x02, 0x01, 0x03,
+    ];
+    assert_eq!(from_der(&with_length), Ok(Group::new(23, 5)));
+}
+
+#[test]
+fn test_invalid_dhparam() {
+    let invalid: [&[u8]; 4] = [
+        &[],
+        &[0x30, 0x03, 0x02, 0x01, 0x17],
+        &[
+            0x30, 0x0c, 0x02, 0x01, 0x17, 0x02, 0x01, 0x05, 0x02, 0x01, 0x03, 0x02, 0x01, 0x00,
+        ],
+        &[0x30, 0x06, 0x02, 0x01, 0x17, 0x02, 0x01, 0x05, 0x00],
+    ];
+    for encoded in invalid {
+        assert_eq!(
+            from_der(encoded),
+            Err(KeyError::InvalidDer),
+            "{:?}",
+            encoded
+        );
+    }
+
+    // a prime from OpenSSL is far too large for a `Num`
+    let mut prime = vec![0x02, NUM_BYTES as u8 + 1, 0x01];
+    prime.extend_from_slice(&[0xff; NUM_BYTES]);
+    let mut contents = prime;
+    contents.extend_from_slice(&[0x02, 0x01, 0x02]);
+    let mut encoded = vec![0x30, contents.len() as u8];
+    encoded.extend_from_slice(&contents);
+    assert_eq!(from_der(&encoded), Err(KeyError::NumberTooLarge));
+}
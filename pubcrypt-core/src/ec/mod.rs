@@ -0,0 +1,485 @@
+/*!
+ * ElGamal over an elliptic curve, as an alternative `cipher::PublicKeyCipher` to ElGamal over
+ * the integers.
+ *
+ * Every key uses the same fixed curve `y^2 = x^3 + B` over the integers modulo `PRIME`, which has
+ * a prime number of points, `ORDER`, so every point but the point at infinity generates the whole
+ * group. It's a toy version of curves like secp256k1: `PRIME` is the largest prime that fits in a
+ * `Num` and gives such a curve with an order less than the prime. Discrete logarithms on a curve
+ * have no known shortcuts like the index calculus, so a curve over a field of a given size is
+ * much harder to break than the multiplicative group of a prime of the same size.
+ *
+ * A private key is a scalar `x`, and its public key is the point `x * GENERATOR`. Each block is
+ * mapped to a point `M` by searching for an x-coordinate that starts with the block and ends in
+ * `ENCODING_BITS` bits of counter, and encrypts to the pair of points `k * GENERATOR` and
+ * `M + k * Q` for a random `k`, where `Q` is the public key.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    cipher::PublicKeyCipher, Algorithm, Block, CryptError, KeyError, KeyGenError, Num, BLOCK_BYTES,
+    NUM_BYTES,
+};
+use math::{modint::ModInt, sqrt_mod};
+use rand::Rng;
+use std::ops::{Add, Neg, Sub};
+
+/**
+ * The prime modulus of the curve's field.
+ */
+#[cfg(not(feature = "wide"))]
+pub const PRIME: Num = 18446744073709551163;
+#[cfg(feature = "wide")]
+pub const PRIME: Num = 340282366920938463463374607431768196021;
+
+/**
+ * The constant term of the curve's equation.
+ */
+#[cfg(not(feature = "wide"))]
+pub const B: Num = 2;
+#[cfg(feature = "wide")]
+pub const B: Num = 13;
+
+/**
+ * The number of points on the curve, including the point at infinity, which is prime.
+ */
+#[cfg(not(feature = "wide"))]
+pub const ORDER: Num = 18446744066322874501;
+#[cfg(feature = "wide")]
+pub const ORDER: Num = 340282366920938463441538754438269807651;
+
+/**
+ * The base point that keys are multiples of.
+ */
+#[cfg(not(feature = "wide"))]
+pub const GENERATOR: Point = Point::Affine {
+    x: 2,
+    y: 16929852090725970766,
+};
+#[cfg(feature = "wide")]
+pub const GENERATOR: Point = Point::Affine {
+    x: 2,
+    y: 230977102483316961469280134347615887420,
+};
+
+/**
+ * The number of low bits of a point's x-coordinate that are searched to map a block to a point.
+ * Each candidate x-coordinate is on the curve about half the time, so all of them fail with a
+ * probability of about `2^-(2^ENCODING_BITS)`.
+ */
+pub const ENCODING_BITS: usize = 8;
+
+const BITS_PER_BYTE: usize = 8;
+const NUM_BITS: usize = NUM_BYTES * BITS_PER_BYTE;
+
+/**
+ * The number of bytes in a serialized point.
+ */
+pub const POINT_BYTES: usize = NUM_BYTES * 2;
+
+/**
+ * A point on the curve.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Point {
+    /**
+     * The point at infinity, which is the group's identity.
+     */
+    Infinity,
+    /**
+     * A point with the given coordinates, which are less than `PRIME`.
+     */
+    Affine { x: Num, y: Num },
+}
+
+/**
+ * Get `value` as an element of the curve's field.
+ */
+fn field(value: Num) -> ModInt {
+    ModInt::new(value, PRIME)
+}
+
+/**
+ * Get the right-hand side of the curve's equation, `x^3 + B`.
+ */
+fn curve_rhs(x: ModInt) -> ModInt {
+    x * x * x + field(B)
+}
+
+impl Point {
+    /**
+     * Create the point with the given coordinates, or `None` if it isn't on the curve.
+     */
+    pub fn new(x: Num, y: Num) -> Option<Self> {
+        let point = Self::Affine { x, y };
+        if point.is_on_curve() {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
+    /**
+     * Check whether the point is the point at infinity or has reduced coordinates that satisfy
+     * the curve's equation.
+     */
+    pub fn is_on_curve(self) -> bool {
+        match self {
+            Self::Infinity => true,
+            Self::Affine { x, y } => {
+                x < PRIME && y < PRIME && field(y) * field(y) == curve_rhs(field(x))
+            }
+        }
+    }
+
+    /**
+     * Multiply the point by `scalar`, i.e., add it to itself `scalar` times, using a Montgomery
+     * ladder, which does one addition and one doubling for every bit of the scalar.
+     */
+    pub fn multiply(self, scalar: Num) -> Self {
+        let mut low = Self::Infinity;
+        let mut high = self;
+
+        // invariant: `high == low + self`, with `low` multiplied by the bits seen so far
+        for i in (0..NUM_BITS).rev() {
+            if (scalar >> i) & 1 == 1 {
+                low = low + high;
+                high = high + high;
+            } else {
+                high = low + high;
+                low = low + low;
+            }
+        }
+
+        low
+    }
+
+    /**
+     * Convert the point to bytes. The point at infinity is written as zeros, which aren't the
+     * coordinates of any point on the curve, since `B` isn't 0.
+     */
+    pub fn serialize(self) -> [u8; POINT_BYTES] {
+        let mut result = [0_u8; POINT_BYTES];
+        if let Self::Affine { x, y } = self {
+            result[..NUM_BYTES].copy_from_slice(&x.to_be_bytes());
+            result[NUM_BYTES..].copy_from_slice(&y.to_be_bytes());
+        }
+
+        result
+    }
+
+    /**
+     * Read a point written by `serialize`, or `None` if it isn't on the curve.
+     */
+    pub fn deserialize(bytes: &[u8; POINT_BYTES]) -> Option<Self> {
+        if bytes.iter().all(|&b| b == 0) {
+            return Some(Self::Infinity);
+        }
+
+        Self::new(read_num(bytes, 0), read_num(bytes, 1))
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let ((x1, y1), (x2, y2)) = match (self, other) {
+            (Self::Infinity, point) | (point, Self::Infinity) => return point,
+            (Self::Affine { x: x1, y: y1 }, Self::Affine { x: x2, y: y2 }) => {
+                ((field(x1), field(y1)), (field(x2), field(y2)))
+            }
+        };
+
+        // the field's modulus is prime, so every nonzero element has an inverse
+        let slope = if x1 != x2 {
+            (y2 - y1) * (x2 - x1).inverse().unwrap()
+        } else if y1 == -y2 {
+            // the points are each other's negation, including a point with `y == 0` doubled
+            return Self::Infinity;
+        } else {
+            // the tangent at a point being doubled; `a` is 0, so it's `3x^2 / 2y`
+            let three = field(3);
+            three * x1 * x1 * (y1 + y1).inverse().unwrap()
+        };
+
+        let x3 = slope * slope - x1 - x2;
+        let y3 = slope * (x1 - x3) - y1;
+        Self::Affine {
+            x: x3.value(),
+            y: y3.value(),
+        }
+    }
+}
+
+impl Neg for Point {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self {
+            Self::Infinity => Self::Infinity,
+            Self::Affine { x, y } => Self::Affine {
+                x,
+                y: (-field(y)).value(),
+            },
+        }
+    }
+}
+
+impl Sub for Point {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+/**
+ * Read the `index`th `Num` from `bytes`.
+ */
+fn read_num(bytes: &[u8], index: usize) -> Num {
+    let mut buf = [0_u8; NUM_BYTES];
+    buf.copy_from_slice(&bytes[index * NUM_BYTES..(index + 1) * NUM_BYTES]);
+    Num::from_be_bytes(buf)
+}
+
+/**
+ * An elliptic-curve ElGamal public key: a point other than the point at infinity.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EcPublicKey {
+    point: Point,
+}
+
+impl EcPublicKey {
+    /**
+     * The number of bytes in a serialized public key.
+     */
+    pub const KEY_BYTES: usize = POINT_BYTES;
+
+    /**
+     * Get the key's point.
+     */
+    pub fn point(&self) -> Point {
+        self.point
+    }
+
+    /**
+     * Convert key to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; Self::KEY_BYTES] {
+        self.point.serialize()
+    }
+
+    /**
+     * Read key from serialized bytes, and check that its point is on the curve and isn't the
+     * point at infinity. Since the curve's order is prime, any such point is a multiple of
+     * `GENERATOR`.
+     */
+    pub fn try_deserialize(bytes: &[u8; Self::KEY_BYTES]) -> Result<Self, KeyError> {
+        match Point::deserialize(bytes) {
+            None => Err(KeyError::NotOnCurve),
+            Some(Point::Infinity) => Err(KeyError::InvalidValue),
+            Some(point) => Ok(Self { point }),
+        }
+    }
+}
+
+/**
+ * An elliptic-curve ElGamal private key: a scalar in the range `[1, ORDER)`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EcPrivateKey {
+    scalar: Num,
+}
+
+impl EcPrivateKey {
+    /**
+     * The number of bytes in a serialized private key.
+     */
+    pub const KEY_BYTES: usize = NUM_BYTES;
+
+    /**
+     * Generate a private key using `rng`.
+     */
+    pub fn generate<T: Rng>(rng: &mut T) -> Self {
+        Self {
+            scalar: rng.gen_range(1..ORDER),
+        }
+    }
+
+    /**
+     * Get the public key that corresponds to this private key.
+     */
+    pub fn public_key(&self) -> EcPublicKey {
+        EcPublicKey {
+            point: GENERATOR.multiply(self.scalar),
+        }
+    }
+
+    /**
+     * Convert key to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; Self::KEY_BYTES] {
+        self.scalar.to_be_bytes()
+    }
+
+    /**
+     * Read key from serialized bytes, and check that its scalar is in the range `[1, ORDER)`.
+     */
+    pub fn try_deserialize(bytes: &[u8; Self::KEY_BYTES]) -> Result<Self, KeyError> {
+        let scalar = Num::from_be_bytes(*bytes);
+        if scalar == 0 || scalar >= ORDER {
+            return Err(KeyError::InvalidValue);
+        }
+
+        Ok(Self { scalar })
+    }
+}
+
+/**
+ * Map `block` to a point whose x-coordinate is the block followed by `ENCODING_BITS` bits of
+ * counter, taking the first counter for which the point is on the curve.
+ */
+fn encode_block(block: Block) -> Option<Point> {
+    let start = (block as Num) << ENCODING_BITS;
+    (start..start + (1 << ENCODING_BITS)).find_map(|x| {
+        let y = sqrt_mod(curve_rhs(field(x)).value(), PRIME)?;
+        Some(Point::Affine { x, y })
+    })
+}
+
+/**
+ * Recover the block from a point made by `encode_block`, or `None` if the point's x-coordinate
+ * doesn't hold a block.
+ */
+fn decode_block(point: Point) -> Option<Block> {
+    match point {
+        Point::Infinity => None,
+        Point::Affine { x, .. } => {
+            let block = x >> ENCODING_BITS;
+            if block > Block::MAX as Num {
+                None
+            } else {
+                Some(block as Block)
+            }
+        }
+    }
+}
+
+/**
+ * Encrypt `block` with `key`, using a random scalar from `rng`. Returns the pair of points
+ * `(k * GENERATOR, M + k * Q)`.
+ *
+ * Returns `CryptError::OutOfRange` in the vanishingly unlikely case that the block can't be mapped
+ * to a point (see `ENCODING_BITS`).
+ */
+pub fn encrypt_block<T: Rng>(
+    block: Block,
+    key: &EcPublicKey,
+    rng: &mut T,
+) -> Result<(Point, Point), CryptError> {
+    let message = encode_block(block).ok_or(CryptError::OutOfRange)?;
+    let k = rng.gen_range(1..ORDER);
+    Ok((GENERATOR.multiply(k), message + key.point.multiply(k)))
+}
+
+/**
+ * Decrypt the pair of points `(c1, c2)` with `key`.
+ *
+ * Returns `CryptError::OutOfRange` if either point isn't on the curve, and `CryptError::WrongKey`
+ * if they don't decrypt to an encoded block, which happens when they were encrypted for a
+ * different key.
+ */
+pub fn decrypt_block(c1: Point, c2: Point, key: &EcPrivateKey) -> Result<Block, CryptError> {
+    if !c1.is_on_curve() || !c2.is_on_curve() {
+        return Err(CryptError::OutOfRange);
+    }
+
+    decode_block(c2 - c1.multiply(key.scalar)).ok_or(CryptError::WrongKey)
+}
+
+/**
+ * The elliptic-curve ElGamal `cipher::PublicKeyCipher`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EcElGamal;
+
+impl PublicKeyCipher for EcElGamal {
+    const ALGORITHM: Algorithm = Algorithm::EcElGamal;
+    const CIPHERTEXT_BYTES: usize = POINT_BYTES * 2;
+
+    type PublicKey = EcPublicKey;
+    type PrivateKey = EcPrivateKey;
+
+    fn generate<T: Rng>(rng: &mut T) -> Result<(EcPublicKey, EcPrivateKey), KeyGenError> {
+        let private = EcPrivateKey::generate(rng);
+        Ok((private.public_key(), private))
+    }
+
+    fn max_block_bytes(_key: &EcPublicKey) -> usize {
+        BLOCK_BYTES
+    }
+
+    fn encrypt_block<T: Rng>(
+        block: Block,
+        key: &EcPublicKey,
+        rng: &mut T,
+    ) -> Result<Vec<u8>, CryptError> {
+        let (c1, c2) = encrypt_block(block, key, rng)?;
+        let mut result = c1.serialize().to_vec();
+        result.extend_from_slice(&c2.serialize());
+        Ok(result)
+    }
+
+    fn decrypt_block(ciphertext: &[u8], key: &EcPrivateKey) -> Result<Block, CryptError> {
+        if ciphertext.len() != Self::CIPHERTEXT_BYTES {
+            return Err(CryptError::CorruptBlock);
+        }
+
+        let mut c1_buf = [0_u8; POINT_BYTES];
+        let mut c2_buf = [0_u8; POINT_BYTES];
+        c1_buf.copy_from_slice(&ciphertext[..POINT_BYTES]);
+        c2_buf.copy_from_slice(&ciphertext[POINT_BYTES..]);
+        match (Point::deserialize(&c1_buf), Point::deserialize(&c2_buf)) {
+            (Some(c1), Some(c2)) => decrypt_block(c1, c2, key),
+            _ => Err(CryptError::OutOfRange),
+        }
+    }
+
+    fn serialize_public_key(key: &EcPublicKey) -> Vec<u8> {
+        key.serialize().to_vec()
+    }
+
+    fn serialize_private_key(key: &EcPrivateKey) -> Vec<u8> {
+        key.serialize().to_vec()
+    }
+
+    fn deserialize_public_key<T: Rng>(bytes: &[u8], _rng: &mut T) -> Result<EcPublicKey, KeyError> {
+        let mut buf = [0_u8; EcPublicKey::KEY_BYTES];
+        if bytes.len() != buf.len() {
+            return Err(KeyError::InvalidLength);
+        }
+
+        buf.copy_from_slice(bytes);
+        EcPublicKey::try_deserialize(&buf)
+    }
+
+    fn deserialize_private_key<T: Rng>(
+        bytes: &[u8],
+        _rng: &mut T,
+    ) -> Result<EcPrivateKey, KeyError> {
+        let mut buf = [0_u8; EcPrivateKey::KEY_BYTES];
+        if bytes.len() != buf.len() {
+            return Err(KeyError::InvalidLength);
+        }
+
+        buf.copy_from_slice(bytes);
+        EcPrivateKey::try_deserialize(&buf)
+    }
+}
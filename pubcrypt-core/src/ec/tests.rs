@@ -0,0 +1,133 @@
+use super::*;
+use crate::cipher;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_point_arithmetic() {
+    assert!(GENERATOR.is_on_curve());
+    assert_eq!(GENERATOR.multiply(ORDER), Point::Infinity);
+    assert_eq!(GENERATOR.multiply(0), Point::Infinity);
+    assert_eq!(GENERATOR.multiply(1), GENERATOR);
+    assert_eq!(GENERATOR + Point::Infinity, GENERATOR);
+    assert_eq!(GENERATOR - GENERATOR, Point::Infinity);
+
+    let double = GENERATOR + GENERATOR;
+    assert!(double.is_on_curve());
+    assert_eq!(GENERATOR.multiply(2), double);
+    assert_eq!(double + GENERATOR, GENERATOR.multiply(3));
+    assert_eq!(GENERATOR.multiply(ORDER - 1), -GENERATOR);
+
+    // scalar multiplication distributes over addition of scalars
+    let (a, b) = (0x1234_5678, ORDER / 3);
+    assert_eq!(
+        GENERATOR.multiply(a) + GENERATOR.multiply(b),
+        GENERATOR.multiply(a + b)
+    );
+    assert_eq!(
+        GENERATOR.multiply(a).multiply(b),
+        GENERATOR.multiply(b).multiply(a)
+    );
+
+    assert_eq!(Point::new(2, 0), None);
+    assert_eq!(
+        Point::deserialize(&Point::Infinity.serialize()),
+        Some(Point::Infinity)
+    );
+    assert_eq!(Point::deserialize(&double.serialize()), Some(double));
+}
+
+#[test]
+fn test_block_encoding() {
+    for &block in &[0, 1, Block::MAX / 3, Block::MAX] {
+        let point = encode_block(block).unwrap();
+        assert!(point.is_on_curve());
+        assert_eq!(decode_block(point), Some(block));
+    }
+
+    assert_eq!(decode_block(Point::Infinity), None);
+}
+
+#[test]
+fn test_ec_keys() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = EcElGamal::generate(&mut rng).unwrap();
+
+    assert_eq!(private.public_key(), public);
+    assert_eq!(
+        EcPublicKey::try_deserialize(&public.serialize()),
+        Ok(public)
+    );
+    assert_eq!(
+        EcPrivateKey::try_deserialize(&private.serialize()),
+        Ok(private)
+    );
+
+    let mut off_curve = public.serialize();
+    off_curve[POINT_BYTES - 1] ^= 1;
+    assert_eq!(
+        EcPublicKey::try_deserialize(&off_curve),
+        Err(KeyError::NotOnCurve)
+    );
+    assert_eq!(
+        EcPublicKey::try_deserialize(&Point::Infinity.serialize()),
+        Err(KeyError::InvalidValue)
+    );
+    assert_eq!(
+        EcPrivateKey::try_deserialize(&ORDER.to_be_bytes()),
+        Err(KeyError::InvalidValue)
+    );
+    assert_eq!(
+        EcPrivateKey::try_deserialize(&[0; EcPrivateKey::KEY_BYTES]),
+        Err(KeyError::InvalidValue)
+    );
+}
+
+#[test]
+fn test_ec_crypt_block() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = EcElGamal::generate(&mut rng).unwrap();
+    let (_, other_private) = EcElGamal::generate(&mut rng).unwrap();
+
+    for &block in &[0, 1, Block::MAX / 3, Block::MAX] {
+        let (c1, c2) = encrypt_block(block, &public, &mut rng).unwrap();
+        assert_eq!(decrypt_block(c1, c2, &private).unwrap(), block);
+
+        // the random scalar keeps equal blocks from encrypting to the same ciphertext
+        assert_ne!(encrypt_block(block, &public, &mut rng).unwrap(), (c1, c2));
+    }
+
+    let (c1, c2) = encrypt_block(Block::MAX, &public, &mut rng).unwrap();
+    assert!(matches!(
+        decrypt_block(c1, c2, &other_private),
+        Err(CryptError::WrongKey)
+    ));
+    assert!(matches!(
+        decrypt_block(Point::Affine { x: 2, y: 0 }, c2, &private),
+        Err(CryptError::OutOfRange)
+    ));
+}
+
+#[test]
+fn test_ec_cipher() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = EcElGamal::generate(&mut rng).unwrap();
+    let plaintext = b"encrypted with ElGamal on a curve";
+
+    let mut ciphertext = Vec::new();
+    cipher::encrypt::<EcElGamal, _, _, _>(&mut &plaintext[..], &mut ciphertext, &public, &mut rng)
+        .unwrap();
+    let mut decrypted = Vec::new();
+    cipher::decrypt::<EcElGamal, _, _>(&mut &ciphertext[..], &mut decrypted, &private).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // a corrupted point is rejected instead of decrypting to garbage
+    let header_len = crate::header::Header::read(&mut &ciphertext[..])
+        .unwrap()
+        .len();
+    ciphertext[header_len + 1] ^= 1;
+    let mut output = Vec::new();
+    let error = cipher::decrypt::<EcElGamal, _, _>(&mut &ciphertext[..], &mut output, &private)
+        .unwrap_err();
+    assert!(matches!(error.root_cause(), CryptError::OutOfRange));
+}
@@ -0,0 +1,126 @@
+/*!
+ * Encryption and decryption of byte streams in electronic codebook (ECB) mode.
+ *
+ * The plaintext is split into blocks of up to `BLOCK_BYTES` bytes, each of which is encrypted
+ * independently into a pair of `Num`s and written in big-endian order. The final block is always
+ * padded: by default, unused bytes are filled with random data, and the last byte holds the number
+ * of pad bytes (see `Padding` for the other schemes). If the plaintext length is a multiple of the
+ * block size, an extra block consisting solely of padding is appended so that the decryptor can
+ * always find the padding.
+ *
+ * The ciphertext starts with a `Header` recording how blocks were encoded and how wide they are.
+ * `decrypt_ecb` also decrypts ciphertexts written in other modes (see `Mode`), since the mode is
+ * recorded in the header too.
+ * If the header says blocks are numbered, each block also carries a sequence number above its
+ * plaintext, with the top bit marking the final block, so reordered or truncated ciphertexts fail
+ * with `CryptError::OutOfSequence` or `CryptError::Truncated`. If it says blocks are checksummed,
+ * each block carries a truncated SHA-256 digest of its position and contents above that, which
+ * `decrypt_ecb` checks before anything else, so a corrupted block fails with
+ * `CryptError::ChecksumMismatch` at its own offset.
+ * Authenticated ciphertexts (see `Header::authenticated`) end with a MAC over the whole ciphertext
+ * instead, which `decrypt_ecb` checks before writing any plaintext, so corruption anywhere in the
+ * file is caught with `CryptError::AuthenticationFailed`.
+ * Ciphertexts with blocks narrower than `BLOCK_BYTES`, such as those written by builds with a
+ * smaller `Block`, can still be decrypted. Ciphertexts written before headers were introduced are
+ * decrypted as 4-byte blocks using `Encoding::Raw`.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    stream::{pad_slice, DecryptReader, EncryptWriter},
+    CryptError, PrivateKey, PublicKey, BLOCK_BYTES,
+};
+use rand::Rng;
+use std::io::{self, BufRead, Write};
+
+/**
+ * Fill unused space in the buffer with random bytes, and place the number of
+ * pad bytes at the end of the buffer.
+ *
+ * Panics if `pad_bytes > buf.len()`.
+ */
+pub fn pad_block<T: Rng, const N: usize>(buf: &mut [u8; N], pad_bytes: u8, rng: &mut T) {
+    pad_slice(buf, pad_bytes, rng);
+}
+
+/**
+ * Encrypt the plaintext read from `reader` with the public key `key`, writing the ciphertext to
+ * `writer`. The random exponent for each block and the padding bytes are generated by `rng`.
+ *
+ * Blocks are `BLOCK_BYTES` bytes wide, and are encoded with the best `Encoding` the key supports.
+ * To encrypt to a writer incrementally, use `stream::EncryptWriter` instead.
+ */
+pub fn encrypt_ecb<R: BufRead, W: Write, T: Rng>(
+    reader: &mut R,
+    writer: &mut W,
+    key: &PublicKey,
+    rng: &mut T,
+) -> Result<(), CryptError> {
+    encrypt_ecb_sized::<_, _, _, BLOCK_BYTES>(reader, writer, key, rng)
+}
+
+/**
+ * Encrypt as in `encrypt_ecb`, but with blocks of `block_bytes` bytes. This is for choosing the
+ * block size at runtime; prefer `encrypt_ecb_sized` when it's known at compile time.
+ *
+ * Returns `CryptError::UnsupportedBlockSize` if `block_bytes` is 0 or greater than `BLOCK_BYTES`.
+ */
+pub fn encrypt_ecb_with_block_size<R: BufRead, W: Write, T: Rng>(
+    reader: &mut R,
+    writer: &mut W,
+    key: &PublicKey,
+    block_bytes: usize,
+    rng: &mut T,
+) -> Result<(), CryptError> {
+    let mut encryptor = EncryptWriter::with_block_size(writer, key.clone(), block_bytes, rng)?;
+    io::copy(reader, &mut encryptor)?;
+    encryptor.finish()?;
+    Ok(())
+}
+
+/**
+ * Encrypt as in `encrypt_ecb`, but with blocks of `N` bytes, which is recorded in the ciphertext
+ * header. Narrower blocks expand more when encrypted, but leave a wider margin between the largest
+ * block and the key's prime.
+ *
+ * Returns `CryptError::UnsupportedBlockSize` if `N` is 0 or greater than `BLOCK_BYTES`.
+ */
+pub fn encrypt_ecb_sized<R: BufRead, W: Write, T: Rng, const N: usize>(
+    reader: &mut R,
+    writer: &mut W,
+    key: &PublicKey,
+    rng: &mut T,
+) -> Result<(), CryptError> {
+    encrypt_ecb_with_block_size(reader, writer, key, N, rng)
+}
+
+/**
+ * Decrypt the ciphertext read from `reader` with the private key `key`, writing the plaintext to
+ * `writer`.
+ *
+ * Returns `CryptError::InvalidHeader` or `CryptError::UnsupportedVersion` if the ciphertext's
+ * header can't be read, and `CryptError::UnsupportedBlockSize` if its blocks are wider than
+ * `BLOCK_BYTES`. The block size is read from the header, so ciphertexts written by any of the
+ * `encrypt_ecb` variants can be decrypted.
+ *
+ * Errors in individual blocks are reported as `CryptError::Block`, along with the byte offset of
+ * the offending block in the ciphertext. These occur when the ciphertext isn't a whole number of
+ * encrypted blocks, when a block can't be decrypted, or when the final block's padding isn't valid
+ * for the scheme recorded in the header. Note that the plaintext preceding the offending block may
+ * already have been written when an error occurs.
+ *
+ * Authenticated ciphertexts are read into memory and their MAC is checked before anything is
+ * decrypted, so no plaintext is written if `CryptError::AuthenticationFailed` is returned.
+ */
+pub fn decrypt_ecb<R: BufRead, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    key: &PrivateKey,
+) -> Result<(), CryptError> {
+    let mut decryptor = DecryptReader::new(reader, key.clone())?;
+    decryptor.verify()?;
+    io::copy(&mut decryptor, writer)?;
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use super::*;
+use crate::{
+    encrypt_block,
+    header::{Header, CURRENT_VERSION, MAGIC},
+    Block, Encoding, KeyPair, Padding, NUM_BYTES,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+fn round_trip(plaintext: &[u8], keys: &KeyPair, rng: &mut StdRng) -> Vec<u8> {
+    let mut ciphertext = Vec::new();
+    encrypt_ecb(&mut &plaintext[..], &mut ciphertext, &keys.public, rng).unwrap();
+
+    // every block, including the final padded block, expands to two `Num`s after the header
+    let header = Header::new(Encoding::QuadraticResidue);
+    let block_count = plaintext.len() / BLOCK_BYTES + 1;
+    assert_eq!(ciphertext.len(), header.len() + block_count * NUM_BYTES * 2);
+    assert_eq!(Header::read(&mut &ciphertext[..]).unwrap(), header);
+
+    let mut decrypted = Vec::new();
+    decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &keys.private).unwrap();
+    decrypted
+}
+
+#[test]
+fn test_pad_block() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut buf = [0xaa_u8; 8];
+
+    pad_block(&mut buf, 3, &mut rng);
+    assert_eq!(buf[..5], [0xaa; 5]);
+    assert_eq!(buf[7], 3);
+
+    pad_block(&mut buf, 8, &mut rng);
+    assert_eq!(buf[7], 8);
+}
+
+#[test]
+fn random_test_crypt_ecb() {
+    let mut rng = StdRng::from_entropy();
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    // edge cases with inputs no more than two blocks long, then some larger inputs
+    let lengths = (0..=BLOCK_BYTES * 2).chain(vec![100, 1000, 4097]);
+    for len in lengths {
+        let mut plaintext = vec![0_u8; len];
+        rng.fill(&mut plaintext[..]);
+        assert_eq!(round_trip(&plaintext, &keys, &mut rng), plaintext);
+    }
+}
+
+#[test]
+fn test_crypt_block_sizes() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    for block_bytes in 1..=BLOCK_BYTES {
+        for len in (0..=block_bytes * 2).chain(vec![100]) {
+            let mut plaintext = vec![0_u8; len];
+            rng.fill(&mut plaintext[..]);
+
+            let mut ciphertext = Vec::new();
+            encrypt_ecb_with_block_size(
+                &mut &plaintext[..],
+                &mut ciphertext,
+                &keys.public,
+                block_bytes,
+                &mut rng,
+            )
+            .unwrap();
+
+            let header = Header::read(&mut &ciphertext[..]).unwrap();
+            let block_count = len / block_bytes + 1;
+            assert_eq!(header.block_bytes, block_bytes);
+            assert_eq!(ciphertext.len(), header.len() + block_count * NUM_BYTES * 2);
+
+            let mut decrypted = Vec::new();
+            decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &keys.private).unwrap();
+            assert_eq!(decrypted, plaintext);
+        }
+    }
+
+    for &block_bytes in &[0, BLOCK_BYTES + 1, 100] {
+        let result = encrypt_ecb_with_block_size(
+            &mut &b"hello"[..],
+            &mut Vec::new(),
+            &keys.public,
+            block_bytes,
+            &mut rng,
+        );
+        assert!(matches!(
+            result,
+            Err(CryptError::UnsupportedBlockSize(size)) if size == block_bytes
+        ));
+    }
+
+    let result = encrypt_ecb_sized::<_, _, _, 0>(
+        &mut &b"hello"[..],
+        &mut Vec::new(),
+        &keys.public,
+        &mut rng,
+    );
+    assert!(matches!(result, Err(CryptError::UnsupportedBlockSize(0))));
+}
+
+#[test]
+fn test_decrypt_truncated() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let mut ciphertext = Vec::new();
+    let plaintext = [0x42_u8; BLOCK_BYTES + 1];
+    encrypt_ecb(&mut &plaintext[..], &mut ciphertext, &keys.public, &mut rng).unwrap();
+    ciphertext.pop();
+
+    let result = decrypt_ecb(&mut &ciphertext[..], &mut Vec::new(), &keys.private);
+    match result {
+        // the plaintext spans two blocks, and the second one is truncated
+        Err(CryptError::Block { offset, error }) => {
+            let header_len = Header::new(Encoding::QuadraticResidue).len();
+            assert_eq!(offset, (header_len + NUM_BYTES * 2) as u64);
+            assert!(matches!(*error, CryptError::CorruptBlock));
+        }
+        _ => panic!("Expected a truncated block, got {:?}", result),
+    }
+}
+
+#[test]
+fn test_decrypt_invalid_padding() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    // a final block with a pad count of 0 or more than the block length is invalid; the
+    // ciphertext is built without a header, so it's decrypted as a legacy ciphertext
+    for &pad_count in &[0, BLOCK_BYTES as u8 + 1, u8::MAX] {
+        let block = pad_count as Block;
+        let (c1, c2) = encrypt_block(block, &keys.public, Encoding::Raw, &mut rng).unwrap();
+
+        let mut ciphertext = Vec::new();
+        ciphertext.extend_from_slice(&c1.to_be_bytes());
+        ciphertext.extend_from_slice(&c2.to_be_bytes());
+
+        let result = decrypt_ecb(&mut &ciphertext[..], &mut Vec::new(), &keys.private);
+        assert!(matches!(
+            result.unwrap_err().root_cause(),
+            CryptError::InvalidPadding(Padding::Random)
+        ));
+    }
+}
+
+#[test]
+fn test_decrypt_wrong_key() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    let mut ciphertext = Vec::new();
+    encrypt_ecb(&mut &b"hello"[..], &mut ciphertext, &keys.public, &mut rng).unwrap();
+
+    let result = decrypt_ecb(&mut &ciphertext[..], &mut Vec::new(), &other_keys.private);
+    assert!(matches!(
+        result.unwrap_err().root_cause(),
+        CryptError::WrongKey
+    ));
+}
+
+#[test]
+fn test_crypt_authenticated() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    let plaintext = [0x42_u8; 100];
+    let mut header = Header::new(Encoding::best_for(keys.public.key().prime));
+    header.authenticated = true;
+    let mut encryptor =
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng).unwrap();
+    encryptor.write_all(&plaintext).unwrap();
+    let ciphertext = encryptor.finish().unwrap();
+    assert!(Header::read(&mut &ciphertext[..]).unwrap().authenticated);
+
+    let mut decrypted = Vec::new();
+    decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &keys.private).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // any change to the file, or the wrong key, is caught before anything is written
+    let mut corrupted = ciphertext.clone();
+    let middle = corrupted.len() / 2;
+    corrupted[middle] ^= 1;
+    let truncated = &ciphertext[..ciphertext.len() - 1];
+    for (ciphertext, key) in [
+        (&corrupted[..], &keys.private),
+        (truncated, &keys.private),
+        (&ciphertext[..], &other_keys.private),
+    ] {
+        let mut decrypted = Vec::new();
+        let result = decrypt_ecb(&mut &ciphertext[..], &mut decrypted, key);
+        assert!(matches!(result, Err(CryptError::AuthenticationFailed)));
+        assert!(decrypted.is_empty());
+    }
+}
+
+#[test]
+fn test_decrypt_out_of_range() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let prime = keys.private.key().prime;
+
+    let mut ciphertext = Vec::new();
+    let plaintext = [0xab_u8; BLOCK_BYTES * 3];
+    encrypt_ecb(&mut &plaintext[..], &mut ciphertext, &keys.public, &mut rng).unwrap();
+
+    // replace `c2` of the third block with the prime
+    let header_len = Header::new(Encoding::QuadraticResidue).len();
+    let block_len = NUM_BYTES * 2;
+    let c2_start = header_len + block_len * 2 + NUM_BYTES;
+    ciphertext[c2_start..c2_start + NUM_BYTES].copy_from_slice(&prime.to_be_bytes());
+
+    let mut decrypted = Vec::new();
+    let result = decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &keys.private);
+    match result {
+        Err(CryptError::Block { offset, error }) => {
+            assert_eq!(offset, (header_len + block_len * 2) as u64);
+            assert!(matches!(*error, CryptError::OutOfRange));
+        }
+        _ => panic!("Expected an out of range block, got {:?}", result),
+    }
+
+    // blocks before the offending one are still decrypted
+    assert_eq!(decrypted, plaintext[..BLOCK_BYTES * 2]);
+}
+
+/**
+ * Encrypt `plaintext` by hand with blocks of `header.block_bytes` bytes, as an older or narrower
+ * build would.
+ */
+fn encrypt_with_header(
+    plaintext: &[u8],
+    header: &Header,
+    keys: &KeyPair,
+    rng: &mut StdRng,
+) -> Vec<u8> {
+    let width = header.block_bytes;
+    let mut ciphertext = Vec::new();
+    header.write(&mut ciphertext).unwrap();
+
+    let mut chunks: Vec<&[u8]> = plaintext.chunks(width).collect();
+    if plaintext.len().is_multiple_of(width) {
+        chunks.push(&[]);
+    }
+
+    for chunk in chunks {
+        // narrower blocks are stored in the low bytes of a `Block`
+        let mut buf = [0_u8; BLOCK_BYTES];
+        let block_buf = &mut buf[BLOCK_BYTES - width..];
+        block_buf[..chunk.len()].copy_from_slice(chunk);
+
+        if chunk.len() < width {
+            rng.fill(&mut block_buf[chunk.len()..width - 1]);
+            block_buf[width - 1] = (width - chunk.len()) as u8;
+        }
+
+        let block = Block::from_be_bytes(buf);
+        let (c1, c2) = encrypt_block(block, &keys.public, header.encoding, rng).unwrap();
+        ciphertext.extend_from_slice(&c1.to_be_bytes());
+        ciphertext.extend_from_slice(&c2.to_be_bytes());
+    }
+
+    ciphertext
+}
+
+#[test]
+fn test_decrypt_legacy() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    // legacy ciphertexts have no header, and encrypt each 4-byte block as-is
+    let plaintext = b"legacy ciphertext";
+    let ciphertext = encrypt_with_header(plaintext, &Header::legacy(), &keys, &mut rng);
+
+    let mut decrypted = Vec::new();
+    decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &keys.private).unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn test_decrypt_block_sizes() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let plaintext = b"blocks of any width up to BLOCK_BYTES";
+
+    for width in 1..=BLOCK_BYTES {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.block_bytes = width;
+        let ciphertext = encrypt_with_header(plaintext, &header, &keys, &mut rng);
+
+        let mut decrypted = Vec::new();
+        decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &keys.private).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    // blocks wider than `Block` can't be decrypted
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.block_bytes = BLOCK_BYTES + 1;
+    let mut ciphertext = Vec::new();
+    header.write(&mut ciphertext).unwrap();
+
+    let result = decrypt_ecb(&mut &ciphertext[..], &mut Vec::new(), &keys.private);
+    assert!(matches!(
+        result,
+        Err(CryptError::UnsupportedBlockSize(size)) if size == BLOCK_BYTES + 1
+    ));
+}
+
+#[test]
+fn test_decrypt_unsupported_version() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let mut ciphertext = Vec::new();
+    encrypt_ecb(&mut &b"hello"[..], &mut ciphertext, &keys.public, &mut rng).unwrap();
+    ciphertext[MAGIC.len()] = CURRENT_VERSION + 1;
+
+    let result = decrypt_ecb(&mut &ciphertext[..], &mut Vec::new(), &keys.private);
+    assert!(matches!(
+        result,
+        Err(CryptError::UnsupportedVersion(v)) if v == CURRENT_VERSION + 1
+    ));
+}
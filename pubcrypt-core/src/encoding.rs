@@ -0,0 +1,123 @@
+use crate::{Block, CryptError, Num};
+use math::mod_exp;
+
+/**
+ * How plaintext blocks are mapped into the multiplicative group before encryption.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Encoding {
+    /**
+     * Blocks are encrypted as-is. Since the group has even order, this leaks whether each block is
+     * a quadratic residue (i.e., its Legendre symbol). This is only used for legacy ciphertexts and
+     * for keys whose primes are too small for `QuadraticResidue`.
+     */
+    Raw,
+    /**
+     * Blocks are mapped into the subgroup of quadratic residues, which has prime order `q` for a
+     * safe prime `p = 2q + 1`. Each block `m` is mapped to `m + 1` if that's a residue, and
+     * otherwise to `p - (m + 1)`, which is then a residue since `p == 3 (mod 4)`. Every value the
+     * encryption produces is then a residue, so no information is leaked through the Legendre
+     * symbol.
+     */
+    QuadraticResidue,
+}
+
+impl Encoding {
+    /**
+     * Check if blocks can be encoded with this encoding for a key with the given prime.
+     */
+    pub fn supports(self, prime: Num) -> bool {
+        match self {
+            Self::Raw => prime > Block::MAX as Num,
+            // `m + 1` must be at most `q`, so that `m + 1` and `p - (m + 1)` don't overlap
+            Self::QuadraticResidue => prime / 2 > Block::MAX as Num,
+        }
+    }
+
+    /**
+     * Get the best encoding supported by a key with the given prime.
+     */
+    pub fn best_for(prime: Num) -> Self {
+        if Self::QuadraticResidue.supports(prime) {
+            Self::QuadraticResidue
+        } else {
+            Self::Raw
+        }
+    }
+
+    /**
+     * Map `block` to the group element to be encrypted.
+     *
+     * Returns `CryptError::MalformedKey` if the encoding isn't supported for `prime`.
+     */
+    pub fn encode(self, block: Block, prime: Num) -> Result<Num, CryptError> {
+        if !self.supports(prime) {
+            return Err(CryptError::MalformedKey);
+        }
+
+        match self {
+            Self::Raw => Ok(block as Num),
+            Self::QuadraticResidue => {
+                let shifted = block as Num + 1;
+                let q = (prime - 1) / 2;
+
+                // Euler's criterion
+                if mod_exp(shifted, q, prime) == 1 {
+                    Ok(shifted)
+                } else {
+                    Ok(prime - shifted)
+                }
+            }
+        }
+    }
+
+    /**
+     * Map the decrypted group element `value` back to a block, reversing `encode`.
+     *
+     * Returns `CryptError::WrongKey` if `value` couldn't have been produced by `encode`, and
+     * `CryptError::MalformedKey` if the encoding isn't supported for `prime`.
+     */
+    pub fn decode(self, value: Num, prime: Num) -> Result<Block, CryptError> {
+        if !self.supports(prime) {
+            return Err(CryptError::MalformedKey);
+        }
+
+        let block = match self {
+            Self::Raw => value,
+            Self::QuadraticResidue => {
+                let q = (prime - 1) / 2;
+                let shifted = if value <= q { value } else { prime - value };
+                shifted.checked_sub(1).ok_or(CryptError::WrongKey)?
+            }
+        };
+
+        if block > Block::MAX as Num {
+            return Err(CryptError::WrongKey);
+        }
+
+        Ok(block as Block)
+    }
+
+    /**
+     * Get the identifier used for the encoding in ciphertext headers.
+     */
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Self::Raw => 0,
+            Self::QuadraticResidue => 1,
+        }
+    }
+
+    /**
+     * Get the encoding with the given header identifier, if any.
+     */
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Raw),
+            1 => Some(Self::QuadraticResidue),
+            _ => None,
+        }
+    }
+}
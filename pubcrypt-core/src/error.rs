@@ -0,0 +1,513 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io,
+};
+
+use crate::{Algorithm, Fingerprint, Padding, PrimeError, NUM_BYTES};
+
+/**
+ * An error that occurred while encrypting or decrypting.
+ */
+#[derive(Debug)]
+pub enum CryptError {
+    /**
+     * The key's parameters can't be used for encryption or decryption.
+     */
+    MalformedKey,
+    /**
+     * A block decrypted to a value that can't be plaintext, which means the ciphertext was
+     * encrypted for a different key.
+     */
+    WrongKey,
+    /**
+     * The ciphertext isn't made up of whole encrypted blocks.
+     */
+    CorruptBlock,
+    /**
+     * A ciphertext component isn't in the range of values produced by encryption.
+     */
+    OutOfRange,
+    /**
+     * The final block's padding isn't valid for the given scheme, or, when encrypting with
+     * `Padding::Zero`, the plaintext ends with a zero byte.
+     */
+    InvalidPadding(Padding),
+    /**
+     * The ciphertext header is truncated or malformed.
+     */
+    InvalidHeader,
+    /**
+     * The ciphertext was written with a format version that isn't supported.
+     */
+    UnsupportedVersion(u8),
+    /**
+     * Blocks of the given number of bytes are either empty or wider than `Block`, so they can't be
+     * encrypted or decrypted.
+     */
+    UnsupportedBlockSize(usize),
+    /**
+     * A block's sequence number doesn't match its position, which means blocks were reordered,
+     * duplicated, or removed.
+     */
+    OutOfSequence,
+    /**
+     * The ciphertext ends before its final block, which means it was truncated.
+     */
+    Truncated,
+    /**
+     * A block's checksum doesn't match its contents and position, which means the block was
+     * corrupted, or the ciphertext was encrypted for a different key.
+     */
+    ChecksumMismatch,
+    /**
+     * The ciphertext's MAC doesn't match, which means it was modified, truncated, or encrypted
+     * for a different key.
+     */
+    AuthenticationFailed,
+    /**
+     * A chunk of a chunked ciphertext claims to be longer than `stream::CHUNK_BYTES`.
+     */
+    InvalidChunk,
+    /**
+     * The plaintext of a ciphertext marked as holding a file isn't a valid `container`.
+     */
+    InvalidContainer,
+    /**
+     * The plaintext of a ciphertext marked as holding an archive isn't a valid `archive`.
+     */
+    InvalidArchive,
+    /**
+     * A `volume` of a split ciphertext is malformed, out of order, or belongs to another
+     * ciphertext.
+     */
+    InvalidVolume,
+    /**
+     * The `volume` with the given number, counting from 1, couldn't be found.
+     */
+    MissingVolume(u64),
+    /**
+     * The contents of a file in a `container` or `archive` don't match its digest, which means
+     * the ciphertext was corrupted.
+     */
+    DigestMismatch,
+    /**
+     * Two keys that must be in the same group have different primes or roots.
+     */
+    IncompatibleKeys,
+    /**
+     * Key shares or partial decryptions are too few to meet their threshold, are inconsistent
+     * with each other, or were requested with an impossible threshold.
+     */
+    InvalidShares,
+    /**
+     * The ciphertext was encrypted with the given algorithm, which isn't the algorithm of the key
+     * it's being decrypted with.
+     */
+    WrongAlgorithm(Algorithm),
+    /**
+     * The ciphertext was encrypted for the public key with the first fingerprint, but is being
+     * decrypted with the private key of the second.
+     */
+    WrongRecipient(Fingerprint, Fingerprint),
+    /**
+     * A protected private key couldn't be decrypted, which means the passphrase is wrong or the
+     * protected key was modified.
+     */
+    WrongPassphrase,
+    /**
+     * Decrypting the ciphertext block at the given byte offset failed.
+     */
+    Block { offset: u64, error: Box<CryptError> },
+    /**
+     * Reading the input or writing the output failed.
+     */
+    Io(io::Error),
+}
+
+impl Display for CryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MalformedKey => write!(f, "the key is malformed"),
+            Self::WrongKey => write!(
+                f,
+                "the ciphertext is corrupt or was encrypted for a different key"
+            ),
+            Self::CorruptBlock => write!(f, "the ciphertext is truncated or corrupt"),
+            Self::OutOfRange => write!(
+                f,
+                "the ciphertext is out of range for the key; it may be corrupt or encrypted for a different key"
+            ),
+            Self::InvalidPadding(Padding::Zero) => write!(
+                f,
+                "the zero padding is invalid; the plaintext can't end with a zero byte, and the ciphertext may be corrupt or encrypted for a different key"
+            ),
+            Self::InvalidPadding(padding) => write!(
+                f,
+                "the ciphertext has invalid {} padding; it may be corrupt or encrypted for a different key",
+                padding
+            ),
+            Self::InvalidHeader => write!(f, "the ciphertext header is truncated or corrupt"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the ciphertext uses unsupported format version {}",
+                version
+            ),
+            Self::UnsupportedBlockSize(size) => write!(
+                f,
+                "unsupported block size of {} bytes",
+                size
+            ),
+            Self::OutOfSequence => write!(
+                f,
+                "the ciphertext blocks are out of order; blocks may have been reordered, duplicated, or removed"
+            ),
+            Self::Truncated => write!(f, "the ciphertext ends before its final block"),
+            Self::ChecksumMismatch => write!(
+                f,
+                "the block's checksum doesn't match; the ciphertext is corrupt or was encrypted for a different key"
+            ),
+            Self::AuthenticationFailed => write!(
+                f,
+                "the ciphertext failed authentication; it was modified, truncated, or encrypted for a different key"
+            ),
+            Self::InvalidChunk => write!(f, "the ciphertext has a chunk with an invalid length"),
+            Self::InvalidContainer => write!(
+                f,
+                "the decrypted file container is truncated or corrupt"
+            ),
+            Self::InvalidArchive => write!(
+                f,
+                "the decrypted directory archive is truncated, corrupt, or has an unsafe path"
+            ),
+            Self::InvalidVolume => write!(
+                f,
+                "a volume of the ciphertext is corrupt, out of order, or from another ciphertext"
+            ),
+            Self::MissingVolume(number) => write!(f, "volume {} of the ciphertext is missing", number),
+            Self::DigestMismatch => write!(
+                f,
+                "the decrypted file doesn't match its digest; the ciphertext is corrupt"
+            ),
+            Self::IncompatibleKeys => write!(f, "the keys use different primes or roots"),
+            Self::InvalidShares => write!(
+                f,
+                "the key shares are invalid, inconsistent, or too few to meet the threshold"
+            ),
+            Self::WrongAlgorithm(algorithm) => write!(
+                f,
+                "the ciphertext was encrypted with {}, which doesn't match the key",
+                algorithm
+            ),
+            Self::WrongRecipient(expected, actual) => write!(
+                f,
+                "the ciphertext was encrypted for key {:#}, but the key supplied is {:#}",
+                expected, actual
+            ),
+            Self::WrongPassphrase => write!(
+                f,
+                "the passphrase is wrong or the protected key is corrupt"
+            ),
+            Self::Block { offset, error } => write!(f, "at byte offset {}: {}", offset, error),
+            Self::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl CryptError {
+    /**
+     * Get the underlying error, skipping over any block offset information.
+     */
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::Block { error, .. } => error.root_cause(),
+            e => e,
+        }
+    }
+}
+
+impl Error for CryptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Block { error, .. } => Some(error.as_ref()),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CryptError {
+    /**
+     * Wrap `e` in `CryptError::Io`, unless it's just a wrapper around a `CryptError` (as created by
+     * converting a `CryptError` to an `io::Error`), in which case the original error is returned.
+     */
+    fn from(e: io::Error) -> Self {
+        if e.get_ref().is_some_and(|inner| inner.is::<Self>()) {
+            // the inner error was just checked, so neither unwrap can fail
+            return *e.into_inner().unwrap().downcast::<Self>().unwrap();
+        }
+
+        Self::Io(e)
+    }
+}
+
+impl From<CryptError> for io::Error {
+    /**
+     * Convert `e` to an `io::Error` so that it can be returned from `Read` and `Write`
+     * implementations. I/O errors are unwrapped, and anything else is reported as invalid data.
+     */
+    fn from(e: CryptError) -> Self {
+        match e {
+            CryptError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/**
+ * A reason that a key's parameters are invalid.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyError {
+    /**
+     * The prime is too small to encrypt a block.
+     */
+    PrimeTooSmall,
+    /**
+     * The prime isn't a safe prime, i.e., a prime `p` such that `(p - 1) / 2` is also prime.
+     */
+    NotSafePrime,
+    /**
+     * The root isn't a primitive root of the prime.
+     */
+    InvalidRoot,
+    /**
+     * The key's value is zero or isn't less than the prime.
+     */
+    InvalidValue,
+    /**
+     * The public and private keys of a pair don't belong together.
+     */
+    MismatchedPair,
+    /**
+     * The key's modulus isn't the product of two distinct primes, or is too small to encrypt a
+     * block.
+     */
+    InvalidModulus,
+    /**
+     * One of the key's primes isn't prime.
+     */
+    NotPrime,
+    /**
+     * The key's point isn't on the curve.
+     */
+    NotOnCurve,
+    /**
+     * The key file is for an algorithm that isn't supported.
+     */
+    UnknownAlgorithm,
+    /**
+     * The key is the wrong length for its algorithm.
+     */
+    InvalidLength,
+    /**
+     * The key file's metadata is truncated or malformed.
+     */
+    InvalidMetadata,
+    /**
+     * The key file is from an unsupported version of the key file format.
+     */
+    UnsupportedVersion(u8),
+    /**
+     * The key pair file is truncated or malformed.
+     */
+    InvalidPair,
+    /**
+     * The key was made with a different size of `Num`, i.e., with or without the `wide` feature.
+     */
+    WrongWidth,
+    /**
+     * The key file's signature doesn't match its metadata and key.
+     */
+    InvalidSignature,
+    /**
+     * The key's DER encoding is malformed, or doesn't have the expected structure.
+     */
+    InvalidDer,
+    /**
+     * The key's JSON is malformed, or doesn't have the expected fields.
+     */
+    InvalidJson,
+    /**
+     * The key's text is malformed, or doesn't have the expected words.
+     */
+    InvalidText,
+    /**
+     * A number in the key is too large to fit in a `Num`, such as the prime of a group made by
+     * another tool.
+     */
+    NumberTooLarge,
+    /**
+     * The OpenPGP key's packets are malformed.
+     */
+    InvalidOpenPgp,
+    /**
+     * The one-line key is malformed.
+     */
+    InvalidLine,
+    /**
+     * The detached signature file is malformed.
+     */
+    InvalidSignatureFile,
+    /**
+     * The word of a mnemonic at the given position, counting from 1, isn't in the word list.
+     */
+    UnknownWord(usize),
+    /**
+     * The mnemonic has the wrong number of words, or its checksum doesn't match.
+     */
+    InvalidMnemonic,
+    /**
+     * The compact key is malformed, or its checksum doesn't match.
+     */
+    InvalidCompact,
+}
+
+impl Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PrimeTooSmall => write!(f, "the key's prime is too small"),
+            Self::NotSafePrime => write!(f, "the key's prime isn't a safe prime"),
+            Self::InvalidRoot => write!(f, "the key's root isn't a primitive root of its prime"),
+            Self::InvalidValue => write!(f, "the key's value is out of range"),
+            Self::MismatchedPair => write!(f, "the public and private keys don't match"),
+            Self::InvalidModulus => write!(f, "the key's modulus is invalid"),
+            Self::NotPrime => write!(f, "one of the key's primes isn't prime"),
+            Self::NotOnCurve => write!(f, "the key's point isn't on the curve"),
+            Self::UnknownAlgorithm => write!(f, "the key is for an unsupported algorithm"),
+            Self::InvalidLength => write!(f, "the key is the wrong length for its algorithm"),
+            Self::InvalidMetadata => write!(f, "the key file's metadata is malformed"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "version {} key files aren't supported", version)
+            }
+            Self::InvalidPair => write!(f, "the key pair file is malformed"),
+            Self::WrongWidth => write!(
+                f,
+                "the key was made for a different number width (see the `wide` feature)"
+            ),
+            Self::InvalidSignature => write!(f, "the key file's signature is invalid"),
+            Self::InvalidDer => write!(f, "the key's DER encoding is malformed"),
+            Self::InvalidJson => write!(f, "the key's JSON is malformed"),
+            Self::InvalidText => write!(f, "the key's text is malformed"),
+            Self::NumberTooLarge => write!(
+                f,
+                "a number in the key has more than {} bits (see the `wide` feature)",
+                NUM_BYTES * 8
+            ),
+            Self::InvalidOpenPgp => write!(f, "the OpenPGP key is malformed"),
+            Self::InvalidLine => write!(f, "the one-line key is malformed"),
+            Self::InvalidSignatureFile => write!(f, "the signature file is malformed"),
+            Self::UnknownWord(position) => {
+                write!(
+                    f,
+                    "word {} of the mnemonic isn't in the word list",
+                    position
+                )
+            }
+            Self::InvalidMnemonic => write!(
+                f,
+                "the mnemonic has the wrong number of words, or a word is wrong or out of place"
+            ),
+            Self::InvalidCompact => write!(
+                f,
+                "the compact key is malformed, or a character is wrong or missing"
+            ),
+        }
+    }
+}
+
+impl Error for KeyError {}
+
+/**
+ * A reason that keys couldn't be generated.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyGenError {
+    /**
+     * The range that primes are picked from is too small to contain a suitable prime, or starts
+     * below `PRIME_MIN`.
+     */
+    InvalidRange,
+    /**
+     * Every attempt to find a prime failed. Another try, or a larger attempt budget, is likely to
+     * succeed.
+     */
+    PrimeNotFound { attempts: usize },
+}
+
+impl Display for KeyGenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidRange => write!(f, "the prime range is too small"),
+            Self::PrimeNotFound { attempts: 1 } => write!(f, "no prime was found"),
+            Self::PrimeNotFound { attempts } => {
+                write!(f, "no prime was found in {} attempts", attempts)
+            }
+        }
+    }
+}
+
+impl Error for KeyGenError {}
+
+impl From<PrimeError> for KeyGenError {
+    /**
+     * Convert the error from a single search for a prime.
+     */
+    fn from(error: PrimeError) -> Self {
+        match error {
+            PrimeError::InvalidRange => Self::InvalidRange,
+            PrimeError::PrimeNotFound => Self::PrimeNotFound { attempts: 1 },
+        }
+    }
+}
+
+/**
+ * A reason that armored text couldn't be unwrapped.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArmorError {
+    /**
+     * The text doesn't start and end with matching delimiter lines.
+     */
+    InvalidDelimiters,
+    /**
+     * The delimiter lines name an unknown kind of data.
+     */
+    UnknownLabel,
+    /**
+     * The data or checksum isn't valid base64.
+     */
+    InvalidBase64,
+    /**
+     * The checksum doesn't match the data, which was probably changed in transit.
+     */
+    ChecksumMismatch,
+}
+
+impl Display for ArmorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidDelimiters => write!(f, "the armor's BEGIN and END lines are missing"),
+            Self::UnknownLabel => write!(f, "the armor is for an unknown kind of data"),
+            Self::InvalidBase64 => write!(f, "the armored data isn't valid base64"),
+            Self::ChecksumMismatch => {
+                write!(
+                    f,
+                    "the armor's checksum doesn't match; the text may be damaged"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ArmorError {}
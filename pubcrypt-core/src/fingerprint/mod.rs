@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{hash::Sha256, Key};
+use std::fmt;
+
+/**
+ * The number of bytes in a key fingerprint.
+ */
+pub const FINGERPRINT_BYTES: usize = 16;
+
+/**
+ * A short identifier for a key: the first `FINGERPRINT_BYTES` bytes of a SHA-256 hash of its
+ * prime, root, and value.
+ *
+ * The `Display` implementation writes the fingerprint as lowercase hex digits, and the alternate
+ * form (`{:#}`) groups them into colon-separated bytes, e.g., `3f:a0:...`, which is easier for
+ * people to compare.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint([u8; FINGERPRINT_BYTES]);
+
+impl Fingerprint {
+    /**
+     * Compute the fingerprint of `key`.
+     */
+    pub fn of(key: &Key) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"pubcrypt fingerprint");
+        hasher.update(&key.serialize());
+
+        let mut result = [0_u8; FINGERPRINT_BYTES];
+        result.copy_from_slice(&hasher.finalize()[..FINGERPRINT_BYTES]);
+        Self(result)
+    }
+
+    /**
+     * Get the bytes of the fingerprint.
+     */
+    pub fn as_bytes(&self) -> &[u8; FINGERPRINT_BYTES] {
+        &self.0
+    }
+
+    /**
+     * Format the fingerprint as hex digits, as in `Display`.
+     */
+    pub fn to_hex(&self) -> String {
+        format!("{}", self)
+    }
+
+    /**
+     * Format the fingerprint as colon-separated bytes, as in `Display`'s alternate form.
+     */
+    pub fn to_colon_hex(&self) -> String {
+        format!("{:#}", self)
+    }
+}
+
+impl From<[u8; FINGERPRINT_BYTES]> for Fingerprint {
+    fn from(bytes: [u8; FINGERPRINT_BYTES]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if f.alternate() && i > 0 {
+                write!(f, ":")?;
+            }
+
+            write!(f, "{:02x}", b)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use super::*;
+use crate::KeyPair;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_fingerprint() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate_in_group(&keys.public.key().group(), &mut rng);
+
+    let fingerprint = keys.public.fingerprint();
+    assert_eq!(fingerprint, Fingerprint::of(keys.public.key()));
+    assert_eq!(fingerprint, keys.public.key().fingerprint());
+    assert_ne!(fingerprint, other_keys.public.fingerprint());
+    assert_ne!(fingerprint, keys.private.key().fingerprint());
+
+    // the fingerprint only depends on the key's parameters
+    let reloaded = crate::PublicKey::deserialize(&keys.public.serialize());
+    assert_eq!(reloaded.fingerprint(), fingerprint);
+}
+
+#[test]
+fn test_fingerprint_display() {
+    let mut bytes = [0_u8; FINGERPRINT_BYTES];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = (i * 17) as u8;
+    }
+    let fingerprint = Fingerprint::from(bytes);
+
+    assert_eq!(fingerprint.to_hex(), "00112233445566778899aabbccddeeff");
+    assert_eq!(
+        fingerprint.to_colon_hex(),
+        "00:11:22:33:44:55:66:77:88:99:aa:bb:cc:dd:ee:ff"
+    );
+    assert_eq!(format!("{}", fingerprint), fingerprint.to_hex());
+    assert_eq!(format!("{:#}", fingerprint), fingerprint.to_colon_hex());
+    assert_eq!(fingerprint.as_bytes(), &bytes);
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{Block, KeyError};
+use math::{mod_exp, primes, Num};
+use rand::Rng;
+
+/**
+ * A group that keys can be generated in: a safe prime and one of its primitive roots.
+ *
+ * Generating a key normally searches for a new random safe prime, which is the slow part of key
+ * generation. Keys can instead be generated in a fixed, well-known group with
+ * `KeyPair::generate_in_group`, which is fast and lets key pairs agree on shared secrets with
+ * `dh::derive_shared_secret`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Group {
+    prime: Num,
+    root: Num,
+}
+
+/**
+ * The fixed groups that can be looked up with `Group::by_name`.
+ *
+ * The MODP groups of RFC 3526 use primes of 1536 bits or more, which don't fit in a `Num`, so
+ * these groups are chosen the same way at the sizes that do: the largest safe prime that fits,
+ * with its smallest primitive root. Unlike RFC 3526, the root generates the whole group rather
+ * than the quadratic residues, since encryption relies on it being a primitive root.
+ */
+#[cfg(not(feature = "wide"))]
+const NAMED_GROUPS: &[(&str, Group)] = &[("modp-64", Group::MODP_64)];
+#[cfg(feature = "wide")]
+const NAMED_GROUPS: &[(&str, Group)] = &[("modp-128", Group::MODP_128)];
+
+impl Group {
+    /**
+     * The largest 64-bit safe prime, `2^64 - 1469`, with the primitive root 2.
+     */
+    #[cfg(not(feature = "wide"))]
+    pub const MODP_64: Self = Self {
+        prime: 18446744073709550147,
+        root: 2,
+    };
+
+    /**
+     * The largest 128-bit safe prime, `2^128 - 15449`, with the primitive root 5.
+     */
+    #[cfg(feature = "wide")]
+    pub const MODP_128: Self = Self {
+        prime: 340282366920938463463374607431768196007,
+        root: 5,
+    };
+
+    /**
+     * Create a group from a prime and root without checking them. Groups from untrusted sources
+     * should be checked with `validate`.
+     */
+    pub fn new(prime: Num, root: Num) -> Self {
+        Self { prime, root }
+    }
+
+    /**
+     * Look up one of the fixed groups by its name, e.g., `modp-64` (or `modp-128` with the `wide`
+     * feature).
+     */
+    pub fn by_name(name: &str) -> Option<Self> {
+        NAMED_GROUPS
+            .iter()
+            .find(|(group_name, _)| *group_name == name)
+            .map(|&(_, group)| group)
+    }
+
+    /**
+     * Get the names of the fixed groups accepted by `by_name`.
+     */
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        NAMED_GROUPS.iter().map(|&(name, _)| name)
+    }
+
+    /**
+     * Get the prime modulus of the group.
+     */
+    pub fn prime(&self) -> Num {
+        self.prime
+    }
+
+    /**
+     * Get the primitive root of the group's prime.
+     */
+    pub fn root(&self) -> Num {
+        self.root
+    }
+
+    /**
+     * Check that keys can be generated in the group: the prime must be a safe prime large enough
+     * to hold a block, and the root must be a primitive root of the prime. Primality is tested
+     * probabilistically using `rng`.
+     */
+    pub fn validate<T: Rng>(&self, rng: &mut T) -> Result<(), KeyError> {
+        const WITNESS_COUNT: usize = primes::DEFAULT_WITNESS_COUNT;
+
+        if self.prime <= Block::MAX as Num {
+            return Err(KeyError::PrimeTooSmall);
+        }
+
+        // all keys use safe primes, i.e., primes of the form `2q + 1` where `q` is also prime
+        let q = (self.prime - 1) / 2;
+        if !primes::is_prime(self.prime, WITNESS_COUNT, rng)
+            || !primes::is_prime(q, WITNESS_COUNT, rng)
+        {
+            return Err(KeyError::NotSafePrime);
+        }
+
+        // the order of the group is `2q`, so an element is a generator iff it doesn't have order
+        // 1, 2, or `q`
+        let is_generator =
+            self.root > 1 && self.root < self.prime - 1 && mod_exp(self.root, q, self.prime) != 1;
+        if !is_generator {
+            return Err(KeyError::InvalidRoot);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use super::*;
+use crate::{decrypt_block, encrypt_block, Encoding, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_named_groups() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for name in Group::names() {
+        let group = Group::by_name(name).unwrap();
+        assert_eq!(group.validate(&mut rng), Ok(()), "invalid group {}", name);
+        assert!(Encoding::QuadraticResidue.supports(group.prime()));
+
+        let keys = KeyPair::generate_in_group(&group, &mut rng);
+        assert_eq!(keys.public.key().group(), group);
+        assert_eq!(keys.validate(&mut rng), Ok(()));
+
+        let encoding = Encoding::QuadraticResidue;
+        let (c1, c2) = encrypt_block(Block::MAX, &keys.public, encoding, &mut rng).unwrap();
+        assert_eq!(
+            decrypt_block(c1, c2, &keys.private, encoding).unwrap(),
+            Block::MAX
+        );
+    }
+
+    assert_eq!(Group::by_name("modp-2048"), None);
+    assert_eq!(Group::by_name(""), None);
+}
+
+#[test]
+fn test_group_validation() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let group = keys.public.key().group();
+    assert_eq!(group.validate(&mut rng), Ok(()));
+
+    let check = |prime: Num, root: Num, expected: Result<(), KeyError>| {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(Group::new(prime, root).validate(&mut rng), expected);
+    };
+    check(Block::MAX as Num, 2, Err(KeyError::PrimeTooSmall));
+    check(group.prime() + 2, group.root(), Err(KeyError::NotSafePrime));
+    check(group.prime(), 1, Err(KeyError::InvalidRoot));
+    check(group.prime(), group.prime() - 1, Err(KeyError::InvalidRoot));
+}
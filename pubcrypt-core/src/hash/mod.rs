@@ -0,0 +1,179 @@
+/*!
+ * The SHA-256 hash function, as specified in FIPS 180-4.
+ *
+ * A digest is computed incrementally by creating a `Sha256`, adding the message with `update` (or
+ * by writing to it, since it implements `Write`), and finishing with `finalize`. `sha256` computes
+ * a digest in one step.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use std::io::{self, Write};
+
+/**
+ * The number of bytes in a SHA-256 digest.
+ */
+pub const DIGEST_BYTES: usize = 32;
+
+/**
+ * The number of bytes in each block processed by the compression function.
+ */
+pub const CHUNK_BYTES: usize = 64;
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/**
+ * An incremental SHA-256 computation. Data is added with `update`, and the digest is produced by
+ * `finalize`.
+ */
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    chunk: [u8; CHUNK_BYTES],
+    chunk_len: usize,
+    total_len: u64,
+}
+
+impl Sha256 {
+    /**
+     * Start computing a digest of an empty message.
+     */
+    pub fn new() -> Self {
+        Self {
+            state: INITIAL_STATE,
+            chunk: [0_u8; CHUNK_BYTES],
+            chunk_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /**
+     * Add `data` to the hashed message.
+     */
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(data.len() as u64);
+
+        while !data.is_empty() {
+            let count = (CHUNK_BYTES - self.chunk_len).min(data.len());
+            self.chunk[self.chunk_len..self.chunk_len + count].copy_from_slice(&data[..count]);
+            self.chunk_len += count;
+            data = &data[count..];
+
+            if self.chunk_len == CHUNK_BYTES {
+                compress(&mut self.state, &self.chunk);
+                self.chunk_len = 0;
+            }
+        }
+    }
+
+    /**
+     * Pad the message and return its digest.
+     */
+    pub fn finalize(mut self) -> [u8; DIGEST_BYTES] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        // a single 1 bit, then zeros up to the last 8 bytes of a chunk, then the message length
+        self.update(&[0x80]);
+        while self.chunk_len != CHUNK_BYTES - 8 {
+            self.update(&[0]);
+        }
+        self.update(&bit_len.to_be_bytes());
+
+        let mut digest = [0_u8; DIGEST_BYTES];
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(&self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for Sha256 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/**
+ * Compute the digest of `data` in one step.
+ */
+pub fn sha256(data: &[u8]) -> [u8; DIGEST_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/**
+ * Run the compression function on `chunk`, updating `state`.
+ */
+fn compress(state: &mut [u32; 8], chunk: &[u8; CHUNK_BYTES]) {
+    let mut schedule = [0_u32; 64];
+    for (word, bytes) in schedule.iter_mut().zip(chunk.chunks_exact(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+
+    for i in 16..64 {
+        let s0 = schedule[i - 15].rotate_right(7)
+            ^ schedule[i - 15].rotate_right(18)
+            ^ (schedule[i - 15] >> 3);
+        let s1 = schedule[i - 2].rotate_right(17)
+            ^ schedule[i - 2].rotate_right(19)
+            ^ (schedule[i - 2] >> 10);
+        schedule[i] = schedule[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(schedule[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let temp1 = h
+            .wrapping_add(s1)
+            .wrapping_add(choice)
+            .wrapping_add(ROUND_CONSTANTS[i])
+            .wrapping_add(schedule[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(majority);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+
+    for (word, value) in state.iter_mut().zip(&[a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(*value);
+    }
+}
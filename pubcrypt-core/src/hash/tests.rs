@@ -0,0 +1,52 @@
+use super::*;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_sha256_vectors() {
+    let vectors: &[(&[u8], &str)] = &[
+        (
+            b"",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ),
+        (
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+    ];
+
+    for &(message, expected) in vectors {
+        assert_eq!(to_hex(&sha256(message)), expected);
+    }
+
+    let million_a = vec![b'a'; 1_000_000];
+    assert_eq!(
+        to_hex(&sha256(&million_a)),
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+}
+
+#[test]
+fn test_sha256_incremental() {
+    let message: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let expected = sha256(&message);
+
+    for &chunk_len in &[1, 7, 63, 64, 65, 200] {
+        let mut hasher = Sha256::new();
+        for chunk in message.chunks(chunk_len) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), expected);
+    }
+
+    // digests can also be computed by copying a reader into the hasher
+    let mut hasher = Sha256::default();
+    io::copy(&mut &message[..], &mut hasher).unwrap();
+    assert_eq!(hasher.finalize(), expected);
+}
@@ -0,0 +1,423 @@
+/*!
+ * The header written at the start of each ciphertext.
+ *
+ * A header consists of the magic bytes `PUBCRYPT`, a one-byte format version, the big-endian
+ * two-byte length of the remaining fields, and the fields themselves. Each field is a one-byte tag,
+ * a one-byte length, and the field's value, which allows fields to be added in later versions
+ * without changing the layout.
+ *
+ * Ciphertexts written before headers were introduced have no header at all, and are treated as
+ * version 0. Because each legacy ciphertext starts with a `Num` less than the key's prime, it can
+ * only be mistaken for a header if that `Num` happens to begin with the magic bytes.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    oaep, Algorithm, CryptError, Encoding, Fingerprint, Mode, Num, Padding, BLOCK_BYTES,
+    FINGERPRINT_BYTES, NUM_BYTES,
+};
+use std::io::{self, BufRead, Read, Write};
+
+/**
+ * The bytes that every versioned ciphertext starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRYPT";
+
+/**
+ * The format version written by this version of the library.
+ */
+pub const CURRENT_VERSION: u8 = 1;
+
+const LEGACY_VERSION: u8 = 0;
+const FIXED_BYTES: usize = MAGIC.len() + 3;
+
+// the block size of ciphertexts that don't record one
+const LEGACY_BLOCK_BYTES: usize = 4;
+
+// field tags
+const ENCODING_TAG: u8 = 1;
+const BLOCK_BYTES_TAG: u8 = 2;
+const MODE_TAG: u8 = 3;
+const SEQUENCE_TAG: u8 = 4;
+const PADDING_TAG: u8 = 5;
+const OAEP_TAG: u8 = 6;
+const AUTHENTICATED_TAG: u8 = 7;
+const WRAPPED_KEY_TAG: u8 = 8;
+const ALGORITHM_TAG: u8 = 9;
+const CHECKSUM_TAG: u8 = 10;
+const FILE_TAG: u8 = 11;
+const CHUNKED_TAG: u8 = 12;
+const RECIPIENT_TAG: u8 = 13;
+const ARCHIVE_TAG: u8 = 14;
+
+/**
+ * The parameters needed to decrypt a ciphertext.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    version: u8,
+    /**
+     * The algorithm the ciphertext was encrypted with. Only algorithms other than
+     * `Algorithm::ElGamal` are recorded, so ElGamal headers are the same as before algorithms
+     * were introduced.
+     */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub algorithm: Algorithm,
+    pub encoding: Encoding,
+    /**
+     * The number of plaintext bytes in each block.
+     */
+    pub block_bytes: usize,
+    pub mode: Mode,
+    /**
+     * The number of bytes above the plaintext in each block that hold the block's sequence
+     * number, or 0 if blocks aren't numbered. The sequence number's top bit marks the final block.
+     */
+    pub sequence_bytes: usize,
+    /**
+     * The number of bytes above the sequence number in each block that hold a checksum of the
+     * block's index and contents, or 0 if blocks aren't checksummed. Only recorded when nonzero,
+     * so other headers are the same as before checksums were introduced.
+     */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub checksum_bytes: usize,
+    /**
+     * How the final block is padded. Ignored in CTR and hybrid modes, which have no padding.
+     */
+    pub padding: Padding,
+    /**
+     * Whether each block is passed through an OAEP-style randomized transform before encryption,
+     * which uses some of the space above the plaintext and sequence number.
+     */
+    pub oaep: bool,
+    /**
+     * Whether the ciphertext is authenticated with a MAC (see `stream::EncryptWriter`).
+     */
+    pub authenticated: bool,
+    /**
+     * The ElGamal pair wrapping the symmetric key in `Mode::Hybrid`, or `None` in other modes.
+     * `stream::EncryptWriter` fills this in when the header is written.
+     */
+    pub wrapped_key: Option<(Num, Num)>,
+    /**
+     * Whether the plaintext is a `container` holding a file's contents and metadata, rather than
+     * the bare contents. Only recorded when set.
+     */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub contains_file: bool,
+    /**
+     * Whether the plaintext is an `archive` holding a directory tree, rather than the bare
+     * contents. Only recorded when set.
+     */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub archive: bool,
+    /**
+     * Whether everything after the header is framed in length-prefixed chunks (see `stream`), so
+     * that the end of the ciphertext can be found without reaching the end of its input. Only
+     * recorded when set.
+     */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub chunked: bool,
+    /**
+     * The fingerprint of the public key the ciphertext was encrypted for, so that decrypting with
+     * another key fails with `CryptError::WrongRecipient` before anything is decrypted. `None`
+     * for anonymous ciphertexts, which don't reveal who they're for. Only recorded when present.
+     */
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub recipient: Option<Fingerprint>,
+}
+
+impl Header {
+    /**
+     * Create a header for the current format version, with blocks of `BLOCK_BYTES` bytes in ECB
+     * mode.
+     */
+    pub fn new(encoding: Encoding) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            algorithm: Algorithm::ElGamal,
+            encoding,
+            block_bytes: BLOCK_BYTES,
+            mode: Mode::Ecb,
+            sequence_bytes: 0,
+            checksum_bytes: 0,
+            padding: Padding::Random,
+            oaep: false,
+            authenticated: false,
+            wrapped_key: None,
+            contains_file: false,
+            archive: false,
+            chunked: false,
+            recipient: None,
+        }
+    }
+
+    /**
+     * Create a header describing a legacy, headerless ciphertext.
+     */
+    pub fn legacy() -> Self {
+        Self {
+            version: LEGACY_VERSION,
+            algorithm: Algorithm::ElGamal,
+            encoding: Encoding::Raw,
+            block_bytes: LEGACY_BLOCK_BYTES,
+            mode: Mode::Ecb,
+            sequence_bytes: 0,
+            checksum_bytes: 0,
+            padding: Padding::Random,
+            oaep: false,
+            authenticated: false,
+            wrapped_key: None,
+            contains_file: false,
+            archive: false,
+            chunked: false,
+            recipient: None,
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /**
+     * Get the number of bytes of each `Block` used by the header's blocks, including their
+     * sequence numbers, checksums, and the OAEP transform.
+     */
+    pub(crate) fn block_width(&self) -> usize {
+        let oaep_bytes = if self.oaep { oaep::OVERHEAD_BYTES } else { 0 };
+        self.block_bytes + self.sequence_bytes + self.checksum_bytes + oaep_bytes
+    }
+
+    /**
+     * Get the largest block size that fits in a `Block` alongside the sequence numbers,
+     * checksums, and OAEP transform requested by the header.
+     */
+    pub fn max_block_bytes(&self) -> usize {
+        let width = self.block_width() - self.block_bytes;
+        BLOCK_BYTES.saturating_sub(width)
+    }
+
+    /**
+     * Get the fields of the header as `(tag, value)` pairs.
+     */
+    fn fields(&self) -> Vec<(u8, Vec<u8>)> {
+        let mut fields = vec![
+            (ENCODING_TAG, vec![self.encoding.id()]),
+            (BLOCK_BYTES_TAG, vec![self.block_bytes as u8]),
+            (MODE_TAG, vec![self.mode.id()]),
+            (SEQUENCE_TAG, vec![self.sequence_bytes as u8]),
+            (PADDING_TAG, vec![self.padding.id()]),
+            (OAEP_TAG, vec![self.oaep as u8]),
+            (AUTHENTICATED_TAG, vec![self.authenticated as u8]),
+        ];
+
+        if let Some((c1, c2)) = self.wrapped_key {
+            let mut value = c1.to_be_bytes().to_vec();
+            value.extend_from_slice(&c2.to_be_bytes());
+            fields.push((WRAPPED_KEY_TAG, value));
+        }
+
+        if self.algorithm != Algorithm::ElGamal {
+            fields.push((ALGORITHM_TAG, vec![self.algorithm.id()]));
+        }
+
+        if self.checksum_bytes > 0 {
+            fields.push((CHECKSUM_TAG, vec![self.checksum_bytes as u8]));
+        }
+
+        if self.contains_file {
+            fields.push((FILE_TAG, vec![1]));
+        }
+
+        if self.archive {
+            fields.push((ARCHIVE_TAG, vec![1]));
+        }
+
+        if self.chunked {
+            fields.push((CHUNKED_TAG, vec![1]));
+        }
+
+        if let Some(recipient) = self.recipient {
+            fields.push((RECIPIENT_TAG, recipient.as_bytes().to_vec()));
+        }
+
+        fields
+    }
+
+    /**
+     * Get the number of bytes the header occupies in the ciphertext.
+     */
+    pub fn len(&self) -> usize {
+        if self.version == LEGACY_VERSION {
+            return 0;
+        }
+
+        let fields_len: usize = self.fields().iter().map(|(_, v)| v.len() + 2).sum();
+        FIXED_BYTES + fields_len
+    }
+
+    /**
+     * Check if the header occupies no bytes, which is only the case for legacy ciphertexts.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     * Write the header to `writer`. Nothing is written for legacy headers.
+     */
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), CryptError> {
+        if self.version == LEGACY_VERSION {
+            return Ok(());
+        }
+
+        let mut fields = Vec::new();
+        for (tag, value) in self.fields() {
+            fields.push(tag);
+            fields.push(value.len() as u8);
+            fields.extend_from_slice(&value);
+        }
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[self.version])?;
+        writer.write_all(&(fields.len() as u16).to_be_bytes())?;
+        writer.write_all(&fields)?;
+        Ok(())
+    }
+
+    /**
+     * Read a header from `reader`, leaving the reader at the start of the first ciphertext block.
+     * If the ciphertext doesn't start with `MAGIC`, nothing is consumed and a legacy header is
+     * returned. The magic bytes are looked for in what `reader` has already buffered, so a reader
+     * that can return short reads, like a pipe, should be wrapped in a `PeekReader` with
+     * `MAGIC.len()` bytes peeked first, as `DecryptReader::new` does.
+     *
+     * Headers without a block size, mode, sequence number size, padding, OAEP flag, or
+     * authentication flag are from before those were recorded, so their blocks are 4 bytes, in
+     * ECB mode, unnumbered, randomly padded, and not transformed, and the ciphertext isn't
+     * authenticated. Headers without an algorithm are for `Algorithm::ElGamal`, those without a
+     * checksum size have no checksums, those without a file or archive flag hold bare contents,
+     * those without a chunked flag aren't chunked, and those without a recipient are anonymous.
+     *
+     * Returns `CryptError::UnsupportedVersion` if the header is from a newer version of the format,
+     * and `CryptError::InvalidHeader` if it's malformed.
+     */
+    pub fn read<R: BufRead>(reader: &mut R) -> Result<Self, CryptError> {
+        if !reader.fill_buf()?.starts_with(&MAGIC) {
+            return Ok(Self::legacy());
+        }
+
+        let mut fixed = [0_u8; FIXED_BYTES];
+        read_header_bytes(reader, &mut fixed)?;
+
+        let version = fixed[MAGIC.len()];
+        if version == LEGACY_VERSION || version > CURRENT_VERSION {
+            return Err(CryptError::UnsupportedVersion(version));
+        }
+
+        let fields_len = u16::from_be_bytes([fixed[MAGIC.len() + 1], fixed[MAGIC.len() + 2]]);
+        let mut fields = vec![0_u8; fields_len as usize];
+        read_header_bytes(reader, &mut fields)?;
+
+        let mut encoding = None;
+        let mut block_bytes = LEGACY_BLOCK_BYTES;
+        let mut mode = Mode::Ecb;
+        let mut sequence_bytes = 0;
+        let mut checksum_bytes = 0;
+        let mut padding = Padding::Random;
+        let mut oaep = false;
+        let mut authenticated = false;
+        let mut wrapped_key = None;
+        let mut contains_file = false;
+        let mut archive = false;
+        let mut chunked = false;
+        let mut recipient = None;
+        let mut algorithm = Algorithm::ElGamal;
+        let mut remaining = &fields[..];
+
+        while !remaining.is_empty() {
+            let (tag, value_len) = match remaining {
+                [tag, value_len, ..] => (*tag, *value_len as usize),
+                _ => return Err(CryptError::InvalidHeader),
+            };
+
+            let value = remaining
+                .get(2..2 + value_len)
+                .ok_or(CryptError::InvalidHeader)?;
+            remaining = &remaining[2 + value_len..];
+
+            match (tag, value) {
+                (ENCODING_TAG, [id]) => encoding = Encoding::from_id(*id),
+                (BLOCK_BYTES_TAG, [size]) if *size > 0 => block_bytes = *size as usize,
+                (MODE_TAG, [id]) => mode = Mode::from_id(*id).ok_or(CryptError::InvalidHeader)?,
+                (SEQUENCE_TAG, [size]) => sequence_bytes = *size as usize,
+                (PADDING_TAG, [id]) => {
+                    padding = Padding::from_id(*id).ok_or(CryptError::InvalidHeader)?
+                }
+                (OAEP_TAG, [0]) => oaep = false,
+                (OAEP_TAG, [1]) => oaep = true,
+                (AUTHENTICATED_TAG, [0]) => authenticated = false,
+                (AUTHENTICATED_TAG, [1]) => authenticated = true,
+                (WRAPPED_KEY_TAG, _) if value.len() == NUM_BYTES * 2 => {
+                    let mut c1_buf = [0_u8; NUM_BYTES];
+                    let mut c2_buf = [0_u8; NUM_BYTES];
+                    c1_buf.copy_from_slice(&value[..NUM_BYTES]);
+                    c2_buf.copy_from_slice(&value[NUM_BYTES..]);
+                    wrapped_key = Some((Num::from_be_bytes(c1_buf), Num::from_be_bytes(c2_buf)));
+                }
+                (ALGORITHM_TAG, [id]) => {
+                    algorithm = Algorithm::from_id(*id).ok_or(CryptError::InvalidHeader)?
+                }
+                (CHECKSUM_TAG, [size]) => checksum_bytes = *size as usize,
+                (FILE_TAG, [0]) => contains_file = false,
+                (FILE_TAG, [1]) => contains_file = true,
+                (ARCHIVE_TAG, [0]) => archive = false,
+                (ARCHIVE_TAG, [1]) => archive = true,
+                (CHUNKED_TAG, [0]) => chunked = false,
+                (CHUNKED_TAG, [1]) => chunked = true,
+                (RECIPIENT_TAG, _) if value.len() == FINGERPRINT_BYTES => {
+                    let mut bytes = [0_u8; FINGERPRINT_BYTES];
+                    bytes.copy_from_slice(value);
+                    recipient = Some(Fingerprint::from(bytes));
+                }
+                _ => return Err(CryptError::InvalidHeader),
+            }
+        }
+
+        // the wrapped key is present exactly when it's needed
+        if (mode == Mode::Hybrid) != wrapped_key.is_some() {
+            return Err(CryptError::InvalidHeader);
+        }
+
+        Ok(Self {
+            version,
+            algorithm,
+            encoding: encoding.ok_or(CryptError::InvalidHeader)?,
+            block_bytes,
+            mode,
+            sequence_bytes,
+            checksum_bytes,
+            padding,
+            oaep,
+            authenticated,
+            wrapped_key,
+            contains_file,
+            archive,
+            chunked,
+            recipient,
+        })
+    }
+}
+
+/**
+ * Fill `buf` from `reader`, treating a premature EOF as an invalid header.
+ */
+fn read_header_bytes<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), CryptError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => CryptError::InvalidHeader,
+        _ => CryptError::Io(e),
+    })
+}
@@ -0,0 +1,262 @@
+use super::*;
+
+fn round_trip(header: &Header) -> Header {
+    let mut bytes = Vec::new();
+    header.write(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), header.len());
+
+    let mut reader = &bytes[..];
+    let result = Header::read(&mut reader).unwrap();
+    assert!(reader.is_empty());
+    result
+}
+
+#[test]
+fn test_header_round_trip() {
+    for &encoding in &[Encoding::Raw, Encoding::QuadraticResidue] {
+        let header = Header::new(encoding);
+        assert_eq!(round_trip(&header), header);
+        assert_eq!(header.version(), CURRENT_VERSION);
+        assert_eq!(header.block_bytes, BLOCK_BYTES);
+    }
+
+    let narrow = Header {
+        block_bytes: 2,
+        ..Header::new(Encoding::Raw)
+    };
+    assert_eq!(round_trip(&narrow), narrow);
+
+    let chained = Header {
+        mode: Mode::Cbc,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&chained), chained);
+
+    let numbered = Header {
+        block_bytes: 2,
+        sequence_bytes: 2,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&numbered), numbered);
+
+    let checksummed = Header {
+        block_bytes: 1,
+        sequence_bytes: 1,
+        checksum_bytes: 2,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&checksummed), checksummed);
+    assert_eq!(checksummed.max_block_bytes(), BLOCK_BYTES - 3);
+
+    let packed = Header {
+        contains_file: true,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&packed), packed);
+
+    let archived = Header {
+        archive: true,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&archived), archived);
+
+    let chunked = Header {
+        chunked: true,
+        mode: Mode::Ctr,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&chunked), chunked);
+    assert_eq!(
+        packed.len(),
+        Header::new(Encoding::QuadraticResidue).len() + 3
+    );
+
+    let addressed = Header {
+        recipient: Some(Fingerprint::from([0xab; FINGERPRINT_BYTES])),
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&addressed), addressed);
+    assert_eq!(
+        addressed.len(),
+        Header::new(Encoding::QuadraticResidue).len() + 2 + FINGERPRINT_BYTES
+    );
+
+    for &padding in &[Padding::Pkcs7, Padding::Zero] {
+        let padded = Header {
+            padding,
+            ..Header::new(Encoding::Raw)
+        };
+        assert_eq!(round_trip(&padded), padded);
+    }
+
+    let transformed = Header {
+        block_bytes: 1,
+        oaep: true,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&transformed), transformed);
+    assert!(transformed.max_block_bytes() < BLOCK_BYTES);
+
+    let authenticated = Header {
+        authenticated: true,
+        mode: Mode::Ctr,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&authenticated), authenticated);
+
+    let hybrid = Header {
+        mode: Mode::Hybrid,
+        wrapped_key: Some((Num::MAX - 1, 2)),
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&hybrid), hybrid);
+
+    let rsa = Header {
+        algorithm: Algorithm::Rsa,
+        ..Header::new(Encoding::QuadraticResidue)
+    };
+    assert_eq!(round_trip(&rsa), rsa);
+
+    // only algorithms other than ElGamal are recorded
+    assert!(rsa.len() > Header::new(Encoding::QuadraticResidue).len());
+}
+
+#[test]
+fn test_wrapped_key_mismatch() {
+    // hybrid mode needs a wrapped key, and other modes can't have one
+    let unwrapped = Header {
+        mode: Mode::Hybrid,
+        ..Header::new(Encoding::Raw)
+    };
+    let wrapped = Header {
+        wrapped_key: Some((2, 3)),
+        ..Header::new(Encoding::Raw)
+    };
+
+    for header in &[unwrapped, wrapped] {
+        let mut bytes = Vec::new();
+        header.write(&mut bytes).unwrap();
+        assert!(matches!(
+            Header::read(&mut &bytes[..]),
+            Err(CryptError::InvalidHeader)
+        ));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_header_serde() {
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.mode = Mode::Cbc;
+    header.padding = Padding::Pkcs7;
+    header.authenticated = true;
+
+    let json = serde_json::to_value(&header).unwrap();
+    assert_eq!(json["encoding"], "quadratic_residue");
+    assert_eq!(json["mode"], "cbc");
+    assert_eq!(json["padding"], "pkcs7");
+    assert_eq!(serde_json::from_value::<Header>(json).unwrap(), header);
+}
+
+#[test]
+fn test_missing_block_size() {
+    // headers written before the block size was recorded only have an encoding
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&[CURRENT_VERSION, 0, 3, ENCODING_TAG, 1, 1]);
+
+    let header = Header::read(&mut &bytes[..]).unwrap();
+    assert_eq!(header.encoding, Encoding::QuadraticResidue);
+    assert_eq!(header.block_bytes, LEGACY_BLOCK_BYTES);
+    assert_eq!(header.mode, Mode::Ecb);
+    assert_eq!(header.sequence_bytes, 0);
+    assert_eq!(header.checksum_bytes, 0);
+    assert!(!header.contains_file);
+    assert!(!header.archive);
+    assert!(!header.chunked);
+    assert_eq!(header.recipient, None);
+    assert_eq!(header.padding, Padding::Random);
+    assert!(!header.oaep);
+    assert!(!header.authenticated);
+}
+
+#[test]
+fn test_legacy_header() {
+    let header = Header::legacy();
+    assert!(header.is_empty());
+    assert_eq!(header.encoding, Encoding::Raw);
+    assert_eq!(header.block_bytes, LEGACY_BLOCK_BYTES);
+
+    // legacy ciphertexts are left untouched
+    let ciphertext = [0x12_u8; 32];
+    let mut reader = &ciphertext[..];
+    assert_eq!(Header::read(&mut reader).unwrap(), header);
+    assert_eq!(reader.len(), ciphertext.len());
+
+    let mut bytes = Vec::new();
+    header.write(&mut bytes).unwrap();
+    assert!(bytes.is_empty());
+}
+
+#[test]
+fn test_invalid_header() {
+    let mut valid = Vec::new();
+    Header::new(Encoding::QuadraticResidue)
+        .write(&mut valid)
+        .unwrap();
+
+    let check = |bytes: &[u8], expected: fn(&CryptError) -> bool| {
+        let result = Header::read(&mut &bytes[..]);
+        assert!(expected(&result.unwrap_err()), "bytes: {:?}", bytes);
+    };
+
+    // newer or nonsensical versions
+    let mut newer = valid.clone();
+    newer[MAGIC.len()] = CURRENT_VERSION + 1;
+    check(&newer, |e| matches!(e, CryptError::UnsupportedVersion(_)));
+
+    let mut zero = valid.clone();
+    zero[MAGIC.len()] = 0;
+    check(&zero, |e| matches!(e, CryptError::UnsupportedVersion(0)));
+
+    // truncated headers
+    for len in MAGIC.len()..valid.len() {
+        check(&valid[..len], |e| matches!(e, CryptError::InvalidHeader));
+    }
+
+    // unknown encoding
+    let mut unknown_encoding = valid.clone();
+    unknown_encoding[FIXED_BYTES + 2] = 0xff;
+    check(&unknown_encoding, |e| {
+        matches!(e, CryptError::InvalidHeader)
+    });
+
+    // unknown mode
+    let mut unknown_mode = valid.clone();
+    unknown_mode[FIXED_BYTES + 8] = 0xff;
+    check(&unknown_mode, |e| matches!(e, CryptError::InvalidHeader));
+
+    // unknown padding
+    let mut unknown_padding = valid.clone();
+    unknown_padding[FIXED_BYTES + 14] = 0xff;
+    check(&unknown_padding, |e| matches!(e, CryptError::InvalidHeader));
+
+    // OAEP flag that isn't a boolean
+    let mut bad_oaep = valid.clone();
+    bad_oaep[FIXED_BYTES + 17] = 2;
+    check(&bad_oaep, |e| matches!(e, CryptError::InvalidHeader));
+
+    // empty blocks
+    let mut zero_block_size = valid.clone();
+    zero_block_size[FIXED_BYTES + 5] = 0;
+    check(&zero_block_size, |e| matches!(e, CryptError::InvalidHeader));
+
+    // unknown field
+    let mut unknown_field = valid.clone();
+    unknown_field[FIXED_BYTES] = 0xff;
+    check(&unknown_field, |e| matches!(e, CryptError::InvalidHeader));
+
+    // missing encoding
+    let mut missing = valid[..MAGIC.len() + 1].to_vec();
+    missing.extend_from_slice(&[0, 0]);
+    check(&missing, |e| matches!(e, CryptError::InvalidHeader));
+}
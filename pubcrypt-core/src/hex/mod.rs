@@ -0,0 +1,97 @@
+/*!
+ * Hexadecimal text for ciphertexts, which makes block-level problems easy to see and small
+ * ciphertexts easy to embed in text files.
+ *
+ * `format_ciphertext` writes the header on the first line, followed by each encrypted block on a
+ * line of its own. `decode` ignores all whitespace, so the line breaks are only for the reader.
+ * `is_hex_ciphertext` recognizes such text by the header's magic bytes on its first line.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    cipher::{ElGamal, PublicKeyCipher},
+    ec::EcElGamal,
+    header::{Header, MAGIC},
+    rsa::Rsa,
+    Algorithm, CryptError,
+};
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/**
+ * Encode `bytes` as lowercase hexadecimal, two digits per byte.
+ */
+pub fn encode(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        result.push(DIGITS[(byte >> 4) as usize] as char);
+        result.push(DIGITS[(byte & 0xf) as usize] as char);
+    }
+
+    result
+}
+
+/**
+ * Decode hexadecimal text in either case, ignoring any whitespace between digits. Returns `None`
+ * if there's any other character, or an odd number of digits.
+ */
+pub fn decode(text: &[u8]) -> Option<Vec<u8>> {
+    let digits = text
+        .iter()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|&c| (c as char).to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<_>>>()?;
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+
+    Some(
+        digits
+            .chunks(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect(),
+    )
+}
+
+/**
+ * Check whether `bytes` looks like a hex ciphertext, i.e., starts with the hex encoding of the
+ * header's magic bytes after any whitespace, in either case. The rest of the text is checked by
+ * `decode`.
+ */
+pub fn is_hex_ciphertext(bytes: &[u8]) -> bool {
+    let start = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let magic = encode(&MAGIC);
+    bytes.len() - start >= magic.len()
+        && bytes[start..start + magic.len()].eq_ignore_ascii_case(magic.as_bytes())
+}
+
+/**
+ * Format a complete ciphertext as hexadecimal lines: the header, then one line for each block the
+ * algorithm in the header encrypts to. Anything else after the header, such as an authentication
+ * tag, is split into lines of the same size, so the last line may be shorter. The result ends
+ * with a newline.
+ *
+ * Returns an error if the ciphertext doesn't start with a valid header.
+ */
+pub fn format_ciphertext(ciphertext: &[u8]) -> Result<String, CryptError> {
+    let header = Header::read(&mut &ciphertext[..])?;
+    let (header_bytes, blocks) = ciphertext.split_at(header.len());
+    let block_bytes = match header.algorithm {
+        Algorithm::ElGamal => ElGamal::CIPHERTEXT_BYTES,
+        Algorithm::Rsa => Rsa::CIPHERTEXT_BYTES,
+        Algorithm::EcElGamal => EcElGamal::CIPHERTEXT_BYTES,
+    };
+
+    let mut result = String::new();
+    for line in std::iter::once(header_bytes).chain(blocks.chunks(block_bytes)) {
+        result.push_str(&encode(line));
+        result.push('\n');
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,72 @@
+use super::*;
+use crate::{cipher, ecb, KeyPair, NUM_BYTES};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_encode_decode() {
+    assert_eq!(encode(&[]), "");
+    assert_eq!(encode(&[0x00, 0x1f, 0xa0, 0xff]), "001fa0ff");
+    assert_eq!(decode(b"001fa0ff"), Some(vec![0x00, 0x1f, 0xa0, 0xff]));
+    assert_eq!(
+        decode(b"00 1F\nA0\r\n ff\n"),
+        Some(vec![0x00, 0x1f, 0xa0, 0xff])
+    );
+    assert_eq!(decode(b""), Some(vec![]));
+
+    assert_eq!(decode(b"abc"), None);
+    assert_eq!(decode(b"0g"), None);
+    assert_eq!(decode(b"+1"), None);
+}
+
+#[test]
+fn test_format_ciphertext() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let plaintext = b"formatted as hex";
+
+    let mut ciphertext = Vec::new();
+    ecb::encrypt_ecb(&mut &plaintext[..], &mut ciphertext, &keys.public, &mut rng).unwrap();
+    let text = format_ciphertext(&ciphertext).unwrap();
+    assert_eq!(decode(text.as_bytes()), Some(ciphertext.clone()));
+    assert!(is_hex_ciphertext(text.as_bytes()));
+    assert!(is_hex_ciphertext(text.to_uppercase().as_bytes()));
+    assert!(!is_hex_ciphertext(&ciphertext));
+
+    // the header is on its own line, followed by one line per pair of `Num`s
+    let header = Header::read(&mut &ciphertext[..]).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], encode(&ciphertext[..header.len()]));
+    assert_eq!(
+        lines.len(),
+        1 + (ciphertext.len() - header.len()) / (NUM_BYTES * 2)
+    );
+    assert!(lines[1..].iter().all(|line| line.len() == NUM_BYTES * 4));
+
+    let mut decrypted = Vec::new();
+    let decoded = decode(text.as_bytes()).unwrap();
+    ecb::decrypt_ecb(&mut &decoded[..], &mut decrypted, &keys.private).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // other algorithms' blocks are split at their own size
+    let (public, _) = Rsa::generate(&mut rng).unwrap();
+    let mut ciphertext = Vec::new();
+    cipher::encrypt::<Rsa, _, _, _>(&mut &plaintext[..], &mut ciphertext, &public, &mut rng)
+        .unwrap();
+    let text = format_ciphertext(&ciphertext).unwrap();
+    assert!(text
+        .lines()
+        .skip(1)
+        .all(|line| line.len() == Rsa::CIPHERTEXT_BYTES * 2));
+
+    assert!(format_ciphertext(b"PUBCRYPT").is_err());
+}
+
+#[test]
+fn test_is_hex_ciphertext() {
+    assert!(is_hex_ciphertext(b"5055424352595054"));
+    assert!(is_hex_ciphertext(b"\n  5055424352595054\n01"));
+    assert!(!is_hex_ciphertext(b"505542435259"));
+    assert!(!is_hex_ciphertext(b"0011223344556677"));
+    assert!(!is_hex_ciphertext(b""));
+}
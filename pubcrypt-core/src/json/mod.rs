@@ -0,0 +1,154 @@
+/*!
+ * JSON key files, for tools that manage secrets as JSON rather than binary files. Requires the
+ * `json` feature.
+ *
+ * A key is an object with its prime, root, and value as decimal strings, which keeps 128-bit
+ * numbers (see the `wide` feature) intact in parsers that read every number as a double, and a
+ * `kind` of `"public"` or `"private"`:
+ *
+ * ```json
+ * {"prime": "23", "root": "5", "value": "8", "kind": "public"}
+ * ```
+ *
+ * A key pair is an object with `public` and `private` fields holding its two keys.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{Key, KeyError, KeyKind, KeyPair, Num, PrivateKey, PublicKey};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+struct JsonFingerprint<'a> {
+    path: &'a str,
+    fingerprint: String,
+    bits: u32,
+    comment: &'a str,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonKey {
+    prime: String,
+    root: String,
+    value: String,
+    kind: KeyKind,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonKeyPair {
+    public: JsonKey,
+    private: JsonKey,
+}
+
+impl JsonKey {
+    fn new(key: &Key, kind: KeyKind) -> Self {
+        Self {
+            prime: key.prime.to_string(),
+            root: key.root.to_string(),
+            value: key.value.to_string(),
+            kind,
+        }
+    }
+
+    fn to_key(&self) -> Result<Key, KeyError> {
+        let parse = |field: &str| {
+            // `Num::from_str` also accepts a leading `+`, which isn't part of the format
+            if field.starts_with('+') {
+                return Err(KeyError::InvalidJson);
+            }
+            field.parse::<Num>().map_err(|_| KeyError::InvalidJson)
+        };
+
+        Ok(Key {
+            prime: parse(&self.prime)?,
+            root: parse(&self.root)?,
+            value: parse(&self.value)?,
+        })
+    }
+}
+
+/**
+ * Convert `key` to pretty-printed JSON, labeled as the given kind.
+ */
+pub fn key_to_json(key: &Key, kind: KeyKind) -> String {
+    serde_json::to_string_pretty(&JsonKey::new(key, kind)).unwrap()
+}
+
+/**
+ * Convert a public key to pretty-printed JSON.
+ */
+pub fn public_key_to_json(key: &PublicKey) -> String {
+    key_to_json(key.key(), KeyKind::Public)
+}
+
+/**
+ * Convert a private key to pretty-printed JSON.
+ */
+pub fn private_key_to_json(key: &PrivateKey) -> String {
+    key_to_json(key.key(), KeyKind::Private)
+}
+
+/**
+ * Read a key written by `key_to_json`, returning it along with its kind. Like
+ * `Key::deserialize`, this doesn't check the key.
+ *
+ * Returns `KeyError::InvalidJson` if the text isn't a JSON key with exactly the expected fields,
+ * or a number doesn't fit in a `Num`.
+ */
+pub fn key_from_json(text: &str) -> Result<(Key, KeyKind), KeyError> {
+    let key: JsonKey = serde_json::from_str(text).map_err(|_| KeyError::InvalidJson)?;
+    Ok((key.to_key()?, key.kind))
+}
+
+/**
+ * Convert a key pair to pretty-printed JSON.
+ */
+pub fn key_pair_to_json(pair: &KeyPair) -> String {
+    let pair = JsonKeyPair {
+        public: JsonKey::new(pair.public.key(), KeyKind::Public),
+        private: JsonKey::new(pair.private.key(), KeyKind::Private),
+    };
+    serde_json::to_string_pretty(&pair).unwrap()
+}
+
+/**
+ * Convert a list of public keys, each with the path it was read from and its comment, to
+ * pretty-printed JSON: an array of objects with each key's `path`, colon-separated `fingerprint`,
+ * prime size in `bits`, and `comment`.
+ */
+pub fn fingerprints_to_json<'a, I>(keys: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a PublicKey, &'a str)>,
+{
+    let keys: Vec<JsonFingerprint> = keys
+        .into_iter()
+        .map(|(path, key, comment)| JsonFingerprint {
+            path,
+            fingerprint: key.fingerprint().to_colon_hex(),
+            bits: key.key().prime_bits(),
+            comment,
+        })
+        .collect();
+    serde_json::to_string_pretty(&keys).unwrap()
+}
+
+/**
+ * Read a key pair written by `key_pair_to_json`. The keys aren't checked, nor is whether they
+ * belong together.
+ *
+ * Returns `KeyError::InvalidJson` as in `key_from_json`, or if either key has the wrong kind.
+ */
+pub fn key_pair_from_json(text: &str) -> Result<KeyPair, KeyError> {
+    let pair: JsonKeyPair = serde_json::from_str(text).map_err(|_| KeyError::InvalidJson)?;
+    if pair.public.kind != KeyKind::Public || pair.private.kind != KeyKind::Private {
+        return Err(KeyError::InvalidJson);
+    }
+
+    Ok(KeyPair {
+        public: PublicKey::from(pair.public.to_key()?),
+        private: PrivateKey::from(pair.private.to_key()?),
+    })
+}
@@ -0,0 +1,95 @@
+use super::*;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_key_json() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let text = public_key_to_json(&keys.public);
+    let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(parsed["prime"], keys.public.key().prime().to_string());
+    assert_eq!(parsed["root"], keys.public.key().root().to_string());
+    assert_eq!(parsed["kind"], "public");
+    assert_eq!(
+        key_from_json(&text),
+        Ok((keys.public.key().clone(), KeyKind::Public))
+    );
+
+    let text = private_key_to_json(&keys.private);
+    assert_eq!(
+        key_from_json(&text),
+        Ok((keys.private.key().clone(), KeyKind::Private))
+    );
+
+    // numbers too large for a double survive, since they're strings
+    let key = Key {
+        prime: Num::MAX,
+        root: 2,
+        value: Num::MAX - 1,
+    };
+    let text = key_to_json(&key, KeyKind::Public);
+    assert!(text.contains(&format!("\"{}\"", Num::MAX)));
+    assert_eq!(key_from_json(&text), Ok((key, KeyKind::Public)));
+}
+
+#[test]
+fn test_key_pair_json() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let text = key_pair_to_json(&keys);
+    assert_eq!(key_pair_from_json(&text), Ok(keys.clone()));
+
+    let swapped = text.replacen("\"public\"", "\"private\"", 1);
+    assert!(key_pair_from_json(&swapped).is_err());
+}
+
+#[test]
+fn test_fingerprints_json() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let alice = KeyPair::generate(&mut rng).unwrap();
+    let bob = KeyPair::generate(&mut rng).unwrap();
+
+    let keys = [
+        ("alice.pub", &alice.public, "alice@laptop"),
+        ("bob.pub", &bob.public, "quotes \" and\nnewlines"),
+    ];
+    let text = fingerprints_to_json(keys.iter().copied());
+    let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let entries = parsed.as_array().unwrap();
+    assert_eq!(entries.len(), keys.len());
+    for (entry, (path, key, comment)) in entries.iter().zip(&keys) {
+        assert_eq!(entry["path"], *path);
+        assert_eq!(entry["fingerprint"], key.fingerprint().to_colon_hex());
+        assert_eq!(entry["bits"], key.key().prime_bits());
+        assert_eq!(entry["comment"], *comment);
+    }
+
+    assert_eq!(fingerprints_to_json(Vec::new()), "[]");
+}
+
+#[test]
+fn test_invalid_json() {
+    let invalid = [
+        "",
+        "{}",
+        r#"{"prime": "23", "root": "5", "value": "8"}"#,
+        r#"{"prime": 23, "root": "5", "value": "8", "kind": "public"}"#,
+        r#"{"prime": "23", "root": "5", "value": "8", "kind": "secret"}"#,
+        r#"{"prime": "0x17", "root": "5", "value": "8", "kind": "public"}"#,
+        r#"{"prime": "+23", "root": "5", "value": "8", "kind": "public"}"#,
+        r#"{"prime": "-23", "root": "5", "value": "8", "kind": "public"}"#,
+        r#"{"prime": "23", "root": "5", "value": "8", "kind": "public", "extra": 1}"#,
+    ];
+    for text in invalid {
+        assert_eq!(key_from_json(text), Err(KeyError::InvalidJson), "{}", text);
+    }
+
+    let too_large = format!(
+        r#"{{"prime": "{}0", "root": "5", "value": "8", "kind": "public"}}"#,
+        Num::MAX
+    );
+    assert_eq!(key_from_json(&too_large), Err(KeyError::InvalidJson));
+}
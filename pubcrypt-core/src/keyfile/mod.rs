@@ -0,0 +1,160 @@
+/*!
+ * Passphrase-protected private keys, for storing private keys at rest.
+ *
+ * A protected key consists of the magic bytes `PUBCRKEY`, the big-endian four-byte PBKDF2
+ * iteration count, a random salt, the serialized key XORed with a SHA-256 keystream, and an
+ * HMAC-SHA-256 tag over everything before it. The keystream and MAC keys are derived from the
+ * passphrase and salt with `mac::pbkdf2`, so each guess at the passphrase costs an attacker as
+ * many HMAC computations as the iteration count.
+ *
+ * Protected keys are longer than serialized keys, so the two can be told apart by their length
+ * alone, as `is_protected` does.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    hash::{Sha256, DIGEST_BYTES},
+    mac::{self, Hmac, MAC_BYTES},
+    CryptError, Key, PrivateKey,
+};
+use rand::Rng;
+
+/**
+ * The bytes that every protected key starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRKEY";
+
+/**
+ * The number of PBKDF2 iterations used by `protect_key`.
+ */
+pub const DEFAULT_ITERATIONS: u32 = 100_000;
+
+/**
+ * The number of bytes in the random salt of a protected key.
+ */
+pub const SALT_BYTES: usize = 16;
+
+/**
+ * The number of bytes in a protected key.
+ */
+pub const PROTECTED_KEY_BYTES: usize =
+    MAGIC.len() + ITERATIONS_BYTES + SALT_BYTES + Key::KEY_BYTES + MAC_BYTES;
+
+const ITERATIONS_BYTES: usize = 4;
+const SALT_START: usize = MAGIC.len() + ITERATIONS_BYTES;
+const KEY_START: usize = SALT_START + SALT_BYTES;
+const TAG_START: usize = KEY_START + Key::KEY_BYTES;
+
+/**
+ * Encrypt and authenticate `key` with a key derived from `passphrase`, using a random salt
+ * generated by `rng` and `DEFAULT_ITERATIONS` iterations of PBKDF2.
+ */
+pub fn protect_key<T: Rng>(
+    key: &PrivateKey,
+    passphrase: &[u8],
+    rng: &mut T,
+) -> [u8; PROTECTED_KEY_BYTES] {
+    protect_key_with_iterations(key, passphrase, DEFAULT_ITERATIONS, rng)
+}
+
+/**
+ * Protect `key` as in `protect_key`, with the given number of PBKDF2 iterations. More iterations
+ * make the passphrase harder to guess, and the key slower to load.
+ *
+ * Panics if `iterations` is 0.
+ */
+pub fn protect_key_with_iterations<T: Rng>(
+    key: &PrivateKey,
+    passphrase: &[u8],
+    iterations: u32,
+    rng: &mut T,
+) -> [u8; PROTECTED_KEY_BYTES] {
+    assert!(iterations > 0);
+
+    let mut result = [0_u8; PROTECTED_KEY_BYTES];
+    result[..MAGIC.len()].copy_from_slice(&MAGIC);
+    result[MAGIC.len()..SALT_START].copy_from_slice(&iterations.to_be_bytes());
+    rng.fill(&mut result[SALT_START..KEY_START]);
+
+    let (cipher_key, mac_key) = derive_keys(passphrase, &result[SALT_START..KEY_START], iterations);
+    result[KEY_START..TAG_START].copy_from_slice(&key.serialize());
+    apply_keystream(&cipher_key, &mut result[KEY_START..TAG_START]);
+
+    let tag = mac::hmac(&mac_key, &result[..TAG_START]);
+    result[TAG_START..].copy_from_slice(&tag);
+    result
+}
+
+/**
+ * Check whether `bytes` looks like a protected key rather than a serialized one.
+ */
+pub fn is_protected(bytes: &[u8]) -> bool {
+    bytes.len() == PROTECTED_KEY_BYTES && bytes.starts_with(&MAGIC)
+}
+
+/**
+ * Recover the private key from a key protected with `passphrase`.
+ *
+ * Returns `CryptError::InvalidHeader` if `bytes` isn't a protected key, or
+ * `CryptError::WrongPassphrase` if the passphrase is wrong or the key was modified. Like
+ * `PrivateKey::deserialize`, this doesn't check the key itself.
+ */
+pub fn unprotect_key(bytes: &[u8], passphrase: &[u8]) -> Result<PrivateKey, CryptError> {
+    if !is_protected(bytes) {
+        return Err(CryptError::InvalidHeader);
+    }
+
+    let mut iterations_buf = [0_u8; ITERATIONS_BYTES];
+    iterations_buf.copy_from_slice(&bytes[MAGIC.len()..SALT_START]);
+    let iterations = u32::from_be_bytes(iterations_buf);
+    if iterations == 0 {
+        return Err(CryptError::InvalidHeader);
+    }
+
+    let (cipher_key, mac_key) = derive_keys(passphrase, &bytes[SALT_START..KEY_START], iterations);
+    let mut mac = Hmac::new(&mac_key);
+    mac.update(&bytes[..TAG_START]);
+    if !mac.verify(&bytes[TAG_START..]) {
+        return Err(CryptError::WrongPassphrase);
+    }
+
+    let mut key_buf = [0_u8; Key::KEY_BYTES];
+    key_buf.copy_from_slice(&bytes[KEY_START..TAG_START]);
+    apply_keystream(&cipher_key, &mut key_buf);
+    Ok(PrivateKey::deserialize(&key_buf))
+}
+
+/**
+ * Derive the keystream and MAC keys from the passphrase and salt.
+ */
+fn derive_keys(
+    passphrase: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> ([u8; DIGEST_BYTES], [u8; MAC_BYTES]) {
+    let mut derived = [0_u8; DIGEST_BYTES + MAC_BYTES];
+    mac::pbkdf2(passphrase, salt, iterations, &mut derived);
+
+    let mut cipher_key = [0_u8; DIGEST_BYTES];
+    let mut mac_key = [0_u8; MAC_BYTES];
+    cipher_key.copy_from_slice(&derived[..DIGEST_BYTES]);
+    mac_key.copy_from_slice(&derived[DIGEST_BYTES..]);
+    (cipher_key, mac_key)
+}
+
+/**
+ * XOR `buf` with the SHA-256 counter mode keystream for `key`.
+ */
+fn apply_keystream(key: &[u8; DIGEST_BYTES], buf: &mut [u8]) {
+    for (counter, chunk) in buf.chunks_mut(DIGEST_BYTES).enumerate() {
+        let mut hasher = Sha256::new();
+        hasher.update(key);
+        hasher.update(&(counter as u64).to_be_bytes());
+
+        for (b, k) in chunk.iter_mut().zip(&hasher.finalize()) {
+            *b ^= k;
+        }
+    }
+}
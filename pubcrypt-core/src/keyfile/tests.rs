@@ -0,0 +1,69 @@
+use super::*;
+use crate::KeyPair;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+// few enough iterations to keep the tests fast
+const TEST_ITERATIONS: u32 = 10;
+
+#[test]
+fn test_protect_key() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let protected =
+        protect_key_with_iterations(&keys.private, b"hunter2", TEST_ITERATIONS, &mut rng);
+
+    assert!(is_protected(&protected));
+    assert!(!is_protected(&keys.private.serialize()));
+    assert_eq!(unprotect_key(&protected, b"hunter2").unwrap(), keys.private);
+
+    // the salt is random, so protecting the same key twice gives different bytes
+    let again = protect_key_with_iterations(&keys.private, b"hunter2", TEST_ITERATIONS, &mut rng);
+    assert_ne!(protected, again);
+    assert_eq!(unprotect_key(&again, b"hunter2").unwrap(), keys.private);
+}
+
+#[test]
+fn test_unprotect_errors() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let protected =
+        protect_key_with_iterations(&keys.private, b"hunter2", TEST_ITERATIONS, &mut rng);
+
+    assert!(matches!(
+        unprotect_key(&protected, b"hunter3"),
+        Err(CryptError::WrongPassphrase)
+    ));
+    assert!(matches!(
+        unprotect_key(&protected, b""),
+        Err(CryptError::WrongPassphrase)
+    ));
+
+    // everything after the magic is authenticated; only the low bit of the iteration count is
+    // flipped, since flipping a high bit would make deriving the keys take too long
+    for i in (SALT_START - 1)..PROTECTED_KEY_BYTES {
+        let mut modified = protected;
+        modified[i] ^= 1;
+        assert!(
+            unprotect_key(&modified, b"hunter2").is_err(),
+            "modifying byte {} went undetected",
+            i
+        );
+    }
+
+    assert!(matches!(
+        unprotect_key(&protected[..PROTECTED_KEY_BYTES - 1], b"hunter2"),
+        Err(CryptError::InvalidHeader)
+    ));
+    assert!(matches!(
+        unprotect_key(&keys.private.serialize(), b"hunter2"),
+        Err(CryptError::InvalidHeader)
+    ));
+
+    let mut no_iterations = protected;
+    no_iterations[MAGIC.len()..SALT_START].fill(0);
+    assert!(matches!(
+        unprotect_key(&no_iterations, b"hunter2"),
+        Err(CryptError::InvalidHeader)
+    ));
+}
@@ -0,0 +1,11 @@
+/**
+ * Which half of a key pair a key is, for key formats that record it alongside the key's numbers,
+ * such as `Key::to_text`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum KeyKind {
+    Public,
+    Private,
+}
@@ -0,0 +1,960 @@
+/*!
+ * ElGamal public-key encryption over the multiplicative group of integers modulo a safe prime.
+ *
+ * Keys are generated with `KeyPair::generate`, or recreated from a seed with `KeyPair::from_seed`.
+ * Messages held in memory are encrypted and decrypted with `encrypt_bytes` and `decrypt_bytes`, and
+ * individual plaintext blocks with `encrypt_block` and `decrypt_block` (or `encrypt_blocks` and
+ * `decrypt_blocks` for sequences of blocks). Each `Block` encrypts to a pair of `Num`s. Before
+ * encryption, each block is mapped into the group according to an `Encoding`.
+ * `decrypt_block_blinded` decrypts with a random `Blinding` to resist timing side channels, and
+ * the `stream` and `ecb` modules always use it. When many blocks are encrypted or decrypted with
+ * one key, an `EncryptContext` or `DecryptContext` does the key-dependent setup only once.
+ * Public keys are identified by their `Fingerprint`, and keys can be generated in a fixed `Group`
+ * instead of a newly generated one.
+ *
+ * For encrypting arbitrary byte streams, the `ecb` module handles splitting the input into blocks,
+ * padding the final block, and framing the ciphertext behind a `header::Header` that records the
+ * parameters needed to decrypt it. The `stream` module does the same for any `Read` or `Write`
+ * implementor, one piece at a time.
+ *
+ * The `hash` module provides SHA-256, and the `mac` module provides HMAC-SHA-256, which
+ * authenticated ciphertexts use to detect tampering. The same keys can also sign message hashes,
+ * either with classic ElGamal signatures in the `signature` module or with Schnorr signatures in
+ * the `schnorr` module, and agree on shared secrets with the `dh` module. The `threshold` module
+ * splits a private key into shares so that decryption needs the cooperation of several holders.
+ * The `deterministic` module supports reproducible encryption, which gives up semantic security.
+ *
+ * Private keys can be stored encrypted under a passphrase with the `keyfile` module, and key files
+ * can record when a key was created and expires, along with a comment, with the `metadata` module.
+ * Keys can be encoded in DER with `Key::to_der`, using the `der` module, as plain text with
+ * `Key::to_text`, using the `text` module, and with the `json` feature, as JSON with the `json`
+ * module.
+ * Keys and ciphertexts can be wrapped in base64 text for pasting into email with the `armor`
+ * module, and ciphertexts can be written as one line of hexadecimal per block with the `hex`
+ * module.
+ * Keys and ciphertexts from untrusted sources can be checked more thoroughly than when they're
+ * used with the `audit` module, and the crate's own arithmetic and encryption with the `selftest`
+ * module.
+ *
+ * The `cipher` module defines the `PublicKeyCipher` trait that ElGamal and any other `Algorithm`
+ * implement, along with key files and ciphertext framing that record the algorithm. The `rsa`
+ * module implements it with RSA, and the `ec` module with ElGamal over an elliptic curve.
+ *
+ * With the `serde` feature, keys and headers can be serialized with Serde.
+ *
+ * **NOTE**: This crate was written primarily for learning purposes, and is almost definitely not
+ * cryptographically secure.
+ */
+
+mod algorithm;
+pub mod archive;
+pub mod armor;
+pub mod audit;
+pub mod cipher;
+pub mod compact;
+pub mod container;
+mod context;
+pub mod der;
+pub mod detached;
+pub mod deterministic;
+pub mod dh;
+pub mod dhparam;
+pub mod ec;
+pub mod ecb;
+mod encoding;
+mod error;
+mod fingerprint;
+mod group;
+pub mod hash;
+pub mod header;
+pub mod hex;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod keyfile;
+mod kind;
+pub mod mac;
+pub mod metadata;
+pub mod mnemonic;
+mod mode;
+mod oaep;
+pub mod oneline;
+pub mod openpgp;
+mod padding;
+pub mod pair;
+pub mod peek;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod rsa;
+pub mod schnorr;
+pub mod selftest;
+pub mod signature;
+pub mod stream;
+pub mod text;
+pub mod threshold;
+pub mod volume;
+
+#[cfg(test)]
+mod tests;
+
+pub use algorithm::Algorithm;
+pub use context::{DecryptContext, EncryptContext};
+pub use encoding::Encoding;
+pub use error::{ArmorError, CryptError, KeyError, KeyGenError};
+pub use fingerprint::{Fingerprint, FINGERPRINT_BYTES};
+pub use group::Group;
+pub use kind::KeyKind;
+pub use mode::Mode;
+pub use padding::Padding;
+
+use rand::Rng;
+use std::{io::Write, mem};
+
+use math::{mod_exp, mod_exp_ct, mul_mod, primes};
+pub use math::{
+    primes::{PrimeError, DEFAULT_WITNESS_COUNT},
+    Num,
+};
+
+/**
+ * A block of plaintext. Each block is encrypted independently, so it must be less than the key's
+ * prime; with the `wide` feature, primes are large enough to hold 64-bit blocks.
+ */
+#[cfg(not(feature = "wide"))]
+pub type Block = u32;
+#[cfg(feature = "wide")]
+pub type Block = u64;
+
+/**
+ * The number of bytes in a plaintext block.
+ */
+pub const BLOCK_BYTES: usize = mem::size_of::<Block>();
+
+/**
+ * The number of bytes in a serialized `Num`. Each encrypted block is serialized as two `Num`s.
+ */
+pub const NUM_BYTES: usize = mem::size_of::<Num>();
+
+/**
+ * The number of bytes in a seed for `KeyPair::from_seed`.
+ */
+pub const SEED_BYTES: usize = 32;
+
+/**
+ * The number of PBKDF2 iterations used by `KeyPair::from_passphrase` to stretch a passphrase into
+ * a seed.
+ */
+pub const PASSPHRASE_ITERATIONS: u32 = 100_000;
+
+const PASSPHRASE_SALT: &[u8] = b"pubcrypt key seed";
+
+/**
+ * The number of attempts `KeyPair::generate` makes to find a prime before giving up.
+ */
+pub const DEFAULT_MAX_ATTEMPTS: usize = 8;
+
+// the number of candidates tested in the first attempt to find a prime; each attempt after it
+// tests twice as many as the last, so a short run of bad luck is retried quickly
+const FIRST_ATTEMPT_CANDIDATES: usize = 1 << 12;
+
+// the version recorded in DER-encoded keys; a later version can add fields to the sequence
+const DER_VERSION: Num = 1;
+
+/**
+ * The smallest prime that `KeyPair::generate` picks, which is large enough for every generated key
+ * to support `Encoding::QuadraticResidue`. `KeyPair::generate_in_range` rejects ranges below it.
+ */
+pub const PRIME_MIN: Num = 2 * (Block::MAX as Num + 1);
+
+/**
+ * The largest prime that `KeyPair::generate` picks.
+ */
+pub const PRIME_MAX: Num = Num::MAX;
+
+/**
+ * The raw parameters of one half of a key pair: the prime modulus, its primitive root, and either
+ * the private exponent or the corresponding public value.
+ *
+ * A `Key` doesn't know which half of the pair it is; wrap it in a `PublicKey` or `PrivateKey` to
+ * use it for encryption or decryption.
+ *
+ * With the `serde` feature, keys (along with `PublicKey`, `PrivateKey`, `KeyPair`, and
+ * `header::Header`) implement `Serialize` and `Deserialize`, as a structure with `prime`, `root`,
+ * and `value` fields. Like `deserialize`, this doesn't check the key, so keys from untrusted
+ * sources should be checked with `validate`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Key {
+    prime: Num,
+    root: Num,
+    value: Num,
+}
+
+impl Key {
+    pub const KEY_BYTES: usize = NUM_BYTES * 3;
+
+    /**
+     * Get the prime modulus of the key's group.
+     */
+    pub fn prime(&self) -> Num {
+        self.prime
+    }
+
+    /**
+     * Get the primitive root of the key's prime.
+     */
+    pub fn root(&self) -> Num {
+        self.root
+    }
+
+    /**
+     * Get the number of bits in the key's prime, which is how keys are usually sized.
+     */
+    pub fn prime_bits(&self) -> u32 {
+        Num::BITS - self.prime.leading_zeros()
+    }
+
+    /**
+     * Get the group that the key belongs to, i.e., its prime and root.
+     */
+    pub fn group(&self) -> Group {
+        Group::new(self.prime, self.root)
+    }
+
+    /**
+     * Get a short identifier for the key, computed from its prime, root, and value. Keys are
+     * normally identified by the fingerprint of their public half.
+     */
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(self)
+    }
+
+    /**
+     * Convert key to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; Self::KEY_BYTES] {
+        let mut result = [0u8; Self::KEY_BYTES];
+        result[..NUM_BYTES].copy_from_slice(&self.prime.to_be_bytes());
+        result[NUM_BYTES..NUM_BYTES * 2].copy_from_slice(&self.root.to_be_bytes());
+        result[NUM_BYTES * 2..NUM_BYTES * 3].copy_from_slice(&self.value.to_be_bytes());
+        result
+    }
+
+    /**
+     * Read key from serialized bytes.
+     */
+    pub fn deserialize(bytes: &[u8; Self::KEY_BYTES]) -> Self {
+        let mut prime_buf = [0u8; NUM_BYTES];
+        let mut root_buf = [0u8; NUM_BYTES];
+        let mut value_buf = [0u8; NUM_BYTES];
+        prime_buf.copy_from_slice(&bytes[..NUM_BYTES]);
+        root_buf.copy_from_slice(&bytes[NUM_BYTES..NUM_BYTES * 2]);
+        value_buf.copy_from_slice(&bytes[NUM_BYTES * 2..NUM_BYTES * 3]);
+
+        Self {
+            prime: Num::from_be_bytes(prime_buf),
+            root: Num::from_be_bytes(root_buf),
+            value: Num::from_be_bytes(value_buf),
+        }
+    }
+
+    /**
+     * Convert the key to DER: a SEQUENCE of INTEGERs holding the encoding's version (1), then the
+     * prime, root, and value. Unlike `serialize`, the result records its own structure, so fields
+     * can be added in later versions without misreading older keys.
+     */
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut contents = Vec::new();
+        for &field in &[DER_VERSION, self.prime, self.root, self.value] {
+            der::write_integer(&mut contents, field);
+        }
+
+        let mut result = Vec::new();
+        der::write_sequence(&mut result, &contents);
+        result
+    }
+
+    /**
+     * Read a key written by `to_der`. Like `deserialize`, this doesn't check the key.
+     *
+     * Returns `KeyError::InvalidDer` if the bytes aren't a DER-encoded key, and
+     * `KeyError::UnsupportedVersion` if the key is from a later version of the encoding.
+     */
+    pub fn from_der(bytes: &[u8]) -> Result<Self, KeyError> {
+        let mut reader = der::Reader::new(bytes);
+        let mut fields = reader.read_sequence()?;
+        reader.finish()?;
+
+        let version = fields.read_integer()?;
+        if version != DER_VERSION {
+            return Err(KeyError::UnsupportedVersion(
+                version.min(u8::MAX as Num) as u8
+            ));
+        }
+
+        let key = Self {
+            prime: fields.read_integer()?,
+            root: fields.read_integer()?,
+            value: fields.read_integer()?,
+        };
+        fields.finish()?;
+        Ok(key)
+    }
+
+    /**
+     * Convert the key to the plain-text format of the `text` module, labeled as the given kind:
+     * a tag on its own line, then the prime, root, and value in decimal.
+     */
+    pub fn to_text(&self, kind: KeyKind) -> String {
+        format!(
+            "{}\n{} {} {}\n",
+            text::tag(kind),
+            self.prime,
+            self.root,
+            self.value
+        )
+    }
+
+    /**
+     * Read a key in the plain-text format of the `text` module, returning it along with its
+     * kind. Like `deserialize`, this doesn't check the key.
+     *
+     * Returns `KeyError::InvalidText` if the text isn't a tag followed by exactly three numbers
+     * that fit in a `Num`.
+     */
+    pub fn from_text(text: &str) -> Result<(Self, KeyKind), KeyError> {
+        let words: Vec<&str> = text::parse_words(text).collect();
+        let (kind, numbers) = match words[..] {
+            [tag, prime, root, value] => (text::kind_from_tag(tag), [prime, root, value]),
+            _ => return Err(KeyError::InvalidText),
+        };
+
+        let kind = kind.ok_or(KeyError::InvalidText)?;
+        let parse = |word| text::parse_number(word).ok_or(KeyError::InvalidText);
+        let key = Self {
+            prime: parse(numbers[0])?,
+            root: parse(numbers[1])?,
+            value: parse(numbers[2])?,
+        };
+        Ok((key, kind))
+    }
+
+    /**
+     * Read key from serialized bytes as in `deserialize`, then check that it's valid with
+     * `validate`.
+     */
+    pub fn try_deserialize<T: Rng>(
+        bytes: &[u8; Self::KEY_BYTES],
+        rng: &mut T,
+    ) -> Result<Self, KeyError> {
+        let key = Self::deserialize(bytes);
+        key.validate(rng)?;
+        Ok(key)
+    }
+
+    /**
+     * Check that the key's parameters are usable for encryption or decryption: the prime must be a
+     * safe prime large enough to hold a block, the root must be a primitive root of the prime, and
+     * the value must be in the range `[1, prime)`. Primality is tested probabilistically using
+     * `rng`.
+     */
+    pub fn validate<T: Rng>(&self, rng: &mut T) -> Result<(), KeyError> {
+        self.group().validate(rng)?;
+
+        if self.value == 0 || self.value >= self.prime {
+            return Err(KeyError::InvalidValue);
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * A public key, used for encryption. The key's value is `root^x mod prime`, where `x` is the
+ * private exponent.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PublicKey(Key);
+
+impl PublicKey {
+    /**
+     * Convert key to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; Key::KEY_BYTES] {
+        self.0.serialize()
+    }
+
+    /**
+     * Read key from serialized bytes.
+     */
+    pub fn deserialize(bytes: &[u8; Key::KEY_BYTES]) -> Self {
+        Self(Key::deserialize(bytes))
+    }
+
+    /**
+     * Get the raw parameters of the key.
+     */
+    pub fn key(&self) -> &Key {
+        &self.0
+    }
+
+    /**
+     * Get the key's fingerprint, which identifies the key pair.
+     */
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.0.fingerprint()
+    }
+}
+
+impl From<Key> for PublicKey {
+    fn from(key: Key) -> Self {
+        Self(key)
+    }
+}
+
+/**
+ * A private key, used for decryption. The key's value is the secret exponent `x`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivateKey(Key);
+
+impl PrivateKey {
+    /**
+     * Convert key to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; Key::KEY_BYTES] {
+        self.0.serialize()
+    }
+
+    /**
+     * Read key from serialized bytes.
+     */
+    pub fn deserialize(bytes: &[u8; Key::KEY_BYTES]) -> Self {
+        Self(Key::deserialize(bytes))
+    }
+
+    /**
+     * Get the raw parameters of the key.
+     */
+    pub fn key(&self) -> &Key {
+        &self.0
+    }
+
+    /**
+     * Compute the public key that corresponds to this private key, whose value is
+     * `root^x mod prime` for the private exponent `x`. This recovers a lost public key, since
+     * the private key holds everything needed to recreate it.
+     */
+    pub fn derive_public(&self) -> PublicKey {
+        let key = &self.0;
+        PublicKey(Key {
+            prime: key.prime,
+            root: key.root,
+            value: mod_exp_ct(key.root, key.value, key.prime),
+        })
+    }
+}
+
+impl From<Key> for PrivateKey {
+    fn from(key: Key) -> Self {
+        Self(key)
+    }
+}
+
+/**
+ * A matching pair of public and private keys.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyPair {
+    pub public: PublicKey,
+    pub private: PrivateKey,
+}
+
+impl KeyPair {
+    /**
+     * Generate a pair of keys, private and public, to use for encryption. The
+     * return value is wrapped in a structure instead of just using a tuple to
+     * avoid confusion about which is the public and which is the private key.
+     *
+     * The search for the key's prime is made in up to `DEFAULT_MAX_ATTEMPTS` attempts, as in
+     * `generate_with_attempts`.
+     */
+    pub fn generate<T: Rng>(rng: &mut T) -> Result<Self, KeyGenError> {
+        Self::generate_with_witnesses(primes::DEFAULT_WITNESS_COUNT, rng)
+    }
+
+    /**
+     * Generate a pair of keys as in `generate`, making up to `max_attempts` attempts to find the
+     * key's prime. Each attempt tests twice as many random candidates as the one before it, so
+     * a budget of a few attempts makes failure vanishingly unlikely, while still bounding the
+     * time spent.
+     *
+     * Returns `KeyGenError::PrimeNotFound` if every attempt fails.
+     */
+    pub fn generate_with_attempts<T: Rng>(
+        max_attempts: usize,
+        rng: &mut T,
+    ) -> Result<Self, KeyGenError> {
+        Self::search(
+            PRIME_MIN,
+            PRIME_MAX,
+            primes::DEFAULT_WITNESS_COUNT,
+            max_attempts,
+            1,
+            rng,
+        )
+    }
+
+    /**
+     * Generate a pair of keys as in `generate_with_attempts`, searching for the key's prime across
+     * `thread_count` threads, which is much faster on a multi-core machine.
+     *
+     * Each thread draws its candidates from its own generator, seeded from `rng`, so the keys only
+     * depend on `rng` and the thread count, not on how the threads are scheduled (see
+     * `primes::pick_random_with_root_threaded`). With a single thread, this is the same as
+     * `generate_with_attempts`.
+     */
+    pub fn generate_with_threads<T: Rng>(
+        thread_count: usize,
+        max_attempts: usize,
+        rng: &mut T,
+    ) -> Result<Self, KeyGenError> {
+        Self::search(
+            PRIME_MIN,
+            PRIME_MAX,
+            primes::DEFAULT_WITNESS_COUNT,
+            max_attempts,
+            thread_count,
+            rng,
+        )
+    }
+
+    /**
+     * Generate a pair of keys as in `generate`, testing the primality of the key's prime with
+     * `witness_count` Miller-Rabin witnesses. Raising the count lowers the chance of a composite
+     * being mistaken for a prime, at the cost of slower generation.
+     */
+    pub fn generate_with_witnesses<T: Rng>(
+        witness_count: usize,
+        rng: &mut T,
+    ) -> Result<Self, KeyGenError> {
+        Self::search(
+            PRIME_MIN,
+            PRIME_MAX,
+            witness_count,
+            DEFAULT_MAX_ATTEMPTS,
+            1,
+            rng,
+        )
+    }
+
+    /**
+     * Generate a pair of keys as in `generate_with_threads`, picking the key's prime from
+     * `min..=max` instead of the full range, and testing its primality with `witness_count`
+     * Miller-Rabin witnesses as in `generate_with_witnesses`. A narrower range gives keys of a
+     * chosen size, e.g., `2^(n - 1)..=2^n - 1` for `n`-bit primes.
+     *
+     * Returns `KeyGenError::InvalidRange` if `min` is below `PRIME_MIN`, or if the range is too
+     * small to contain a suitable prime.
+     */
+    pub fn generate_in_range<T: Rng>(
+        min: Num,
+        max: Num,
+        witness_count: usize,
+        thread_count: usize,
+        max_attempts: usize,
+        rng: &mut T,
+    ) -> Result<Self, KeyGenError> {
+        if min < PRIME_MIN || min > max {
+            return Err(KeyGenError::InvalidRange);
+        }
+
+        Self::search(min, max, witness_count, max_attempts, thread_count, rng)
+    }
+
+    /**
+     * Search for the key's prime in `min..=max` with the given witness count, attempt budget, and
+     * thread count, then generate the keys in its group.
+     */
+    fn search<T: Rng>(
+        min: Num,
+        max: Num,
+        witness_count: usize,
+        max_attempts: usize,
+        thread_count: usize,
+        rng: &mut T,
+    ) -> Result<Self, KeyGenError> {
+        let mut candidate_count = FIRST_ATTEMPT_CANDIDATES;
+        for attempt in 1..=max_attempts {
+            match primes::pick_random_with_root_threaded(
+                min,
+                max,
+                witness_count,
+                candidate_count,
+                thread_count,
+                rng,
+            ) {
+                Ok((prime, root)) => {
+                    log::info!(
+                        "found the prime {} with primitive root {} in attempt {}",
+                        prime,
+                        root,
+                        attempt
+                    );
+                    return Ok(Self::generate_in_group(&Group::new(prime, root), rng));
+                }
+                Err(PrimeError::PrimeNotFound) => {
+                    log::debug!(
+                        "attempt {} found no prime among {} candidates; retrying",
+                        attempt,
+                        candidate_count
+                    );
+                    candidate_count = candidate_count.saturating_mul(2)
+                }
+                Err(PrimeError::InvalidRange) => return Err(KeyGenError::InvalidRange),
+            }
+        }
+
+        Err(KeyGenError::PrimeNotFound {
+            attempts: max_attempts,
+        })
+    }
+
+    /**
+     * Generate a pair of keys as in `generate`, with all randomness drawn from a
+     * `deterministic::DeterministicRng` seeded with `seed`. The same seed always gives the same
+     * keys, so keys can be recreated from a backed-up seed, and tests can use stable keys.
+     *
+     * Anyone who knows the seed can recreate the private key, so it must be kept as secret as the
+     * key itself, and generated randomly.
+     */
+    pub fn from_seed(seed: &[u8; SEED_BYTES]) -> Result<Self, KeyGenError> {
+        let mut rng = deterministic::DeterministicRng::with_label(b"pubcrypt key generation", seed);
+        Self::generate(&mut rng)
+    }
+
+    /**
+     * Generate a pair of keys as in `from_seed`, with the seed derived from `passphrase` using
+     * `PASSPHRASE_ITERATIONS` iterations of `mac::pbkdf2`. The same passphrase always gives the
+     * same keys, so the passphrase must be long and unpredictable; there's no salt, so anyone can
+     * try guessing it.
+     */
+    pub fn from_passphrase(passphrase: &[u8]) -> Result<Self, KeyGenError> {
+        let mut seed = [0_u8; SEED_BYTES];
+        mac::pbkdf2(
+            passphrase,
+            PASSPHRASE_SALT,
+            PASSPHRASE_ITERATIONS,
+            &mut seed,
+        );
+        Self::from_seed(&seed)
+    }
+
+    /**
+     * Check whether the public and private keys belong together, i.e., whether they have the same
+     * prime and root, and the public value is `root^x mod prime` for the private exponent `x`.
+     */
+    pub fn matches(&self) -> bool {
+        let private = self.private.key();
+        private.value < private.prime && self.private.derive_public() == self.public
+    }
+
+    /**
+     * Check that both keys are valid as in `Key::validate`, and that they belong together as in
+     * `matches`. Returns `KeyError::MismatchedPair` if they don't.
+     */
+    pub fn validate<T: Rng>(&self, rng: &mut T) -> Result<(), KeyError> {
+        // the private key is in the same group, so its parameters only need to be checked once
+        self.public.key().validate(rng)?;
+        if !self.matches() {
+            return Err(KeyError::MismatchedPair);
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Generate a pair of keys with a random private exponent in `group`, which is much faster
+     * than `generate` since no prime has to be found. The group can be a fixed one from
+     * `Group::by_name`, or that of an existing key (see `Key::group`). Key pairs in the same group
+     * can agree on a shared secret with `dh::derive_shared_secret`.
+     *
+     * `group` isn't checked, so groups from untrusted sources should be checked with
+     * `Group::validate` first.
+     */
+    pub fn generate_in_group<T: Rng>(group: &Group, rng: &mut T) -> Self {
+        let (prime, root) = (group.prime(), group.root());
+        let priv_exp = rng.gen_range(1..prime - 1);
+        let pub_exp = mod_exp(root, priv_exp, prime);
+
+        Self {
+            public: PublicKey(Key {
+                prime,
+                root,
+                value: pub_exp,
+            }),
+            private: PrivateKey(Key {
+                prime,
+                root,
+                value: priv_exp,
+            }),
+        }
+    }
+}
+
+/**
+ * Encrypt `block` with the given key and encoding, using `r` for exponentiation.
+ *
+ * This function is separate from the randomly encrypted block to allow
+ * deterministic testing.
+ */
+fn encrypt_block_det(
+    block: Block,
+    key: &PublicKey,
+    encoding: Encoding,
+    r: Num,
+) -> Result<(Num, Num), CryptError> {
+    let key = key.key();
+    if key.prime <= Block::MAX as Num {
+        return Err(CryptError::MalformedKey);
+    }
+
+    assert!(r < key.prime);
+    assert!(r > 0);
+
+    let message = encoding.encode(block, key.prime)?;
+    let er_mod_p = mod_exp(key.value, r, key.prime);
+    let c1 = mod_exp(key.root, r, key.prime);
+    let c2 = mul_mod(message, er_mod_p, key.prime);
+    Ok((c1, c2))
+}
+
+/**
+ * Encrypt `block` with the given public key and a random exponent generated by
+ * `rng`, mapping it into the group with `encoding`.
+ *
+ * Returns `CryptError::MalformedKey` if the key's prime is too small to encrypt a block with the
+ * given encoding.
+ */
+pub fn encrypt_block<T: Rng>(
+    block: Block,
+    key: &PublicKey,
+    encoding: Encoding,
+    rng: &mut T,
+) -> Result<(Num, Num), CryptError> {
+    let prime = key.key().prime;
+    if !encoding.supports(prime) {
+        return Err(CryptError::MalformedKey);
+    }
+
+    encrypt_block_det(block, key, encoding, rng.gen_range(1..prime))
+}
+
+/**
+ * Decrypt the ciphertext block represented by `c1` and `c2` with the given
+ * private key, reversing the `encoding` the block was encrypted with.
+ *
+ * Returns `CryptError::OutOfRange` if either component couldn't have been produced by encrypting
+ * with the corresponding public key, `CryptError::WrongKey` if the result isn't a valid block,
+ * which happens when the ciphertext was encrypted for a different key, or
+ * `CryptError::MalformedKey` if the key can't be used for decryption.
+ */
+pub fn decrypt_block(
+    c1: Num,
+    c2: Num,
+    key: &PrivateKey,
+    encoding: Encoding,
+) -> Result<Block, CryptError> {
+    let key = key.key();
+    check_ciphertext(c1, c2, key, encoding)?;
+
+    // `c1^(p - x - 1) == c1^-x`, so one `mod_exp` gives the inverse directly, rather than
+    // computing `c1^x` and then inverting it with `ext_gcd`. `mod_exp` squares once for every bit
+    // of a `Num` and multiplies once more for every set bit of the exponent, so this costs at
+    // most one extra multiplication per bit over `c1^x`, which is less than the inversion: the
+    // `decrypt_block` benchmark measured the inverse about 1.5 times slower (1.3 times with the
+    // `wide` feature).
+    let c1_term = mod_exp(c1, key.prime - key.value - 1, key.prime);
+    let result = mul_mod(c1_term, c2, key.prime);
+    encoding.decode(result, key.prime)
+}
+
+/**
+ * Check that the private key `key` can decrypt with `encoding`, and that `c1` and `c2` are in
+ * range for it.
+ */
+fn check_ciphertext(c1: Num, c2: Num, key: &Key, encoding: Encoding) -> Result<(), CryptError> {
+    if !encoding.supports(key.prime) || key.value >= key.prime {
+        return Err(CryptError::MalformedKey);
+    }
+
+    // `c1` is a power of the root, so it can't be 0, while `c2` is 0 for a zero block
+    if c1 == 0 || c1 >= key.prime || c2 >= key.prime {
+        return Err(CryptError::OutOfRange);
+    }
+
+    Ok(())
+}
+
+/**
+ * A random blinding factor `root^b`, and the correction `value^b` that cancels it out after
+ * decryption, where `value` is the public key's value.
+ *
+ * Decrypting `c1 * root^b` instead of `c1` means the private exponent is never applied to a value
+ * chosen by whoever wrote the ciphertext, so timing or cache measurements of many decryptions
+ * can't be correlated with it. The factor is squared after each use, so one `Blinding` can be
+ * kept for a whole file, or a new one made for each block.
+ */
+#[derive(Debug, Clone)]
+pub struct Blinding {
+    prime: Num,
+    factor: Num,
+    correction: Num,
+}
+
+impl Blinding {
+    /**
+     * Create a blinding factor for `key` with a random exponent generated by `rng`.
+     */
+    pub fn new<T: Rng>(key: &PrivateKey, rng: &mut T) -> Self {
+        let key = key.key();
+        let b = rng.gen_range(1..key.prime - 1);
+        let value = mod_exp_ct(key.root, key.value, key.prime);
+
+        Self {
+            prime: key.prime,
+            factor: mod_exp(key.root, b, key.prime),
+            correction: mod_exp(value, b, key.prime),
+        }
+    }
+
+    /**
+     * Get the current factor and correction for a key with the given prime, then move on to the
+     * next factor, `root^(2b)`.
+     *
+     * Returns `CryptError::IncompatibleKeys` if the blinding was created for a different prime.
+     */
+    pub(crate) fn next(&mut self, prime: Num) -> Result<(Num, Num), CryptError> {
+        if prime != self.prime {
+            return Err(CryptError::IncompatibleKeys);
+        }
+
+        let current = (self.factor, self.correction);
+        self.factor = mul_mod(self.factor, self.factor, self.prime);
+        self.correction = mul_mod(self.correction, self.correction, self.prime);
+        Ok(current)
+    }
+}
+
+/**
+ * Decrypt the ciphertext block represented by `c1` and `c2` as in `decrypt_block`, but blinding
+ * `c1` with `blinding` first and exponentiating in constant time, to protect the private key
+ * from side channels when decrypting untrusted ciphertext. `blinding` is advanced for the next
+ * call.
+ *
+ * Returns the same errors as `decrypt_block`, and `CryptError::IncompatibleKeys` if `blinding`
+ * was created for a key with a different prime.
+ */
+pub fn decrypt_block_blinded(
+    c1: Num,
+    c2: Num,
+    key: &PrivateKey,
+    encoding: Encoding,
+    blinding: &mut Blinding,
+) -> Result<Block, CryptError> {
+    let key = key.key();
+    check_ciphertext(c1, c2, key, encoding)?;
+    let (factor, correction) = blinding.next(key.prime)?;
+
+    // `(c1 * root^b)^-x == c1^-x * value^-b`, so the correction restores `c1^-x`
+    let blinded = mul_mod(c1, factor, key.prime);
+    let c1_term = mod_exp_ct(blinded, key.prime - key.value - 1, key.prime);
+    let unblinded = mul_mod(c1_term, correction, key.prime);
+
+    let result = mul_mod(unblinded, c2, key.prime);
+    encoding.decode(result, key.prime)
+}
+
+/**
+ * Multiply two ciphertext pairs encrypted with `key`, giving a ciphertext pair that decrypts to
+ * the product of their plaintexts modulo the key's prime.
+ *
+ * The product is of the group elements that were encrypted, so it's only the product of the
+ * blocks themselves with `Encoding::Raw`, and then only decodes to a block if it's no greater
+ * than `Block::MAX`. Since `c1` is multiplied as well, the result is as random as its inputs.
+ */
+pub fn ct_mul(a: (Num, Num), b: (Num, Num), key: &PublicKey) -> (Num, Num) {
+    let prime = key.key().prime;
+    (mul_mod(a.0, b.0, prime), mul_mod(a.1, b.1, prime))
+}
+
+/**
+ * Encrypt each block yielded by `blocks` as in `encrypt_block`, yielding the ciphertext pairs.
+ * This is for framing ciphertexts some other way than the `ecb` module, e.g., storing each
+ * ciphertext pair separately.
+ */
+pub fn encrypt_blocks<'a, I, T>(
+    blocks: I,
+    key: &'a PublicKey,
+    encoding: Encoding,
+    rng: &'a mut T,
+) -> impl Iterator<Item = Result<(Num, Num), CryptError>> + 'a
+where
+    I: IntoIterator<Item = Block>,
+    I::IntoIter: 'a,
+    T: Rng,
+{
+    blocks
+        .into_iter()
+        .map(move |block| encrypt_block(block, key, encoding, rng))
+}
+
+/**
+ * Decrypt each ciphertext pair yielded by `pairs` as in `decrypt_block`, yielding the plaintext
+ * blocks. This reverses `encrypt_blocks`.
+ */
+pub fn decrypt_blocks<'a, I>(
+    pairs: I,
+    key: &'a PrivateKey,
+    encoding: Encoding,
+) -> impl Iterator<Item = Result<Block, CryptError>> + 'a
+where
+    I: IntoIterator<Item = (Num, Num)>,
+    I::IntoIter: 'a,
+{
+    pairs
+        .into_iter()
+        .map(move |(c1, c2)| decrypt_block(c1, c2, key, encoding))
+}
+
+/**
+ * Encrypt `plaintext` with the given public key, padding and framing it as in `ecb::encrypt_ecb`.
+ * The random exponent for each block and the padding bytes are generated by `rng`.
+ *
+ * Returns `CryptError::MalformedKey` if the key's prime is too small to encrypt a block.
+ */
+pub fn encrypt_bytes<T: Rng>(
+    plaintext: &[u8],
+    key: &PublicKey,
+    rng: &mut T,
+) -> Result<Vec<u8>, CryptError> {
+    let mut encryptor = stream::EncryptWriter::new(Vec::new(), key.clone(), rng);
+    encryptor.write_all(plaintext)?;
+    encryptor.finish()
+}
+
+/**
+ * Decrypt `ciphertext` with the given private key, as produced by `encrypt_bytes` or any of the
+ * `ecb::encrypt_ecb` variants.
+ *
+ * Returns the same errors as `ecb::decrypt_ecb`. Unlike `decrypt_ecb`, no plaintext is returned
+ * if any part of the ciphertext fails to decrypt.
+ */
+pub fn decrypt_bytes(ciphertext: &[u8], key: &PrivateKey) -> Result<Vec<u8>, CryptError> {
+    let mut plaintext = Vec::new();
+    ecb::decrypt_ecb(&mut &ciphertext[..], &mut plaintext, key)?;
+    Ok(plaintext)
+}
@@ -0,0 +1,138 @@
+/*!
+ * HMAC-SHA-256 message authentication codes, as specified in RFC 2104.
+ *
+ * A tag is computed incrementally by creating an `Hmac` with the key, adding the message with
+ * `update` (or by writing to it, since it implements `Write`), and finishing with `finalize`, or
+ * with `verify` to check an existing tag. `hmac` computes a tag in one step, and `pbkdf2` stretches
+ * a password into a key by iterating HMAC.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::hash::{Sha256, CHUNK_BYTES, DIGEST_BYTES};
+use std::io::{self, Write};
+
+/**
+ * The number of bytes in an HMAC-SHA-256 tag.
+ */
+pub const MAC_BYTES: usize = DIGEST_BYTES;
+
+const INNER_PAD: u8 = 0x36;
+const OUTER_PAD: u8 = 0x5c;
+
+/**
+ * An incremental HMAC-SHA-256 computation. Data is added with `update`, and the tag is produced
+ * by `finalize` or checked by `verify`.
+ */
+#[derive(Debug, Clone)]
+pub struct Hmac {
+    inner: Sha256,
+    outer: Sha256,
+}
+
+impl Hmac {
+    /**
+     * Start computing a tag with the given key. Keys longer than a SHA-256 chunk are hashed first.
+     */
+    pub fn new(key: &[u8]) -> Self {
+        let mut padded_key = [0_u8; CHUNK_BYTES];
+        if key.len() > CHUNK_BYTES {
+            let mut hasher = Sha256::new();
+            hasher.update(key);
+            padded_key[..DIGEST_BYTES].copy_from_slice(&hasher.finalize());
+        } else {
+            padded_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = Sha256::new();
+        let mut outer = Sha256::new();
+        inner.update(&padded_key.map(|b| b ^ INNER_PAD));
+        outer.update(&padded_key.map(|b| b ^ OUTER_PAD));
+
+        Self { inner, outer }
+    }
+
+    /**
+     * Add `data` to the authenticated message.
+     */
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    /**
+     * Return the tag for the message.
+     */
+    pub fn finalize(self) -> [u8; MAC_BYTES] {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+
+    /**
+     * Check whether `tag` is the tag for the message. The comparison takes the same time no
+     * matter where the tags differ.
+     */
+    pub fn verify(self, tag: &[u8]) -> bool {
+        let expected = self.finalize();
+        let difference = expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+
+        tag.len() == MAC_BYTES && difference == 0
+    }
+}
+
+impl Write for Hmac {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/**
+ * Compute the tag for `message` with the given key in one step.
+ */
+pub fn hmac(key: &[u8], message: &[u8]) -> [u8; MAC_BYTES] {
+    let mut mac = Hmac::new(key);
+    mac.update(message);
+    mac.finalize()
+}
+
+/**
+ * Fill `output` with key material derived from `password` and `salt` using PBKDF2-HMAC-SHA-256,
+ * as specified in RFC 8018. Each block of output takes `iterations` HMAC computations, which makes
+ * guessing the password that much slower.
+ */
+pub fn pbkdf2(password: &[u8], salt: &[u8], iterations: u32, output: &mut [u8]) {
+    assert!(iterations > 0);
+
+    // the padded key is the same for every HMAC, so it's only hashed once
+    let keyed = Hmac::new(password);
+
+    for (i, chunk) in output.chunks_mut(MAC_BYTES).enumerate() {
+        // blocks are numbered from 1
+        let block_index = i as u32 + 1;
+        let mut mac = keyed.clone();
+        mac.update(salt);
+        mac.update(&block_index.to_be_bytes());
+
+        let mut u = mac.finalize();
+        let mut block = u;
+        for _ in 1..iterations {
+            let mut mac = keyed.clone();
+            mac.update(&u);
+            u = mac.finalize();
+            for (b, x) in block.iter_mut().zip(&u) {
+                *b ^= x;
+            }
+        }
+
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
@@ -0,0 +1,96 @@
+use super::*;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_hmac_vectors() {
+    // test cases 1, 2, 4, and 6 from RFC 4231
+    let vectors: Vec<(Vec<u8>, &[u8], &str)> = vec![
+        (
+            vec![0x0b; 20],
+            b"Hi There",
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+        ),
+        (
+            b"Jefe".to_vec(),
+            b"what do ya want for nothing?",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        ),
+        (
+            (1..=25).collect(),
+            &[0xcd; 50],
+            "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+        ),
+        (
+            vec![0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        ),
+    ];
+
+    for (key, message, expected) in vectors {
+        assert_eq!(to_hex(&hmac(&key, message)), expected);
+
+        // writing the message in pieces gives the same tag
+        let mut mac = Hmac::new(&key);
+        for chunk in message.chunks(7) {
+            mac.write_all(chunk).unwrap();
+        }
+        assert_eq!(to_hex(&mac.finalize()), expected);
+    }
+}
+
+#[test]
+fn test_hmac_verify() {
+    let mut mac = Hmac::new(b"key");
+    for chunk in b"an incrementally authenticated message".chunks(5) {
+        mac.update(chunk);
+    }
+
+    let tag = mac.clone().finalize();
+    assert!(mac.clone().verify(&tag));
+    assert!(!mac.clone().verify(&tag[..MAC_BYTES - 1]));
+
+    let mut bad_tag = tag;
+    bad_tag[MAC_BYTES - 1] ^= 1;
+    assert!(!mac.verify(&bad_tag));
+}
+
+#[test]
+fn test_pbkdf2_vectors() {
+    // PBKDF2-HMAC-SHA-256 versions of the RFC 6070 test cases
+    let vectors: [(&[u8], &[u8], u32, &str); 4] = [
+        (
+            b"password",
+            b"salt",
+            1,
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+        ),
+        (
+            b"password",
+            b"salt",
+            2,
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43",
+        ),
+        (
+            b"password",
+            b"salt",
+            4096,
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a",
+        ),
+        (
+            b"passwordPASSWORDpassword",
+            b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+            4096,
+            "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1c635518c7dac47e9",
+        ),
+    ];
+
+    for (password, salt, iterations, expected) in vectors {
+        let mut output = vec![0_u8; expected.len() / 2];
+        pbkdf2(password, salt, iterations, &mut output);
+        assert_eq!(to_hex(&output), expected);
+    }
+}
@@ -0,0 +1,287 @@
+/*!
+ * Versions 2 and 3 of the key file format, which record metadata about the key alongside it, much
+ * like the comment of an SSH key.
+ *
+ * A version 3 key file consists of the magic bytes `PUBCRKMD`, the version byte `VERSION`, the
+ * key's algorithm identifier, the number of bytes in a `Num` when the key was made, the big-endian
+ * eight-byte creation and expiry times in seconds since the Unix epoch (with an expiry of 0
+ * meaning the key never expires), the big-endian two-byte length of the comment, the comment in
+ * UTF-8, the big-endian two-byte length of the signature (0 if the file isn't signed), the
+ * signature, and finally the key itself. The key is serialized by its `cipher::PublicKeyCipher`
+ * without an algorithm prefix (ElGamal keys may also be DER-encoded with `Key::to_der`), or
+ * protected with `keyfile::protect_key`. Version 2 files are the
+ * same, but without the signature or its length.
+ *
+ * The signature is a Schnorr signature by the key's owner over the metadata and the public key, so
+ * that corrupting either is detected when the key is loaded, and the metadata can't be changed
+ * without the private key. Only ElGamal keys can be signed, since they're the only keys that can
+ * make signatures.
+ *
+ * Key files from before metadata was introduced (version 1) are still read by
+ * `cipher::read_key_file` and `keyfile::unprotect_key`; use `has_metadata` to tell them apart,
+ * since a newer file can happen to be the same length as a version 1 file.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    hash::{Sha256, DIGEST_BYTES},
+    schnorr::{self, Signature, SIGNATURE_BYTES},
+    Algorithm, KeyError, PrivateKey, PublicKey, NUM_BYTES,
+};
+
+/**
+ * The bytes that every version 2 key file starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRKMD";
+
+/**
+ * The version of the key file format written by `KeyMetadata::write_key_file`.
+ */
+pub const VERSION: u8 = 3;
+
+/**
+ * The oldest version of the key file format that `KeyMetadata::read_key_file` can read.
+ */
+pub const MIN_VERSION: u8 = 2;
+
+/**
+ * The maximum number of bytes in a key's comment.
+ */
+pub const MAX_COMMENT_BYTES: usize = u16::MAX as usize;
+
+const TIME_BYTES: usize = 8;
+const LEN_BYTES: usize = 2;
+const VERSION_START: usize = MAGIC.len();
+const ALGORITHM_START: usize = VERSION_START + 1;
+const WIDTH_START: usize = ALGORITHM_START + 1;
+const CREATED_START: usize = WIDTH_START + 1;
+const EXPIRES_START: usize = CREATED_START + TIME_BYTES;
+const COMMENT_LEN_START: usize = EXPIRES_START + TIME_BYTES;
+const COMMENT_START: usize = COMMENT_LEN_START + LEN_BYTES;
+
+/**
+ * Information about a key that's stored in its key file, but isn't needed to use the key.
+ *
+ * Times are in seconds since the Unix epoch.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyMetadata {
+    pub algorithm: Algorithm,
+    pub created: u64,
+    pub expires: Option<u64>,
+    pub comment: String,
+    pub signature: Option<Signature>,
+}
+
+impl KeyMetadata {
+    /**
+     * Create metadata for a key for `algorithm` created at `created`, which never expires, has
+     * no comment, and isn't signed.
+     */
+    pub fn new(algorithm: Algorithm, created: u64) -> Self {
+        Self {
+            algorithm,
+            created,
+            expires: None,
+            comment: String::new(),
+            signature: None,
+        }
+    }
+
+    /**
+     * Check whether the key has expired by the time `now`.
+     */
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| now >= expires)
+    }
+
+    /**
+     * Sign the metadata and the public key of `key`, replacing any existing signature. Any
+     * later change to the metadata invalidates the signature, so this should be done last.
+     */
+    pub fn sign(&mut self, key: &PrivateKey) {
+        let digest = self.digest(&key.derive_public());
+        self.signature = Some(schnorr::sign(&digest, key));
+    }
+
+    /**
+     * Check the signature against the metadata and `key`, which is the public key in the file or
+     * the one derived from its private key.
+     *
+     * Returns `KeyError::InvalidSignature` if the metadata is signed, but the signature doesn't
+     * match it and `key`, or the key isn't an ElGamal key. Unsigned metadata is always accepted,
+     * so that older files can still be read.
+     */
+    pub fn verify(&self, key: &PublicKey) -> Result<(), KeyError> {
+        match &self.signature {
+            None => Ok(()),
+            Some(signature)
+                if self.algorithm == Algorithm::ElGamal
+                    && schnorr::verify(&self.digest(key), signature, key) =>
+            {
+                Ok(())
+            }
+            Some(_) => Err(KeyError::InvalidSignature),
+        }
+    }
+
+    /**
+     * Hash everything in the metadata except the signature, along with the public key.
+     */
+    fn digest(&self, key: &PublicKey) -> [u8; DIGEST_BYTES] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"pubcrypt key metadata");
+        hasher.update(&[self.algorithm.id(), NUM_BYTES as u8]);
+        hasher.update(&self.created.to_be_bytes());
+        hasher.update(&self.expires.unwrap_or(0).to_be_bytes());
+        hasher.update(&(self.comment.len() as u64).to_be_bytes());
+        hasher.update(self.comment.as_bytes());
+        hasher.update(&key.serialize());
+        hasher.finalize()
+    }
+
+    /**
+     * Write a version 3 key file containing the metadata and `key_bytes`, which are a key
+     * serialized without an algorithm prefix, or a protected key.
+     *
+     * Returns `KeyError::InvalidMetadata` if the comment is longer than `MAX_COMMENT_BYTES`, or
+     * the key expires at time 0, which is reserved for keys that never expire.
+     */
+    pub fn write_key_file(&self, key_bytes: &[u8]) -> Result<Vec<u8>, KeyError> {
+        if self.comment.len() > MAX_COMMENT_BYTES || self.expires == Some(0) {
+            return Err(KeyError::InvalidMetadata);
+        }
+
+        let mut result = MAGIC.to_vec();
+        result.push(VERSION);
+        result.push(self.algorithm.id());
+        result.push(NUM_BYTES as u8);
+        result.extend_from_slice(&self.created.to_be_bytes());
+        result.extend_from_slice(&self.expires.unwrap_or(0).to_be_bytes());
+        result.extend_from_slice(&(self.comment.len() as u16).to_be_bytes());
+        result.extend_from_slice(self.comment.as_bytes());
+        match &self.signature {
+            Some(signature) => {
+                result.extend_from_slice(&(SIGNATURE_BYTES as u16).to_be_bytes());
+                result.extend_from_slice(&signature.serialize());
+            }
+            None => result.extend_from_slice(&0_u16.to_be_bytes()),
+        }
+        result.extend_from_slice(key_bytes);
+        Ok(result)
+    }
+
+    /**
+     * Split a version 2 or 3 key file into its metadata and the key bytes that follow it. The
+     * signature isn't checked; see `verify`.
+     *
+     * Returns `KeyError::UnsupportedVersion` if the file is from a newer version, `WrongWidth` if
+     * the key was made with a different size of `Num` (see the `wide` feature),
+     * `UnknownAlgorithm` if its algorithm isn't known, and `InvalidMetadata` if the file doesn't
+     * start with `MAGIC`, or is truncated or malformed.
+     */
+    pub fn read_key_file(bytes: &[u8]) -> Result<(Self, &[u8]), KeyError> {
+        if !has_metadata(bytes) || bytes.len() < COMMENT_START {
+            return Err(KeyError::InvalidMetadata);
+        }
+
+        let version = bytes[VERSION_START];
+        if !(MIN_VERSION..=VERSION).contains(&version) {
+            return Err(KeyError::UnsupportedVersion(version));
+        }
+
+        if bytes[WIDTH_START] as usize != NUM_BYTES {
+            return Err(KeyError::WrongWidth);
+        }
+
+        let algorithm =
+            Algorithm::from_id(bytes[ALGORITHM_START]).ok_or(KeyError::UnknownAlgorithm)?;
+        let created = read_time(&bytes[CREATED_START..EXPIRES_START]);
+        let expires = match read_time(&bytes[EXPIRES_START..COMMENT_LEN_START]) {
+            0 => None,
+            expires => Some(expires),
+        };
+
+        let comment_end = COMMENT_START + read_len(&bytes[COMMENT_LEN_START..COMMENT_START]);
+        let comment = bytes
+            .get(COMMENT_START..comment_end)
+            .and_then(|comment| String::from_utf8(comment.to_vec()).ok())
+            .ok_or(KeyError::InvalidMetadata)?;
+
+        let (signature, key_start) = if version < 3 {
+            (None, comment_end)
+        } else {
+            let signature_start = comment_end + LEN_BYTES;
+            let len = bytes
+                .get(comment_end..signature_start)
+                .map(read_len)
+                .ok_or(KeyError::InvalidMetadata)?;
+            match len {
+                0 => (None, signature_start),
+                SIGNATURE_BYTES => {
+                    let mut signature = [0_u8; SIGNATURE_BYTES];
+                    let signature_end = signature_start + SIGNATURE_BYTES;
+                    let signature_bytes = bytes
+                        .get(signature_start..signature_end)
+                        .ok_or(KeyError::InvalidMetadata)?;
+                    signature.copy_from_slice(signature_bytes);
+                    (Some(Signature::deserialize(&signature)), signature_end)
+                }
+                _ => return Err(KeyError::InvalidMetadata),
+            }
+        };
+
+        if bytes.len() <= key_start {
+            return Err(KeyError::InvalidMetadata);
+        }
+
+        let metadata = Self {
+            algorithm,
+            created,
+            expires,
+            comment,
+            signature,
+        };
+
+        Ok((metadata, &bytes[key_start..]))
+    }
+}
+
+/**
+ * Check whether `bytes` starts like a key file with metadata, rather than a version 1 file.
+ */
+pub fn has_metadata(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/**
+ * Get the version of the key file format that `bytes` claims to be in, which is 1 for files
+ * without metadata. The version is only read, not checked.
+ */
+pub fn version(bytes: &[u8]) -> u8 {
+    match bytes.get(VERSION_START) {
+        Some(&version) if has_metadata(bytes) => version,
+        _ => 1,
+    }
+}
+
+/**
+ * Read a big-endian length from `bytes`, which must be `LEN_BYTES` long.
+ */
+fn read_len(bytes: &[u8]) -> usize {
+    let mut buf = [0_u8; LEN_BYTES];
+    buf.copy_from_slice(bytes);
+    u16::from_be_bytes(buf) as usize
+}
+
+/**
+ * Read a big-endian time from `bytes`, which must be `TIME_BYTES` long.
+ */
+fn read_time(bytes: &[u8]) -> u64 {
+    let mut buf = [0_u8; TIME_BYTES];
+    buf.copy_from_slice(bytes);
+    u64::from_be_bytes(buf)
+}
@@ -0,0 +1,156 @@
+use super::*;
+use crate::{cipher, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_key_file_round_trip() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let key_bytes = keys.public.serialize();
+
+    let mut metadata = KeyMetadata::new(Algorithm::ElGamal, 1_700_000_000);
+    for (expires, comment) in [(None, ""), (Some(1_800_000_000), "alice@example.com")] {
+        metadata.expires = expires;
+        metadata.comment = comment.to_string();
+
+        let file = metadata.write_key_file(&key_bytes).unwrap();
+        assert!(has_metadata(&file));
+        let (read, read_key_bytes) = KeyMetadata::read_key_file(&file).unwrap();
+        assert_eq!(read, metadata);
+        assert_eq!(read_key_bytes, key_bytes);
+    }
+
+    // version 2 files are the same as unsigned version 3 files, without the signature length
+    let file = metadata.write_key_file(&key_bytes).unwrap();
+    let signature_len_start = COMMENT_START + metadata.comment.len();
+    let mut old_file = file[..signature_len_start].to_vec();
+    old_file[VERSION_START] = 2;
+    old_file.extend_from_slice(&file[signature_len_start + LEN_BYTES..]);
+    assert_eq!(version(&old_file), 2);
+    assert_eq!(
+        KeyMetadata::read_key_file(&old_file),
+        Ok((metadata, &key_bytes[..]))
+    );
+
+    // version 1 key files are still told apart
+    assert!(!has_metadata(&key_bytes));
+    assert_eq!(version(&key_bytes), 1);
+    let rsa_file = cipher::write_key_file(Algorithm::Rsa, &[1, 2, 3]);
+    assert!(!has_metadata(&rsa_file));
+}
+
+#[test]
+fn test_signature() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+    let key_bytes = keys.public.serialize();
+
+    let mut metadata = KeyMetadata::new(Algorithm::ElGamal, 1_700_000_000);
+    metadata.comment = "alice@example.com".to_string();
+    assert_eq!(metadata.verify(&keys.public), Ok(()));
+
+    metadata.sign(&keys.private);
+    assert_eq!(metadata.verify(&keys.public), Ok(()));
+    let file = metadata.write_key_file(&key_bytes).unwrap();
+    let (read, _) = KeyMetadata::read_key_file(&file).unwrap();
+    assert_eq!(read, metadata);
+    assert_eq!(read.verify(&keys.public), Ok(()));
+
+    // changing the metadata or key invalidates the signature
+    assert_eq!(
+        metadata.verify(&other_keys.public),
+        Err(KeyError::InvalidSignature)
+    );
+    let mut changed = metadata.clone();
+    changed.comment.push('!');
+    assert_eq!(
+        changed.verify(&keys.public),
+        Err(KeyError::InvalidSignature)
+    );
+    let mut changed = metadata.clone();
+    changed.expires = Some(1_800_000_000);
+    assert_eq!(
+        changed.verify(&keys.public),
+        Err(KeyError::InvalidSignature)
+    );
+
+    // a corrupted signature is detected too
+    let mut corrupted = file.clone();
+    corrupted[COMMENT_START + metadata.comment.len() + LEN_BYTES] ^= 1;
+    let (read, _) = KeyMetadata::read_key_file(&corrupted).unwrap();
+    assert_eq!(read.verify(&keys.public), Err(KeyError::InvalidSignature));
+}
+
+#[test]
+fn test_expiry() {
+    let mut metadata = KeyMetadata::new(Algorithm::ElGamal, 100);
+    assert!(!metadata.is_expired(u64::MAX));
+
+    metadata.expires = Some(200);
+    assert!(!metadata.is_expired(199));
+    assert!(metadata.is_expired(200));
+    assert!(metadata.is_expired(201));
+
+    metadata.expires = Some(0);
+    assert_eq!(
+        metadata.write_key_file(&[0]),
+        Err(KeyError::InvalidMetadata)
+    );
+}
+
+#[test]
+fn test_invalid_key_files() {
+    let mut metadata = KeyMetadata::new(Algorithm::Rsa, 0);
+    metadata.comment = "comment".to_string();
+    let file = metadata.write_key_file(&[1, 2, 3]).unwrap();
+
+    let with_byte = |index: usize, value: u8| {
+        let mut file = file.clone();
+        file[index] = value;
+        KeyMetadata::read_key_file(&file).map(|_| ())
+    };
+    assert_eq!(
+        with_byte(VERSION_START, VERSION + 1),
+        Err(KeyError::UnsupportedVersion(VERSION + 1))
+    );
+    assert_eq!(
+        with_byte(VERSION_START, 1),
+        Err(KeyError::UnsupportedVersion(1))
+    );
+    assert_eq!(
+        with_byte(ALGORITHM_START, 0xff),
+        Err(KeyError::UnknownAlgorithm)
+    );
+    assert_eq!(
+        with_byte(WIDTH_START, NUM_BYTES as u8 * 2),
+        Err(KeyError::WrongWidth)
+    );
+    assert_eq!(
+        with_byte(COMMENT_START, 0xff),
+        Err(KeyError::InvalidMetadata)
+    );
+    assert_eq!(with_byte(0, b'X'), Err(KeyError::InvalidMetadata));
+
+    // signatures of the wrong length are rejected
+    let signature_len_start = COMMENT_START + metadata.comment.len();
+    assert_eq!(
+        with_byte(signature_len_start + 1, 1),
+        Err(KeyError::InvalidMetadata)
+    );
+
+    // truncated anywhere, including right before the key
+    for len in 0..signature_len_start + LEN_BYTES + 1 {
+        assert_eq!(
+            KeyMetadata::read_key_file(&file[..len]).map(|_| ()),
+            Err(KeyError::InvalidMetadata)
+        );
+    }
+
+    metadata.comment = "x".repeat(MAX_COMMENT_BYTES + 1);
+    assert_eq!(
+        metadata.write_key_file(&[0]),
+        Err(KeyError::InvalidMetadata)
+    );
+}
@@ -0,0 +1,124 @@
+/*!
+ * Mnemonic phrases, which spell out a private key as a list of words for a paper backup that
+ * doesn't depend on keeping a file alive.
+ *
+ * A mnemonic encodes the key's serialized prime, root, and value, followed by a checksum made of
+ * the first bits of their SHA-256 digest, one bit for every 32 bits of the key, in the manner of
+ * BIP 39. The bits are split into groups of `BITS_PER_WORD`, and each group picks a word from the
+ * BIP 39 English word list, so a key takes `WORD_COUNT` words (18, or 36 with the `wide` feature).
+ *
+ * Every word in the list is identified by its first four letters, so `from_mnemonic` also accepts
+ * words shortened to four letters or more, as well as any mix of case and whitespace.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{hash, Key, KeyError, PrivateKey};
+
+/**
+ * The number of bits that each word encodes.
+ */
+pub const BITS_PER_WORD: usize = 11;
+
+/**
+ * The number of words in a mnemonic.
+ */
+pub const WORD_COUNT: usize = (KEY_BITS + CHECKSUM_BITS) / BITS_PER_WORD;
+
+const KEY_BITS: usize = Key::KEY_BYTES * 8;
+const CHECKSUM_BITS: usize = KEY_BITS / 32;
+const MIN_PREFIX_CHARS: usize = 4;
+
+/**
+ * The BIP 39 English word list, one word per line in sorted order.
+ */
+const WORD_LIST: &str = include_str!("english.txt");
+
+/**
+ * Get the words in the word list, in order.
+ */
+pub fn words() -> impl Iterator<Item = &'static str> {
+    WORD_LIST.lines()
+}
+
+/**
+ * Spell out `key` as a mnemonic of `WORD_COUNT` words.
+ */
+pub fn to_mnemonic(key: &PrivateKey) -> Vec<&'static str> {
+    let bytes = key.serialize();
+    let checksum = hash::sha256(&bytes);
+    let bits: Vec<bool> = to_bits(&bytes)
+        .chain(to_bits(&checksum).take(CHECKSUM_BITS))
+        .collect();
+
+    let list: Vec<&str> = words().collect();
+    bits.chunks(BITS_PER_WORD)
+        .map(|chunk| {
+            let index = chunk
+                .iter()
+                .fold(0, |index, &bit| index << 1 | bit as usize);
+            list[index]
+        })
+        .collect()
+}
+
+/**
+ * Read a private key from the mnemonic `text`, whose words are separated by whitespace. Like
+ * `PrivateKey::deserialize`, this doesn't check the key itself.
+ *
+ * Returns `KeyError::UnknownWord` with the position of the first word, counting from 1, that isn't
+ * in the word list or is too short to tell which word it is, and `KeyError::InvalidMnemonic` if
+ * there aren't `WORD_COUNT` words, or the checksum doesn't match.
+ */
+pub fn from_mnemonic(text: &str) -> Result<PrivateKey, KeyError> {
+    let indices = text
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, word)| find_word(word).ok_or(KeyError::UnknownWord(i + 1)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if indices.len() != WORD_COUNT {
+        return Err(KeyError::InvalidMnemonic);
+    }
+
+    let bits: Vec<bool> = indices
+        .iter()
+        .flat_map(|&index| (0..BITS_PER_WORD).rev().map(move |i| index >> i & 1 == 1))
+        .collect();
+    let (key_bits, checksum_bits) = bits.split_at(KEY_BITS);
+
+    let mut bytes = [0_u8; Key::KEY_BYTES];
+    for (byte, chunk) in bytes.iter_mut().zip(key_bits.chunks(8)) {
+        *byte = chunk.iter().fold(0, |byte, &bit| byte << 1 | bit as u8);
+    }
+
+    let checksum = hash::sha256(&bytes);
+    if !to_bits(&checksum)
+        .take(CHECKSUM_BITS)
+        .eq(checksum_bits.iter().copied())
+    {
+        return Err(KeyError::InvalidMnemonic);
+    }
+
+    Ok(PrivateKey::deserialize(&bytes))
+}
+
+/**
+ * Find the index of `word` in the word list, ignoring case. A word of at least
+ * `MIN_PREFIX_CHARS` letters also matches the one word in the list that it starts.
+ */
+fn find_word(word: &str) -> Option<usize> {
+    let word = word.to_lowercase();
+    words().position(|candidate| {
+        candidate == word || (word.len() >= MIN_PREFIX_CHARS && candidate.starts_with(&word))
+    })
+}
+
+/**
+ * Get the bits of `bytes`, most significant first.
+ */
+fn to_bits(bytes: &[u8]) -> impl Iterator<Item = bool> + '_ {
+    bytes
+        .iter()
+        .flat_map(|&byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+}
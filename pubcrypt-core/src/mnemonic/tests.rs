@@ -0,0 +1,102 @@
+use super::*;
+use crate::KeyPair;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_word_list() {
+    let list: Vec<&str> = words().collect();
+    assert_eq!(list.len(), 1 << BITS_PER_WORD);
+    assert!(list.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(
+        hash::sha256(WORD_LIST.as_bytes())[..4],
+        [0x2f, 0x5e, 0xed, 0x53]
+    );
+
+    // every word is identified by its first four letters
+    let prefixes: std::collections::HashSet<&str> = list
+        .iter()
+        .map(|word| &word[..word.len().min(MIN_PREFIX_CHARS)])
+        .collect();
+    assert_eq!(prefixes.len(), list.len());
+}
+
+#[test]
+fn test_round_trip() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..5 {
+        let keys = KeyPair::generate(&mut rng).unwrap();
+        let mnemonic = to_mnemonic(&keys.private);
+        assert_eq!(mnemonic.len(), WORD_COUNT);
+        assert_eq!(from_mnemonic(&mnemonic.join(" ")).unwrap(), keys.private);
+
+        // words can be shortened, capitalized, and split across lines
+        let shortened: Vec<String> = mnemonic
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let word = &word[..word.len().min(MIN_PREFIX_CHARS)];
+                if i % 2 == 0 {
+                    word.to_uppercase()
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect();
+        let text = shortened
+            .chunks(6)
+            .map(|line| line.join("  "))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(from_mnemonic(&text).unwrap(), keys.private);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "wide"))]
+fn test_bip39_vector() {
+    // BIP 39's test vector for 24 zero bytes
+    let key = PrivateKey::deserialize(&[0; Key::KEY_BYTES]);
+    let mut expected = vec!["abandon"; WORD_COUNT - 1];
+    expected.push("agent");
+    assert_eq!(to_mnemonic(&key), expected);
+}
+
+#[test]
+fn test_invalid_mnemonics() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let mnemonic = to_mnemonic(&keys.private);
+
+    let mut unknown = mnemonic.clone();
+    unknown[3] = "notaword";
+    assert_eq!(
+        from_mnemonic(&unknown.join(" ")),
+        Err(KeyError::UnknownWord(4))
+    );
+
+    // prefixes shorter than four letters are ambiguous
+    let mut short = mnemonic.clone();
+    short[0] = "ab";
+    assert_eq!(
+        from_mnemonic(&short.join(" ")),
+        Err(KeyError::UnknownWord(1))
+    );
+
+    assert_eq!(
+        from_mnemonic(&mnemonic[1..].join(" ")),
+        Err(KeyError::InvalidMnemonic)
+    );
+    assert_eq!(from_mnemonic(""), Err(KeyError::InvalidMnemonic));
+
+    // swapping two different words almost always breaks the checksum
+    let mut swapped = mnemonic.clone();
+    let i = (1..WORD_COUNT)
+        .find(|&i| mnemonic[i] != mnemonic[0])
+        .unwrap();
+    swapped.swap(0, i);
+    assert_eq!(
+        from_mnemonic(&swapped.join(" ")),
+        Err(KeyError::InvalidMnemonic)
+    );
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+
+/**
+ * How consecutive blocks of a ciphertext relate to each other.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Mode {
+    /**
+     * Electronic codebook: each block is encrypted independently. The random exponent hides
+     * repeated plaintext blocks, but blocks can be reordered or spliced between ciphertexts for
+     * the same key without detection.
+     */
+    Ecb,
+    /**
+     * Ciphertext block chaining: before encryption, each plaintext block is XORed with the low
+     * bytes of `c1 ^ c2` from the previous ciphertext pair (the first block is left as-is).
+     * Reordering or splicing blocks then garbles the plaintext that follows.
+     */
+    Cbc,
+    /**
+     * Counter: the plaintext is XORed with a keystream instead of being encrypted block by block,
+     * so the ciphertext is only slightly longer than the plaintext and needs no padding. The
+     * ciphertext is split into segments of `stream::CTR_SEGMENT_BYTES` bytes, each of which starts
+     * with an encrypted random nonce `n`. The segment's key is the SHA-256 hash of `n` and the
+     * secret `root^(r * x)` shared through its encryption, and keystream block `i` of the segment
+     * is the leading bytes of the HMAC-SHA-256 of `i` under that key, so knowing one block of
+     * plaintext reveals nothing about the keystream for the others.
+     *
+     * Like any stream cipher without authentication, flipping a ciphertext bit flips the
+     * corresponding plaintext bit, and truncation isn't detected.
+     */
+    Ctr,
+    /**
+     * Hybrid: a single ElGamal encryption wraps a random nonce, which is recorded in the header
+     * (see `Header::wrapped_key`). The symmetric key is the SHA-256 hash of the nonce and the
+     * secret `root^(r * x)` shared through its encryption, and the plaintext is XORed with a
+     * keystream whose block `i` is the SHA-256 hash of the key and `i`. Since there's only one
+     * exponentiation per ciphertext, this is by far the fastest mode for large inputs.
+     *
+     * As in CTR mode, the ciphertext isn't padded, and it's malleable unless it's authenticated.
+     */
+    Hybrid,
+}
+
+impl Mode {
+    /**
+     * Get the identifier used for the mode in ciphertext headers.
+     */
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Self::Ecb => 0,
+            Self::Cbc => 1,
+            Self::Ctr => 2,
+            Self::Hybrid => 3,
+        }
+    }
+
+    /**
+     * Get the mode with the given header identifier, if any.
+     */
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Ecb),
+            1 => Some(Self::Cbc),
+            2 => Some(Self::Ctr),
+            3 => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ecb => write!(f, "ECB"),
+            Self::Cbc => write!(f, "CBC"),
+            Self::Ctr => write!(f, "CTR"),
+            Self::Hybrid => write!(f, "hybrid"),
+        }
+    }
+}
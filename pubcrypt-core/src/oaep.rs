@@ -0,0 +1,77 @@
+/*!
+ * An OAEP-style randomized transform applied to each block before encryption.
+ *
+ * The top `SEED_BYTES` bytes of the block hold a random seed, and the rest of the block (the data
+ * block) starts with `CHECK_BYTES` zero bytes followed by the payload. The data block is masked
+ * with a mask generated from the seed, then the seed is masked with a mask generated from the
+ * masked data block. Every bit of the result depends on the random seed, so identical payloads
+ * never produce related values, and a change to any bit of the transformed block garbles the
+ * check bytes with high probability.
+ */
+
+use crate::{hash::Sha256, BLOCK_BYTES};
+use rand::Rng;
+
+/**
+ * The number of random seed bytes in each transformed block.
+ */
+const SEED_BYTES: usize = BLOCK_BYTES / 4;
+
+/**
+ * The number of zero bytes that are checked when the transform is reversed.
+ */
+const CHECK_BYTES: usize = BLOCK_BYTES / 4;
+
+/**
+ * The number of bytes of each block taken up by the transform.
+ */
+pub(crate) const OVERHEAD_BYTES: usize = SEED_BYTES + CHECK_BYTES;
+
+/**
+ * XOR `buf` with a mask generated from `seed`, using MGF1 with SHA-256.
+ */
+fn apply_mask(buf: &mut [u8], seed: &[u8]) {
+    for (counter, chunk) in buf.chunks_mut(crate::hash::DIGEST_BYTES).enumerate() {
+        let mut hasher = Sha256::new();
+        hasher.update(seed);
+        hasher.update(&(counter as u32).to_be_bytes());
+
+        let mask = hasher.finalize();
+        for (b, m) in chunk.iter_mut().zip(&mask) {
+            *b ^= m;
+        }
+    }
+}
+
+/**
+ * Transform `block` in place, generating the seed with `rng`. The payload is stored in the low
+ * bytes of `block`, below the top `OVERHEAD_BYTES` bytes, which must be zero.
+ */
+pub(crate) fn encode<T: Rng>(block: &mut [u8; BLOCK_BYTES], rng: &mut T) {
+    debug_assert!(block[..OVERHEAD_BYTES].iter().all(|&b| b == 0));
+
+    let (seed, data) = block.split_at_mut(SEED_BYTES);
+    rng.fill(seed);
+    apply_mask(data, seed);
+    apply_mask(seed, data);
+}
+
+/**
+ * Reverse the transform applied to `block` by `encode`, leaving the payload in its low bytes and
+ * zeros in its top `OVERHEAD_BYTES` bytes.
+ *
+ * Returns false if the check bytes aren't zero, in which case the block was modified after it
+ * was transformed.
+ */
+pub(crate) fn decode(block: &mut [u8; BLOCK_BYTES]) -> bool {
+    let (seed, data) = block.split_at_mut(SEED_BYTES);
+    apply_mask(seed, data);
+    apply_mask(data, seed);
+
+    if data[..CHECK_BYTES].iter().any(|&b| b != 0) {
+        return false;
+    }
+
+    seed.fill(0);
+    true
+}
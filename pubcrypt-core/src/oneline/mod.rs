@@ -0,0 +1,83 @@
+/*!
+ * One-line public keys in the style of OpenSSH, which are easy to paste into chat messages or
+ * collect in a file with one key per line, like `authorized_keys`.
+ *
+ * A one-line key is the key type `KEY_TYPE`, the base64 encoding of the key in DER (see
+ * `Key::to_der`), and optionally a comment such as the owner's name, separated by single spaces:
+ *
+ * ```text
+ * pubcrypt-elgamal MBsCAQECCQDTwJsu+8PIAwIBAgIIW9+M2c0ynnQ= alice@laptop
+ * ```
+ *
+ * Only public keys can be written this way, since private keys shouldn't be pasted anywhere.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    armor::{decode_base64, encode_base64},
+    Key, KeyError, PublicKey,
+};
+
+/**
+ * The key type that starts every one-line key.
+ */
+pub const KEY_TYPE: &str = "pubcrypt-elgamal";
+
+/**
+ * Write `key` as a one-line key with the given comment, which is left out if it's empty. The
+ * result doesn't end with a newline.
+ *
+ * Returns `KeyError::InvalidMetadata` if the comment contains a line break.
+ */
+pub fn to_line(key: &PublicKey, comment: &str) -> Result<String, KeyError> {
+    if comment.contains(['\n', '\r']) {
+        return Err(KeyError::InvalidMetadata);
+    }
+
+    let mut result = format!("{} {}", KEY_TYPE, encode_base64(&key.key().to_der()));
+    if !comment.is_empty() {
+        result.push(' ');
+        result.push_str(comment);
+    }
+    Ok(result)
+}
+
+/**
+ * Read a one-line key written by `to_line`, returning the key and its comment, which is empty if
+ * there isn't one. Whitespace around the line is ignored. Like `Key::deserialize`, this doesn't
+ * check the key.
+ *
+ * Returns `KeyError::InvalidLine` if the text isn't a single line starting with `KEY_TYPE` and a
+ * base64-encoded key, or an error from `Key::from_der` if the encoded key is invalid.
+ */
+pub fn from_line(text: &str) -> Result<(PublicKey, String), KeyError> {
+    let text = text.trim();
+    if text.contains(['\n', '\r']) {
+        return Err(KeyError::InvalidLine);
+    }
+
+    let mut words = text.splitn(3, ' ');
+    if words.next() != Some(KEY_TYPE) {
+        return Err(KeyError::InvalidLine);
+    }
+
+    let encoded = words
+        .next()
+        .filter(|encoded| !encoded.is_empty())
+        .and_then(decode_base64)
+        .ok_or(KeyError::InvalidLine)?;
+    let key = Key::from_der(&encoded)?;
+    let comment = words.next().unwrap_or("").to_string();
+    Ok((PublicKey::from(key), comment))
+}
+
+/**
+ * Check whether `bytes` looks like a one-line key, i.e., starts with `KEY_TYPE` and a space after
+ * any whitespace. The rest of the line is checked by `from_line`.
+ */
+pub fn is_line(bytes: &[u8]) -> bool {
+    let prefix = format!("{} ", KEY_TYPE);
+    bytes.trim_ascii_start().starts_with(prefix.as_bytes())
+}
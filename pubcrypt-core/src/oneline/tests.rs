@@ -0,0 +1,59 @@
+use super::*;
+use crate::KeyPair;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_one_line_keys() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let line = to_line(&keys.public, "Alice <alice@example.com>").unwrap();
+    assert!(line.starts_with("pubcrypt-elgamal "));
+    assert!(line.ends_with(" Alice <alice@example.com>"));
+    assert!(!line.contains('\n'));
+    assert!(is_line(line.as_bytes()));
+    assert_eq!(
+        from_line(&line),
+        Ok((keys.public.clone(), "Alice <alice@example.com>".to_string()))
+    );
+
+    let line = to_line(&keys.public, "").unwrap();
+    assert_eq!(line.matches(' ').count(), 1);
+    assert_eq!(
+        from_line(&format!("  {}\n", line)),
+        Ok((keys.public.clone(), String::new()))
+    );
+
+    assert_eq!(
+        to_line(&keys.public, "two\nlines"),
+        Err(KeyError::InvalidMetadata)
+    );
+}
+
+#[test]
+fn test_invalid_lines() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let line = to_line(&keys.public, "comment").unwrap();
+    let encoded = line.split(' ').nth(1).unwrap();
+
+    let invalid = [
+        String::new(),
+        "pubcrypt-elgamal".to_string(),
+        format!("pubcrypt-rsa {}", encoded),
+        format!("pubcrypt-elgamal  {}", encoded),
+        format!("pubcrypt-elgamal {}*", encoded),
+        format!("{}\n{}", line, line),
+    ];
+    for text in &invalid {
+        assert_eq!(from_line(text), Err(KeyError::InvalidLine), "{}", text);
+    }
+
+    // valid base64 that isn't a key is rejected as DER
+    assert_eq!(
+        from_line("pubcrypt-elgamal AAAA"),
+        Err(KeyError::InvalidDer)
+    );
+    assert!(!is_line(b"pubcrypt-elgamalAAAA"));
+}
@@ -0,0 +1,190 @@
+/*!
+ * Just enough of the OpenPGP packet format (RFC 4880) to import the ElGamal encryption key from a
+ * key exported by GnuPG, e.g., with `gpg --export`, so that messages can be encrypted to it.
+ *
+ * An exported key is a sequence of packets, each a header giving its tag and length, followed by
+ * its body. `read_elgamal_key` looks for the first public key or subkey packet whose algorithm is
+ * ElGamal, reads its prime `p`, generator `g`, and value `y` as a pubcrypt `Key`, and also keeps
+ * the first User ID packet, which names the key's owner. Every other packet, including
+ * signatures, is skipped without being checked.
+ *
+ * GnuPG's ElGamal keys are 1024 bits or more, so they're only usable once their numbers fit in a
+ * `Num`; until then, `KeyError::NumberTooLarge` is returned for them.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{Key, KeyError, Num, PublicKey, NUM_BYTES};
+
+/**
+ * The label of an exported public key in ASCII armor; see `armor::depem_labeled`.
+ */
+pub const PUBLIC_KEY_LABEL: &str = "PGP PUBLIC KEY BLOCK";
+
+const PUBLIC_KEY_TAG: u8 = 6;
+const USER_ID_TAG: u8 = 13;
+const PUBLIC_SUBKEY_TAG: u8 = 14;
+
+// ElGamal keys are encrypt-only (16), or in old keys, also for signing (20)
+const ELGAMAL_ALGORITHMS: [u8; 2] = [16, 20];
+
+/**
+ * The ElGamal key imported from an OpenPGP key, with the details that pubcrypt can record about
+ * it.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenPgpKey {
+    /**
+     * The ElGamal key itself.
+     */
+    pub key: PublicKey,
+    /**
+     * When the key was created, in seconds since the Unix epoch.
+     */
+    pub created: u64,
+    /**
+     * The first User ID in the exported key, e.g., `Alice <alice@example.com>`, if there is one.
+     */
+    pub user_id: Option<String>,
+}
+
+/**
+ * Read the first ElGamal key from the binary OpenPGP packets in `bytes`. The key isn't checked.
+ *
+ * Returns `KeyError::InvalidOpenPgp` if the packets are malformed, `UnknownAlgorithm` if none of
+ * them is an ElGamal key, and `NumberTooLarge` if the key's numbers don't fit in a `Num`.
+ */
+pub fn read_elgamal_key(mut bytes: &[u8]) -> Result<OpenPgpKey, KeyError> {
+    let mut user_id = None;
+    let mut found = None;
+
+    while !bytes.is_empty() {
+        let (tag, body, rest) = read_packet(bytes)?;
+        bytes = rest;
+
+        match tag {
+            USER_ID_TAG if user_id.is_none() => {
+                user_id = Some(String::from_utf8_lossy(body).into_owned());
+            }
+            PUBLIC_KEY_TAG | PUBLIC_SUBKEY_TAG if found.is_none() => {
+                found = read_elgamal_body(body)?;
+            }
+            _ => {}
+        }
+    }
+
+    let (key, created) = found.ok_or(KeyError::UnknownAlgorithm)?;
+    Ok(OpenPgpKey {
+        key,
+        created,
+        user_id,
+    })
+}
+
+/**
+ * Split the packet at the start of `bytes` into its tag, its body, and the bytes after it.
+ */
+fn read_packet(bytes: &[u8]) -> Result<(u8, &[u8], &[u8]), KeyError> {
+    let (&header, rest) = bytes.split_first().ok_or(KeyError::InvalidOpenPgp)?;
+    if header & 0x80 == 0 {
+        return Err(KeyError::InvalidOpenPgp);
+    }
+
+    let (tag, len, rest) = if header & 0x40 != 0 {
+        // new format: the tag is the low six bits, and the length's size depends on its first byte
+        let (&first, rest) = rest.split_first().ok_or(KeyError::InvalidOpenPgp)?;
+        match first {
+            0..=191 => (header & 0x3f, first as usize, rest),
+            192..=223 => {
+                let (&second, rest) = rest.split_first().ok_or(KeyError::InvalidOpenPgp)?;
+                let len = ((first as usize - 192) << 8) + second as usize + 192;
+                (header & 0x3f, len, rest)
+            }
+            255 => {
+                let (len, rest) = read_be(rest, 4)?;
+                (header & 0x3f, len, rest)
+            }
+            // partial lengths are only used for data packets, never keys
+            _ => return Err(KeyError::InvalidOpenPgp),
+        }
+    } else {
+        // old format: the tag is bits 2 to 5, and the low two bits give the length's size
+        let len_bytes = match header & 0x03 {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => return Err(KeyError::InvalidOpenPgp),
+        };
+        let (len, rest) = read_be(rest, len_bytes)?;
+        ((header >> 2) & 0x0f, len, rest)
+    };
+
+    if rest.len() < len {
+        return Err(KeyError::InvalidOpenPgp);
+    }
+    let (body, rest) = rest.split_at(len);
+    Ok((tag, body, rest))
+}
+
+/**
+ * Read the body of a public key packet, returning the key and its creation time if it's an
+ * ElGamal key, or `None` if it's for another algorithm.
+ */
+fn read_elgamal_body(body: &[u8]) -> Result<Option<(PublicKey, u64)>, KeyError> {
+    let (&version, rest) = body.split_first().ok_or(KeyError::InvalidOpenPgp)?;
+    let (created, rest) = read_be(rest, 4)?;
+    let rest = match version {
+        // version 3 keys also have a two-byte validity period
+        3 => rest.get(2..).ok_or(KeyError::InvalidOpenPgp)?,
+        4 => rest,
+        _ => return Err(KeyError::InvalidOpenPgp),
+    };
+
+    let (&algorithm, rest) = rest.split_first().ok_or(KeyError::InvalidOpenPgp)?;
+    if !ELGAMAL_ALGORITHMS.contains(&algorithm) {
+        return Ok(None);
+    }
+
+    let (prime, rest) = read_mpi(rest)?;
+    let (root, rest) = read_mpi(rest)?;
+    let (value, _) = read_mpi(rest)?;
+    let key = Key { prime, root, value };
+    Ok(Some((PublicKey::from(key), created as u64)))
+}
+
+/**
+ * Read a multiprecision integer, i.e., its two-byte length in bits followed by its big-endian
+ * bytes, from the start of `bytes`.
+ */
+fn read_mpi(bytes: &[u8]) -> Result<(Num, &[u8]), KeyError> {
+    let (bits, rest) = read_be(bytes, 2)?;
+    let len = bits.div_ceil(8);
+    if rest.len() < len {
+        return Err(KeyError::InvalidOpenPgp);
+    }
+
+    let (value, rest) = rest.split_at(len);
+    if len > NUM_BYTES {
+        return Err(KeyError::NumberTooLarge);
+    }
+
+    let mut buf = [0_u8; NUM_BYTES];
+    buf[NUM_BYTES - len..].copy_from_slice(value);
+    Ok((Num::from_be_bytes(buf), rest))
+}
+
+/**
+ * Read a big-endian number of `len` bytes from the start of `bytes`.
+ */
+fn read_be(bytes: &[u8], len: usize) -> Result<(usize, &[u8]), KeyError> {
+    if bytes.len() < len {
+        return Err(KeyError::InvalidOpenPgp);
+    }
+
+    let (number, rest) = bytes.split_at(len);
+    let number = number
+        .iter()
+        .fold(0_usize, |number, &byte| number << 8 | byte as usize);
+    Ok((number, rest))
+}
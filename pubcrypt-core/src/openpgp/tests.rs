@@ -0,0 +1,121 @@
+use super::*;
+use crate::armor;
+
+/**
+ * Encode `value` as an MPI.
+ */
+fn mpi(value: Num) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(NUM_BYTES);
+    let bits = (Num::BITS - value.leading_zeros()) as u16;
+    let mut result = bits.to_be_bytes().to_vec();
+    result.extend_from_slice(&bytes[start..]);
+    result
+}
+
+/**
+ * Encode a new-format packet with a one-byte length.
+ */
+fn packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut result = vec![0xc0 | tag, body.len() as u8];
+    result.extend_from_slice(body);
+    result
+}
+
+/**
+ * Encode the body of a version 4 public key packet.
+ */
+fn key_body(algorithm: u8, numbers: &[Num]) -> Vec<u8> {
+    let mut body = vec![4, 0x60, 0x00, 0x00, 0x00, algorithm];
+    for &number in numbers {
+        body.extend_from_slice(&mpi(number));
+    }
+    body
+}
+
+#[test]
+fn test_elgamal_subkey() {
+    // a DSA primary key with a user ID and an ElGamal subkey, like GnuPG's defaults used to be
+    let mut exported = packet(PUBLIC_KEY_TAG, &key_body(17, &[23, 11, 4, 8]));
+    exported.extend(packet(USER_ID_TAG, b"Alice <alice@example.com>"));
+    exported.extend(packet(2, &[4, 0x18, 0x11]));
+    exported.extend(packet(PUBLIC_SUBKEY_TAG, &key_body(16, &[23, 5, 8])));
+
+    let expected = OpenPgpKey {
+        key: PublicKey::from(Key {
+            prime: 23,
+            root: 5,
+            value: 8,
+        }),
+        created: 0x6000_0000,
+        user_id: Some("Alice <alice@example.com>".to_string()),
+    };
+    assert_eq!(read_elgamal_key(&exported), Ok(expected.clone()));
+
+    // the same key in old-format packets and ASCII armor with a header
+    let body = key_body(20, &[23, 5, 8]);
+    let mut old_format = vec![0x80 | PUBLIC_KEY_TAG << 2 | 1, 0, body.len() as u8];
+    old_format.extend_from_slice(&body);
+    let text = armor::pem_labeled(PUBLIC_KEY_LABEL, &old_format).replacen(
+        "-----\n",
+        "-----\nVersion: GnuPG v1\n\n",
+        1,
+    );
+    let bytes = armor::depem_labeled(PUBLIC_KEY_LABEL, text.as_bytes()).unwrap();
+    assert_eq!(
+        read_elgamal_key(&bytes),
+        Ok(OpenPgpKey {
+            user_id: None,
+            ..expected
+        })
+    );
+}
+
+#[test]
+fn test_two_byte_lengths() {
+    let user_id = vec![b'a'; 300];
+    let mut exported = vec![0xc0 | USER_ID_TAG, 192, (300 - 192) as u8];
+    exported.extend_from_slice(&user_id);
+    exported.extend(packet(PUBLIC_SUBKEY_TAG, &key_body(16, &[Num::MAX, 2, 3])));
+
+    let key = read_elgamal_key(&exported).unwrap();
+    assert_eq!(key.user_id.as_deref(), Some(&"a".repeat(300)[..]));
+    assert_eq!(key.key.key().prime(), Num::MAX);
+}
+
+#[test]
+fn test_invalid_openpgp() {
+    let rsa_only = packet(PUBLIC_KEY_TAG, &key_body(1, &[3233, 17]));
+    assert_eq!(read_elgamal_key(&rsa_only), Err(KeyError::UnknownAlgorithm));
+    assert_eq!(read_elgamal_key(&[]), Err(KeyError::UnknownAlgorithm));
+
+    let key = packet(PUBLIC_KEY_TAG, &key_body(16, &[23, 5, 8]));
+    let mut version_5 = key.clone();
+    version_5[2] = 5;
+    let invalid = [
+        &key[..key.len() - 1],
+        &[0x00, 0x00],
+        &[0xc6, 230, 0x00],
+        &[0x9b, 0x00],
+        &version_5[..],
+    ];
+    for bytes in invalid {
+        assert_eq!(
+            read_elgamal_key(bytes),
+            Err(KeyError::InvalidOpenPgp),
+            "{:?}",
+            bytes
+        );
+    }
+
+    // GnuPG's keys have primes of 1024 bits or more
+    let mut large = vec![4, 0, 0, 0, 0, 16];
+    large.extend_from_slice(&1024_u16.to_be_bytes());
+    large.extend_from_slice(&[0xff; 128]);
+    large.extend(mpi(5));
+    large.extend(mpi(8));
+    assert_eq!(
+        read_elgamal_key(&packet(PUBLIC_SUBKEY_TAG, &large)),
+        Err(KeyError::NumberTooLarge)
+    );
+}
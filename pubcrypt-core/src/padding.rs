@@ -0,0 +1,105 @@
+use crate::stream::pad_slice;
+use rand::Rng;
+use std::fmt;
+
+/**
+ * How the final block of a ciphertext is padded out to the block size. The final block is always
+ * padded with at least one byte, so a plaintext that ends on a block boundary gets an extra block
+ * consisting solely of padding.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Padding {
+    /**
+     * Unused bytes are filled with random data, and the last byte holds the number of pad bytes.
+     * Only the pad count can be validated on decryption.
+     */
+    Random,
+    /**
+     * PKCS#7: each pad byte holds the number of pad bytes. Every pad byte is validated on
+     * decryption.
+     */
+    Pkcs7,
+    /**
+     * Unused bytes are filled with zeros, and every trailing zero byte of the final block is
+     * stripped on decryption. Since trailing zeros in the plaintext would be stripped too, only
+     * plaintexts that don't end with a zero byte can be encrypted with this padding.
+     */
+    Zero,
+}
+
+impl Padding {
+    /**
+     * Get the identifier used for the padding in ciphertext headers.
+     */
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Self::Random => 0,
+            Self::Pkcs7 => 1,
+            Self::Zero => 2,
+        }
+    }
+
+    /**
+     * Get the padding with the given header identifier, if any.
+     */
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Random),
+            1 => Some(Self::Pkcs7),
+            2 => Some(Self::Zero),
+            _ => None,
+        }
+    }
+
+    /**
+     * Fill the last `pad_bytes` bytes of `buf` with padding, generating any random bytes with
+     * `rng`.
+     *
+     * Panics if `pad_bytes` is 0 or greater than `buf.len()`.
+     */
+    pub(crate) fn pad<T: Rng>(self, buf: &mut [u8], pad_bytes: u8, rng: &mut T) {
+        assert!(pad_bytes > 0);
+
+        let pad_start = buf.len() - pad_bytes as usize;
+        match self {
+            Self::Random => pad_slice(buf, pad_bytes, rng),
+            Self::Pkcs7 => buf[pad_start..].fill(pad_bytes),
+            Self::Zero => buf[pad_start..].fill(0),
+        }
+    }
+
+    /**
+     * Get the number of pad bytes at the end of the final block `buf`, or `None` if the padding
+     * isn't valid.
+     */
+    pub(crate) fn unpad(self, buf: &[u8]) -> Option<usize> {
+        let last = *buf.last()?;
+        let pad_bytes = match self {
+            Self::Random | Self::Pkcs7 => last as usize,
+            Self::Zero => buf.iter().rev().take_while(|&&b| b == 0).count(),
+        };
+
+        if pad_bytes == 0 || pad_bytes > buf.len() {
+            return None;
+        }
+
+        let pad_start = buf.len() - pad_bytes;
+        if self == Self::Pkcs7 && buf[pad_start..].iter().any(|&b| b != last) {
+            return None;
+        }
+
+        Some(pad_bytes)
+    }
+}
+
+impl fmt::Display for Padding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Random => write!(f, "random"),
+            Self::Pkcs7 => write!(f, "PKCS#7"),
+            Self::Zero => write!(f, "zero"),
+        }
+    }
+}
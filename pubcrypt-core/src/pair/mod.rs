@@ -0,0 +1,76 @@
+/*!
+ * Key pair files, which hold the public and private key files of one pair, so that both halves
+ * can be provisioned and kept together as a single file.
+ *
+ * A key pair file consists of the magic bytes `PUBCRKPR`, the big-endian four-byte length of the
+ * public key file, the public key file, and the private key file, which takes up the rest. Both
+ * are complete key files, with their own metadata, and the private key file may be protected.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{KeyError, KeyKind};
+use std::convert::{TryFrom, TryInto};
+
+/**
+ * The bytes that every key pair file starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRKPR";
+
+const LENGTH_BYTES: usize = 4;
+const HEADER_BYTES: usize = MAGIC.len() + LENGTH_BYTES;
+
+/**
+ * Write a key pair file holding the key files `public` and `private`.
+ *
+ * # Panics
+ *
+ * Panics if `public` is longer than `u32::MAX` bytes, which no key file is.
+ */
+pub fn write_pair(public: &[u8], private: &[u8]) -> Vec<u8> {
+    let length: u32 = public.len().try_into().expect("key file is too long");
+    let mut result = Vec::with_capacity(HEADER_BYTES + public.len() + private.len());
+    result.extend_from_slice(&MAGIC);
+    result.extend_from_slice(&length.to_be_bytes());
+    result.extend_from_slice(public);
+    result.extend_from_slice(private);
+    result
+}
+
+/**
+ * Check whether `bytes` looks like a key pair file. The rest of the file is checked by
+ * `read_pair`.
+ */
+pub fn is_pair(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/**
+ * Split a key pair file into its public and private key files.
+ *
+ * Returns `KeyError::InvalidPair` if `bytes` isn't a key pair file, or either key file is empty.
+ */
+pub fn read_pair(bytes: &[u8]) -> Result<(&[u8], &[u8]), KeyError> {
+    if !is_pair(bytes) || bytes.len() < HEADER_BYTES {
+        return Err(KeyError::InvalidPair);
+    }
+
+    let length = u32::from_be_bytes(bytes[MAGIC.len()..HEADER_BYTES].try_into().unwrap());
+    let rest = &bytes[HEADER_BYTES..];
+    match usize::try_from(length) {
+        Ok(length) if length > 0 && length < rest.len() => Ok(rest.split_at(length)),
+        _ => Err(KeyError::InvalidPair),
+    }
+}
+
+/**
+ * Get the key file of the given kind from a key pair file, as in `read_pair`.
+ */
+pub fn read_half(bytes: &[u8], kind: KeyKind) -> Result<&[u8], KeyError> {
+    let (public, private) = read_pair(bytes)?;
+    Ok(match kind {
+        KeyKind::Public => public,
+        KeyKind::Private => private,
+    })
+}
@@ -0,0 +1,29 @@
+use super::*;
+
+#[test]
+fn test_pair() {
+    let pair = write_pair(b"public", b"private key");
+    assert!(is_pair(&pair));
+    assert_eq!(
+        read_pair(&pair).unwrap(),
+        (&b"public"[..], &b"private key"[..])
+    );
+    assert_eq!(read_half(&pair, KeyKind::Public).unwrap(), b"public");
+    assert_eq!(read_half(&pair, KeyKind::Private).unwrap(), b"private key");
+}
+
+#[test]
+fn test_pair_errors() {
+    let pair = write_pair(b"public", b"private key");
+    assert!(!is_pair(b"public"));
+    assert_eq!(read_pair(b"public"), Err(KeyError::InvalidPair));
+
+    // a file cut off inside either key file, or with an empty one, isn't a pair
+    for len in 0..=HEADER_BYTES + b"public".len() {
+        assert_eq!(read_pair(&pair[..len]), Err(KeyError::InvalidPair));
+    }
+    assert_eq!(
+        read_pair(&write_pair(b"", b"private key")),
+        Err(KeyError::InvalidPair)
+    );
+}
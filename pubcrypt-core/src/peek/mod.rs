@@ -0,0 +1,111 @@
+/*!
+ * A buffered reader that can look ahead by a given number of bytes, for telling formats apart by
+ * their first bytes when reading from pipes, sockets, and other sources where short reads are
+ * normal.
+ *
+ * `BufReader::fill_buf` only reads when its buffer is empty, and a single read from a pipe can
+ * return as little as one byte, so checking its buffer for magic bytes can miss them. `PeekReader`
+ * keeps reading until it has as many bytes as were asked for, or the input ends.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use std::io::{self, BufRead, Read};
+
+const READ_BYTES: usize = 8 * 1024;
+
+/**
+ * A reader that buffers `inner`, and can be asked to buffer a minimum number of bytes.
+ */
+pub struct PeekReader<R: Read> {
+    inner: R,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> PeekReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /**
+     * Get at least the next `len` bytes without consuming them, reading from the inner reader as
+     * many times as needed. Fewer bytes are returned only if the input ends first. More may be
+     * returned if they're already buffered.
+     */
+    pub fn peek(&mut self, len: usize) -> io::Result<&[u8]> {
+        // move the unconsumed bytes to the front, so the buffer doesn't grow without bound
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+
+        while self.buf.len() < len {
+            let start = self.buf.len();
+            self.buf.resize(start + (len - start).max(READ_BYTES), 0);
+            match self.inner.read(&mut self.buf[start..]) {
+                Ok(count) => {
+                    self.buf.truncate(start + count);
+                    if count == 0 {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    self.buf.truncate(start);
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Ok(&self.buf)
+    }
+
+    /**
+     * Get the bytes that are buffered, but haven't been consumed yet.
+     */
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /**
+     * Get the inner reader, discarding anything buffered.
+     */
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for PeekReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // large reads skip the buffer once it's empty
+        if self.pos == self.buf.len() && buf.len() >= READ_BYTES {
+            return self.inner.read(buf);
+        }
+
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<R: Read> BufRead for PeekReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.buf.len() {
+            self.peek(1)?;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
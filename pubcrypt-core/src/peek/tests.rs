@@ -0,0 +1,75 @@
+use super::*;
+
+/**
+ * A reader that returns one byte at a time, and is interrupted before every other byte, like a
+ * slow pipe.
+ */
+struct Trickle<'a> {
+    data: &'a [u8],
+    interrupt: bool,
+}
+
+impl<'a> Trickle<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            interrupt: true,
+        }
+    }
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::Error::from(io::ErrorKind::Interrupted));
+        }
+        let len = buf.len().min(1);
+        self.data.read(&mut buf[..len])
+    }
+}
+
+#[test]
+fn test_peek() {
+    let data: Vec<u8> = (0..100).collect();
+    let mut reader = PeekReader::new(Trickle::new(&data));
+    assert_eq!(reader.peek(8).unwrap(), &data[..8]);
+    assert_eq!(reader.peek(4).unwrap(), &data[..8]);
+
+    let mut start = [0_u8; 3];
+    reader.read_exact(&mut start).unwrap();
+    assert_eq!(start, data[..3]);
+    assert_eq!(reader.buffer(), &data[3..8]);
+    assert_eq!(reader.peek(10).unwrap(), &data[3..13]);
+
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, data[3..]);
+    assert!(reader.peek(8).unwrap().is_empty());
+}
+
+#[test]
+fn test_peek_past_end() {
+    let data = b"short";
+    let mut reader = PeekReader::new(Trickle::new(data));
+    assert_eq!(reader.peek(100).unwrap(), data);
+    assert_eq!(reader.fill_buf().unwrap(), data);
+    reader.consume(2);
+    assert_eq!(reader.fill_buf().unwrap(), b"ort");
+
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "ort");
+}
+
+#[test]
+fn test_large_reads() {
+    let data = vec![7_u8; READ_BYTES * 3];
+    let mut reader = PeekReader::new(&data[..]);
+    assert_eq!(reader.peek(2).unwrap().len(), READ_BYTES);
+
+    let mut buf = vec![0_u8; READ_BYTES * 2];
+    assert_eq!(reader.read(&mut buf).unwrap(), READ_BYTES);
+    assert_eq!(reader.read(&mut buf).unwrap(), READ_BYTES * 2);
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+}
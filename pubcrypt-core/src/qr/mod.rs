@@ -0,0 +1,116 @@
+/*!
+ * QR codes, which let a phone or another device scan a compact key (see the `compact` module)
+ * instead of typing it. Codes can be drawn as text for a terminal, or written as PNG images.
+ *
+ * This module is only available with the `qr` feature. Codes use the medium error correction
+ * level, and are surrounded by the four-module quiet zone that scanners need.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use qrcode::{types::Color, EcLevel, QrCode};
+use std::io::{self, Write};
+
+/**
+ * The number of light modules around each side of a code.
+ */
+pub const QUIET_ZONE: usize = 4;
+
+/**
+ * The number of pixels on each side of a module in PNG images.
+ */
+pub const PNG_SCALE: usize = 8;
+
+/**
+ * The modules of a QR code, including its quiet zone.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrModules {
+    width: usize,
+    dark: Vec<bool>,
+}
+
+impl QrModules {
+    /**
+     * Encode `text` as a QR code.
+     *
+     * Returns an error of kind `io::ErrorKind::InvalidInput` if the text is too long for a QR
+     * code.
+     */
+    pub fn new(text: &str) -> io::Result<Self> {
+        let code = QrCode::with_error_correction_level(text, EcLevel::M)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        let inner = code.width();
+        let colors = code.to_colors();
+
+        let width = inner + QUIET_ZONE * 2;
+        let mut dark = vec![false; width * width];
+        for (i, &color) in colors.iter().enumerate() {
+            let (x, y) = (i % inner + QUIET_ZONE, i / inner + QUIET_ZONE);
+            dark[y * width + x] = color == Color::Dark;
+        }
+
+        Ok(Self { width, dark })
+    }
+
+    /**
+     * Get the number of modules on each side of the code, including its quiet zone.
+     */
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /**
+     * Check whether the module in column `x` and row `y` is dark.
+     */
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.width + x]
+    }
+
+    /**
+     * Draw the code with Unicode half blocks, two rows of modules per line. Light modules are
+     * drawn as blocks and dark ones as spaces, so the code scans on a terminal with a dark
+     * background.
+     */
+    pub fn to_terminal(&self) -> String {
+        let mut result = String::new();
+        for y in (0..self.width).step_by(2) {
+            for x in 0..self.width {
+                let top = !self.is_dark(x, y);
+                let bottom = y + 1 < self.width && !self.is_dark(x, y + 1);
+                result.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    /**
+     * Write the code to `writer` as an 8-bit grayscale PNG image, with `PNG_SCALE` pixels on each
+     * side of a module.
+     */
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let side = self.width * PNG_SCALE;
+        let mut pixels = Vec::with_capacity(side * side);
+        for y in 0..side {
+            for x in 0..side {
+                let dark = self.is_dark(x / PNG_SCALE, y / PNG_SCALE);
+                pixels.push(if dark { 0x00 } else { 0xff });
+            }
+        }
+
+        let mut encoder = png::Encoder::new(writer, side as u32, side as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use super::*;
+use crate::{compact, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+fn key_modules() -> QrModules {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    QrModules::new(&compact::to_compact(&keys.public)).unwrap()
+}
+
+#[test]
+fn test_modules() {
+    let modules = key_modules();
+    let width = modules.width();
+    assert_eq!((width - QUIET_ZONE * 2) % 4, 1);
+
+    // the quiet zone is light, and the finder pattern starts in the top left corner
+    for i in 0..width {
+        assert!(!modules.is_dark(i, 0) && !modules.is_dark(0, i));
+        assert!(!modules.is_dark(i, width - 1) && !modules.is_dark(width - 1, i));
+    }
+    assert!(modules.is_dark(QUIET_ZONE, QUIET_ZONE));
+    assert!(!modules.is_dark(QUIET_ZONE + 1, QUIET_ZONE + 1));
+
+    let drawn = modules.to_terminal();
+    let lines: Vec<&str> = drawn.lines().collect();
+    assert_eq!(lines.len(), width.div_ceil(2));
+    assert!(lines.iter().all(|line| line.chars().count() == width));
+    assert!(lines[0].chars().all(|c| c == '█'));
+
+    let too_long = "A".repeat(5000);
+    assert_eq!(
+        QrModules::new(&too_long).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn test_png() {
+    let modules = key_modules();
+    let mut image = Vec::new();
+    modules.write_png(&mut image).unwrap();
+
+    let decoder = png::Decoder::new(&image[..]);
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    let side = modules.width() * PNG_SCALE;
+    assert_eq!((info.width as usize, info.height as usize), (side, side));
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+
+    for (i, &pixel) in pixels[..info.buffer_size()].iter().enumerate() {
+        let (x, y) = (i % side / PNG_SCALE, i / side / PNG_SCALE);
+        assert_eq!(pixel == 0, modules.is_dark(x, y));
+    }
+}
@@ -0,0 +1,381 @@
+/*!
+ * RSA encryption, as an alternative `cipher::PublicKeyCipher` to ElGamal.
+ *
+ * Keys are made of two random primes of half a `Num` each, whose product, the modulus, has its
+ * top bit set. The public exponent is always `PUBLIC_EXPONENT`, and decryption uses the Chinese
+ * remainder theorem, exponentiating modulo each prime separately.
+ *
+ * Before encryption, each block is formatted in the style of PKCS #1 v1.5: a zero byte, the byte
+ * 2, random nonzero bytes, and then the block, filling a `Num`. The zero byte keeps the formatted
+ * block below the modulus, and the random bytes keep equal blocks from encrypting to the same
+ * ciphertext. With a 64-bit modulus there are only 2 random bytes (6 with the `wide` feature), so
+ * this is far weaker than real RSA, which uses moduli of thousands of bits.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    cipher::PublicKeyCipher, Algorithm, Block, CryptError, KeyError, KeyGenError, Num, BLOCK_BYTES,
+    NUM_BYTES,
+};
+use math::{ext_gcd, gcd, isqrt, mod_exp, mod_exp_ct, mul_mod, primes};
+use rand::Rng;
+
+/**
+ * The public exponent of every generated key.
+ */
+pub const PUBLIC_EXPONENT: Num = 65537;
+
+const BITS_PER_BYTE: usize = 8;
+const NUM_BITS: usize = NUM_BYTES * BITS_PER_BYTE;
+
+// the marker byte after the leading zero byte of a formatted block
+const BLOCK_TYPE: u8 = 2;
+const RANDOM_BYTES: usize = NUM_BYTES - 2 - BLOCK_BYTES;
+
+/**
+ * An RSA public key: the modulus and public exponent.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RsaPublicKey {
+    modulus: Num,
+    exponent: Num,
+}
+
+impl RsaPublicKey {
+    /**
+     * The number of bytes in a serialized public key.
+     */
+    pub const KEY_BYTES: usize = NUM_BYTES * 2;
+
+    /**
+     * Get the modulus, which is the product of the private key's primes.
+     */
+    pub fn modulus(&self) -> Num {
+        self.modulus
+    }
+
+    /**
+     * Get the public exponent.
+     */
+    pub fn exponent(&self) -> Num {
+        self.exponent
+    }
+
+    /**
+     * Convert key to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; Self::KEY_BYTES] {
+        let mut result = [0_u8; Self::KEY_BYTES];
+        result[..NUM_BYTES].copy_from_slice(&self.modulus.to_be_bytes());
+        result[NUM_BYTES..].copy_from_slice(&self.exponent.to_be_bytes());
+        result
+    }
+
+    /**
+     * Read key from serialized bytes, and check that it's valid with `validate`.
+     */
+    pub fn try_deserialize(bytes: &[u8; Self::KEY_BYTES]) -> Result<Self, KeyError> {
+        let key = Self {
+            modulus: read_num(bytes, 0),
+            exponent: read_num(bytes, 1),
+        };
+
+        key.validate()?;
+        Ok(key)
+    }
+
+    /**
+     * Check that the modulus has its top bit set, so that every formatted block is less than it,
+     * and that the exponent is odd and in the range `(1, modulus)`. The modulus can't be factored
+     * to check it any further.
+     */
+    pub fn validate(&self) -> Result<(), KeyError> {
+        if self.modulus >> (NUM_BITS - 1) != 1 {
+            return Err(KeyError::InvalidModulus);
+        }
+
+        if self.exponent <= 1 || self.exponent >= self.modulus || self.exponent.is_multiple_of(2) {
+            return Err(KeyError::InvalidValue);
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * An RSA private key: the two primes, the public and private exponents, and the values needed to
+ * decrypt with the Chinese remainder theorem.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RsaPrivateKey {
+    p: Num,
+    q: Num,
+    public_exponent: Num,
+    private_exponent: Num,
+    // `d mod (p - 1)`, `d mod (q - 1)`, and `q^-1 mod p`
+    dp: Num,
+    dq: Num,
+    q_inv: Num,
+}
+
+impl RsaPrivateKey {
+    /**
+     * The number of bytes in a serialized private key.
+     */
+    pub const KEY_BYTES: usize = NUM_BYTES * 4;
+
+    /**
+     * Create a private key from its primes and public exponent, computing the rest.
+     *
+     * Returns `KeyError::InvalidValue` if the exponent has no inverse modulo `(p - 1)(q - 1)`.
+     */
+    fn from_primes(p: Num, q: Num, public_exponent: Num) -> Result<Self, KeyError> {
+        let phi = (p - 1) * (q - 1);
+        let (divisor, private_exponent) = ext_gcd(public_exponent, phi);
+        if divisor != 1 {
+            return Err(KeyError::InvalidValue);
+        }
+
+        Ok(Self {
+            p,
+            q,
+            public_exponent,
+            private_exponent,
+            dp: private_exponent % (p - 1),
+            dq: private_exponent % (q - 1),
+            q_inv: ext_gcd(q, p).1,
+        })
+    }
+
+    /**
+     * Get the public key that corresponds to this private key.
+     */
+    pub fn public_key(&self) -> RsaPublicKey {
+        RsaPublicKey {
+            modulus: self.p * self.q,
+            exponent: self.public_exponent,
+        }
+    }
+
+    /**
+     * Convert key to bytes that can be saved to the disk. Only the primes and exponents are
+     * stored; the rest is recomputed when the key is read.
+     */
+    pub fn serialize(&self) -> [u8; Self::KEY_BYTES] {
+        let mut result = [0_u8; Self::KEY_BYTES];
+        let nums = [self.p, self.q, self.public_exponent, self.private_exponent];
+        for (chunk, num) in result.chunks_mut(NUM_BYTES).zip(&nums) {
+            chunk.copy_from_slice(&num.to_be_bytes());
+        }
+
+        result
+    }
+
+    /**
+     * Read key from serialized bytes, and check that it's valid: the primes must be distinct
+     * primes whose product is a valid modulus for `RsaPublicKey::validate`, and the exponents
+     * must be inverses. Primality is tested probabilistically using `rng`.
+     */
+    pub fn try_deserialize<T: Rng>(
+        bytes: &[u8; Self::KEY_BYTES],
+        rng: &mut T,
+    ) -> Result<Self, KeyError> {
+        const WITNESS_COUNT: usize = primes::DEFAULT_WITNESS_COUNT;
+
+        let (p, q) = (read_num(bytes, 0), read_num(bytes, 1));
+        let private_exponent = read_num(bytes, 3);
+
+        // both primes fit in half a `Num`, so their product can only overflow if one doesn't
+        if p == q || p.checked_mul(q).is_none() {
+            return Err(KeyError::InvalidModulus);
+        }
+
+        if !primes::is_prime(p, WITNESS_COUNT, rng) || !primes::is_prime(q, WITNESS_COUNT, rng) {
+            return Err(KeyError::NotPrime);
+        }
+
+        let key = Self::from_primes(p, q, read_num(bytes, 2))?;
+        key.public_key().validate()?;
+        if key.private_exponent != private_exponent {
+            return Err(KeyError::MismatchedPair);
+        }
+
+        Ok(key)
+    }
+}
+
+/**
+ * Read the `index`th `Num` from `bytes`.
+ */
+fn read_num(bytes: &[u8], index: usize) -> Num {
+    let mut buf = [0_u8; NUM_BYTES];
+    buf.copy_from_slice(&bytes[index * NUM_BYTES..(index + 1) * NUM_BYTES]);
+    Num::from_be_bytes(buf)
+}
+
+/**
+ * Generate an RSA key pair using `rng`, with primes tested using `witness_count` Miller-Rabin
+ * witnesses.
+ */
+pub fn generate_with_witnesses<T: Rng>(
+    witness_count: usize,
+    rng: &mut T,
+) -> Result<(RsaPublicKey, RsaPrivateKey), KeyGenError> {
+    // primes at least `sqrt(2^(NUM_BITS - 1))` make a product with the top bit set
+    let half_bits = NUM_BITS / 2;
+    let max: Num = (1 << half_bits) - 1;
+    let min = isqrt((1 << (NUM_BITS - 1)) - 1) + 1;
+
+    let mut pick = || loop {
+        let prime = primes::pick_random(min, max, witness_count, rng)?;
+        if gcd(PUBLIC_EXPONENT, prime - 1) == 1 {
+            return Ok::<_, KeyGenError>(prime);
+        }
+    };
+
+    let p = pick()?;
+    let q = loop {
+        let q = pick()?;
+        if q != p {
+            break q;
+        }
+    };
+
+    // the exponent was checked against each prime, so it has an inverse
+    let private = RsaPrivateKey::from_primes(p, q, PUBLIC_EXPONENT).unwrap();
+    Ok((private.public_key(), private))
+}
+
+/**
+ * Format `block` as described in the module documentation, with random bytes from `rng`.
+ */
+fn format_block<T: Rng>(block: Block, rng: &mut T) -> Num {
+    let mut buf = [0_u8; NUM_BYTES];
+    buf[1] = BLOCK_TYPE;
+    for b in &mut buf[2..2 + RANDOM_BYTES] {
+        *b = rng.gen_range(1..=u8::MAX);
+    }
+
+    buf[2 + RANDOM_BYTES..].copy_from_slice(&block.to_be_bytes());
+    Num::from_be_bytes(buf)
+}
+
+/**
+ * Recover the block from a formatted `Num`, or `None` if the formatting is invalid.
+ */
+fn unformat_block(formatted: Num) -> Option<Block> {
+    let buf = formatted.to_be_bytes();
+    let random = &buf[2..2 + RANDOM_BYTES];
+    if buf[0] != 0 || buf[1] != BLOCK_TYPE || random.contains(&0) {
+        return None;
+    }
+
+    let mut block_buf = [0_u8; BLOCK_BYTES];
+    block_buf.copy_from_slice(&buf[2 + RANDOM_BYTES..]);
+    Some(Block::from_be_bytes(block_buf))
+}
+
+/**
+ * Encrypt `block` with `key`, formatting it with random bytes from `rng`.
+ */
+pub fn encrypt_block<T: Rng>(block: Block, key: &RsaPublicKey, rng: &mut T) -> Num {
+    mod_exp(format_block(block, rng), key.exponent, key.modulus)
+}
+
+/**
+ * Decrypt `ciphertext` with `key`, using the Chinese remainder theorem.
+ *
+ * Returns `CryptError::OutOfRange` if the ciphertext isn't less than the modulus, and
+ * `CryptError::WrongKey` if it doesn't decrypt to a formatted block, which happens when it was
+ * encrypted for a different key.
+ */
+pub fn decrypt_block(ciphertext: Num, key: &RsaPrivateKey) -> Result<Block, CryptError> {
+    let (p, q) = (key.p, key.q);
+    if ciphertext >= p * q {
+        return Err(CryptError::OutOfRange);
+    }
+
+    let m1 = mod_exp_ct(ciphertext % p, key.dp, p);
+    let m2 = mod_exp_ct(ciphertext % q, key.dq, q);
+
+    // `m = m2 + q * ((m1 - m2) * q^-1 mod p)`, which is `m1 mod p` and `m2 mod q`
+    let difference = (m1 + p - m2 % p) % p;
+    let h = mul_mod(difference, key.q_inv, p);
+    unformat_block(m2 + h * q).ok_or(CryptError::WrongKey)
+}
+
+/**
+ * The RSA `cipher::PublicKeyCipher`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rsa;
+
+impl PublicKeyCipher for Rsa {
+    const ALGORITHM: Algorithm = Algorithm::Rsa;
+    const CIPHERTEXT_BYTES: usize = NUM_BYTES;
+
+    type PublicKey = RsaPublicKey;
+    type PrivateKey = RsaPrivateKey;
+
+    fn generate<T: Rng>(rng: &mut T) -> Result<(RsaPublicKey, RsaPrivateKey), KeyGenError> {
+        generate_with_witnesses(primes::DEFAULT_WITNESS_COUNT, rng)
+    }
+
+    fn max_block_bytes(_key: &RsaPublicKey) -> usize {
+        BLOCK_BYTES
+    }
+
+    fn encrypt_block<T: Rng>(
+        block: Block,
+        key: &RsaPublicKey,
+        rng: &mut T,
+    ) -> Result<Vec<u8>, CryptError> {
+        Ok(encrypt_block(block, key, rng).to_be_bytes().to_vec())
+    }
+
+    fn decrypt_block(ciphertext: &[u8], key: &RsaPrivateKey) -> Result<Block, CryptError> {
+        if ciphertext.len() != Self::CIPHERTEXT_BYTES {
+            return Err(CryptError::CorruptBlock);
+        }
+
+        decrypt_block(read_num(ciphertext, 0), key)
+    }
+
+    fn serialize_public_key(key: &RsaPublicKey) -> Vec<u8> {
+        key.serialize().to_vec()
+    }
+
+    fn serialize_private_key(key: &RsaPrivateKey) -> Vec<u8> {
+        key.serialize().to_vec()
+    }
+
+    fn deserialize_public_key<T: Rng>(
+        bytes: &[u8],
+        _rng: &mut T,
+    ) -> Result<RsaPublicKey, KeyError> {
+        let mut buf = [0_u8; RsaPublicKey::KEY_BYTES];
+        if bytes.len() != buf.len() {
+            return Err(KeyError::InvalidLength);
+        }
+
+        buf.copy_from_slice(bytes);
+        RsaPublicKey::try_deserialize(&buf)
+    }
+
+    fn deserialize_private_key<T: Rng>(
+        bytes: &[u8],
+        rng: &mut T,
+    ) -> Result<RsaPrivateKey, KeyError> {
+        let mut buf = [0_u8; RsaPrivateKey::KEY_BYTES];
+        if bytes.len() != buf.len() {
+            return Err(KeyError::InvalidLength);
+        }
+
+        buf.copy_from_slice(bytes);
+        RsaPrivateKey::try_deserialize(&buf, rng)
+    }
+}
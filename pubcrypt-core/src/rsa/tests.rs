@@ -0,0 +1,131 @@
+use super::*;
+use crate::cipher;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_rsa_keys() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = Rsa::generate(&mut rng).unwrap();
+
+    assert_eq!(public.exponent(), PUBLIC_EXPONENT);
+    assert_eq!(public.modulus() >> (NUM_BITS - 1), 1);
+    assert_eq!(private.public_key(), public);
+    assert_eq!(public.validate(), Ok(()));
+    assert_eq!(
+        mul_mod(
+            private.public_exponent,
+            private.private_exponent,
+            (private.p - 1) * (private.q - 1)
+        ),
+        1
+    );
+
+    assert_eq!(
+        RsaPublicKey::try_deserialize(&public.serialize()),
+        Ok(public)
+    );
+    assert_eq!(
+        RsaPrivateKey::try_deserialize(&private.serialize(), &mut rng),
+        Ok(private)
+    );
+}
+
+#[test]
+fn test_rsa_key_validation() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = Rsa::generate(&mut rng).unwrap();
+
+    let small = RsaPublicKey {
+        modulus: public.modulus >> 1,
+        ..public.clone()
+    };
+    assert_eq!(small.validate(), Err(KeyError::InvalidModulus));
+    let even = RsaPublicKey {
+        exponent: 4,
+        ..public
+    };
+    assert_eq!(even.validate(), Err(KeyError::InvalidValue));
+
+    let check = |p: Num, q: Num, d: Num, expected: KeyError| {
+        let mut rng = StdRng::seed_from_u64(0);
+        let key = RsaPrivateKey {
+            p,
+            q,
+            private_exponent: d,
+            ..private.clone()
+        };
+        assert_eq!(
+            RsaPrivateKey::try_deserialize(&key.serialize(), &mut rng),
+            Err(expected)
+        );
+    };
+    let d = private.private_exponent;
+    check(private.p, private.p, d, KeyError::InvalidModulus);
+    check(private.p, private.q + 2, d, KeyError::NotPrime);
+    check(private.p, private.q, d + 1, KeyError::MismatchedPair);
+}
+
+#[test]
+fn test_rsa_crypt_block() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = Rsa::generate(&mut rng).unwrap();
+    let (_, other_private) = Rsa::generate(&mut rng).unwrap();
+
+    for &block in &[0, 1, Block::MAX / 3, Block::MAX] {
+        let c = encrypt_block(block, &public, &mut rng);
+        assert_eq!(decrypt_block(c, &private).unwrap(), block);
+
+        // the random bytes keep equal blocks from encrypting to the same ciphertext
+        assert_ne!(encrypt_block(block, &public, &mut rng), c);
+
+        // decrypting with the CRT gives the same result as the textbook formula
+        let formatted = mod_exp(c, private.private_exponent, public.modulus);
+        assert_eq!(unformat_block(formatted), Some(block));
+    }
+
+    let c = encrypt_block(Block::MAX, &public, &mut rng);
+    assert!(matches!(
+        decrypt_block(c % (other_private.p * other_private.q), &other_private),
+        Err(CryptError::WrongKey)
+    ));
+    assert!(matches!(
+        decrypt_block(public.modulus, &private),
+        Err(CryptError::OutOfRange)
+    ));
+}
+
+#[test]
+fn test_rsa_cipher() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (public, private) = Rsa::generate(&mut rng).unwrap();
+    let plaintext = b"encrypted with RSA instead of ElGamal";
+
+    let mut ciphertext = Vec::new();
+    cipher::encrypt::<Rsa, _, _, _>(&mut &plaintext[..], &mut ciphertext, &public, &mut rng)
+        .unwrap();
+    let mut decrypted = Vec::new();
+    cipher::decrypt::<Rsa, _, _>(&mut &ciphertext[..], &mut decrypted, &private).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // RSA ciphertexts are rejected by the ElGamal decryptors, and vice versa
+    let elgamal = crate::KeyPair::generate(&mut rng).unwrap();
+    let mut output = Vec::new();
+    assert!(matches!(
+        crate::ecb::decrypt_ecb(&mut &ciphertext[..], &mut output, &elgamal.private),
+        Err(CryptError::WrongAlgorithm(Algorithm::Rsa))
+    ));
+
+    let mut elgamal_ciphertext = Vec::new();
+    crate::ecb::encrypt_ecb(
+        &mut &plaintext[..],
+        &mut elgamal_ciphertext,
+        &elgamal.public,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(matches!(
+        cipher::decrypt::<Rsa, _, _>(&mut &elgamal_ciphertext[..], &mut output, &private),
+        Err(CryptError::WrongAlgorithm(Algorithm::ElGamal))
+    ));
+}
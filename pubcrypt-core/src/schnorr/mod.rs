@@ -0,0 +1,150 @@
+/*!
+ * Schnorr signatures over the subgroup of quadratic residues, using the same keys as encryption.
+ *
+ * For a safe prime `p = 2q + 1`, the squares modulo `p` form a subgroup of prime order `q`,
+ * generated by `g = root^2`. A key's private exponent `x` corresponds to the subgroup element
+ * `y = g^x = value^2`, so no new keys are needed.
+ *
+ * To sign the hash `m` of a message, the nonce `k` is derived deterministically by hashing the
+ * private exponent with `m`, so signing needs no randomness and a nonce never repeats across
+ * different messages. The signature is the pair `e = H(g^k, y, m) mod q` and
+ * `s = k + x * e mod q`, and it's valid if `e == H(g^s * y^-e, y, m) mod q`.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    hash::{Sha256, DIGEST_BYTES},
+    signature::hash_to_exponent,
+    Key, Num, PrivateKey, PublicKey, NUM_BYTES,
+};
+use math::modint::ModInt;
+
+/**
+ * The number of bytes in a serialized `Signature`.
+ */
+pub const SIGNATURE_BYTES: usize = NUM_BYTES * 2;
+
+/**
+ * A Schnorr signature on a message hash.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Signature {
+    e: Num,
+    s: Num,
+}
+
+impl Signature {
+    /**
+     * Get the challenge, the hash of the nonce commitment, public key, and message hash.
+     */
+    pub fn e(&self) -> Num {
+        self.e
+    }
+
+    /**
+     * Get the response, which ties the nonce to the challenge and key.
+     */
+    pub fn s(&self) -> Num {
+        self.s
+    }
+
+    /**
+     * Convert the signature to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; SIGNATURE_BYTES] {
+        let mut result = [0_u8; SIGNATURE_BYTES];
+        result[..NUM_BYTES].copy_from_slice(&self.e.to_be_bytes());
+        result[NUM_BYTES..].copy_from_slice(&self.s.to_be_bytes());
+        result
+    }
+
+    /**
+     * Read a signature from serialized bytes. The values are checked when the signature is
+     * verified.
+     */
+    pub fn deserialize(bytes: &[u8; SIGNATURE_BYTES]) -> Self {
+        let mut e_buf = [0_u8; NUM_BYTES];
+        let mut s_buf = [0_u8; NUM_BYTES];
+        e_buf.copy_from_slice(&bytes[..NUM_BYTES]);
+        s_buf.copy_from_slice(&bytes[NUM_BYTES..]);
+
+        Self {
+            e: Num::from_be_bytes(e_buf),
+            s: Num::from_be_bytes(s_buf),
+        }
+    }
+}
+
+/**
+ * Get the subgroup generator `g = root^2` and the subgroup order `q` for the key's prime.
+ */
+fn subgroup(key: &Key) -> (ModInt, Num) {
+    let root = ModInt::new(key.root, key.prime);
+    (root * root, (key.prime - 1) / 2)
+}
+
+/**
+ * Compute the challenge for the nonce commitment `commitment`, public element `y`, and message
+ * hash.
+ */
+fn challenge(commitment: ModInt, y: ModInt, message_hash: &[u8], order: Num) -> Num {
+    let mut hasher = Sha256::new();
+    hasher.update(b"pubcrypt schnorr challenge");
+    hasher.update(&commitment.value().to_be_bytes());
+    hasher.update(&y.value().to_be_bytes());
+    hasher.update(message_hash);
+
+    hash_to_exponent(&hasher.finalize(), order).value()
+}
+
+/**
+ * Sign `message_hash`, the SHA-256 digest of a message, with the given private key. Signing is
+ * deterministic, so the same message and key always produce the same signature.
+ */
+pub fn sign(message_hash: &[u8; DIGEST_BYTES], key: &PrivateKey) -> Signature {
+    let key = key.key();
+    let (generator, order) = subgroup(key);
+    let x = ModInt::new(key.value, order);
+    let y = generator.pow(key.value);
+
+    for counter in 0_u32.. {
+        let mut hasher = Sha256::new();
+        hasher.update(b"pubcrypt schnorr nonce");
+        hasher.update(&key.value.to_be_bytes());
+        hasher.update(message_hash);
+        hasher.update(&counter.to_be_bytes());
+
+        let k = hash_to_exponent(&hasher.finalize(), order);
+        if k.value() == 0 {
+            continue;
+        }
+
+        let e = challenge(generator.pow(k.value()), y, message_hash, order);
+        let s = k + x * ModInt::new(e, order);
+        return Signature { e, s: s.value() };
+    }
+
+    unreachable!("Ran out of nonces while signing")
+}
+
+/**
+ * Check that `signature` is a valid signature on `message_hash` by the owner of the given public
+ * key.
+ */
+pub fn verify(message_hash: &[u8; DIGEST_BYTES], signature: &Signature, key: &PublicKey) -> bool {
+    let key = key.key();
+    let (generator, order) = subgroup(key);
+    let Signature { e, s } = *signature;
+    if e >= order || s >= order {
+        return false;
+    }
+
+    // `y^-e == y^(q - e)`, since `y` has order `q`
+    let value = ModInt::new(key.value, key.prime);
+    let y = value * value;
+    let commitment = generator.pow(s) * y.pow(order - e);
+    challenge(commitment, y, message_hash, order) == e
+}
@@ -0,0 +1,79 @@
+use super::*;
+use crate::{hash::sha256, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_sign_verify() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    for i in 0..20 {
+        let message_hash = sha256(format!("message {}", i).as_bytes());
+        let signature = sign(&message_hash, &keys.private);
+        assert!(verify(&message_hash, &signature, &keys.public));
+
+        // the signature doesn't hold for other messages or keys
+        let other_hash = sha256(format!("message {}", i + 1).as_bytes());
+        assert!(!verify(&other_hash, &signature, &keys.public));
+        assert!(!verify(&message_hash, &signature, &other_keys.public));
+
+        // or when either half is changed
+        let bad_e = Signature {
+            e: signature.e() ^ 1,
+            ..signature
+        };
+        let bad_s = Signature {
+            s: signature.s() ^ 1,
+            ..signature
+        };
+        assert!(!verify(&message_hash, &bad_e, &keys.public));
+        assert!(!verify(&message_hash, &bad_s, &keys.public));
+    }
+}
+
+#[test]
+fn test_deterministic_nonces() {
+    let keys = KeyPair::generate(&mut StdRng::seed_from_u64(0)).unwrap();
+    let first_hash = sha256(b"first");
+    let second_hash = sha256(b"second");
+
+    // signing is deterministic, and different messages get different challenges
+    let first = sign(&first_hash, &keys.private);
+    assert_eq!(sign(&first_hash, &keys.private), first);
+    assert_ne!(sign(&second_hash, &keys.private).e(), first.e());
+}
+
+#[test]
+fn test_signature_ranges() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let message_hash = sha256(b"out of range");
+    let signature = sign(&message_hash, &keys.private);
+
+    // adding the subgroup order to either half gives an equivalent but non-canonical signature
+    let order = (keys.public.key().prime() - 1) / 2;
+    let shifted_e = Signature {
+        e: signature.e() + order,
+        ..signature
+    };
+    let shifted_s = Signature {
+        s: signature.s() + order,
+        ..signature
+    };
+    assert!(!verify(&message_hash, &shifted_e, &keys.public));
+    assert!(!verify(&message_hash, &shifted_s, &keys.public));
+}
+
+#[test]
+fn test_signature_serialization() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let message_hash = sha256(b"serialized");
+
+    let signature = sign(&message_hash, &keys.private);
+    let deserialized = Signature::deserialize(&signature.serialize());
+    assert_eq!(deserialized, signature);
+    assert!(verify(&message_hash, &deserialized, &keys.public));
+}
@@ -0,0 +1,248 @@
+/*!
+ * A self-test of the arithmetic and encryption this crate relies on, for packagers and for
+ * diagnosing platform-specific problems.
+ *
+ * `run` checks `mod_exp` and the Miller-Rabin test against known answers, checks that each
+ * `Padding` scheme can be removed once it's added, and encrypts and decrypts edge-case plaintexts
+ * in every `Mode` with a newly generated key pair. Each part is reported as an `audit::Check`.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    audit::Check,
+    header::Header,
+    stream::{DecryptReader, EncryptWriter},
+    CryptError, Encoding, KeyPair, Mode, Num, Padding, BLOCK_BYTES,
+};
+use math::{mod_exp, mod_exp_ct, primes};
+use rand::Rng;
+use std::io::{Read, Write};
+
+/**
+ * `(base, exponent, modulus, base^exponent mod modulus)` for checking `mod_exp`. The last few need
+ * the full width of a `Num` for their intermediate products.
+ */
+const MOD_EXP_VECTORS: &[(Num, Num, Num, Num)] = &[
+    (4, 13, 497, 445),
+    (2, 10, 1000, 24),
+    (3, 0, 7, 1),
+    (0, 5, 13, 0),
+    (5, 117, 19, 1),
+    (2, 61, (1 << 61) - 1, 1),
+    (3, (1 << 61) - 2, (1 << 61) - 1, 1),
+    (Num::MAX - 1, 2, Num::MAX, 1),
+];
+
+/**
+ * Numbers that the Miller-Rabin test must report as prime, up to the largest 64-bit prime.
+ */
+const PRIMES: &[Num] = &[
+    2,
+    3,
+    5,
+    7919,
+    2147483647,
+    2305843009213693951,
+    18446744073709550147,
+    18446744073709551557,
+];
+
+/**
+ * Numbers that the Miller-Rabin test must report as composite, including Carmichael numbers and
+ * strong pseudoprimes to many small bases.
+ */
+const COMPOSITES: &[Num] = &[
+    0,
+    1,
+    4,
+    9,
+    561,
+    41041,
+    3215031751,
+    4294967297,
+    3825123056546413051,
+    18446744073709551615,
+];
+
+/**
+ * The plaintext lengths encrypted in each mode: empty, a single byte, and either side of one and
+ * two blocks.
+ */
+const PLAINTEXT_LENGTHS: &[usize] = &[
+    0,
+    1,
+    BLOCK_BYTES - 1,
+    BLOCK_BYTES,
+    BLOCK_BYTES + 1,
+    BLOCK_BYTES * 2,
+    1000,
+];
+
+/**
+ * Run every check, using `rng` for the key pair, plaintexts, and primality tests.
+ */
+pub fn run<T: Rng>(rng: &mut T) -> Vec<Check> {
+    let mut checks = vec![
+        Check::new("mod_exp matches known answers", check_mod_exp()),
+        Check::new("primality test matches known answers", check_primes(rng)),
+        Check::new("padding can be removed once it's added", check_padding(rng)),
+    ];
+
+    let keys = match KeyPair::generate(rng) {
+        Ok(keys) => keys,
+        Err(_) => {
+            checks.push(Check::new("an ephemeral key pair can be generated", false));
+            return checks;
+        }
+    };
+    checks.push(Check::new(
+        "an ephemeral key pair can be generated",
+        keys.validate(rng).is_ok(),
+    ));
+
+    for &(mode, name) in &[
+        (Mode::Ecb, "plaintexts round trip in ECB mode"),
+        (Mode::Cbc, "plaintexts round trip in CBC mode"),
+        (Mode::Ctr, "plaintexts round trip in CTR mode"),
+        (Mode::Hybrid, "plaintexts round trip in hybrid mode"),
+    ] {
+        checks.push(Check::new(name, check_round_trips(&keys, mode, rng)));
+    }
+
+    checks.push(Check::new(
+        "tampered ciphertexts are rejected",
+        check_tampering(&keys, rng),
+    ));
+    checks
+}
+
+/**
+ * Check `mod_exp` and `mod_exp_ct` against `MOD_EXP_VECTORS`.
+ */
+fn check_mod_exp() -> bool {
+    MOD_EXP_VECTORS
+        .iter()
+        .all(|&(base, exponent, modulus, expected)| {
+            mod_exp(base, exponent, modulus) == expected
+                && mod_exp_ct(base, exponent, modulus) == expected
+        })
+}
+
+/**
+ * Check the Miller-Rabin test against `PRIMES` and `COMPOSITES`.
+ */
+fn check_primes<T: Rng>(rng: &mut T) -> bool {
+    const WITNESS_COUNT: usize = primes::DEFAULT_WITNESS_COUNT;
+
+    PRIMES
+        .iter()
+        .all(|&n| primes::is_prime(n, WITNESS_COUNT, rng))
+        && COMPOSITES
+            .iter()
+            .all(|&n| !primes::is_prime(n, WITNESS_COUNT, rng))
+}
+
+/**
+ * Check that every amount of each `Padding` scheme is removed in full from a block of nonzero
+ * bytes, and that malformed padding is rejected.
+ */
+fn check_padding<T: Rng>(rng: &mut T) -> bool {
+    for &padding in &[Padding::Random, Padding::Pkcs7, Padding::Zero] {
+        for pad_bytes in 1..=BLOCK_BYTES {
+            let mut block = [0xa5_u8; BLOCK_BYTES];
+            padding.pad(&mut block, pad_bytes as u8, rng);
+            if padding.unpad(&block) != Some(pad_bytes) {
+                return false;
+            }
+        }
+    }
+
+    let mut mismatched = [2_u8; BLOCK_BYTES];
+    mismatched[BLOCK_BYTES - 2] = 1;
+    let too_long = [BLOCK_BYTES as u8 + 1; BLOCK_BYTES];
+    Padding::Pkcs7.unpad(&mismatched).is_none()
+        && Padding::Pkcs7.unpad(&[0_u8; BLOCK_BYTES]).is_none()
+        && Padding::Random.unpad(&too_long).is_none()
+        && Padding::Zero.unpad(&[0xa5_u8; BLOCK_BYTES]).is_none()
+}
+
+/**
+ * Check that each of `PLAINTEXT_LENGTHS` decrypts to itself in `mode`, with and without
+ * authentication and, in ECB and CBC modes, with each `Padding` scheme.
+ */
+fn check_round_trips<T: Rng>(keys: &KeyPair, mode: Mode, rng: &mut T) -> bool {
+    let paddings: &[Padding] = match mode {
+        Mode::Ecb | Mode::Cbc => &[Padding::Random, Padding::Pkcs7, Padding::Zero],
+        Mode::Ctr | Mode::Hybrid => &[Padding::Random],
+    };
+
+    for &padding in paddings {
+        for &authenticated in &[false, true] {
+            for &len in PLAINTEXT_LENGTHS {
+                // zero padding can't tell a trailing zero byte from padding
+                let plaintext: Vec<u8> = (0..len).map(|_| rng.gen_range(1..=u8::MAX)).collect();
+                let mut header = Header::new(Encoding::best_for(keys.public.key().prime()));
+                header.mode = mode;
+                header.padding = padding;
+                header.authenticated = authenticated;
+
+                let ciphertext = match encrypt(keys, header, &plaintext, rng) {
+                    Ok(ciphertext) => ciphertext,
+                    Err(_) => return false,
+                };
+                match decrypt(keys, &ciphertext) {
+                    Ok(decrypted) if decrypted == plaintext => {}
+                    _ => return false,
+                }
+            }
+        }
+    }
+
+    true
+}
+
+/**
+ * Check that changing any byte of an authenticated ciphertext after its header makes it fail to
+ * decrypt.
+ */
+fn check_tampering<T: Rng>(keys: &KeyPair, rng: &mut T) -> bool {
+    let mut header = Header::new(Encoding::best_for(keys.public.key().prime()));
+    header.authenticated = true;
+    let header_len = header.len();
+    let ciphertext = match encrypt(keys, header, &[1_u8; BLOCK_BYTES * 2], rng) {
+        Ok(ciphertext) => ciphertext,
+        Err(_) => return false,
+    };
+
+    (header_len..ciphertext.len()).all(|i| {
+        let mut tampered = ciphertext.clone();
+        tampered[i] ^= 1;
+        decrypt(keys, &tampered).is_err()
+    })
+}
+
+/**
+ * Encrypt `plaintext` for `keys` with `header`.
+ */
+fn encrypt<T: Rng>(
+    keys: &KeyPair,
+    header: Header,
+    plaintext: &[u8],
+    rng: &mut T,
+) -> Result<Vec<u8>, CryptError> {
+    let mut encryptor = EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng)?;
+    encryptor.write_all(plaintext)?;
+    encryptor.finish()
+}
+
+/**
+ * Decrypt `ciphertext` with `keys`.
+ */
+fn decrypt(keys: &KeyPair, ciphertext: &[u8]) -> Result<Vec<u8>, CryptError> {
+    let mut decryptor = DecryptReader::new(ciphertext, keys.private.clone())?;
+    let mut plaintext = Vec::new();
+    decryptor.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
@@ -0,0 +1,11 @@
+use super::*;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_run() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for check in run(&mut rng) {
+        assert!(check.passed, "{}", check.name);
+    }
+}
@@ -0,0 +1,155 @@
+/*!
+ * ElGamal digital signatures, using the same keys as encryption.
+ *
+ * To sign the hash `h` of a message with the private exponent `x`, a nonce `k` coprime to `p - 1`
+ * is chosen, and the signature is the pair `r = root^k mod p` and
+ * `s = (h - x * r) * k^-1 mod (p - 1)`. The signature is valid if
+ * `root^h == value^r * r^s (mod p)`, where `value` is the public key's value.
+ *
+ * Anyone who sees two signatures made with the same nonce can recover the private exponent, so
+ * nonces are never taken from `rng` alone. Each nonce is instead derived by hashing the private
+ * exponent and the message hash together with fresh random bytes, so a nonce only repeats if the
+ * same message is signed with the same random bytes, which yields the same signature anyway.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    hash::{Sha256, DIGEST_BYTES},
+    Num, PrivateKey, PublicKey, NUM_BYTES,
+};
+use math::{gcd, mod_exp, modint::ModInt};
+use rand::Rng;
+
+/**
+ * The number of bytes in a serialized `Signature`.
+ */
+pub const SIGNATURE_BYTES: usize = NUM_BYTES * 2;
+
+/**
+ * An ElGamal signature on a message hash.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Signature {
+    r: Num,
+    s: Num,
+}
+
+impl Signature {
+    /**
+     * Get the signature's commitment to the nonce, `root^k mod p`.
+     */
+    pub fn r(&self) -> Num {
+        self.r
+    }
+
+    /**
+     * Get the second half of the signature, which ties the nonce to the message hash and key.
+     */
+    pub fn s(&self) -> Num {
+        self.s
+    }
+
+    /**
+     * Convert the signature to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; SIGNATURE_BYTES] {
+        let mut result = [0_u8; SIGNATURE_BYTES];
+        result[..NUM_BYTES].copy_from_slice(&self.r.to_be_bytes());
+        result[NUM_BYTES..].copy_from_slice(&self.s.to_be_bytes());
+        result
+    }
+
+    /**
+     * Read a signature from serialized bytes. The values are checked when the signature is
+     * verified.
+     */
+    pub fn deserialize(bytes: &[u8; SIGNATURE_BYTES]) -> Self {
+        let mut r_buf = [0_u8; NUM_BYTES];
+        let mut s_buf = [0_u8; NUM_BYTES];
+        r_buf.copy_from_slice(&bytes[..NUM_BYTES]);
+        s_buf.copy_from_slice(&bytes[NUM_BYTES..]);
+
+        Self {
+            r: Num::from_be_bytes(r_buf),
+            s: Num::from_be_bytes(s_buf),
+        }
+    }
+}
+
+/**
+ * Reduce a message hash to an exponent modulo `modulus`.
+ */
+pub(crate) fn hash_to_exponent(message_hash: &[u8], modulus: Num) -> ModInt {
+    let base = ModInt::new(256, modulus);
+    message_hash
+        .iter()
+        .fold(ModInt::new(0, modulus), |acc, &b| {
+            acc * base + ModInt::new(b as Num, modulus)
+        })
+}
+
+/**
+ * Derive a signing nonce for `message_hash` from the private exponent `x`, the random bytes
+ * `entropy`, and a counter that's incremented until the nonce is usable.
+ */
+fn derive_nonce(x: Num, message_hash: &[u8], entropy: &[u8], counter: u32, order: Num) -> Num {
+    let mut hasher = Sha256::new();
+    hasher.update(b"pubcrypt elgamal nonce");
+    hasher.update(&x.to_be_bytes());
+    hasher.update(message_hash);
+    hasher.update(entropy);
+    hasher.update(&counter.to_be_bytes());
+
+    hash_to_exponent(&hasher.finalize(), order).value()
+}
+
+/**
+ * Sign `message_hash`, the SHA-256 digest of a message, with the given private key. The nonce is
+ * derived from the key, the hash, and random bytes generated by `rng`.
+ */
+pub fn sign<T: Rng>(message_hash: &[u8; DIGEST_BYTES], key: &PrivateKey, rng: &mut T) -> Signature {
+    let key = key.key();
+    let order = key.prime - 1;
+    let h = hash_to_exponent(message_hash, order);
+    let x = ModInt::new(key.value, order);
+
+    let mut entropy = [0_u8; DIGEST_BYTES];
+    rng.fill(&mut entropy);
+
+    for counter in 0.. {
+        let k = derive_nonce(key.value, message_hash, &entropy, counter, order);
+        if k == 0 || gcd(k, order) != 1 {
+            continue;
+        }
+
+        let r = mod_exp(key.root, k, key.prime);
+        let k_inv = ModInt::new(k, order).inverse().unwrap();
+        let s = (h - x * ModInt::new(r, order)) * k_inv;
+
+        // a zero `s` would make the signature independent of the key
+        if s.value() != 0 {
+            return Signature { r, s: s.value() };
+        }
+    }
+
+    unreachable!("Ran out of nonces while signing")
+}
+
+/**
+ * Check that `signature` is a valid signature on `message_hash` by the owner of the given public
+ * key.
+ */
+pub fn verify(message_hash: &[u8; DIGEST_BYTES], signature: &Signature, key: &PublicKey) -> bool {
+    let key = key.key();
+    let Signature { r, s } = *signature;
+    if r == 0 || r >= key.prime || s == 0 || s >= key.prime - 1 {
+        return false;
+    }
+
+    let h = hash_to_exponent(message_hash, key.prime - 1).value();
+    let expected = ModInt::new(key.root, key.prime).pow(h);
+    let actual = ModInt::new(key.value, key.prime).pow(r) * ModInt::new(r, key.prime).pow(s);
+    expected == actual
+}
@@ -0,0 +1,82 @@
+use super::*;
+use crate::{hash::sha256, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_sign_verify() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    for i in 0..20 {
+        let message_hash = sha256(format!("message {}", i).as_bytes());
+        let signature = sign(&message_hash, &keys.private, &mut rng);
+        assert!(verify(&message_hash, &signature, &keys.public));
+
+        // the signature doesn't hold for other messages or keys
+        let other_hash = sha256(format!("message {}", i + 1).as_bytes());
+        assert!(!verify(&other_hash, &signature, &keys.public));
+        assert!(!verify(&message_hash, &signature, &other_keys.public));
+
+        // or when either half is changed
+        let bad_r = Signature {
+            r: signature.r() ^ 1,
+            ..signature
+        };
+        let bad_s = Signature {
+            s: signature.s() ^ 1,
+            ..signature
+        };
+        assert!(!verify(&message_hash, &bad_r, &keys.public));
+        assert!(!verify(&message_hash, &bad_s, &keys.public));
+    }
+}
+
+#[test]
+fn test_signature_ranges() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let prime = keys.public.key().prime();
+    let message_hash = sha256(b"out of range");
+
+    let out_of_range = [(0, 1), (1, 0), (prime, 1), (1, prime - 1)];
+    for &(r, s) in &out_of_range {
+        assert!(!verify(&message_hash, &Signature { r, s }, &keys.public));
+    }
+}
+
+#[test]
+fn test_nonce_reuse() {
+    let keys = KeyPair::generate(&mut StdRng::seed_from_u64(0)).unwrap();
+    let first_hash = sha256(b"first");
+    let second_hash = sha256(b"second");
+
+    // even if the random bytes repeat, different messages get different nonces
+    let first = sign(&first_hash, &keys.private, &mut StdRng::seed_from_u64(1));
+    let second = sign(&second_hash, &keys.private, &mut StdRng::seed_from_u64(1));
+    assert_ne!(first.r(), second.r());
+
+    // and the same message signed twice gets different nonces
+    let mut rng = StdRng::seed_from_u64(2);
+    let again = sign(&first_hash, &keys.private, &mut rng);
+    let once_more = sign(&first_hash, &keys.private, &mut rng);
+    assert_ne!(again.r(), once_more.r());
+    assert!(verify(&first_hash, &again, &keys.public));
+    assert!(verify(&first_hash, &once_more, &keys.public));
+}
+
+#[test]
+fn test_signature_serialization() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let message_hash = sha256(b"serialized");
+
+    let signature = sign(&message_hash, &keys.private, &mut rng);
+    let bytes = signature.serialize();
+    assert_eq!(bytes.len(), SIGNATURE_BYTES);
+
+    let deserialized = Signature::deserialize(&bytes);
+    assert_eq!(deserialized, signature);
+    assert!(verify(&message_hash, &deserialized, &keys.public));
+}
@@ -0,0 +1,159 @@
+/*!
+ * Encrypt-then-MAC framing for authenticated ciphertexts.
+ *
+ * After the header, an authenticated ciphertext holds a key share `g^r mod p`, from which the
+ * encryptor (using the public value) and the decryptor (using the private exponent) derive the
+ * same secret, and from that the MAC key. The rest of the ciphertext follows as usual, and an
+ * HMAC-SHA-256 tag over everything from the header onward is appended at the end.
+ */
+
+use crate::{
+    hash::Sha256,
+    mac::{Hmac, MAC_BYTES},
+    CryptError, Num,
+};
+use std::io::{self, Read, Write};
+
+/**
+ * Derive the MAC key from the shared secret and the key share it was derived from.
+ */
+pub(crate) fn derive_mac_key(shared: Num, share: Num) -> [u8; MAC_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"pubcrypt mac key");
+    hasher.update(&shared.to_be_bytes());
+    hasher.update(&share.to_be_bytes());
+    hasher.finalize()
+}
+
+/**
+ * A writer that passes everything through to an inner writer, computing a MAC over it once
+ * `mac` is set.
+ */
+pub(crate) struct MacWriter<W: Write> {
+    pub(crate) inner: W,
+    pub(crate) mac: Option<Hmac>,
+}
+
+impl<W: Write> MacWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self { inner, mac: None }
+    }
+
+    /**
+     * Append the tag if a MAC is being computed, flush the inner writer, and return it.
+     */
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        if let Some(mac) = self.mac.take() {
+            self.inner.write_all(&mac.finalize())?;
+        }
+
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for MacWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        if let Some(mac) = &mut self.mac {
+            mac.update(&buf[..count]);
+        }
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/**
+ * A reader that passes through everything but the tag at the end of its input when a MAC is
+ * given, and checks the tag once the rest of the input has been read. Without a MAC, the input is
+ * passed through unchanged.
+ */
+pub(crate) struct MacReader<R: Read> {
+    source: R,
+    mac: Option<Hmac>,
+    pending: Vec<u8>,
+}
+
+impl<R: Read> MacReader<R> {
+    /**
+     * Create a reader over `source`, authenticated with `mac` if it's given.
+     */
+    pub(crate) fn new(source: R, mac: Option<Hmac>) -> Self {
+        Self {
+            source,
+            mac,
+            pending: Vec::new(),
+        }
+    }
+
+    /**
+     * Read the rest of the input into memory and check the tag, so that everything read
+     * afterwards is already authenticated.
+     */
+    pub(crate) fn verify(&mut self) -> Result<(), CryptError> {
+        let mut mac = match self.mac.take() {
+            Some(mac) => mac,
+            None => return Ok(()),
+        };
+
+        self.source.read_to_end(&mut self.pending)?;
+        if self.pending.len() < MAC_BYTES {
+            return Err(CryptError::AuthenticationFailed);
+        }
+
+        let body_len = self.pending.len() - MAC_BYTES;
+        mac.update(&self.pending[..body_len]);
+        if !mac.verify(&self.pending[body_len..]) {
+            return Err(CryptError::AuthenticationFailed);
+        }
+
+        self.pending.truncate(body_len);
+        Ok(())
+    }
+
+    /**
+     * Move bytes from the front of the pending buffer into `buf`, returning how many were moved.
+     */
+    fn release(&mut self, buf: &mut [u8], available: usize) -> usize {
+        let count = buf.len().min(available);
+        buf[..count].copy_from_slice(&self.pending[..count]);
+        self.pending.drain(..count);
+        count
+    }
+}
+
+impl<R: Read> Read for MacReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.mac.is_none() {
+            if self.pending.is_empty() {
+                return self.source.read(buf);
+            }
+
+            let available = self.pending.len();
+            return Ok(self.release(buf, available));
+        }
+
+        // the last `MAC_BYTES` bytes read so far might be the tag, so they're held back
+        let mut chunk = [0_u8; 4096];
+        while self.pending.len() <= MAC_BYTES {
+            let count = self.source.read(&mut chunk)?;
+            if count == 0 {
+                self.verify()?;
+                return Ok(0);
+            }
+
+            self.pending.extend_from_slice(&chunk[..count]);
+        }
+
+        let available = self.pending.len() - MAC_BYTES;
+        if let Some(mac) = &mut self.mac {
+            mac.update(&self.pending[..available.min(buf.len())]);
+        }
+
+        Ok(self.release(buf, available))
+    }
+}
@@ -0,0 +1,163 @@
+/*!
+ * Chunked framing for ciphertexts whose header sets `chunked`, so that they can be read from
+ * streams that go on after the ciphertext ends, such as pipes and sockets.
+ *
+ * Everything after the header is split into chunks of at most `CHUNK_BYTES` bytes, each preceded
+ * by its big-endian four-byte length, whose top bit marks the final chunk. A reader stops at the
+ * end of the final chunk without reading any further, so the end of the ciphertext doesn't depend
+ * on the end of the stream.
+ */
+
+use super::CHUNK_BYTES;
+use crate::CryptError;
+use std::{
+    cmp,
+    io::{self, Read, Write},
+};
+
+const FINAL_FLAG: u32 = 1 << 31;
+
+/**
+ * A writer that passes everything through to an inner writer until `start` is called, and frames
+ * it in chunks afterwards.
+ */
+pub(crate) struct ChunkWriter<W: Write> {
+    inner: W,
+    // the contents of the current chunk, or `None` before framing starts
+    chunk: Option<Vec<u8>>,
+}
+
+impl<W: Write> ChunkWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self { inner, chunk: None }
+    }
+
+    /**
+     * Frame everything written from now on in chunks.
+     */
+    pub(crate) fn start(&mut self) {
+        self.chunk = Some(Vec::with_capacity(CHUNK_BYTES));
+    }
+
+    /**
+     * Write the rest of the input as the final chunk if it's framed, and return the inner writer.
+     * The inner writer isn't flushed.
+     */
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        if self.chunk.is_some() {
+            self.write_chunk(true)?;
+        }
+
+        Ok(self.inner)
+    }
+
+    /**
+     * Write the current chunk to the inner writer and start a new one.
+     */
+    fn write_chunk(&mut self, is_final: bool) -> io::Result<()> {
+        let chunk = self.chunk.as_mut().unwrap();
+        let mut len = chunk.len() as u32;
+        if is_final {
+            len |= FINAL_FLAG;
+        }
+
+        self.inner.write_all(&len.to_be_bytes())?;
+        self.inner.write_all(chunk)?;
+        chunk.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ChunkWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let chunk = match &mut self.chunk {
+            Some(chunk) => chunk,
+            None => return self.inner.write(buf),
+        };
+
+        let count = cmp::min(buf.len(), CHUNK_BYTES - chunk.len());
+        chunk.extend_from_slice(&buf[..count]);
+        if chunk.len() == CHUNK_BYTES {
+            self.write_chunk(false)?;
+        }
+
+        Ok(count)
+    }
+
+    /**
+     * Write everything so far as a chunk, so that a reader at the other end of a stream can
+     * decrypt it, then flush the inner writer.
+     */
+    fn flush(&mut self) -> io::Result<()> {
+        if self.chunk.as_ref().is_some_and(|chunk| !chunk.is_empty()) {
+            self.write_chunk(false)?;
+        }
+
+        self.inner.flush()
+    }
+}
+
+/**
+ * A reader that passes through the contents of the chunks read from an inner reader, ending at
+ * the end of the final chunk. Without framing, the input is passed through unchanged.
+ */
+pub(crate) struct ChunkReader<R: Read> {
+    inner: R,
+    chunked: bool,
+    remaining: usize,
+    is_final: bool,
+}
+
+impl<R: Read> ChunkReader<R> {
+    pub(crate) fn new(inner: R, chunked: bool) -> Self {
+        Self {
+            inner,
+            chunked,
+            remaining: 0,
+            is_final: false,
+        }
+    }
+}
+
+impl<R: Read> Read for ChunkReader<R> {
+    /**
+     * Read from the current chunk, moving on to the next one if it's used up. Returns
+     * `CryptError::Truncated` if the input ends before the end of the final chunk, and
+     * `CryptError::InvalidChunk` if a chunk is longer than `CHUNK_BYTES`.
+     */
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.chunked {
+            return self.inner.read(buf);
+        }
+
+        while self.remaining == 0 {
+            if self.is_final || buf.is_empty() {
+                return Ok(0);
+            }
+
+            let mut len_buf = [0_u8; 4];
+            self.inner
+                .read_exact(&mut len_buf)
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::UnexpectedEof => io::Error::from(CryptError::Truncated),
+                    _ => e,
+                })?;
+
+            let len = u32::from_be_bytes(len_buf);
+            self.is_final = len & FINAL_FLAG != 0;
+            self.remaining = (len & !FINAL_FLAG) as usize;
+            if self.remaining > CHUNK_BYTES {
+                return Err(io::Error::from(CryptError::InvalidChunk));
+            }
+        }
+
+        let len = cmp::min(buf.len(), self.remaining);
+        let count = self.inner.read(&mut buf[..len])?;
+        if count == 0 {
+            return Err(io::Error::from(CryptError::Truncated));
+        }
+
+        self.remaining -= count;
+        Ok(count)
+    }
+}
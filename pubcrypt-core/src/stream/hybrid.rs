@@ -0,0 +1,99 @@
+/*!
+ * The symmetric half of `Mode::Hybrid`: wrapping and unwrapping the key, and the keystream it
+ * generates.
+ */
+
+use crate::{
+    encrypt_block_det,
+    hash::{Sha256, DIGEST_BYTES},
+    Blinding, Block, CryptError, DecryptContext, Encoding, Num, PrivateKey, PublicKey,
+};
+use math::{mod_exp, mod_exp_ct};
+use rand::Rng;
+
+/**
+ * A SHA-256 counter mode keystream.
+ */
+pub(crate) struct HybridKeystream {
+    key: [u8; DIGEST_BYTES],
+    counter: u64,
+    block: [u8; DIGEST_BYTES],
+    block_pos: usize,
+}
+
+impl HybridKeystream {
+    /**
+     * Derive the symmetric key from the secret shared through the wrapped key and the nonce it
+     * wraps.
+     */
+    fn new(shared: Num, nonce: Block) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"pubcrypt hybrid key");
+        hasher.update(&shared.to_be_bytes());
+        hasher.update(&nonce.to_be_bytes());
+
+        Self {
+            key: hasher.finalize(),
+            counter: 0,
+            block: [0_u8; DIGEST_BYTES],
+            block_pos: DIGEST_BYTES,
+        }
+    }
+
+    /**
+     * XOR all of `buf` with the keystream.
+     */
+    pub(crate) fn apply(&mut self, buf: &mut [u8]) {
+        for b in buf {
+            if self.block_pos == DIGEST_BYTES {
+                let mut hasher = Sha256::new();
+                hasher.update(&self.key);
+                hasher.update(&self.counter.to_be_bytes());
+                self.block = hasher.finalize();
+                self.block_pos = 0;
+                self.counter += 1;
+            }
+
+            *b ^= self.block[self.block_pos];
+            self.block_pos += 1;
+        }
+    }
+}
+
+/**
+ * Wrap a random nonce with the public key, returning the wrapped pair for the header and the
+ * keystream it keys.
+ */
+pub(crate) fn wrap_key<T: Rng>(
+    key: &PublicKey,
+    encoding: Encoding,
+    rng: &mut T,
+) -> Result<((Num, Num), HybridKeystream), CryptError> {
+    let inner = key.key();
+    if inner.prime <= Block::MAX as Num {
+        return Err(CryptError::MalformedKey);
+    }
+
+    let r = rng.gen_range(1..inner.prime);
+    let nonce: Block = rng.gen();
+    let wrapped = encrypt_block_det(nonce, key, encoding, r)?;
+    let shared = mod_exp(inner.value, r, inner.prime);
+    Ok((wrapped, HybridKeystream::new(shared, nonce)))
+}
+
+/**
+ * Recover the keystream from a pair written by `wrap_key`.
+ */
+pub(crate) fn unwrap_key(
+    wrapped: (Num, Num),
+    key: &PrivateKey,
+    context: &DecryptContext,
+    encoding: Encoding,
+    blinding: &mut Blinding,
+) -> Result<HybridKeystream, CryptError> {
+    let (c1, c2) = wrapped;
+    let nonce = context.decrypt_block_blinded(c1, c2, encoding, blinding)?;
+    let inner = key.key();
+    let shared = mod_exp_ct(c1, inner.value, inner.prime);
+    Ok(HybridKeystream::new(shared, nonce))
+}
@@ -0,0 +1,1049 @@
+/*!
+ * Streaming encryption and decryption, for use with sockets, pipes, in-memory buffers, and any
+ * other `Read` or `Write` implementor.
+ *
+ * `EncryptWriter` and `DecryptReader` produce and consume the same ciphertext format as the `ecb`
+ * module, handling the header, block splitting, padding, and chaining (see `Mode`) internally.
+ * Ciphertexts whose header is marked as authenticated also carry a MAC, which is computed and
+ * checked along the way, and those marked as chunked are framed so that `DecryptReader` stops at
+ * the end of the ciphertext rather than the end of its input.
+ */
+
+mod auth;
+mod chunked;
+mod hybrid;
+#[cfg(test)]
+mod tests;
+
+pub(crate) use self::chunked::ChunkReader;
+
+use self::{
+    auth::{derive_mac_key, MacReader, MacWriter},
+    chunked::ChunkWriter,
+    hybrid::HybridKeystream,
+};
+use crate::{
+    cipher::PublicKeyCipher,
+    deterministic::{DeterministicRng, ExponentSource},
+    ec::EcElGamal,
+    encrypt_block, encrypt_block_det,
+    hash::Sha256,
+    header::{self, Header},
+    mac::{hmac, Hmac, MAC_BYTES},
+    oaep,
+    peek::PeekReader,
+    rsa::Rsa,
+    Algorithm, Blinding, Block, CryptError, DecryptContext, Encoding, EncryptContext, Mode, Num,
+    Padding, PrivateKey, PublicKey, BLOCK_BYTES, NUM_BYTES,
+};
+use math::{mod_exp, mod_exp_ct};
+use rand::Rng;
+use std::{
+    cmp,
+    io::{self, BufRead, BufReader, Cursor, Read, Write},
+};
+
+/**
+ * The number of plaintext bytes in each segment of a `Mode::Ctr` ciphertext. Each segment is
+ * preceded by its encrypted nonce.
+ */
+pub const CTR_SEGMENT_BYTES: usize = 1 << 16;
+
+/**
+ * The maximum number of ciphertext bytes in each chunk of a chunked ciphertext (see
+ * `Header::chunked`).
+ */
+pub const CHUNK_BYTES: usize = 1 << 16;
+
+/**
+ * The number of blocks and plaintext bytes in a ciphertext, estimated from its length by
+ * `estimate_size`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeEstimate {
+    /**
+     * The number of encrypted blocks, including the encrypted nonces of `Mode::Ctr` segments.
+     * Hybrid mode ciphertexts have none.
+     */
+    pub blocks: u64,
+    /**
+     * The smallest possible length of the plaintext.
+     */
+    pub min_plaintext_bytes: u64,
+    /**
+     * The largest possible length of the plaintext.
+     */
+    pub max_plaintext_bytes: u64,
+}
+
+/**
+ * Estimate the size of a ciphertext with `header`, as written by `EncryptWriter` or
+ * `cipher::encrypt`, by reading everything after the header from `body`. Nothing is decrypted, so
+ * no key is needed. For files and archives (see `Header::contains_file` and `Header::archive`),
+ * the plaintext is their container.
+ *
+ * The padding of the final block is what makes the plaintext length uncertain, so it's only exact
+ * in CTR and hybrid modes. Returns `CryptError::Truncated` or `CryptError::InvalidChunk` if a
+ * chunked ciphertext's framing is cut short or malformed.
+ */
+pub fn estimate_size<R: Read>(header: &Header, body: R) -> Result<SizeEstimate, CryptError> {
+    // chunked ciphertexts end at their final chunk, and the lengths before each chunk aren't
+    // part of the payload
+    let mut payload = io::copy(&mut ChunkReader::new(body, header.chunked), &mut io::sink())?;
+    if header.authenticated {
+        payload = payload.saturating_sub((NUM_BYTES + MAC_BYTES) as u64);
+    }
+
+    let block_width = ciphertext_block_width(header.algorithm) as u64;
+
+    let estimate = match header.mode {
+        Mode::Hybrid => SizeEstimate {
+            blocks: 0,
+            min_plaintext_bytes: payload,
+            max_plaintext_bytes: payload,
+        },
+        Mode::Ctr => {
+            let segment_width = block_width + CTR_SEGMENT_BYTES as u64;
+            let blocks = payload.div_ceil(segment_width);
+            let plaintext_bytes = payload.saturating_sub(blocks * block_width);
+            SizeEstimate {
+                blocks,
+                min_plaintext_bytes: plaintext_bytes,
+                max_plaintext_bytes: plaintext_bytes,
+            }
+        }
+        // the final block holds at least one byte of padding
+        Mode::Ecb | Mode::Cbc => {
+            let blocks = payload / block_width;
+            let block_bytes = header.block_bytes as u64;
+            SizeEstimate {
+                blocks,
+                min_plaintext_bytes: blocks.saturating_sub(1) * block_bytes,
+                max_plaintext_bytes: (blocks * block_bytes).saturating_sub(1),
+            }
+        }
+    };
+    Ok(estimate)
+}
+
+/**
+ * Count the encrypted blocks of a ciphertext with `header` for a plaintext of `plaintext_bytes`
+ * bytes, as in `SizeEstimate::blocks`. For files and archives, the plaintext is their container.
+ */
+pub fn count_blocks(header: &Header, plaintext_bytes: u64) -> u64 {
+    match header.mode {
+        Mode::Hybrid => 0,
+        Mode::Ctr => plaintext_bytes.div_ceil(CTR_SEGMENT_BYTES as u64),
+        // the final block holds at least one byte of padding
+        Mode::Ecb | Mode::Cbc => plaintext_bytes / header.block_bytes as u64 + 1,
+    }
+}
+
+/**
+ * Get the number of bytes each encrypted block takes up in a ciphertext for `algorithm`.
+ */
+pub(crate) fn ciphertext_block_width(algorithm: Algorithm) -> usize {
+    match algorithm {
+        Algorithm::ElGamal => NUM_BYTES * 2,
+        Algorithm::Rsa => Rsa::CIPHERTEXT_BYTES,
+        Algorithm::EcElGamal => EcElGamal::CIPHERTEXT_BYTES,
+    }
+}
+
+/**
+ * The keystream for one segment of a `Mode::Ctr` ciphertext.
+ */
+struct Keystream {
+    key: [u8; MAC_BYTES],
+    counter: u64,
+    block: [u8; BLOCK_BYTES],
+    block_bytes: usize,
+    block_pos: usize,
+    remaining: usize,
+}
+
+impl Keystream {
+    /**
+     * Derive the segment's key from the secret shared through the nonce's encryption and the
+     * nonce itself.
+     */
+    fn new(shared: Num, nonce: Block, block_bytes: usize) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"pubcrypt ctr key");
+        hasher.update(&shared.to_be_bytes());
+        hasher.update(&nonce.to_be_bytes());
+
+        Self {
+            key: hasher.finalize(),
+            counter: 0,
+            block: [0_u8; BLOCK_BYTES],
+            block_bytes,
+            block_pos: block_bytes,
+            remaining: CTR_SEGMENT_BYTES,
+        }
+    }
+
+    /**
+     * XOR as much of `buf` as fits in the rest of the segment with the keystream, returning the
+     * number of bytes processed.
+     */
+    fn apply(&mut self, buf: &mut [u8]) -> usize {
+        let count = cmp::min(buf.len(), self.remaining);
+
+        for b in &mut buf[..count] {
+            if self.block_pos == self.block_bytes {
+                self.next_block();
+            }
+
+            *b ^= self.block[self.block_pos];
+            self.block_pos += 1;
+        }
+
+        self.remaining -= count;
+        count
+    }
+
+    /**
+     * Derive the next block of the keystream from the segment's key and the block's index.
+     */
+    fn next_block(&mut self) {
+        let value = hmac(&self.key, &self.counter.to_be_bytes());
+        self.block[..self.block_bytes].copy_from_slice(&value[..self.block_bytes]);
+        self.block_pos = 0;
+        self.counter += 1;
+    }
+}
+
+/**
+ * The ciphertext after the header: whatever was buffered while reading the header, followed by
+ * the rest of the input.
+ */
+type Source<R> = io::Chain<Cursor<Vec<u8>>, R>;
+
+/**
+ * A writer that encrypts everything written to it, writing the ciphertext to an inner writer.
+ *
+ * Plaintext is buffered until a whole block is available, so the final partial block is only
+ * written once `finish` is called. Dropping the writer without calling `finish` leaves the
+ * ciphertext without its final block (and its MAC, if it's authenticated), and it won't decrypt.
+ */
+pub struct EncryptWriter<W: Write, T: Rng> {
+    writer: MacWriter<ChunkWriter<W>>,
+    key: PublicKey,
+    context: Option<EncryptContext>,
+    rng: T,
+    exponents: Option<ExponentSource>,
+    header: Header,
+    header_written: bool,
+    buf: [u8; BLOCK_BYTES],
+    buf_len: usize,
+    chain: [u8; BLOCK_BYTES],
+    keystream: Option<Keystream>,
+    hybrid: Option<HybridKeystream>,
+    block_index: u64,
+    last_byte: Option<u8>,
+}
+
+impl<W: Write, T: Rng> EncryptWriter<W, T> {
+    /**
+     * Create a writer that encrypts to `writer` with the public key `key`, in ECB mode with blocks
+     * of `BLOCK_BYTES` bytes. The random exponent for each block and the padding bytes are
+     * generated by `rng`.
+     */
+    pub fn new(writer: W, key: PublicKey, rng: T) -> Self {
+        let header = Header::new(Encoding::best_for(key.key().prime));
+
+        Self {
+            writer: MacWriter::new(ChunkWriter::new(writer)),
+            // keys that can't be prepared are reported by `encrypt_block` once a block is written
+            context: EncryptContext::new(&key).ok(),
+            key,
+            rng,
+            exponents: None,
+            header,
+            header_written: false,
+            buf: [0_u8; BLOCK_BYTES],
+            buf_len: 0,
+            chain: [0_u8; BLOCK_BYTES],
+            keystream: None,
+            hybrid: None,
+            block_index: 0,
+            last_byte: None,
+        }
+    }
+
+    /**
+     * Create a writer as in `new`, but with blocks of `block_bytes` bytes.
+     *
+     * Returns `CryptError::UnsupportedBlockSize` if `block_bytes` is 0 or greater than
+     * `BLOCK_BYTES`.
+     */
+    pub fn with_block_size(
+        writer: W,
+        key: PublicKey,
+        block_bytes: usize,
+        rng: T,
+    ) -> Result<Self, CryptError> {
+        let mut header = Header::new(Encoding::best_for(key.key().prime));
+        header.block_bytes = block_bytes;
+        Self::with_header(writer, key, header, rng)
+    }
+
+    /**
+     * Create a writer as in `new`, but with the encoding, block size, and mode given by `header`,
+     * which is written at the start of the ciphertext.
+     *
+     * Returns `CryptError::UnsupportedBlockSize` if the header's block size is 0, or if it's
+     * greater than `BLOCK_BYTES` once the sequence number, checksum, and OAEP transform are
+     * included. Returns `CryptError::InvalidHeader` if sequence numbers, checksums, padding other
+     * than `Padding::Random`, or the OAEP transform are requested in CTR or hybrid mode, which have
+     * no blocks to number, check, pad, or transform. In hybrid mode, the header's wrapped key is
+     * generated when it's written.
+     */
+    pub fn with_header(
+        writer: W,
+        key: PublicKey,
+        header: Header,
+        rng: T,
+    ) -> Result<Self, CryptError> {
+        let width = header.block_width();
+        if header.block_bytes == 0 || width > BLOCK_BYTES {
+            return Err(CryptError::UnsupportedBlockSize(width));
+        }
+
+        let is_padded = header.sequence_bytes > 0
+            || header.checksum_bytes > 0
+            || header.padding != Padding::Random
+            || header.oaep;
+        if matches!(header.mode, Mode::Ctr | Mode::Hybrid) && is_padded {
+            return Err(CryptError::InvalidHeader);
+        }
+
+        let mut result = Self::new(writer, key, rng);
+        result.header = header;
+        Ok(result)
+    }
+
+    /**
+     * Get the header written at the start of the ciphertext.
+     */
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /**
+     * Encrypt as much of `buf` as possible, returning the number of bytes consumed.
+     */
+    fn encrypt(&mut self, buf: &[u8]) -> Result<usize, CryptError> {
+        self.write_header()?;
+        match self.header.mode {
+            Mode::Ctr => return self.encrypt_ctr(buf),
+            Mode::Hybrid => return self.encrypt_hybrid(buf),
+            _ => (),
+        }
+
+        if let Some(&last) = buf.last() {
+            self.last_byte = Some(last);
+        }
+
+        let block_bytes = self.header.block_bytes;
+        let mut consumed = 0;
+
+        while consumed < buf.len() {
+            let count = cmp::min(block_bytes - self.buf_len, buf.len() - consumed);
+            self.buf[self.buf_len..self.buf_len + count]
+                .copy_from_slice(&buf[consumed..consumed + count]);
+            self.buf_len += count;
+            consumed += count;
+
+            // a full block can be written immediately, since the final block is always padded
+            if self.buf_len == block_bytes {
+                self.write_block(false)?;
+                self.buf_len = 0;
+            }
+        }
+
+        Ok(consumed)
+    }
+
+    /**
+     * Encrypt all of `buf` in CTR mode, starting new segments as needed.
+     */
+    fn encrypt_ctr(&mut self, buf: &[u8]) -> Result<usize, CryptError> {
+        let mut chunk = [0_u8; 512];
+        let mut consumed = 0;
+
+        while consumed < buf.len() {
+            let keystream = match self.keystream.as_mut() {
+                Some(keystream) if keystream.remaining > 0 => keystream,
+                _ => self.start_segment()?,
+            };
+
+            let len = cmp::min(chunk.len(), buf.len() - consumed);
+            chunk[..len].copy_from_slice(&buf[consumed..consumed + len]);
+            let count = keystream.apply(&mut chunk[..len]);
+
+            self.writer.write_all(&chunk[..count])?;
+            consumed += count;
+        }
+
+        Ok(consumed)
+    }
+
+    /**
+     * Encrypt all of `buf` with the hybrid mode keystream.
+     */
+    fn encrypt_hybrid(&mut self, buf: &[u8]) -> Result<usize, CryptError> {
+        let keystream = self.hybrid.as_mut().unwrap();
+        let mut chunk = [0_u8; 512];
+
+        for piece in buf.chunks(chunk.len()) {
+            let chunk = &mut chunk[..piece.len()];
+            chunk.copy_from_slice(piece);
+            keystream.apply(chunk);
+            self.writer.write_all(chunk)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    /**
+     * Start a new CTR mode segment by writing an encrypted random nonce, and return the keystream
+     * for the segment.
+     */
+    fn start_segment(&mut self) -> Result<&mut Keystream, CryptError> {
+        let key = self.key.key();
+        if key.prime <= Block::MAX as Num {
+            return Err(CryptError::MalformedKey);
+        }
+
+        let r = self.rng.gen_range(1..key.prime);
+        let nonce: Block = self.rng.gen();
+        let (c1, c2) = encrypt_block_det(nonce, &self.key, self.header.encoding, r)?;
+        let shared = mod_exp(key.value, r, key.prime);
+
+        self.writer.write_all(&c1.to_be_bytes())?;
+        self.writer.write_all(&c2.to_be_bytes())?;
+
+        let keystream = Keystream::new(shared, nonce, self.header.block_bytes);
+        Ok(self.keystream.insert(keystream))
+    }
+
+    /**
+     * Write the header if it hasn't been written yet, followed by the MAC key share if the
+     * ciphertext is authenticated. In hybrid mode, the symmetric key is wrapped first so the
+     * header can record it. Everything after the header is framed in chunks if the header is
+     * chunked.
+     */
+    fn write_header(&mut self) -> Result<(), CryptError> {
+        if self.header_written {
+            return Ok(());
+        }
+
+        if self.header.mode == Mode::Hybrid {
+            let (wrapped, keystream) =
+                hybrid::wrap_key(&self.key, self.header.encoding, &mut self.rng)?;
+            self.header.wrapped_key = Some(wrapped);
+            self.hybrid = Some(keystream);
+        }
+
+        log_header("encrypting", &self.header);
+        let mut header_bytes = Vec::new();
+        self.header.write(&mut header_bytes)?;
+        self.writer.write_all(&header_bytes)?;
+        self.header_written = true;
+        if self.header.chunked {
+            self.writer.inner.start();
+        }
+
+        if self.header.authenticated {
+            let key = self.key.key();
+            let r = self.rng.gen_range(1..key.prime);
+            let share = mod_exp(key.root, r, key.prime);
+            let shared = mod_exp(key.value, r, key.prime);
+            self.writer.write_all(&share.to_be_bytes())?;
+
+            let mut mac = Hmac::new(&derive_mac_key(shared, share));
+            mac.update(&header_bytes);
+            mac.update(&share.to_be_bytes());
+            self.writer.mac = Some(mac);
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Encrypt the full block in the buffer to the inner writer. Blocks narrower than `BLOCK_BYTES`
+     * are stored in the low bytes of a `Block`, below the sequence number and checksum if there
+     * are any.
+     */
+    fn write_block(&mut self, is_final: bool) -> Result<(), CryptError> {
+        let block_bytes = self.header.block_bytes;
+        if self.header.mode == Mode::Cbc {
+            xor_in_place(&mut self.buf[..block_bytes], &self.chain);
+        }
+
+        let mut block_buf = [0_u8; BLOCK_BYTES];
+        let sequence_start = BLOCK_BYTES - block_bytes - self.header.sequence_bytes;
+        encode_sequence(
+            &mut block_buf[sequence_start..BLOCK_BYTES - block_bytes],
+            self.block_index,
+            is_final,
+        );
+        block_buf[BLOCK_BYTES - block_bytes..].copy_from_slice(&self.buf[..block_bytes]);
+        let (checksum, contents) = block_buf.split_at_mut(sequence_start);
+        let checksum_start = sequence_start - self.header.checksum_bytes;
+        block_checksum(&mut checksum[checksum_start..], self.block_index, contents);
+        let index = self.block_index;
+        self.block_index += 1;
+
+        if self.header.oaep {
+            oaep::encode(&mut block_buf, &mut self.rng);
+        }
+
+        let block = Block::from_be_bytes(block_buf);
+        let encoding = self.header.encoding;
+        let prime = self.key.key().prime;
+        let r = self.exponents.as_ref().map(|e| e.exponent(index, prime));
+        let (c1, c2) = match (&self.context, r) {
+            (Some(context), Some(r)) => context.encrypt_block_det(block, encoding, r)?,
+            (Some(context), None) => context.encrypt_block(block, encoding, &mut self.rng)?,
+            (None, Some(r)) => encrypt_block_det(block, &self.key, encoding, r)?,
+            (None, None) => encrypt_block(block, &self.key, encoding, &mut self.rng)?,
+        };
+        self.chain = chain_value(c1, c2);
+        log::trace!("encrypted block {}", index);
+
+        self.writer.write_all(&c1.to_be_bytes())?;
+        self.writer.write_all(&c2.to_be_bytes())?;
+        Ok(())
+    }
+
+    /**
+     * Pad and encrypt the final block (except in CTR and hybrid modes, which have no padding),
+     * append the MAC
+     * if the ciphertext is authenticated, flush the inner writer, and return it.
+     *
+     * Returns `CryptError::InvalidPadding` if the header's padding is `Padding::Zero` and the
+     * plaintext ends with a zero byte, which couldn't be told apart from the padding.
+     */
+    pub fn finish(mut self) -> Result<W, CryptError> {
+        self.write_header()?;
+        if matches!(self.header.mode, Mode::Ctr | Mode::Hybrid) {
+            return Ok(self.writer.finish()?.finish()?);
+        }
+
+        // if the plaintext ended on a block boundary, this is a block consisting solely of padding
+        let padding = self.header.padding;
+        if padding == Padding::Zero && self.last_byte == Some(0) {
+            return Err(CryptError::InvalidPadding(padding));
+        }
+
+        let block_bytes = self.header.block_bytes;
+        let pad_bytes = (block_bytes - self.buf_len) as u8;
+        padding.pad(&mut self.buf[..block_bytes], pad_bytes, &mut self.rng);
+        self.write_block(true)?;
+
+        Ok(self.writer.finish()?.finish()?)
+    }
+}
+
+impl<W: Write> EncryptWriter<W, DeterministicRng> {
+    /**
+     * Create a writer as in `with_header`, but deterministic: the exponent for each block is
+     * derived from `nonce` and the block's index, and everything else that would be random comes
+     * from a `DeterministicRng` seeded with `nonce`. Encrypting the same plaintext with the same
+     * key, header, and nonce always produces the same ciphertext.
+     *
+     * **WARNING**: this gives up ElGamal's semantic security. Identical plaintexts are
+     * recognizable, and reusing a nonce for different plaintexts leaks the ratio of blocks at
+     * the same position. Only use it for test fixtures, deduplication, and similar cases, with a
+     * nonce that's unique to each plaintext. See the `deterministic` module.
+     *
+     * Returns the same errors as `with_header`, and `CryptError::MalformedKey` if the key can't be
+     * used for encryption.
+     */
+    pub fn deterministic(
+        writer: W,
+        key: PublicKey,
+        header: Header,
+        nonce: &[u8],
+    ) -> Result<Self, CryptError> {
+        let context = EncryptContext::new(&key)?;
+        let mut result = Self::with_header(writer, key, header, DeterministicRng::new(nonce))?;
+        result.context = Some(context);
+        result.exponents = Some(ExponentSource::new(nonce));
+        Ok(result)
+    }
+}
+
+impl<W: Write, T: Rng> Write for EncryptWriter<W, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(self.encrypt(buf)?)
+    }
+
+    /**
+     * Flush the inner writer, after writing the ciphertext so far as a chunk if the ciphertext is
+     * chunked. Any buffered partial block isn't written until `finish` is called.
+     */
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/**
+ * Log the options in `header` for a ciphertext that's being encrypted or decrypted, as `action`
+ * says.
+ */
+fn log_header(action: &str, header: &Header) {
+    log::info!(
+        "{} in {} mode with {}-byte blocks and {} padding",
+        action,
+        header.mode,
+        header.block_bytes,
+        header.padding
+    );
+    log::debug!(
+        "sequence bytes: {}, checksum bytes: {}, OAEP: {}, authenticated: {}, chunked: {}",
+        header.sequence_bytes,
+        header.checksum_bytes,
+        header.oaep,
+        header.authenticated,
+        header.chunked
+    );
+}
+
+/**
+ * Derive the value that the next plaintext block is XORed with in CBC mode from the previous
+ * ciphertext pair.
+ */
+fn chain_value(c1: Num, c2: Num) -> [u8; BLOCK_BYTES] {
+    let mut result = [0_u8; BLOCK_BYTES];
+    result.copy_from_slice(&(c1 ^ c2).to_be_bytes()[NUM_BYTES - BLOCK_BYTES..]);
+    result
+}
+
+/**
+ * Write the sequence number of block `index` to `buf`, which is empty if blocks aren't numbered.
+ * Only the low bits of the index fit, and the top bit is set for the final block.
+ */
+fn encode_sequence(buf: &mut [u8], index: u64, is_final: bool) {
+    if buf.is_empty() {
+        return;
+    }
+
+    let flag_bit = buf.len() * 8 - 1;
+    let mut value = index & ((1 << flag_bit) - 1);
+    if is_final {
+        value |= 1 << flag_bit;
+    }
+
+    buf.copy_from_slice(&value.to_be_bytes()[8 - buf.len()..]);
+}
+
+/**
+ * Read a sequence number written by `encode_sequence`, returning the low bits of the block's
+ * index and whether it's the final block.
+ */
+fn decode_sequence(buf: &[u8]) -> (u64, bool) {
+    let flag_bit = buf.len() * 8 - 1;
+    let mut value_buf = [0_u8; 8];
+    value_buf[8 - buf.len()..].copy_from_slice(buf);
+
+    let value = u64::from_be_bytes(value_buf);
+    (value & ((1 << flag_bit) - 1), value >> flag_bit == 1)
+}
+
+/**
+ * Fill `buf` with the checksum of block `index`, whose sequence number and plaintext are
+ * `contents`. The checksum is the start of their SHA-256 digest, so any change to the block is
+ * caught unless it happens to leave the digest's first `buf.len()` bytes the same. Nothing is done
+ * if `buf` is empty, i.e., blocks aren't checksummed.
+ */
+fn block_checksum(buf: &mut [u8], index: u64, contents: &[u8]) {
+    if buf.is_empty() {
+        return;
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(&index.to_be_bytes());
+    hasher.update(contents);
+    let digest = hasher.finalize();
+    buf.copy_from_slice(&digest[..buf.len()]);
+}
+
+/**
+ * XOR each byte of `buf` with the corresponding byte of `mask`.
+ */
+fn xor_in_place(buf: &mut [u8], mask: &[u8]) {
+    for (b, m) in buf.iter_mut().zip(mask) {
+        *b ^= m;
+    }
+}
+
+/**
+ * Fill unused space in the buffer with random bytes, and place the number of
+ * pad bytes at the end of the buffer.
+ *
+ * Panics if `pad_bytes > buf.len()`.
+ */
+pub(crate) fn pad_slice<T: Rng>(buf: &mut [u8], pad_bytes: u8, rng: &mut T) {
+    assert!(pad_bytes as usize <= buf.len());
+
+    let pad_start = buf.len() - pad_bytes as usize;
+    let pad_end = buf.len() - 1;
+    rng.fill(&mut buf[pad_start..pad_end]);
+    buf[pad_end] = pad_bytes;
+}
+
+/**
+ * A reader that decrypts ciphertext read from an inner reader.
+ *
+ * Errors in individual blocks are reported as `CryptError::Block`, along with the byte offset of
+ * the offending block in the ciphertext, and are returned from `read` wrapped in an `io::Error`.
+ * Converting the `io::Error` back with `CryptError::from` recovers the original error.
+ *
+ * Since the ciphertext may come from anyone, blocks are decrypted as in `decrypt_block_blinded`,
+ * using a random `Blinding` chosen when the reader is created.
+ */
+pub struct DecryptReader<R: Read> {
+    reader: BufReader<MacReader<ChunkReader<Source<R>>>>,
+    key: PrivateKey,
+    context: DecryptContext,
+    blinding: Blinding,
+    header: Header,
+    offset: u64,
+    block: [u8; BLOCK_BYTES],
+    block_start: usize,
+    block_end: usize,
+    finished: bool,
+    chain: [u8; BLOCK_BYTES],
+    keystream: Option<Keystream>,
+    hybrid: Option<HybridKeystream>,
+    block_index: u64,
+}
+
+impl<R: Read> DecryptReader<R> {
+    /**
+     * Create a reader that decrypts ciphertext from `reader` with the private key `key`. The
+     * ciphertext's header is read immediately.
+     *
+     * Returns `CryptError::InvalidHeader` or `CryptError::UnsupportedVersion` if the header can't
+     * be read, `CryptError::WrongAlgorithm` if the ciphertext wasn't encrypted with ElGamal,
+     * `CryptError::WrongRecipient` if the header names a recipient other than `key`'s public key,
+     * and `CryptError::UnsupportedBlockSize` if the ciphertext's blocks, including their
+     * sequence numbers and OAEP transform, are wider than `BLOCK_BYTES`. In hybrid mode, the
+     * wrapped key is also unwrapped, which fails as in `decrypt_block`.
+     *
+     * If the ciphertext is authenticated, its MAC is checked once the last block is read, and
+     * `CryptError::AuthenticationFailed` is returned if it doesn't match. Plaintext read before
+     * then hasn't been authenticated yet; call `verify` first to authenticate the whole
+     * ciphertext up front.
+     *
+     * If the ciphertext is chunked, nothing is read from `reader` after the final chunk, so it can
+     * be followed by anything, and the byte offsets in errors don't count the chunk lengths. A
+     * ciphertext that ends before its final chunk fails with `CryptError::Truncated`.
+     */
+    pub fn new(reader: R, key: PrivateKey) -> Result<Self, CryptError> {
+        let mut reader = PeekReader::new(reader);
+        reader.peek(header::MAGIC.len())?;
+        let header = Header::read(&mut reader)?;
+        if header.algorithm != Algorithm::ElGamal {
+            return Err(CryptError::WrongAlgorithm(header.algorithm));
+        }
+
+        if let Some(recipient) = header.recipient {
+            let actual = key.derive_public().fingerprint();
+            if recipient != actual {
+                return Err(CryptError::WrongRecipient(recipient, actual));
+            }
+        }
+
+        log_header("decrypting", &header);
+        let mut offset = header.len() as u64;
+
+        let width = header.block_width();
+        if width > BLOCK_BYTES {
+            return Err(CryptError::UnsupportedBlockSize(width));
+        }
+
+        // bytes already buffered while reading the header belong to the rest of the ciphertext
+        let buffered = reader.buffer().to_vec();
+        let source = Cursor::new(buffered).chain(reader.into_inner());
+        let mut reader = ChunkReader::new(source, header.chunked);
+
+        let mut mac = None;
+        if header.authenticated {
+            let mut share_buf = [0_u8; NUM_BYTES];
+            reader
+                .read_exact(&mut share_buf)
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::UnexpectedEof => CryptError::AuthenticationFailed,
+                    _ => CryptError::from(e),
+                })?;
+
+            let key = key.key();
+            let share = Num::from_be_bytes(share_buf);
+            let shared = mod_exp_ct(share, key.value, key.prime);
+
+            let mut header_bytes = Vec::new();
+            header.write(&mut header_bytes)?;
+            let mut hmac = Hmac::new(&derive_mac_key(shared, share));
+            hmac.update(&header_bytes);
+            hmac.update(&share_buf);
+            mac = Some(hmac);
+            offset += NUM_BYTES as u64;
+        }
+
+        let context = DecryptContext::new(&key)?;
+        let mut blinding = Blinding::new(&key, &mut rand::thread_rng());
+        let hybrid = match header.wrapped_key {
+            Some(wrapped) => {
+                let keystream =
+                    hybrid::unwrap_key(wrapped, &key, &context, header.encoding, &mut blinding)?;
+                Some(keystream)
+            }
+            None => None,
+        };
+
+        let reader = BufReader::new(MacReader::new(reader, mac));
+
+        Ok(Self {
+            reader,
+            key,
+            context,
+            blinding,
+            offset,
+            header,
+            block: [0_u8; BLOCK_BYTES],
+            block_start: 0,
+            block_end: 0,
+            finished: false,
+            chain: [0_u8; BLOCK_BYTES],
+            keystream: None,
+            hybrid,
+            block_index: 0,
+        })
+    }
+
+    /**
+     * Get the header read from the start of the ciphertext.
+     */
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /**
+     * Authenticate the rest of the ciphertext before any more of it is decrypted, by reading it
+     * into memory and checking its MAC. Does nothing if the ciphertext isn't authenticated.
+     *
+     * Returns `CryptError::AuthenticationFailed` if the MAC doesn't match.
+     */
+    pub fn verify(&mut self) -> Result<(), CryptError> {
+        self.reader.get_mut().verify()
+    }
+
+    /**
+     * Wrap `error` to report that it occurred in the block at the current offset.
+     */
+    fn at_offset(&self, error: CryptError) -> CryptError {
+        CryptError::Block {
+            offset: self.offset,
+            error: Box::new(error),
+        }
+    }
+
+    /**
+     * Read the next ciphertext pair, without advancing the offset.
+     */
+    fn read_pair(&mut self) -> Result<(Num, Num), CryptError> {
+        let mut c1_buf = [0_u8; NUM_BYTES];
+        let mut c2_buf = [0_u8; NUM_BYTES];
+        let result = self
+            .reader
+            .read_exact(&mut c1_buf)
+            .and_then(|_| self.reader.read_exact(&mut c2_buf));
+
+        match result {
+            Ok(()) => Ok((Num::from_be_bytes(c1_buf), Num::from_be_bytes(c2_buf))),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                Err(self.at_offset(CryptError::CorruptBlock))
+            }
+            Err(e) => Err(CryptError::from(e)),
+        }
+    }
+
+    /**
+     * Decrypt the next ciphertext block into the buffer, stripping the padding if it's the last
+     * block.
+     *
+     * If blocks are checksummed, returns `CryptError::ChecksumMismatch` if the block's checksum
+     * doesn't match. If blocks are numbered, returns `CryptError::OutOfSequence` if the block's
+     * sequence number doesn't match its position, and `CryptError::Truncated` if the ciphertext
+     * ends before the block marked as final.
+     */
+    fn decrypt_next(&mut self) -> Result<(), CryptError> {
+        if self.header.mode == Mode::Ctr {
+            return self.decrypt_next_ctr();
+        }
+
+        let offset = self.offset;
+        let at_offset = |error| CryptError::Block {
+            offset,
+            error: Box::new(error),
+        };
+
+        let (c1, c2) = self.read_pair()?;
+        let encoding = self.header.encoding;
+        let decrypted = self
+            .context
+            .decrypt_block_blinded(c1, c2, encoding, &mut self.blinding)
+            .map_err(at_offset)?;
+
+        // narrower blocks are stored in the low bytes of a `Block`, below the sequence number and
+        // checksum
+        let block_bytes = self.header.block_bytes;
+        let mut decrypted_bytes = Block::to_be_bytes(decrypted);
+        if self.header.oaep && !oaep::decode(&mut decrypted_bytes) {
+            return Err(at_offset(CryptError::CorruptBlock));
+        }
+
+        let sequence_start = BLOCK_BYTES - block_bytes - self.header.sequence_bytes;
+        let (high_bytes, contents) = decrypted_bytes.split_at(sequence_start);
+        let (unused_bytes, checksum) =
+            high_bytes.split_at(high_bytes.len() - self.header.checksum_bytes);
+        let (sequence_bytes, low_bytes) = contents.split_at(self.header.sequence_bytes);
+
+        // checked first, so that corruption is reported as such rather than as a bad sequence
+        // number or padding
+        let mut expected_checksum = [0_u8; BLOCK_BYTES];
+        let expected_checksum = &mut expected_checksum[..checksum.len()];
+        block_checksum(expected_checksum, self.block_index, contents);
+        if checksum != expected_checksum {
+            return Err(at_offset(CryptError::ChecksumMismatch));
+        }
+
+        if unused_bytes.iter().any(|&b| b != 0) {
+            return Err(at_offset(CryptError::WrongKey));
+        }
+
+        let is_last = self.reader.fill_buf()?.is_empty();
+        if !sequence_bytes.is_empty() {
+            let (index, is_final) = decode_sequence(sequence_bytes);
+            let expected = self.block_index & ((1 << (sequence_bytes.len() * 8 - 1)) - 1);
+
+            if index != expected || (is_final && !is_last) {
+                return Err(at_offset(CryptError::OutOfSequence));
+            } else if is_last && !is_final {
+                return Err(at_offset(CryptError::Truncated));
+            }
+        }
+
+        log::trace!("decrypted block {}", self.block_index);
+        self.block_index += 1;
+
+        self.block[..block_bytes].copy_from_slice(low_bytes);
+        if self.header.mode == Mode::Cbc {
+            xor_in_place(&mut self.block[..block_bytes], &self.chain);
+            self.chain = chain_value(c1, c2);
+        }
+
+        self.block_start = 0;
+        self.block_end = block_bytes;
+        self.offset += (NUM_BYTES * 2) as u64;
+
+        if is_last {
+            // last ciphertext block, so it's always padded
+            let padding = self.header.padding;
+            let pad_bytes = match padding.unpad(&self.block[..block_bytes]) {
+                Some(pad_bytes) => pad_bytes,
+                None => {
+                    self.block_end = 0;
+                    return Err(at_offset(CryptError::InvalidPadding(padding)));
+                }
+            };
+
+            self.block_end -= pad_bytes;
+            self.finished = true;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Decrypt the next piece of a CTR mode ciphertext into the buffer, starting a new segment if
+     * the current one is used up.
+     */
+    fn decrypt_next_ctr(&mut self) -> Result<(), CryptError> {
+        let needs_segment = self.keystream.as_ref().is_none_or(|k| k.remaining == 0);
+        if needs_segment {
+            if self.reader.fill_buf()?.is_empty() {
+                self.finished = true;
+                return Ok(());
+            }
+
+            let (c1, c2) = self.read_pair()?;
+            let key = self.key.key();
+            let encoding = self.header.encoding;
+            let nonce = self
+                .context
+                .decrypt_block_blinded(c1, c2, encoding, &mut self.blinding)
+                .map_err(|e| self.at_offset(e))?;
+            let shared = mod_exp_ct(c1, key.value, key.prime);
+
+            let keystream = Keystream::new(shared, nonce, self.header.block_bytes);
+            self.keystream = Some(keystream);
+            self.offset += (NUM_BYTES * 2) as u64;
+        }
+
+        let keystream = self.keystream.as_mut().unwrap();
+        let len = cmp::min(self.header.block_bytes, keystream.remaining);
+        let count = self.reader.read(&mut self.block[..len])?;
+        if count == 0 {
+            self.finished = true;
+        }
+
+        keystream.apply(&mut self.block[..count]);
+        self.block_start = 0;
+        self.block_end = count;
+        self.offset += count as u64;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // hybrid mode has no blocks, so it's decrypted straight into `buf`
+        if let Some(keystream) = self.hybrid.as_mut() {
+            let count = self.reader.read(buf)?;
+            keystream.apply(&mut buf[..count]);
+            self.offset += count as u64;
+            return Ok(count);
+        }
+
+        if self.block_start == self.block_end {
+            if self.finished {
+                return Ok(0);
+            }
+
+            if let Err(e) = self.decrypt_next() {
+                // CTR mode has no blocks to count
+                match self.header.mode {
+                    Mode::Ctr => log::debug!("decryption failed at byte {}: {}", self.offset, e),
+                    _ => log::debug!(
+                        "decryption failed at block {} (byte {}): {}",
+                        self.block_index,
+                        self.offset,
+                        e
+                    ),
+                }
+                return Err(e.into());
+            }
+        }
+
+        let count = cmp::min(buf.len(), self.block_end - self.block_start);
+        buf[..count].copy_from_slice(&self.block[self.block_start..self.block_start + count]);
+        self.block_start += count;
+        Ok(count)
+    }
+}
@@ -0,0 +1,1007 @@
+use super::*;
+use crate::{decrypt_block, ecb, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+use std::convert::TryInto;
+
+/**
+ * Encrypt `plaintext` with an `EncryptWriter`, writing it in chunks of `chunk_len` bytes.
+ */
+fn encrypt_chunked(
+    plaintext: &[u8],
+    chunk_len: usize,
+    keys: &KeyPair,
+    rng: &mut StdRng,
+) -> Vec<u8> {
+    let mut encryptor = EncryptWriter::new(Vec::new(), keys.public.clone(), rng);
+    for chunk in plaintext.chunks(chunk_len) {
+        encryptor.write_all(chunk).unwrap();
+    }
+
+    encryptor.finish().unwrap()
+}
+
+/**
+ * Decrypt `ciphertext` with a `DecryptReader`, reading it in chunks of at most `chunk_len` bytes.
+ */
+fn decrypt_chunked(ciphertext: &[u8], chunk_len: usize, keys: &KeyPair) -> Vec<u8> {
+    let mut decryptor = DecryptReader::new(ciphertext, keys.private.clone()).unwrap();
+    let mut buf = vec![0_u8; chunk_len];
+    let mut result = Vec::new();
+
+    loop {
+        match decryptor.read(&mut buf).unwrap() {
+            0 => return result,
+            n => result.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+#[test]
+fn test_stream_round_trip() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    for len in (0..=BLOCK_BYTES * 2).chain(vec![100, 1000]) {
+        let mut plaintext = vec![0_u8; len];
+        rng.fill(&mut plaintext[..]);
+
+        for &chunk_len in &[1, 3, BLOCK_BYTES, 64] {
+            let ciphertext = encrypt_chunked(&plaintext, chunk_len, &keys, &mut rng);
+
+            // every block, including the final padded block, expands to two `Num`s after the
+            // header, just as with `ecb::encrypt_ecb`
+            let header = Header::new(Encoding::QuadraticResidue);
+            let block_count = len / BLOCK_BYTES + 1;
+            assert_eq!(ciphertext.len(), header.len() + block_count * NUM_BYTES * 2);
+
+            assert_eq!(decrypt_chunked(&ciphertext, chunk_len, &keys), plaintext);
+        }
+    }
+}
+
+#[test]
+fn test_stream_compatible_with_ecb() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let plaintext = b"streamed and whole-file ciphertexts are interchangeable";
+
+    let streamed = encrypt_chunked(plaintext, 5, &keys, &mut rng);
+    let mut decrypted = Vec::new();
+    ecb::decrypt_ecb(&mut &streamed[..], &mut decrypted, &keys.private).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    let mut whole = Vec::new();
+    ecb::encrypt_ecb(&mut &plaintext[..], &mut whole, &keys.public, &mut rng).unwrap();
+    assert_eq!(decrypt_chunked(&whole, 7, &keys), plaintext);
+}
+
+#[test]
+fn test_stream_block_size() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let plaintext = b"narrow blocks";
+
+    let mut encryptor =
+        EncryptWriter::with_block_size(Vec::new(), keys.public.clone(), 1, &mut rng).unwrap();
+    assert_eq!(encryptor.header().block_bytes, 1);
+    encryptor.write_all(plaintext).unwrap();
+    let ciphertext = encryptor.finish().unwrap();
+
+    let decryptor = DecryptReader::new(&ciphertext[..], keys.private.clone()).unwrap();
+    assert_eq!(decryptor.header().block_bytes, 1);
+    assert_eq!(decrypt_chunked(&ciphertext, 2, &keys), plaintext);
+
+    for &block_bytes in &[0, BLOCK_BYTES + 1] {
+        let result =
+            EncryptWriter::with_block_size(Vec::new(), keys.public.clone(), block_bytes, &mut rng);
+        assert!(matches!(
+            result,
+            Err(CryptError::UnsupportedBlockSize(size)) if size == block_bytes
+        ));
+    }
+}
+
+#[test]
+fn test_stream_errors() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    let mut ciphertext = encrypt_chunked(b"hello", 1, &keys, &mut rng);
+
+    // errors are returned as `io::Error`s, but the original error can be recovered
+    let mut decryptor = DecryptReader::new(&ciphertext[..], other_keys.private.clone()).unwrap();
+    let error = CryptError::from(decryptor.read(&mut [0_u8; 16]).unwrap_err());
+    assert!(matches!(error.root_cause(), CryptError::WrongKey));
+
+    ciphertext.pop();
+    let mut decryptor = DecryptReader::new(&ciphertext[..], keys.private.clone()).unwrap();
+    let error = CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err());
+    assert!(matches!(error.root_cause(), CryptError::CorruptBlock));
+
+    // the header is read as soon as the reader is created
+    let mut bad_header = ciphertext.clone();
+    bad_header[crate::header::MAGIC.len()] = 0xff;
+    assert!(matches!(
+        DecryptReader::new(&bad_header[..], keys.private.clone()),
+        Err(CryptError::UnsupportedVersion(0xff))
+    ));
+}
+
+#[test]
+fn test_io_error_conversion() {
+    // I/O errors pass through unchanged in both directions
+    let io_error = io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed");
+    let crypt_error = CryptError::from(io_error);
+    assert!(matches!(&crypt_error, CryptError::Io(e) if e.kind() == io::ErrorKind::BrokenPipe));
+    assert_eq!(
+        io::Error::from(crypt_error).kind(),
+        io::ErrorKind::BrokenPipe
+    );
+
+    // other errors are reported as invalid data, and survive a round trip
+    let io_error = io::Error::from(CryptError::InvalidPadding(Padding::Random));
+    assert_eq!(io_error.kind(), io::ErrorKind::InvalidData);
+    assert!(matches!(
+        CryptError::from(io_error),
+        CryptError::InvalidPadding(Padding::Random)
+    ));
+}
+
+#[test]
+fn test_cbc_mode() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let encrypt_cbc = |plaintext: &[u8], block_bytes, rng: &mut StdRng| {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.mode = Mode::Cbc;
+        header.block_bytes = block_bytes;
+
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    };
+
+    for block_bytes in 1..=BLOCK_BYTES {
+        for len in (0..=block_bytes * 2).chain(vec![100]) {
+            let mut plaintext = vec![0_u8; len];
+            rng.fill(&mut plaintext[..]);
+
+            let ciphertext = encrypt_cbc(&plaintext, block_bytes, &mut rng);
+            assert_eq!(decrypt_chunked(&ciphertext, 3, &keys), plaintext);
+            assert_eq!(
+                crate::decrypt_bytes(&ciphertext, &keys.private).unwrap(),
+                plaintext
+            );
+        }
+    }
+
+    // swapping two blocks garbles the plaintext from the first swapped block onward
+    let plaintext = [0x5a_u8; BLOCK_BYTES * 4];
+    let mut ciphertext = encrypt_cbc(&plaintext, BLOCK_BYTES, &mut rng);
+    let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+    let pair_len = NUM_BYTES * 2;
+    let (first, second) = ciphertext[header_len..].split_at_mut(pair_len);
+    first.swap_with_slice(&mut second[..pair_len]);
+
+    let mut decrypted = Vec::new();
+    let _ = DecryptReader::new(&ciphertext[..], keys.private.clone())
+        .unwrap()
+        .read_to_end(&mut decrypted);
+    assert!(decrypted.len() >= BLOCK_BYTES * 3);
+    assert_ne!(decrypted[..BLOCK_BYTES], plaintext[..BLOCK_BYTES]);
+    assert_ne!(
+        decrypted[BLOCK_BYTES * 2..BLOCK_BYTES * 3],
+        plaintext[BLOCK_BYTES * 2..BLOCK_BYTES * 3]
+    );
+}
+
+#[test]
+fn test_ctr_mode() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let encrypt_ctr = |plaintext: &[u8], block_bytes, rng: &mut StdRng| {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.mode = Mode::Ctr;
+        header.block_bytes = block_bytes;
+
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        for chunk in plaintext.chunks(1000) {
+            encryptor.write_all(chunk).unwrap();
+        }
+
+        encryptor.finish().unwrap()
+    };
+
+    let lengths = vec![
+        0,
+        1,
+        BLOCK_BYTES + 1,
+        1000,
+        CTR_SEGMENT_BYTES,
+        CTR_SEGMENT_BYTES * 2 + 5,
+    ];
+
+    for len in lengths {
+        let mut plaintext = vec![0_u8; len];
+        rng.fill(&mut plaintext[..]);
+
+        for &block_bytes in &[1, BLOCK_BYTES] {
+            let ciphertext = encrypt_ctr(&plaintext, block_bytes, &mut rng);
+
+            // no padding, just an encrypted nonce per segment
+            let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+            let segments = len.div_ceil(CTR_SEGMENT_BYTES);
+            assert_eq!(
+                ciphertext.len(),
+                header_len + segments * NUM_BYTES * 2 + len
+            );
+
+            assert_eq!(decrypt_chunked(&ciphertext, 100, &keys), plaintext);
+        }
+    }
+
+    // flipping a ciphertext bit flips the corresponding plaintext bit
+    let plaintext = b"counter mode keystream";
+    let mut ciphertext = encrypt_ctr(plaintext, BLOCK_BYTES, &mut rng);
+    let last = ciphertext.len() - 1;
+    ciphertext[last] ^= 1;
+
+    let mut expected = plaintext.to_vec();
+    expected[plaintext.len() - 1] ^= 1;
+    assert_eq!(decrypt_chunked(&ciphertext, 100, &keys), expected);
+
+    // a truncated nonce is reported at its offset
+    let ciphertext = encrypt_ctr(plaintext, BLOCK_BYTES, &mut rng);
+    let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+    let truncated = &ciphertext[..header_len + NUM_BYTES];
+    let mut decryptor = DecryptReader::new(truncated, keys.private.clone()).unwrap();
+    match CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err()) {
+        CryptError::Block { offset, error } => {
+            assert_eq!(offset, header_len as u64);
+            assert!(matches!(*error, CryptError::CorruptBlock));
+        }
+        e => panic!("Expected a truncated block, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_hybrid_mode() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    let encrypt_hybrid = |plaintext: &[u8], rng: &mut StdRng| {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.mode = Mode::Hybrid;
+
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        for chunk in plaintext.chunks(1000) {
+            encryptor.write_all(chunk).unwrap();
+        }
+
+        encryptor.finish().unwrap()
+    };
+
+    for &len in &[0, 1, 31, 32, 33, 1000, CTR_SEGMENT_BYTES * 2 + 5] {
+        let mut plaintext = vec![0_u8; len];
+        rng.fill(&mut plaintext[..]);
+        let ciphertext = encrypt_hybrid(&plaintext, &mut rng);
+
+        // the wrapped key is in the header, so there's nothing else to add
+        let header = Header::read(&mut &ciphertext[..]).unwrap();
+        assert_eq!(header.mode, Mode::Hybrid);
+        assert!(header.wrapped_key.is_some());
+        assert_eq!(ciphertext.len(), header.len() + len);
+
+        assert_eq!(decrypt_chunked(&ciphertext, 100, &keys), plaintext);
+    }
+
+    // each ciphertext gets its own key
+    let plaintext = [0_u8; 64];
+    let first = encrypt_hybrid(&plaintext, &mut rng);
+    let second = encrypt_hybrid(&plaintext, &mut rng);
+    let header_len = Header::read(&mut &first[..]).unwrap().len();
+    assert_ne!(first[header_len..], second[header_len..]);
+
+    // the wrong key can't recover the plaintext
+    let mut decrypted = Vec::new();
+    if let Ok(mut decryptor) = DecryptReader::new(&first[..], other_keys.private.clone()) {
+        if decryptor.read_to_end(&mut decrypted).is_ok() {
+            assert_ne!(decrypted, plaintext);
+        }
+    }
+
+    // padding schemes and block transforms don't apply
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.mode = Mode::Hybrid;
+    header.padding = Padding::Pkcs7;
+    assert!(matches!(
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng),
+        Err(CryptError::InvalidHeader)
+    ));
+}
+
+#[test]
+fn test_deterministic_encryption() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let mut plaintext = vec![0_u8; 1000];
+    rng.fill(&mut plaintext[..]);
+
+    let encrypt = |header: &Header, nonce: &[u8], plaintext: &[u8]| {
+        let mut encryptor =
+            EncryptWriter::deterministic(Vec::new(), keys.public.clone(), header.clone(), nonce)
+                .unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    };
+
+    for &mode in &[Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Hybrid] {
+        for &authenticated in &[false, true] {
+            let mut header = Header::new(Encoding::QuadraticResidue);
+            header.mode = mode;
+            header.authenticated = authenticated;
+            if !matches!(mode, Mode::Ctr | Mode::Hybrid) {
+                header.oaep = true;
+                header.block_bytes = BLOCK_BYTES - oaep::OVERHEAD_BYTES;
+            }
+
+            let ciphertext = encrypt(&header, b"nonce", &plaintext);
+            assert_eq!(ciphertext, encrypt(&header, b"nonce", &plaintext));
+            assert_ne!(ciphertext, encrypt(&header, b"other nonce", &plaintext));
+            assert_eq!(decrypt_chunked(&ciphertext, 100, &keys), plaintext);
+        }
+    }
+
+    // the exponent depends on the block's position, so equal blocks encrypt differently
+    let ciphertext = encrypt(
+        &Header::new(Encoding::QuadraticResidue),
+        b"nonce",
+        &[0_u8; 8],
+    );
+    let header_len = Header::new(Encoding::QuadraticResidue).len();
+    let pair_bytes = NUM_BYTES * 2;
+    let blocks: Vec<_> = ciphertext[header_len..].chunks(pair_bytes).collect();
+    assert_ne!(blocks[0], blocks[1]);
+
+    let malformed = crate::Key {
+        prime: 4,
+        root: 2,
+        value: 2,
+    };
+    let malformed = PublicKey::deserialize(&malformed.serialize());
+    assert!(matches!(
+        EncryptWriter::deterministic(Vec::new(), malformed, Header::new(Encoding::Raw), b"nonce"),
+        Err(CryptError::MalformedKey)
+    ));
+}
+
+#[test]
+fn test_sequence_numbers() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let encrypt_numbered = |plaintext: &[u8], block_bytes, sequence_bytes, rng: &mut StdRng| {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.block_bytes = block_bytes;
+        header.sequence_bytes = sequence_bytes;
+
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    };
+
+    for sequence_bytes in 1..BLOCK_BYTES {
+        for block_bytes in 1..=BLOCK_BYTES - sequence_bytes {
+            // long enough for a one-byte sequence number to wrap around
+            for &len in &[0, 1, block_bytes * 3, 300] {
+                let mut plaintext = vec![0_u8; len];
+                rng.fill(&mut plaintext[..]);
+
+                let ciphertext =
+                    encrypt_numbered(&plaintext, block_bytes, sequence_bytes, &mut rng);
+                assert_eq!(decrypt_chunked(&ciphertext, 3, &keys), plaintext);
+            }
+        }
+    }
+
+    // blocks and their sequence numbers must fit in a `Block`, and CTR mode has no blocks
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.sequence_bytes = 1;
+    assert!(matches!(
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header.clone(), &mut rng),
+        Err(CryptError::UnsupportedBlockSize(size)) if size == BLOCK_BYTES + 1
+    ));
+
+    header.block_bytes = 1;
+    header.mode = Mode::Ctr;
+    assert!(matches!(
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng),
+        Err(CryptError::InvalidHeader)
+    ));
+
+    let plaintext = [0x5a_u8; 8];
+    let ciphertext = encrypt_numbered(&plaintext, 2, 2, &mut rng);
+    let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+    let pair_len = NUM_BYTES * 2;
+    let pair = |i: usize| header_len + i * pair_len..header_len + (i + 1) * pair_len;
+
+    let check = |ciphertext: &[u8], expected_offset: usize, expected: fn(&CryptError) -> bool| {
+        let mut decryptor = DecryptReader::new(ciphertext, keys.private.clone()).unwrap();
+        match CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err()) {
+            CryptError::Block { offset, error } => {
+                assert_eq!(offset, expected_offset as u64);
+                assert!(expected(&error), "unexpected error {:?}", error);
+            }
+            e => panic!("Expected a block error, got {:?}", e),
+        }
+    };
+
+    // swapped blocks
+    let mut swapped = ciphertext.clone();
+    let (first, second) = swapped[header_len..].split_at_mut(pair_len);
+    first.swap_with_slice(&mut second[..pair_len]);
+    check(&swapped, header_len, |e| {
+        matches!(e, CryptError::OutOfSequence)
+    });
+
+    // a block removed from the middle
+    let mut removed = ciphertext.clone();
+    removed.drain(pair(1));
+    check(&removed, pair(1).start, |e| {
+        matches!(e, CryptError::OutOfSequence)
+    });
+
+    // a duplicated block
+    let mut duplicated = ciphertext.clone();
+    let copy = duplicated[pair(0)].to_vec();
+    duplicated.splice(pair(1).start..pair(1).start, copy);
+    check(&duplicated, pair(1).start, |e| {
+        matches!(e, CryptError::OutOfSequence)
+    });
+
+    // blocks removed from the end
+    let truncated = &ciphertext[..pair(2).end];
+    check(truncated, pair(2).start, |e| {
+        matches!(e, CryptError::Truncated)
+    });
+
+    // the final block moved earlier
+    let mut final_first = ciphertext[..header_len].to_vec();
+    final_first.extend_from_slice(&ciphertext[pair(4)]);
+    final_first.extend_from_slice(&ciphertext[pair(0)]);
+    check(&final_first, header_len, |e| {
+        matches!(e, CryptError::OutOfSequence)
+    });
+}
+
+#[test]
+fn test_block_checksums() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let encrypt_checked = |plaintext: &[u8], header: Header, rng: &mut StdRng| {
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    };
+
+    for checksum_bytes in 1..BLOCK_BYTES {
+        for sequence_bytes in 0..BLOCK_BYTES - checksum_bytes {
+            for block_bytes in 1..=BLOCK_BYTES - checksum_bytes - sequence_bytes {
+                let mut header = Header::new(Encoding::QuadraticResidue);
+                header.block_bytes = block_bytes;
+                header.sequence_bytes = sequence_bytes;
+                header.checksum_bytes = checksum_bytes;
+
+                for &len in &[0, 1, block_bytes * 3] {
+                    let mut plaintext = vec![0_u8; len];
+                    rng.fill(&mut plaintext[..]);
+
+                    let ciphertext = encrypt_checked(&plaintext, header.clone(), &mut rng);
+                    assert_eq!(decrypt_chunked(&ciphertext, 3, &keys), plaintext);
+                }
+            }
+        }
+    }
+
+    // blocks and their checksums must fit in a `Block`, and CTR mode has no blocks
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.checksum_bytes = 1;
+    assert!(matches!(
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header.clone(), &mut rng),
+        Err(CryptError::UnsupportedBlockSize(size)) if size == BLOCK_BYTES + 1
+    ));
+
+    header.block_bytes = 1;
+    header.mode = Mode::Ctr;
+    assert!(matches!(
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng),
+        Err(CryptError::InvalidHeader)
+    ));
+
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.block_bytes = BLOCK_BYTES - 2;
+    header.checksum_bytes = 2;
+    let plaintext = [0x5a_u8; 16];
+    let ciphertext = encrypt_checked(&plaintext, header, &mut rng);
+    let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+    let pair_len = NUM_BYTES * 2;
+
+    let check = |ciphertext: &[u8],
+                 private: &PrivateKey,
+                 expected_offset: usize,
+                 expected: fn(&CryptError) -> bool| {
+        let mut decryptor = DecryptReader::new(ciphertext, private.clone()).unwrap();
+        match CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err()) {
+            CryptError::Block { offset, error } => {
+                assert_eq!(offset, expected_offset as u64);
+                assert!(expected(&error), "unexpected error {:?}", error);
+            }
+            e => panic!("Expected a block error, got {:?}", e),
+        }
+    };
+
+    // a block with other contents is caught where it occurs, even though it decrypts
+    let mut replaced = ciphertext.clone();
+    let offset = header_len + pair_len * 2;
+    let block: Block = 0x5a5a;
+    let (c1, c2) =
+        encrypt_block(block, &keys.public, Encoding::QuadraticResidue, &mut rng).unwrap();
+    replaced[offset..offset + NUM_BYTES].copy_from_slice(&c1.to_be_bytes());
+    replaced[offset + NUM_BYTES..offset + pair_len].copy_from_slice(&c2.to_be_bytes());
+    check(&replaced, &keys.private, offset, |e| {
+        matches!(e, CryptError::ChecksumMismatch)
+    });
+
+    // flipped bits are caught at the same offset, if not by the decoding then by the checksum
+    let mut corrupted = ciphertext.clone();
+    corrupted[offset + pair_len - 1] ^= 1;
+    check(&corrupted, &keys.private, offset, |e| {
+        matches!(
+            e,
+            CryptError::ChecksumMismatch | CryptError::WrongKey | CryptError::OutOfRange
+        )
+    });
+
+    // swapped blocks have the wrong positions
+    let mut swapped = ciphertext.clone();
+    let (first, second) = swapped[header_len..].split_at_mut(pair_len);
+    first.swap_with_slice(&mut second[..pair_len]);
+    check(&swapped, &keys.private, header_len, |e| {
+        matches!(e, CryptError::ChecksumMismatch)
+    });
+
+    // a different key garbles every block, if the ciphertext is even in range for it
+    let other = KeyPair::generate(&mut rng).unwrap();
+    check(&ciphertext, &other.private, header_len, |e| {
+        matches!(
+            e,
+            CryptError::ChecksumMismatch | CryptError::WrongKey | CryptError::OutOfRange
+        )
+    });
+}
+
+#[test]
+fn test_padding_schemes() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let encrypt_padded = |plaintext: &[u8], padding, mode, rng: &mut StdRng| {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.padding = padding;
+        header.mode = mode;
+
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish()
+    };
+
+    for &padding in &[Padding::Random, Padding::Pkcs7, Padding::Zero] {
+        for &mode in &[Mode::Ecb, Mode::Cbc] {
+            for len in 0..=BLOCK_BYTES * 2 {
+                // zero padding can't encrypt plaintexts ending with a zero byte
+                let mut plaintext = vec![0_u8; len];
+                rng.fill(&mut plaintext[..]);
+                if let Some(last) = plaintext.last_mut() {
+                    *last |= 1;
+                }
+
+                let ciphertext = encrypt_padded(&plaintext, padding, mode, &mut rng).unwrap();
+                let decryptor = DecryptReader::new(&ciphertext[..], keys.private.clone()).unwrap();
+                assert_eq!(decryptor.header().padding, padding);
+                assert_eq!(decrypt_chunked(&ciphertext, 3, &keys), plaintext);
+            }
+        }
+    }
+
+    assert!(matches!(
+        encrypt_padded(b"ends with zero\0", Padding::Zero, Mode::Ecb, &mut rng),
+        Err(CryptError::InvalidPadding(Padding::Zero))
+    ));
+
+    // random padding isn't valid PKCS#7 or zero padding, so relabeling it in the header is caught
+    let ciphertext = encrypt_padded(b"", Padding::Random, Mode::Ecb, &mut rng).unwrap();
+    let mut body = &ciphertext[..];
+    let mut header = Header::read(&mut body).unwrap();
+
+    for &padding in &[Padding::Pkcs7, Padding::Zero] {
+        header.padding = padding;
+        let mut relabeled = Vec::new();
+        header.write(&mut relabeled).unwrap();
+        relabeled.extend_from_slice(body);
+
+        let mut decryptor = DecryptReader::new(&relabeled[..], keys.private.clone()).unwrap();
+        let error = CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err());
+        assert!(matches!(error.root_cause(), CryptError::InvalidPadding(p) if *p == padding));
+    }
+
+    // CTR mode has nothing to pad
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.mode = Mode::Ctr;
+    header.padding = Padding::Pkcs7;
+    assert!(matches!(
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng),
+        Err(CryptError::InvalidHeader)
+    ));
+}
+
+#[test]
+fn test_oaep_transform() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let oaep_header = |block_bytes, sequence_bytes| {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.oaep = true;
+        header.sequence_bytes = sequence_bytes;
+        header.block_bytes = block_bytes;
+        header
+    };
+    let encrypt_oaep = |plaintext: &[u8], header, rng: &mut StdRng| {
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    };
+
+    let max_block_bytes = oaep_header(1, 0).max_block_bytes();
+    assert!(max_block_bytes < BLOCK_BYTES);
+
+    for sequence_bytes in 0..max_block_bytes {
+        for block_bytes in 1..=max_block_bytes - sequence_bytes {
+            for &len in &[0, 1, block_bytes * 3, 100] {
+                let mut plaintext = vec![0_u8; len];
+                rng.fill(&mut plaintext[..]);
+
+                let header = oaep_header(block_bytes, sequence_bytes);
+                let ciphertext = encrypt_oaep(&plaintext, header, &mut rng);
+                assert_eq!(decrypt_chunked(&ciphertext, 3, &keys), plaintext);
+            }
+        }
+    }
+
+    let header = oaep_header(max_block_bytes + 1, 0);
+    assert!(matches!(
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng),
+        Err(CryptError::UnsupportedBlockSize(_))
+    ));
+
+    // identical plaintext blocks are transformed into unrelated values before encryption; the
+    // seed is small enough for some values to repeat by chance, but most must differ
+    let plaintext = [0x5a_u8; 64];
+    let ciphertext = encrypt_oaep(&plaintext, oaep_header(max_block_bytes, 0), &mut rng);
+    let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+    let pair_len = NUM_BYTES * 2;
+    let transformed: Vec<_> = ciphertext[header_len..]
+        .chunks(pair_len)
+        .map(|pair| {
+            let c1 = Num::from_be_bytes(pair[..NUM_BYTES].try_into().unwrap());
+            let c2 = Num::from_be_bytes(pair[NUM_BYTES..].try_into().unwrap());
+            decrypt_block(c1, c2, &keys.private, Encoding::QuadraticResidue).unwrap()
+        })
+        .collect();
+    let mut distinct = transformed.clone();
+    distinct.sort_unstable();
+    distinct.dedup();
+    assert!(distinct.len() > transformed.len() / 2);
+
+    // replacing a block is detected when the transform is reversed
+    let mut tampered = ciphertext.clone();
+    let block = rng.gen::<Block>();
+    let (c1, c2) =
+        encrypt_block(block, &keys.public, Encoding::QuadraticResidue, &mut rng).unwrap();
+    tampered[header_len..header_len + NUM_BYTES].copy_from_slice(&c1.to_be_bytes());
+    tampered[header_len + NUM_BYTES..header_len + pair_len].copy_from_slice(&c2.to_be_bytes());
+
+    let mut decryptor = DecryptReader::new(&tampered[..], keys.private.clone()).unwrap();
+    let error = CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err());
+    assert!(matches!(error.root_cause(), CryptError::CorruptBlock));
+}
+
+#[test]
+fn test_authenticated_encryption() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    let encrypt_authenticated = |plaintext: &[u8], mode, rng: &mut StdRng| {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.authenticated = true;
+        header.mode = mode;
+
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, rng).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    };
+    let decrypt_ecb = |ciphertext: &[u8], key: &PrivateKey| {
+        let mut plaintext = Vec::new();
+        let result = ecb::decrypt_ecb(&mut &ciphertext[..], &mut plaintext, key);
+        result.map(|_| plaintext)
+    };
+
+    for &mode in &[Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Hybrid] {
+        for &len in &[0, 1, BLOCK_BYTES, 100, 5000] {
+            let mut plaintext = vec![0_u8; len];
+            rng.fill(&mut plaintext[..]);
+
+            let ciphertext = encrypt_authenticated(&plaintext, mode, &mut rng);
+            assert_eq!(decrypt_chunked(&ciphertext, 3, &keys), plaintext);
+            assert_eq!(decrypt_ecb(&ciphertext, &keys.private).unwrap(), plaintext);
+        }
+    }
+
+    let plaintext = b"tampering with any byte is detected before decryption";
+    let ciphertext = encrypt_authenticated(plaintext, Mode::Ecb, &mut rng);
+    let header_len = Header::read(&mut &ciphertext[..]).unwrap().len();
+
+    // every byte after the header is covered by the MAC, and nothing is written on failure
+    for i in header_len..ciphertext.len() {
+        let mut tampered = ciphertext.clone();
+        tampered[i] ^= 0x10;
+
+        let mut written = Vec::new();
+        let result = ecb::decrypt_ecb(&mut &tampered[..], &mut written, &keys.private);
+        assert!(
+            matches!(result, Err(CryptError::AuthenticationFailed)),
+            "byte {}",
+            i
+        );
+        assert!(written.is_empty());
+    }
+
+    // truncated ciphertexts and the wrong key are detected too
+    for len in header_len..ciphertext.len() {
+        assert!(matches!(
+            decrypt_ecb(&ciphertext[..len], &keys.private),
+            Err(CryptError::AuthenticationFailed)
+        ));
+    }
+    assert!(matches!(
+        decrypt_ecb(&ciphertext, &other_keys.private),
+        Err(CryptError::AuthenticationFailed)
+    ));
+
+    // when streaming, the MAC is checked before the last block is released
+    let mut tampered = ciphertext.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    let mut decryptor = DecryptReader::new(&tampered[..], keys.private.clone()).unwrap();
+    let error = CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err());
+    assert!(matches!(error, CryptError::AuthenticationFailed));
+}
+
+/**
+ * A reader that fails if it's read past the end of its bytes, like a stream that stays open
+ * after the ciphertext.
+ */
+struct OpenStream<'a>(&'a [u8]);
+
+impl Read for OpenStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(io::Error::other("read past the end of the ciphertext"));
+        }
+
+        self.0.read(buf)
+    }
+}
+
+#[test]
+fn test_chunked_ciphertexts() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let cases = [
+        (Mode::Ecb, false, 20_000),
+        (Mode::Cbc, true, 100),
+        (Mode::Ctr, false, CHUNK_BYTES + 1),
+        (Mode::Hybrid, true, CHUNK_BYTES * 2),
+    ];
+    for &(mode, authenticated, len) in &cases {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.mode = mode;
+        header.authenticated = authenticated;
+        header.chunked = true;
+
+        let mut plaintext = vec![0_u8; len];
+        rng.fill(&mut plaintext[..]);
+
+        // flushing ends a chunk early, which readers have to handle too
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng).unwrap();
+        encryptor.write_all(&plaintext[..len / 2]).unwrap();
+        encryptor.flush().unwrap();
+        encryptor.write_all(&plaintext[len / 2..]).unwrap();
+        let ciphertext = encryptor.finish().unwrap();
+        assert!(Header::read(&mut &ciphertext[..]).unwrap().chunked);
+        assert_eq!(decrypt_chunked(&ciphertext, 1000, &keys), plaintext);
+
+        // decryption stops at the final chunk, without waiting for the end of the stream
+        let mut decryptor =
+            DecryptReader::new(OpenStream(&ciphertext), keys.private.clone()).unwrap();
+        let mut decrypted = Vec::new();
+        decryptor.read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        // and anything after it is ignored
+        let mut followed = ciphertext.clone();
+        followed.extend_from_slice(b"trailing data");
+        assert_eq!(decrypt_chunked(&followed, 1000, &keys), plaintext);
+
+        let truncated = &ciphertext[..ciphertext.len() - 1];
+        let mut decryptor = DecryptReader::new(truncated, keys.private.clone()).unwrap();
+        let error = CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err());
+        assert!(
+            matches!(error.root_cause(), CryptError::Truncated),
+            "{:?}",
+            error
+        );
+    }
+
+    // chunks can't be longer than `CHUNK_BYTES`
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.chunked = true;
+    let mut ciphertext = Vec::new();
+    header.write(&mut ciphertext).unwrap();
+    ciphertext.extend_from_slice(&(CHUNK_BYTES as u32 + 1).to_be_bytes());
+    ciphertext.resize(ciphertext.len() + CHUNK_BYTES + 1, 0);
+    let mut decryptor = DecryptReader::new(&ciphertext[..], keys.private.clone()).unwrap();
+    let error = CryptError::from(decryptor.read_to_end(&mut Vec::new()).unwrap_err());
+    assert!(matches!(error, CryptError::InvalidChunk), "{:?}", error);
+}
+
+/**
+ * A reader that returns one byte at a time, like a slow pipe.
+ */
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(1);
+        self.0.read(&mut buf[..len])
+    }
+}
+
+#[test]
+fn test_short_reads() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let mut plaintext = vec![0_u8; 1000];
+    rng.fill(&mut plaintext[..]);
+
+    // the header's magic bytes are found even when they arrive one at a time
+    for &chunked in &[false, true] {
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.mode = Mode::Cbc;
+        header.chunked = chunked;
+        let mut encryptor =
+            EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng).unwrap();
+        encryptor.write_all(&plaintext).unwrap();
+        let ciphertext = encryptor.finish().unwrap();
+
+        let mut decryptor = DecryptReader::new(Trickle(&ciphertext), keys.private.clone()).unwrap();
+        let mut decrypted = Vec::new();
+        decryptor.read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+}
+
+#[test]
+fn test_estimate_size() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    for &mode in &[Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Hybrid] {
+        for &(authenticated, chunked) in &[(false, false), (true, false), (true, true)] {
+            for &len in &[
+                0,
+                1,
+                BLOCK_BYTES,
+                1000,
+                CTR_SEGMENT_BYTES + 1,
+                CHUNK_BYTES * 2,
+            ] {
+                let mut header = Header::new(Encoding::QuadraticResidue);
+                header.mode = mode;
+                header.authenticated = authenticated;
+                header.chunked = chunked;
+                let plaintext = vec![1_u8; len];
+                let mut encryptor =
+                    EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng)
+                        .unwrap();
+                encryptor.write_all(&plaintext).unwrap();
+                let ciphertext = encryptor.finish().unwrap();
+                let header = Header::read(&mut &ciphertext[..]).unwrap();
+
+                // anything after a chunked ciphertext is ignored
+                let mut body = ciphertext[header.len()..].to_vec();
+                if chunked {
+                    body.extend_from_slice(b"trailing data");
+                }
+                let estimate = estimate_size(&header, &body[..]).unwrap();
+                let len = len as u64;
+                assert!(
+                    estimate.min_plaintext_bytes <= len && len <= estimate.max_plaintext_bytes,
+                    "{:?} {:?} {}",
+                    header,
+                    estimate,
+                    len
+                );
+
+                let expected_blocks = match mode {
+                    Mode::Ecb | Mode::Cbc => len / BLOCK_BYTES as u64 + 1,
+                    Mode::Ctr => len.div_ceil(CTR_SEGMENT_BYTES as u64),
+                    Mode::Hybrid => 0,
+                };
+                assert_eq!(estimate.blocks, expected_blocks);
+                assert_eq!(count_blocks(&header, len), expected_blocks);
+                if matches!(mode, Mode::Ctr | Mode::Hybrid) {
+                    assert_eq!(estimate.min_plaintext_bytes, estimate.max_plaintext_bytes);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_recipient_fingerprint() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other = KeyPair::generate(&mut rng).unwrap();
+    let plaintext = b"for your eyes only";
+
+    let mut header = Header::new(Encoding::QuadraticResidue);
+    header.recipient = Some(keys.public.fingerprint());
+    let mut encryptor =
+        EncryptWriter::with_header(Vec::new(), keys.public.clone(), header, &mut rng).unwrap();
+    encryptor.write_all(plaintext).unwrap();
+    let ciphertext = encryptor.finish().unwrap();
+    assert_eq!(decrypt_chunked(&ciphertext, 5, &keys), plaintext);
+
+    // the wrong key is reported before anything is decrypted
+    match DecryptReader::new(&ciphertext[..], other.private.clone()) {
+        Err(CryptError::WrongRecipient(expected, actual)) => {
+            assert_eq!(expected, keys.public.fingerprint());
+            assert_eq!(actual, other.public.fingerprint());
+        }
+        Err(error) => panic!("unexpected error: {:?}", error),
+        Ok(_) => panic!("decrypted with the wrong key"),
+    }
+
+    // anonymous ciphertexts don't name their recipient
+    let anonymous = encrypt_chunked(plaintext, 5, &keys, &mut rng);
+    assert_eq!(Header::read(&mut &anonymous[..]).unwrap().recipient, None);
+    assert_eq!(decrypt_chunked(&anonymous, 5, &keys), plaintext);
+}
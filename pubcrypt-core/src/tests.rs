@@ -0,0 +1,527 @@
+use super::*;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+// the smallest safe prime above `Block::MAX` with a primitive root of 2, which is too small for
+// `Encoding::QuadraticResidue`
+#[cfg(not(feature = "wide"))]
+const SMALL_SAFE_PRIME: Num = 4294967387;
+#[cfg(feature = "wide")]
+const SMALL_SAFE_PRIME: Num = 18446744073709562747;
+
+// a prime above `Block::MAX` that isn't a safe prime, since `(p - 1) / 2` is divisible by 5 (or by
+// 2 with the `wide` feature)
+#[cfg(not(feature = "wide"))]
+const UNSAFE_PRIME: Num = 4294967311;
+#[cfg(feature = "wide")]
+const UNSAFE_PRIME: Num = 18446744073709551629;
+
+#[test]
+fn random_test_crypt_block() {
+    const KEYS: usize = 10;
+    #[cfg(not(feature = "wide"))]
+    const BLOCKS_PER_KEY: usize = 100_000;
+    // software multiplication makes 128-bit blocks much slower to encrypt
+    #[cfg(feature = "wide")]
+    const BLOCKS_PER_KEY: usize = 1_000;
+    let mut rng = StdRng::from_entropy();
+
+    for _ in 0..KEYS {
+        let keys = KeyPair::generate(&mut rng).unwrap();
+
+        for i in 0..BLOCKS_PER_KEY {
+            let encoding = if i % 2 == 0 {
+                Encoding::QuadraticResidue
+            } else {
+                Encoding::Raw
+            };
+            let block = rng.gen_range(0..=Block::MAX);
+            let (c1, c2) = encrypt_block(block, &keys.public, encoding, &mut rng).unwrap();
+            let decrypted_block = decrypt_block(c1, c2, &keys.private, encoding).unwrap();
+
+            assert_eq!(
+                block, decrypted_block,
+                concat!(
+                    "Encryption process failed. Plaintext = 0x{:x}. ",
+                    "Ciphertext = (0x{:x}, 0x{:x}). Decrypted block = 0x{:x}."
+                ),
+                block, c1, c2, decrypted_block
+            );
+        }
+    }
+}
+
+#[test]
+fn test_key_serialization() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let public = PublicKey::deserialize(&keys.public.serialize());
+    let private = PrivateKey::deserialize(&keys.private.serialize());
+    assert_eq!(public, keys.public);
+    assert_eq!(private, keys.private);
+
+    // both halves share the same group parameters
+    assert_eq!(public.key().prime, private.key().prime);
+    assert_eq!(public.key().root, private.key().root);
+    assert_ne!(public.key().value, private.key().value);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_key_serde() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let json = serde_json::to_string(&keys).unwrap();
+    assert_eq!(serde_json::from_str::<KeyPair>(&json).unwrap(), keys);
+
+    // the key wrappers are transparent, so either half reads as a bare `Key`
+    let key = keys.public.key();
+    let json = serde_json::to_string(&keys.public).unwrap();
+    let expected = format!(
+        r#"{{"prime":{},"root":{},"value":{}}}"#,
+        key.prime, key.root, key.value
+    );
+    assert_eq!(json, expected);
+    assert_eq!(serde_json::from_str::<Key>(&json).unwrap(), *key);
+
+    assert!(serde_json::from_str::<Key>(r#"{ "prime": 23, "root": 5 }"#).is_err());
+}
+
+#[test]
+fn test_crypt_errors() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    let qr = Encoding::QuadraticResidue;
+    let (c1, c2) = encrypt_block(0x1234_5678, &keys.public, qr, &mut rng).unwrap();
+    assert!(matches!(
+        decrypt_block(c1, c2, &other_keys.private, qr),
+        Err(CryptError::WrongKey)
+    ));
+
+    // the prime must be large enough to hold a block
+    let small_key = Key {
+        prime: 11,
+        root: 2,
+        value: 3,
+    };
+    assert!(matches!(
+        encrypt_block(0, &PublicKey::from(small_key.clone()), qr, &mut rng),
+        Err(CryptError::MalformedKey)
+    ));
+    assert!(matches!(
+        decrypt_block(c1, c2, &PrivateKey::from(small_key), qr),
+        Err(CryptError::MalformedKey)
+    ));
+
+    // the private exponent must be less than the prime
+    let mut bad_exponent = keys.private.key().clone();
+    bad_exponent.value = bad_exponent.prime;
+    assert!(matches!(
+        decrypt_block(c1, c2, &PrivateKey::from(bad_exponent), qr),
+        Err(CryptError::MalformedKey)
+    ));
+}
+
+#[test]
+fn test_key_validation() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    assert_eq!(keys.public.key().validate(&mut rng), Ok(()));
+    assert_eq!(keys.private.key().validate(&mut rng), Ok(()));
+
+    let check = |prime, root, value, expected| {
+        let key = Key { prime, root, value };
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(key.validate(&mut rng), expected);
+        assert_eq!(
+            Key::try_deserialize(&key.serialize(), &mut rng).err(),
+            expected.err()
+        );
+    };
+
+    const PRIME: Num = SMALL_SAFE_PRIME;
+    check(PRIME, 2, 12345, Ok(()));
+    check(PRIME, 2, PRIME - 1, Ok(()));
+    check(23, 5, 3, Err(KeyError::PrimeTooSmall));
+    check(PRIME + 4, 2, 12345, Err(KeyError::NotSafePrime));
+
+    check(UNSAFE_PRIME, 3, 12345, Err(KeyError::NotSafePrime));
+
+    // 1 and -1 have orders 1 and 2, and squares have order `q`
+    check(PRIME, 1, 12345, Err(KeyError::InvalidRoot));
+    check(PRIME, PRIME - 1, 12345, Err(KeyError::InvalidRoot));
+    check(PRIME, 4, 12345, Err(KeyError::InvalidRoot));
+    check(PRIME, PRIME + 2, 12345, Err(KeyError::InvalidRoot));
+
+    check(PRIME, 2, 0, Err(KeyError::InvalidValue));
+    check(PRIME, 2, PRIME, Err(KeyError::InvalidValue));
+}
+
+#[test]
+fn test_key_pair_matching() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    assert!(keys.matches());
+    assert_eq!(keys.validate(&mut rng), Ok(()));
+
+    // keys from different pairs don't match, even in the same group
+    let other_keys = KeyPair::generate_in_group(&keys.public.key().group(), &mut rng);
+    let mixed = KeyPair {
+        public: keys.public.clone(),
+        private: other_keys.private.clone(),
+    };
+    assert!(!mixed.matches());
+    assert_eq!(mixed.validate(&mut rng), Err(KeyError::MismatchedPair));
+
+    let swapped = KeyPair {
+        public: PublicKey::from(keys.private.key().clone()),
+        private: PrivateKey::from(keys.public.key().clone()),
+    };
+    assert!(!swapped.matches());
+
+    // invalid parameters are reported before the mismatch
+    let mut bad_value = keys.clone();
+    bad_value.public.0.value = 0;
+    assert_eq!(bad_value.validate(&mut rng), Err(KeyError::InvalidValue));
+
+    let mut bad_exponent = keys;
+    bad_exponent.private.0.value += bad_exponent.private.0.prime;
+    assert!(!bad_exponent.matches());
+}
+
+#[test]
+fn test_key_pair_from_seed() {
+    let seed = [7_u8; SEED_BYTES];
+    let keys = KeyPair::from_seed(&seed).unwrap();
+    assert_eq!(KeyPair::from_seed(&seed).unwrap(), keys);
+    assert!(keys.matches());
+
+    let mut other_seed = seed;
+    other_seed[SEED_BYTES - 1] ^= 1;
+    assert_ne!(KeyPair::from_seed(&other_seed).unwrap(), keys);
+
+    let from_passphrase = KeyPair::from_passphrase(b"correct horse battery staple").unwrap();
+    assert_eq!(
+        KeyPair::from_passphrase(b"correct horse battery staple").unwrap(),
+        from_passphrase
+    );
+    assert_ne!(
+        KeyPair::from_passphrase(b"correct horse battery stapler").unwrap(),
+        from_passphrase
+    );
+}
+
+#[test]
+fn test_key_pair_attempts() {
+    let mut rng = StdRng::seed_from_u64(0);
+    assert_eq!(
+        KeyPair::generate_with_attempts(0, &mut rng),
+        Err(KeyGenError::PrimeNotFound { attempts: 0 })
+    );
+
+    let keys = KeyPair::generate_with_attempts(DEFAULT_MAX_ATTEMPTS, &mut rng).unwrap();
+    assert!(keys.matches());
+
+    // the attempts search the same sequence of candidates as a single search would, so the
+    // budget doesn't change which keys a seeded generator gives
+    let mut rng = StdRng::seed_from_u64(1);
+    let keys = KeyPair::generate_with_attempts(DEFAULT_MAX_ATTEMPTS, &mut rng).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    let (prime, _) = primes::pick_random_with_root(
+        PRIME_MIN,
+        PRIME_MAX,
+        primes::DEFAULT_WITNESS_COUNT,
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(keys.public.key().prime(), prime);
+}
+
+#[test]
+fn test_key_pair_threads() {
+    let generate = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        KeyPair::generate_with_threads(4, DEFAULT_MAX_ATTEMPTS, &mut rng).unwrap()
+    };
+
+    // the keys depend only on the seed, however the threads are scheduled
+    let keys = generate(0);
+    assert!(keys.matches());
+    assert_eq!(generate(0), keys);
+    assert_ne!(generate(1), keys);
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let single = KeyPair::generate_with_threads(1, DEFAULT_MAX_ATTEMPTS, &mut rng).unwrap();
+    let mut rng = StdRng::seed_from_u64(0);
+    assert_eq!(KeyPair::generate(&mut rng).unwrap(), single);
+}
+
+#[test]
+fn test_key_pair_range() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (min, max) = (PRIME_MIN, PRIME_MIN * 2 - 1);
+    let keys = KeyPair::generate_in_range(
+        min,
+        max,
+        DEFAULT_WITNESS_COUNT,
+        1,
+        DEFAULT_MAX_ATTEMPTS,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(keys.matches());
+    assert!((min..=max).contains(&keys.public.key().prime()));
+
+    // the full range with the default witness count is the same search as `generate`
+    let mut rng = StdRng::seed_from_u64(1);
+    let full = KeyPair::generate_in_range(
+        PRIME_MIN,
+        PRIME_MAX,
+        DEFAULT_WITNESS_COUNT,
+        1,
+        DEFAULT_MAX_ATTEMPTS,
+        &mut rng,
+    )
+    .unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    assert_eq!(KeyPair::generate(&mut rng).unwrap(), full);
+
+    for &(min, max) in &[(PRIME_MIN - 1, PRIME_MAX), (PRIME_MIN * 2, PRIME_MIN)] {
+        assert_eq!(
+            KeyPair::generate_in_range(min, max, DEFAULT_WITNESS_COUNT, 1, 1, &mut rng),
+            Err(KeyGenError::InvalidRange)
+        );
+    }
+}
+
+#[test]
+fn test_derive_public() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    assert_eq!(keys.private.derive_public(), keys.public);
+
+    let other_keys = KeyPair::generate_in_group(&keys.public.key().group(), &mut rng);
+    assert_ne!(other_keys.private.derive_public(), keys.public);
+}
+
+#[test]
+fn test_decrypt_out_of_range() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let prime = keys.private.key().prime;
+
+    let raw = Encoding::Raw;
+    let (c1, c2) = encrypt_block(0, &keys.public, raw, &mut rng).unwrap();
+    assert_eq!(c2, 0);
+    assert_eq!(decrypt_block(c1, c2, &keys.private, raw).unwrap(), 0);
+
+    for &(c1, c2) in &[(0, c2), (prime, c2), (c1, prime), (Num::MAX, Num::MAX)] {
+        assert!(matches!(
+            decrypt_block(c1, c2, &keys.private, raw),
+            Err(CryptError::OutOfRange)
+        ));
+    }
+}
+
+#[test]
+fn test_quadratic_residue_encoding() {
+    const ENCODING: Encoding = Encoding::QuadraticResidue;
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let prime = keys.public.key().prime;
+    let q = (prime - 1) / 2;
+
+    // every encoded block is a quadratic residue
+    let random_blocks: Vec<Block> = (0..1000).map(|_| rng.gen()).collect();
+    let blocks = (0..1000).chain(Block::MAX - 1000..=Block::MAX);
+    for block in blocks.chain(random_blocks) {
+        let encoded = ENCODING.encode(block, prime).unwrap();
+        assert_eq!(mod_exp(encoded, q, prime), 1);
+        assert_eq!(ENCODING.decode(encoded, prime).unwrap(), block);
+
+        let (c1, c2) = encrypt_block(block, &keys.public, ENCODING, &mut rng).unwrap();
+        assert_eq!(
+            decrypt_block(c1, c2, &keys.private, ENCODING).unwrap(),
+            block
+        );
+    }
+
+    // values that no block encodes to
+    assert!(matches!(
+        ENCODING.decode(0, prime),
+        Err(CryptError::WrongKey)
+    ));
+    assert!(matches!(
+        ENCODING.decode(q, prime),
+        Err(CryptError::WrongKey)
+    ));
+}
+
+#[test]
+fn test_encoding_support() {
+    // large enough to hold a block, but not to encode one as a quadratic residue
+    const SMALL_PRIME: Num = SMALL_SAFE_PRIME;
+    assert!(Encoding::Raw.supports(SMALL_PRIME));
+    assert!(!Encoding::QuadraticResidue.supports(SMALL_PRIME));
+    assert_eq!(Encoding::best_for(SMALL_PRIME), Encoding::Raw);
+    assert!(matches!(
+        Encoding::QuadraticResidue.encode(0, SMALL_PRIME),
+        Err(CryptError::MalformedKey)
+    ));
+
+    // generated keys always support quadratic residue encoding
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..10 {
+        let keys = KeyPair::generate(&mut rng).unwrap();
+        assert_eq!(
+            Encoding::best_for(keys.public.key().prime),
+            Encoding::QuadraticResidue
+        );
+    }
+}
+
+#[test]
+fn test_crypt_bytes() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    for len in (0..=BLOCK_BYTES * 2).chain(vec![100, 1000]) {
+        let mut plaintext = vec![0_u8; len];
+        rng.fill(&mut plaintext[..]);
+
+        let ciphertext = encrypt_bytes(&plaintext, &keys.public, &mut rng).unwrap();
+        assert_eq!(
+            decrypt_bytes(&ciphertext, &keys.private).unwrap(),
+            plaintext
+        );
+
+        // the same format as the byte stream functions
+        let mut decrypted = Vec::new();
+        ecb::decrypt_ecb(&mut &ciphertext[..], &mut decrypted, &keys.private).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+    let ciphertext = encrypt_bytes(b"hello", &keys.public, &mut rng).unwrap();
+    assert!(matches!(
+        decrypt_bytes(&ciphertext, &other_keys.private)
+            .unwrap_err()
+            .root_cause(),
+        CryptError::WrongKey
+    ));
+
+    let small_key = PublicKey::from(Key {
+        prime: 11,
+        root: 2,
+        value: 3,
+    });
+    assert!(matches!(
+        encrypt_bytes(b"hello", &small_key, &mut rng),
+        Err(CryptError::MalformedKey)
+    ));
+}
+
+#[test]
+fn test_crypt_blocks() {
+    const ENCODING: Encoding = Encoding::QuadraticResidue;
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let blocks: Vec<Block> = (0..100).map(|_| rng.gen()).collect();
+
+    let pairs = encrypt_blocks(blocks.iter().copied(), &keys.public, ENCODING, &mut rng)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(pairs.len(), blocks.len());
+
+    let decrypted = decrypt_blocks(pairs.iter().copied(), &keys.private, ENCODING)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(decrypted, blocks);
+
+    // errors are reported per block
+    let mut results = decrypt_blocks(vec![pairs[0], (0, 0), pairs[1]], &keys.private, ENCODING);
+    assert_eq!(results.next().unwrap().unwrap(), blocks[0]);
+    assert!(matches!(results.next(), Some(Err(CryptError::OutOfRange))));
+    assert_eq!(results.next().unwrap().unwrap(), blocks[1]);
+    assert!(results.next().is_none());
+
+    let small_key = PublicKey::from(Key {
+        prime: 11,
+        root: 2,
+        value: 3,
+    });
+    assert!(encrypt_blocks(vec![1, 2], &small_key, ENCODING, &mut rng)
+        .all(|result| matches!(result, Err(CryptError::MalformedKey))));
+}
+
+#[test]
+fn test_ct_mul() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let prime = keys.public.key().prime();
+    let x = keys.private.key().value;
+
+    for _ in 0..100 {
+        // products of half-width blocks still fit in a block, so they decode
+        let a = rng.gen_range(0..=Block::MAX >> (Block::BITS / 2));
+        let b = rng.gen_range(0..=Block::MAX >> (Block::BITS / 2));
+        let enc_a = encrypt_block(a, &keys.public, Encoding::Raw, &mut rng).unwrap();
+        let enc_b = encrypt_block(b, &keys.public, Encoding::Raw, &mut rng).unwrap();
+
+        let (c1, c2) = ct_mul(enc_a, enc_b, &keys.public);
+        let product = decrypt_block(c1, c2, &keys.private, Encoding::Raw).unwrap();
+        assert_eq!(product, a * b);
+
+        // larger products wrap around modulo the prime
+        let a = rng.gen_range(1..=Block::MAX);
+        let b = rng.gen_range(1..=Block::MAX);
+        let enc_a = encrypt_block(a, &keys.public, Encoding::Raw, &mut rng).unwrap();
+        let enc_b = encrypt_block(b, &keys.public, Encoding::Raw, &mut rng).unwrap();
+
+        let (c1, c2) = ct_mul(enc_a, enc_b, &keys.public);
+        let decrypted = mul_mod(mod_exp(c1, prime - 1 - x, prime), c2, prime);
+        assert_eq!(decrypted, mul_mod(a as Num, b as Num, prime));
+    }
+}
+
+#[test]
+fn test_blinded_decryption() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let other_keys = KeyPair::generate(&mut rng).unwrap();
+
+    // one blinding can be reused, and a fresh one works just as well
+    let mut blinding = Blinding::new(&keys.private, &mut rng);
+    for i in 0..1000 {
+        let encoding = if i % 2 == 0 {
+            Encoding::QuadraticResidue
+        } else {
+            Encoding::Raw
+        };
+        let block = rng.gen();
+        let (c1, c2) = encrypt_block(block, &keys.public, encoding, &mut rng).unwrap();
+
+        let decrypted = decrypt_block_blinded(c1, c2, &keys.private, encoding, &mut blinding);
+        assert_eq!(decrypted.unwrap(), block);
+
+        let mut fresh = Blinding::new(&keys.private, &mut rng);
+        let decrypted = decrypt_block_blinded(c1, c2, &keys.private, encoding, &mut fresh);
+        assert_eq!(decrypted.unwrap(), block);
+    }
+
+    // the blinding must belong to the key, and ciphertexts are still checked
+    let (c1, c2) = encrypt_block(1, &keys.public, Encoding::Raw, &mut rng).unwrap();
+    let mut other_blinding = Blinding::new(&other_keys.private, &mut rng);
+    assert!(matches!(
+        decrypt_block_blinded(c1, c2, &keys.private, Encoding::Raw, &mut other_blinding),
+        Err(CryptError::IncompatibleKeys)
+    ));
+    assert!(matches!(
+        decrypt_block_blinded(0, c2, &keys.private, Encoding::Raw, &mut blinding),
+        Err(CryptError::OutOfRange)
+    ));
+}
@@ -0,0 +1,83 @@
+/*!
+ * A plain-text key format, for ElGamal keys that are dictated, printed, or compared with `diff`.
+ *
+ * A text key is a tag naming its kind, `PUBLIC_TAG` or `PRIVATE_TAG`, followed by the key's prime,
+ * root, and value, all separated by whitespace. `Key::to_text` writes the tag on a line of its own
+ * and the numbers in decimal on the next. `Key::from_text` also reads numbers in hexadecimal with
+ * a `0x` prefix, and any whitespace between words; a `#` starts a comment that runs to the end of
+ * its line.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{KeyKind, Num};
+
+/**
+ * The tag that starts a public text key.
+ */
+pub const PUBLIC_TAG: &str = "elgamal-public";
+
+/**
+ * The tag that starts a private text key.
+ */
+pub const PRIVATE_TAG: &str = "elgamal-private";
+
+/**
+ * Get the tag that starts a text key of the given kind.
+ */
+pub fn tag(kind: KeyKind) -> &'static str {
+    match kind {
+        KeyKind::Public => PUBLIC_TAG,
+        KeyKind::Private => PRIVATE_TAG,
+    }
+}
+
+/**
+ * Get the kind of key that `tag` starts, if it's a tag at all.
+ */
+pub fn kind_from_tag(tag: &str) -> Option<KeyKind> {
+    match tag {
+        PUBLIC_TAG => Some(KeyKind::Public),
+        PRIVATE_TAG => Some(KeyKind::Private),
+        _ => None,
+    }
+}
+
+/**
+ * Split `text` into its whitespace-separated words, leaving out comments.
+ */
+pub fn parse_words(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(str::split_whitespace)
+}
+
+/**
+ * Parse a word as a number in decimal, or in hexadecimal with a `0x` prefix. Returns `None` if
+ * the word isn't a number in either form, or the number doesn't fit in a `Num`.
+ */
+pub fn parse_number(word: &str) -> Option<Num> {
+    let (digits, radix) = match word.strip_prefix("0x") {
+        Some(digits) => (digits, 16),
+        None => (word, 10),
+    };
+
+    // `Num::from_str_radix` also accepts a leading `+`, which isn't part of the format
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    Num::from_str_radix(digits, radix).ok()
+}
+
+/**
+ * Check whether `bytes` looks like a text key, i.e., its first word is a tag. The rest of the
+ * key is checked by `Key::from_text`.
+ */
+pub fn is_text_key(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| parse_words(text).next())
+        .and_then(kind_from_tag)
+        .is_some()
+}
@@ -0,0 +1,87 @@
+use super::*;
+use crate::{Key, KeyError, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_key_text() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    let text = keys.public.key().to_text(KeyKind::Public);
+    assert!(text.starts_with("elgamal-public\n"));
+    assert!(is_text_key(text.as_bytes()));
+    assert_eq!(
+        Key::from_text(&text),
+        Ok((keys.public.key().clone(), KeyKind::Public))
+    );
+
+    let text = keys.private.key().to_text(KeyKind::Private);
+    assert_eq!(
+        Key::from_text(&text),
+        Ok((keys.private.key().clone(), KeyKind::Private))
+    );
+
+    let key = Key {
+        prime: Num::MAX,
+        root: 2,
+        value: Num::MAX - 1,
+    };
+    let text = key.to_text(KeyKind::Public);
+    assert_eq!(Key::from_text(&text), Ok((key, KeyKind::Public)));
+}
+
+#[test]
+fn test_key_text_layout() {
+    let expected = Key {
+        prime: 23,
+        root: 5,
+        value: 8,
+    };
+    let texts = [
+        "elgamal-public 23 5 8",
+        "elgamal-public\n0x17 0x5 0x8\n",
+        "  # Alice's key\nelgamal-public # ElGamal\n\t23\n5 # the root\n8",
+    ];
+    for text in texts {
+        assert_eq!(
+            Key::from_text(text),
+            Ok((expected.clone(), KeyKind::Public)),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn test_invalid_text() {
+    let invalid = [
+        "",
+        "elgamal-public",
+        "elgamal-public 23 5",
+        "elgamal-public 23 5 8 9",
+        "elgamal-secret 23 5 8",
+        "23 5 8 elgamal-public",
+        "elgamal-public +23 5 8",
+        "elgamal-public -23 5 8",
+        "elgamal-public 0x 5 8",
+        "elgamal-public 0X17 5 8",
+        "elgamal-public 0x+17 5 8",
+        "elgamal-public 23.0 5 8",
+    ];
+    for text in invalid {
+        assert_eq!(Key::from_text(text), Err(KeyError::InvalidText), "{}", text);
+    }
+
+    let too_large = format!("elgamal-public {}0 5 8", Num::MAX);
+    assert_eq!(Key::from_text(&too_large), Err(KeyError::InvalidText));
+
+    assert!(!is_text_key(b"{\"kind\": \"public\"}"));
+    assert!(!is_text_key(&[0xff, 0xfe]));
+}
+
+#[test]
+fn test_parse_words() {
+    let words: Vec<_> = parse_words("a b\n\n  c#d e\n# f\ng\t h").collect();
+    assert_eq!(words, ["a", "b", "c", "g", "h"]);
+}
@@ -0,0 +1,359 @@
+/*!
+ * Threshold decryption, by splitting a private key into Shamir shares.
+ *
+ * A private exponent `x` is split into `n` shares such that any `t` of them can decrypt, but fewer
+ * reveal nothing about the key. The shares are points on a random polynomial of degree `t - 1`
+ * over the integers modulo the subgroup order `q`, where `p = 2q + 1`, with `x mod q` as its
+ * constant term.
+ *
+ * To decrypt a ciphertext pair `(c1, c2)`, each shareholder computes a `PartialDecryption`
+ * `c1^(2 * s_i)` from their share `s_i`, and `combine` interpolates `t` of these in the exponent
+ * to get `c1^(2x)` without ever learning `x`. Since `c1` may lie outside the subgroup of order `q`,
+ * `c1^x` is then recovered as `(c1^(2x))^((q + 1) / 2) * (c1^q)^(x mod 2)`, where the parity of
+ * `x` is public anyway: it's whether the public key's value is a quadratic residue.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::{Block, CryptError, Encoding, Key, Num, PrivateKey, PublicKey, NUM_BYTES};
+use math::{mod_exp, modint::ModInt};
+use rand::Rng;
+
+/**
+ * The number of bytes in a serialized `KeyShare`.
+ */
+pub const SHARE_BYTES: usize = Key::KEY_BYTES + NUM_BYTES + 2;
+
+/**
+ * The number of bytes in a serialized `PartialDecryption`.
+ */
+pub const PARTIAL_BYTES: usize = NUM_BYTES + 2;
+
+/**
+ * The most shares a key can be split into, since share indices are stored in one byte.
+ */
+pub const MAX_SHARES: usize = u8::MAX as usize;
+
+/**
+ * One share of a private key, along with the public key it belongs to.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyShare {
+    public: Key,
+    index: u8,
+    threshold: u8,
+    value: Num,
+}
+
+impl KeyShare {
+    /**
+     * Get the share's index, from 1 to the number of shares.
+     */
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /**
+     * Get the number of shares needed to decrypt.
+     */
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /**
+     * Get the public key that the share's private key belongs to.
+     */
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from(self.public.clone())
+    }
+
+    /**
+     * Compute this share's part of the decryption of a ciphertext pair whose first component is
+     * `c1`.
+     *
+     * Returns `CryptError::OutOfRange` if `c1` couldn't have been produced by encryption.
+     */
+    pub fn partial_decrypt(&self, c1: Num) -> Result<PartialDecryption, CryptError> {
+        let prime = self.public.prime;
+        if c1 == 0 || c1 >= prime {
+            return Err(CryptError::OutOfRange);
+        }
+
+        // squaring moves `c1` into the subgroup of order `q`, where the share is meaningful
+        let squared = ModInt::new(c1, prime).pow(2);
+        Ok(PartialDecryption {
+            index: self.index,
+            threshold: self.threshold,
+            value: squared.pow(self.value).value(),
+        })
+    }
+
+    /**
+     * Convert the share to bytes that can be saved to the disk.
+     */
+    pub fn serialize(&self) -> [u8; SHARE_BYTES] {
+        let mut result = [0_u8; SHARE_BYTES];
+        result[..Key::KEY_BYTES].copy_from_slice(&self.public.serialize());
+        result[Key::KEY_BYTES..Key::KEY_BYTES + NUM_BYTES]
+            .copy_from_slice(&self.value.to_be_bytes());
+        result[SHARE_BYTES - 2] = self.index;
+        result[SHARE_BYTES - 1] = self.threshold;
+        result
+    }
+
+    /**
+     * Read a share from serialized bytes. The values are checked when the share is used.
+     */
+    pub fn deserialize(bytes: &[u8; SHARE_BYTES]) -> Self {
+        let mut key_buf = [0_u8; Key::KEY_BYTES];
+        let mut value_buf = [0_u8; NUM_BYTES];
+        key_buf.copy_from_slice(&bytes[..Key::KEY_BYTES]);
+        value_buf.copy_from_slice(&bytes[Key::KEY_BYTES..Key::KEY_BYTES + NUM_BYTES]);
+
+        Self {
+            public: Key::deserialize(&key_buf),
+            index: bytes[SHARE_BYTES - 2],
+            threshold: bytes[SHARE_BYTES - 1],
+            value: Num::from_be_bytes(value_buf),
+        }
+    }
+}
+
+/**
+ * One shareholder's part of the decryption of a ciphertext pair.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PartialDecryption {
+    index: u8,
+    threshold: u8,
+    value: Num,
+}
+
+impl PartialDecryption {
+    /**
+     * Get the index of the share that produced this partial decryption.
+     */
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /**
+     * Convert the partial decryption to bytes that can be sent to whoever combines them.
+     */
+    pub fn serialize(&self) -> [u8; PARTIAL_BYTES] {
+        let mut result = [0_u8; PARTIAL_BYTES];
+        result[..NUM_BYTES].copy_from_slice(&self.value.to_be_bytes());
+        result[NUM_BYTES] = self.index;
+        result[NUM_BYTES + 1] = self.threshold;
+        result
+    }
+
+    /**
+     * Read a partial decryption from serialized bytes. The values are checked when it's combined.
+     */
+    pub fn deserialize(bytes: &[u8; PARTIAL_BYTES]) -> Self {
+        let mut value_buf = [0_u8; NUM_BYTES];
+        value_buf.copy_from_slice(&bytes[..NUM_BYTES]);
+
+        Self {
+            index: bytes[NUM_BYTES],
+            threshold: bytes[NUM_BYTES + 1],
+            value: Num::from_be_bytes(value_buf),
+        }
+    }
+}
+
+/**
+ * Split `key` into `shares` shares, any `threshold` of which can decrypt. The polynomial's
+ * coefficients are generated by `rng`.
+ *
+ * Returns `CryptError::InvalidShares` if `threshold` is 0 or greater than `shares`, or if `shares`
+ * is greater than `MAX_SHARES`.
+ */
+pub fn split_key<T: Rng>(
+    key: &PrivateKey,
+    threshold: usize,
+    shares: usize,
+    rng: &mut T,
+) -> Result<Vec<KeyShare>, CryptError> {
+    if threshold == 0 || threshold > shares || shares > MAX_SHARES {
+        return Err(CryptError::InvalidShares);
+    }
+
+    let key = key.key();
+    let order = (key.prime - 1) / 2;
+    let public = Key {
+        value: mod_exp(key.root, key.value, key.prime),
+        ..key.clone()
+    };
+
+    let mut coefficients = vec![ModInt::new(key.value, order)];
+    coefficients.extend((1..threshold).map(|_| ModInt::new(rng.gen_range(0..order), order)));
+
+    let result = (1..=shares)
+        .map(|index| {
+            // Horner's method, from the highest coefficient down
+            let x = ModInt::new(index as Num, order);
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(ModInt::new(0, order), |acc, &c| acc * x + c);
+
+            KeyShare {
+                public: public.clone(),
+                index: index as u8,
+                threshold: threshold as u8,
+                value: value.value(),
+            }
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/**
+ * Get the Lagrange coefficients for interpolating the polynomial through the given indices at 0,
+ * modulo `order`.
+ *
+ * Returns `CryptError::InvalidShares` if the indices aren't distinct and nonzero.
+ */
+fn lagrange_coefficients(indices: &[u8], order: Num) -> Result<Vec<ModInt>, CryptError> {
+    indices
+        .iter()
+        .enumerate()
+        .map(|(j, &index)| {
+            let mut numerator = ModInt::new(1, order);
+            let mut denominator = ModInt::new(1, order);
+
+            for (m, &other) in indices.iter().enumerate() {
+                if m != j {
+                    let other = ModInt::new(other as Num, order);
+                    numerator = numerator * other;
+                    denominator = denominator * (other - ModInt::new(index as Num, order));
+                }
+            }
+
+            let inverse = denominator.inverse().ok_or(CryptError::InvalidShares)?;
+            Ok(numerator * inverse)
+        })
+        .collect()
+}
+
+/**
+ * Check that there are enough partial decryptions or shares with a consistent threshold, and
+ * return the first `threshold` of their indices.
+ */
+fn threshold_indices<I>(thresholds_and_indices: I) -> Result<Vec<u8>, CryptError>
+where
+    I: IntoIterator<Item = (u8, u8)>,
+{
+    let pairs: Vec<_> = thresholds_and_indices.into_iter().collect();
+    let threshold = match pairs.first() {
+        Some(&(threshold, _)) if threshold > 0 => threshold as usize,
+        _ => return Err(CryptError::InvalidShares),
+    };
+
+    if pairs.len() < threshold || pairs.iter().any(|&(t, _)| t as usize != threshold) {
+        return Err(CryptError::InvalidShares);
+    }
+
+    let indices: Vec<_> = pairs[..threshold].iter().map(|&(_, index)| index).collect();
+    if indices.contains(&0) {
+        return Err(CryptError::InvalidShares);
+    }
+
+    Ok(indices)
+}
+
+/**
+ * Decrypt the ciphertext pair `(c1, c2)` from partial decryptions of `c1`, reversing `encoding`
+ * as in `decrypt_block`. Only the first `threshold` partial decryptions are used.
+ *
+ * Returns `CryptError::InvalidShares` if there are too few partial decryptions, or if their
+ * thresholds or indices are inconsistent, and otherwise fails as `decrypt_block` does.
+ */
+pub fn combine(
+    c1: Num,
+    c2: Num,
+    partials: &[PartialDecryption],
+    key: &PublicKey,
+    encoding: Encoding,
+) -> Result<Block, CryptError> {
+    let key = key.key();
+    if !encoding.supports(key.prime) {
+        return Err(CryptError::MalformedKey);
+    }
+
+    if c1 == 0 || c1 >= key.prime || c2 >= key.prime {
+        return Err(CryptError::OutOfRange);
+    }
+
+    let indices = threshold_indices(partials.iter().map(|p| (p.threshold, p.index)))?;
+    let order = (key.prime - 1) / 2;
+    let coefficients = lagrange_coefficients(&indices, order)?;
+
+    // `c1^(2x)`, interpolated in the exponent
+    let doubled = partials
+        .iter()
+        .zip(&coefficients)
+        .fold(ModInt::new(1, key.prime), |acc, (partial, lambda)| {
+            acc * ModInt::new(partial.value, key.prime).pow(lambda.value())
+        });
+
+    // halve the exponent, then correct for `c1`'s component outside the subgroup if `x` is odd
+    let c1 = ModInt::new(c1, key.prime);
+    let mut shared = doubled.pow(order.div_ceil(2));
+    let x_is_odd = mod_exp(key.value, order, key.prime) != 1;
+    if x_is_odd {
+        shared = shared * c1.pow(order);
+    }
+
+    let inverse = shared.inverse().ok_or(CryptError::InvalidShares)?;
+    let message = inverse * ModInt::new(c2, key.prime);
+    encoding.decode(message.value(), key.prime)
+}
+
+/**
+ * Reconstruct the private key from at least `threshold` shares. This defeats the purpose of
+ * splitting the key, so it's only meant for recovering it, e.g., to retire the shares.
+ *
+ * Returns `CryptError::InvalidShares` if there are too few shares, if their thresholds or indices
+ * are inconsistent, or if they don't interpolate to the private key, and
+ * `CryptError::IncompatibleKeys` if they belong to different keys.
+ */
+pub fn combine_shares(shares: &[KeyShare]) -> Result<PrivateKey, CryptError> {
+    let indices = threshold_indices(shares.iter().map(|s| (s.threshold, s.index)))?;
+    let public = &shares[0].public;
+    if shares.iter().any(|share| share.public != *public) {
+        return Err(CryptError::IncompatibleKeys);
+    }
+
+    let order = (public.prime - 1) / 2;
+    let coefficients = lagrange_coefficients(&indices, order)?;
+    let reduced = shares
+        .iter()
+        .zip(&coefficients)
+        .fold(ModInt::new(0, order), |acc, (share, &lambda)| {
+            acc + ModInt::new(share.value, order) * lambda
+        })
+        .value();
+
+    // the shares only determine `x mod q`, and the public key's value gives its parity
+    let x_is_odd = mod_exp(public.value, order, public.prime) != 1;
+    let value = if (reduced % 2 == 1) == x_is_odd {
+        reduced
+    } else {
+        reduced + order
+    };
+
+    // shares that were corrupted or tampered with interpolate to some other exponent
+    if mod_exp(public.root, value, public.prime) != public.value {
+        return Err(CryptError::InvalidShares);
+    }
+
+    Ok(PrivateKey::from(Key {
+        value,
+        ..public.clone()
+    }))
+}
@@ -0,0 +1,134 @@
+use super::*;
+use crate::{decrypt_block, encrypt_block, KeyPair};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_threshold_decryption() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..4 {
+        let keys = KeyPair::generate(&mut rng).unwrap();
+        let shares = split_key(&keys.private, 3, 5, &mut rng).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|s| s.public_key() == keys.public));
+
+        for _ in 0..20 {
+            let block = rng.gen();
+            let encoding = Encoding::QuadraticResidue;
+            let (c1, c2) = encrypt_block(block, &keys.public, encoding, &mut rng).unwrap();
+
+            // any three shares decrypt, in any order
+            for chosen in &[[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+                let partials: Vec<_> = chosen
+                    .iter()
+                    .map(|&i| shares[i].partial_decrypt(c1).unwrap())
+                    .collect();
+                let decrypted = combine(c1, c2, &partials, &keys.public, encoding).unwrap();
+                assert_eq!(decrypted, block);
+            }
+
+            // two aren't enough
+            let partials: Vec<_> = shares[..2]
+                .iter()
+                .map(|s| s.partial_decrypt(c1).unwrap())
+                .collect();
+            assert!(matches!(
+                combine(c1, c2, &partials, &keys.public, encoding),
+                Err(CryptError::InvalidShares)
+            ));
+
+            assert_eq!(
+                decrypt_block(c1, c2, &keys.private, encoding).unwrap(),
+                block
+            );
+        }
+    }
+}
+
+#[test]
+fn test_combine_shares() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..10 {
+        let keys = KeyPair::generate(&mut rng).unwrap();
+        let shares = split_key(&keys.private, 2, 3, &mut rng).unwrap();
+
+        assert_eq!(combine_shares(&shares[..2]).unwrap(), keys.private);
+        assert_eq!(combine_shares(&shares[1..]).unwrap(), keys.private);
+        assert!(matches!(
+            combine_shares(&shares[..1]),
+            Err(CryptError::InvalidShares)
+        ));
+
+        // a tampered share no longer interpolates to the key
+        let mut tampered = shares[0].clone();
+        tampered.value ^= 1;
+        assert!(matches!(
+            combine_shares(&[tampered, shares[1].clone()]),
+            Err(CryptError::InvalidShares)
+        ));
+
+        // shares of different keys can't be mixed
+        let other_keys = KeyPair::generate_in_group(&keys.public.key().group(), &mut rng);
+        let other_shares = split_key(&other_keys.private, 2, 3, &mut rng).unwrap();
+        assert!(matches!(
+            combine_shares(&[shares[0].clone(), other_shares[1].clone()]),
+            Err(CryptError::IncompatibleKeys)
+        ));
+    }
+}
+
+#[test]
+fn test_invalid_shares() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+
+    for &(threshold, shares) in &[(0, 3), (4, 3), (2, MAX_SHARES + 1)] {
+        assert!(matches!(
+            split_key(&keys.private, threshold, shares, &mut rng),
+            Err(CryptError::InvalidShares)
+        ));
+    }
+
+    // duplicate shares don't count towards the threshold
+    let shares = split_key(&keys.private, 2, 2, &mut rng).unwrap();
+    let duplicated = [shares[0].clone(), shares[0].clone()];
+    assert!(matches!(
+        combine_shares(&duplicated),
+        Err(CryptError::InvalidShares)
+    ));
+
+    let (c1, c2) = encrypt_block(1, &keys.public, Encoding::Raw, &mut rng).unwrap();
+    let partial = shares[0].partial_decrypt(c1).unwrap();
+    assert!(matches!(
+        combine(c1, c2, &[partial, partial], &keys.public, Encoding::Raw),
+        Err(CryptError::InvalidShares)
+    ));
+    assert!(matches!(
+        shares[0].partial_decrypt(0),
+        Err(CryptError::OutOfRange)
+    ));
+}
+
+#[test]
+fn test_share_serialization() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let keys = KeyPair::generate(&mut rng).unwrap();
+    let shares = split_key(&keys.private, 2, 3, &mut rng).unwrap();
+    let (c1, c2) = encrypt_block(42, &keys.public, Encoding::Raw, &mut rng).unwrap();
+
+    let partials: Vec<_> = shares
+        .iter()
+        .map(|share| {
+            let share = KeyShare::deserialize(&share.serialize());
+            let partial = share.partial_decrypt(c1).unwrap();
+            PartialDecryption::deserialize(&partial.serialize())
+        })
+        .collect();
+
+    assert_eq!(
+        combine(c1, c2, &partials, &keys.public, Encoding::Raw).unwrap(),
+        42
+    );
+}
@@ -0,0 +1,288 @@
+/*!
+ * Volumes, which split a ciphertext across a sequence of files that are each no bigger than a
+ * given size, for moving large ciphertexts through channels with size limits.
+ *
+ * Each volume starts with a continuation header: the magic bytes `PUBCRVOL`, the version byte
+ * `VERSION`, a random 16-byte identifier shared by every volume of the same ciphertext, the
+ * big-endian four-byte index of the volume (starting from 0), and the big-endian eight-byte size
+ * of every volume. The next part of the ciphertext follows. Every volume but the last is exactly
+ * the recorded size, so the last one is the first that's shorter; when the ciphertext happens to
+ * end with a full volume, it's followed by one with nothing but a header.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use crate::CryptError;
+use std::{
+    cmp,
+    convert::TryInto,
+    io::{self, Read, Write},
+};
+
+/**
+ * The bytes that every volume starts with.
+ */
+pub const MAGIC: [u8; 8] = *b"PUBCRVOL";
+
+/**
+ * The version of the volume format written by `VolumeWriter`.
+ */
+pub const VERSION: u8 = 1;
+
+/**
+ * The number of bytes in a volume's identifier.
+ */
+pub const ID_BYTES: usize = 16;
+
+/**
+ * The number of bytes in the header at the start of each volume. Volumes must be bigger than
+ * this to hold any of the ciphertext.
+ */
+pub const HEADER_BYTES: usize = MAGIC.len() + 1 + ID_BYTES + INDEX_BYTES + SIZE_BYTES;
+
+const INDEX_BYTES: usize = 4;
+const SIZE_BYTES: usize = 8;
+
+/**
+ * The continuation header at the start of a volume.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct VolumeHeader {
+    id: [u8; ID_BYTES],
+    index: u32,
+    volume_bytes: u64,
+}
+
+impl VolumeHeader {
+    fn to_bytes(self) -> Vec<u8> {
+        let mut result = MAGIC.to_vec();
+        result.push(VERSION);
+        result.extend_from_slice(&self.id);
+        result.extend_from_slice(&self.index.to_be_bytes());
+        result.extend_from_slice(&self.volume_bytes.to_be_bytes());
+        result
+    }
+
+    /**
+     * Read a header, returning `CryptError::InvalidVolume` if it's truncated, doesn't start with
+     * `MAGIC`, or is from another version.
+     */
+    fn read<R: Read>(reader: &mut R) -> Result<Self, CryptError> {
+        let mut bytes = [0_u8; HEADER_BYTES];
+        reader.read_exact(&mut bytes).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => CryptError::InvalidVolume,
+            _ => CryptError::from(e),
+        })?;
+        if !is_volume(&bytes) || bytes[MAGIC.len()] != VERSION {
+            return Err(CryptError::InvalidVolume);
+        }
+
+        let (id, rest) = bytes[MAGIC.len() + 1..].split_at(ID_BYTES);
+        let (index, volume_bytes) = rest.split_at(INDEX_BYTES);
+        Ok(Self {
+            id: id.try_into().unwrap(),
+            index: u32::from_be_bytes(index.try_into().unwrap()),
+            volume_bytes: u64::from_be_bytes(volume_bytes.try_into().unwrap()),
+        })
+    }
+}
+
+/**
+ * A writer that splits everything written to it into volumes of a fixed size, opening each volume
+ * as it's needed.
+ */
+pub struct VolumeWriter<W: Write, F: FnMut(u32) -> io::Result<W>> {
+    open: F,
+    current: W,
+    header: VolumeHeader,
+    // the number of bytes in the current volume, including its header
+    written: u64,
+}
+
+impl<W: Write, F: FnMut(u32) -> io::Result<W>> VolumeWriter<W, F> {
+    /**
+     * Create a writer for volumes of `volume_bytes` bytes each, identified by `id`, which should
+     * be random. `open` is called with the index of each volume, starting from 0, to create the
+     * writer for it. The first volume is created immediately, and each later one as soon as the
+     * previous one is full.
+     *
+     * Returns an error of kind `io::ErrorKind::InvalidInput` if the volumes aren't bigger than
+     * `HEADER_BYTES`, or any error from opening or writing the first volume.
+     */
+    pub fn new(volume_bytes: u64, id: [u8; ID_BYTES], mut open: F) -> io::Result<Self> {
+        if volume_bytes <= HEADER_BYTES as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "volumes are too small to hold their headers",
+            ));
+        }
+
+        let header = VolumeHeader {
+            id,
+            index: 0,
+            volume_bytes,
+        };
+        let mut current = open(0)?;
+        current.write_all(&header.to_bytes())?;
+
+        Ok(Self {
+            open,
+            current,
+            header,
+            written: HEADER_BYTES as u64,
+        })
+    }
+
+    /**
+     * Get the number of volumes that have been created so far.
+     */
+    pub fn volume_count(&self) -> u32 {
+        self.header.index + 1
+    }
+
+    /**
+     * Flush the current volume, and create the next one.
+     */
+    fn next_volume(&mut self) -> io::Result<()> {
+        self.current.flush()?;
+        self.header.index = self.header.index.checked_add(1).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "there are too many volumes")
+        })?;
+
+        self.current = (self.open)(self.header.index)?;
+        self.current.write_all(&self.header.to_bytes())?;
+        self.written = HEADER_BYTES as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write, F: FnMut(u32) -> io::Result<W>> Write for VolumeWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let room = self.header.volume_bytes - self.written;
+        let count = cmp::min(buf.len() as u64, room) as usize;
+        self.current.write_all(&buf[..count])?;
+        self.written += count as u64;
+
+        // the next volume is created right away, so a full volume is never the last one
+        if self.written == self.header.volume_bytes {
+            self.next_volume()?;
+        }
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.current.flush()
+    }
+}
+
+/**
+ * A reader that reassembles a ciphertext from the volumes written by `VolumeWriter`.
+ */
+pub struct VolumeReader<R: Read, F: FnMut(u32) -> io::Result<R>> {
+    open: F,
+    current: R,
+    header: VolumeHeader,
+    // the number of bytes read from the current volume, including its header
+    read: u64,
+    finished: bool,
+}
+
+impl<R: Read, F: FnMut(u32) -> io::Result<R>> VolumeReader<R, F> {
+    /**
+     * Create a reader that starts with the volume `first`, whose header is read immediately.
+     * `open` is called with the index of each later volume to open it, and must fail with an
+     * error of kind `io::ErrorKind::NotFound` if there's no such volume.
+     *
+     * Returns `CryptError::InvalidVolume` if `first` doesn't start with a valid header, or isn't
+     * the first volume.
+     *
+     * Reading fails with `CryptError::MissingVolume` if a volume can't be found, and
+     * `CryptError::InvalidVolume` if one is out of order, belongs to another ciphertext, is too
+     * big, or is followed by another volume even though it's short enough to be the last.
+     */
+    pub fn new(mut first: R, open: F) -> Result<Self, CryptError> {
+        let header = VolumeHeader::read(&mut first)?;
+        if header.index != 0 || header.volume_bytes <= HEADER_BYTES as u64 {
+            return Err(CryptError::InvalidVolume);
+        }
+
+        Ok(Self {
+            open,
+            current: first,
+            header,
+            read: HEADER_BYTES as u64,
+            finished: false,
+        })
+    }
+
+    /**
+     * Open the volume after the current one, or return `None` if there isn't one.
+     */
+    fn open_next(&mut self) -> Result<Option<R>, CryptError> {
+        let index = self
+            .header
+            .index
+            .checked_add(1)
+            .ok_or(CryptError::InvalidVolume)?;
+        match (self.open)(index) {
+            Ok(next) => Ok(Some(next)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(CryptError::from(e)),
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(u32) -> io::Result<R>> Read for VolumeReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.finished && !buf.is_empty() {
+            let count = self.current.read(buf)?;
+            if count > 0 {
+                self.read += count as u64;
+                if self.read > self.header.volume_bytes {
+                    return Err(CryptError::InvalidVolume.into());
+                }
+                return Ok(count);
+            }
+
+            // a short volume is the last one, so nothing may come after it
+            let next = self.open_next()?;
+            if self.read < self.header.volume_bytes {
+                if next.is_some() {
+                    return Err(CryptError::InvalidVolume.into());
+                }
+                self.finished = true;
+                break;
+            }
+
+            let number = self.header.index as u64 + 2;
+            let mut next = next.ok_or(CryptError::MissingVolume(number))?;
+            let header = VolumeHeader::read(&mut next)?;
+            let expected = VolumeHeader {
+                index: self.header.index + 1,
+                ..self.header
+            };
+            if header != expected {
+                return Err(CryptError::InvalidVolume.into());
+            }
+
+            self.current = next;
+            self.header = header;
+            self.read = HEADER_BYTES as u64;
+        }
+
+        Ok(0)
+    }
+}
+
+/**
+ * Check whether `bytes` starts like a volume.
+ */
+pub fn is_volume(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
@@ -0,0 +1,115 @@
+use super::*;
+
+use std::{cell::RefCell, io::Cursor, rc::Rc};
+
+const ID: [u8; ID_BYTES] = [0x42; ID_BYTES];
+
+/**
+ * A volume written in memory, which stays readable after the writer is done with it.
+ */
+#[derive(Clone, Default)]
+struct SharedVolume(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedVolume {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/**
+ * Split `data` into volumes of `volume_bytes` bytes.
+ */
+fn split(data: &[u8], volume_bytes: u64) -> Vec<Vec<u8>> {
+    let mut volumes: Vec<SharedVolume> = Vec::new();
+    let mut writer = VolumeWriter::new(volume_bytes, ID, |index| {
+        assert_eq!(index as usize, volumes.len());
+        volumes.push(SharedVolume::default());
+        Ok(volumes.last().unwrap().clone())
+    })
+    .unwrap();
+    writer.write_all(data).unwrap();
+    writer.flush().unwrap();
+    let count = writer.volume_count();
+    drop(writer);
+
+    assert_eq!(count as usize, volumes.len());
+    volumes
+        .iter()
+        .map(|volume| volume.0.borrow().clone())
+        .collect()
+}
+
+/**
+ * Reassemble the data in `volumes`.
+ */
+fn join(volumes: &[Vec<u8>]) -> Result<Vec<u8>, CryptError> {
+    let open = |index: u32| match volumes.get(index as usize) {
+        Some(volume) => Ok(Cursor::new(volume.clone())),
+        None => Err(io::Error::from(io::ErrorKind::NotFound)),
+    };
+    let mut reader = VolumeReader::new(Cursor::new(volumes[0].clone()), open)?;
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[test]
+fn test_round_trip() {
+    let volume_bytes = HEADER_BYTES as u64 + 10;
+    for len in [0, 1, 9, 10, 11, 20, 55] {
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let volumes = split(&data, volume_bytes);
+        assert_eq!(volumes.len(), len / 10 + 1);
+        assert!(volumes.iter().all(|volume| is_volume(volume)));
+        assert!(volumes
+            .iter()
+            .all(|volume| volume.len() as u64 <= volume_bytes));
+        assert_eq!(join(&volumes).unwrap(), data);
+    }
+
+    assert_eq!(
+        VolumeWriter::new(HEADER_BYTES as u64, ID, |_| Ok(Vec::new()))
+            .err()
+            .unwrap()
+            .kind(),
+        io::ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn test_invalid_volumes() {
+    let data = [7_u8; 35];
+    let volumes = split(&data, HEADER_BYTES as u64 + 10);
+    assert_eq!(volumes.len(), 4);
+
+    let check = |volumes: &[Vec<u8>], is_expected: fn(&CryptError) -> bool| {
+        let error = join(volumes).unwrap_err();
+        assert!(is_expected(&error), "{:?}", error);
+    };
+
+    // a missing volume in the middle or at the end
+    let missing = [volumes[0].clone(), volumes[2].clone(), volumes[3].clone()];
+    check(&missing, |e| matches!(e, CryptError::InvalidVolume));
+    check(&volumes[..2], |e| matches!(e, CryptError::MissingVolume(3)));
+
+    // a truncated volume before the last one
+    let mut truncated = volumes.clone();
+    truncated[1].pop();
+    check(&truncated, |e| matches!(e, CryptError::InvalidVolume));
+
+    // a volume from another ciphertext
+    let mut other = volumes.clone();
+    other[2][MAGIC.len() + 1] ^= 1;
+    check(&other, |e| matches!(e, CryptError::InvalidVolume));
+
+    // starting anywhere but the first volume
+    check(&volumes[1..], |e| matches!(e, CryptError::InvalidVolume));
+
+    let mut corrupt = volumes;
+    corrupt[0][0] ^= 1;
+    check(&corrupt, |e| matches!(e, CryptError::InvalidVolume));
+}
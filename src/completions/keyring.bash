@@ -0,0 +1,43 @@
+
+# complete the names of keys in the keyring, after the options that read a key and for
+# `key remove` and `key export`, and leave everything else to `_pubcrypt`
+_pubcrypt_keyring() {
+    local line="${COMP_LINE:0:COMP_POINT}"
+    local word="${line##*[[:space:]]}"
+    local before="${line%"$word"}"
+    before="${before%"${before##*[![:space:]]}"}"
+    local prev="${before##*[[:space:]]}"
+    local names
+
+    case "$prev" in
+        -k|--key|--priv|--peer|--old-priv|--new-pub)
+            # `genkey --priv` and `restore --priv` write a key instead of reading one
+            if [[ "$prev" == --priv && ( "$line" == *" genkey "* || "$line" == *" restore "* ) ]]; then
+                _pubcrypt
+                return
+            fi
+            names=$(pubcrypt key list 2>/dev/null | cut -f1 | sed 's/^/name:/')
+            COMPREPLY=($(compgen -W "$names" -- "$word") $(compgen -f -- "$word"))
+            ;;
+        remove|export)
+            if [[ "$line" != *" key "* ]]; then
+                _pubcrypt
+                return
+            fi
+            names=$(pubcrypt key list 2>/dev/null | cut -f1)
+            COMPREPLY=($(compgen -W "$names" -- "$word"))
+            ;;
+        *)
+            _pubcrypt
+            return
+            ;;
+    esac
+
+    # bash splits words at colons, so only the part after the last one is replaced
+    if [[ "$word" == *:* && "$COMP_WORDBREAKS" == *:* ]]; then
+        local colon_prefix="${word%"${word##*:}"}"
+        COMPREPLY=("${COMPREPLY[@]#"$colon_prefix"}")
+    fi
+}
+
+complete -F _pubcrypt_keyring -o bashdefault -o default pubcrypt
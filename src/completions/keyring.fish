@@ -0,0 +1,13 @@
+
+# complete the names of keys in the keyring, after the options that read a key and for
+# `key remove` and `key export`
+function __pubcrypt_keyring_names
+    pubcrypt key list 2>/dev/null | string replace -r '\t.*' ''
+end
+
+complete -c pubcrypt -s k -l key -a "(__pubcrypt_keyring_names | string replace -r '^' name:)"
+complete -c pubcrypt -n "not __fish_seen_subcommand_from genkey restore" -l priv -a "(__pubcrypt_keyring_names | string replace -r '^' name:)"
+complete -c pubcrypt -l peer -a "(__pubcrypt_keyring_names | string replace -r '^' name:)"
+complete -c pubcrypt -l old-priv -a "(__pubcrypt_keyring_names | string replace -r '^' name:)"
+complete -c pubcrypt -l new-pub -a "(__pubcrypt_keyring_names | string replace -r '^' name:)"
+complete -c pubcrypt -n "__fish_seen_subcommand_from key; and __fish_seen_subcommand_from remove export" -f -a "(__pubcrypt_keyring_names)"
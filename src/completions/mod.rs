@@ -0,0 +1,97 @@
+/*!
+ * Shell completion scripts, generated by `clap` from the command-line definition, with additions
+ * that complete the names of keys in the keyring.
+ *
+ * `clap` only completes fixed values, so each script gets shell functions that list the keyring
+ * with `pubcrypt key list`. Key names are offered as `name:<NAME>` alongside files after the
+ * options that read a key, and on their own for `key remove` and `key export`. Bash and fish merge
+ * completions registered later, so theirs are appended, but zsh's have to be named by each option.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use clap::{App, Shell};
+
+/**
+ * The name of the command being completed.
+ */
+const BIN_NAME: &str = "pubcrypt";
+
+/**
+ * The options that read a key in some subcommand, as they start zsh's option specs.
+ */
+const ZSH_KEY_OPTIONS: &[&str] = &[
+    "-k+[",
+    "--key=[",
+    "--priv=[",
+    "--peer=[",
+    "--old-priv=[",
+    "--new-pub=[",
+];
+
+/**
+ * The start of zsh's specs for the `NAME` of `key remove` and `key export`.
+ */
+const ZSH_KEY_NAME: &str = "':NAME -- The name of the key to ";
+
+const BASH_KEYRING: &str = include_str!("keyring.bash");
+const ZSH_KEYRING: &str = include_str!("keyring.zsh");
+const FISH_KEYRING: &str = include_str!("keyring.fish");
+
+/**
+ * Generate the completion script of `app` for `shell`.
+ */
+pub fn script(mut app: App<'_, '_>, shell: Shell) -> String {
+    let mut script = Vec::new();
+    app.gen_completions_to(BIN_NAME, shell, &mut script);
+    let script = String::from_utf8(script).expect("Clap should generate UTF-8 scripts");
+
+    match shell {
+        Shell::Bash => script + BASH_KEYRING,
+        Shell::Zsh => add_zsh_keyring(&script),
+        Shell::Fish => script + FISH_KEYRING,
+        Shell::PowerShell | Shell::Elvish => script,
+    }
+}
+
+/**
+ * Name the keyring's completion functions as the actions of the key options and key names in the
+ * zsh script `script`, and define them before it calls its main function on its last line.
+ */
+fn add_zsh_keyring(script: &str) -> String {
+    let mut lines: Vec<String> = script
+        .lines()
+        .map(|line| {
+            if reads_key(line) {
+                line.replacen("]' \\", "]: :_pubcrypt_keys' \\", 1)
+            } else if line.starts_with(ZSH_KEY_NAME) {
+                line.replacen(":_files'", ":_pubcrypt_key_names'", 1)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+
+    let main = lines.pop().unwrap_or_default();
+    lines.push(ZSH_KEYRING.to_string() + &main);
+    lines.join("\n")
+}
+
+/**
+ * Check whether the zsh option spec `line` is for an option that reads a key, rather than writing
+ * one, like `genkey --priv`. Options without a value to complete are left alone.
+ */
+fn reads_key(line: &str) -> bool {
+    let spec = line.trim_start_matches('\'');
+    // options that conflict with others list them first, in parentheses
+    let spec = match spec.strip_prefix('(') {
+        Some(rest) => rest.split_once(')').map_or("", |(_, spec)| spec),
+        None => spec,
+    };
+
+    ZSH_KEY_OPTIONS
+        .iter()
+        .any(|&option| spec.starts_with(option) && !spec[option.len()..].starts_with("Output"))
+        && line.ends_with("]' \\")
+}
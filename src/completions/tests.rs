@@ -0,0 +1,39 @@
+use super::*;
+use crate::build_clap_app;
+
+#[test]
+fn test_bash_script() {
+    let script = script(build_clap_app(), Shell::Bash);
+    assert!(script.starts_with("_pubcrypt() {"));
+    assert!(script.contains("_pubcrypt_keyring() {"));
+    assert!(script
+        .trim_end()
+        .ends_with("complete -F _pubcrypt_keyring -o bashdefault -o default pubcrypt"));
+}
+
+#[test]
+fn test_zsh_script() {
+    let script = script(build_clap_app(), Shell::Zsh);
+    assert!(
+        script.contains("'--key=[Read the public key from the given file]: :_pubcrypt_keys' \\")
+    );
+    assert!(script.contains("'-k+[Read the private key from the given file]: :_pubcrypt_keys' \\"));
+    assert!(script.contains(
+        "'--peer=[Read the other side'\\''s public key from the given file]: :_pubcrypt_keys' \\"
+    ));
+    assert!(script.contains("'--priv=[Output private key to given file]' \\"));
+    assert!(script.contains("':NAME -- The name of the key to remove:_pubcrypt_key_names' \\"));
+    assert!(script.contains("':NAME -- The name to give the key:_files' \\"));
+
+    // the functions are defined before they're first used
+    let definition = script.find("_pubcrypt_keys() {").unwrap();
+    assert!(script.rfind("\n_pubcrypt \"$@\"").unwrap() > definition);
+    assert!(script.trim_end().ends_with("_pubcrypt \"$@\""));
+}
+
+#[test]
+fn test_fish_script() {
+    let script = script(build_clap_app(), Shell::Fish);
+    assert!(script.contains("function __pubcrypt_keyring_names"));
+    assert!(script.contains("complete -c pubcrypt -s k -l key -a"));
+}
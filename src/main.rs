@@ -1,266 +1,4944 @@
-mod crypt;
+mod completions;
+mod prompt;
 
-use clap::{clap_app, AppSettings, ArgMatches};
+use clap::{clap_app, AppSettings, ArgMatches, Shell};
+use log::{Level, LevelFilter};
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::{
-    cmp,
+    cell::Cell,
+    convert::TryInto,
+    env,
     fmt::Display,
-    fs::{self, File},
-    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write},
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime, SystemTimeError, UNIX_EPOCH},
 };
 
-use crypt::{Block, Key, KeyPair, Num, BLOCK_BYTES, NUM_BYTES};
+use pubcrypt_core::{
+    archive::{self, Archiver},
+    armor::{self, ArmorKind},
+    audit,
+    cipher::{self, ElGamal, PublicKeyCipher},
+    compact,
+    container::{self, FileInfo, Packer},
+    detached::{self, DetachedSignature},
+    deterministic::DeterministicRng,
+    dh, dhparam,
+    ec::{EcElGamal, EcPrivateKey, EcPublicKey},
+    header::{self, Header},
+    hex, keyfile,
+    metadata::{self, KeyMetadata},
+    mnemonic, oneline, openpgp, pair,
+    peek::PeekReader,
+    rsa::{Rsa, RsaPrivateKey, RsaPublicKey},
+    selftest,
+    stream::{self, DecryptReader, EncryptWriter},
+    text,
+    threshold::{self, KeyShare, SHARE_BYTES},
+    volume::{self, VolumeReader, VolumeWriter},
+    Algorithm, ArmorError, CryptError, Encoding, Fingerprint, Group, Key, KeyError, KeyGenError,
+    KeyKind, KeyPair, Mode, Num, Padding, PrivateKey, PublicKey, BLOCK_BYTES, DEFAULT_MAX_ATTEMPTS,
+    DEFAULT_WITNESS_COUNT, PRIME_MAX, PRIME_MIN,
+};
+
+#[cfg(feature = "json")]
+use pubcrypt_core::json;
+#[cfg(feature = "qr")]
+use pubcrypt_core::qr;
+
+/**
+ * The number of seconds in a day, for `--expires`.
+ */
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/**
+ * The permissions of files written with private keys on Unix, which only their owner can read.
+ */
+#[cfg(unix)]
+const PRIVATE_FILE_MODE: u32 = 0o600;
+
+/**
+ * The number of bytes of each block used for its sequence number when `--sequence` is given.
+ */
+const SEQUENCE_BYTES: usize = 2;
+
+/**
+ * The number of bytes of each block used for its checksum when `--checksum` is given.
+ */
+const CHECKSUM_BYTES: usize = 2;
+
+/**
+ * The path that stands for standard input or output in `crypt`, which is also used when `--in`
+ * or `--out` isn't given.
+ */
+const STDIO_PATH: &str = "-";
+
+/**
+ * The suffix of the first volume's path when `--split-size` is given. Later volumes count up
+ * from it.
+ */
+const FIRST_VOLUME_SUFFIX: &str = ".001";
+
+/**
+ * The number of bytes at the start of `crypt -d`'s input that are checked for armor, which leaves
+ * room for whitespace before the `BEGIN` line.
+ */
+const ARMOR_DETECT_BYTES: usize = 256;
+
+/**
+ * The `info` that `exchange` expands the shared secret with, unless another is given.
+ */
+const EXCHANGE_INFO: &str = "pubcrypt exchange";
+
+/**
+ * The most key material that `exchange` can expand from a shared secret.
+ */
+const EXCHANGE_MAX_BYTES: usize = 255 * dh::SHARED_SECRET_BYTES;
+
+/**
+ * The number of words printed on each line by `backup`.
+ */
+const MNEMONIC_WORDS_PER_LINE: usize = 6;
+
+/**
+ * The prefix of a key path that names a key in the keyring instead of a file, as in
+ * `--key name:alice`.
+ */
+const KEYRING_PREFIX: &str = "name:";
+
+/**
+ * The placeholder in a `crypt --out` template that's replaced by each input's file name.
+ */
+const NAME_PLACEHOLDER: &str = "{name}";
+
+/**
+ * The placeholder in a `crypt --out` template that's replaced by each input's file name without
+ * its last extension, e.g., for decrypting `foo.txt.pce` to `foo.txt`.
+ */
+const STEM_PLACEHOLDER: &str = "{stem}";
+
+/**
+ * The shortest time between redraws of `crypt`'s progress line.
+ */
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/**
+ * The suffix added to each file's name when it's encrypted with `crypt -r`, and removed when it's
+ * decrypted.
+ */
+const RECURSIVE_SUFFIX: &str = ".pce";
+
+/**
+ * The environment variable that `crypt` reads a base64-encoded key file from when it isn't given
+ * `--key` or `--key-fd`.
+ */
+const KEY_ENV_VAR: &str = "PUBCRYPT_KEY";
+
+/**
+ * The key path that stands for the key in `KEY_ENV_VAR`, and names it in messages.
+ */
+const ENV_KEY_PATH: &str = "$PUBCRYPT_KEY";
+
+/**
+ * The temporary files of every uncommitted `PendingOutput`, which `die!` removes before exiting,
+ * since exiting doesn't run their destructors.
+ */
+static PENDING_OUTPUTS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/**
+ * The contents of the keys read from standard input, a file descriptor, or `KEY_ENV_VAR`, by key
+ * path. Those can only be read once, but a key is sometimes read more than once, e.g., to check
+ * whether it's protected before prompting for its passphrase.
+ */
+static STREAMED_KEYS: Mutex<Vec<(String, Vec<u8>)>> = Mutex::new(Vec::new());
+
+/**
+ * A key read from a key file, for whichever algorithm the file records.
+ */
+#[derive(Debug, Clone)]
+enum AnyKey {
+    ElGamal(Key),
+    RsaPublic(RsaPublicKey),
+    RsaPrivate(RsaPrivateKey),
+    EcPublic(EcPublicKey),
+    EcPrivate(EcPrivateKey),
+}
+
+/**
+ * How a key file is written by `genkey` or `pubkey`. Key files in any format are detected when
+ * they're read.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum KeyFormat {
+    Binary,
+    Armor,
+    Pem,
+    Json,
+    Text,
+    /**
+     * A one-line public key, with the private key written as binary.
+     */
+    Ssh,
+    /**
+     * A compact public key, with the private key written as binary.
+     */
+    Compact,
+}
+
+impl KeyFormat {
+    /**
+     * Check whether keys in this format are bare ElGamal keys, without metadata or protection.
+     */
+    fn is_bare(self) -> bool {
+        matches!(self, Self::Json | Self::Text)
+    }
+
+    /**
+     * Check whether only ElGamal keys can be written in this format.
+     */
+    fn is_elgamal_only(self) -> bool {
+        self.is_bare() || self.is_one_line()
+    }
+
+    /**
+     * Check whether public keys in this format are written on one line by `write_line`, with
+     * private keys written as binary.
+     */
+    fn is_one_line(self) -> bool {
+        matches!(self, Self::Ssh | Self::Compact)
+    }
+}
+
+/**
+ * How a ciphertext is written by `crypt -e`, or read by `crypt -d`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CiphertextFormat {
+    Binary,
+    Armor,
+    Hex,
+}
+
+struct CryptSettings {
+    reader: Box<dyn BufRead>,
+    writer: Box<dyn Write>,
+    key: AnyKey,
+    format: CiphertextFormat,
+    /**
+     * The directory to restore a file or directory tree from the ciphertext into, with
+     * `--restore-metadata` or when decrypting into a directory, in which case `writer` is unused.
+     */
+    restore_dir: Option<PathBuf>,
+    /**
+     * The paths or obfuscated names of the entries to restore from a directory archive, or
+     * empty to restore everything.
+     */
+    extract: Vec<String>,
+}
+
+/**
+ * An output file that's written to a temporary file in the same directory, and only renamed into
+ * place by `commit` once it's complete, so that a failure never leaves a partial file at its path
+ * or replaces an existing file. The temporary file is removed if it's dropped without being
+ * committed. A file that it replaces keeps its permissions, unless the output is made private.
+ *
+ * Symbolic links are followed, so the file they point to is replaced instead of the link.
+ * Devices and FIFOs can't be replaced by renaming, so they're written to directly.
+ */
+struct PendingOutput {
+    /**
+     * A handle to the temporary file, kept to sync it before it's renamed.
+     */
+    file: File,
+    /**
+     * The path of the temporary file, or `None` if the output is written to directly.
+     */
+    temp_path: Option<PathBuf>,
+    path: PathBuf,
+    /**
+     * Whether `commit` may replace a file that was created at `path` after the output was.
+     */
+    force: bool,
+    committed: bool,
+}
+
+impl PendingOutput {
+    /**
+     * Create a temporary file for `path`, returning it along with a handle to write to. Fails if
+     * `path` already exists, as in `replaces_file`, unless `force` is given.
+     */
+    fn create(path: &str, force: bool) -> io::Result<(Self, File)> {
+        if !force && replaces_file(path) {
+            return Err(already_exists());
+        }
+
+        let path = resolve_symlinks(Path::new(path))?;
+        if fs::metadata(&path).is_ok_and(|metadata| is_special_file(&metadata)) {
+            let file = OpenOptions::new().write(true).open(&path)?;
+            let output = Self {
+                file: file.try_clone()?,
+                temp_path: None,
+                path,
+                force,
+                committed: false,
+            };
+            return Ok((output, file));
+        }
+
+        // the temporary file has to be in the same directory to be renamed atomically
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "not a file path",
+                ))
+            }
+        };
+        // the name doesn't affect the output, so it's random even with `--seed`
+        let mut rng = StdRng::from_entropy();
+        loop {
+            let temp_path = dir.join(format!(".{}.{:08x}.tmp", name, rng.gen::<u32>()));
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp_path)
+            {
+                Ok(file) => {
+                    lock_pending_outputs().push(temp_path.clone());
+                    let output = Self {
+                        file: file.try_clone()?,
+                        temp_path: Some(temp_path),
+                        path,
+                        force,
+                        committed: false,
+                    };
+                    if let Ok(metadata) = fs::metadata(&output.path) {
+                        output.file.set_permissions(metadata.permissions())?;
+                    }
+                    return Ok((output, file));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /**
+     * Make the temporary file readable and writable only by its owner, for output that holds a
+     * private key. This does nothing on platforms other than Unix, or for devices and FIFOs,
+     * which are written to directly.
+     */
+    #[cfg(unix)]
+    fn make_private(&self) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if self.temp_path.is_none() {
+            return Ok(());
+        }
+        self.file
+            .set_permissions(fs::Permissions::from_mode(PRIVATE_FILE_MODE))
+    }
+
+    #[cfg(not(unix))]
+    fn make_private(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /**
+     * Sync the temporary file to disk, move it to the output path, and sync the directory so the
+     * new entry survives a crash. Everything written to it must already be flushed. Unless `force`
+     * was given, a file that appeared at the output path since `create` isn't replaced.
+     */
+    fn commit(mut self) -> io::Result<()> {
+        if let Some(temp_path) = &self.temp_path {
+            self.file.sync_all()?;
+            if self.force {
+                fs::rename(temp_path, &self.path)?;
+            } else {
+                // a hard link fails instead of replacing an existing file, unlike a rename
+                match fs::hard_link(temp_path, &self.path) {
+                    Ok(()) => fs::remove_file(temp_path)?,
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                        return Err(already_exists())
+                    }
+                    // filesystems without hard links can only check first
+                    Err(_) if replaces_file(&self.path) => return Err(already_exists()),
+                    Err(_) => fs::rename(temp_path, &self.path)?,
+                }
+            }
+            sync_parent_dir(&self.path)?;
+        }
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for PendingOutput {
+    fn drop(&mut self) {
+        let temp_path = match &self.temp_path {
+            Some(temp_path) => temp_path,
+            None => return,
+        };
+        if !self.committed {
+            let _ = fs::remove_file(temp_path);
+        }
+        lock_pending_outputs().retain(|path| path != temp_path);
+    }
+}
+
+/**
+ * The error for an output file that already exists when `--force` isn't given.
+ */
+fn already_exists() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "it already exists; give --force to replace it",
+    )
+}
+
+/**
+ * Sync the directory holding `path`, so that a file just renamed or linked into it is recorded on
+ * disk. Directories can't be opened for syncing on platforms other than Unix, so this does nothing
+ * there.
+ */
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/**
+ * The most symbolic links that `resolve_symlinks` follows, as in Linux's `ELOOP` limit.
+ */
+const MAX_SYMLINKS: usize = 40;
+
+/**
+ * Check whether writing to `path` would replace an existing file or directory, following
+ * symbolic links. Devices and FIFOs are written to rather than replaced, so they don't count.
+ */
+fn replaces_file<P: AsRef<Path>>(path: P) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| !is_special_file(&metadata))
+}
+
+/**
+ * Check whether `metadata` is of something other than a regular file or directory, such as a
+ * device or FIFO.
+ */
+fn is_special_file(metadata: &fs::Metadata) -> bool {
+    !metadata.is_file() && !metadata.is_dir()
+}
+
+/**
+ * Resolve the symbolic links in `path`, including a last link to a file that doesn't exist yet,
+ * so that an output file is written where the link points instead of replacing the link. Paths
+ * that don't exist are returned as they are.
+ */
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+    for _ in 0..MAX_SYMLINKS {
+        if let Ok(resolved) = fs::canonicalize(&path) {
+            return Ok(resolved);
+        }
+
+        // a relative target is relative to the link's directory, and `join` keeps absolute ones
+        match fs::read_link(&path) {
+            Ok(target) => path = path.parent().unwrap_or(Path::new("")).join(target),
+            Err(_) => return Ok(path),
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "too many levels of symbolic links",
+    ))
+}
+
+/**
+ * A reader that shows how much of its input has been read on standard error, along with the
+ * throughput and the estimated time left. Nothing is shown until reading has taken
+ * `PROGRESS_INTERVAL`, so small inputs finish without a progress line.
+ */
+struct ProgressReader<R> {
+    inner: R,
+    label: String,
+    total: u64,
+    done: u64,
+    start: Instant,
+    last_drawn: Option<Instant>,
+    finished: bool,
+}
+
+impl<R> ProgressReader<R> {
+    /**
+     * Create a reader that reports reading the `total` bytes of `inner`, labeled with `label`.
+     */
+    fn new(inner: R, label: String, total: u64) -> Self {
+        Self {
+            inner,
+            label,
+            total,
+            done: 0,
+            start: Instant::now(),
+            last_drawn: None,
+            finished: false,
+        }
+    }
+
+    /**
+     * Redraw the progress line as of `now`.
+     */
+    fn draw(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.start).as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            self.done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let percent = match self.total {
+            0 => 100,
+            total => self.done.min(total) * 100 / total,
+        };
+        let eta = if rate > 0.0 {
+            format_seconds((self.total.saturating_sub(self.done) as f64 / rate).ceil() as u64)
+        } else {
+            "?".to_string()
+        };
+
+        // `\x1b[K` clears whatever is left of a longer line drawn earlier
+        eprint!(
+            "\r{}: {} / {} ({}%), {}/s, ETA {}\x1b[K",
+            self.label,
+            format_bytes(self.done),
+            format_bytes(self.total),
+            percent,
+            format_bytes(rate as u64),
+            eta
+        );
+        self.last_drawn = Some(now);
+    }
+
+    /**
+     * Draw the progress line one last time and end it, if it was ever drawn.
+     */
+    fn finish(&mut self) {
+        if !self.finished && self.last_drawn.is_some() {
+            self.draw(Instant::now());
+            eprintln!();
+        }
+        self.finished = true;
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.done += count as u64;
+        if count == 0 && !buf.is_empty() {
+            self.finish();
+            return Ok(0);
+        }
+
+        let now = Instant::now();
+        let since = self.last_drawn.unwrap_or(self.start);
+        if now.duration_since(since) >= PROGRESS_INTERVAL {
+            self.draw(now);
+        }
+        Ok(count)
+    }
+}
+
+/**
+ * A reader or writer that adds the number of bytes that pass through it to a shared count, which
+ * can still be read once it's been moved into `CryptSettings`.
+ */
+struct ByteCounter<T> {
+    inner: T,
+    count: Rc<Cell<u64>>,
+}
+
+impl<T> ByteCounter<T> {
+    fn new(inner: T, count: Rc<Cell<u64>>) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<R: Read> Read for ByteCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.count.set(self.count.get() + count as u64);
+        Ok(count)
+    }
+}
+
+impl<W: Write> Write for ByteCounter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.count.set(self.count.get() + count as u64);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R> Drop for ProgressReader<R> {
+    fn drop(&mut self) {
+        // a failure stops reading early, and its message shouldn't share the progress line
+        if !self.finished && self.last_drawn.is_some() {
+            eprintln!();
+        }
+    }
+}
+
+/**
+ * Format `bytes` for people, in the largest binary unit it fills, e.g., `1.5 MiB`.
+ */
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/**
+ * Format a number of seconds as `m:ss`, or `h:mm:ss` if it's an hour or more.
+ */
+fn format_seconds(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/**
+ * The logger for messages from pubcrypt and its library, which prints each one on standard error
+ * after its level.
+ */
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let level = match record.level() {
+            Level::Error => "error",
+            Level::Warn => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+        eprintln!("{}: {}", level, record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/**
+ * Install `LOGGER`, which shows warnings by default, only errors with `--quiet`, and
+ * informational, debugging, and tracing messages with one, two, or three `-v`s.
+ */
+fn init_logging(matches: &ArgMatches) {
+    let level = match matches.occurrences_of("VERBOSE") {
+        _ if matches.is_present("QUIET") => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    // nothing else sets a logger, so this can't fail
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+/**
+ * Lock `PENDING_OUTPUTS`, even if a thread panicked while holding it.
+ */
+fn lock_pending_outputs() -> MutexGuard<'static, Vec<PathBuf>> {
+    PENDING_OUTPUTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/**
+ * Remove the temporary files of every uncommitted `PendingOutput`, before exiting with `die!`.
+ */
+fn remove_pending_outputs() {
+    for path in lock_pending_outputs().drain(..) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/**
+ * The kinds of failure that pubcrypt exits with, each with its own exit status so that scripts
+ * can tell them apart.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Failure {
+    /**
+     * Anything that isn't one of the other kinds, such as failing to generate a key.
+     */
+    Other,
+    /**
+     * The command line is invalid, or asks for something that can't be done.
+     */
+    Usage,
+    /**
+     * Reading or writing a file failed, or an output file already exists.
+     */
+    Io,
+    /**
+     * A key can't be read, is the wrong kind of key, or its passphrase is wrong.
+     */
+    BadKey,
+    /**
+     * A ciphertext or signature is malformed, or was made for a different key.
+     */
+    Corrupt,
+    /**
+     * An integrity check failed: a MAC, checksum, digest, signature, or validation.
+     */
+    Verification,
+}
+
+impl Failure {
+    /**
+     * Get the exit status for the failure.
+     */
+    fn exit_code(self) -> i32 {
+        match self {
+            Self::Other => 1,
+            Self::Usage => 2,
+            Self::Io => 3,
+            Self::BadKey => 4,
+            Self::Corrupt => 5,
+            Self::Verification => 6,
+        }
+    }
+}
+
+impl From<&io::Error> for Failure {
+    fn from(_: &io::Error) -> Self {
+        Self::Io
+    }
+}
+
+impl From<&CryptError> for Failure {
+    fn from(error: &CryptError) -> Self {
+        match error {
+            CryptError::Io(e) => Self::from(e),
+            CryptError::Block { error, .. } => Self::from(&**error),
+            CryptError::MalformedKey
+            | CryptError::IncompatibleKeys
+            | CryptError::InvalidShares
+            | CryptError::WrongPassphrase => Self::BadKey,
+            CryptError::AuthenticationFailed
+            | CryptError::ChecksumMismatch
+            | CryptError::DigestMismatch => Self::Verification,
+            _ => Self::Corrupt,
+        }
+    }
+}
+
+impl From<&KeyError> for Failure {
+    fn from(_: &KeyError) -> Self {
+        Self::BadKey
+    }
+}
+
+impl From<&KeyGenError> for Failure {
+    fn from(_: &KeyGenError) -> Self {
+        Self::Other
+    }
+}
+
+impl From<&ArmorError> for Failure {
+    fn from(_: &ArmorError) -> Self {
+        Self::Corrupt
+    }
+}
+
+impl From<&SystemTimeError> for Failure {
+    fn from(_: &SystemTimeError) -> Self {
+        Self::Other
+    }
+}
+
+impl From<&ParseIntError> for Failure {
+    fn from(_: &ParseIntError) -> Self {
+        Self::Usage
+    }
+}
+
+/**
+ * Print a message and exit the process with the status of the `Failure` given before `=>`,
+ * e.g., `die!(Failure::Io => "{} is missing", path)`.
+ */
+macro_rules! die {
+    ($failure:expr => $($tts:tt)+) => {{
+        eprintln!($($tts)+);
+        remove_pending_outputs();
+        std::process::exit(Failure::exit_code($failure));
+    }};
+}
+
+/**
+ * Get the inner `Ok` value of the result. If the result is `Err(_)`, print an informational message
+ * prefixed with `msg` and exit the process with the status of the error's `Failure`.
+ *
+ * Returns the `Ok` value of `r`. Does not return if `r` is an error.
+ */
+fn ok_or_die<T, E: Display>(r: Result<T, E>, msg: &str) -> T
+where
+    for<'a> Failure: From<&'a E>,
+{
+    match r {
+        Ok(val) => val,
+        Err(e) => die!(Failure::from(&e) => "{}: {}", msg, e),
+    }
+}
+
+/**
+ * Get the inner `Ok` value of the result as in `ok_or_die`, but exit with the status of `failure`
+ * whatever the error, for errors whose kind depends on what was being read.
+ */
+fn ok_or_die_as<T, E: Display>(r: Result<T, E>, failure: Failure, msg: &str) -> T {
+    match r {
+        Ok(val) => val,
+        Err(e) => die!(failure => "{}: {}", msg, e),
+    }
+}
+
+/**
+ * Unwrap the result of generating keys, or exit with a message that suggests what to do about the
+ * error.
+ */
+fn ok_or_die_generating<T>(result: Result<T, KeyGenError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e @ KeyGenError::InvalidRange) => {
+            die!(Failure::Other =>
+                "Failed to generate key: {}; this is a bug in the key parameters",
+                e
+            )
+        }
+        Err(e @ KeyGenError::PrimeNotFound { .. }) => die!(Failure::Other =>
+            "Failed to generate key: {}. This is rare, so try again, or increase --max-attempts",
+            e
+        ),
+    }
+}
+
+/**
+ * The generator that `new_rng` seeds every generator from when `--seed` is given, or `None` to
+ * seed them from the operating system.
+ */
+static SEEDED_RNG: Mutex<Option<DeterministicRng>> = Mutex::new(None);
+
+/**
+ * Seed every generator made by `new_rng` from `--seed`, if it's given, so that runs with the same
+ * seed and arguments generate the same keys and ciphertexts.
+ */
+fn init_rng(matches: &ArgMatches) {
+    if let Some(seed) = matches.value_of("SEED") {
+        let seed = match parse_hex(seed) {
+            Some(seed) => seed,
+            None => {
+                die!(Failure::Usage => "Invalid seed; must be a non-empty string of hex digits")
+            }
+        };
+        log::warn!(
+            "--seed is for testing only. Every key and ciphertext made with it can be recreated \
+             by anyone who knows the seed."
+        );
+        *SEEDED_RNG
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(DeterministicRng::new(&seed));
+    }
+}
+
+/**
+ * Create a random number generator, seeded from the operating system, or from `SEEDED_RNG` if
+ * `--seed` is given.
+ */
+fn new_rng() -> StdRng {
+    let mut seeded = SEEDED_RNG
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match seeded.as_mut() {
+        Some(rng) => StdRng::from_rng(rng).expect("a deterministic generator can't fail"),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/**
+ * Get the range that `genkey` picks the key's prime from: that of `BITS`-bit numbers if it's
+ * given, or else from `PRIME_MIN` to `PRIME_MAX`, each defaulting to the full range that
+ * `KeyPair::generate` uses.
+ */
+fn prime_range(matches: &ArgMatches) -> (Num, Num) {
+    // the sizes of the smallest and largest primes that keys can have
+    let min_bits = Num::BITS - PRIME_MIN.leading_zeros();
+    let max_bits = Num::BITS;
+
+    if let Some(bits) = matches.value_of("BITS") {
+        let bits = ok_or_die(bits.parse::<u32>(), "Invalid bit count");
+        if bits < min_bits || bits > max_bits {
+            die!(Failure::Usage =>
+                "Invalid bit count: must be between {} and {}",
+                min_bits,
+                max_bits
+            );
+        }
+        return (1 << (bits - 1), Num::MAX >> (max_bits - bits));
+    }
+
+    let parse = |arg, default| match matches.value_of(arg) {
+        Some(n) => ok_or_die(n.parse::<Num>(), "Invalid prime bound"),
+        None => default,
+    };
+    let (min, max) = (parse("PRIME_MIN", PRIME_MIN), parse("PRIME_MAX", PRIME_MAX));
+    if min < PRIME_MIN {
+        die!(Failure::Usage => "Invalid --prime-min: must be at least {}", PRIME_MIN);
+    }
+    if min > max {
+        die!(Failure::Usage => "Invalid prime range: --prime-min is above --prime-max");
+    }
+
+    (min, max)
+}
+
+/**
+ * Generate a public-private key pair and write them to the values `PUB_OUT` and `PRIV_OUT` from
+ * `matches`, respectively, or together to the key pair file `OUT`, as in `write_key_files`. If
+ * `GROUP` is given, the keys are generated in that fixed group instead of a new one, or if
+ * `DHPARAM` is given, in the group from that OpenSSL DH parameters file. If `SEED_FILE` is given,
+ * they're derived from the file's contents as in `KeyPair::from_passphrase`. Otherwise, up to
+ * `MAX_ATTEMPTS` attempts are made to find a prime, across `THREADS` threads, in the range from
+ * `prime_range` and with `WITNESSES` Miller-Rabin witnesses.
+ * If `ALGO` is `rsa` or `ec-elgamal`, keys for that algorithm are generated instead.
+ *
+ * Both keys are written with metadata from `key_metadata`, which is signed by ElGamal keys, in the
+ * format from `key_format`.
+ */
+fn gen_keys(matches: &ArgMatches) -> io::Result<()> {
+    let out_paths = [
+        matches.value_of("PUB_OUT"),
+        matches.value_of("PRIV_OUT"),
+        matches.value_of("OUT"),
+    ];
+    let algo = matches.value_of("ALGO").unwrap_or("elgamal");
+    match out_paths {
+        [_, Some(STDIO_PATH), _] | [_, _, Some(STDIO_PATH)] => {
+            die!(Failure::Usage => "Private keys can't be written to standard output; give a file")
+        }
+        [Some(_), Some(_), None] | [None, None, Some(_)] => {}
+        _ => {
+            die!(Failure::Usage => "Give both --pub and --priv, or --out to write both keys to one file")
+        }
+    }
+    if algo != "elgamal"
+        && [
+            "GROUP",
+            "DHPARAM",
+            "SEED_FILE",
+            "MAX_ATTEMPTS",
+            "THREADS",
+            "BITS",
+            "PRIME_MIN",
+            "PRIME_MAX",
+            "WITNESSES",
+            "PROTECT",
+        ]
+        .iter()
+        .any(|&arg| matches.is_present(arg))
+    {
+        die!(Failure::Usage =>
+            "--group, --dhparam, --from-seed-file, --max-attempts, --threads, --bits, \
+             --prime-min, --prime-max, --witnesses, and --protect can only be used with ElGamal \
+             keys"
+        );
+    }
+
+    let format = key_format(matches);
+    if format.is_elgamal_only() && algo != "elgamal" {
+        die!(Failure::Usage =>
+            "--format {} can only be used with ElGamal keys",
+            matches.value_of("FORMAT").unwrap()
+        );
+    }
+    if matches.is_present("OUT") && (format.is_bare() || format.is_one_line()) {
+        die!(Failure::Usage =>
+            "--out can't be used with --format {}; give --pub and --priv instead",
+            matches.value_of("FORMAT").unwrap()
+        );
+    }
+    if format.is_bare() {
+        let format = matches.value_of("FORMAT").unwrap();
+        if ["PROTECT", "COMMENT", "EXPIRES"]
+            .iter()
+            .any(|&arg| matches.is_present(arg))
+        {
+            die!(Failure::Usage =>
+                "--protect, --comment, and --expires can't be used with --format {}",
+                format
+            );
+        }
+    }
+
+    match algo {
+        "rsa" => return gen_cipher_keys::<Rsa>(matches),
+        "ec-elgamal" => return gen_cipher_keys::<EcElGamal>(matches),
+        _ => {}
+    }
+
+    let mut rng = new_rng();
+    let group = match (matches.value_of("GROUP"), matches.value_of("DHPARAM")) {
+        (Some(name), _) => Some(named_group(name)),
+        (None, Some(path)) => Some(read_dhparam(path, &mut rng)),
+        (None, None) => None,
+    };
+    let generated = match (group, matches.value_of("SEED_FILE")) {
+        (Some(group), _) => Ok(KeyPair::generate_in_group(&group, &mut rng)),
+        (None, Some(seed_path)) => {
+            let seed = ok_or_die(fs::read(seed_path), "Failed to read seed file");
+            KeyPair::from_passphrase(&seed)
+        }
+        (None, None) => {
+            let max_attempts = match matches.value_of("MAX_ATTEMPTS") {
+                Some(count) => match ok_or_die(count.parse::<usize>(), "Invalid attempt count") {
+                    0 => die!(Failure::Usage => "Invalid attempt count: must be at least 1"),
+                    count => count,
+                },
+                None => DEFAULT_MAX_ATTEMPTS,
+            };
+            let threads = match matches.value_of("THREADS") {
+                Some(count) => match ok_or_die(count.parse::<usize>(), "Invalid thread count") {
+                    0 => die!(Failure::Usage => "Invalid thread count: must be at least 1"),
+                    count => count,
+                },
+                None => 1,
+            };
+            let witnesses = match matches.value_of("WITNESSES") {
+                Some(count) => match ok_or_die(count.parse::<usize>(), "Invalid witness count") {
+                    0 => die!(Failure::Usage => "Invalid witness count: must be at least 1"),
+                    count => count,
+                },
+                None => DEFAULT_WITNESS_COUNT,
+            };
+            let (min, max) = prime_range(matches);
+            KeyPair::generate_in_range(min, max, witnesses, threads, max_attempts, &mut rng)
+        }
+    };
+    let keys = ok_or_die_generating(generated);
+    let mut metadata = key_metadata(matches, Algorithm::ElGamal);
+    metadata.sign(&keys.private);
+
+    // `OUT` was rejected above for bare and one-line keys, so both paths are given for them
+    let (pub_path, priv_path) = (out_paths[0], out_paths[1]);
+    let force = matches.is_present("FORCE");
+    if format.is_bare() {
+        return write_both(
+            pub_path.unwrap(),
+            || {
+                write_bare_key(
+                    pub_path.unwrap(),
+                    keys.public.key(),
+                    KeyKind::Public,
+                    format,
+                    force,
+                )
+            },
+            || {
+                let private = keys.private.key();
+                write_bare_key(priv_path.unwrap(), private, KeyKind::Private, format, force)
+            },
+        );
+    }
+
+    let private = if matches.is_present("PROTECT") {
+        let passphrase = read_new_passphrase(get_passphrase_path(matches).as_deref());
+        let protected = keyfile::protect_key(&keys.private, &passphrase, &mut rng);
+        write_key_file(&metadata, &protected)
+    } else {
+        write_key_file(&metadata, &keys.private.key().to_der())
+    };
+    if format.is_one_line() {
+        return write_both(
+            pub_path.unwrap(),
+            || {
+                write_line(
+                    pub_path.unwrap(),
+                    &keys.public,
+                    &metadata.comment,
+                    format,
+                    force,
+                )
+            },
+            || {
+                write_output(
+                    priv_path.unwrap(),
+                    ArmorKind::PrivateKey,
+                    &private,
+                    format,
+                    force,
+                )
+            },
+        );
+    }
+
+    let public = write_key_file(&metadata, &keys.public.key().to_der());
+    write_key_files(matches, &public, &private, format)
+}
+
+/**
+ * Write the public and private key files `public` and `private` for `genkey`, in the given
+ * format: to `PUB_OUT` and `PRIV_OUT` from `matches`, or if `OUT` is given, together to that path
+ * as a key pair file from `pair::write_pair`. Existing files are only replaced with `FORCE`.
+ */
+fn write_key_files(
+    matches: &ArgMatches,
+    public: &[u8],
+    private: &[u8],
+    format: KeyFormat,
+) -> io::Result<()> {
+    let force = matches.is_present("FORCE");
+    if let Some(out_path) = matches.value_of("OUT") {
+        let bytes = pair::write_pair(public, private);
+        return write_output(out_path, ArmorKind::KeyPair, &bytes, format, force);
+    }
+
+    let pub_path = matches.value_of("PUB_OUT").unwrap();
+    let priv_path = matches.value_of("PRIV_OUT").unwrap();
+    write_both(
+        pub_path,
+        || write_output(pub_path, ArmorKind::PublicKey, public, format, force),
+        || write_output(priv_path, ArmorKind::PrivateKey, private, format, force),
+    )
+}
+
+/**
+ * Write a public key file to `pub_path` with `write_public`, then its private key file with
+ * `write_private`. If the private key can't be written, for example because its file already
+ * exists, a public key file that didn't exist before is removed again, so that half of a new pair
+ * isn't left behind.
+ */
+fn write_both<F, G>(pub_path: &str, write_public: F, write_private: G) -> io::Result<()>
+where
+    F: FnOnce() -> io::Result<()>,
+    G: FnOnce() -> io::Result<()>,
+{
+    let is_new = pub_path != STDIO_PATH && fs::symlink_metadata(pub_path).is_err();
+    write_public()?;
+    write_private().inspect_err(|_| {
+        if is_new {
+            let _ = fs::remove_file(pub_path);
+        }
+    })
+}
+
+/**
+ * Get the current time in seconds since the Unix epoch.
+ */
+fn now() -> u64 {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH);
+    ok_or_die(elapsed, "The system clock is before 1970").as_secs()
+}
+
+/**
+ * Create the metadata for a new key for `algorithm`, created now, with the comment `COMMENT` and
+ * an expiry `EXPIRES` days from now if they're given in `matches`.
+ */
+fn key_metadata(matches: &ArgMatches, algorithm: Algorithm) -> KeyMetadata {
+    let mut metadata = KeyMetadata::new(algorithm, now());
+    if let Some(comment) = matches.value_of("COMMENT") {
+        if comment.len() > metadata::MAX_COMMENT_BYTES {
+            die!(Failure::Usage =>
+                "Invalid comment: must be at most {} bytes",
+                metadata::MAX_COMMENT_BYTES
+            );
+        }
+
+        metadata.comment = comment.to_string();
+    }
+
+    if let Some(days) = matches.value_of("EXPIRES") {
+        let days = ok_or_die(days.parse::<u64>(), "Invalid expiry");
+        let expires = days
+            .checked_mul(SECONDS_PER_DAY)
+            .and_then(|seconds| metadata.created.checked_add(seconds));
+        metadata.expires = match expires {
+            Some(expires) if days > 0 => Some(expires),
+            _ => die!(Failure::Usage => "Invalid expiry: must be a positive number of days"),
+        };
+    }
+
+    metadata
+}
+
+/**
+ * Get the format to write keys in: armor if `ARMOR` is given, or else `FORMAT`.
+ */
+fn key_format(matches: &ArgMatches) -> KeyFormat {
+    match matches.value_of("FORMAT") {
+        _ if matches.is_present("ARMOR") => KeyFormat::Armor,
+        Some("pem") => KeyFormat::Pem,
+        Some("json") => KeyFormat::Json,
+        Some("text") => KeyFormat::Text,
+        Some("ssh") => KeyFormat::Ssh,
+        Some("compact") => KeyFormat::Compact,
+        Some("binary") | None => KeyFormat::Binary,
+        Some(_) => unreachable!("Clap should reject unknown key formats"),
+    }
+}
+
+/**
+ * Write the key file, key pair file, or ciphertext `bytes` to `path` in the given format, labeled
+ * with `kind` if it's text. Files with private keys are only readable by their owner, as in
+ * `write_atomically`. One-line keys are written by `write_line`, so only private keys are written
+ * here with `KeyFormat::Ssh` and `KeyFormat::Compact`, as binary.
+ */
+fn write_output(
+    path: &str,
+    kind: ArmorKind,
+    bytes: &[u8],
+    format: KeyFormat,
+    force: bool,
+) -> io::Result<()> {
+    let is_private = matches!(kind, ArmorKind::PrivateKey | ArmorKind::KeyPair);
+    match format {
+        KeyFormat::Binary | KeyFormat::Ssh | KeyFormat::Compact => {
+            write_atomically(path, bytes, is_private, force)
+        }
+        KeyFormat::Armor => write_atomically(path, armor::armor(kind, bytes), is_private, force),
+        KeyFormat::Pem => write_atomically(path, armor::pem(kind, bytes), is_private, force),
+        KeyFormat::Json | KeyFormat::Text => {
+            unreachable!("bare keys are written by `write_bare_key`")
+        }
+    }
+}
+
+/**
+ * Write the public key `key` to `path` on one line in the given format, with the given comment if
+ * it's a one-line key. Compact keys don't have comments.
+ */
+fn write_line(
+    path: &str,
+    key: &PublicKey,
+    comment: &str,
+    format: KeyFormat,
+    force: bool,
+) -> io::Result<()> {
+    let line = match format {
+        KeyFormat::Ssh => ok_or_die(oneline::to_line(key, comment), "Invalid key comment"),
+        KeyFormat::Compact => compact::to_compact(key),
+        _ => unreachable!("only one-line and compact keys are written on one line"),
+    };
+    write_atomically(path, line + "\n", false, force)
+}
+
+/**
+ * Write the ElGamal key `key` to `path` as a bare key file of the given kind and format.
+ */
+fn write_bare_key(
+    path: &str,
+    key: &Key,
+    kind: KeyKind,
+    format: KeyFormat,
+    force: bool,
+) -> io::Result<()> {
+    let is_private = kind == KeyKind::Private;
+    match format {
+        KeyFormat::Json => write_atomically(path, key_to_json(key, kind), is_private, force),
+        KeyFormat::Text => write_atomically(path, key.to_text(kind), is_private, force),
+        _ => unreachable!("only JSON and text keys are bare"),
+    }
+}
+
+/**
+ * Write `bytes` to `path` through a `PendingOutput`, or to standard output if `path` is `-`. A file
+ * that's already there is only replaced if `force` is given, and only once the new one is
+ * complete. If `is_private` is given, the file is made readable only by its owner before anything
+ * is written to it.
+ */
+fn write_atomically<B: AsRef<[u8]>>(
+    path: &str,
+    bytes: B,
+    is_private: bool,
+    force: bool,
+) -> io::Result<()> {
+    if path == STDIO_PATH {
+        return io::stdout().write_all(bytes.as_ref());
+    }
+
+    let (output, mut file) = PendingOutput::create(path, force)?;
+    if is_private {
+        output.make_private()?;
+    }
+    file.write_all(bytes.as_ref())?;
+    output.commit()
+}
+
+/**
+ * Write `bytes`, which hold a private key, a share of one, or another secret, to `path` through a
+ * `PendingOutput` that's readable only by its owner, as in `PendingOutput::make_private`. Fails if
+ * `path` already exists, unless `force` is given.
+ */
+fn write_private_file(path: &str, bytes: &[u8], force: bool) -> io::Result<()> {
+    let (output, mut file) = PendingOutput::create(path, force)?;
+    output.make_private()?;
+    file.write_all(bytes)?;
+    output.commit()
+}
+
+/**
+ * Convert the ElGamal key `key` to a JSON key file labeled with `kind`.
+ */
+#[cfg(feature = "json")]
+fn key_to_json(key: &Key, kind: KeyKind) -> String {
+    json::key_to_json(key, kind) + "\n"
+}
+
+#[cfg(not(feature = "json"))]
+fn key_to_json(_key: &Key, _kind: KeyKind) -> String {
+    die!(Failure::Usage => "JSON keys need pubcrypt to be built with the `json` feature");
+}
+
+/**
+ * Read a JSON key file, returning the DER encoding of the key inside it.
+ */
+#[cfg(feature = "json")]
+fn key_from_json(bytes: &[u8], err_msg: &str) -> Vec<u8> {
+    let text = ok_or_die_as(std::str::from_utf8(bytes), Failure::BadKey, err_msg);
+    let (key, _) = ok_or_die(json::key_from_json(text), err_msg);
+    key.to_der()
+}
+
+#[cfg(not(feature = "json"))]
+fn key_from_json(_bytes: &[u8], err_msg: &str) -> Vec<u8> {
+    die!(Failure::Usage =>
+        "{}: JSON keys need pubcrypt to be built with the `json` feature",
+        err_msg
+    );
+}
+
+/**
+ * Read the key file at `key_path` as in `read_key_or_pair_file`, taking the key file of the given
+ * kind from it if it's a key pair file.
+ */
+fn read_key_file(key_path: &str, kind: KeyKind) -> Vec<u8> {
+    let bytes = read_key_or_pair_file(key_path);
+    if !pair::is_pair(&bytes) {
+        return bytes;
+    }
+
+    let err_msg = format!("Invalid key pair file {}", key_path);
+    ok_or_die(pair::read_half(&bytes, kind), &err_msg).to_vec()
+}
+
+/**
+ * Read the key file or key pair file at `key_path`, unwrapping its armor or PEM text if it has
+ * any. JSON, text, and one-line keys are converted to DER.
+ */
+fn read_key_or_pair_file(key_path: &str) -> Vec<u8> {
+    let bytes = read_key_bytes(key_path);
+    let err_msg = format!("Invalid key file {}", key_path);
+    if bytes.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{') {
+        return key_from_json(&bytes, &err_msg);
+    }
+    if oneline::is_line(&bytes) {
+        let line = ok_or_die_as(std::str::from_utf8(&bytes), Failure::BadKey, &err_msg);
+        let (key, _) = ok_or_die(oneline::from_line(line), &err_msg);
+        return key.key().to_der();
+    }
+    if compact::is_compact(&bytes) {
+        let text = ok_or_die_as(std::str::from_utf8(&bytes), Failure::BadKey, &err_msg);
+        let key = ok_or_die(compact::from_compact(text), &err_msg);
+        return key.key().to_der();
+    }
+    if text::is_text_key(&bytes) {
+        // the first word is a tag, so the key is valid UTF-8
+        let text = std::str::from_utf8(&bytes).unwrap();
+        let (key, _) = ok_or_die(Key::from_text(text), &err_msg);
+        return key.to_der();
+    }
+    if !armor::is_armored(&bytes) {
+        return bytes;
+    }
+
+    match ok_or_die_as(armor::dearmor(&bytes), Failure::BadKey, &err_msg) {
+        (kind, bytes) if kind.is_key() => bytes,
+        _ => die!(Failure::BadKey => "{}: it contains a message, not a key", err_msg),
+    }
+}
+
+/**
+ * Read the raw contents of the key file at `key_path`, which can also be `-` for standard input,
+ * a `/dev/fd/` path, or `ENV_KEY_PATH`. Those are only read once, and remembered in
+ * `STREAMED_KEYS` for later reads. Exits the process if the key can't be read.
+ */
+fn read_key_bytes(key_path: &str) -> Vec<u8> {
+    let is_streamed =
+        key_path == STDIO_PATH || key_path == ENV_KEY_PATH || key_path.starts_with("/dev/fd/");
+    if !is_streamed {
+        return ok_or_die(
+            fs::read(key_file_path(key_path)),
+            "Failed to read key from file",
+        );
+    }
+
+    let mut streamed = STREAMED_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, bytes)) = streamed.iter().find(|(path, _)| path == key_path) {
+        return bytes.clone();
+    }
+
+    let bytes = if key_path == ENV_KEY_PATH {
+        let value = env::var(KEY_ENV_VAR).unwrap_or_default();
+        let text: String = value.split_whitespace().collect();
+        match armor::decode_base64(&text) {
+            Some(bytes) => bytes,
+            None => {
+                die!(Failure::BadKey => "{} isn't a valid base64-encoded key file", KEY_ENV_VAR)
+            }
+        }
+    } else if key_path == STDIO_PATH {
+        let mut bytes = Vec::new();
+        ok_or_die(
+            io::stdin().read_to_end(&mut bytes),
+            "Failed to read key from standard input",
+        );
+        bytes
+    } else {
+        ok_or_die(
+            fs::read(key_path),
+            "Failed to read key from file descriptor",
+        )
+    };
+    streamed.push((key_path.to_string(), bytes.clone()));
+    bytes
+}
+
+/**
+ * Get the path of `crypt`'s key from `matches`: the `--key` path, the `/dev/fd/` path of
+ * `--key-fd`, or `ENV_KEY_PATH` if `KEY_ENV_VAR` is set. Exits the process if there's none.
+ */
+fn crypt_key_path(matches: &ArgMatches) -> String {
+    if let Some(key_path) = matches.value_of("KEYPATH") {
+        return key_path.to_string();
+    }
+    if let Some(fd) = matches.value_of("KEY_FD") {
+        let fd = ok_or_die(fd.parse::<u32>(), "Invalid key file descriptor");
+        return format!("/dev/fd/{}", fd);
+    }
+    if env::var_os(KEY_ENV_VAR).is_some() {
+        return ENV_KEY_PATH.to_string();
+    }
+    die!(Failure::Usage =>
+        "Give the key with --key or --key-fd, or set {}",
+        KEY_ENV_VAR
+    );
+}
+
+/**
+ * Get the kind of key that `crypt` uses from `matches`: the public key to encrypt, or the private
+ * key to decrypt. Only key pair files hold both, so this picks which half of them is read.
+ */
+fn crypt_key_kind(matches: &ArgMatches) -> KeyKind {
+    if matches.is_present("ENCRYPT") {
+        KeyKind::Public
+    } else {
+        KeyKind::Private
+    }
+}
+
+/**
+ * Write a key file containing `metadata` and `key_bytes`.
+ */
+fn write_key_file(metadata: &KeyMetadata, key_bytes: &[u8]) -> Vec<u8> {
+    ok_or_die(metadata.write_key_file(key_bytes), "Invalid key metadata")
+}
+
+/**
+ * Format `time`, in seconds since the Unix epoch, as a UTC date and time.
+ */
+fn format_time(time: u64) -> String {
+    // convert days since the epoch to a civil date, as in Howard Hinnant's `civil_from_days`
+    let days = time / SECONDS_PER_DAY;
+    let seconds = time % SECONDS_PER_DAY;
+    let shifted = days + 719_468;
+    let era = shifted / 146_097;
+    let day_of_era = shifted % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/**
+ * Look up the fixed group called `name`, exiting the process if there isn't one.
+ */
+fn named_group(name: &str) -> Group {
+    match Group::by_name(name) {
+        Some(group) => group,
+        None => die!(Failure::Usage =>
+            "Unknown group {}; expected one of: {}",
+            name,
+            Group::names().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/**
+ * Read the group from the OpenSSL DH parameters file at `path`, in PEM or DER, and check that keys
+ * can be generated in it, exiting the process if they can't.
+ */
+fn read_dhparam<T: Rng>(path: &str, rng: &mut T) -> Group {
+    let err_msg = format!("Invalid DH parameters {}", path);
+    let bytes = read_labeled_file(path, dhparam::PEM_LABEL, &err_msg);
+    let group = ok_or_die(dhparam::from_der(&bytes), &err_msg);
+    ok_or_die(
+        group.validate(rng),
+        &format!("Unusable DH parameters {}", path),
+    );
+    group
+}
+
+/**
+ * Read the file at `path` written by another tool, unwrapping it if it's PEM text or armor with
+ * the given label, and otherwise returning it as is. Exits the process with `err_msg` if the text
+ * is invalid.
+ */
+fn read_labeled_file(path: &str, label: &str, err_msg: &str) -> Vec<u8> {
+    let bytes = ok_or_die(fs::read(path), &format!("Failed to read {}", path));
+    if bytes.trim_ascii_start().starts_with(b"-----BEGIN ") {
+        ok_or_die(armor::depem_labeled(label, &bytes), err_msg)
+    } else {
+        bytes
+    }
+}
+
+/**
+ * Import the ElGamal key from the OpenPGP key at `PGP_IN`, in binary or armor, and write it to
+ * `PUB_OUT` as a public key in the format from `key_format`. The key's User ID and creation time
+ * are kept as its comment and creation time.
+ */
+fn import_pgp(matches: &ArgMatches) -> io::Result<()> {
+    let in_path = matches.value_of("PGP_IN").unwrap();
+    let err_msg = format!("Invalid OpenPGP key {}", in_path);
+    let bytes = read_labeled_file(in_path, openpgp::PUBLIC_KEY_LABEL, &err_msg);
+    let imported = ok_or_die(openpgp::read_elgamal_key(&bytes), &err_msg);
+    let key = imported.key.key();
+    ok_or_die(
+        key.validate(&mut new_rng()),
+        &format!("Unusable ElGamal key in {}", in_path),
+    );
+
+    let pub_path = matches.value_of("PUB_OUT").unwrap();
+    let format = key_format(matches);
+    if format.is_bare() {
+        return write_bare_key(pub_path, key, KeyKind::Public, format, true);
+    }
+
+    let comment = imported.user_id.unwrap_or_default();
+    if format.is_one_line() {
+        return write_line(pub_path, &imported.key, &comment, format, true);
+    }
+
+    let mut metadata = KeyMetadata::new(Algorithm::ElGamal, imported.created);
+    metadata.comment = comment;
+    let file = write_key_file(&metadata, &key.to_der());
+    write_output(pub_path, ArmorKind::PublicKey, &file, format, true)
+}
+
+/**
+ * Write the group of the ElGamal key at `KEYPATH`, or the fixed group `GROUP`, to `OUT` as OpenSSL
+ * DH parameters, in PEM text unless `FORMAT` is `der`.
+ */
+fn export_dhparam(matches: &ArgMatches) -> io::Result<()> {
+    let group = match matches.value_of("GROUP") {
+        Some(name) => named_group(name),
+        None => match read_key(
+            matches.value_of("KEYPATH").unwrap(),
+            KeyKind::Public,
+            get_passphrase_path(matches).as_deref(),
+        ) {
+            AnyKey::ElGamal(key) => key.group(),
+            _ => die!(Failure::BadKey => "Only ElGamal keys have DH parameters"),
+        },
+    };
+
+    let encoded = dhparam::to_der(&group);
+    let out_path = matches.value_of("OUT").unwrap();
+    match matches.value_of("FORMAT") {
+        Some("der") => fs::write(out_path, encoded),
+        _ => fs::write(out_path, armor::pem_labeled(dhparam::PEM_LABEL, &encoded)),
+    }
+}
+
+/**
+ * Derive the secret shared between the ElGamal private key at `PRIV_IN` and the public key at
+ * `PEER`, and write `LENGTH` bytes of key material expanded from it for `INFO` to `OUT`, in binary
+ * unless `FORMAT` is `hex`. A file is written as in `write_private_file`, replacing an existing one
+ * only if `FORCE` is given. If `CONFIRM` is given, the secret's confirmation words are printed for
+ * both sides to compare.
+ */
+fn exchange(matches: &ArgMatches) -> io::Result<()> {
+    let private = PrivateKey::from(read_elgamal_key(
+        matches.value_of("PRIV_IN").unwrap(),
+        KeyKind::Private,
+        get_passphrase_path(matches).as_deref(),
+    ));
+    let peer_path = matches.value_of("PEER").unwrap();
+    let peer = PublicKey::from(read_elgamal_key(peer_path, KeyKind::Public, None));
+    if peer == private.derive_public() {
+        die!(Failure::BadKey =>
+            "{} is your own public key; give the other side's",
+            peer_path
+        );
+    }
+    let secret = ok_or_die(
+        dh::derive_shared_secret(&private, &peer),
+        "Failed to derive the shared secret",
+    );
+
+    if let Some(out_path) = matches.value_of("OUT") {
+        let len = match matches.value_of("LENGTH").map(str::parse::<usize>) {
+            None => dh::SHARED_SECRET_BYTES,
+            Some(Ok(len)) if len > 0 && len <= EXCHANGE_MAX_BYTES => len,
+            Some(_) => die!(Failure::Usage =>
+                "Invalid length; must be between 1 and {} bytes",
+                EXCHANGE_MAX_BYTES
+            ),
+        };
+        let info = matches.value_of("INFO").unwrap_or(EXCHANGE_INFO);
+        let mut key = vec![0_u8; len];
+        secret.expand(info.as_bytes(), &mut key);
+
+        if matches.value_of("FORMAT") == Some("hex") {
+            key = format!("{}\n", hex::encode(&key)).into_bytes();
+        }
+        if out_path == STDIO_PATH {
+            io::stdout().write_all(&key)?;
+        } else {
+            let err_msg = format!("Failed to write the derived key to {}", out_path);
+            let force = matches.is_present("FORCE");
+            ok_or_die(write_private_file(out_path, &key, force), &err_msg);
+        }
+    }
+
+    if matches.is_present("CONFIRM") {
+        println!("Confirmation: {}", secret.confirmation().join(" "));
+    }
+    Ok(())
+}
+
+/**
+ * Sign the file at `IN` or `FILE` with the ElGamal private key at `KEYPATH`, writing a detached
+ * signature to `OUT`, in binary unless `ARMOR` is given. The file is hashed as it's read, so it
+ * doesn't have to fit in memory. Standard input is signed if the file is `-` or isn't given, and
+ * the signature is
+ * written to standard output if `OUT` is `-`; `OUT` defaults to the file with `.sig` appended, or
+ * standard output when signing standard input.
+ */
+fn sign_file(matches: &ArgMatches) -> io::Result<()> {
+    let key_path = matches.value_of("KEYPATH").unwrap();
+    let passphrase_path = get_passphrase_path(matches);
+    let file_path = signed_file_path(matches);
+
+    // without a terminal, a passphrase prompt would read the file instead of the passphrase
+    if file_path == STDIO_PATH
+        && needs_passphrase_from_stdin(key_path, KeyKind::Private, passphrase_path.as_deref())
+    {
+        die!(Failure::Usage =>
+            "{} is protected; give its passphrase with --passphrase-fd or --passphrase-file when signing standard input",
+            key_path
+        );
+    }
+
+    let key = match read_key(key_path, KeyKind::Private, passphrase_path.as_deref()) {
+        AnyKey::ElGamal(key) => PrivateKey::from(key),
+        _ => die!(Failure::BadKey => "Only ElGamal keys can sign files"),
+    };
+
+    let mut file = open_signed_file(file_path)?;
+    let signed = DetachedSignature::sign(&mut file, &key, &mut new_rng())?;
+
+    let out_path = match matches.value_of("OUT") {
+        Some(path) => path.to_string(),
+        None if file_path == STDIO_PATH => STDIO_PATH.to_string(),
+        None => format!("{}.sig", file_path),
+    };
+    let mut bytes = signed.serialize();
+    if matches.is_present("ARMOR") {
+        bytes = armor::armor(ArmorKind::Signature, &bytes).into_bytes();
+    }
+    if out_path == STDIO_PATH {
+        io::stdout().write_all(&bytes)
+    } else {
+        fs::write(out_path, bytes)
+    }
+}
+
+/**
+ * Get the path of the file given to `sign` or `verify`, either with `IN` or as `FILE`, or `-` for
+ * standard input if it's given with neither.
+ */
+fn signed_file_path<'a>(matches: &'a ArgMatches) -> &'a str {
+    matches
+        .value_of("IN")
+        .or_else(|| matches.value_of("FILE"))
+        .unwrap_or(STDIO_PATH)
+}
+
+/**
+ * Open the file at `path` for `sign` or `verify`, or standard input if it's `-`.
+ */
+fn open_signed_file(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == STDIO_PATH {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+/**
+ * Check the detached signature at `SIG` on the file at `IN` or `FILE` (standard input if it's `-`
+ * or isn't given) against the ElGamal public key at `KEYPATH`. The file is hashed as it's read.
+ * Exits the process with an error, and a nonzero status, if the signature isn't valid.
+ */
+fn verify_file(matches: &ArgMatches) {
+    let key = match read_key(matches.value_of("KEYPATH").unwrap(), KeyKind::Public, None) {
+        AnyKey::ElGamal(key) => PublicKey::from(key),
+        _ => die!(Failure::BadKey => "Only ElGamal keys can verify signatures"),
+    };
+
+    let sig_path = matches.value_of("SIG").unwrap();
+    let bytes = ok_or_die(fs::read(sig_path), &format!("Failed to read {}", sig_path));
+    let err_msg = format!("Invalid signature file {}", sig_path);
+    let bytes = if armor::is_armored(&bytes) {
+        match ok_or_die(armor::dearmor(&bytes), &err_msg) {
+            (ArmorKind::Signature, bytes) => bytes,
+            _ => die!(Failure::Corrupt => "{}: it doesn't contain a signature", err_msg),
+        }
+    } else {
+        bytes
+    };
+    let signed = ok_or_die(DetachedSignature::deserialize(&bytes), &err_msg);
+
+    if signed.signer != key.fingerprint() {
+        die!(Failure::Verification =>
+            "Bad signature: the file was signed by key {:#}, not {:#}",
+            signed.signer,
+            key.fingerprint()
+        );
+    }
+
+    let file_path = signed_file_path(matches);
+    let err_msg = format!("Failed to read {}", file_path);
+    let mut file = ok_or_die(open_signed_file(file_path), &err_msg);
+    if !ok_or_die(signed.verify(&mut file, &key), &err_msg) {
+        die!(Failure::Verification =>
+            "Bad signature: {} was changed after it was signed",
+            file_path
+        );
+    }
+
+    println!("Good signature from key {:#}", signed.signer);
+}
+
+/**
+ * Generate a key pair for the algorithm `C` and write them as in `write_key_files`, with metadata
+ * from `key_metadata`, in the format from `key_format`.
+ */
+fn gen_cipher_keys<C: PublicKeyCipher>(matches: &ArgMatches) -> io::Result<()> {
+    let mut rng = new_rng();
+    let (public, private) = ok_or_die_generating(C::generate(&mut rng));
+    let metadata = key_metadata(matches, C::ALGORITHM);
+
+    let public = C::serialize_public_key(&public);
+    let private = C::serialize_private_key(&private);
+    let format = key_format(matches);
+    let public = write_key_file(&metadata, &public);
+    let private = write_key_file(&metadata, &private);
+    write_key_files(matches, &public, &private, format)
+}
+
+/**
+ * Get the file to read a passphrase from, as given by `PASSPHRASE_FD` or `PASSPHRASE_FILE` in
+ * `matches`, or `None` if it should be prompted for. Exits the process if the file descriptor is
+ * invalid.
+ */
+fn get_passphrase_path(matches: &ArgMatches) -> Option<String> {
+    if let Some(fd) = matches.value_of("PASSPHRASE_FD") {
+        let fd = ok_or_die(fd.parse::<u32>(), "Invalid passphrase file descriptor");
+        return Some(format!("/dev/fd/{}", fd));
+    }
+    matches.value_of("PASSPHRASE_FILE").map(String::from)
+}
+
+/**
+ * Read a passphrase from the file at `path` if it's given, as from `get_passphrase_path`, or
+ * otherwise prompt for it on the terminal with `prompt`, without echoing it. Only the first line
+ * is used, without its line ending.
+ */
+fn read_passphrase(prompt: &str, path: Option<&str>) -> Vec<u8> {
+    let line = match path {
+        Some(path) => {
+            let err_msg = format!("Failed to read passphrase from {}", path);
+            let file = ok_or_die(File::open(path), &err_msg);
+            ok_or_die(prompt::read_line(BufReader::new(file)), &err_msg)
+        }
+        None => ok_or_die(prompt::read_hidden(prompt), "Failed to read passphrase"),
+    };
+
+    line.into_bytes()
+}
+
+/**
+ * Check whether a passphrase for the key of the given kind at `key_path` would have to be prompted
+ * for on standard input, with no file to read it from and no terminal to prompt on. Exits the
+ * process if the key can't be read.
+ */
+fn needs_passphrase_from_stdin(
+    key_path: &str,
+    kind: KeyKind,
+    passphrase_path: Option<&str>,
+) -> bool {
+    passphrase_path.is_none() && !prompt::has_terminal() && is_protected_key_file(key_path, kind)
+}
+
+/**
+ * Read a passphrase for a newly protected key as in `read_passphrase`, asking for it twice when
+ * prompting to catch typos.
+ */
+fn read_new_passphrase(path: Option<&str>) -> Vec<u8> {
+    let passphrase = read_passphrase("Passphrase: ", path);
+    if path.is_none() && read_passphrase("Repeat passphrase: ", None) != passphrase {
+        die!(Failure::Usage => "Passphrases don't match");
+    }
+
+    passphrase
+}
+
+/**
+ * Derive the public key from the private key at `PRIV_IN` and write it to `PUB_OUT`. If the
+ * private key's file has metadata, the public key is written with the same metadata; otherwise,
+ * it's written in the same format as the private key. The public key file is written in the
+ * format from `key_format`.
+ */
+fn derive_pubkey(matches: &ArgMatches) -> io::Result<()> {
+    let (key, metadata) = read_key_with_metadata(
+        matches.value_of("PRIV_IN").unwrap(),
+        KeyKind::Private,
+        get_passphrase_path(matches).as_deref(),
+    );
+    let pub_path = matches.value_of("PUB_OUT").unwrap();
+    let format = key_format(matches);
+    if format.is_elgamal_only() {
+        return match key {
+            AnyKey::ElGamal(key) if format.is_one_line() => {
+                let public = PrivateKey::from(key).derive_public();
+                let comment = metadata
+                    .map(|metadata| metadata.comment)
+                    .unwrap_or_default();
+                write_line(pub_path, &public, &comment, format, true)
+            }
+            AnyKey::ElGamal(key) => {
+                let public = PrivateKey::from(key).derive_public();
+                write_bare_key(pub_path, public.key(), KeyKind::Public, format, true)
+            }
+            _ => die!(Failure::Usage =>
+                "--format {} can only be used with ElGamal keys",
+                matches.value_of("FORMAT").unwrap()
+            ),
+        };
+    }
+
+    let (algorithm, public) = match key {
+        // ElGamal keys with metadata are DER-encoded, while older ones keep their fixed layout
+        AnyKey::ElGamal(key) => {
+            let public = PrivateKey::from(key).derive_public();
+            match metadata {
+                Some(_) => (Algorithm::ElGamal, public.key().to_der()),
+                None => (Algorithm::ElGamal, public.serialize().to_vec()),
+            }
+        }
+        AnyKey::RsaPrivate(key) => (Rsa::ALGORITHM, Rsa::serialize_public_key(&key.public_key())),
+        AnyKey::EcPrivate(key) => (
+            EcElGamal::ALGORITHM,
+            EcElGamal::serialize_public_key(&key.public_key()),
+        ),
+        AnyKey::RsaPublic(_) | AnyKey::EcPublic(_) => {
+            die!(Failure::BadKey => "The key is already a public key")
+        }
+    };
+
+    let file = match metadata {
+        Some(metadata) => write_key_file(&metadata, &public),
+        None => cipher::write_key_file(algorithm, &public),
+    };
+    write_output(pub_path, ArmorKind::PublicKey, &file, format, true)
+}
+
+/**
+ * Print what the key file or ciphertext at `FILE`, or the key file at `KEYPATH`, holds. The kind
+ * of file is detected as in `open_ciphertext`.
+ */
+fn inspect(matches: &ArgMatches) -> Result<(), CryptError> {
+    let path = match matches.value_of("KEYPATH") {
+        Some(key_path) => {
+            inspect_key(key_path);
+            return Ok(());
+        }
+        None => matches.value_of("FILE").unwrap(),
+    };
+
+    match open_ciphertext(path) {
+        Some(reader) => inspect_ciphertext(reader),
+        None => {
+            inspect_key(path);
+            Ok(())
+        }
+    }
+}
+
+/**
+ * Open the file at `path` as a ciphertext, unwrapping it from its volumes or armor, or return
+ * `None` if it's a key file. Files are checked for a volume header, a ciphertext header, and
+ * armor, in that order; anything else that isn't a key file is taken to be a legacy, headerless
+ * ciphertext. Exits the process if it's neither.
+ */
+fn open_ciphertext(path: &str) -> Option<Box<dyn Read>> {
+    let err_msg = format!("Failed to read input file {}", path);
+    let file = ok_or_die(File::open(key_file_path(path)), &err_msg);
+    let mut reader = PeekReader::new(Box::new(file) as Box<dyn Read>);
+
+    let start = ok_or_die(reader.peek(volume::MAGIC.len()), &err_msg);
+    if volume::is_volume(start) {
+        reader = PeekReader::new(open_volumes(path, reader));
+    }
+
+    let start = ok_or_die(reader.peek(ARMOR_DETECT_BYTES), &err_msg);
+    if start.starts_with(&header::MAGIC) {
+        return Some(Box::new(reader));
+    }
+
+    let mut bytes = Vec::new();
+    ok_or_die(reader.read_to_end(&mut bytes), &err_msg);
+    if armor::is_armored(&bytes) {
+        let err_msg = format!("Invalid input file {}", path);
+        match ok_or_die(armor::dearmor(&bytes), &err_msg) {
+            (ArmorKind::Message, ciphertext) => Some(Box::new(io::Cursor::new(ciphertext))),
+            _ => None,
+        }
+    } else if is_key_file(&bytes) {
+        None
+    } else if bytes.is_empty() || bytes.len() % ElGamal::CIPHERTEXT_BYTES != 0 {
+        die!(Failure::Corrupt => "{} isn't a key file or ciphertext", path);
+    } else {
+        Some(Box::new(io::Cursor::new(bytes)))
+    }
+}
+
+/**
+ * Print the header of the ciphertext read from `reader`, along with its number of blocks and the
+ * size of its plaintext, which is estimated without decrypting it.
+ */
+fn inspect_ciphertext<R: Read>(reader: R) -> Result<(), CryptError> {
+    let mut reader = PeekReader::new(reader);
+    reader.peek(header::MAGIC.len())?;
+    let header = Header::read(&mut reader)?;
+    let estimate = stream::estimate_size(&header, reader)?;
+
+    println!("Type: ciphertext");
+    match header.version() {
+        0 => println!("Format: version 0 (legacy, no header)"),
+        version => println!("Format: version {}", version),
+    }
+    println!("Algorithm: {}", header.algorithm);
+    println!("Mode: {}", header.mode);
+    println!("Block size: {} bytes", header.block_bytes);
+    if matches!(header.mode, Mode::Ecb | Mode::Cbc) {
+        println!("Padding: {}", header.padding);
+    }
+    println!("Sequence numbers: {} bytes", header.sequence_bytes);
+    println!("Checksums: {} bytes", header.checksum_bytes);
+    println!("OAEP: {}", header.oaep);
+    println!("Authenticated: {}", header.authenticated);
+    println!("Chunked: {}", header.chunked);
+
+    let contents = if header.archive {
+        "directory archive"
+    } else if header.contains_file {
+        "file with metadata"
+    } else {
+        "data"
+    };
+    println!("Contents: {}", contents);
+    match header.recipient {
+        Some(recipient) => println!("Recipient: {:#}", recipient),
+        None => println!("Recipient: anonymous"),
+    }
+
+    println!("Blocks: {}", estimate.blocks);
+    if estimate.min_plaintext_bytes == estimate.max_plaintext_bytes {
+        println!("Plaintext size: {} bytes", estimate.max_plaintext_bytes);
+    } else {
+        println!(
+            "Plaintext size: {} to {} bytes",
+            estimate.min_plaintext_bytes, estimate.max_plaintext_bytes
+        );
+    }
+    Ok(())
+}
+
+/**
+ * Print the format, algorithm, parameters, and metadata of the key file at `key_path`, warning if
+ * the key has expired. Protected keys are inspected without their passphrase, so their parameters
+ * and signatures can't be read.
+ */
+fn inspect_key(key_path: &str) {
+    let bytes = read_key_or_pair_file(key_path);
+    let bytes = if pair::is_pair(&bytes) {
+        println!("Type: key pair (showing the public key)");
+        read_key_file(key_path, KeyKind::Public)
+    } else {
+        println!("Type: key");
+        bytes
+    };
+    let err_msg = format!("Invalid key file {}", key_path);
+    let (metadata, algorithm, key_bytes) = split_key_file(&bytes, &err_msg);
+
+    let protected = keyfile::is_protected(key_bytes);
+
+    match &metadata {
+        Some(_) => println!("Format: version {}", metadata::version(&bytes)),
+        None => println!("Format: version 1 (no metadata)"),
+    }
+    println!("Algorithm: {}", algorithm);
+    println!("Protected: {}", protected);
+
+    if protected {
+        println!("Kind: private");
+    } else {
+        let key = read_unprotected_key(algorithm, key_bytes, &err_msg);
+        print_key_parameters(&key, metadata.as_ref());
+    }
+
+    if let Some(metadata) = metadata {
+        println!("Created: {}", format_time(metadata.created));
+        match metadata.expires {
+            Some(expires) => println!("Expires: {}", format_time(expires)),
+            None => println!("Expires: never"),
+        }
+        println!("Comment: {}", metadata.comment);
+
+        let signature = match metadata.signature {
+            None => "none",
+            Some(_) if protected => "unchecked (the key is protected)",
+            Some(_) => {
+                let key = read_unprotected_key(algorithm, key_bytes, &err_msg);
+                match check_signature(&metadata, &key) {
+                    Ok(()) => "valid",
+                    Err(_) => "INVALID",
+                }
+            }
+        };
+        println!("Signature: {}", signature);
+        warn_if_expired(key_path, &metadata);
+    }
+}
+
+/**
+ * Print the kind of `key` and its parameters. ElGamal keys are the same shape whether they're
+ * public or private, so their kind is only known if `metadata` is signed.
+ */
+fn print_key_parameters(key: &AnyKey, metadata: Option<&KeyMetadata>) {
+    let key = match key {
+        AnyKey::ElGamal(key) => key,
+        AnyKey::RsaPublic(key) => {
+            println!("Kind: public");
+            let modulus = key.modulus();
+            println!("Modulus: {} bits", Num::BITS - modulus.leading_zeros());
+            return;
+        }
+        AnyKey::RsaPrivate(key) => {
+            println!("Kind: private");
+            let modulus = key.public_key().modulus();
+            println!("Modulus: {} bits", Num::BITS - modulus.leading_zeros());
+            return;
+        }
+        AnyKey::EcPublic(_) => return println!("Kind: public"),
+        AnyKey::EcPrivate(_) => return println!("Kind: private"),
+    };
+
+    let public = PublicKey::from(key.clone());
+    let derived = PrivateKey::from(key.clone()).derive_public();
+    let (kind, fingerprint) = match elgamal_kind(key, metadata) {
+        Some(KeyKind::Public) => ("public", public.fingerprint().to_colon_hex()),
+        Some(KeyKind::Private) => ("private", derived.fingerprint().to_colon_hex()),
+        None => {
+            let fingerprint = format!(
+                "{:#} if public, {:#} if private",
+                public.fingerprint(),
+                derived.fingerprint()
+            );
+            ("unknown (the key file isn't signed)", fingerprint)
+        }
+    };
+
+    println!("Kind: {}", kind);
+    println!("Prime: {} bits", key.prime_bits());
+    println!("Generator: {}", key.root());
+    println!("Fingerprint: {}", fingerprint);
+}
+
+/**
+ * Tell whether the ElGamal key `key` is public or private from the signature in `metadata`, since
+ * the two are the same shape. Returns `None` if there's no signature that matches either.
+ */
+fn elgamal_kind(key: &Key, metadata: Option<&KeyMetadata>) -> Option<KeyKind> {
+    let metadata = metadata.filter(|metadata| metadata.signature.is_some())?;
+    if metadata.verify(&PublicKey::from(key.clone())).is_ok() {
+        Some(KeyKind::Public)
+    } else if metadata
+        .verify(&PrivateKey::from(key.clone()).derive_public())
+        .is_ok()
+    {
+        Some(KeyKind::Private)
+    } else {
+        None
+    }
+}
+
+/**
+ * Run the checks of the `audit` module on the key file or ciphertext at `FILE`, which is detected
+ * as in `open_ciphertext`, and print whether each one passed, along with the path of the file it
+ * was run on. If the ElGamal key at `KEYPATH` is also given, a key file is checked against it as
+ * the other half of its pair, and a ciphertext is checked against it as its recipient. Exits the
+ * process with a nonzero status if any check fails.
+ */
+fn validate(matches: &ArgMatches) -> Result<(), CryptError> {
+    let path = matches.value_of("FILE").unwrap();
+    let passphrase_path = get_passphrase_path(matches);
+    let key_path = matches.value_of("KEYPATH");
+
+    let mut checks = Vec::new();
+    match open_ciphertext(path) {
+        Some(reader) => {
+            let key = key_path.map(|key_path| {
+                let (key, metadata) =
+                    read_key_with_metadata(key_path, KeyKind::Private, passphrase_path.as_deref());
+                match key {
+                    AnyKey::ElGamal(key) => public_half(key_path, key, metadata.as_ref()),
+                    _ => die!(Failure::BadKey => "{} isn't an ElGamal key", key_path),
+                }
+            });
+            for check in validate_ciphertext(reader, key.as_ref())? {
+                checks.push((path, check));
+            }
+        }
+        None => {
+            let key = validate_key_file(path, passphrase_path.as_deref(), &mut checks);
+            if let Some(key_path) = key_path {
+                let other = validate_key_file(key_path, passphrase_path.as_deref(), &mut checks);
+                // a pair can only be compared once both halves are known to be well-formed
+                if let (Some(key), Some(other)) = (key, other) {
+                    checks.push((path, check_key_pair(&key, &other)));
+                }
+            }
+        }
+    }
+
+    for (path, check) in &checks {
+        let outcome = if check.passed { "pass" } else { "FAIL" };
+        println!("{}\t{}\t{}", outcome, path, check.name);
+    }
+    if checks.iter().any(|(_, check)| !check.passed) {
+        die!(Failure::Verification => "{} failed validation", path);
+    }
+    Ok(())
+}
+
+/**
+ * Check the header and structure of the ciphertext read from `reader` for `validate`, as in
+ * `audit::check_ciphertext`.
+ */
+fn validate_ciphertext<R: Read>(
+    reader: R,
+    key: Option<&PublicKey>,
+) -> Result<Vec<audit::Check>, CryptError> {
+    let mut reader = PeekReader::new(reader);
+    reader.peek(header::MAGIC.len())?;
+    let header = match Header::read(&mut reader) {
+        Ok(header) => header,
+        Err(CryptError::InvalidHeader) | Err(CryptError::UnsupportedVersion(_)) => {
+            return Ok(vec![audit::Check {
+                name: "header is well-formed",
+                passed: false,
+            }]);
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut checks = vec![audit::Check {
+        name: "header is well-formed",
+        passed: true,
+    }];
+    checks.extend(audit::check_ciphertext(&header, reader, key)?);
+    Ok(checks)
+}
+
+/**
+ * Check the key file at `key_path` for `validate`, adding the outcome of each check to `checks`.
+ * ElGamal keys are checked with `audit::check_key` without being checked as they're read, so a
+ * malformed key is reported check by check, and RSA and EC keys get the checks made when they're
+ * read. Returns the key if it passed them all.
+ */
+fn validate_key_file<'a>(
+    key_path: &'a str,
+    passphrase_path: Option<&str>,
+    checks: &mut Vec<(&'a str, audit::Check)>,
+) -> Option<AnyKey> {
+    let bytes = read_key_file(key_path, KeyKind::Private);
+    let err_msg = format!("Invalid key file {}", key_path);
+    let (metadata, algorithm, key_bytes) = split_key_file(&bytes, &err_msg);
+
+    let key = if algorithm == Algorithm::ElGamal {
+        let key = if keyfile::is_protected(key_bytes) {
+            let prompt = format!("Passphrase for {}: ", key_path);
+            let passphrase = read_passphrase(&prompt, passphrase_path);
+            let key = ok_or_die(keyfile::unprotect_key(key_bytes, &passphrase), &err_msg);
+            key.key().clone()
+        } else {
+            ok_or_die(cipher::deserialize_key_unchecked(key_bytes), &err_msg)
+        };
+
+        let key_checks = audit::check_key(&key, &mut new_rng());
+        let passed = key_checks.iter().all(|check| check.passed);
+        checks.extend(key_checks.into_iter().map(|check| (key_path, check)));
+        Some(AnyKey::ElGamal(key)).filter(|_| passed)
+    } else {
+        let key = deserialize_key(algorithm, key_bytes);
+        let check = audit::Check {
+            name: "key parameters are valid",
+            passed: key.is_ok(),
+        };
+        checks.push((key_path, check));
+        key.ok()
+    };
+
+    let metadata = metadata.filter(|metadata| metadata.signature.is_some());
+    if let (Some(metadata), Some(key)) = (metadata, &key) {
+        let check = audit::Check {
+            name: "metadata signature is valid",
+            passed: check_signature(&metadata, key).is_ok(),
+        };
+        checks.push((key_path, check));
+    }
+    key
+}
+
+/**
+ * Check whether the keys `a` and `b` are the private and public halves of a pair, in either order.
+ */
+fn check_key_pair(a: &AnyKey, b: &AnyKey) -> audit::Check {
+    let passed = match (a, b) {
+        // ElGamal keys don't say which half they are, so both ways are tried
+        (AnyKey::ElGamal(a), AnyKey::ElGamal(b)) => {
+            let forward =
+                audit::check_pair(&PrivateKey::from(a.clone()), &PublicKey::from(b.clone()));
+            if forward.passed {
+                return forward;
+            }
+            return audit::check_pair(&PrivateKey::from(b.clone()), &PublicKey::from(a.clone()));
+        }
+        (AnyKey::RsaPrivate(private), AnyKey::RsaPublic(public))
+        | (AnyKey::RsaPublic(public), AnyKey::RsaPrivate(private)) => {
+            private.public_key() == *public
+        }
+        (AnyKey::EcPrivate(private), AnyKey::EcPublic(public))
+        | (AnyKey::EcPublic(public), AnyKey::EcPrivate(private)) => private.public_key() == *public,
+        _ => false,
+    };
+    audit::Check {
+        name: "public key matches private key",
+        passed,
+    }
+}
+
+/**
+ * Get the public key of the ElGamal key `key`, read from `key_path` along with `metadata`. Private
+ * keys are told apart as in `elgamal_kind`, and key files that don't say which they are are taken
+ * to hold public keys.
+ */
+fn public_half(key_path: &str, key: Key, metadata: Option<&KeyMetadata>) -> PublicKey {
+    let is_private = is_protected_key_file(key_path, KeyKind::Private)
+        || elgamal_kind(&key, metadata) == Some(KeyKind::Private);
+    if is_private {
+        PrivateKey::from(key).derive_public()
+    } else {
+        PublicKey::from(key)
+    }
+}
+
+/**
+ * Run `selftest::run` with a newly seeded generator, and print whether each check passed. Exits
+ * the process with a nonzero status if any check fails.
+ */
+fn self_test() {
+    let checks = selftest::run(&mut new_rng());
+    for check in &checks {
+        let outcome = if check.passed { "pass" } else { "FAIL" };
+        println!("{}\t{}", outcome, check.name);
+    }
+    if checks.iter().any(|check| !check.passed) {
+        die!(Failure::Verification => "The self-test failed; pubcrypt isn't safe to use on this platform");
+    }
+}
+
+/**
+ * Print the fingerprint, prime size, path, and comment of each ElGamal key file in `KEYPATHS`, one
+ * per line with tabs between them, or as JSON if `JSON` is given. Private keys are listed under
+ * the fingerprint of their public key, as are key files that don't say which they are. Protected
+ * keys need their passphrase to be read.
+ */
+fn list_fingerprints(matches: &ArgMatches) {
+    let mut keys = Vec::new();
+    for key_path in matches.values_of("KEYPATHS").unwrap() {
+        let (key, metadata) = read_key_with_metadata(key_path, KeyKind::Public, None);
+        let key = match key {
+            AnyKey::ElGamal(key) => key,
+            _ => die!(Failure::BadKey =>
+                "{} isn't an ElGamal key, so it has no fingerprint",
+                key_path
+            ),
+        };
+
+        let public = public_half(key_path, key, metadata.as_ref());
+        let comment = metadata
+            .map(|metadata| metadata.comment)
+            .unwrap_or_default();
+        keys.push((key_path, public, comment));
+    }
+
+    if matches.is_present("JSON") {
+        println!("{}", fingerprints_to_json(&keys));
+        return;
+    }
+    for (key_path, public, comment) in &keys {
+        println!(
+            "{:#}\t{}\t{}\t{}",
+            public.fingerprint(),
+            public.key().prime_bits(),
+            key_path,
+            comment
+        );
+    }
+}
+
+/**
+ * Convert the `(path, key, comment)` listing of `list_fingerprints` to JSON.
+ */
+#[cfg(feature = "json")]
+fn fingerprints_to_json(keys: &[(&str, PublicKey, String)]) -> String {
+    json::fingerprints_to_json(
+        keys.iter()
+            .map(|(path, key, comment)| (*path, key, comment.as_str())),
+    )
+}
+
+#[cfg(not(feature = "json"))]
+fn fingerprints_to_json(_keys: &[(&str, PublicKey, String)]) -> String {
+    die!(Failure::Usage => "--json needs pubcrypt to be built with the `json` feature");
+}
+
+/**
+ * Split the private key at `KEYPATH` into `SHARES` shares, any `THRESHOLD` of which can decrypt,
+ * and write them to `OUTPREFIX.1` through `OUTPREFIX.<SHARES>` as in `write_private_file`. If any
+ * of them already exists, nothing is written unless `FORCE` is given.
+ */
+fn split_key(matches: &ArgMatches) -> Result<(), CryptError> {
+    let key = PrivateKey::from(read_elgamal_key(
+        matches.value_of("KEYPATH").unwrap(),
+        KeyKind::Private,
+        get_passphrase_path(matches).as_deref(),
+    ));
+    let threshold = ok_or_die(
+        matches.value_of("THRESHOLD").unwrap().parse::<usize>(),
+        "Invalid threshold",
+    );
+    let shares = ok_or_die(
+        matches.value_of("SHARES").unwrap().parse::<usize>(),
+        "Invalid share count",
+    );
+    if threshold == 0 || threshold > shares {
+        die!(Failure::Usage => "Invalid threshold; must be between 1 and the number of shares");
+    } else if shares > threshold::MAX_SHARES {
+        die!(Failure::Usage =>
+            "Too many shares; at most {} are supported",
+            threshold::MAX_SHARES
+        );
+    }
+
+    let prefix = matches.value_of("OUTPREFIX").unwrap();
+    if !matches.is_present("FORCE") {
+        for path in (1..=shares).map(|index| format!("{}.{}", prefix, index)) {
+            if replaces_file(&path) {
+                die!(Failure::Io => "{} already exists; give --force to replace it", path);
+            }
+        }
+    }
+
+    let mut rng = new_rng();
+    for share in threshold::split_key(&key, threshold, shares, &mut rng)? {
+        let path = format!("{}.{}", prefix, share.index());
+        let err_msg = format!("Failed to write share {}", path);
+        ok_or_die(
+            write_private_file(&path, &share.serialize(), true),
+            &err_msg,
+        );
+    }
+
+    Ok(())
+}
+
+/**
+ * Show the ElGamal public key at `KEYPATH` as a QR code of its compact key, writing it to `OUT` as
+ * a PNG image if it's given, or else drawing it in the terminal along with the compact key.
+ */
+#[cfg(feature = "qr")]
+fn show_qr(matches: &ArgMatches) -> io::Result<()> {
+    let key_path = matches.value_of("KEYPATH").unwrap();
+    let key = PublicKey::from(read_elgamal_key(key_path, KeyKind::Public, None));
+    let text = compact::to_compact(&key);
+    let modules = qr::QrModules::new(&text)?;
+
+    match matches.value_of("OUT") {
+        Some(path) => modules.write_png(BufWriter::new(File::create(path)?)),
+        None => {
+            print!("{}", modules.to_terminal());
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(feature = "qr"))]
+fn show_qr(_matches: &ArgMatches) -> io::Result<()> {
+    die!(Failure::Usage => "QR codes need pubcrypt to be built with the `qr` feature");
+}
+
+/**
+ * Reconstruct the private key from the shares at `SHAREPATH` and write it to `PRIV_OUT` as in
+ * `write_private_file`, replacing an existing file only if `FORCE` is given.
+ */
+fn combine_shares(matches: &ArgMatches) -> Result<(), CryptError> {
+    let mut shares = Vec::new();
+    for path in matches.values_of("SHAREPATH").unwrap() {
+        let bytes = ok_or_die(fs::read(path), &format!("Failed to read share {}", path));
+        let bytes: [u8; SHARE_BYTES] = match bytes.try_into() {
+            Ok(bytes) => bytes,
+            Err(_) => die!(Failure::BadKey => "Invalid share file {}", path),
+        };
+        shares.push(KeyShare::deserialize(&bytes));
+    }
+
+    let key = threshold::combine_shares(&shares)?;
+    let path = matches.value_of("PRIV_OUT").unwrap();
+    let err_msg = format!("Failed to write private key {}", path);
+    let force = matches.is_present("FORCE");
+    ok_or_die(write_private_file(path, &key.serialize(), force), &err_msg);
+    Ok(())
+}
+
+/**
+ * Print the private key at `PRIV_IN` as a mnemonic, `MNEMONIC_WORDS_PER_LINE` words per line.
+ */
+fn backup_key(matches: &ArgMatches) {
+    let key = PrivateKey::from(read_elgamal_key(
+        matches.value_of("PRIV_IN").unwrap(),
+        KeyKind::Private,
+        get_passphrase_path(matches).as_deref(),
+    ));
+
+    log::warn!("anyone with these words can decrypt anything sent to this key");
+    for line in mnemonic::to_mnemonic(&key).chunks(MNEMONIC_WORDS_PER_LINE) {
+        println!("{}", line.join(" "));
+    }
+}
+
+/**
+ * Read a mnemonic from `IN`, or from stdin if it isn't given, and write the private key it spells
+ * out to `PRIV_OUT` as in `genkey`, with new metadata. If `PUB_OUT` is given, the matching public
+ * key is written there too.
+ */
+fn restore_key(matches: &ArgMatches) -> io::Result<()> {
+    let mut text = String::new();
+    match matches.value_of("IN") {
+        Some(path) => text = ok_or_die(fs::read_to_string(path), "Failed to read mnemonic"),
+        None => {
+            ok_or_die(
+                io::stdin().read_to_string(&mut text),
+                "Failed to read mnemonic",
+            );
+        }
+    }
+
+    let key = ok_or_die(mnemonic::from_mnemonic(&text), "Invalid mnemonic");
+    let mut rng = new_rng();
+    ok_or_die(key.key().validate(&mut rng), "Invalid key in mnemonic");
+
+    let format = key_format(matches);
+    let mut metadata = key_metadata(matches, Algorithm::ElGamal);
+    metadata.sign(&key);
+
+    if let Some(pub_path) = matches.value_of("PUB_OUT") {
+        let public = write_key_file(&metadata, &key.derive_public().key().to_der());
+        write_output(pub_path, ArmorKind::PublicKey, &public, format, true)?;
+    }
+    let private = if matches.is_present("PROTECT") {
+        let passphrase = read_new_passphrase(get_passphrase_path(matches).as_deref());
+        let protected = keyfile::protect_key(&key, &passphrase, &mut rng);
+        write_key_file(&metadata, &protected)
+    } else {
+        write_key_file(&metadata, &key.key().to_der())
+    };
+    write_output(
+        matches.value_of("PRIV_OUT").unwrap(),
+        ArmorKind::PrivateKey,
+        &private,
+        format,
+        true,
+    )
+}
+
+/**
+ * Rewrite the key file or ciphertext at `IN` in the newest format, and write it to `OUT`, as armor
+ * if `ARMOR` is given. Key files without metadata get new metadata, while version 2 key files keep
+ * theirs; ElGamal key files are then signed by their private key, which is either the key itself
+ * when it's protected, or the key at `KEYPATH`. Headerless ciphertexts are decrypted with the
+ * private key at `KEYPATH`, and encrypted again for its public key with the default options.
+ *
+ * The migrated file is written as in `write_output`, so private keys are only readable by their
+ * owner. `OUT` can be `IN` itself, but any other existing file is only replaced if `FORCE` is
+ * given.
+ */
+fn migrate(matches: &ArgMatches) -> Result<(), CryptError> {
+    let in_path = matches.value_of("IN").unwrap();
+    let out_path = matches.value_of("OUT").unwrap();
+    let is_same_file = match (fs::canonicalize(in_path), fs::canonicalize(out_path)) {
+        (Ok(in_file), Ok(out_file)) => in_file == out_file,
+        _ => false,
+    };
+    if !matches.is_present("FORCE") && !is_same_file && replaces_file(out_path) {
+        die!(Failure::Io => "{} already exists; give --force to replace it", out_path);
+    }
+    let bytes = ok_or_die(fs::read(in_path), "Failed to read input file");
+    let err_msg = format!("Invalid input file {}", in_path);
+    if bytes.starts_with(&header::MAGIC) || volume::is_volume(&bytes) || pair::is_pair(&bytes) {
+        die!(Failure::Usage => "{} is already in the newest format", in_path);
+    }
+
+    let (is_key, bytes) = if armor::is_armored(&bytes) {
+        match ok_or_die(armor::dearmor(&bytes), &err_msg) {
+            (ArmorKind::Message, ciphertext) => (false, ciphertext),
+            (ArmorKind::KeyPair, _) => {
+                die!(Failure::Usage => "{} is already in the newest format", in_path)
+            }
+            _ => (true, read_key_file(in_path, KeyKind::Private)),
+        }
+    } else if is_key_file(&bytes) {
+        (true, read_key_file(in_path, KeyKind::Private))
+    } else {
+        (false, bytes)
+    };
+
+    let (kind, migrated) = if is_key {
+        migrate_key_file(matches, &bytes)
+    } else {
+        (ArmorKind::Message, migrate_ciphertext(matches, &bytes)?)
+    };
+
+    let format = if matches.is_present("ARMOR") {
+        KeyFormat::Armor
+    } else {
+        KeyFormat::Binary
+    };
+    Ok(write_output(
+        out_path,
+        kind,
+        &migrated,
+        format,
+        matches.is_present("FORCE") || is_same_file,
+    )?)
+}
+
+/**
+ * Rewrite the key file `bytes`, read from `IN`, with the newest version of its metadata, as
+ * described in `migrate`. Returns the kind of key along with the new file.
+ */
+fn migrate_key_file(matches: &ArgMatches, bytes: &[u8]) -> (ArmorKind, Vec<u8>) {
+    let in_path = matches.value_of("IN").unwrap();
+    let err_msg = format!("Invalid key file {}", in_path);
+    if metadata::version(bytes) == metadata::VERSION {
+        die!(Failure::Usage => "{} is already in the newest format", in_path);
+    }
+
+    let (metadata, algorithm, key_bytes) = split_key_file(bytes, &err_msg);
+    let mut metadata = metadata.unwrap_or_else(|| KeyMetadata::new(algorithm, now()));
+    if algorithm != Algorithm::ElGamal {
+        if matches.is_present("KEYPATH") {
+            die!(Failure::BadKey => "Only ElGamal key files can be signed");
+        }
+        let kind = match read_unprotected_key(algorithm, key_bytes, &err_msg) {
+            AnyKey::RsaPrivate(_) | AnyKey::EcPrivate(_) => ArmorKind::PrivateKey,
+            _ => ArmorKind::PublicKey,
+        };
+        return (kind, write_key_file(&metadata, key_bytes));
+    }
+
+    // a protected key is always a private key, so it can sign itself
+    let passphrase_path = get_passphrase_path(matches);
+    if keyfile::is_protected(key_bytes) {
+        let key = PrivateKey::from(read_elgamal_key(
+            in_path,
+            KeyKind::Private,
+            passphrase_path.as_deref(),
+        ));
+        metadata.sign(&key);
+        return (ArmorKind::PrivateKey, write_key_file(&metadata, key_bytes));
+    }
+
+    let key = match read_unprotected_key(algorithm, key_bytes, &err_msg) {
+        AnyKey::ElGamal(key) => key,
+        _ => unreachable!("ElGamal key files hold ElGamal keys"),
+    };
+    // an ElGamal key file without metadata doesn't say whether it's public or private
+    let kind = match matches.value_of("KEYPATH") {
+        Some(key_path) => {
+            let signer = PrivateKey::from(read_elgamal_key(
+                key_path,
+                KeyKind::Private,
+                passphrase_path.as_deref(),
+            ));
+            let kind = if *signer.key() == key {
+                ArmorKind::PrivateKey
+            } else if *signer.derive_public().key() == key {
+                ArmorKind::PublicKey
+            } else {
+                die!(Failure::BadKey => "{} isn't the private key of {}", key_path, in_path);
+            };
+            metadata.sign(&signer);
+            kind
+        }
+        None if matches.is_present("ARMOR") => {
+            die!(Failure::Usage => "--armor needs the key's private key, given with --key, to tell what kind of key it is")
+        }
+        None => {
+            log::warn!(
+                "the migrated key file isn't signed; give its private key with --key to sign it"
+            );
+            ArmorKind::PublicKey
+        }
+    };
+    (kind, write_key_file(&metadata, &key.to_der()))
+}
+
+/**
+ * Decrypt the headerless ciphertext `bytes` with the private key at `KEYPATH`, and encrypt the
+ * plaintext again for the matching public key, returning the new ciphertext.
+ */
+fn migrate_ciphertext(matches: &ArgMatches, bytes: &[u8]) -> Result<Vec<u8>, CryptError> {
+    let key_path = match matches.value_of("KEYPATH") {
+        Some(key_path) => key_path,
+        None => {
+            die!(Failure::Usage => "Migrating a ciphertext needs its private key; give it with --key")
+        }
+    };
+    let key = PrivateKey::from(read_elgamal_key(
+        key_path,
+        KeyKind::Private,
+        get_passphrase_path(matches).as_deref(),
+    ));
+    let public = key.derive_public();
+
+    let mut reader = bytes;
+    let mut decryptor = DecryptReader::new(&mut reader, key)?;
+    decryptor.verify()?;
+
+    let mut header = Header::new(Encoding::best_for(public.key().prime()));
+    header.recipient = Some(public.fingerprint());
+    let mut ciphertext = Vec::new();
+    let key = AnyKey::ElGamal(public.key().clone());
+    encrypt_to(&mut decryptor, &mut ciphertext, key, header, None)?;
+    Ok(ciphertext)
+}
+
+/**
+ * Decrypt the ciphertext at `IN` with the ElGamal private key at `OLD_PRIV`, and encrypt the
+ * plaintext again for the ElGamal public key at `NEW_PUB`, writing the new ciphertext to `OUT` in
+ * binary unless `ARMOR` is given. Either path can be `-` or left out for standard input or output.
+ *
+ * Both happen in a single pass, so the plaintext is never written anywhere, and binary ciphertexts
+ * aren't held in memory. The new ciphertext has the same options as the old one, except that it
+ * records the new key's fingerprint unless the old one was anonymous; legacy ciphertexts get the
+ * default options. An authenticated ciphertext's MAC is only checked once it's all been read, so
+ * the new ciphertext is written through a `PendingOutput`, and only put in place if decryption
+ * succeeds. An existing output file is only replaced if `FORCE` is given.
+ */
+fn rekey(matches: &ArgMatches) -> Result<(), CryptError> {
+    let old_path = matches.value_of("OLD_PRIV").unwrap();
+    let passphrase_path = get_passphrase_path(matches);
+    let in_path = matches.value_of("IN").unwrap_or(STDIO_PATH);
+    let out_path = matches.value_of("OUT").unwrap_or(STDIO_PATH);
+    // comparing the resolved paths also catches `./foo.enc` and symlinks to the input
+    let is_same_file = match (fs::canonicalize(in_path), fs::canonicalize(out_path)) {
+        (Ok(in_file), Ok(out_file)) => in_file == out_file,
+        _ => false,
+    };
+    if in_path != STDIO_PATH && (in_path == out_path || is_same_file) {
+        die!(Failure::Usage => "The new ciphertext can't overwrite the old one; write it to another file");
+    }
+
+    // without a terminal, a passphrase prompt would read the input instead of the passphrase
+    if in_path == STDIO_PATH
+        && needs_passphrase_from_stdin(old_path, KeyKind::Private, passphrase_path.as_deref())
+    {
+        die!(Failure::Usage =>
+            "{} is protected; give its passphrase with --passphrase-fd or --passphrase-file when reading standard input",
+            old_path
+        );
+    }
+    let old_key = PrivateKey::from(read_elgamal_key(
+        old_path,
+        KeyKind::Private,
+        passphrase_path.as_deref(),
+    ));
+    let new_key = read_elgamal_key(matches.value_of("NEW_PUB").unwrap(), KeyKind::Public, None);
+
+    let err_msg = format!("Failed to read input file {}", in_path);
+    let in_file: Box<dyn Read> = if in_path == STDIO_PATH {
+        Box::new(io::stdin())
+    } else {
+        Box::new(ok_or_die(File::open(in_path), &err_msg))
+    };
+    let mut reader = PeekReader::new(in_file);
+    let start = ok_or_die(reader.peek(ARMOR_DETECT_BYTES), &err_msg);
+    let reader: Box<dyn Read> = if armor::is_armored(start) {
+        let mut text = Vec::new();
+        ok_or_die(reader.read_to_end(&mut text), &err_msg);
+        let err_msg = format!("Invalid input file {}", in_path);
+        match ok_or_die(armor::dearmor(&text), &err_msg) {
+            (ArmorKind::Message, ciphertext) => Box::new(io::Cursor::new(ciphertext)),
+            _ => die!(Failure::Corrupt => "{}: it doesn't contain a ciphertext", err_msg),
+        }
+    } else {
+        Box::new(reader)
+    };
+
+    let mut decryptor = DecryptReader::new(reader, old_key)?;
+    let old_header = decryptor.header();
+    let anonymous = !old_header.is_empty() && old_header.recipient.is_none();
+    let mut header = if old_header.is_empty() {
+        Header::new(Encoding::best_for(new_key.prime()))
+    } else {
+        old_header.clone()
+    };
+    header.wrapped_key = None;
+    header.recipient = Some(new_key.fingerprint()).filter(|_| !anonymous);
+
+    let (output, mut writer): (_, Box<dyn Write>) = if out_path == STDIO_PATH {
+        (None, Box::new(BufWriter::new(io::stdout())))
+    } else {
+        let err_msg = format!("Failed to open output file {}", out_path);
+        let force = matches.is_present("FORCE");
+        let (output, file) = ok_or_die(PendingOutput::create(out_path, force), &err_msg);
+        (Some(output), Box::new(BufWriter::new(file)))
+    };
+    let new_key = AnyKey::ElGamal(new_key);
+    let result = if matches.is_present("ARMOR") {
+        let mut ciphertext = Vec::new();
+        encrypt_to(&mut decryptor, &mut ciphertext, new_key, header, None).and_then(|()| {
+            let text = armor::armor(ArmorKind::Message, &ciphertext);
+            Ok(writer.write_all(text.as_bytes())?)
+        })
+    } else {
+        encrypt_to(&mut decryptor, &mut writer, new_key, header, None)
+    };
+
+    // dropping the output without committing it removes the new ciphertext if anything failed
+    result.and_then(|()| Ok(writer.flush()?))?;
+    drop(writer);
+    match output {
+        Some(output) => Ok(output.commit()?),
+        None => Ok(()),
+    }
+}
+
+/**
+ * Wrap the binary key file, ciphertext, or detached signature at `IN` (standard input if it's `-`
+ * or isn't given) in armor, and write it to `OUT` (standard output likewise). The kind of file is
+ * detected unless it's given with `KIND`, which is needed for ElGamal key files that aren't signed.
+ */
+fn armor_file(matches: &ArgMatches) -> io::Result<()> {
+    let in_path = matches.value_of("IN").unwrap_or(STDIO_PATH);
+    let bytes = read_input(in_path);
+    if armor::is_armored(&bytes) {
+        die!(Failure::Usage => "{} is already armored", in_path);
+    }
+
+    let kind = match matches.value_of("KIND") {
+        Some("public") => ArmorKind::PublicKey,
+        Some("private") => ArmorKind::PrivateKey,
+        Some("message") => ArmorKind::Message,
+        Some("signature") => ArmorKind::Signature,
+        Some(_) => unreachable!("Clap should reject unknown kinds"),
+        None => detect_armor_kind(in_path, &bytes),
+    };
+    let text = armor::armor(kind, &bytes);
+    write_stdio_output(
+        matches.value_of("OUT").unwrap_or(STDIO_PATH),
+        text.as_bytes(),
+    )
+}
+
+/**
+ * Tell which kind of armor the binary file `bytes`, read from `in_path`, goes in, exiting the
+ * process if it isn't a pubcrypt file or its kind can't be told.
+ */
+fn detect_armor_kind(in_path: &str, bytes: &[u8]) -> ArmorKind {
+    if volume::is_volume(bytes) {
+        die!(Failure::Usage =>
+            "{} is a volume of a split ciphertext; only whole ciphertexts can be armored",
+            in_path
+        );
+    } else if bytes.starts_with(&header::MAGIC) {
+        return ArmorKind::Message;
+    } else if detached::is_detached_signature(bytes) {
+        return ArmorKind::Signature;
+    } else if pair::is_pair(bytes) {
+        return ArmorKind::KeyPair;
+    }
+
+    let is_binary_key = metadata::has_metadata(bytes)
+        || keyfile::is_protected(bytes)
+        || cipher::read_key_file(bytes).is_ok();
+    if !is_binary_key {
+        if is_key_file(bytes) {
+            die!(Failure::Usage => "{} is a text key file, so it can't be armored", in_path);
+        } else if bytes.is_empty() || !bytes.len().is_multiple_of(ElGamal::CIPHERTEXT_BYTES) {
+            die!(Failure::Corrupt => "{} isn't a key file, ciphertext, or signature", in_path);
+        }
+        // anything else is taken to be a legacy, headerless ciphertext, as in `inspect`
+        return ArmorKind::Message;
+    }
+
+    let err_msg = format!("Invalid key file {}", in_path);
+    let (metadata, algorithm, key_bytes) = split_key_file(bytes, &err_msg);
+    if keyfile::is_protected(key_bytes) {
+        return ArmorKind::PrivateKey;
+    }
+    match read_unprotected_key(algorithm, key_bytes, &err_msg) {
+        AnyKey::RsaPublic(_) | AnyKey::EcPublic(_) => ArmorKind::PublicKey,
+        AnyKey::RsaPrivate(_) | AnyKey::EcPrivate(_) => ArmorKind::PrivateKey,
+        AnyKey::ElGamal(key) => match elgamal_kind(&key, metadata.as_ref()) {
+            Some(KeyKind::Public) => ArmorKind::PublicKey,
+            Some(KeyKind::Private) => ArmorKind::PrivateKey,
+            None => die!(Failure::Usage =>
+                "{} doesn't say whether it's a public or private key; give it with --kind",
+                in_path
+            ),
+        },
+    }
+}
+
+/**
+ * Unwrap the armored key file, ciphertext, or detached signature at `IN` (standard input if it's
+ * `-` or isn't given), and write the binary file inside to `OUT` (standard output likewise).
+ */
+fn dearmor_file(matches: &ArgMatches) -> io::Result<()> {
+    let in_path = matches.value_of("IN").unwrap_or(STDIO_PATH);
+    let bytes = read_input(in_path);
+    if !armor::is_armored(&bytes) {
+        die!(Failure::Corrupt => "{} isn't armored", in_path);
+    }
+
+    let err_msg = format!("Invalid input file {}", in_path);
+    let (_, bytes) = ok_or_die(armor::dearmor(&bytes), &err_msg);
+    write_stdio_output(matches.value_of("OUT").unwrap_or(STDIO_PATH), &bytes)
+}
+
+/**
+ * Read all of the file at `path`, or standard input if it's `-`, exiting the process if it can't
+ * be read.
+ */
+fn read_input(path: &str) -> Vec<u8> {
+    let err_msg = format!("Failed to read input file {}", path);
+    if path == STDIO_PATH {
+        let mut bytes = Vec::new();
+        ok_or_die(io::stdin().read_to_end(&mut bytes), &err_msg);
+        bytes
+    } else {
+        ok_or_die(fs::read(path), &err_msg)
+    }
+}
+
+/**
+ * Write `bytes` to the file at `path`, or standard output if it's `-`.
+ */
+fn write_stdio_output(path: &str, bytes: &[u8]) -> io::Result<()> {
+    if path == STDIO_PATH {
+        io::stdout().write_all(bytes)
+    } else {
+        fs::write(path, bytes)
+    }
+}
+
+/**
+ * Manage the keyring with the `add`, `list`, `remove`, and `export` subcommands of `key`.
+ */
+fn manage_keyring(matches: &ArgMatches) -> io::Result<()> {
+    if let Some(matches) = matches.subcommand_matches("add") {
+        add_to_keyring(matches)
+    } else if matches.subcommand_matches("list").is_some() {
+        list_keyring()
+    } else if let Some(matches) = matches.subcommand_matches("remove") {
+        let name = matches.value_of("NAME").unwrap();
+        fs::remove_file(existing_keyring_path(name))
+    } else if let Some(matches) = matches.subcommand_matches("export") {
+        export_from_keyring(matches)
+    } else {
+        unreachable!("Clap should require a `key` subcommand")
+    }
+}
+
+/**
+ * Copy the key file at `KEYPATH` into the keyring as `NAME`, keeping its format. An existing key
+ * with the same name is only replaced if `FORCE` is given.
+ */
+fn add_to_keyring(matches: &ArgMatches) -> io::Result<()> {
+    let name = matches.value_of("NAME").unwrap();
+    let key_path = matches.value_of("KEYPATH").unwrap();
+    let path = keyring_path(name);
+    if path.exists() && !matches.is_present("FORCE") {
+        die!(Failure::Io =>
+            "There's already a key named {} in the keyring; give --force to replace it",
+            name
+        );
+    }
+
+    let bytes = read_key_file(key_path, KeyKind::Public);
+    split_key_file(&bytes, &format!("Invalid key file {}", key_path));
+    fs::create_dir_all(keyring_dir())?;
+    fs::copy(key_file_path(key_path), path)?;
+    Ok(())
+}
+
+/**
+ * Write the key named `NAME` out of the keyring as it was added, to `OUT`, or standard output if
+ * it's `-` or not given. A file is written through a `PendingOutput`, and only readable by its
+ * owner if the key may be private, as in `may_hold_private_key`. An existing file is only replaced
+ * if `FORCE` is given.
+ */
+fn export_from_keyring(matches: &ArgMatches) -> io::Result<()> {
+    let name = matches.value_of("NAME").unwrap();
+    let bytes = fs::read(existing_keyring_path(name))?;
+    let out_path = matches.value_of("OUT").unwrap_or(STDIO_PATH);
+    if out_path == STDIO_PATH {
+        return io::stdout().write_all(&bytes);
+    }
+
+    let err_msg = format!("Failed to write {}", out_path);
+    let (output, mut file) = ok_or_die(
+        PendingOutput::create(out_path, matches.is_present("FORCE")),
+        &err_msg,
+    );
+    if may_hold_private_key(&format!("{}{}", KEYRING_PREFIX, name)) {
+        output.make_private()?;
+    }
+    file.write_all(&bytes)?;
+    output.commit()
+}
+
+/**
+ * Check whether the key file at `key_path` may hold a private key: a key pair file, a protected
+ * key, an RSA or EC private key, or an ElGamal key that isn't signed as a public key, since
+ * unsigned ElGamal keys don't say which they are.
+ */
+fn may_hold_private_key(key_path: &str) -> bool {
+    let bytes = read_key_or_pair_file(key_path);
+    if pair::is_pair(&bytes) {
+        return true;
+    }
+
+    let err_msg = format!("Invalid key file {}", key_path);
+    let (metadata, algorithm, key_bytes) = split_key_file(&bytes, &err_msg);
+    if keyfile::is_protected(key_bytes) {
+        return true;
+    }
+    match read_unprotected_key(algorithm, key_bytes, &err_msg) {
+        AnyKey::RsaPublic(_) | AnyKey::EcPublic(_) => false,
+        AnyKey::RsaPrivate(_) | AnyKey::EcPrivate(_) => true,
+        AnyKey::ElGamal(key) => elgamal_kind(&key, metadata.as_ref()) != Some(KeyKind::Public),
+    }
+}
+
+/**
+ * Print the name, algorithm, and comment of each key in the keyring, one per line with tabs
+ * between them, in order of name.
+ */
+fn list_keyring() -> io::Result<()> {
+    let mut names = match fs::read_dir(keyring_dir()) {
+        Ok(entries) => entries
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<io::Result<Vec<_>>>()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    names.sort();
+
+    for name in names.iter().filter(|name| is_valid_key_name(name)) {
+        let key_path = format!("{}{}", KEYRING_PREFIX, name);
+        let bytes = read_key_file(&key_path, KeyKind::Public);
+        let (metadata, algorithm, _) = split_key_file(&bytes, &format!("Invalid key {}", name));
+        let comment = metadata
+            .map(|metadata| metadata.comment)
+            .unwrap_or_default();
+        println!("{}\t{}\t{}", name, algorithm, comment);
+    }
+    Ok(())
+}
+
+/**
+ * Get the path of the key file given as `key_path`, which is either a path or the name of a key
+ * in the keyring after `KEYRING_PREFIX`. Exits the process if there's no key with that name.
+ */
+fn key_file_path(key_path: &str) -> PathBuf {
+    match key_path.strip_prefix(KEYRING_PREFIX) {
+        Some(name) => existing_keyring_path(name),
+        None => PathBuf::from(key_path),
+    }
+}
+
+/**
+ * Get the path of the key named `name` in the keyring, exiting the process if there's no such
+ * key.
+ */
+fn existing_keyring_path(name: &str) -> PathBuf {
+    let path = keyring_path(name);
+    if !path.is_file() {
+        die!(Failure::Io => "There's no key named {} in the keyring", name);
+    }
+    path
+}
+
+/**
+ * Get the path that the key named `name` is kept at in the keyring, whether it's there or not.
+ * Exits the process if the name isn't valid.
+ */
+fn keyring_path(name: &str) -> PathBuf {
+    if !is_valid_key_name(name) {
+        die!(Failure::Usage =>
+            "Invalid key name {:?}; names can only contain letters, digits, ., _, -, and @, and can't start with .",
+            name
+        );
+    }
+    keyring_dir().join(name)
+}
+
+/**
+ * Check whether `name` can name a key in the keyring, i.e., whether it's a nonempty file name that
+ * doesn't start with `.`, made of letters, digits, `.`, `_`, `-`, and `@`.
+ */
+fn is_valid_key_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '@'))
+}
+
+/**
+ * Get the keyring's directory, `pubcrypt/keyring` under the user's data directory, which is
+ * `$XDG_DATA_HOME`, or `~/.local/share` if that isn't set.
+ */
+fn keyring_dir() -> PathBuf {
+    let data_dir = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => match env::var_os("HOME") {
+            Some(home) => Path::new(&home).join(".local").join("share"),
+            None => {
+                die!(Failure::Io => "The keyring can't be found, since neither $XDG_DATA_HOME nor $HOME is set")
+            }
+        },
+    };
+    data_dir.join("pubcrypt").join("keyring")
+}
+
+/**
+ * Encrypt as in `encrypt`, but with the algorithm `C` and the framing of `cipher::encrypt`, which
+ * only supports the default options. Exits if any others are given in `header` or `nonce`.
+ */
+fn encrypt_with<C: PublicKeyCipher, R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    header: &Header,
+    nonce: Option<&[u8]>,
+    key: &C::PublicKey,
+) -> Result<(), CryptError> {
+    let is_default = header.mode == Mode::Ecb
+        && header.padding == Padding::Random
+        && header.sequence_bytes == 0
+        && header.checksum_bytes == 0
+        && !header.contains_file
+        && !header.archive
+        && !header.chunked
+        && !header.oaep
+        && !header.authenticated
+        && header.block_bytes == C::max_block_bytes(key)
+        && nonce.is_none();
+    if !is_default {
+        die!(Failure::Usage =>
+            "{} keys can only encrypt in ECB mode with the default options",
+            C::ALGORITHM
+        );
+    }
+
+    let mut rng = new_rng();
+    cipher::encrypt::<C, _, _, _>(reader, writer, key, &mut rng)
+}
+
+/**
+ * Encrypt according to the configuration in `settings`. The plaintext is read
+ * from `settings.reader`, encrypted with the public key `settings.key` in the
+ * format described by `header`, and written to `settings.writer`. The header's
+ * encoding is replaced by the best one for the key. If `nonce` is given, the
+ * encryption is deterministic (see `EncryptWriter::deterministic`). Unless
+ * `settings.format` is binary, the ciphertext is converted to text once it's
+ * complete.
+ */
+fn encrypt(
+    settings: CryptSettings,
+    header: Header,
+    nonce: Option<&[u8]>,
+) -> Result<(), CryptError> {
+    let CryptSettings {
+        mut reader,
+        mut writer,
+        key,
+        format,
+        ..
+    } = settings;
+    if format == CiphertextFormat::Binary {
+        return encrypt_to(&mut reader, &mut writer, key, header, nonce);
+    }
+
+    let mut ciphertext = Vec::new();
+    encrypt_to(&mut reader, &mut ciphertext, key, header, nonce)?;
+    let text = match format {
+        CiphertextFormat::Armor => armor::armor(ArmorKind::Message, &ciphertext),
+        CiphertextFormat::Hex => hex::format_ciphertext(&ciphertext)?,
+        CiphertextFormat::Binary => unreachable!("Binary ciphertexts are written directly"),
+    };
+    writer.write_all(text.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/**
+ * Encrypt everything in `reader` with `key` and write the ciphertext to `writer`, as in `encrypt`.
+ */
+fn encrypt_to<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    key: AnyKey,
+    mut header: Header,
+    nonce: Option<&[u8]>,
+) -> Result<(), CryptError> {
+    let key = match key {
+        AnyKey::ElGamal(key) => PublicKey::from(key),
+        AnyKey::RsaPublic(key) => {
+            return encrypt_with::<Rsa, _, _>(reader, writer, &header, nonce, &key)
+        }
+        AnyKey::EcPublic(key) => {
+            return encrypt_with::<EcElGamal, _, _>(reader, writer, &header, nonce, &key)
+        }
+        AnyKey::RsaPrivate(_) | AnyKey::EcPrivate(_) => {
+            die!(Failure::BadKey => "Encryption needs a public key, not a private key")
+        }
+    };
+    let prime = key.key().prime();
+    header.encoding = Encoding::best_for(prime);
+
+    // every block is encoded as a full `Block`, whatever its size, so the prime has to exceed
+    // the largest one
+    if !header.encoding.supports(prime) {
+        die!(Failure::BadKey =>
+            "The key's {}-bit prime is too small to encrypt blocks; it must have more than {} bits",
+            Num::BITS - prime.leading_zeros(),
+            BLOCK_BYTES * 8
+        );
+    }
+    log::debug!(
+        "the key's {}-bit prime holds up to {}-byte blocks",
+        Num::BITS - prime.leading_zeros(),
+        header.max_block_bytes()
+    );
+
+    match nonce {
+        Some(nonce) => {
+            let encryptor = EncryptWriter::deterministic(writer, key, header, nonce)?;
+            copy_encrypted(reader, encryptor)
+        }
+        None => {
+            let rng = new_rng();
+            let encryptor = EncryptWriter::with_header(writer, key, header, rng)?;
+            copy_encrypted(reader, encryptor)
+        }
+    }
+}
+
+/**
+ * Encrypt everything in `reader` with `encryptor`, then finish the ciphertext.
+ */
+fn copy_encrypted<R: Read, W: Write, T: Rng>(
+    reader: &mut R,
+    mut encryptor: EncryptWriter<W, T>,
+) -> Result<(), CryptError> {
+    io::copy(reader, &mut encryptor)?;
+    encryptor.finish()?;
+    Ok(())
+}
+
+/**
+ * Parse a string of hexadecimal digits into bytes, two digits per byte.
+ */
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/**
+ * Decrypt according to the configuration in `settings`. The ciphertext is read
+ * from `settings.reader`, decrypted with the private key `settings.key`, and
+ * written to `settings.writer`. If the plaintext is a file container, only the
+ * file's contents are written, or with `settings.restore_dir`, the file is
+ * restored into that directory. Directory archives are always restored into
+ * `settings.restore_dir`.
+ */
+fn decrypt(mut settings: CryptSettings) -> Result<(), CryptError> {
+    let is_elgamal = matches!(settings.key, AnyKey::ElGamal(_));
+    if settings.restore_dir.is_some() && !is_elgamal {
+        die!(Failure::Usage => "Only ElGamal ciphertexts can hold file metadata to restore");
+    }
+
+    match settings.key {
+        AnyKey::ElGamal(key) => {
+            let key = PrivateKey::from(key);
+            let recipient = key.derive_public().fingerprint();
+            let mut decryptor = DecryptReader::new(&mut settings.reader, key)?;
+            decryptor.verify()?;
+            if decryptor.header().archive {
+                let dir = match settings.restore_dir.as_deref() {
+                    Some(dir) => dir,
+                    None => {
+                        die!(Failure::Usage => "The ciphertext holds a directory; decrypt it with --out naming an existing directory to restore it into")
+                    }
+                };
+                let extract = &settings.extract;
+                let entries = archive::unpack_selected(&mut decryptor, dir, |entry| {
+                    extract.is_empty()
+                        || extract.iter().any(|name| {
+                            *name == entry.path
+                                || *name == archive::obfuscated_name(&recipient, &entry.path)
+                        })
+                })?;
+                println!("Restored {} entries into {}", entries.len(), dir.display());
+            } else if !settings.extract.is_empty() {
+                die!(Failure::Usage => "The ciphertext doesn't hold a directory to extract entries from");
+            } else if decryptor.header().contains_file {
+                unpack_file(
+                    &mut decryptor,
+                    &mut settings.writer,
+                    settings.restore_dir.as_deref(),
+                )?;
+            } else if settings.restore_dir.is_some() {
+                die!(Failure::Usage => "The ciphertext doesn't hold file metadata to restore; encrypt with --store-metadata");
+            } else {
+                io::copy(&mut decryptor, &mut settings.writer)?;
+            }
+        }
+        AnyKey::RsaPrivate(key) => {
+            cipher::decrypt::<Rsa, _, _>(&mut settings.reader, &mut settings.writer, &key)?;
+        }
+        AnyKey::EcPrivate(key) => {
+            cipher::decrypt::<EcElGamal, _, _>(&mut settings.reader, &mut settings.writer, &key)?;
+        }
+        AnyKey::RsaPublic(_) | AnyKey::EcPublic(_) => {
+            die!(Failure::BadKey => "Decryption needs a private key, not a public key")
+        }
+    }
+
+    Ok(settings.writer.flush()?)
+}
+
+/**
+ * Unpack the file container read from `reader`, writing its contents to
+ * `writer`, or if `restore_dir` is given, to a new file in that directory with
+ * the file's name and modification time.
+ */
+fn unpack_file<R: Read>(
+    reader: &mut R,
+    writer: &mut dyn Write,
+    restore_dir: Option<&Path>,
+) -> Result<(), CryptError> {
+    let info = FileInfo::read(reader)?;
+    let dir = match restore_dir {
+        Some(dir) => dir,
+        None => return container::unpack(reader, writer, &info),
+    };
+
+    // an existing file is never replaced, since its name comes from the ciphertext
+    let path = dir.join(&info.name);
+    let err_msg = format!("Failed to create output file {}", path.display());
+    let file = ok_or_die(
+        OpenOptions::new().write(true).create_new(true).open(&path),
+        &err_msg,
+    );
+
+    let mut file_writer = BufWriter::new(file);
+    container::unpack(reader, &mut file_writer, &info)?;
+    let file = file_writer.into_inner().map_err(|e| e.into_error())?;
+    file.set_modified(UNIX_EPOCH + Duration::from_secs(info.modified))?;
+    println!("Restored {}", path.display());
+    Ok(())
+}
+
+/**
+ * Print the completion script for `shell`, as generated by `completions::script`.
+ */
+fn print_completions(shell: &str) {
+    let shell = match shell {
+        "bash" => Shell::Bash,
+        "zsh" => Shell::Zsh,
+        "fish" => Shell::Fish,
+        _ => unreachable!("Clap should reject unknown shells"),
+    };
+    print!("{}", completions::script(build_clap_app(), shell));
+}
+
+/**
+ * Build the command-line application using `clap`.
+ */
+fn build_clap_app() -> clap::App<'static, 'static> {
+    clap_app!(pubcrypt =>
+        (author: "jk977")
+        (about: "Public key encryption and decryption application")
+        (setting: AppSettings::SubcommandRequiredElseHelp)
+        (@arg VERBOSE:
+            -v --verbose +multiple +global
+            "Show more about what's being done; repeat for debugging and tracing messages"
+        )
+        (@arg SEED:
+            --seed +takes_value +global
+            "TESTING ONLY: derive all randomness from the given hex string, so runs can be reproduced exactly"
+        )
+        (@arg QUIET:
+            -q --quiet +global conflicts_with[VERBOSE]
+            "Only show errors, without warnings, progress, or a line for each file that succeeds with several inputs or --recursive"
+        )
+        (@subcommand genkey =>
+            (@arg PUB_OUT: --pub +takes_value "Output public key to given file, or standard output if it's -")
+            (@arg PRIV_OUT: --priv +takes_value "Output private key to given file")
+            (@arg OUT:
+                --out +takes_value conflicts_with[PUB_OUT PRIV_OUT]
+                "Output both keys to the given key pair file"
+            )
+            (@arg FORCE: --force "Replace the key files if they already exist")
+            (@arg ALGO: --algo +takes_value possible_values(&["elgamal", "rsa", "ec-elgamal"]) "Generate keys for the given algorithm")
+            (@arg GROUP: --group +takes_value "Generate the keys in the given fixed group, e.g., modp-64")
+            (@arg DHPARAM:
+                --dhparam +takes_value conflicts_with[GROUP]
+                "Generate the keys in the group from the given OpenSSL DH parameters file"
+            )
+            (@arg SEED_FILE:
+                --("from-seed-file") +takes_value conflicts_with[GROUP DHPARAM]
+                "Derive the keys from the contents of the given file, so they can be recreated"
+            )
+            (@arg MAX_ATTEMPTS:
+                --("max-attempts") +takes_value conflicts_with[GROUP DHPARAM SEED_FILE]
+                "Give up after the given number of attempts to find a prime (default: 8)"
+            )
+            (@arg THREADS:
+                --threads +takes_value conflicts_with[GROUP DHPARAM SEED_FILE]
+                "Search for a prime across the given number of threads (default: 1)"
+            )
+            (@arg BITS:
+                --bits +takes_value conflicts_with[GROUP DHPARAM SEED_FILE]
+                "Pick a prime with the given number of bits for the key"
+            )
+            (@arg PRIME_MIN:
+                --("prime-min") +takes_value conflicts_with[GROUP DHPARAM SEED_FILE BITS]
+                "Pick a prime no smaller than the given number for the key"
+            )
+            (@arg PRIME_MAX:
+                --("prime-max") +takes_value conflicts_with[GROUP DHPARAM SEED_FILE BITS]
+                "Pick a prime no larger than the given number for the key"
+            )
+            (@arg WITNESSES:
+                --witnesses +takes_value conflicts_with[GROUP DHPARAM SEED_FILE]
+                "Test primes with the given number of Miller-Rabin witnesses (default: 25)"
+            )
+            (@arg PROTECT: --protect "Encrypt the private key with a passphrase")
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value requires[PROTECT]
+                "Read the passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value requires[PROTECT] conflicts_with[PASSPHRASE_FD]
+                "Read the passphrase from the first line of the given file instead of prompting"
+            )
+            (@arg COMMENT: --comment +takes_value "Store the given comment in the key files")
+            (@arg EXPIRES: --expires +takes_value "Mark the keys as expiring after the given number of days")
+            (@arg FORMAT:
+                --format +takes_value possible_values(&["binary", "pem", "json", "text", "ssh", "compact"])
+                "Write the keys as binary (the default), PEM text, JSON, plain text, or a one-line or compact public key and binary private key (the last four for ElGamal keys only)"
+            )
+            (@arg ARMOR:
+                --armor conflicts_with[FORMAT]
+                "Write the keys as base64 text with a checksum that can be pasted into email"
+            )
+        )
+        (@subcommand inspect =>
+            (about: "Print the parameters of a key file or ciphertext, detecting which it is")
+            (@arg FILE: required_unless[KEYPATH] "Inspect the given key file or ciphertext")
+            (@arg KEYPATH: -k --key +takes_value conflicts_with[FILE] "Inspect the given file as a key file")
+        )
+        (@subcommand validate =>
+            (about: "Check a key file or ciphertext thoroughly before trusting it")
+            (@arg FILE: +required "The key file or ciphertext to check")
+            (@arg KEYPATH:
+                -k --key +takes_value
+                "Check a key file against the given key as the other half of its pair, or a ciphertext against the given ElGamal key as its recipient"
+            )
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the passphrase of a protected key from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the passphrase of a protected key from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand selftest =>
+            (about: "Check pubcrypt's arithmetic and encryption on this platform")
+        )
+        (@subcommand fingerprint =>
+            (about: "Print the fingerprints of ElGamal keys, to check them with their owners before encrypting")
+            (@arg KEYPATHS: +required +multiple "Read the keys from the given files")
+            (@arg JSON: --json "Print the keys as a JSON array")
+        )
+        (@subcommand pubkey =>
+            (about: "Recreate a public key from its private key")
+            (@arg PRIV_IN: --priv +takes_value +required "Read the private key from the given file")
+            (@arg PUB_OUT: -o --out +takes_value +required "Output the public key to the given file")
+            (@arg FORMAT:
+                --format +takes_value possible_values(&["binary", "pem", "json", "text", "ssh", "compact"])
+                "Write the public key as binary (the default), PEM text, JSON, plain text, one line, or a compact string (the last four for ElGamal keys only)"
+            )
+            (@arg ARMOR:
+                --armor conflicts_with[FORMAT]
+                "Write the public key as base64 text with a checksum that can be pasted into email"
+            )
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand dhparam =>
+            (about: "Export the group of an ElGamal key as OpenSSL DH parameters")
+            (@arg KEYPATH: -k --key +takes_value required_unless[GROUP] "Read the key from the given file")
+            (@arg GROUP:
+                --group +takes_value conflicts_with[KEYPATH]
+                "Export the given fixed group instead of a key's, e.g., modp-64"
+            )
+            (@arg OUT: -o --out +takes_value +required "Write the parameters to the given file")
+            (@arg FORMAT:
+                --format +takes_value possible_values(&["pem", "der"])
+                "Write the parameters as PEM text (the default) or as DER"
+            )
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand exchange =>
+            (about: "Derive a symmetric key shared with the owner of another ElGamal key in the same group")
+            (@arg PRIV_IN: --priv +takes_value +required "Read your private key from the given file")
+            (@arg PEER: --peer +takes_value +required "Read the other side's public key from the given file")
+            (@arg OUT:
+                -o --out +takes_value required_unless[CONFIRM]
+                "Write the derived key to the given file, or standard output if it's -"
+            )
+            (@arg LENGTH:
+                --length +takes_value
+                "Derive the given number of bytes (default: 32)"
+            )
+            (@arg INFO:
+                --info +takes_value
+                "Derive the key for the given purpose, so that one exchange can key several tools (default: pubcrypt exchange)"
+            )
+            (@arg FORMAT:
+                --format +takes_value possible_values(&["binary", "hex"])
+                "Write the key as raw bytes (the default) or as a line of hex"
+            )
+            (@arg FORCE: --force "Replace the output file if it already exists")
+            (@arg CONFIRM:
+                --confirm
+                "Print words for both sides to compare, e.g., over the phone, to check that neither public key was swapped"
+            )
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand import_pgp =>
+            (name: "import-pgp")
+            (about: "Import the ElGamal key from an OpenPGP public key, e.g., from gpg --export")
+            (@arg PGP_IN: -i --in +takes_value +required "Read the OpenPGP key from the given file")
+            (@arg PUB_OUT: -o --out +takes_value +required "Output the public key to the given file")
+            (@arg FORMAT:
+                --format +takes_value possible_values(&["binary", "pem", "json", "text", "ssh", "compact"])
+                "Write the public key as binary (the default), PEM text, JSON, plain text, one line, or a compact string"
+            )
+            (@arg ARMOR:
+                --armor conflicts_with[FORMAT]
+                "Write the public key as base64 text with a checksum that can be pasted into email"
+            )
+        )
+        (@subcommand qr =>
+            (about: "Show a public key as a QR code that another device can scan")
+            (@arg KEYPATH: -k --key +takes_value +required "Read the public key from the given file")
+            (@arg OUT:
+                -o --out +takes_value
+                "Write the QR code to the given file as a PNG image instead of drawing it in the terminal"
+            )
+        )
+        (@subcommand split_key =>
+            (name: "split-key")
+            (about: "Split a private key into shares, a threshold of which can reconstruct it")
+            (@arg KEYPATH: -k --key +takes_value +required "Read the private key from the given file")
+            (@arg THRESHOLD:
+                -t --threshold +takes_value +required
+                "Require the given number of shares to reconstruct the key"
+            )
+            (@arg SHARES: -n --shares +takes_value +required "Split the key into the given number of shares")
+            (@arg OUTPREFIX:
+                -o --out +takes_value +required
+                "Write share i to the given path with `.i` appended"
+            )
+            (@arg FORCE: --force "Replace the share files if they already exist")
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand combine =>
+            (about: "Reconstruct a private key from its shares")
+            (@arg SHAREPATH:
+                -s --share +takes_value +required +multiple number_of_values(1)
+                "Read a share from the given file (repeat for each share)"
+            )
+            (@arg PRIV_OUT: -o --out +takes_value +required "Output the private key to the given file")
+            (@arg FORCE: --force "Replace the private key file if it already exists")
+        )
+        (@subcommand backup =>
+            (about: "Print a private key as a list of words that can be written down")
+            (@arg PRIV_IN: --priv +takes_value +required "Read the private key from the given file")
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand restore =>
+            (about: "Recreate a private key from the words printed by backup")
+            (@arg IN: -i --in +takes_value "Read the words from the given file (default: stdin)")
+            (@arg PRIV_OUT: --priv +takes_value +required "Output the private key to the given file")
+            (@arg PUB_OUT: --pub +takes_value "Also output the public key to the given file")
+            (@arg PROTECT: --protect "Encrypt the private key with a passphrase")
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value requires[PROTECT]
+                "Read the passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value requires[PROTECT] conflicts_with[PASSPHRASE_FD]
+                "Read the passphrase from the first line of the given file instead of prompting"
+            )
+            (@arg COMMENT: --comment +takes_value "Store the given comment in the key files")
+            (@arg EXPIRES: --expires +takes_value "Mark the keys as expiring after the given number of days")
+            (@arg FORMAT:
+                --format +takes_value possible_values(&["binary", "pem"])
+                "Write the keys as binary (the default) or PEM text"
+            )
+            (@arg ARMOR:
+                --armor conflicts_with[FORMAT]
+                "Write the keys as base64 text with a checksum that can be pasted into email"
+            )
+        )
+        (@subcommand migrate =>
+            (about: "Rewrite a key file or ciphertext from an earlier version in the newest format")
+            (@arg IN: -i --in +takes_value +required "Read the key file or ciphertext from the given file")
+            (@arg OUT: -o --out +takes_value +required "Write the migrated file to the given file")
+            (@arg FORCE: --force "Replace the output file if it already exists and isn't the input")
+            (@arg KEYPATH:
+                -k --key +takes_value
+                "Decrypt the ciphertext, or sign the ElGamal key file, with the given private key"
+            )
+            (@arg ARMOR:
+                --armor
+                "Write the migrated file as base64 text with a checksum that can be pasted into email"
+            )
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand rekey =>
+            (about: "Encrypt a ciphertext for a new ElGamal key without writing out its plaintext")
+            (@arg OLD_PRIV:
+                --("old-priv") +takes_value +required
+                "Decrypt the ciphertext with the given private key"
+            )
+            (@arg NEW_PUB:
+                --("new-pub") +takes_value +required
+                "Encrypt it again for the given public key"
+            )
+            (@arg IN:
+                -i --in +takes_value
+                "Read the ciphertext from the given file, or standard input if it's - or not given"
+            )
+            (@arg OUT:
+                -o --out +takes_value
+                "Write the new ciphertext to the given file, or standard output if it's - or not given"
+            )
+            (@arg ARMOR:
+                --armor
+                "Write the new ciphertext as base64 text that can be pasted into email"
+            )
+            (@arg FORCE: --force "Replace the output file if it already exists")
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the old private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the old private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand armor =>
+            (about: "Wrap a binary key file, ciphertext, or signature in armor")
+            (@arg IN:
+                -i --in +takes_value
+                "Read the file from the given path, or standard input if it's - or not given"
+            )
+            (@arg OUT:
+                -o --out +takes_value
+                "Write the armor to the given path, or standard output if it's - or not given"
+            )
+            (@arg KIND:
+                --kind +takes_value possible_values(&["public", "private", "message", "signature"])
+                "Label the armor with the given kind of file instead of detecting it"
+            )
+        )
+        (@subcommand dearmor =>
+            (about: "Unwrap an armored key file, ciphertext, or signature")
+            (@arg IN:
+                -i --in +takes_value
+                "Read the armor from the given path, or standard input if it's - or not given"
+            )
+            (@arg OUT:
+                -o --out +takes_value
+                "Write the binary file to the given path, or standard output if it's - or not given"
+            )
+        )
+        (@subcommand key =>
+            (about: "Manage the keyring, whose keys can be given anywhere a key path can as name:<NAME>")
+            (setting: AppSettings::SubcommandRequiredElseHelp)
+            (@subcommand add =>
+                (about: "Copy a key file into the keyring under a name")
+                (@arg NAME: +required "The name to give the key")
+                (@arg KEYPATH: +required "The key file to add")
+                (@arg FORCE: --force "Replace any key that already has the name")
+            )
+            (@subcommand list =>
+                (about: "List the name, algorithm, and comment of each key in the keyring")
+            )
+            (@subcommand remove =>
+                (about: "Remove a key from the keyring")
+                (@arg NAME: +required "The name of the key to remove")
+            )
+            (@subcommand export =>
+                (about: "Write a key from the keyring out as it was added")
+                (@arg NAME: +required "The name of the key to export")
+                (@arg OUT:
+                    -o --out +takes_value
+                    "Write the key file to the given path, or standard output if it's - or not given"
+                )
+                (@arg FORCE: --force "Replace the output file if it already exists")
+            )
+        )
+        (@subcommand sign =>
+            (about: "Sign a file with an ElGamal private key")
+            (@arg DETACH:
+                --detach
+                "Write the signature to a separate file (the default, and currently the only kind of signature)"
+            )
+            (@arg KEYPATH: -k --key +takes_value +required "Read the private key from the given file")
+            (@arg IN:
+                -i --in +takes_value conflicts_with[FILE]
+                "Sign the given file, or standard input if it's -"
+            )
+            (@arg FILE: "The file to sign, as with --in (default: standard input)")
+            (@arg OUT:
+                -o --out +takes_value
+                "Write the signature to the given file, or standard output if it's - (default: the file with .sig appended, or standard output when signing standard input)"
+            )
+            (@arg ARMOR:
+                --armor
+                "Write the signature as base64 text with a checksum that can be pasted into email"
+            )
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+        (@subcommand verify =>
+            (about: "Check a detached signature on a file")
+            (@arg SIG: --sig +takes_value +required "Read the signature from the given file")
+            (@arg KEYPATH: -k --key +takes_value +required "Read the public key from the given file")
+            (@arg IN:
+                -i --in +takes_value conflicts_with[FILE]
+                "Check the signature on the given file, or standard input if it's -"
+            )
+            (@arg FILE: "The file that was signed, as with --in (default: standard input)")
+        )
+        (@subcommand completions =>
+            (about: "Print a shell completion script, which also completes key names from the keyring")
+            (@arg SHELL: +required possible_value[bash zsh fish] "The shell to complete for")
+        )
+        (@subcommand crypt =>
+            (@group mode =>
+                (@attributes +required)
+                (@arg ENCRYPT: -e "Sets algorithm to encrypt")
+                (@arg DECRYPT: -d "Sets algorithm to decrypt")
+            )
+            (@arg INPATH:
+                -i --in +takes_value +multiple number_of_values(1)
+                "Read the algorithm input from the given file, or standard input if it's - or not given. A directory is encrypted with everything in it. Can be repeated with an --out template"
+            )
+            (@arg OUTPATH:
+                -o --out +takes_value
+                "Write the algorithm output to the given file, or standard output if it's - or not given. Decrypting into an existing directory restores the stored file or directory there. {name} and {stem} are replaced by each input's file name, and its name without the last extension"
+            )
+            (@arg INPUTS:
+                +multiple +last
+                "Encrypt or decrypt each of the given files on its own, into the files named by the --out template"
+            )
+            (@arg FORCE: --force "Replace the output file if it already exists")
+            (@arg NO_PROGRESS: --("no-progress") "Don't show progress on standard error")
+            (@arg SHRED_INPUT:
+                --("shred-input") requires[ENCRYPT] conflicts_with[SPLIT_SIZE]
+                "Overwrite and delete the input file once its ciphertext is written (best-effort)"
+            )
+            (@arg DRY_RUN:
+                --("dry-run") requires[ENCRYPT] conflicts_with[SPLIT_SIZE SHRED_INPUT NAME_MAP]
+                "Print the output path, mode, block count, and exact ciphertext size without writing anything"
+            )
+            (@arg KEYPATH:
+                -k --key +takes_value
+                "Read the encryption/decryption key from the given file, or standard input if it's -"
+            )
+            (@arg KEY_FD:
+                --("key-fd") +takes_value conflicts_with[KEYPATH]
+                "Read the encryption/decryption key from the given file descriptor"
+            )
+            (@arg BLOCK_SIZE:
+                --("block-size") +takes_value requires[ENCRYPT]
+                "Encrypt in blocks of the given number of bytes (decryption reads it from the input)"
+            )
+            (@arg MODE:
+                --mode +takes_value possible_value[ecb cbc ctr hybrid] requires[ENCRYPT]
+                "Encrypt using the given block mode (default: cbc, or ecb for RSA and EC-ElGamal keys); decryption reads it from the input"
+            )
+            (@arg PADDING:
+                --padding +takes_value possible_value[random pkcs7 zero] requires[ENCRYPT]
+                "Pad the final block using the given scheme (decryption reads it from the input)"
+            )
+            (@arg AUTHENTICATE:
+                --authenticate requires[ENCRYPT]
+                "Append a MAC so that decryption detects tampering before writing any output"
+            )
+            (@arg OAEP:
+                --oaep requires[ENCRYPT]
+                "Pass each block through a randomized OAEP-style transform that detects tampering"
+            )
+            (@arg SEQUENCE:
+                --sequence requires[ENCRYPT]
+                "Number each block so that reordered or truncated ciphertexts fail to decrypt"
+            )
+            (@arg CHECKSUM:
+                --checksum requires[ENCRYPT]
+                "Checksum each block so that corrupted blocks are reported where they occur"
+            )
+            (@arg CHUNKED:
+                --chunked requires[ENCRYPT]
+                "Frame the ciphertext in chunks so that decryption stops at its end, for use in pipelines"
+            )
+            (@arg ANONYMOUS:
+                --anonymous requires[ENCRYPT]
+                "Don't record the recipient's key fingerprint in the ciphertext"
+            )
+            (@arg RECURSIVE:
+                -r --recursive conflicts_with[INPUTS]
+                "Encrypt or decrypt each file under the input directory on its own, into the same tree under the --out directory"
+            )
+            (@arg INCLUDE:
+                --include +takes_value +multiple number_of_values(1) requires[RECURSIVE]
+                "With --recursive, only handle files whose name or path matches the given pattern, where * and ? are wildcards"
+            )
+            (@arg EXCLUDE:
+                --exclude +takes_value +multiple number_of_values(1)
+                "Leave out entries of the input directory whose name or path matches the given pattern, where * and ? are wildcards"
+            )
+            (@arg NAME_MAP:
+                --("name-map") +takes_value requires[ENCRYPT]
+                "Write the obfuscated name and path of each entry of the input directory to the given file, for choosing entries with --extract"
+            )
+            (@arg EXTRACT:
+                --extract +takes_value +multiple number_of_values(1) requires[DECRYPT]
+                "Restore only the directory archive entry with the given path or obfuscated name, and everything inside it"
+            )
+            (@arg SPLIT_SIZE:
+                --("split-size") +takes_value requires[ENCRYPT]
+                "Split the ciphertext into numbered volumes of at most the given number of bytes, named after --out with .001, .002, and so on"
+            )
+            (@arg STORE_METADATA:
+                --("store-metadata") requires[ENCRYPT]
+                "Store the input file's name, size, modification time, and digest in the ciphertext"
+            )
+            (@arg RESTORE_METADATA:
+                --("restore-metadata") requires[DECRYPT]
+                "Restore the file under its stored name and modification time, in --out or the current directory"
+            )
+            (@arg DETERMINISTIC:
+                --deterministic requires[ENCRYPT NONCE]
+                "INSECURE: derive all randomness from --nonce, so equal inputs give equal output"
+            )
+            (@arg NONCE:
+                --nonce +takes_value requires[DETERMINISTIC]
+                "Use the given hex string as the nonce for deterministic encryption"
+            )
+            (@arg FORMAT:
+                --format +takes_value possible_values(&["binary", "hex"])
+                "Write the ciphertext as binary (the default) or as hex text, one block per line (decryption detects it)"
+            )
+            (@arg ARMOR:
+                --armor requires[ENCRYPT] conflicts_with[FORMAT]
+                "Write the ciphertext as base64 text that can be pasted into email (decryption detects it)"
+            )
+            (@arg PASSPHRASE_FD:
+                --("passphrase-fd") +takes_value
+                "Read the private key's passphrase from the given file descriptor instead of prompting"
+            )
+            (@arg PASSPHRASE_FILE:
+                --("passphrase-file") +takes_value conflicts_with[PASSPHRASE_FD]
+                "Read the private key's passphrase from the first line of the given file instead of prompting"
+            )
+        )
+    )
+}
 
-#[derive(Debug)]
-struct CryptSettings {
-    reader: BufReader<File>,
-    writer: BufWriter<File>,
-    key: Key,
+/**
+ * Read and validate the key at `key_path`, exiting the process if it can't be loaded. The key's
+ * algorithm is read from the file, and if it's a key pair file, the key of the given kind is read
+ * from it. If the key is passphrase-protected, the passphrase is read as in `read_passphrase`.
+ */
+fn read_key(key_path: &str, kind: KeyKind, passphrase_path: Option<&str>) -> AnyKey {
+    read_key_with_metadata(key_path, kind, passphrase_path).0
 }
 
-macro_rules! die {
-    ($($tts:tt)+) => {{
-        eprintln!($($tts)+);
-        std::process::exit(1);
-    }}
+/**
+ * Split the contents of a key file into its metadata, if it's a version 2 file, its algorithm,
+ * and the serialized or protected key, exiting the process with `err_msg` if it's invalid.
+ */
+fn split_key_file<'a>(
+    bytes: &'a [u8],
+    err_msg: &str,
+) -> (Option<KeyMetadata>, Algorithm, &'a [u8]) {
+    if metadata::has_metadata(bytes) {
+        let (metadata, key_bytes) = ok_or_die(KeyMetadata::read_key_file(bytes), err_msg);
+        let algorithm = metadata.algorithm;
+        (Some(metadata), algorithm, key_bytes)
+    } else if keyfile::is_protected(bytes) {
+        (None, Algorithm::ElGamal, bytes)
+    } else {
+        let (algorithm, key_bytes) = ok_or_die(cipher::read_key_file(bytes), err_msg);
+        (None, algorithm, key_bytes)
+    }
 }
 
 /**
- * Get the inner `Ok` value of the result. If the result is `Err(_)`, print an informational message
- * prefixed with `msg` and exit the process.
- *
- * Returns the `Ok` value of `r`. Does not return if `r` is an error.
+ * Print a warning if the key at `key_path` has expired.
  */
-fn ok_or_die<T, E: Display>(r: Result<T, E>, msg: &str) -> T {
-    match r {
-        Ok(val) => val,
-        Err(e) => die!("{}: {}", msg, e),
+fn warn_if_expired(key_path: &str, metadata: &KeyMetadata) {
+    if let Some(expires) = metadata.expires.filter(|_| metadata.is_expired(now())) {
+        log::warn!(
+            "the key in {} expired on {}",
+            key_path,
+            format_time(expires)
+        );
     }
 }
 
 /**
- * Generate a public-private key pair and write them to the values `PUB_OUT` and `PRIV_OUT` from
- * `matches`, respectively.
+ * Check the signature in `metadata` against the key from the same file.
  */
-fn gen_keys(matches: &ArgMatches) -> io::Result<()> {
-    use math::primes::PrimeError;
+fn check_signature(metadata: &KeyMetadata, key: &AnyKey) -> Result<(), KeyError> {
+    match key {
+        // a version 1 ElGamal key doesn't say whether it's public or private, so try both
+        AnyKey::ElGamal(key) => {
+            let derived = PrivateKey::from(key.clone()).derive_public();
+            let public = PublicKey::from(key.clone());
+            metadata
+                .verify(&public)
+                .or_else(|_| metadata.verify(&derived))
+        }
+        _ if metadata.signature.is_some() => Err(KeyError::InvalidSignature),
+        _ => Ok(()),
+    }
+}
 
-    let pub_path = matches.value_of("PUB_OUT").unwrap();
-    let priv_path = matches.value_of("PRIV_OUT").unwrap();
-    let mut rng = StdRng::from_entropy();
-    let keys = match KeyPair::generate(&mut rng) {
-        Err(PrimeError::InvalidRange) => die!("Failed to generate key; invalid prime range"),
-        Err(PrimeError::PrimeNotFound) => die!("Failed to generate key; no primes found"),
-        Ok(k) => k,
+/**
+ * Read the key at `key_path` as in `read_key`, along with its metadata if the file has any. A
+ * warning is printed if the key has expired, but it's still returned. If the metadata is signed,
+ * the process exits unless the signature matches the key.
+ */
+fn read_key_with_metadata(
+    key_path: &str,
+    kind: KeyKind,
+    passphrase_path: Option<&str>,
+) -> (AnyKey, Option<KeyMetadata>) {
+    let bytes = read_key_file(key_path, kind);
+    let err_msg = format!("Invalid key file {}", key_path);
+    let mut rng = new_rng();
+
+    let (metadata, algorithm, key_bytes) = split_key_file(&bytes, &err_msg);
+    if let Some(metadata) = &metadata {
+        warn_if_expired(key_path, metadata);
+    }
+
+    let key = if keyfile::is_protected(key_bytes) {
+        if algorithm != Algorithm::ElGamal {
+            die!(Failure::BadKey => "{}: only ElGamal keys can be protected", err_msg);
+        }
+
+        let prompt = format!("Passphrase for {}: ", key_path);
+        let passphrase = read_passphrase(&prompt, passphrase_path);
+        let key = ok_or_die(keyfile::unprotect_key(key_bytes, &passphrase), &err_msg);
+        let key = ok_or_die(Key::try_deserialize(&key.serialize(), &mut rng), &err_msg);
+        AnyKey::ElGamal(key)
+    } else {
+        read_unprotected_key(algorithm, key_bytes, &err_msg)
     };
 
-    let write_key = |path: &str, key: Key| fs::write(path, &key.serialize());
-    write_key(pub_path, keys.public)?;
-    write_key(priv_path, keys.private)?;
-    Ok(())
+    if let Some(metadata) = &metadata {
+        ok_or_die(check_signature(metadata, &key), &err_msg);
+    }
+
+    log::info!("read {} key from {}", algorithm, key_path);
+    if let AnyKey::ElGamal(key) = &key {
+        log::debug!(
+            "key {}: prime {}, root {}",
+            key.fingerprint(),
+            key.prime(),
+            key.root()
+        );
+    }
+    (key, metadata)
 }
 
 /**
- * Check if the reader is at the end of the file by trying to fill its buffer, then
- * checking whether or not it's empty. If it is, the reader has reached EOF. Otherwise,
- * there are still bytes remaining.
+ * Deserialize the unprotected key `key_bytes` for `algorithm`, exiting the process with `err_msg`
+ * if it's invalid.
  */
-fn reader_is_eof<R: BufRead>(reader: &mut R) -> io::Result<bool> {
-    reader.fill_buf().map(|buf| buf.is_empty())
+fn read_unprotected_key(algorithm: Algorithm, key_bytes: &[u8], err_msg: &str) -> AnyKey {
+    ok_or_die(deserialize_key(algorithm, key_bytes), err_msg)
 }
 
 /**
- * Encrypt the given block to the file specified in `settings`, using the
- * encryption key also present in `settings`.
+ * Deserialize and check the unprotected key `key_bytes` for `algorithm`.
  */
-fn encrypt_block_to_file<T: Rng>(
-    buf: [u8; BLOCK_BYTES],
-    settings: &mut CryptSettings,
-    rng: &mut T,
-) -> io::Result<()> {
-    let block = Block::from_be_bytes(buf);
-    let (c1, c2) = crypt::encrypt_block(block, &settings.key, rng);
-
-    settings.writer.write_all(&c1.to_be_bytes())?;
-    settings.writer.write_all(&c2.to_be_bytes())?;
-    Ok(())
+fn deserialize_key(algorithm: Algorithm, key_bytes: &[u8]) -> Result<AnyKey, KeyError> {
+    let mut rng = new_rng();
+    let key = match algorithm {
+        Algorithm::ElGamal => {
+            let key = ElGamal::deserialize_public_key(key_bytes, &mut rng)?;
+            AnyKey::ElGamal(key.key().clone())
+        }
+        // RSA public and private keys are different lengths, so the length tells them apart
+        Algorithm::Rsa if key_bytes.len() == RsaPublicKey::KEY_BYTES => {
+            AnyKey::RsaPublic(Rsa::deserialize_public_key(key_bytes, &mut rng)?)
+        }
+        Algorithm::Rsa => AnyKey::RsaPrivate(Rsa::deserialize_private_key(key_bytes, &mut rng)?),
+        Algorithm::EcElGamal if key_bytes.len() == EcPublicKey::KEY_BYTES => {
+            AnyKey::EcPublic(EcElGamal::deserialize_public_key(key_bytes, &mut rng)?)
+        }
+        Algorithm::EcElGamal => {
+            AnyKey::EcPrivate(EcElGamal::deserialize_private_key(key_bytes, &mut rng)?)
+        }
+    };
+    Ok(key)
 }
 
 /**
- * Fill unused space in the buffer with random bytes, and place the number of
- * pad bytes at the end of the buffer.
- *
- * Panics if `pad_bytes > buf.len()`.
+ * Check whether the key file at `key_path` holds a protected key, which needs a passphrase to
+ * read, taking the key of the given kind if it's a key pair file. Exits the process if the file
+ * can't be read.
  */
-fn pad_block<T: Rng, const N: usize>(buf: &mut [u8; N], pad_bytes: u8, rng: &mut T) {
-    assert!(pad_bytes as usize <= buf.len());
-
-    let pad_start = buf.len() - pad_bytes as usize;
-    let pad_end = buf.len() - 1;
-    rng.fill(&mut buf[pad_start..pad_end]);
-    buf[pad_end] = pad_bytes;
+fn is_protected_key_file(key_path: &str, kind: KeyKind) -> bool {
+    let bytes = read_key_file(key_path, kind);
+    let err_msg = format!("Invalid key file {}", key_path);
+    let (_, _, key_bytes) = split_key_file(&bytes, &err_msg);
+    keyfile::is_protected(key_bytes)
 }
 
 /**
- * Encrypt according to the configuration in `settings`. The plaintext is read
- * from `settings.reader`, encrypted with the public key `settings.key`, and
- * written to `settings.writer`.
+ * Check whether `bytes` look like a key file in any of the formats `read_key_file` accepts, other
+ * than armor.
  */
-fn encrypt_ecb(mut settings: CryptSettings) -> io::Result<()> {
-    let mut rng = StdRng::from_entropy();
-    let mut buf = [0_u8; BLOCK_BYTES];
-    let mut has_padded_block = false;
+fn is_key_file(bytes: &[u8]) -> bool {
+    metadata::has_metadata(bytes)
+        || pair::is_pair(bytes)
+        || keyfile::is_protected(bytes)
+        || cipher::read_key_file(bytes).is_ok()
+        || bytes.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{')
+        || oneline::is_line(bytes)
+        || compact::is_compact(bytes)
+        || text::is_text_key(bytes)
+}
 
-    while !reader_is_eof(&mut settings.reader)? {
-        let reader_buf = settings.reader.fill_buf()?;
-        let bytes_consumed = cmp::min(reader_buf.len(), buf.len());
-        let pad_bytes = (buf.len() - bytes_consumed) as u8;
+/**
+ * Read the key at `key_path` as in `read_key`, exiting the process if it isn't an ElGamal key.
+ */
+fn read_elgamal_key(key_path: &str, kind: KeyKind, passphrase_path: Option<&str>) -> Key {
+    match read_key(key_path, kind, passphrase_path) {
+        AnyKey::ElGamal(key) => key,
+        _ => die!(Failure::BadKey => "{} isn't an ElGamal key", key_path),
+    }
+}
 
-        // consume bytes directly from the reader's buffer since it doesn't
-        // support reading a sequence of bytes shorter than or equal in length
-        // to a given number
-        buf[..bytes_consumed].copy_from_slice(&reader_buf[..bytes_consumed]);
-        settings.reader.consume(bytes_consumed);
+/**
+ * Get the settings for `crypt` from `matches`, along with the output file to commit once the
+ * output is complete, if it's written to a single file.
+ */
+fn get_crypt_settings(matches: &ArgMatches) -> (CryptSettings, Option<PendingOutput>) {
+    let key_path = &crypt_key_path(matches);
+    let kind = crypt_key_kind(matches);
+    let passphrase_path = get_passphrase_path(matches);
+    let in_path = matches.value_of("INPATH").unwrap_or(STDIO_PATH);
 
-        // pad final byte with the length if not enough bytes were read
-        if pad_bytes > 0 {
-            pad_block(&mut buf, pad_bytes, &mut rng);
-            has_padded_block = true;
-        }
+    if key_path == STDIO_PATH && in_path == STDIO_PATH {
+        die!(Failure::Usage => "The key and the input can't both be read from standard input");
+    }
 
-        encrypt_block_to_file(buf, &mut settings, &mut rng)?;
+    // without a terminal, a passphrase prompt would read the input or the key instead of the
+    // passphrase
+    let reads_stdin = in_path == STDIO_PATH || key_path == STDIO_PATH;
+    if reads_stdin && needs_passphrase_from_stdin(key_path, kind, passphrase_path.as_deref()) {
+        die!(Failure::Usage =>
+            "{} is protected; give its passphrase with --passphrase-fd or --passphrase-file when reading standard input",
+            key_path
+        );
     }
 
-    if !has_padded_block {
-        // add an empty padded block at the end to prevent bad things when decrypting
-        let pad_bytes = buf.len() as u8;
-        pad_block(&mut buf, pad_bytes, &mut rng);
-        encrypt_block_to_file(buf, &mut settings, &mut rng)?;
+    let is_archive = matches.is_present("ENCRYPT") && is_directory(in_path);
+    if matches.is_present("EXCLUDE") && !is_archive {
+        die!(Failure::Usage => "--exclude needs an input directory");
+    } else if matches.is_present("NAME_MAP") && !is_archive {
+        die!(Failure::Usage => "--name-map needs an input directory");
     }
 
-    Ok(())
+    // load the key first so that an invalid key doesn't leave behind an empty output file
+    let key = read_key(key_path, kind, passphrase_path.as_deref());
+    open_crypt_files(matches, key, in_path, matches.value_of("OUTPATH"))
 }
 
 /**
- * Decrypt according to the configuration in `settings`. The ciphertext is read
- * from `settings.reader`, decrypted with the private key `settings.key`, and
- * written to `settings.writer`.
+ * Open the input and output of `crypt` at `in_path` and `out_path` for `key`, with the rest of
+ * the settings from `matches`, as in `get_crypt_settings`.
  */
-fn decrypt_ecb(mut settings: CryptSettings) -> io::Result<()> {
-    loop {
-        let buf = settings.reader.fill_buf()?;
+fn open_crypt_files(
+    matches: &ArgMatches,
+    key: AnyKey,
+    in_path: &str,
+    out_path: Option<&str>,
+) -> (CryptSettings, Option<PendingOutput>) {
+    let is_archive = matches.is_present("ENCRYPT") && is_directory(in_path);
+
+    let in_file: Box<dyn Read> = if in_path == STDIO_PATH {
+        Box::new(io::stdin())
+    } else if is_archive {
+        let exclude: Vec<&str> = matches.values_of("EXCLUDE").into_iter().flatten().collect();
+        let err_msg = format!("Failed to read input directory {}", in_path);
+        let archiver = ok_or_die(Archiver::new(Path::new(in_path), &exclude), &err_msg);
+        if let Some(map_path) = matches.value_of("NAME_MAP") {
+            let recipient = match &key {
+                AnyKey::ElGamal(key) => key.fingerprint(),
+                _ => die!(Failure::BadKey => "--name-map needs an ElGamal key"),
+            };
+            let err_msg = format!("Failed to write name map {}", map_path);
+            ok_or_die(write_name_map(map_path, &archiver, &recipient), &err_msg);
+        }
+        Box::new(archiver)
+    } else {
+        let err_msg = format!("Failed to open input file {}", in_path);
+        let file = ok_or_die(File::open(in_path), &err_msg);
+        match file.metadata() {
+            // only a regular file's size says how much is left to read
+            Ok(metadata) if metadata.is_file() && shows_progress(matches) => Box::new(
+                ProgressReader::new(file, in_path.to_string(), metadata.len()),
+            ),
+            _ => Box::new(file),
+        }
+    };
 
-        if buf.len() < NUM_BYTES * 2 {
-            let e_msg = format!(
-                "Decrypted file must have a multiple of {} bytes",
-                NUM_BYTES * 2
+    // when restoring, the output file or directory is named by the ciphertext
+    let is_restore = matches.is_present("RESTORE_METADATA")
+        || (matches.is_present("DECRYPT") && out_path.is_some_and(is_directory));
+    let out_path = out_path.unwrap_or(if is_restore { "." } else { STDIO_PATH });
+    let force = matches.is_present("FORCE");
+    let mut output = None;
+    let (writer, restore_dir): (Box<dyn Write>, _) = if is_restore {
+        let dir = PathBuf::from(out_path);
+        if !dir.is_dir() {
+            die!(Failure::Io => "Output directory {} doesn't exist", dir.display());
+        }
+        (Box::new(io::sink()), Some(dir))
+    } else if matches.is_present("DRY_RUN") {
+        // nothing is written, but an output that would be refused is still reported
+        if out_path != STDIO_PATH && !force && replaces_file(out_path) {
+            die!(Failure::Io => "{} already exists; give --force to replace it", out_path);
+        }
+        (Box::new(io::sink()), None)
+    } else if let Some(size) = matches.value_of("SPLIT_SIZE") {
+        let size = ok_or_die(size.parse::<u64>(), "Invalid split size");
+        if size <= volume::HEADER_BYTES as u64 {
+            die!(Failure::Usage =>
+                "Invalid split size; must be more than {} bytes",
+                volume::HEADER_BYTES
             );
-            let e = io::Error::new(io::ErrorKind::UnexpectedEof, e_msg);
-            return Err(e);
+        } else if out_path == STDIO_PATH {
+            die!(Failure::Usage => "--split-size needs an output file, not standard output");
         }
 
-        let mut c1_buf = [0_u8; NUM_BYTES];
-        let mut c2_buf = [0_u8; NUM_BYTES];
-        c1_buf.copy_from_slice(&buf[..NUM_BYTES]);
-        c2_buf.copy_from_slice(&buf[NUM_BYTES..NUM_BYTES * 2]);
-        settings.reader.consume(NUM_BYTES * 2);
+        let base = out_path.to_string();
+        let first_path = volume_path(&base, 0);
+        let open = move |index| {
+            let path = volume_path(&base, index);
+            let mut options = OpenOptions::new();
+            options.write(true);
+            if force {
+                options.create(true).truncate(true);
+            } else {
+                options.create_new(true);
+            }
+            options.open(path).map(BufWriter::new)
+        };
+        let id = new_rng().gen();
+        let err_msg = format!("Failed to open output file {}", first_path);
+        let volumes = ok_or_die(VolumeWriter::new(size, id, open), &err_msg);
+        (Box::new(volumes), None)
+    } else if out_path == STDIO_PATH {
+        (Box::new(BufWriter::new(io::stdout())), None)
+    } else {
+        let err_msg = format!("Failed to open output file {}", out_path);
+        let (pending, out_file) = ok_or_die(PendingOutput::create(out_path, force), &err_msg);
+        output = Some(pending);
+        (Box::new(BufWriter::new(out_file)), None)
+    };
+
+    let format = if matches.is_present("ARMOR") {
+        CiphertextFormat::Armor
+    } else if matches.value_of("FORMAT") == Some("hex") {
+        CiphertextFormat::Hex
+    } else {
+        CiphertextFormat::Binary
+    };
 
-        let c1 = Num::from_be_bytes(c1_buf);
-        let c2 = Num::from_be_bytes(c2_buf);
-        let decrypted = crypt::decrypt_block(c1, c2, &settings.key);
-        let block_bytes = Block::to_be_bytes(decrypted);
+    // armored and hex ciphertexts are detected, so decryption needs no format option
+    let mut reader = PeekReader::new(in_file);
+    let err_msg = format!("Failed to read input file {}", in_path);
 
-        if reader_is_eof(&mut settings.reader)? {
-            // last ciphertext block, so it contains a pad count
-            let pad_bytes = block_bytes[block_bytes.len() - 1] as usize;
-            assert!(pad_bytes <= block_bytes.len());
+    // split ciphertexts are read from their first volume, which names the rest
+    let start = ok_or_die(reader.peek(volume::MAGIC.len()), &err_msg);
+    let is_split = volume::is_volume(start);
+    if matches.is_present("DECRYPT") && is_split {
+        reader = PeekReader::new(open_volumes(in_path, reader));
+    }
 
-            let unpadded = &block_bytes[..block_bytes.len() - pad_bytes];
-            return settings.writer.write_all(unpadded);
+    let start = ok_or_die(reader.peek(ARMOR_DETECT_BYTES), &err_msg);
+    let is_armored = armor::is_armored(start);
+    let is_hex = format == CiphertextFormat::Hex || (!is_armored && hex::is_hex_ciphertext(start));
+    let reader: Box<dyn BufRead> = if matches.is_present("DECRYPT") && (is_hex || is_armored) {
+        let mut text = Vec::new();
+        ok_or_die(reader.read_to_end(&mut text), &err_msg);
+        let err_msg = format!("Invalid input file {}", in_path);
+        let ciphertext = if is_hex {
+            match hex::decode(&text) {
+                Some(ciphertext) => ciphertext,
+                None => die!(Failure::Corrupt => "{}: it isn't valid hex", err_msg),
+            }
         } else {
-            settings.writer.write_all(&block_bytes)?;
+            match ok_or_die(armor::dearmor(&text), &err_msg) {
+                (ArmorKind::Message, ciphertext) => ciphertext,
+                _ => die!(Failure::Corrupt => "{}: it contains a key, not a message", err_msg),
+            }
         };
+        Box::new(io::Cursor::new(ciphertext))
+    } else if matches.is_present("STORE_METADATA") {
+        if in_path == STDIO_PATH {
+            die!(Failure::Usage => "--store-metadata needs an input file, not standard input");
+        } else if is_archive {
+            die!(Failure::Usage => "--store-metadata can't be used with an input directory, whose metadata is always stored");
+        }
+        let info = file_info(in_path);
+        let packer = ok_or_die(
+            Packer::new(&info, reader),
+            "Failed to store the file's metadata",
+        );
+        Box::new(BufReader::new(packer))
+    } else {
+        Box::new(reader)
+    };
+
+    let settings = CryptSettings {
+        reader,
+        writer,
+        key,
+        format,
+        restore_dir,
+        extract: matches
+            .values_of("EXTRACT")
+            .into_iter()
+            .flatten()
+            .map(str::to_string)
+            .collect(),
+    };
+    (settings, output)
+}
+
+/**
+ * Check whether `crypt` should show a progress line for its input, which it does on a terminal
+ * unless `--quiet` or `--no-progress` is given, or debugging messages would interrupt it.
+ */
+fn shows_progress(matches: &ArgMatches) -> bool {
+    !matches.is_present("QUIET")
+        && !matches.is_present("NO_PROGRESS")
+        && log::max_level() < LevelFilter::Debug
+        && io::stderr().is_terminal()
+}
+
+/**
+ * Encrypt or decrypt with `settings` and `header` as `matches` asks, then put `output` in place
+ * if the output is written to a single file. With `--dry-run`, the ciphertext is only measured,
+ * as in `dry_run`.
+ */
+fn run_crypt(
+    matches: &ArgMatches,
+    settings: CryptSettings,
+    output: Option<PendingOutput>,
+    mut header: Header,
+    nonce: Option<&[u8]>,
+) -> Result<(), CryptError> {
+    // only ElGamal ciphertexts are decrypted with a stream that can check the recipient
+    match &settings.key {
+        AnyKey::ElGamal(key) if !matches.is_present("ANONYMOUS") => {
+            header.recipient = Some(key.fingerprint());
+        }
+        AnyKey::ElGamal(_) => {}
+        _ if !matches.is_present("MODE") => header.mode = Mode::Ecb,
+        _ => {}
+    }
+
+    if matches.is_present("DRY_RUN") {
+        return dry_run(matches, settings, header, nonce);
     }
+
+    let result = if matches.is_present("ENCRYPT") {
+        encrypt(settings, header, nonce)
+    } else {
+        decrypt(settings)
+    };
+
+    // the output file is only put in place once it's complete
+    result.and_then(|()| match output {
+        Some(output) => Ok(output.commit()?),
+        None => Ok(()),
+    })
 }
 
 /**
- * Build the command-line application using `clap`.
+ * Encrypt with `settings` and `header` as in `encrypt`, but count the ciphertext's bytes instead
+ * of writing them, then print where it would be written, its mode and block count, and its exact
+ * size next to the plaintext's. For files and archives, the plaintext is their container.
  */
-fn build_clap_app() -> clap::App<'static, 'static> {
-    clap_app!(pubcrypt =>
-        (author: "jk977")
-        (about: "Public key encryption and decryption application")
-        (setting: AppSettings::SubcommandRequiredElseHelp)
-        (@subcommand genkey =>
-            (@arg PUB_OUT: --pub +takes_value +required "Output public key to given file")
-            (@arg PRIV_OUT: --priv +takes_value +required "Output private key to given file")
-        )
-        (@subcommand crypt =>
-            (@group mode =>
-                (@attributes +required)
-                (@arg ENCRYPT: -e "Sets algorithm to encrypt")
-                (@arg DECRYPT: -d "Sets algorithm to decrypt")
-            )
-            (@arg INPATH:
-                -i --in +takes_value +required
-                "Read the algorithm input from the given file"
-            )
-            (@arg OUTPATH:
-                -o --out +takes_value +required
-                "Write the algorithm output to the given file"
-            )
-            (@arg KEYPATH:
-                -k --key +takes_value +required
-                "Read the encryption/decryption key from the given file"
-            )
-        )
-    )
+fn dry_run(
+    matches: &ArgMatches,
+    mut settings: CryptSettings,
+    header: Header,
+    nonce: Option<&[u8]>,
+) -> Result<(), CryptError> {
+    let plaintext_bytes = Rc::new(Cell::new(0));
+    let ciphertext_bytes = Rc::new(Cell::new(0));
+    let reader = ByteCounter::new(settings.reader, plaintext_bytes.clone());
+    settings.reader = Box::new(BufReader::new(reader));
+    settings.writer = Box::new(ByteCounter::new(io::sink(), ciphertext_bytes.clone()));
+    encrypt(settings, header.clone(), nonce)?;
+
+    let (plaintext_bytes, ciphertext_bytes) = (plaintext_bytes.get(), ciphertext_bytes.get());
+    let out_path = match matches.value_of("OUTPATH").unwrap_or(STDIO_PATH) {
+        STDIO_PATH => "standard output",
+        out_path => out_path,
+    };
+    println!("Output: {}", out_path);
+    println!("Mode: {}", header.mode);
+    println!("Block size: {} bytes", header.block_bytes);
+    println!("Blocks: {}", stream::count_blocks(&header, plaintext_bytes));
+    println!("Plaintext size: {} bytes", plaintext_bytes);
+    if plaintext_bytes == 0 {
+        println!("Ciphertext size: {} bytes", ciphertext_bytes);
+    } else {
+        println!(
+            "Ciphertext size: {} bytes ({:.2}x the plaintext)",
+            ciphertext_bytes,
+            ciphertext_bytes as f64 / plaintext_bytes as f64
+        );
+    }
+    Ok(())
 }
 
-fn get_crypt_settings(matches: &ArgMatches) -> CryptSettings {
-    let in_file = {
-        let in_path = matches.value_of("INPATH").unwrap();
-        let err_msg = format!("Failed to open input file {}", in_path);
-        ok_or_die(File::open(in_path), &err_msg)
+/**
+ * Get the inputs and outputs for `crypt_batch` from several inputs `inputs`, each of which is
+ * written to the file named by the `--out` template with `{name}` or `{stem}` filled in from the
+ * input's file name.
+ */
+fn template_jobs(matches: &ArgMatches, inputs: &[&str]) -> Vec<(String, String)> {
+    let template = match matches.value_of("OUTPATH") {
+        Some(template)
+            if template.contains(NAME_PLACEHOLDER) || template.contains(STEM_PLACEHOLDER) =>
+        {
+            template
+        }
+        _ => die!(Failure::Usage =>
+            "Several input files need an --out template containing {} or {}, e.g., {}.pce",
+            NAME_PLACEHOLDER,
+            STEM_PLACEHOLDER,
+            NAME_PLACEHOLDER
+        ),
     };
-    let out_file = {
-        let out_path = matches.value_of("OUTPATH").unwrap();
-        let err_msg = format!("Failed to open output file {}", out_path);
-        ok_or_die(File::create(out_path), &err_msg)
+    if matches.is_present("EXCLUDE") {
+        die!(Failure::Usage => "--exclude can't be used with an --out template");
+    }
+
+    inputs
+        .iter()
+        .map(|&in_path| {
+            let path = Path::new(in_path);
+            let (name, stem) = match (path.file_name(), path.file_stem()) {
+                _ if in_path == STDIO_PATH || is_directory(in_path) => die!(Failure::Usage =>
+                    "Invalid input {}: with an --out template, every input must be a file",
+                    in_path
+                ),
+                (Some(name), Some(stem)) => (name.to_string_lossy(), stem.to_string_lossy()),
+                _ => {
+                    die!(Failure::Usage => "Invalid input {}: it doesn't have a file name", in_path)
+                }
+            };
+            let out_path = template
+                .replace(NAME_PLACEHOLDER, &name)
+                .replace(STEM_PLACEHOLDER, &stem);
+            (in_path.to_string(), out_path)
+        })
+        .collect()
+}
+
+/**
+ * Get the inputs and outputs for `crypt_batch` with `--recursive`: every file under the input
+ * directory that isn't excluded by `--exclude` and, if any are given, matches an `--include`
+ * pattern. Each is written to the same path under the `--out` directory, with
+ * `RECURSIVE_SUFFIX` added when encrypting and removed when decrypting.
+ */
+fn recursive_jobs(matches: &ArgMatches, inputs: &[&str]) -> Vec<(String, String)> {
+    let in_dir = match inputs {
+        [in_dir] if is_directory(in_dir) => Path::new(in_dir),
+        _ => die!(Failure::Usage => "--recursive needs a single input directory"),
     };
-    let key = {
-        let key_path = matches.value_of("KEYPATH").unwrap();
-        let mut key_file = ok_or_die(File::open(key_path), "Failed to open key file");
-        let mut buf = [0u8; Key::KEY_BYTES];
-        ok_or_die(
-            key_file.read_exact(&mut buf),
-            "Failed to read key from file",
+    let out_dir = match matches.value_of("OUTPATH") {
+        Some(out_dir) if out_dir != STDIO_PATH => Path::new(out_dir),
+        _ => die!(Failure::Usage => "--recursive needs an output directory, not standard output"),
+    };
+    if out_dir.exists() && !out_dir.is_dir() {
+        die!(Failure::Io => "{} isn't a directory", out_dir.display());
+    }
+
+    let exclude: Vec<&str> = matches.values_of("EXCLUDE").into_iter().flatten().collect();
+    let include: Vec<&str> = matches.values_of("INCLUDE").into_iter().flatten().collect();
+    let err_msg = format!("Failed to read input directory {}", in_dir.display());
+    let files = ok_or_die(archive::list_files(in_dir, &exclude), &err_msg);
+
+    // outputs from an earlier run inside the input directory aren't inputs
+    let skipped = fs::canonicalize(out_dir).ok();
+    let encrypting = matches.is_present("ENCRYPT");
+    files
+        .into_iter()
+        .filter(|path| {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            include.is_empty()
+                || include.iter().any(|pattern| {
+                    archive::matches_pattern(pattern, name)
+                        || archive::matches_pattern(pattern, path)
+                })
+        })
+        .filter(
+            |path| match (&skipped, fs::canonicalize(in_dir.join(path))) {
+                (Some(skipped), Ok(file)) => !file.starts_with(skipped),
+                _ => true,
+            },
+        )
+        .map(|path| {
+            let out_path = if encrypting {
+                format!("{}{}", path, RECURSIVE_SUFFIX)
+            } else {
+                path.strip_suffix(RECURSIVE_SUFFIX)
+                    .unwrap_or(&path)
+                    .to_string()
+            };
+            let in_path = in_dir.join(&path).to_string_lossy().into_owned();
+            let out_path = out_dir.join(out_path).to_string_lossy().into_owned();
+            (in_path, out_path)
+        })
+        .collect()
+}
+
+/**
+ * Encrypt or decrypt each input of `jobs` on its own into its output, with `header`, as `matches`
+ * asks. The key is only read once. Whether each file succeeded is printed, and the process exits
+ * with an error if any failed.
+ */
+fn crypt_batch(
+    matches: &ArgMatches,
+    jobs: &[(String, String)],
+    header: Header,
+    nonce: Option<&[u8]>,
+) {
+    for &(arg, flag) in &[
+        ("SPLIT_SIZE", "--split-size"),
+        ("RESTORE_METADATA", "--restore-metadata"),
+        ("EXTRACT", "--extract"),
+        ("NAME_MAP", "--name-map"),
+        ("DRY_RUN", "--dry-run"),
+    ] {
+        if matches.is_present(arg) {
+            die!(Failure::Usage => "{} can't be used with several inputs or --recursive", flag);
+        }
+    }
+
+    let key = read_key(
+        &crypt_key_path(matches),
+        crypt_key_kind(matches),
+        get_passphrase_path(matches).as_deref(),
+    );
+    let mut failures = 0;
+    for (in_path, out_path) in jobs {
+        match crypt_batch_file(matches, &key, in_path, out_path, header.clone(), nonce) {
+            Ok(()) if matches.is_present("QUIET") => {}
+            Ok(()) => println!("ok\t{}\t{}", in_path, out_path),
+            Err(e) => {
+                println!("FAIL\t{}\t{}", in_path, e);
+                failures += 1;
+            }
+        }
+    }
+
+    if matches.is_present("SHRED_INPUT") && failures < jobs.len() {
+        log::warn!(
+            "the input files were overwritten and deleted, but copies of them may \
+             survive on SSDs, copy-on-write or journaling filesystems, and in backups or \
+             snapshots."
         );
-        Key::deserialize(&buf)
+    }
+    if failures > 0 {
+        die!(Failure::Other => "{} of {} files failed", failures, jobs.len());
+    }
+}
+
+/**
+ * Encrypt or decrypt the file at `in_path` into `out_path` for `crypt_batch`, returning a
+ * description of what went wrong if it fails. With `--recursive`, the output's parent
+ * directories are created as needed.
+ */
+fn crypt_batch_file(
+    matches: &ArgMatches,
+    key: &AnyKey,
+    in_path: &str,
+    out_path: &str,
+    header: Header,
+    nonce: Option<&[u8]>,
+) -> Result<(), String> {
+    // opening the files exits on failure, so anything likely to fail is checked first
+    if let Err(e) = File::open(in_path) {
+        return Err(format!("failed to open it: {}", e));
+    } else if !matches.is_present("FORCE") && replaces_file(out_path) {
+        return Err(format!(
+            "{} already exists; give --force to replace it",
+            out_path
+        ));
+    }
+    let shred = matches.is_present("SHRED_INPUT");
+    if shred && Path::new(in_path).is_symlink() {
+        return Err("--shred-input can't be used with a symbolic link".to_string());
+    } else if shred && fs::canonicalize(in_path).ok() == fs::canonicalize(out_path).ok() {
+        return Err("--shred-input can't be used when the output replaces the input".to_string());
+    }
+    if matches.is_present("RECURSIVE") {
+        if let Some(dir) = Path::new(out_path).parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        }
+    }
+
+    let (settings, output) = open_crypt_files(matches, key.clone(), in_path, Some(out_path));
+    run_crypt(matches, settings, output, header, nonce).map_err(|e| e.to_string())?;
+    if shred {
+        shred_file(in_path).map_err(|e| format!("failed to shred it: {}", e))?;
+    }
+    Ok(())
+}
+
+/**
+ * Write the obfuscated name of each entry that `archiver` archives for the key with the
+ * fingerprint `recipient` to the file at `path`, one per line, followed by the entry's path.
+ */
+fn write_name_map(path: &str, archiver: &Archiver, recipient: &Fingerprint) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for entry in archiver.entries() {
+        let name = archive::obfuscated_name(recipient, &entry.path);
+        writeln!(writer, "{}  {}", name, entry.path)?;
+    }
+    writer.flush()
+}
+
+/**
+ * Read the split ciphertext whose first volume, at `in_path`, is read from `first`, opening the
+ * rest of its volumes alongside it. Exits the process if `in_path` isn't the first volume.
+ */
+fn open_volumes<R: Read + 'static>(in_path: &str, first: R) -> Box<dyn Read> {
+    let base = match in_path.strip_suffix(FIRST_VOLUME_SUFFIX) {
+        Some(base) => base.to_string(),
+        None => die!(Failure::Usage =>
+            "{} is a volume of a split ciphertext; use the first volume, ending in {}",
+            in_path,
+            FIRST_VOLUME_SUFFIX
+        ),
     };
+    let open = move |index| {
+        File::open(volume_path(&base, index)).map(|file| Box::new(file) as Box<dyn Read>)
+    };
+    let first: Box<dyn Read> = Box::new(first);
+    let err_msg = format!("Invalid input file {}", in_path);
+    Box::new(ok_or_die(VolumeReader::new(first, open), &err_msg))
+}
 
-    CryptSettings {
-        reader: BufReader::new(in_file),
-        writer: BufWriter::new(out_file),
-        key,
+/**
+ * Get the path of the volume with the given index when a ciphertext written to `base` is split
+ * with `--split-size`, e.g., `out.pce.001` for the first one.
+ */
+fn volume_path(base: &str, index: u32) -> String {
+    format!("{}.{:03}", base, index as u64 + 1)
+}
+
+/**
+ * Check whether `path` names an existing directory, rather than a file or standard input or
+ * output.
+ */
+fn is_directory(path: &str) -> bool {
+    path != STDIO_PATH && Path::new(path).is_dir()
+}
+
+/**
+ * Overwrite the file at `path` with random bytes, sync it to disk, and delete it, for
+ * `--shred-input`. This is best-effort, since the storage may keep the old contents elsewhere.
+ * Symbolic links are refused, since deleting the link would leave its overwritten target behind.
+ */
+fn shred_file(path: &str) -> io::Result<()> {
+    if Path::new(path).is_symlink() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "it's a symbolic link",
+        ));
+    }
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let mut rng = StdRng::from_entropy();
+    let mut buf = [0_u8; 8192];
+    while remaining > 0 {
+        let len = remaining.min(buf.len() as u64) as usize;
+        rng.fill(&mut buf[..len]);
+        file.write_all(&buf[..len])?;
+        remaining -= len as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
+
+/**
+ * Get the metadata of the file at `path` to store with `--store-metadata`.
+ */
+fn file_info(path: &str) -> FileInfo {
+    let err_msg = format!("Failed to read the metadata of input file {}", path);
+    let metadata = ok_or_die(fs::metadata(path), &err_msg);
+    let name = match Path::new(path).file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.to_string(),
+        None => die!(Failure::Io => "{}: its name isn't valid UTF-8", err_msg),
+    };
+
+    // files modified before the epoch are recorded as modified at it
+    let modified = ok_or_die(metadata.modified(), &err_msg)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    FileInfo {
+        name,
+        size: metadata.len(),
+        modified,
     }
 }
 
 fn main() {
-    let matches = build_clap_app().get_matches();
+    let matches = match build_clap_app().get_matches_safe() {
+        Ok(matches) => matches,
+        Err(e) if e.use_stderr() => {
+            eprintln!("{}", e.message);
+            std::process::exit(Failure::Usage.exit_code());
+        }
+        // help and version information aren't errors
+        Err(e) => e.exit(),
+    };
+    init_logging(&matches);
+    init_rng(&matches);
 
     if let Some(matches) = matches.subcommand_matches("genkey") {
         // `genkey` subcommand; generate public/private key pair
         ok_or_die(gen_keys(matches), "Failed to generate and write keys");
+    } else if let Some(matches) = matches.subcommand_matches("pubkey") {
+        // `pubkey` subcommand; derive a public key from its private key
+        ok_or_die(
+            derive_pubkey(matches),
+            "Failed to derive and write public key",
+        );
+    } else if let Some(matches) = matches.subcommand_matches("inspect") {
+        // `inspect` subcommand; print a key's or ciphertext's parameters
+        ok_or_die(inspect(matches), "Failed to inspect file");
+    } else if let Some(matches) = matches.subcommand_matches("validate") {
+        // `validate` subcommand; check a key file or ciphertext thoroughly
+        ok_or_die(validate(matches), "Validation failed");
+    } else if matches.subcommand_matches("selftest").is_some() {
+        // `selftest` subcommand; check the arithmetic and encryption with an ephemeral key pair
+        self_test();
+    } else if let Some(matches) = matches.subcommand_matches("fingerprint") {
+        // `fingerprint` subcommand; list keys' fingerprints
+        list_fingerprints(matches);
+    } else if let Some(matches) = matches.subcommand_matches("dhparam") {
+        // `dhparam` subcommand; export a key's group for OpenSSL
+        ok_or_die(export_dhparam(matches), "Failed to write DH parameters");
+    } else if let Some(matches) = matches.subcommand_matches("exchange") {
+        // `exchange` subcommand; derive a key shared with another key's owner
+        ok_or_die(exchange(matches), "Failed to write the derived key");
+    } else if let Some(matches) = matches.subcommand_matches("import-pgp") {
+        // `import-pgp` subcommand; import an ElGamal key from OpenPGP
+        ok_or_die(import_pgp(matches), "Failed to import and write key");
+    } else if let Some(matches) = matches.subcommand_matches("qr") {
+        // `qr` subcommand; show a public key as a QR code
+        ok_or_die(show_qr(matches), "Failed to write QR code");
+    } else if let Some(matches) = matches.subcommand_matches("split-key") {
+        // `split-key` subcommand; split a private key into shares
+        ok_or_die(split_key(matches), "Failed to split key");
+    } else if let Some(matches) = matches.subcommand_matches("combine") {
+        // `combine` subcommand; reconstruct a private key from its shares
+        ok_or_die(combine_shares(matches), "Failed to combine shares");
+    } else if let Some(matches) = matches.subcommand_matches("backup") {
+        // `backup` subcommand; print a private key as a mnemonic
+        backup_key(matches);
+    } else if let Some(matches) = matches.subcommand_matches("restore") {
+        // `restore` subcommand; recreate a private key from its mnemonic
+        ok_or_die(restore_key(matches), "Failed to restore and write keys");
+    } else if let Some(matches) = matches.subcommand_matches("migrate") {
+        // `migrate` subcommand; rewrite a legacy file in the newest format
+        ok_or_die(migrate(matches), "Failed to migrate file");
+    } else if let Some(matches) = matches.subcommand_matches("rekey") {
+        // `rekey` subcommand; encrypt a ciphertext for a new key
+        ok_or_die(rekey(matches), "Failed to rekey ciphertext");
+    } else if let Some(matches) = matches.subcommand_matches("armor") {
+        // `armor` subcommand; wrap a binary file in armor
+        ok_or_die(armor_file(matches), "Failed to write output file");
+    } else if let Some(matches) = matches.subcommand_matches("dearmor") {
+        // `dearmor` subcommand; unwrap an armored file
+        ok_or_die(dearmor_file(matches), "Failed to write output file");
+    } else if let Some(matches) = matches.subcommand_matches("key") {
+        // `key` subcommand; manage the keyring
+        ok_or_die(manage_keyring(matches), "Failed to update keyring");
+    } else if let Some(matches) = matches.subcommand_matches("sign") {
+        // `sign` subcommand; write a detached signature
+        ok_or_die(sign_file(matches), "Failed to sign file");
+    } else if let Some(matches) = matches.subcommand_matches("verify") {
+        // `verify` subcommand; check a detached signature
+        verify_file(matches);
+    } else if let Some(matches) = matches.subcommand_matches("completions") {
+        // `completions` subcommand; print a shell completion script
+        print_completions(matches.value_of("SHELL").unwrap());
     } else if let Some(matches) = matches.subcommand_matches("crypt") {
         // `crypt` subcommand; handle encryption/decryption
         assert!(matches.is_present("ENCRYPT") || matches.is_present("DECRYPT"));
-        let settings = get_crypt_settings(matches);
 
-        if matches.is_present("ENCRYPT") {
-            ok_or_die(encrypt_ecb(settings), "Encryption failed");
+        // the encoding depends on the key, so it's filled in once the key is loaded
+        let mut header = Header::new(Encoding::QuadraticResidue);
+        header.mode = match matches.value_of("MODE") {
+            Some("cbc") => Mode::Cbc,
+            Some("ctr") => Mode::Ctr,
+            Some("hybrid") => Mode::Hybrid,
+            Some("ecb") => Mode::Ecb,
+            // only ElGamal keys can chain blocks, so `run_crypt` falls back to ECB for the rest
+            None => Mode::Cbc,
+            Some(_) => unreachable!("Clap should reject unknown modes"),
+        };
+        header.padding = match matches.value_of("PADDING") {
+            Some("pkcs7") => Padding::Pkcs7,
+            Some("zero") => Padding::Zero,
+            Some("random") | None => Padding::Random,
+            Some(_) => unreachable!("Clap should reject unknown padding schemes"),
+        };
+        if matches.is_present("SEQUENCE") {
+            header.sequence_bytes = SEQUENCE_BYTES;
+        }
+        if matches.is_present("CHECKSUM") {
+            header.checksum_bytes = CHECKSUM_BYTES;
+        }
+        header.contains_file = matches.is_present("STORE_METADATA");
+        header.archive = matches.is_present("ENCRYPT")
+            && !matches.is_present("RECURSIVE")
+            && is_directory(matches.value_of("INPATH").unwrap_or(STDIO_PATH));
+        header.chunked = matches.is_present("CHUNKED");
+        header.oaep = matches.is_present("OAEP");
+        header.authenticated = matches.is_present("AUTHENTICATE");
+
+        let stream_mode = match header.mode {
+            Mode::Ctr => Some("CTR"),
+            Mode::Hybrid => Some("hybrid"),
+            Mode::Ecb | Mode::Cbc => None,
+        };
+        if let Some(name) = stream_mode {
+            if header.sequence_bytes > 0 {
+                die!(Failure::Usage => "Sequence numbers can't be used in {} mode", name);
+            } else if header.checksum_bytes > 0 {
+                die!(Failure::Usage => "Checksums can't be used in {} mode", name);
+            } else if header.padding != Padding::Random {
+                die!(Failure::Usage => "Padding can't be used in {} mode", name);
+            } else if header.oaep {
+                die!(Failure::Usage => "The OAEP transform can't be used in {} mode", name);
+            }
+        }
+
+        // sequence numbers, checksums, and the OAEP transform share each block with the plaintext
+        let max_block_bytes = header.max_block_bytes();
+        if max_block_bytes == 0 {
+            die!(Failure::Usage =>
+                "Sequence numbers, checksums, and the OAEP transform don't fit in a block together"
+            );
+        }
+
+        header.block_bytes = match matches.value_of("BLOCK_SIZE") {
+            Some(size) => ok_or_die(size.parse::<usize>(), "Invalid block size"),
+            None => max_block_bytes,
+        };
+        if header.block_bytes == 0 || header.block_bytes > max_block_bytes {
+            die!(Failure::Usage =>
+                "Invalid block size; must be between 1 and {} bytes",
+                max_block_bytes
+            );
+        }
+
+        let nonce = matches
+            .value_of("NONCE")
+            .map(|nonce| match parse_hex(nonce) {
+                Some(bytes) => bytes,
+                None => die!(Failure::Usage => "Invalid nonce; must be a non-empty string of hex digits"),
+            });
+        if nonce.is_some() {
+            log::warn!(
+                "deterministic encryption is insecure. Encrypting the same input with \
+                 the same key and nonce gives the same output, and reusing a nonce for different \
+                 inputs leaks information about them."
+            );
+        }
+
+        let inputs: Vec<&str> = matches
+            .values_of("INPATH")
+            .into_iter()
+            .flatten()
+            .chain(matches.values_of("INPUTS").into_iter().flatten())
+            .collect();
+        let is_template = matches
+            .value_of("OUTPATH")
+            .is_some_and(|path| path.contains(NAME_PLACEHOLDER) || path.contains(STEM_PLACEHOLDER));
+        let jobs = if matches.is_present("RECURSIVE") {
+            Some(recursive_jobs(matches, &inputs))
+        } else if inputs.len() > 1 || matches.is_present("INPUTS") || is_template {
+            Some(template_jobs(matches, &inputs))
+        } else {
+            None
+        };
+        if let Some(jobs) = jobs {
+            crypt_batch(matches, &jobs, header, nonce.as_deref());
+            return;
+        }
+
+        // the input is only shredded once its ciphertext is safely in a file
+        let shred_path = if matches.is_present("SHRED_INPUT") {
+            let in_path = matches.value_of("INPATH").unwrap_or(STDIO_PATH);
+            let out_path = matches.value_of("OUTPATH").unwrap_or(STDIO_PATH);
+            if in_path == STDIO_PATH || !Path::new(in_path).is_file() {
+                die!(Failure::Usage => "--shred-input needs an input file, not standard input or a directory");
+            } else if Path::new(in_path).is_symlink() {
+                die!(Failure::Usage => "--shred-input can't be used with a symbolic link; give the file it points to");
+            } else if out_path == STDIO_PATH {
+                die!(Failure::Usage => "--shred-input needs an output file, not standard output");
+            } else if fs::canonicalize(in_path).ok() == fs::canonicalize(out_path).ok() {
+                die!(Failure::Usage => "--shred-input can't be used when the output replaces the input");
+            }
+            Some(in_path)
         } else {
-            ok_or_die(decrypt_ecb(settings), "Decryption failed");
+            None
+        };
+
+        let (settings, output) = get_crypt_settings(matches);
+        let err_msg = if matches.is_present("ENCRYPT") {
+            "Encryption failed"
+        } else {
+            "Decryption failed"
+        };
+        ok_or_die(
+            run_crypt(matches, settings, output, header, nonce.as_deref()),
+            err_msg,
+        );
+
+        if let Some(path) = shred_path {
+            let err_msg = format!("Failed to shred input file {}", path);
+            ok_or_die(shred_file(path), &err_msg);
+            log::warn!(
+                "{} was overwritten and deleted, but copies of it may survive on SSDs, \
+                 copy-on-write or journaling filesystems, and in backups or snapshots.",
+                path
+            );
         }
     } else {
         unreachable!("Failed to cover all subcommands, or Clap is improperly configured");
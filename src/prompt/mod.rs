@@ -0,0 +1,121 @@
+/*!
+ * Questions asked on the terminal, such as passphrase prompts.
+ *
+ * Prompts are written to and answers read from the controlling terminal, `/dev/tty`, so that they
+ * still work when standard input and output are redirected. Without one, e.g., under a job
+ * scheduler or on platforms other than Unix, prompts fall back to standard error and answers to
+ * standard input.
+ */
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+};
+
+/**
+ * Check whether there's a terminal to prompt on, so that prompting won't read standard input.
+ */
+pub fn has_terminal() -> bool {
+    open_terminal().is_ok()
+}
+
+/**
+ * Write `prompt` and read a line in answer, without echoing what's typed, as for a passphrase.
+ * The answer is echoed if it has to be read from standard input.
+ */
+pub fn read_hidden(prompt: &str) -> io::Result<String> {
+    let mut terminal = match open_terminal() {
+        Ok(terminal) => terminal,
+        Err(_) => {
+            eprint!("{}", prompt);
+            return read_line(io::stdin().lock());
+        }
+    };
+
+    terminal.write_all(prompt.as_bytes())?;
+    terminal.flush()?;
+    let _echo = EchoOff::new(&terminal)?;
+    read_line(BufReader::new(&terminal))
+}
+
+/**
+ * Read the first line from `reader`, without its line ending. The line is empty if `reader` is.
+ */
+pub fn read_line<R: BufRead>(mut reader: R) -> io::Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    line.truncate(line.trim_end_matches(&['\r', '\n'][..]).len());
+    Ok(line)
+}
+
+/**
+ * Open the controlling terminal for reading and writing.
+ */
+#[cfg(unix)]
+fn open_terminal() -> io::Result<File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+}
+
+#[cfg(not(unix))]
+fn open_terminal() -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "prompting on the terminal needs Unix",
+    ))
+}
+
+/**
+ * Turns off a terminal's echo, except of the newline that ends a line, while it's alive.
+ */
+#[cfg(unix)]
+struct EchoOff {
+    fd: std::os::unix::io::RawFd,
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl EchoOff {
+    fn new(terminal: &File) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let fd = terminal.as_raw_fd();
+        // SAFETY: `termios` is plain data, and `tcgetattr` fills it in or fails
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut hidden = original;
+        hidden.c_lflag &= !libc::ECHO;
+        hidden.c_lflag |= libc::ECHONL;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct EchoOff;
+
+#[cfg(not(unix))]
+impl EchoOff {
+    fn new(_terminal: &File) -> io::Result<Self> {
+        Ok(Self)
+    }
+}
@@ -0,0 +1,14 @@
+use super::*;
+
+#[test]
+fn test_read_line() {
+    assert_eq!(read_line(&b"secret\nrest\n"[..]).unwrap(), "secret");
+    assert_eq!(read_line(&b"secret\r\n"[..]).unwrap(), "secret");
+    assert_eq!(read_line(&b"no newline"[..]).unwrap(), "no newline");
+    assert_eq!(
+        read_line(&b"  spaces kept  \n"[..]).unwrap(),
+        "  spaces kept  "
+    );
+    assert_eq!(read_line(&b"\nsecret\n"[..]).unwrap(), "");
+    assert_eq!(read_line(&b""[..]).unwrap(), "");
+}